@@ -0,0 +1,45 @@
+//! Hashes the sources of the compiler, so the build cache tells apart the modules translated by
+//! different revisions of it, even if they share the version of the crate.
+use std::{
+    collections::hash_map::DefaultHasher,
+    fs,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+};
+
+const SOURCE_DIRECTORY: &str = "src";
+
+fn main() {
+    println!("cargo:rerun-if-changed={SOURCE_DIRECTORY}");
+
+    let mut files = Vec::new();
+    collect_files(Path::new(SOURCE_DIRECTORY), &mut files);
+    // Sorted, so the hash does not depend on the order the directories are read in
+    files.sort();
+
+    let mut hasher = DefaultHasher::new();
+    for file in files {
+        file.to_string_lossy().hash(&mut hasher);
+        fs::read(&file)
+            .unwrap_or_else(|e| panic!("failed to read {}: {e}", file.display()))
+            .hash(&mut hasher);
+    }
+
+    println!(
+        "cargo:rustc-env=COMPILER_SOURCE_HASH={:016x}",
+        hasher.finish()
+    );
+}
+
+fn collect_files(directory: &Path, files: &mut Vec<PathBuf>) {
+    let entries = fs::read_dir(directory)
+        .unwrap_or_else(|e| panic!("failed to read {}: {e}", directory.display()));
+    for entry in entries {
+        let path = entry.expect("failed to read a directory entry").path();
+        if path.is_dir() {
+            collect_files(&path, files);
+        } else {
+            files.push(path);
+        }
+    }
+}
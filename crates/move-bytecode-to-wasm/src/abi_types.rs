@@ -1,4 +1,5 @@
-mod function_encoding;
+pub mod contract_abi;
+pub(crate) mod function_encoding;
 pub(crate) mod packing;
 pub mod public_function;
 mod unpacking;
@@ -0,0 +1,181 @@
+//! Solidity JSON ABI of a module, following the format emitted by solc, so the contract can be
+//! called from the tooling built around it (alloy, ethers, viem, block explorers).
+//!
+//! Every entry function is described by its camel case name and the Solidity types its arguments
+//! and return values are encoded as. The `signer` and `TxContext` arguments are injected by the
+//! entrypoint, so they are not part of the ABI. Structs are described as tuples, with their fields
+//! as components, except the ones saved in storage, which are passed by their `bytes32` id. Move
+//! does not keep the names of the parameters in the bytecode, so they are empty.
+//!
+//! The modules with an `init` function, or a `#[ext(constructor)]` one, also list the
+//! `constructor` function injected to call them, the one deployers call once the contract is
+//! deployed, with the inputs of the `#[ext(constructor)]` function.
+use serde::Serialize;
+
+use crate::{
+    CompilationContext,
+    compilation_context::ModuleData,
+    translation::intermediate_types::{IntermediateType, structs::IStruct},
+    utils::snake_to_camel,
+    vm_handled_types::{VmHandledType, tx_context::TxContext},
+};
+
+use super::function_encoding::SolName;
+
+#[derive(Debug, Default, Serialize)]
+#[serde(transparent)]
+pub struct ContractAbi {
+    pub functions: Vec<AbiFunction>,
+}
+
+#[derive(Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct AbiFunction {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub name: String,
+    pub inputs: Vec<AbiParameter>,
+    pub outputs: Vec<AbiParameter>,
+    /// `view` for the functions annotated with `#[ext(view)]`, `nonpayable` otherwise
+    pub state_mutability: &'static str,
+}
+
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub struct AbiParameter {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub type_name: String,
+    /// Fields of the tuples, and of the arrays of tuples
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub components: Option<Vec<AbiParameter>>,
+}
+
+impl ContractAbi {
+    /// Describes the entry functions defined in the module, and its constructor.
+    pub(crate) fn new(module_data: &ModuleData, compilation_ctx: &CompilationContext) -> Self {
+        let mut functions: Vec<AbiFunction> = module_data
+            .functions
+            .information
+            .iter()
+            .filter(|f| f.function_id.module_id == module_data.id && f.is_entry && !f.is_generic)
+            .map(|function_information| AbiFunction {
+                kind: "function",
+                name: snake_to_camel(&function_information.function_id.identifier),
+                inputs: parameters(&function_information.signature.arguments, compilation_ctx),
+                outputs: parameters(&function_information.signature.returns, compilation_ctx),
+                state_mutability: if function_information.is_view {
+                    "view"
+                } else {
+                    "nonpayable"
+                },
+            })
+            .collect();
+
+        // The `init` function only receives the OTW and the TxContext, both injected, so the
+        // constructor only takes the arguments of the `#[ext(constructor)]` function
+        if module_data.functions.init.is_some() || module_data.functions.constructor.is_some() {
+            let inputs = module_data.functions.constructor_information().map_or_else(
+                Vec::new,
+                |function_information| {
+                    parameters(&function_information.signature.arguments, compilation_ctx)
+                },
+            );
+
+            functions.push(AbiFunction {
+                kind: "function",
+                name: "constructor".to_owned(),
+                inputs,
+                outputs: Vec::new(),
+                state_mutability: "nonpayable",
+            });
+        }
+
+        Self { functions }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("failed to serialize the contract ABI")
+    }
+}
+
+/// Describes the types that are part of the ABI, skipping the ones injected by the entrypoint.
+fn parameters(
+    types: &[IntermediateType],
+    compilation_ctx: &CompilationContext,
+) -> Vec<AbiParameter> {
+    types
+        .iter()
+        .filter_map(|type_| parameter(String::new(), type_, compilation_ctx))
+        .collect()
+}
+
+fn parameter(
+    name: String,
+    type_: &IntermediateType,
+    compilation_ctx: &CompilationContext,
+) -> Option<AbiParameter> {
+    match type_ {
+        IntermediateType::IRef(inner) | IntermediateType::IMutRef(inner) => {
+            parameter(name, inner, compilation_ctx)
+        }
+        IntermediateType::IVector(inner) => {
+            parameter(name, inner, compilation_ctx).map(|mut parameter| {
+                parameter.type_name.push_str("[]");
+                parameter
+            })
+        }
+        IntermediateType::IStruct { module_id, index }
+            if !TxContext::is_vm_type(module_id, *index, compilation_ctx) =>
+        {
+            let struct_ = compilation_ctx
+                .get_struct_by_index(module_id, *index)
+                .unwrap();
+            struct_parameter(name, struct_, compilation_ctx)
+        }
+        IntermediateType::IGenericStructInstance {
+            module_id,
+            index,
+            types,
+        } => {
+            let struct_ = compilation_ctx
+                .get_struct_by_index(module_id, *index)
+                .unwrap();
+            struct_parameter(name, &struct_.instantiate(types), compilation_ctx)
+        }
+        _ => Some(AbiParameter {
+            name,
+            type_name: type_.sol_name(compilation_ctx)?,
+            components: None,
+        }),
+    }
+}
+
+fn struct_parameter(
+    name: String,
+    struct_: &IStruct,
+    compilation_ctx: &CompilationContext,
+) -> Option<AbiParameter> {
+    if struct_.saved_in_storage {
+        return Some(AbiParameter {
+            name,
+            type_name: "bytes32".to_owned(),
+            components: None,
+        });
+    }
+
+    let components = struct_
+        .fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| {
+            let field_name = struct_.field_names.get(i).cloned().unwrap_or_default();
+            parameter(field_name, field, compilation_ctx)
+        })
+        .collect::<Option<Vec<AbiParameter>>>()?;
+
+    Some(AbiParameter {
+        name,
+        type_name: "tuple".to_owned(),
+        components: Some(components),
+    })
+}
@@ -38,6 +38,23 @@ pub fn move_signature_to_abi_selector<T: SolName>(
     selector(format!("{}({})", function_name, parameter_strings))
 }
 
+/// Calculate the topic identifying an event according to Solidity's [ABI encoding](https://docs.soliditylang.org/en/latest/abi-spec.html#events)
+///
+/// The event is named after the struct, and its parameters are the struct's fields.
+pub fn move_struct_to_event_topic(
+    struct_: &IStruct,
+    compilation_ctx: &CompilationContext,
+) -> [u8; 32] {
+    let parameter_strings = struct_
+        .fields
+        .iter()
+        .filter_map(|f| f.sol_name(compilation_ctx))
+        .collect::<Vec<String>>()
+        .join(",");
+
+    keccak256(format!("{}({})", struct_.identifier, parameter_strings)).0
+}
+
 impl SolName for IntermediateType {
     fn sol_name(&self, compilation_ctx: &CompilationContext) -> Option<String> {
         match self {
@@ -1,7 +1,13 @@
-use walrus::{FunctionId, InstrSeqBuilder, LocalId, Module, ValType, ir::BinaryOp};
+use std::collections::HashMap;
+
+use walrus::{
+    FunctionId, InstrSeqBuilder, LocalId, Module, ValType,
+    ir::{BinaryOp, MemArg, StoreKind},
+};
 
 use crate::{
     CompilationContext,
+    hostio::reentrancy::add_reentrancy_check_instructions,
     translation::{
         functions::add_unpack_function_return_values_instructions,
         intermediate_types::{ISignature, IntermediateType},
@@ -35,6 +41,11 @@ pub struct PublicFunction<'a> {
     function_id: FunctionId,
     function_selector: AbiFunctionSelector,
     signature: &'a ISignature,
+    /// Fields accessed for the arguments that are immutable references to storage objects. Only
+    /// those fields are read from storage when unpacking the arguments.
+    lazy_storage_fields: HashMap<usize, Vec<usize>>,
+    /// If true, reentrant calls to the function are rejected
+    non_reentrant: bool,
 }
 
 impl<'a> PublicFunction<'a> {
@@ -54,9 +65,27 @@ impl<'a> PublicFunction<'a> {
             function_id,
             function_selector,
             signature,
+            lazy_storage_fields: HashMap::new(),
+            non_reentrant: false,
         }
     }
 
+    /// Sets the fields to read from storage for the arguments that are immutable references to
+    /// storage objects. Arguments not present are fully read.
+    pub fn with_lazy_storage_fields(
+        mut self,
+        lazy_storage_fields: HashMap<usize, Vec<usize>>,
+    ) -> Self {
+        self.lazy_storage_fields = lazy_storage_fields;
+        self
+    }
+
+    /// Sets if the function rejects reentrant calls.
+    pub fn with_non_reentrant(mut self, non_reentrant: bool) -> Self {
+        self.non_reentrant = non_reentrant;
+        self
+    }
+
     #[cfg(test)]
     pub fn get_selector(&self) -> &AbiFunctionSelector {
         &self.function_selector
@@ -74,7 +103,7 @@ impl<'a> PublicFunction<'a> {
         args_pointer: LocalId,
         args_len: LocalId,
         write_return_data_function: FunctionId,
-        storage_flush_cache_function: FunctionId,
+        storage_cache_commit_function: FunctionId,
         compilation_ctx: &CompilationContext,
     ) {
         router_builder.block(None, |block| {
@@ -85,51 +114,122 @@ impl<'a> PublicFunction<'a> {
             block.binop(BinaryOp::I32Ne);
             block.br_if(block_id);
 
-            // Offset args pointer by 4 bytes to exclude selector
-            block.local_get(args_pointer);
-            block.i32_const(4);
-            block.binop(BinaryOp::I32Add);
-            block.local_set(args_pointer);
-
-            // If the first argument's type is signer, we inject the tx.origin into the stack as a
-            // first parameter
-            match self.signature.arguments.first() {
-                Some(IntermediateType::ISigner) => {
-                    Signer::inject(block, module, compilation_ctx);
-                }
-                Some(IntermediateType::IRef(inner)) if **inner == IntermediateType::ISigner => {
-                    Signer::inject(block, module, compilation_ctx);
-                }
-                _ => {
-                    // If there's no signer, reduce args length by 4 bytes to exclude selector,
-                    // otherwise we reuse the selector's 4 bytes (32 bits) for the signer pointer
-                    block.local_get(args_len);
-                    block.i32_const(4);
-                    block.binop(BinaryOp::I32Sub);
-                    block.local_set(args_len);
-                }
-            }
-
-            // Wrap function to pack/unpack parameters
-            self.wrap_public_function(module, block, args_pointer, compilation_ctx);
+            self.add_call_instructions(block, module, args_pointer, args_len, compilation_ctx);
 
             // Stack: [return_data_pointer] [return_data_length] [status]
             let status = module.locals.add(ValType::I32);
             block.local_set(status);
 
+            // Save all the storage changes made during the call
+            block.call(storage_cache_commit_function);
+
             // Write return data to memory
             // Stack: [return_data_pointer] [return_data_length]
             block.call(write_return_data_function);
 
-            block.i32_const(0); // Do not clear cache
-            block.call(storage_flush_cache_function);
-
             // Return status
             block.local_get(status);
             block.return_();
         });
     }
 
+    /// Builds the block of the function in the self dispatch function, that runs the entry
+    /// function matching the selector of a calldata built by the contract itself.
+    ///
+    /// Executes the wrapped function if the selector matches, and returns a pointer to the return
+    /// data, saving its length in `return_data_len_ptr`. Unlike the router, the storage cache is
+    /// not committed, since the transaction goes on after the call.
+    pub fn build_self_dispatch_block(
+        &self,
+        dispatch_builder: &mut InstrSeqBuilder,
+        module: &mut Module,
+        selector_variable: LocalId,
+        args_pointer: LocalId,
+        args_len: LocalId,
+        return_data_len_ptr: LocalId,
+        compilation_ctx: &CompilationContext,
+    ) {
+        let return_data_pointer = module.locals.add(ValType::I32);
+        let return_data_len = module.locals.add(ValType::I32);
+
+        dispatch_builder.block(None, |block| {
+            let block_id = block.id();
+
+            block.local_get(selector_variable);
+            block.i32_const(i32::from_le_bytes(self.function_selector));
+            block.binop(BinaryOp::I32Ne);
+            block.br_if(block_id);
+
+            self.add_call_instructions(block, module, args_pointer, args_len, compilation_ctx);
+
+            // Stack: [return_data_pointer] [return_data_length] [status]
+            // The status is always 0, errors trap
+            block.drop();
+            block.local_set(return_data_len);
+            block.local_set(return_data_pointer);
+
+            block
+                .local_get(return_data_len_ptr)
+                .local_get(return_data_len)
+                .store(
+                    compilation_ctx.memory_id,
+                    StoreKind::I32 { atomic: false },
+                    MemArg {
+                        align: 0,
+                        offset: 0,
+                    },
+                );
+
+            block.local_get(return_data_pointer);
+            block.return_();
+        });
+    }
+
+    /// Adds the instructions to call the function with the arguments in the calldata, checking
+    /// first if the call is reentrant when the function rejects them.
+    ///
+    /// Leaves in the stack the return data pointer, its length and the status.
+    fn add_call_instructions(
+        &self,
+        block: &mut InstrSeqBuilder,
+        module: &mut Module,
+        args_pointer: LocalId,
+        args_len: LocalId,
+        compilation_ctx: &CompilationContext,
+    ) {
+        if self.non_reentrant {
+            add_reentrancy_check_instructions(block, module);
+        }
+
+        // Offset args pointer by 4 bytes to exclude selector
+        block.local_get(args_pointer);
+        block.i32_const(4);
+        block.binop(BinaryOp::I32Add);
+        block.local_set(args_pointer);
+
+        // If the first argument's type is signer, we inject the tx.origin into the stack as a
+        // first parameter
+        match self.signature.arguments.first() {
+            Some(IntermediateType::ISigner) => {
+                Signer::inject(block, module, compilation_ctx);
+            }
+            Some(IntermediateType::IRef(inner)) if **inner == IntermediateType::ISigner => {
+                Signer::inject(block, module, compilation_ctx);
+            }
+            _ => {
+                // If there's no signer, reduce args length by 4 bytes to exclude selector,
+                // otherwise we reuse the selector's 4 bytes (32 bits) for the signer pointer
+                block.local_get(args_len);
+                block.i32_const(4);
+                block.binop(BinaryOp::I32Sub);
+                block.local_set(args_len);
+            }
+        }
+
+        // Wrap function to pack/unpack parameters
+        self.wrap_public_function(module, block, args_pointer, compilation_ctx);
+    }
+
     /// Wraps the function unpacking input parameters from memory and packing output parameters to memory
     ///
     /// Input parameters are read from memory and unpacked as *abi encoded* values
@@ -147,6 +247,7 @@ impl<'a> PublicFunction<'a> {
             block,
             module,
             &self.signature.arguments,
+            &self.lazy_storage_fields,
             args_pointer,
             compilation_ctx,
         );
@@ -234,6 +335,7 @@ mod tests {
 
     use crate::{
         hostio::host_functions,
+        runtime::RuntimeFunction,
         test_compilation_context,
         test_tools::build_module,
         translation::{functions::prepare_function_return, intermediate_types::IntermediateType},
@@ -288,6 +390,14 @@ mod tests {
             .func_wrap("vm_hooks", "storage_flush_cache", |_: i32| Ok(()))
             .unwrap();
 
+        linker
+            .func_wrap(
+                "vm_hooks",
+                "storage_cache_bytes32",
+                |_key_ptr: u32, _value_ptr: u32| Ok(()),
+            )
+            .unwrap();
+
         linker
             .func_wrap(
                 "vm_hooks",
@@ -351,7 +461,8 @@ mod tests {
         let compilation_ctx = test_compilation_context!(memory_id, allocator_func);
         // Build mock router
         let (write_return_data_function, _) = host_functions::write_result(module);
-        let (storage_flush_cache_function, _) = host_functions::storage_flush_cache(module);
+        let storage_cache_commit_function =
+            RuntimeFunction::StorageCacheCommit.get(module, Some(&compilation_ctx));
 
         let selector = module.locals.add(ValType::I32);
         let args_pointer = module.locals.add(ValType::I32);
@@ -391,7 +502,7 @@ mod tests {
             args_pointer,
             args_len,
             write_return_data_function,
-            storage_flush_cache_function,
+            storage_cache_commit_function,
             &compilation_ctx,
         );
 
@@ -1,9 +1,15 @@
+use std::collections::HashMap;
+
 use walrus::{InstrSeqBuilder, LocalId, Module, ValType};
 
 use crate::{
     CompilationContext,
     data::DATA_OBJECTS_MAPPING_SLOT_NUMBER_OFFSET,
     runtime::RuntimeFunction,
+    storage::{
+        encoding::add_read_and_decode_storage_struct_fields_instructions,
+        namespace::add_objects_root_slot_instructions,
+    },
     translation::intermediate_types::{
         IntermediateType,
         address::IAddress,
@@ -48,10 +54,15 @@ pub trait Unpackable {
 ///
 /// Each parameter is decoded and loaded in the WASM stack. Complex data types are kept in memory
 /// and the pointer is pushed onto the stack in the parameter location.
-pub fn build_unpack_instructions<T: Unpackable>(
+///
+/// `lazy_storage_fields` maps the index of arguments that are immutable references to storage
+/// objects to the fields the function accesses. For those arguments, only the requested fields
+/// are read from the storage.
+pub fn build_unpack_instructions(
     function_builder: &mut InstrSeqBuilder,
     module: &mut Module,
-    function_arguments_signature: &[T],
+    function_arguments_signature: &[IntermediateType],
+    lazy_storage_fields: &HashMap<usize, Vec<usize>>,
     args_pointer: LocalId,
     compilation_ctx: &CompilationContext,
 ) {
@@ -64,14 +75,32 @@ pub fn build_unpack_instructions<T: Unpackable>(
 
     // The ABI encoded params are always a tuple
     // Static types are stored in-place, but dynamic types are referenced to the call data
-    for signature_token in function_arguments_signature.iter() {
-        signature_token.add_unpack_instructions(
-            function_builder,
-            module,
-            reader_pointer,
-            calldata_reader_pointer,
-            compilation_ctx,
-        );
+    for (index, signature_token) in function_arguments_signature.iter().enumerate() {
+        match (signature_token, lazy_storage_fields.get(&index)) {
+            (IntermediateType::IRef(inner), Some(fields))
+                if compilation_ctx
+                    .get_struct_by_intermediate_type(inner)
+                    .is_ok_and(|s| s.saved_in_storage) =>
+            {
+                add_unpack_from_storage_instructions(
+                    function_builder,
+                    module,
+                    reader_pointer,
+                    calldata_reader_pointer,
+                    compilation_ctx,
+                    inner,
+                    true,
+                    Some(fields),
+                );
+            }
+            _ => signature_token.add_unpack_instructions(
+                function_builder,
+                module,
+                reader_pointer,
+                calldata_reader_pointer,
+                compilation_ctx,
+            ),
+        }
     }
 }
 
@@ -188,6 +217,7 @@ impl Unpackable for IntermediateType {
                         compilation_ctx,
                         self,
                         false,
+                        None,
                     );
                 } else {
                     // TODO: Check if the struct is TxContext. If it is, panic since the only valid
@@ -220,6 +250,7 @@ impl Unpackable for IntermediateType {
                         compilation_ctx,
                         self,
                         false,
+                        None,
                     );
                 } else {
                     struct_instance.add_unpack_instructions(
@@ -259,6 +290,9 @@ impl Unpackable for IntermediateType {
 
 /// This function searches in the storage for the structure that belongs to the object UID passed
 /// as argument.
+///
+/// If `fields` is set, only those fields of the structure are read.
+#[allow(clippy::too_many_arguments)]
 fn add_unpack_from_storage_instructions(
     function_builder: &mut InstrSeqBuilder,
     module: &mut Module,
@@ -267,7 +301,14 @@ fn add_unpack_from_storage_instructions(
     compilation_ctx: &CompilationContext,
     itype: &IntermediateType,
     unpack_frozen: bool,
+    fields: Option<&[usize]>,
 ) {
+    let struct_ = compilation_ctx
+        .get_struct_by_intermediate_type(itype)
+        .unwrap();
+
+    add_objects_root_slot_instructions(function_builder, module, compilation_ctx, &struct_);
+
     // First we add the instructions to unpack the UID. We use address to unpack it because ids are
     // 32 bytes static, same as an address
     IAddress::add_unpack_instructions(
@@ -291,12 +332,33 @@ fn add_unpack_from_storage_instructions(
     function_builder.call(locate_storage_data_fn);
 
     // Read the object
-    let read_struct_from_storage_fn =
-        RuntimeFunction::DecodeAndReadFromStorage.get_generic(module, compilation_ctx, &[itype]);
+    if let Some(fields) = fields {
+        let slot_ptr = module.locals.add(ValType::I32);
+        function_builder
+            .i32_const(DATA_OBJECTS_MAPPING_SLOT_NUMBER_OFFSET)
+            .local_set(slot_ptr);
 
-    function_builder
-        .i32_const(DATA_OBJECTS_MAPPING_SLOT_NUMBER_OFFSET)
-        .call(read_struct_from_storage_fn);
+        let struct_ptr = add_read_and_decode_storage_struct_fields_instructions(
+            module,
+            function_builder,
+            compilation_ctx,
+            slot_ptr,
+            &struct_,
+            fields,
+        );
+
+        function_builder.local_get(struct_ptr);
+    } else {
+        let read_struct_from_storage_fn = RuntimeFunction::DecodeAndReadFromStorage.get_generic(
+            module,
+            compilation_ctx,
+            &[itype],
+        );
+
+        function_builder
+            .i32_const(DATA_OBJECTS_MAPPING_SLOT_NUMBER_OFFSET)
+            .call(read_struct_from_storage_fn);
+    }
 }
 
 #[cfg(test)]
@@ -347,6 +409,7 @@ mod tests {
                 IntermediateType::IU16,
                 IntermediateType::IU64,
             ],
+            &HashMap::new(),
             args_pointer,
             &compilation_ctx,
         );
@@ -404,6 +467,7 @@ mod tests {
                 IntermediateType::IU16,
                 IntermediateType::IBool,
             ],
+            &HashMap::new(),
             args_pointer,
             &compilation_ctx,
         );
@@ -468,6 +532,7 @@ mod tests {
                 IntermediateType::IU16,
                 IntermediateType::IU64,
             ],
+            &HashMap::new(),
             args_pointer,
             &compilation_ctx,
         );
@@ -49,6 +49,7 @@ impl IRef {
                         compilation_ctx,
                         inner,
                         true,
+                        None,
                     );
                 } else {
                     inner.add_unpack_instructions(
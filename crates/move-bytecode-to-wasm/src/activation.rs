@@ -0,0 +1,100 @@
+//! Information about the activation of the translated modules, emitted as
+//! `<module>.activation.json`. Stylus programs are activated through the `ArbWasm` precompile
+//! after they are deployed, by a call that pays the data fee of the activation, and the precompile
+//! keys the activated programs by the code hash of the contract, so the teams deploying the
+//! modules can look them up and fund their activation before they are deployed.
+//!
+//! The data fee is priced by the chain when the module is activated, so it can't be derived from
+//! the module alone: `move-stylus check --endpoint <url>` simulates the activation on a node and
+//! reports it.
+use alloy_primitives::keccak256;
+use serde::Serialize;
+use wasmparser::{BinaryReaderError, Payload};
+
+use crate::code_size;
+
+/// Prefix of the code of the Stylus programs, before the compressed module
+const STYLUS_CODE_PREFIX: [u8; 3] = [0xef, 0xf0, 0x00];
+
+/// Dictionary byte of the modules compressed without a dictionary, the ones `cargo stylus deploy`
+/// deploys
+const NO_DICTIONARY: u8 = 0x00;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivationInfo {
+    /// Keccak-256 of the code of the contract, as `EXTCODEHASH` returns it, the hash `ArbWasm`
+    /// keys the activated programs by
+    pub code_hash: String,
+    /// Keccak-256 of the module, the hash `move-stylus verify` compares
+    pub module_hash: String,
+    /// Size of the code of the contract, the prefix of Stylus and the compressed module, which the
+    /// deployment pays for
+    pub code_size: usize,
+    /// Size of the module, the one activated
+    pub module_size: usize,
+    /// Pages of memory the module starts with, which every call of the activated program pays for
+    pub initial_memory_pages: u64,
+}
+
+impl ActivationInfo {
+    pub fn new(wasm: &[u8]) -> Result<Self, BinaryReaderError> {
+        let mut initial_memory_pages = 0;
+        for payload in wasmparser::Parser::new(0).parse_all(wasm) {
+            if let Payload::MemorySection(reader) = payload? {
+                for memory in reader {
+                    initial_memory_pages += memory?.initial;
+                }
+            }
+        }
+
+        let code = contract_code(wasm);
+        Ok(Self {
+            code_hash: keccak256(&code).to_string(),
+            module_hash: keccak256(wasm).to_string(),
+            code_size: code.len(),
+            module_size: wasm.len(),
+            initial_memory_pages,
+        })
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("failed to serialize the activation info")
+    }
+}
+
+/// Code of the contract `cargo stylus deploy` deploys the module as.
+fn contract_code(wasm: &[u8]) -> Vec<u8> {
+    [
+        STYLUS_CODE_PREFIX.as_slice(),
+        &[NO_DICTIONARY],
+        &code_size::compress(wasm),
+    ]
+    .concat()
+}
+
+#[cfg(test)]
+mod tests {
+    use walrus::{Module, ModuleConfig};
+
+    use super::*;
+
+    #[test]
+    fn test_activation_info() {
+        let mut module = Module::with_config(ModuleConfig::new());
+        module.memories.add_local(false, false, 2, None, None);
+        let wasm = module.emit_wasm();
+
+        let activation = ActivationInfo::new(&wasm).unwrap();
+        let code = contract_code(&wasm);
+        assert_eq!([0xef, 0xf0, 0x00, 0x00], code[..4]);
+        assert_eq!(keccak256(&code).to_string(), activation.code_hash);
+        assert_eq!(keccak256(&wasm).to_string(), activation.module_hash);
+        assert_eq!(code.len(), activation.code_size);
+        assert_eq!(wasm.len(), activation.module_size);
+        assert_eq!(2, activation.initial_memory_pages);
+
+        let json: serde_json::Value = serde_json::from_str(&activation.to_json()).unwrap();
+        assert_eq!(activation.code_hash, json["codeHash"]);
+    }
+}
@@ -0,0 +1,162 @@
+//! On-disk cache of the translated modules, so building a package again, in another process or in
+//! another CI run, does not translate the modules whose bytecode did not change.
+//!
+//! The functions of the dependencies, MoveStdlib and the Stylus framework among them, are linked
+//! into the modules that call them, so a module is cached with its artifacts, keyed by the hash of
+//! its bytecode, of the bytecode of every module it depends on, transitively, of the sources of
+//! the compiler, hashed when it is built, and of the options it is translated with. The instrumented builds and the ones
+//! with debug information are not cached, their artifacts also depend on the source.
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use alloy_primitives::keccak256;
+use anyhow::Context;
+use move_package::compilation::compiled_package::{CompiledPackage, CompiledUnitWithSource};
+
+use crate::{translation_options::TranslationOptions, wasm_opt::OptimizationLevel};
+
+/// The WASM of a module, as it is written, with its artifacts serialized.
+pub(crate) struct ModuleArtifacts {
+    pub(crate) wasm: Vec<u8>,
+    pub(crate) abi: String,
+    pub(crate) storage_layout: String,
+    pub(crate) storage_gas_report: String,
+    /// Only built with the `coverage` option, so never cached
+    pub(crate) coverage_map: Option<String>,
+    /// Only built with the `debug_info` option, so never cached
+    pub(crate) source_map: Option<String>,
+}
+
+pub(crate) struct BuildCache {
+    directory: PathBuf,
+}
+
+impl BuildCache {
+    pub(crate) fn new(directory: PathBuf) -> Self {
+        Self { directory }
+    }
+
+    /// Key the root module is cached with, or `None` if it can't be cached.
+    pub(crate) fn key(
+        root_compiled_unit: &CompiledUnitWithSource,
+        package: &CompiledPackage,
+        options: &TranslationOptions,
+        wasm_opt: Option<OptimizationLevel>,
+    ) -> Option<String> {
+        if options.coverage || options.unit_tests || options.debug_info {
+            return None;
+        }
+
+        let units: BTreeMap<_, _> = package
+            .root_compiled_units
+            .iter()
+            .chain(package.deps_compiled_units.iter().map(|(_, unit)| unit))
+            .map(|unit| (unit.unit.module.self_id(), unit))
+            .collect();
+
+        // Sorted by id, so the key does not depend on the order the modules are reached in
+        let mut dependencies = BTreeMap::new();
+        let mut pending = vec![root_compiled_unit.unit.module.self_id()];
+        while let Some(module_id) = pending.pop() {
+            if dependencies.contains_key(&module_id) {
+                continue;
+            }
+
+            let module = &units.get(&module_id)?.unit.module;
+            let mut bytecode = Vec::new();
+            module
+                .serialize_with_version(module.version, &mut bytecode)
+                .ok()?;
+            pending.extend(module.immediate_dependencies());
+            dependencies.insert(module_id, bytecode);
+        }
+
+        // The version of the crate is not bumped by every change to the translation, its sources are
+        let mut preimage = format!(
+            "{}\n{options:?}\n{wasm_opt:?}\n",
+            env!("COMPILER_SOURCE_HASH")
+        )
+        .into_bytes();
+        for bytecode in dependencies.values() {
+            preimage.extend((bytecode.len() as u64).to_le_bytes());
+            preimage.extend(bytecode);
+        }
+
+        Some(keccak256(preimage).to_string())
+    }
+
+    /// Artifacts cached with the key, if all of them are.
+    pub(crate) fn load(&self, key: &str) -> Option<ModuleArtifacts> {
+        Some(ModuleArtifacts {
+            wasm: fs::read(self.path(key, "wasm")).ok()?,
+            abi: fs::read_to_string(self.path(key, "abi.json")).ok()?,
+            storage_layout: fs::read_to_string(self.path(key, "storage.json")).ok()?,
+            storage_gas_report: fs::read_to_string(self.path(key, "gas.json")).ok()?,
+            coverage_map: None,
+            source_map: None,
+        })
+    }
+
+    pub(crate) fn store(&self, key: &str, artifacts: &ModuleArtifacts) -> anyhow::Result<()> {
+        fs::create_dir_all(&self.directory)
+            .with_context(|| format!("Failed to create {}", self.directory.display()))?;
+
+        // The WASM is written last, so an interrupted build leaves no entry that loads
+        for (extension, contents) in [
+            ("abi.json", artifacts.abi.as_bytes()),
+            ("storage.json", artifacts.storage_layout.as_bytes()),
+            ("gas.json", artifacts.storage_gas_report.as_bytes()),
+            ("wasm", artifacts.wasm.as_slice()),
+        ] {
+            write(&self.path(key, extension), contents)?;
+        }
+        Ok(())
+    }
+
+    fn path(&self, key: &str, extension: &str) -> PathBuf {
+        self.directory.join(format!("{key}.{extension}"))
+    }
+}
+
+fn write(path: &Path, contents: &[u8]) -> anyhow::Result<()> {
+    fs::write(path, contents).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_store_and_load() {
+        let directory = std::env::temp_dir().join(format!("build-cache-{}", std::process::id()));
+        let cache = BuildCache::new(directory.clone());
+        assert!(cache.load("key").is_none());
+
+        cache
+            .store(
+                "key",
+                &ModuleArtifacts {
+                    wasm: vec![0, 97, 115, 109],
+                    abi: "[]".to_owned(),
+                    storage_layout: "{}".to_owned(),
+                    storage_gas_report: "{}".to_owned(),
+                    coverage_map: None,
+                    source_map: None,
+                },
+            )
+            .unwrap();
+
+        let artifacts = cache.load("key").unwrap();
+        assert_eq!(vec![0, 97, 115, 109], artifacts.wasm);
+        assert_eq!("[]", artifacts.abi);
+
+        // An entry missing the WASM is not loaded
+        fs::remove_file(directory.join("key.wasm")).unwrap();
+        assert!(cache.load("key").is_none());
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
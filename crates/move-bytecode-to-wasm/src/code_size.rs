@@ -0,0 +1,441 @@
+//! Size of the translated modules as Stylus measures it. The code deployed is the module compressed
+//! with brotli, which must fit the contract code size limit of the EVM, so `translate_package_cli`
+//! reports the compressed size of every module and fails if it does not fit, pointing to the
+//! largest functions, the ones worth splitting or moving to another contract.
+//!
+//! Packages can set a tighter budget, uncompressed or compressed, in their `Stylus.toml`, so they
+//! notice a contract growing before it stops fitting. The modules over their budget fail the build
+//! the same way, along with the largest Move constants, inlined wherever they are loaded, and
+//! hints on how to trim the module.
+//!
+//! The size of every function is also emitted as `<module>.size.json`, telling the functions
+//! translated from Move apart from the ones the translation adds, the router, the runtime and the
+//! native functions, which every module pays for.
+use std::{collections::HashMap, fmt, io::Write};
+
+use move_package::compilation::compiled_package::CompiledUnitWithSource;
+use serde::Serialize;
+use wasmparser::{BinaryReaderError, KnownCustom, Name, Payload, TypeRef};
+
+/// Largest module Stylus deploys once compressed, the EVM's limit of the contract code size
+pub const MAX_COMPRESSED_WASM_SIZE: usize = 24 * 1024;
+
+/// Brotli parameters `cargo stylus deploy` compresses the modules with
+const BROTLI_QUALITY: u32 = 11;
+const BROTLI_WINDOW: u32 = 22;
+
+/// Largest sizes a module may have. The limit of Stylus applies whatever the budget.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SizeBudget {
+    /// Largest size of the module as emitted, `None` for no limit
+    pub max_size: Option<usize>,
+    /// Largest size of the module once compressed, the limit of Stylus by default
+    pub max_compressed_size: usize,
+}
+
+impl Default for SizeBudget {
+    fn default() -> Self {
+        Self {
+            max_size: None,
+            max_compressed_size: MAX_COMPRESSED_WASM_SIZE,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodeSize {
+    pub size: usize,
+    /// Size once compressed with brotli, as the module is deployed
+    pub compressed_size: usize,
+    /// Size of the body of every function defined in the module, from the largest, named after
+    /// the name section
+    pub functions: Vec<(String, usize)>,
+}
+
+impl CodeSize {
+    pub fn measure(wasm: &[u8]) -> Result<Self, BinaryReaderError> {
+        let mut imported_functions = 0;
+        let mut bodies = Vec::new();
+        let mut names = HashMap::new();
+
+        for payload in wasmparser::Parser::new(0).parse_all(wasm) {
+            match payload? {
+                Payload::ImportSection(reader) => {
+                    for import in reader {
+                        if let TypeRef::Func(_) = import?.ty {
+                            imported_functions += 1;
+                        }
+                    }
+                }
+                Payload::CodeSectionEntry(body) => bodies.push(body.range().len()),
+                Payload::CustomSection(reader) => {
+                    if let KnownCustom::Name(name_section) = reader.as_known() {
+                        for name in name_section {
+                            if let Name::Function(function_names) = name? {
+                                for naming in function_names {
+                                    let naming = naming?;
+                                    names.insert(naming.index, naming.name.to_owned());
+                                }
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut functions: Vec<(String, usize)> = bodies
+            .into_iter()
+            .enumerate()
+            .map(|(i, size)| {
+                let index = imported_functions + i as u32;
+                let name = names
+                    .remove(&index)
+                    .unwrap_or_else(|| format!("func[{index}]"));
+                (name, size)
+            })
+            .collect();
+        functions.sort_by(|(a, a_size), (b, b_size)| b_size.cmp(a_size).then_with(|| a.cmp(b)));
+
+        Ok(Self {
+            size: wasm.len(),
+            compressed_size: compress(wasm).len(),
+            functions,
+        })
+    }
+
+    /// If true, the module is too big for Stylus to deploy.
+    pub fn exceeds_limit(&self) -> bool {
+        self.compressed_size > MAX_COMPRESSED_WASM_SIZE
+    }
+
+    /// Sizes of the module over the budget, described for the build errors. Empty if the module
+    /// fits it.
+    pub fn budget_violations(&self, budget: &SizeBudget) -> Vec<String> {
+        let mut violations = Vec::new();
+        if let Some(max_size) = budget.max_size.filter(|max_size| self.size > *max_size) {
+            violations.push(format!(
+                "{} bytes, more than its budget of {max_size} bytes",
+                self.size
+            ));
+        }
+        if self.exceeds_limit() {
+            violations.push(format!(
+                "{} bytes compressed, more than the {MAX_COMPRESSED_WASM_SIZE} bytes Stylus deploys",
+                self.compressed_size
+            ));
+        } else if self.compressed_size > budget.max_compressed_size {
+            violations.push(format!(
+                "{} bytes compressed, more than its budget of {} bytes",
+                self.compressed_size, budget.max_compressed_size
+            ));
+        }
+        violations
+    }
+}
+
+/// Size of the data of every constant of the Move module, from the largest, named after the
+/// source map. The data is stored to memory by the instructions every load is translated to.
+pub(crate) fn largest_constants(unit: &CompiledUnitWithSource) -> Vec<(String, usize)> {
+    let mut names: HashMap<u16, String> = unit
+        .unit
+        .source_map
+        .constant_map
+        .iter()
+        .map(|(name, index)| (*index, name.to_string()))
+        .collect();
+
+    let mut constants: Vec<(String, usize)> = unit
+        .unit
+        .module
+        .constant_pool()
+        .iter()
+        .enumerate()
+        .map(|(index, constant)| {
+            let name = names
+                .remove(&(index as u16))
+                .unwrap_or_else(|| format!("constant[{index}]"));
+            (name, constant.data.len())
+        })
+        .collect();
+    constants.sort_by(|(a, a_size), (b, b_size)| b_size.cmp(a_size).then_with(|| a.cmp(b)));
+    constants
+}
+
+/// Changes worth trying on a module over its budget, from the options it was built with, its
+/// largest functions and its largest constants.
+pub(crate) fn trimming_hints(
+    code_size: &CodeSize,
+    constants: &[(String, usize)],
+    optimized: bool,
+    debug: bool,
+) -> Vec<String> {
+    let mut hints = Vec::new();
+    if !optimized {
+        hints.push(
+            "optimize the module with `--wasm-opt z`, or build it with `--profile release`"
+                .to_owned(),
+        );
+    }
+    if debug {
+        hints.push(
+            "build without `--debug`, which keeps the names of the functions and adds the debug host functions"
+                .to_owned(),
+        );
+    }
+    if let Some((function, size)) = code_size
+        .functions
+        .iter()
+        .find(|(function, _)| function.contains("::"))
+    {
+        hints.push(format!(
+            "split {function}, of {size} bytes, in smaller functions, or move it to another contract"
+        ));
+    }
+    if let Some((constant, size)) = constants.first().filter(|(_, size)| *size > 0) {
+        hints.push(format!(
+            "shorten {constant}, of {size} bytes, or load it in fewer places, as its bytes are inlined in every load"
+        ));
+    }
+    hints
+}
+
+/// Size of every function of a module, emitted as `<module>.size.json`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SizeReport {
+    pub size: usize,
+    pub compressed_size: usize,
+    /// Largest module Stylus deploys once compressed
+    pub compressed_size_limit: usize,
+    /// Size of the bodies of the functions translated from Move
+    pub move_functions_size: usize,
+    /// Size of the bodies of the functions added by the translation
+    pub runtime_functions_size: usize,
+    /// Every function defined in the module, from the largest
+    pub functions: Vec<FunctionSize>,
+}
+
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub struct FunctionSize {
+    pub name: String,
+    pub size: usize,
+    pub kind: FunctionKind,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FunctionKind {
+    /// Translated from a Move function, named `<address>::<module>::<function>`
+    Move,
+    /// The router, a runtime function or a native function. The modules optimized by `wasm-opt`
+    /// without the `debug` option, or translated with `strip_names`, lose the names of the
+    /// functions, so all of them are reported as runtime functions.
+    Runtime,
+}
+
+impl SizeReport {
+    pub fn new(code_size: &CodeSize) -> Self {
+        let functions: Vec<FunctionSize> = code_size
+            .functions
+            .iter()
+            .map(|(name, size)| FunctionSize {
+                name: name.clone(),
+                size: *size,
+                kind: if name.contains("::") {
+                    FunctionKind::Move
+                } else {
+                    FunctionKind::Runtime
+                },
+            })
+            .collect();
+        let size_of = |kind| {
+            functions
+                .iter()
+                .filter(|function| function.kind == kind)
+                .map(|function| function.size)
+                .sum()
+        };
+
+        Self {
+            size: code_size.size,
+            compressed_size: code_size.compressed_size,
+            compressed_size_limit: MAX_COMPRESSED_WASM_SIZE,
+            move_functions_size: size_of(FunctionKind::Move),
+            runtime_functions_size: size_of(FunctionKind::Runtime),
+            functions,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("failed to serialize the size report")
+    }
+}
+
+impl fmt::Display for CodeSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} bytes, {} bytes compressed ({:.1}% of the {MAX_COMPRESSED_WASM_SIZE} bytes limit)",
+            self.size,
+            self.compressed_size,
+            self.compressed_size as f64 * 100.0 / MAX_COMPRESSED_WASM_SIZE as f64
+        )
+    }
+}
+
+/// Compresses the module with brotli, as `cargo stylus deploy` does.
+pub(crate) fn compress(wasm: &[u8]) -> Vec<u8> {
+    let mut compressed = Vec::new();
+    {
+        let mut writer =
+            brotli::CompressorWriter::new(&mut compressed, 4096, BROTLI_QUALITY, BROTLI_WINDOW);
+        writer
+            .write_all(wasm)
+            .expect("writing to a vector can't fail");
+    }
+    compressed
+}
+
+#[cfg(test)]
+mod tests {
+    use walrus::{
+        ConstExpr, DataKind, FunctionBuilder, Module, ModuleConfig, ValType,
+        ir::{BinaryOp, Value},
+    };
+
+    use super::*;
+
+    fn add_function(module: &mut Module, name: &str, instructions: i32) {
+        let mut function = FunctionBuilder::new(&mut module.types, &[], &[ValType::I32]);
+        function.name(name.to_owned());
+        let mut body = function.func_body();
+        body.i32_const(0);
+        for i in 0..instructions {
+            body.i32_const(i).binop(BinaryOp::I32Add);
+        }
+        let function = function.finish(vec![], &mut module.funcs);
+        module.exports.add(name, function);
+    }
+
+    #[test]
+    fn test_measure_functions() {
+        let mut module = Module::with_config(ModuleConfig::new());
+        let import_type = module.types.add(&[ValType::I32], &[]);
+        module.add_import_func("vm_hooks", "pay_for_memory_grow", import_type);
+        add_function(&mut module, "small", 1);
+        add_function(&mut module, "large", 100);
+        add_function(&mut module, "medium", 10);
+
+        let code_size = CodeSize::measure(&module.emit_wasm()).unwrap();
+
+        let names: Vec<&str> = code_size
+            .functions
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect();
+        assert_eq!(vec!["large", "medium", "small"], names);
+        assert!(!code_size.exceeds_limit());
+    }
+
+    #[test]
+    fn test_size_report() {
+        let mut module = Module::with_config(ModuleConfig::new());
+        add_function(&mut module, "0x0::counter::increment", 10);
+        add_function(&mut module, "heap_integer_add", 1);
+
+        let code_size = CodeSize::measure(&module.emit_wasm()).unwrap();
+        let report = SizeReport::new(&code_size);
+
+        let kinds: Vec<(&str, FunctionKind)> = report
+            .functions
+            .iter()
+            .map(|function| (function.name.as_str(), function.kind))
+            .collect();
+        assert_eq!(
+            vec![
+                ("0x0::counter::increment", FunctionKind::Move),
+                ("heap_integer_add", FunctionKind::Runtime),
+            ],
+            kinds
+        );
+        assert_eq!(code_size.functions[0].1, report.move_functions_size);
+        assert_eq!(code_size.functions[1].1, report.runtime_functions_size);
+    }
+
+    #[test]
+    fn test_exceeds_limit() {
+        let mut module = Module::with_config(ModuleConfig::new());
+        let memory = module.memories.add_local(false, false, 1, None, None);
+
+        // Pseudo-random bytes don't compress
+        let mut state: u32 = 0x2545_f491;
+        let data: Vec<u8> = (0..32 * 1024)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            })
+            .collect();
+        module.data.add(
+            DataKind::Active {
+                memory,
+                offset: ConstExpr::Value(Value::I32(0)),
+            },
+            data,
+        );
+
+        let code_size = CodeSize::measure(&module.emit_wasm()).unwrap();
+        assert!(code_size.exceeds_limit());
+
+        // Zeros compress well below the limit
+        let mut module = Module::with_config(ModuleConfig::new());
+        let memory = module.memories.add_local(false, false, 1, None, None);
+        module.data.add(
+            DataKind::Active {
+                memory,
+                offset: ConstExpr::Value(Value::I32(0)),
+            },
+            vec![0; 32 * 1024],
+        );
+
+        let code_size = CodeSize::measure(&module.emit_wasm()).unwrap();
+        assert!(!code_size.exceeds_limit());
+    }
+
+    #[test]
+    fn test_budget_violations() {
+        let code_size = CodeSize {
+            size: 2000,
+            compressed_size: 1000,
+            functions: vec![
+                ("heap_integer_add".to_owned(), 600),
+                ("0x0::counter::increment".to_owned(), 400),
+            ],
+        };
+
+        assert!(
+            code_size
+                .budget_violations(&SizeBudget::default())
+                .is_empty()
+        );
+        assert_eq!(
+            vec![
+                "2000 bytes, more than its budget of 1500 bytes".to_owned(),
+                "1000 bytes compressed, more than its budget of 800 bytes".to_owned(),
+            ],
+            code_size.budget_violations(&SizeBudget {
+                max_size: Some(1500),
+                max_compressed_size: 800,
+            })
+        );
+
+        // The runtime functions can't be split, the Move ones can
+        let hints = trimming_hints(&code_size, &[("EInvalid".to_owned(), 64)], false, true);
+        assert_eq!(4, hints.len());
+        assert!(hints[2].contains("0x0::counter::increment"));
+        assert!(hints[3].contains("EInvalid"));
+        assert_eq!(1, trimming_hints(&code_size, &[], true, false).len());
+    }
+}
@@ -1,11 +1,20 @@
 mod error;
 pub mod module_data;
 pub mod reserved_modules;
+mod symbol;
 
-use crate::translation::intermediate_types::{IntermediateType, structs::IStruct};
+use crate::{
+    TranslationOptions,
+    source_map::DebugInfo,
+    translation::{
+        flow::FlowCache,
+        intermediate_types::{IntermediateType, structs::IStruct},
+    },
+};
 pub use error::CompilationContextError;
 pub use module_data::{ModuleData, ModuleId, UserDefinedType};
 use std::{borrow::Cow, collections::HashMap};
+pub use symbol::{Interner, Symbol};
 use walrus::{FunctionId, MemoryId};
 
 type Result<T> = std::result::Result<T, CompilationContextError>;
@@ -25,6 +34,15 @@ pub struct CompilationContext<'a> {
 
     /// Allocator function id
     pub allocator: FunctionId,
+
+    /// Options used to translate the package
+    pub options: &'a TranslationOptions,
+
+    /// Locations of the root module's instructions, collected with the `debug_info` option
+    pub(crate) debug_info: Option<&'a DebugInfo<'a>>,
+
+    /// Flows of the functions already translated by the package's other root modules
+    pub(crate) flows: Option<&'a FlowCache>,
 }
 
 impl CompilationContext<'_> {
@@ -34,12 +52,18 @@ impl CompilationContext<'_> {
         deps_data: &'a HashMap<ModuleId, ModuleData>,
         memory_id: MemoryId,
         allocator: FunctionId,
+        options: &'a TranslationOptions,
+        debug_info: Option<&'a DebugInfo<'a>>,
+        flows: Option<&'a FlowCache>,
     ) -> CompilationContext<'a> {
         CompilationContext::<'a> {
             root_module_data,
             deps_data,
             memory_id,
             allocator,
+            options,
+            debug_info,
+            flows,
         }
     }
 
@@ -4,7 +4,14 @@ mod struct_data;
 
 use crate::{
     GlobalFunctionTable,
-    compilation_context::reserved_modules::STYLUS_FRAMEWORK_ADDRESS,
+    compilation_context::{Interner, Symbol, reserved_modules::STYLUS_FRAMEWORK_ADDRESS},
+    constructor::parse_constructor_functions,
+    hostio::{reentrancy::parse_non_reentrant_functions, view::parse_view_functions},
+    storage::{
+        namespace::{erc7201_root_slot, parse_storage_namespaces},
+        pinned_slots::{PinnedSlots, parse_pinned_slots},
+        versioning::{parse_storage_migrations, parse_storage_versions},
+    },
     translation::{
         functions::MappedFunction,
         intermediate_types::{
@@ -20,10 +27,10 @@ use function_data::FunctionData;
 use move_binary_format::{
     CompiledModule,
     file_format::{
-        Ability, AbilitySet, Constant, DatatypeHandleIndex, EnumDefinitionIndex, FieldHandleIndex,
-        FieldInstantiationIndex, FunctionDefinition, FunctionDefinitionIndex, Signature,
-        SignatureIndex, SignatureToken, StructDefInstantiationIndex, StructDefinitionIndex,
-        VariantHandleIndex, Visibility,
+        Ability, AbilitySet, Bytecode, Constant, DatatypeHandleIndex, EnumDefinitionIndex,
+        FieldHandleIndex, FieldInstantiationIndex, FunctionDefinition, FunctionDefinitionIndex,
+        Signature, SignatureIndex, SignatureToken, StructDefInstantiationIndex,
+        StructDefinitionIndex, VariantHandleIndex, Visibility,
     },
     internals::ModuleIndex,
 };
@@ -32,7 +39,7 @@ use move_package::{
     source_package::parsed_manifest::PackageName,
 };
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fmt::{Debug, Display},
 };
 use struct_data::StructData;
@@ -87,7 +94,7 @@ impl From<[u8; 32]> for Address {
 #[derive(PartialEq, Eq, Hash, Debug, Clone)]
 pub struct ModuleId {
     pub address: Address,
-    pub module_name: String,
+    pub module_name: Symbol,
 }
 
 impl Display for ModuleId {
@@ -101,7 +108,7 @@ impl Default for ModuleId {
     fn default() -> Self {
         Self {
             address: Address::from([0; 32]),
-            module_name: "default".to_owned(),
+            module_name: Symbol::default_module(),
         }
     }
 }
@@ -135,21 +142,33 @@ pub struct ModuleData {
 impl ModuleData {
     pub fn build_module_data<'move_package>(
         module_id: ModuleId,
-        move_module: &'move_package CompiledModule,
+        move_unit: &'move_package CompiledUnitWithSource,
         move_module_dependencies: &'move_package [(PackageName, CompiledUnitWithSource)],
         root_compiled_units: &'move_package [CompiledUnitWithSource],
         function_definitions: &mut GlobalFunctionTable<'move_package>,
+        symbols: &Interner,
     ) -> Self {
+        let move_module = &move_unit.unit.module;
+
         let datatype_handles_map = Self::process_datatype_handles(
             &module_id,
             move_module,
             move_module_dependencies,
             root_compiled_units,
+            symbols,
         );
 
         // Module's structs
-        let (module_structs, fields_to_struct_map) =
-            Self::process_concrete_structs(move_module, &datatype_handles_map);
+        let storage_namespaces = Self::process_storage_namespaces(move_unit);
+        let pinned_slots = Self::process_pinned_slots(move_unit);
+        let storage_versions = Self::process_storage_versions(move_unit);
+        let (module_structs, fields_to_struct_map) = Self::process_concrete_structs(
+            move_module,
+            &datatype_handles_map,
+            &storage_namespaces,
+            &pinned_slots,
+            &storage_versions,
+        );
 
         let (module_generic_structs_instances, generic_fields_to_struct_map) =
             Self::process_generic_structs(move_module, &datatype_handles_map);
@@ -174,14 +193,25 @@ impl ModuleData {
             variants_to_enum: variants_to_enum_map,
         };
 
+        let non_reentrant_functions = Self::process_non_reentrant_functions(move_unit);
+        let view_functions = Self::process_view_functions(move_unit);
+        let storage_migrations = Self::process_storage_migrations(move_unit);
+        let constructor_functions = Self::process_constructor_functions(move_unit);
         let functions = Self::process_function_definitions(
             module_id.clone(),
             move_module,
             &datatype_handles_map,
             function_definitions,
             move_module_dependencies,
+            &non_reentrant_functions,
+            &view_functions,
+            &storage_migrations,
+            &constructor_functions,
+            symbols,
         );
 
+        Self::check_storage_migrations(&functions, &structs);
+
         let signatures = move_module
             .signatures()
             .iter()
@@ -209,6 +239,7 @@ impl ModuleData {
         module: &CompiledModule,
         move_module_dependencies: &[(PackageName, CompiledUnitWithSource)],
         root_compiled_units: &[CompiledUnitWithSource],
+        symbols: &Interner,
     ) -> HashMap<DatatypeHandleIndex, UserDefinedType> {
         let mut datatype_handles_map = HashMap::new();
 
@@ -246,7 +277,7 @@ impl ModuleData {
 
                 let module_id = ModuleId {
                     address: module_address.into_bytes().into(),
-                    module_name: module_name.to_string(),
+                    module_name: symbols.intern(module_name.as_str()),
                 };
 
                 // Find the module where the external data is defined, we first look for it in the
@@ -305,9 +336,112 @@ impl ModuleData {
         datatype_handles_map
     }
 
+    /// Reads the `storage_namespace` attributes from the module's source. Returns, for every
+    /// annotated struct, the ERC-7201 root slot where its objects are saved.
+    fn process_storage_namespaces(move_unit: &CompiledUnitWithSource) -> HashMap<String, [u8; 32]> {
+        let Ok(source) = std::fs::read_to_string(&move_unit.source_path) else {
+            return HashMap::new();
+        };
+
+        parse_storage_namespaces(&source)
+            .into_iter()
+            .map(|(identifier, namespace)| (identifier, erc7201_root_slot(&namespace)))
+            .collect()
+    }
+
+    /// Reads the `non_reentrant` attributes from the module's source. Returns the names of the
+    /// annotated functions.
+    fn process_non_reentrant_functions(move_unit: &CompiledUnitWithSource) -> HashSet<String> {
+        let Ok(source) = std::fs::read_to_string(&move_unit.source_path) else {
+            return HashSet::new();
+        };
+
+        parse_non_reentrant_functions(&source)
+    }
+
+    /// Reads the `view` attributes from the module's source. Returns the names of the annotated
+    /// functions.
+    fn process_view_functions(move_unit: &CompiledUnitWithSource) -> HashSet<String> {
+        let Ok(source) = std::fs::read_to_string(&move_unit.source_path) else {
+            return HashSet::new();
+        };
+
+        parse_view_functions(&source)
+    }
+
+    /// Reads the `storage_version` attributes from the module's source. Returns, for every
+    /// annotated struct, its version.
+    fn process_storage_versions(move_unit: &CompiledUnitWithSource) -> HashMap<String, u8> {
+        let Ok(source) = std::fs::read_to_string(&move_unit.source_path) else {
+            return HashMap::new();
+        };
+
+        parse_storage_versions(&source)
+    }
+
+    /// Reads the `storage_migration` attributes from the module's source. Returns the names of the
+    /// annotated functions.
+    fn process_storage_migrations(move_unit: &CompiledUnitWithSource) -> HashSet<String> {
+        let Ok(source) = std::fs::read_to_string(&move_unit.source_path) else {
+            return HashSet::new();
+        };
+
+        parse_storage_migrations(&source)
+    }
+
+    /// Reads the `constructor` attributes from the module's source. Returns the names of the
+    /// annotated functions.
+    fn process_constructor_functions(move_unit: &CompiledUnitWithSource) -> HashSet<String> {
+        let Ok(source) = std::fs::read_to_string(&move_unit.source_path) else {
+            return HashSet::new();
+        };
+
+        parse_constructor_functions(&source)
+    }
+
+    /// Checks that every storage migration function migrates a different versioned struct.
+    fn check_storage_migrations(functions: &FunctionData, structs: &StructData) {
+        let mut migrated_structs = HashSet::new();
+        for function in functions.information.iter().filter(|f| f.storage_migration) {
+            let Some(IntermediateType::IMutRef(inner)) = function.signature.arguments.first()
+            else {
+                unreachable!("storage migration functions take the struct as first argument");
+            };
+            let IntermediateType::IStruct { index, .. } = inner.as_ref() else {
+                unreachable!("storage migration functions take a struct as first argument");
+            };
+
+            let struct_ = structs.get_by_index(*index).unwrap();
+            assert!(
+                struct_.storage_version.is_some(),
+                "storage migration function {} migrates struct {}, that does not have a storage version",
+                function.function_id.identifier,
+                struct_.identifier
+            );
+            assert!(
+                migrated_structs.insert(*index),
+                "struct {} has more than one storage migration function",
+                struct_.identifier
+            );
+        }
+    }
+
+    /// Reads the `storage_slot` attributes from the module's source. Returns, for every annotated
+    /// struct, the slots pinned in it.
+    fn process_pinned_slots(move_unit: &CompiledUnitWithSource) -> HashMap<String, PinnedSlots> {
+        let Ok(source) = std::fs::read_to_string(&move_unit.source_path) else {
+            return HashMap::new();
+        };
+
+        parse_pinned_slots(&source)
+    }
+
     fn process_concrete_structs(
         module: &CompiledModule,
         datatype_handles_map: &HashMap<DatatypeHandleIndex, UserDefinedType>,
+        storage_namespaces: &HashMap<String, [u8; 32]>,
+        pinned_slots: &HashMap<String, PinnedSlots>,
+        storage_versions: &HashMap<String, u8>,
     ) -> (
         Vec<IStruct>,
         HashMap<FieldHandleIndex, StructDefinitionIndex>,
@@ -319,8 +453,11 @@ impl ModuleData {
             let struct_index = StructDefinitionIndex::new(index as u16);
             let mut fields_map = HashMap::new();
             let mut all_fields = Vec::new();
+            let mut field_names = Vec::new();
             if let Some(fields) = struct_def.fields() {
                 for (field_index, field) in fields.iter().enumerate() {
+                    field_names.push(module.identifier_at(field.name).to_string());
+
                     let intermediate_type = IntermediateType::try_from_signature_token(
                         &field.signature.0,
                         datatype_handles_map,
@@ -364,14 +501,63 @@ impl ModuleData {
 
             let is_one_time_witness = Self::is_one_time_witness(module, struct_def.struct_handle);
 
-            module_structs.push(IStruct::new(
+            let namespace_root_slot = storage_namespaces.get(&identifier).copied();
+            assert!(
+                namespace_root_slot.is_none() || is_saved_in_storage,
+                "struct {identifier} has a storage namespace but it does not have the key ability"
+            );
+
+            let mut struct_ = IStruct::new(
                 struct_index,
                 identifier,
                 all_fields,
                 fields_map,
                 is_saved_in_storage,
                 is_one_time_witness,
-            ));
+            );
+            struct_.namespace_root_slot = namespace_root_slot;
+            struct_.field_names = field_names;
+
+            struct_.storage_version = storage_versions.get(&struct_.identifier).copied();
+            assert!(
+                struct_.storage_version.is_none() || is_saved_in_storage,
+                "struct {} has a storage version but it does not have the key ability",
+                struct_.identifier
+            );
+
+            if let Some(pinned) = pinned_slots.get(&struct_.identifier) {
+                assert!(
+                    is_saved_in_storage,
+                    "struct {} has pinned storage slots but it does not have the key ability",
+                    struct_.identifier
+                );
+
+                // The first field is the UID, the struct slot pins the one after it
+                if let Some(slot) = pinned.struct_slot {
+                    struct_.pinned_slots.insert(1, slot);
+                }
+
+                for (field, slot) in &pinned.field_slots {
+                    let index = struct_
+                        .field_names
+                        .iter()
+                        .position(|f| f == field)
+                        .unwrap_or_else(|| {
+                            panic!(
+                                "struct {} has a pinned storage slot for unknown field {field}",
+                                struct_.identifier
+                            )
+                        });
+                    assert!(
+                        index > 0,
+                        "the UID of struct {} can't be pinned to a storage slot",
+                        struct_.identifier
+                    );
+                    struct_.pinned_slots.insert(index, *slot);
+                }
+            }
+
+            module_structs.push(struct_);
         }
 
         (module_structs, fields_to_struct_map)
@@ -531,12 +717,53 @@ impl ModuleData {
         (module_enums, variants_to_enum_map)
     }
 
+    /// Saves the definitions of the functions defined in the module in the global function table,
+    /// as building its module data does. Used when the module data is reused from a previous
+    /// translation, since the table borrows the definitions from the compiled package.
+    pub fn register_function_definitions<'move_package>(
+        module_id: &ModuleId,
+        move_module: &'move_package CompiledModule,
+        function_definitions: &mut GlobalFunctionTable<'move_package>,
+        symbols: &Interner,
+    ) {
+        for (index, function) in move_module.function_handles().iter().enumerate() {
+            let function_module = move_module.module_handle_at(function.module);
+            let function_module_name = move_module.identifier_at(function_module.name).as_str();
+            let function_module_address: Address = move_module
+                .address_identifier_at(function_module.address)
+                .into_bytes()
+                .into();
+
+            if function_module_name != module_id.module_name
+                || function_module_address != module_id.address
+            {
+                continue;
+            }
+
+            let function_id = FunctionId {
+                identifier: symbols.intern(move_module.identifier_at(function.name).as_str()),
+                module_id: module_id.clone(),
+                type_instantiations: None,
+            };
+            function_definitions.insert(
+                function_id,
+                move_module.function_def_at(FunctionDefinitionIndex::new(index as u16)),
+            );
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
     fn process_function_definitions<'move_package>(
         module_id: ModuleId,
         move_module: &'move_package CompiledModule,
         datatype_handles_map: &HashMap<DatatypeHandleIndex, UserDefinedType>,
         function_definitions: &mut GlobalFunctionTable<'move_package>,
         move_module_dependencies: &'move_package [(PackageName, CompiledUnitWithSource)],
+        non_reentrant_functions: &HashSet<String>,
+        view_functions: &HashSet<String>,
+        storage_migrations: &HashSet<String>,
+        constructor_functions: &HashSet<String>,
+        symbols: &Interner,
     ) -> FunctionData {
         // Return types of functions in intermediate types. Used to fill the stack type
         let mut functions_returns = Vec::new();
@@ -544,6 +771,7 @@ impl ModuleData {
         let mut function_calls = Vec::new();
         let mut function_information = Vec::new();
         let mut init = None;
+        let mut constructor = None;
 
         for (index, function) in move_module.function_handles().iter().enumerate() {
             let move_function_arguments = &move_module.signature_at(function.parameters);
@@ -577,12 +805,11 @@ impl ModuleData {
                 .into_bytes()
                 .into();
 
-            // TODO: clones and to_string()....
             let function_id = FunctionId {
-                identifier: function_name.to_string(),
+                identifier: symbols.intern(function_name),
                 module_id: ModuleId {
                     address: function_module_address,
-                    module_name: function_module_name.to_string(),
+                    module_name: symbols.intern(function_module_name),
                 },
                 type_instantiations: None,
             };
@@ -626,14 +853,77 @@ impl ModuleData {
                     init = Some(function_id.clone());
                 }
 
-                function_information.push(MappedFunction::new(
+                let mut mapped_function = MappedFunction::new(
                     function_id.clone(),
                     move_function_arguments,
                     move_function_return,
                     code_locals,
                     function_def,
                     datatype_handles_map,
-                ));
+                );
+
+                if mapped_function.is_entry {
+                    mapped_function.lazy_storage_fields = Self::get_lazy_storage_fields(
+                        function_def,
+                        move_module,
+                        &mapped_function.signature.arguments,
+                    );
+                }
+
+                if non_reentrant_functions.contains(function_name) {
+                    assert!(
+                        mapped_function.is_entry,
+                        "non_reentrant can only be used in entry functions: {function_name}"
+                    );
+                    mapped_function.non_reentrant = true;
+                }
+
+                if view_functions.contains(function_name) {
+                    assert!(
+                        mapped_function.is_entry,
+                        "view can only be used in entry functions: {function_name}"
+                    );
+                    mapped_function.is_view = true;
+                }
+
+                if storage_migrations.contains(function_name) {
+                    let arguments = &mapped_function.signature.arguments;
+                    let migrates_struct = matches!(
+                        arguments.first(),
+                        Some(IntermediateType::IMutRef(inner))
+                            if matches!(
+                                inner.as_ref(),
+                                IntermediateType::IStruct { module_id: struct_module, .. }
+                                    if *struct_module == module_id
+                            )
+                    );
+                    assert!(
+                        migrates_struct
+                            && arguments.len() == 2
+                            && arguments[1] == IntermediateType::IU8
+                            && mapped_function.signature.returns.is_empty(),
+                        "storage migration functions must have the signature fun(&mut <struct defined in the module>, u8): {function_name}"
+                    );
+                    mapped_function.storage_migration = true;
+                }
+
+                if constructor_functions.contains(function_name) {
+                    // Entry functions could be called again once the contract is constructed
+                    assert!(
+                        !mapped_function.is_entry
+                            && !mapped_function.is_generic
+                            && !is_init
+                            && mapped_function.signature.returns.is_empty(),
+                        "constructor functions must be private, non generic functions, other than init, with no return values: {function_name}"
+                    );
+                    assert!(
+                        constructor.is_none(),
+                        "There can be only a single constructor function per module."
+                    );
+                    constructor = Some(function_id.clone());
+                }
+
+                function_information.push(mapped_function);
 
                 function_definitions.insert(function_id.clone(), function_def);
             }
@@ -661,10 +951,10 @@ impl ModuleData {
                 .unwrap();
 
             let function_id = FunctionId {
-                identifier: function_name.to_string(),
+                identifier: symbols.intern(function_name),
                 module_id: ModuleId {
                     address: function_module_address,
-                    module_name: function_module_name.to_string(),
+                    module_name: symbols.intern(function_module_name),
                 },
                 type_instantiations: Some(type_instantiations),
             };
@@ -679,6 +969,7 @@ impl ModuleData {
             generic_calls: generic_function_calls,
             information: function_information,
             init,
+            constructor,
         }
     }
 
@@ -701,6 +992,84 @@ impl ModuleData {
     // fun init(otw: OTW, ctx: &mut TxContext) { /* ... */ }
     //
 
+    /// For every argument of the function that is an immutable reference to a struct, looks for
+    /// the fields the function accesses, so only those are read when the struct comes from the
+    /// storage.
+    ///
+    /// An argument is only considered if every use of it is a field borrow. If the reference is
+    /// used in any other way (for example, passed to another function) we can't know which
+    /// fields are needed.
+    fn get_lazy_storage_fields(
+        function_def: &FunctionDefinition,
+        move_module: &CompiledModule,
+        arguments: &[IntermediateType],
+    ) -> HashMap<usize, Vec<usize>> {
+        let mut lazy_storage_fields = HashMap::new();
+
+        let Some(code) = function_def.code.as_ref() else {
+            return lazy_storage_fields;
+        };
+
+        for (arg_index, argument) in arguments.iter().enumerate() {
+            let IntermediateType::IRef(inner) = argument else {
+                continue;
+            };
+
+            if !matches!(
+                **inner,
+                IntermediateType::IStruct { .. } | IntermediateType::IGenericStructInstance { .. }
+            ) {
+                continue;
+            }
+
+            let mut fields = Vec::new();
+            let mut only_field_borrows = true;
+            for (pc, instruction) in code.code.iter().enumerate() {
+                let local = match instruction {
+                    Bytecode::CopyLoc(local)
+                    | Bytecode::MoveLoc(local)
+                    | Bytecode::StLoc(local)
+                    | Bytecode::ImmBorrowLoc(local)
+                    | Bytecode::MutBorrowLoc(local) => *local as usize,
+                    _ => continue,
+                };
+
+                if local != arg_index {
+                    continue;
+                }
+
+                let field_handle = match (instruction, code.code.get(pc + 1)) {
+                    (
+                        Bytecode::CopyLoc(_) | Bytecode::MoveLoc(_),
+                        Some(Bytecode::ImmBorrowField(field_handle)),
+                    ) => move_module.field_handle_at(*field_handle),
+                    (
+                        Bytecode::CopyLoc(_) | Bytecode::MoveLoc(_),
+                        Some(Bytecode::ImmBorrowFieldGeneric(field_instantiation)),
+                    ) => move_module.field_handle_at(
+                        move_module
+                            .field_instantiation_at(*field_instantiation)
+                            .handle,
+                    ),
+                    _ => {
+                        only_field_borrows = false;
+                        break;
+                    }
+                };
+
+                fields.push(field_handle.field as usize);
+            }
+
+            if only_field_borrows {
+                fields.sort_unstable();
+                fields.dedup();
+                lazy_storage_fields.insert(arg_index, fields);
+            }
+        }
+
+        lazy_storage_fields
+    }
+
     /// Checks if the given function (by index) is a valid `init` function.
     // TODO: Note that we currently trigger a panic if a function named 'init' fails to satisfy certain criteria to qualify as a constructor.
     // This behavior is not enforced by the move compiler itself.
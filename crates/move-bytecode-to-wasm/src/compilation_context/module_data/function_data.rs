@@ -22,4 +22,18 @@ pub struct FunctionData {
 
     /// The init function of the module.
     pub init: Option<FunctionId>,
+
+    /// The function annotated with `#[ext(constructor)]`, called after `init` with the arguments
+    /// of the constructor.
+    pub constructor: Option<FunctionId>,
+}
+
+impl FunctionData {
+    /// Information about the `#[ext(constructor)]` function, if the module has one.
+    pub fn constructor_information(&self) -> Option<&MappedFunction> {
+        let constructor = self.constructor.as_ref()?;
+        self.information
+            .iter()
+            .find(|f| f.function_id == *constructor)
+    }
 }
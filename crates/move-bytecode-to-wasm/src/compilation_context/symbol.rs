@@ -0,0 +1,190 @@
+//! Interned strings for the names of the modules and the functions, cloned into every `ModuleId`
+//! and `FunctionId`. Every name is kept once by the `Interner` of the translation, which numbers
+//! them in the order they are interned, so the symbols are hashed and compared by their index
+//! instead of the string. Every symbol shares the string of its name, so it can be read without
+//! the interner.
+//!
+//! The interner is kept by the `TranslationCache` with the module data of the dependencies, whose
+//! symbols must be compared with the ones of the rebuilds in `--watch`, and the names are freed
+//! with it. The package is processed in the same order from one build to the next, so two builds
+//! from scratch give the names the same indices, and the generic functions, named after the hashes
+//! of the types they are instantiated with, the same names.
+use std::{
+    collections::HashMap,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Deref,
+    sync::{Arc, Mutex},
+};
+
+/// Index of the symbol of the default module of the tests, outside of every interner
+const DEFAULT_MODULE_INDEX: u32 = u32::MAX;
+
+#[derive(Default)]
+pub struct Interner {
+    indices: Mutex<HashMap<Arc<str>, u32>>,
+}
+
+impl Interner {
+    /// Returns the symbol of the string, adding it to the interner if it is not there yet.
+    pub fn intern(&self, s: &str) -> Symbol {
+        let mut indices = self.indices.lock().expect("the interner is not poisoned");
+        if let Some((name, index)) = indices.get_key_value(s) {
+            return Symbol {
+                index: *index,
+                name: name.clone(),
+            };
+        }
+
+        let index = u32::try_from(indices.len()).expect("too many symbols interned");
+        let name: Arc<str> = s.into();
+        indices.insert(name.clone(), index);
+        Symbol { index, name }
+    }
+
+    pub fn clear(&mut self) {
+        self.indices
+            .get_mut()
+            .expect("the interner is not poisoned")
+            .clear();
+    }
+}
+
+#[derive(Clone)]
+pub struct Symbol {
+    index: u32,
+    name: Arc<str>,
+}
+
+impl Symbol {
+    /// Symbol of the name of the default module, used by the tests.
+    pub(crate) fn default_module() -> Self {
+        Self {
+            index: DEFAULT_MODULE_INDEX,
+            name: "default".into(),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Deref for Symbol {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.name
+    }
+}
+
+impl AsRef<str> for Symbol {
+    fn as_ref(&self) -> &str {
+        &self.name
+    }
+}
+
+// Symbols of the same interner are equal if they have the same index
+impl PartialEq for Symbol {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index
+    }
+}
+
+impl Eq for Symbol {}
+
+impl Hash for Symbol {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.index.hash(state);
+    }
+}
+
+// By name, so the symbols are sorted the same whatever the order they were interned in
+impl PartialOrd for Symbol {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Symbol {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.name.cmp(&other.name)
+    }
+}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        &*self.name == other
+    }
+}
+
+impl PartialEq<&str> for Symbol {
+    fn eq(&self, other: &&str) -> bool {
+        &*self.name == *other
+    }
+}
+
+impl PartialEq<String> for Symbol {
+    fn eq(&self, other: &String) -> bool {
+        &*self.name == other.as_str()
+    }
+}
+
+impl PartialEq<Symbol> for &str {
+    fn eq(&self, other: &Symbol) -> bool {
+        *self == &*other.name
+    }
+}
+
+impl PartialEq<Symbol> for String {
+    fn eq(&self, other: &Symbol) -> bool {
+        self.as_str() == &*other.name
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&*self.name, f)
+    }
+}
+
+// As a `String`, so the symbols are printed as the names were
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&*self.name, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_intern() {
+        let interner = Interner::default();
+        let counter = interner.intern("counter");
+        let owned = String::from("counter");
+        assert_eq!(counter, interner.intern(&owned));
+        assert_eq!(counter.index, interner.intern(&owned).index);
+        assert!(Arc::ptr_eq(&counter.name, &interner.intern(&owned).name));
+        assert_ne!(counter, interner.intern("vault"));
+        assert_eq!(1, interner.intern("vault").index);
+
+        assert_eq!(counter, "counter");
+        assert_eq!("counter", counter);
+        assert_eq!(owned, counter);
+        assert_eq!("\"counter\"", format!("{counter:?}"));
+        assert!(interner.intern("b") > interner.intern("a"));
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut interner = Interner::default();
+        let counter = interner.intern("counter");
+        interner.clear();
+
+        // The names are freed with the interner, once no symbol holds them
+        assert_eq!(1, Arc::strong_count(&counter.name));
+        assert_eq!(0, interner.intern("vault").index);
+    }
+}
@@ -1,23 +1,48 @@
+//! Constructor of the modules, injected as the `constructor` entry function deployers call once
+//! the contract is deployed.
+//!
+//! It calls the module's `init` function, which only receives the one-time witness and the
+//! `TxContext`, and then the private function annotated with `#[ext(constructor)]`, if any, which
+//! receives the arguments of the constructor, decoded as the ones of the entry functions:
+//!
+//! ```move
+//! #[ext(constructor)]
+//! fun setup(owner: address, fee: u64, ctx: &mut TxContext) { ... }
+//! ```
+//!
+//! Attributes are not part of the compiled bytecode, so they are read from the module's source.
+use std::collections::HashSet;
+
 use walrus::{
-    FunctionBuilder, FunctionId as WalrusFunctionId, Module, ValType,
+    FunctionBuilder, FunctionId as WalrusFunctionId, LocalId, Module, ValType,
     ir::{MemArg, StoreKind},
 };
 
 use crate::{
     CompilationContext,
     abi_types::public_function::PublicFunction,
-    hostio::host_functions,
     runtime::RuntimeFunction,
-    translation::{intermediate_types::ISignature, table::FunctionTable},
-    utils::keccak_string_to_memory,
+    translation::{
+        intermediate_types::ISignature,
+        table::{FunctionId, FunctionTable},
+    },
+    utils::{keccak_string_to_memory, parse_ext_flag},
     vm_handled_types::{VmHandledType, tx_context::TxContext},
 };
 
+const CONSTRUCTOR_ATTRIBUTE: &str = "constructor";
+
 static EMPTY_SIGNATURE: ISignature = ISignature {
     arguments: Vec::new(),
     returns: Vec::new(),
 };
 
+/// Searches the Move source for `constructor` attributes and returns the names of the annotated
+/// functions.
+pub fn parse_constructor_functions(source: &str) -> HashSet<String> {
+    parse_ext_flag(source, CONSTRUCTOR_ATTRIBUTE)
+}
+
 /// Injects the constructor as a public function in the module, which will be accesible via the entrypoint router.
 pub fn inject_constructor(
     function_table: &mut FunctionTable,
@@ -25,36 +50,53 @@ pub fn inject_constructor(
     compilation_ctx: &CompilationContext,
     public_functions: &mut Vec<PublicFunction>,
 ) {
-    if let Some(ref init_id) = compilation_ctx.root_module_data.functions.init {
-        let wasm_init_fn = function_table
-            .get_by_function_id(init_id)
+    let functions = &compilation_ctx.root_module_data.functions;
+    if functions.init.is_none() && functions.constructor.is_none() {
+        return;
+    }
+
+    let wasm_function = |function_id: &FunctionId| {
+        function_table
+            .get_by_function_id(function_id)
             .unwrap()
             .wasm_function_id
-            .unwrap();
-
-        let constructor_fn_id = build_constructor(module, compilation_ctx, wasm_init_fn);
-
-        public_functions.push(PublicFunction::new(
-            constructor_fn_id,
-            "constructor",
-            &EMPTY_SIGNATURE,
-            compilation_ctx,
-        ));
+            .unwrap()
     };
+    let wasm_init_fn = functions.init.as_ref().map(wasm_function);
+    let wasm_constructor_fn = functions.constructor.as_ref().map(wasm_function);
+
+    // The constructor takes the arguments of the annotated function
+    let signature = functions
+        .constructor_information()
+        .map_or(&EMPTY_SIGNATURE, |function_information| {
+            &function_information.signature
+        });
+
+    let constructor_fn_id =
+        build_constructor(module, compilation_ctx, wasm_init_fn, wasm_constructor_fn);
+
+    public_functions.push(PublicFunction::new(
+        constructor_fn_id,
+        "constructor",
+        signature,
+        compilation_ctx,
+    ));
 }
 
 /// Builds the constructor function.
 ///
 /// This function performs the following actions:
 /// 1. Verifies whether the constructor has been invoked before using a storage key guard.
-/// 2. If it hasn't, it calls the `init()` function.
+/// 2. If it hasn't, it calls the `init()` function, and then the `#[ext(constructor)]` one with
+///    the arguments of the constructor.
 /// 3. Records in persistent storage that the constructor has been executed.
 ///
 /// This ensures the constructor logic executes only once and safely initializes global storage.
 pub fn build_constructor(
     module: &mut Module,
     compilation_ctx: &CompilationContext,
-    init: WalrusFunctionId,
+    init: Option<WalrusFunctionId>,
+    constructor: Option<WalrusFunctionId>,
 ) -> WalrusFunctionId {
     // Flag to indicate if the constructor has been called.
     // This is what we are going to be storing in the storage.
@@ -64,16 +106,22 @@ pub fn build_constructor(
     let is_zero_fn = RuntimeFunction::IsZero.get(module, Some(compilation_ctx));
 
     // Host functions for storage operations
-    let (storage_load_fn, _) = host_functions::storage_load_bytes32(module);
-    let (storage_cache_fn, _) = host_functions::storage_cache_bytes32(module);
-    let (flush_cache_fn, _) = host_functions::storage_flush_cache(module);
+    let storage_load_fn = RuntimeFunction::StorageCachedLoad.get(module, Some(compilation_ctx));
+    let storage_cache_fn = RuntimeFunction::StorageCachedStore.get(module, Some(compilation_ctx));
 
     // Allocate local variables to hold memory pointers
     let key_ptr = module.locals.add(ValType::I32); // Pointer for the storage key
     let value_ptr = module.locals.add(ValType::I32); // Pointer to store flag
 
-    // Define the constructor function with no parameters or return values
-    let mut function = FunctionBuilder::new(&mut module.types, &[], &[]);
+    // The constructor has the parameters of the `#[ext(constructor)]` function, and no return
+    // values
+    let params = constructor.map_or_else(Vec::new, |constructor| {
+        let constructor_ty = module.funcs.get(constructor).ty();
+        module.types.get(constructor_ty).params().to_vec()
+    });
+    let arguments: Vec<LocalId> = params.iter().map(|p| module.locals.add(*p)).collect();
+
+    let mut function = FunctionBuilder::new(&mut module.types, &params, &[]);
     let mut builder = function.func_body();
 
     // ptr to storage key
@@ -105,22 +153,32 @@ pub fn build_constructor(
         None,
         |then| {
             // If an `init()` function is present, call it
-            let init_ty = module.funcs.get(init).ty();
-            let params = module.types.get(init_ty).params();
-
-            // If the function expects an OTW, push dummy value.
-            // The OTW is a Move pattern used to ensure that the init function is called only once.
-            // Here we replace that logic by writing a marker value into the storage.
-            // TODO: revisit the OTW implementation and check if this approach is correct.
-            if params.len() == 2 {
-                then.i32_const(0); // OTW = 0 
+            if let Some(init) = init {
+                let init_ty = module.funcs.get(init).ty();
+                let params = module.types.get(init_ty).params();
+
+                // If the function expects an OTW, push dummy value.
+                // The OTW is a Move pattern used to ensure that the init function is called only
+                // once. Here we replace that logic by writing a marker value into the storage.
+                // TODO: revisit the OTW implementation and check if this approach is correct.
+                if params.len() == 2 {
+                    then.i32_const(0); // OTW = 0
+                }
+
+                // Inject TxContext as last argument
+                TxContext::inject(then, module, compilation_ctx);
+
+                // Call the `init` function
+                then.call(init);
             }
 
-            // Inject TxContext as last argument
-            TxContext::inject(then, module, compilation_ctx);
-
-            // Call the `init` function
-            then.call(init);
+            // Then the `#[ext(constructor)]` function, with the arguments decoded by the router
+            if let Some(constructor) = constructor {
+                for argument in &arguments {
+                    then.local_get(*argument);
+                }
+                then.call(constructor);
+            }
 
             // Write the flag at value_ptr
             then.local_get(value_ptr).i32_const(FLAG).store(
@@ -132,13 +190,11 @@ pub fn build_constructor(
                 },
             );
 
-            // Cache the flag
+            // Cache the flag. It is persisted when the storage cache is committed at the end of
+            // the call
             then.local_get(key_ptr)
                 .local_get(value_ptr)
                 .call(storage_cache_fn);
-
-            // Flush storage to persist the flag
-            then.i32_const(1).call(flush_cache_fn);
         },
         |_else| {
             // Constructor already called → do nothing
@@ -146,5 +202,5 @@ pub fn build_constructor(
     );
 
     // Finalize and insert the function into the module
-    function.finish(vec![], &mut module.funcs)
+    function.finish(arguments, &mut module.funcs)
 }
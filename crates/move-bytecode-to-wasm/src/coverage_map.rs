@@ -0,0 +1,155 @@
+//! Map from the basic blocks reported by the coverage instrumentation to the lines of the Move
+//! source, emitted as `<module>.coverage.json` when the package is translated with the `coverage`
+//! option.
+//!
+//! Every basic block of the root module's functions calls the `coverage::hit` hook with the id of
+//! its function and the offset of its first instruction, so a test runner can count the blocks
+//! executed and map them to the lines and branches of the source with this map.
+use move_abstract_interpreter::control_flow_graph::{ControlFlowGraph, VMControlFlowGraph};
+use move_binary_format::file_format::{Bytecode, FunctionDefinitionIndex};
+use move_package::compilation::compiled_package::CompiledUnitWithSource;
+use serde::Serialize;
+
+use crate::compilation_context::ModuleData;
+
+#[derive(Debug, Serialize)]
+pub struct CoverageMap {
+    /// Path of the module's source file
+    pub source_path: String,
+    pub functions: Vec<CoverageFunction>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CoverageFunction {
+    /// Id the function's blocks are reported with
+    pub id: u32,
+    pub name: String,
+    /// Line the function is declared at
+    pub line: u32,
+    /// Basic blocks of the function, the first one being its entry
+    pub blocks: Vec<CoverageBlock>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CoverageBlock {
+    /// Offset of the block's first instruction, the block is reported with
+    pub start: u16,
+    /// Lines of the block's instructions, in order and without repetitions
+    pub lines: Vec<u32>,
+    /// Conditional branch the block ends with, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub branch: Option<CoverageBranch>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CoverageBranch {
+    pub line: u32,
+    /// Offsets of the blocks the branch continues in
+    pub targets: Vec<u16>,
+}
+
+impl CoverageMap {
+    /// Maps the blocks of the functions defined in the module to the lines of its source. The
+    /// lines are left empty if the source can't be read.
+    pub(crate) fn new(move_unit: &CompiledUnitWithSource, module_data: &ModuleData) -> Self {
+        let source = std::fs::read_to_string(&move_unit.source_path).unwrap_or_default();
+
+        let move_module = &move_unit.unit.module;
+        let source_map = &move_unit.unit.source_map;
+
+        let mut functions = Vec::new();
+        for (index, function_def) in move_module.function_defs().iter().enumerate() {
+            let Some(code) = function_def.code.as_ref() else {
+                continue;
+            };
+
+            let handle = move_module.function_handle_at(function_def.function);
+            let name = move_module.identifier_at(handle.name).to_string();
+            let Some(id) = coverage_function_id(module_data, &name) else {
+                continue;
+            };
+
+            let Ok(function_source_map) =
+                source_map.get_function_source_map(FunctionDefinitionIndex::new(index as u16))
+            else {
+                continue;
+            };
+
+            let code_line = |offset: u16| {
+                function_source_map
+                    .get_code_location(offset)
+                    .map(|loc| line_at(&source, loc.start()))
+            };
+
+            let cfg = VMControlFlowGraph::new(&code.code, &code.jump_tables);
+            let blocks = (&cfg as &dyn ControlFlowGraph)
+                .blocks()
+                .into_iter()
+                .map(|block| {
+                    let start = cfg.block_start(block);
+                    let end = cfg.block_end(block);
+
+                    let mut lines: Vec<u32> = Vec::new();
+                    for line in (start..=end).filter_map(code_line) {
+                        if !lines.contains(&line) {
+                            lines.push(line);
+                        }
+                    }
+
+                    let branch = match code.code[end as usize] {
+                        Bytecode::BrTrue(_) | Bytecode::BrFalse(_) => {
+                            code_line(end).map(|line| CoverageBranch {
+                                line,
+                                targets: cfg.successors(block).to_vec(),
+                            })
+                        }
+                        _ => None,
+                    };
+
+                    CoverageBlock {
+                        start,
+                        lines,
+                        branch,
+                    }
+                })
+                .collect();
+
+            functions.push(CoverageFunction {
+                id: id as u32,
+                name,
+                line: line_at(&source, function_source_map.definition_location.start()),
+                blocks,
+            });
+        }
+
+        Self {
+            source_path: move_unit.source_path.to_string_lossy().into_owned(),
+            functions,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("failed to serialize the coverage map")
+    }
+}
+
+/// Returns the id the blocks of the module's function are reported with: its position among the
+/// module's functions.
+pub(crate) fn coverage_function_id(module_data: &ModuleData, identifier: &str) -> Option<i32> {
+    module_data
+        .functions
+        .information
+        .iter()
+        .position(|function| function.function_id.identifier == identifier)
+        .map(|position| position as i32)
+}
+
+/// Returns the line, starting from 1, of the byte at `offset` in the source.
+pub(crate) fn line_at(source: &str, offset: u32) -> u32 {
+    let offset = (offset as usize).min(source.len());
+    source.as_bytes()[..offset]
+        .iter()
+        .filter(|byte| **byte == b'\n')
+        .count() as u32
+        + 1
+}
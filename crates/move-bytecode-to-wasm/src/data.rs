@@ -28,12 +28,32 @@ pub const DATA_FROZEN_OBJECTS_KEY_OFFSET: i32 = 160;
 /// key 2 for frozen).
 pub const DATA_STORAGE_OBJECT_OWNER_OFFSET: i32 = 192;
 
+/// Pointer to the first entry of the in-memory storage cache, followed by the number of entries
+/// (both 0 if the cache is empty).
+pub const DATA_STORAGE_CACHE_HEAD_OFFSET: i32 = 224;
+
+/// Pointer to the first value borrowed from the storage that must be written back (0 if there
+/// are none).
+pub const DATA_STORAGE_WRITE_BACK_HEAD_OFFSET: i32 = 256;
+
+/// Pointer to the first entry of the in-memory cache of derived mapping slots (0 if the cache is
+/// empty).
+pub const DATA_SLOT_DERIVATION_CACHE_HEAD_OFFSET: i32 = 288;
+
+/// Pointer to the buffer where the data returned by the calls to other contracts is copied,
+/// followed by its capacity (both 0 if it was not allocated yet).
+pub const DATA_RETURN_DATA_BUFFER_OFFSET: i32 = 320;
+
+/// Pointer to the calldata of the current call, read by the entrypoint router, followed by its
+/// length.
+pub const DATA_CALLDATA_OFFSET: i32 = 352;
+
 /// Amount of memory reserved starting from offset 0.
 ///
 /// # WARNING
 /// This value must be kept in sync to correctly initialize the memory allocator
 /// at the proper offset.
-pub const TOTAL_RESERVED_MEMORY: i32 = 256;
+pub const TOTAL_RESERVED_MEMORY: i32 = 384;
 
 /// Initializes the module's data segment.
 pub fn setup_data_segment(module: &mut Module, memory_id: MemoryId) {
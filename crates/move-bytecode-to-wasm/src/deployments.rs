@@ -0,0 +1,135 @@
+//! Manifest of the contracts deployed from a package, the `deployments.json` file `move-stylus
+//! deploy` appends every deployment to, so the systems of many contracts can take the addresses
+//! of the ones deployed before, and scripts and tests can find them.
+//!
+//! The deployments are kept in the order they were made, so the latest deployment of a module on a
+//! chain is the last one listed.
+use std::{fs, path::Path};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+/// Name of the manifest, in the package root
+pub const DEPLOYMENTS_FILE_NAME: &str = "deployments.json";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Deployment {
+    pub module: String,
+    /// Address of the contract, checksummed
+    pub address: String,
+    pub chain_id: u64,
+    /// RPC endpoint of the node the module was deployed to
+    pub endpoint: String,
+    /// Keccak-256 of the code of the contract, the compressed module, as `EXTCODEHASH` returns it
+    pub code_hash: String,
+    /// Keccak-256 of the module, the hash `move-stylus verify` compares with the rebuilt module
+    pub module_hash: String,
+    /// Arguments the constructor was called with, as they were given
+    pub constructor_args: Vec<String>,
+    /// Transaction the constructor was called in, `None` if the module has no `init` function
+    pub constructor_transaction: Option<String>,
+    /// Seconds since the Unix epoch
+    pub timestamp: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Deployments {
+    pub deployments: Vec<Deployment>,
+}
+
+impl Deployments {
+    /// Reads the manifest at `path`, an empty one if it does not exist.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&contents).with_context(|| format!("Invalid {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        fs::write(path, self.to_json())
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Appends the deployment to the manifest at `path`, creating it if it does not exist.
+    pub fn append(path: &Path, deployment: Deployment) -> anyhow::Result<()> {
+        let mut deployments = Self::load(path)?;
+        deployments.deployments.push(deployment);
+        deployments.save(path)
+    }
+
+    /// Latest deployment of the module on the chain, if any.
+    pub fn latest(&self, module: &str, chain_id: u64) -> Option<&Deployment> {
+        self.deployments
+            .iter()
+            .rev()
+            .find(|deployment| deployment.module == module && deployment.chain_id == chain_id)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("failed to serialize the deployments")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn deployment(module: &str, address: &str, chain_id: u64) -> Deployment {
+        Deployment {
+            module: module.to_owned(),
+            address: address.to_owned(),
+            chain_id,
+            endpoint: "http://localhost:8547".to_owned(),
+            code_hash: format!("0x{}", "1".repeat(64)),
+            module_hash: format!("0x{}", "2".repeat(64)),
+            constructor_args: Vec::new(),
+            constructor_transaction: None,
+            timestamp: 1_700_000_000,
+        }
+    }
+
+    #[test]
+    fn test_append_and_load() {
+        let path = std::env::temp_dir().join(format!("deployments-{}.json", std::process::id()));
+        assert_eq!(Deployments::default(), Deployments::load(&path).unwrap());
+
+        Deployments::append(&path, deployment("token", "0x01", 412346)).unwrap();
+        Deployments::append(&path, deployment("token", "0x02", 421614)).unwrap();
+        Deployments::append(&path, deployment("token", "0x03", 412346)).unwrap();
+
+        let deployments = Deployments::load(&path).unwrap();
+        assert_eq!(3, deployments.deployments.len());
+        assert_eq!(
+            Some("0x03"),
+            deployments
+                .latest("token", 412346)
+                .map(|deployment| deployment.address.as_str())
+        );
+        assert_eq!(
+            Some("0x02"),
+            deployments
+                .latest("token", 421614)
+                .map(|deployment| deployment.address.as_str())
+        );
+        assert!(deployments.latest("vault", 412346).is_none());
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_deployment_to_json() {
+        let json = Deployments {
+            deployments: vec![deployment("token", "0x01", 412346)],
+        }
+        .to_json();
+        assert!(json.contains(r#""chainId": 412346"#));
+        assert!(json.contains(r#""constructorArgs": []"#));
+        assert!(json.contains(r#""constructorTransaction": null"#));
+    }
+}
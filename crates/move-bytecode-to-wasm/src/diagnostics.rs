@@ -0,0 +1,194 @@
+//! Diagnostics reported by `translate_package_cli`, as text or as JSON objects, one per line, that
+//! editors and CI can read without parsing the text. The progress of the build is reported the
+//! same way, as notes, so the standard output of a JSON build only holds JSON objects.
+//!
+//! The translation stops at the first error it finds with a panic. `Diagnostic::from_panic` turns
+//! it into a diagnostic pointing to the module being translated, for the panic hooks of the tools
+//! that report it as JSON.
+use std::{cell::RefCell, fmt, panic::PanicHookInfo, path::PathBuf, str::FromStr};
+
+use move_package::compilation::compiled_package::CompiledUnitWithSource;
+use serde::Serialize;
+
+use crate::coverage_map::line_at;
+
+thread_local! {
+    /// Source and line of the module being translated, pointed to by the panics
+    static TRANSLATING: RefCell<Option<(PathBuf, u32)>> = const { RefCell::new(None) };
+}
+
+/// Format the diagnostics are reported in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MessageFormat {
+    /// `warning: <message>`, to the standard error, and the message of the notes, to the standard
+    /// output
+    #[default]
+    Human,
+    /// A JSON object per diagnostic, notes included, to the standard output
+    Json,
+}
+
+impl fmt::Display for MessageFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MessageFormat::Human => write!(f, "human"),
+            MessageFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+impl FromStr for MessageFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "human" => Ok(MessageFormat::Human),
+            "json" => Ok(MessageFormat::Json),
+            _ => Err(format!(
+                "unknown message format {s}, expected human or json"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+    /// Progress of the build, such as the modules compiled
+    Note,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Note => write!(f, "note"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Kind of the diagnostic, for tools to match on: `code-size`, `build-cache`, `translation`,
+    /// `build` or `progress`
+    pub code: &'static str,
+    /// Move source the diagnostic points to, if any
+    pub file: Option<PathBuf>,
+    /// Line of the source, starting from 1
+    pub line: Option<u32>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn error(code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            code,
+            file: None,
+            line: None,
+            message: message.into(),
+        }
+    }
+
+    pub fn warning(code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            ..Self::error(code, message)
+        }
+    }
+
+    pub fn note(code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Note,
+            ..Self::error(code, message)
+        }
+    }
+
+    /// Points the diagnostic to the declaration of the module, found with `module_location`.
+    pub(crate) fn at(self, (file, line): (PathBuf, u32)) -> Self {
+        Self {
+            file: Some(file),
+            line: Some(line),
+            ..self
+        }
+    }
+
+    /// Error of a panic of the translation, pointing to the module being translated, if any.
+    pub fn from_panic(info: &PanicHookInfo<'_>) -> Self {
+        let payload = info.payload();
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|message| message.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| info.to_string());
+
+        let (file, line) = TRANSLATING
+            .with_borrow(|translating| translating.clone())
+            .unzip();
+        Self {
+            file,
+            line,
+            ..Self::error("translation", message)
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("failed to serialize the diagnostic")
+    }
+
+    pub fn report(&self, format: MessageFormat) {
+        match format {
+            MessageFormat::Human if self.severity == Severity::Note => println!("{}", self.message),
+            MessageFormat::Human => eprintln!("{}: {}", self.severity, self.message),
+            MessageFormat::Json => println!("{}", self.to_json()),
+        }
+    }
+}
+
+/// Sets the module the panics of the translation point to, `None` once it is translated.
+pub(crate) fn set_translating(move_unit: Option<&CompiledUnitWithSource>) {
+    let location = move_unit.map(module_location);
+    TRANSLATING.set(location);
+}
+
+/// Source of the module and line of its declaration, the first one if the source can't be read.
+pub(crate) fn module_location(move_unit: &CompiledUnitWithSource) -> (PathBuf, u32) {
+    let source = std::fs::read_to_string(&move_unit.source_path).unwrap_or_default();
+    let offset = move_unit.unit.source_map.definition_location.start();
+    (move_unit.source_path.clone(), line_at(&source, offset))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diagnostic_to_json() {
+        let diagnostic = Diagnostic {
+            file: Some(PathBuf::from("sources/counter.move")),
+            line: Some(1),
+            ..Diagnostic::warning("code-size", "counter is too big")
+        };
+        assert_eq!(
+            r#"{"severity":"warning","code":"code-size","file":"sources/counter.move","line":1,"message":"counter is too big"}"#,
+            diagnostic.to_json()
+        );
+
+        assert_eq!(
+            r#"{"severity":"error","code":"build","file":null,"line":null,"message":"failed"}"#,
+            Diagnostic::error("build", "failed").to_json()
+        );
+    }
+
+    #[test]
+    fn test_parse_message_format() {
+        for format in [MessageFormat::Human, MessageFormat::Json] {
+            assert_eq!(Ok(format), format.to_string().parse());
+        }
+        assert!("short".parse::<MessageFormat>().is_err());
+    }
+}
@@ -6,20 +6,29 @@ use walrus::{FunctionId, MemoryId, Module, ModuleConfig};
 use crate::{
     data::{TOTAL_RESERVED_MEMORY, setup_data_segment},
     memory::setup_module_memory,
+    translation_options::MemoryGrowth,
 };
 
 pub mod entrypoint_router;
 pub mod host_functions;
+pub mod reentrancy;
+pub mod view;
 
-/// Create a new module with stylus memory management functions and adds the `pay_for_memory_grow` function
-/// as required by stylus
-pub fn new_module_with_host() -> (Module, FunctionId, MemoryId) {
-    let config = ModuleConfig::new();
+/// Create a new module from the configuration with stylus memory management functions and adds the `pay_for_memory_grow` function
+/// as required by stylus. The allocator pays for every page before growing the memory.
+pub fn new_module_with_host(
+    config: ModuleConfig,
+    memory_growth: MemoryGrowth,
+) -> (Module, FunctionId, MemoryId) {
     let mut module = Module::with_config(config);
 
-    let (allocator_function_id, memory_id) =
-        setup_module_memory(&mut module, Some(TOTAL_RESERVED_MEMORY));
-    host_functions::add_pay_for_memory_grow(&mut module);
+    let (pay_for_memory_grow_function_id, _) = host_functions::add_pay_for_memory_grow(&mut module);
+    let (allocator_function_id, memory_id) = setup_module_memory(
+        &mut module,
+        Some(TOTAL_RESERVED_MEMORY),
+        memory_growth,
+        Some(pay_for_memory_grow_function_id),
+    );
 
     // Fill data segment
     setup_data_segment(&mut module, memory_id);
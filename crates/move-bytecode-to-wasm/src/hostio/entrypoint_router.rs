@@ -1,14 +1,14 @@
 use walrus::{
-    FunctionBuilder, FunctionId, Module, ValType,
-    ir::{BinaryOp, LoadKind, MemArg},
+    FunctionBuilder, FunctionId, FunctionKind, Module, ValType,
+    ir::{BinaryOp, LoadKind, MemArg, StoreKind},
 };
 
 use crate::{
-    CompilationContext, abi_types::public_function::PublicFunction,
-    runtime_error_codes::ERROR_NO_FUNCTION_MATCH,
+    CompilationContext, abi_types::public_function::PublicFunction, data::DATA_CALLDATA_OFFSET,
+    runtime::RuntimeFunction, runtime_error_codes::ERROR_NO_FUNCTION_MATCH,
 };
 
-use super::host_functions;
+use super::{host_functions, reentrancy::add_reentrancy_check_instructions};
 
 /// Builds an entrypoint router for the list of public functions provided
 /// and adds it to the module exporting it as `user_entrypoint`
@@ -21,7 +21,8 @@ pub fn build_entrypoint_router(
 ) {
     let (read_args_function, _) = host_functions::read_args(module);
     let (write_return_data_function, _) = host_functions::write_result(module);
-    let (storage_flush_cache_function, _) = host_functions::storage_flush_cache(module);
+    let storage_cache_commit_function =
+        RuntimeFunction::StorageCacheCommit.get(module, Some(compilation_ctx));
 
     let args_len = module.locals.add(ValType::I32);
     let selector_variable = module.locals.add(ValType::I32);
@@ -31,6 +32,11 @@ pub fn build_entrypoint_router(
 
     let mut router_builder = router.func_body();
 
+    // Packages guarded against reentrancy reject the call before reading it
+    if compilation_ctx.options.reentrancy_guard {
+        add_reentrancy_check_instructions(&mut router_builder, module);
+    }
+
     // TODO: handle case where no args data, now we just panic
     router_builder.block(None, |block| {
         let block_id = block.id();
@@ -49,6 +55,30 @@ pub fn build_entrypoint_router(
     router_builder.local_tee(args_pointer);
     router_builder.call(read_args_function);
 
+    // Keep the calldata so it can be read with msg::data and msg::sig
+    router_builder
+        .i32_const(DATA_CALLDATA_OFFSET)
+        .local_get(args_pointer)
+        .store(
+            compilation_ctx.memory_id,
+            StoreKind::I32 { atomic: false },
+            MemArg {
+                align: 0,
+                offset: 0,
+            },
+        );
+    router_builder
+        .i32_const(DATA_CALLDATA_OFFSET)
+        .local_get(args_len)
+        .store(
+            compilation_ctx.memory_id,
+            StoreKind::I32 { atomic: false },
+            MemArg {
+                align: 0,
+                offset: 4,
+            },
+        );
+
     // Load selector from first 4 bytes of args
     router_builder.local_get(args_pointer);
     router_builder.load(
@@ -69,7 +99,7 @@ pub fn build_entrypoint_router(
             args_pointer,
             args_len,
             write_return_data_function,
-            storage_flush_cache_function,
+            storage_cache_commit_function,
             compilation_ctx,
         );
     }
@@ -81,6 +111,99 @@ pub fn build_entrypoint_router(
 
     let router = router.finish(vec![args_len], &mut module.funcs);
     add_entrypoint(module, router);
+
+    build_self_dispatch(module, functions, compilation_ctx);
+}
+
+/// Generates the body of `self_dispatch`, with the same blocks as the router for the list of
+/// public functions provided, but returning the data instead of writing it. If no function
+/// matches the selector, the execution aborts.
+///
+/// If `self_dispatch` was never linked, nothing is done.
+fn build_self_dispatch(
+    module: &mut Module,
+    functions: &[PublicFunction],
+    compilation_ctx: &CompilationContext,
+) {
+    let Some(self_dispatch_fn) = module.funcs.by_name(RuntimeFunction::SelfDispatch.name()) else {
+        return;
+    };
+
+    let args_pointer = module.locals.add(ValType::I32);
+    let args_len = module.locals.add(ValType::I32);
+    let return_data_len_ptr = module.locals.add(ValType::I32);
+    let selector_variable = module.locals.add(ValType::I32);
+
+    // The blocks need the module to be built, so they go in a new function that `self_dispatch`
+    // calls, instead of in the body of `self_dispatch` itself
+    let mut dispatch = FunctionBuilder::new(
+        &mut module.types,
+        &[ValType::I32, ValType::I32, ValType::I32],
+        &[ValType::I32],
+    );
+    let mut dispatch_builder = dispatch
+        .name(format!(
+            "{}_entry_functions",
+            RuntimeFunction::SelfDispatch.name()
+        ))
+        .func_body();
+
+    dispatch_builder.block(None, |block| {
+        let block_id = block.id();
+
+        // If args len is < 4 there is no selector
+        block.local_get(args_len);
+        block.i32_const(4);
+        block.binop(BinaryOp::I32GeS);
+        block.br_if(block_id);
+        block.unreachable();
+    });
+
+    dispatch_builder
+        .local_get(args_pointer)
+        .load(
+            compilation_ctx.memory_id,
+            LoadKind::I32 { atomic: false },
+            MemArg {
+                align: 0,
+                offset: 0,
+            },
+        )
+        .local_set(selector_variable);
+
+    for function in functions {
+        function.build_self_dispatch_block(
+            &mut dispatch_builder,
+            module,
+            selector_variable,
+            args_pointer,
+            args_len,
+            return_data_len_ptr,
+            compilation_ctx,
+        );
+    }
+
+    // No entry function matches the selector
+    dispatch_builder.unreachable();
+
+    let dispatch = dispatch.finish(
+        vec![args_pointer, args_len, return_data_len_ptr],
+        &mut module.funcs,
+    );
+
+    let FunctionKind::Local(function) = &mut module.funcs.get_mut(self_dispatch_fn).kind else {
+        panic!(
+            "{} is not a local function",
+            RuntimeFunction::SelfDispatch.name()
+        );
+    };
+    let args = function.args.clone();
+    let mut builder = function.builder_mut().func_body();
+
+    for arg in args {
+        builder.local_get(arg);
+    }
+    builder.call(dispatch);
 }
 
 /// Add an entrypoint to the module with the interface defined by Stylus
@@ -93,8 +216,9 @@ mod tests {
     use wasmtime::{Caller, Engine, Extern, Linker, Module as WasmModule, Store, TypedFunc};
 
     use crate::{
-        test_compilation_context, test_tools::build_module,
-        translation::intermediate_types::ISignature, utils::display_module,
+        runtime_error_codes::ERROR_REENTRANT_CALL, test_compilation_context,
+        test_tools::build_module, translation::intermediate_types::ISignature,
+        utils::display_module,
     };
 
     use super::*;
@@ -129,6 +253,7 @@ mod tests {
 
     struct ReadArgsData {
         data: Vec<u8>,
+        reentrant: bool,
     }
 
     fn setup_wasmtime_module(
@@ -184,6 +309,22 @@ mod tests {
             .func_wrap("vm_hooks", "storage_flush_cache", |_: i32| {})
             .unwrap();
 
+        linker
+            .func_wrap(
+                "vm_hooks",
+                "msg_reentrant",
+                |caller: Caller<'_, ReadArgsData>| -> i32 { caller.data().reentrant as i32 },
+            )
+            .unwrap();
+
+        linker
+            .func_wrap(
+                "vm_hooks",
+                "storage_cache_bytes32",
+                |_key_ptr: u32, _value_ptr: u32| {},
+            )
+            .unwrap();
+
         linker
             .func_wrap(
                 "vm_hooks",
@@ -248,6 +389,7 @@ mod tests {
 
         let data = ReadArgsData {
             data: noop_selector_data,
+            reentrant: false,
         };
         let data_len = data.data.len() as i32;
 
@@ -258,6 +400,7 @@ mod tests {
 
         let data = ReadArgsData {
             data: noop_2_selector_data,
+            reentrant: false,
         };
         let data_len = data.data.len() as i32;
 
@@ -283,7 +426,10 @@ mod tests {
         display_module(&mut raw_module);
 
         // Invalid selector
-        let data = ReadArgsData { data: vec![] };
+        let data = ReadArgsData {
+            data: vec![],
+            reentrant: false,
+        };
         let data_len = data.data.len() as i32;
 
         let (_, mut store, entrypoint) = setup_wasmtime_module(&mut raw_module, data);
@@ -306,7 +452,10 @@ mod tests {
         display_module(&mut raw_module);
 
         // Invalid selector
-        let data = ReadArgsData { data: vec![0; 4] };
+        let data = ReadArgsData {
+            data: vec![0; 4],
+            reentrant: false,
+        };
         let data_len = data.data.len() as i32;
 
         let (_, mut store, entrypoint) = setup_wasmtime_module(&mut raw_module, data);
@@ -314,4 +463,37 @@ mod tests {
         let result = entrypoint.call(&mut store, data_len).unwrap();
         assert_eq!(result, ERROR_NO_FUNCTION_MATCH);
     }
+
+    #[test]
+    fn test_build_entrypoint_router_non_reentrant() {
+        let (mut raw_module, allocator_func, memory_id) = build_module(None);
+        let compilation_ctx = test_compilation_context!(memory_id, allocator_func);
+        let signature = ISignature {
+            arguments: vec![],
+            returns: vec![],
+        };
+        let noop = add_noop_function(&mut raw_module, &signature, &compilation_ctx)
+            .with_non_reentrant(true);
+        let noop_2 = add_noop_2_function(&mut raw_module, &signature, &compilation_ctx);
+
+        let noop_selector_data = noop.get_selector().to_vec();
+        let noop_2_selector_data = noop_2.get_selector().to_vec();
+
+        build_entrypoint_router(&mut raw_module, &[noop, noop_2], &compilation_ctx);
+        display_module(&mut raw_module);
+
+        for (data, reentrant, expected) in [
+            (noop_selector_data.clone(), false, 0),
+            (noop_selector_data, true, ERROR_REENTRANT_CALL),
+            (noop_2_selector_data, true, 0),
+        ] {
+            let data = ReadArgsData { data, reentrant };
+            let data_len = data.data.len() as i32;
+
+            let (_, mut store, entrypoint) = setup_wasmtime_module(&mut raw_module, data);
+
+            let result = entrypoint.call(&mut store, data_len).unwrap();
+            assert_eq!(result, expected);
+        }
+    }
 }
@@ -1,5 +1,61 @@
 use walrus::{FunctionId, ImportId, Module, ValType};
 
+use crate::translation_options::StylusVersion;
+
+/// Host functions imported by the translated modules, alongside the first Stylus version that
+/// provides them.
+const HOST_FUNCTIONS: &[(&str, StylusVersion)] = &[
+    ("pay_for_memory_grow", StylusVersion::ArbOs31),
+    ("read_args", StylusVersion::ArbOs31),
+    ("write_result", StylusVersion::ArbOs31),
+    ("exit_early", StylusVersion::ArbOs31),
+    ("storage_load_bytes32", StylusVersion::ArbOs31),
+    ("storage_cache_bytes32", StylusVersion::ArbOs31),
+    ("storage_flush_cache", StylusVersion::ArbOs31),
+    ("tx_origin", StylusVersion::ArbOs31),
+    ("emit_log", StylusVersion::ArbOs31),
+    ("msg_sender", StylusVersion::ArbOs31),
+    ("msg_value", StylusVersion::ArbOs31),
+    ("msg_reentrant", StylusVersion::ArbOs31),
+    ("block_number", StylusVersion::ArbOs31),
+    ("math_div", StylusVersion::ArbOs32),
+    ("math_mod", StylusVersion::ArbOs32),
+    ("math_pow", StylusVersion::ArbOs32),
+    ("math_add_mod", StylusVersion::ArbOs32),
+    ("math_mul_mod", StylusVersion::ArbOs32),
+    ("contract_address", StylusVersion::ArbOs31),
+    ("block_basefee", StylusVersion::ArbOs31),
+    ("block_coinbase", StylusVersion::ArbOs31),
+    ("block_gas_limit", StylusVersion::ArbOs31),
+    ("block_timestamp", StylusVersion::ArbOs31),
+    ("evm_gas_left", StylusVersion::ArbOs31),
+    ("evm_ink_left", StylusVersion::ArbOs31),
+    ("chainid", StylusVersion::ArbOs31),
+    ("tx_gas_price", StylusVersion::ArbOs31),
+    ("tx_ink_price", StylusVersion::ArbOs31),
+    ("call_contract", StylusVersion::ArbOs31),
+    ("delegate_call_contract", StylusVersion::ArbOs31),
+    ("static_call_contract", StylusVersion::ArbOs31),
+    ("create1", StylusVersion::ArbOs31),
+    ("create2", StylusVersion::ArbOs31),
+    ("read_return_data", StylusVersion::ArbOs31),
+    ("return_data_size", StylusVersion::ArbOs31),
+    ("account_balance", StylusVersion::ArbOs31),
+    ("account_code", StylusVersion::ArbOs31),
+    ("account_code_size", StylusVersion::ArbOs31),
+    ("account_codehash", StylusVersion::ArbOs31),
+    ("native_keccak256", StylusVersion::ArbOs31),
+];
+
+/// Returns the first Stylus version that provides the host function, or `None` if it is not a
+/// Stylus host function.
+pub fn host_function_version(name: &str) -> Option<StylusVersion> {
+    HOST_FUNCTIONS
+        .iter()
+        .find(|(host_function, _)| *host_function == name)
+        .map(|(_, version)| *version)
+}
+
 pub fn add_pay_for_memory_grow(module: &mut Module) -> (FunctionId, ImportId) {
     get_or_insert_import(module, "pay_for_memory_grow", &[ValType::I32], &[])
 }
@@ -27,6 +83,15 @@ pub fn write_result(module: &mut Module) -> (FunctionId, ImportId) {
     get_or_insert_import(module, "write_result", &[ValType::I32, ValType::I32], &[])
 }
 
+/// Exits program execution early with the given status code. If `0`, the program returns
+/// successfully with any data supplied by `write_result`. Otherwise, the program reverts and
+/// treats `write_result` as revert data.
+///
+/// params: status: u32
+pub fn exit_early(module: &mut Module) -> (FunctionId, ImportId) {
+    get_or_insert_import(module, "exit_early", &[ValType::I32], &[])
+}
+
 /// Reads a 32-byte value from permanent storage. Stylus's storage format is identical to
 /// that of the EVM. This means that, under the hood, this hostio is accessing the 32-byte
 /// value stored in the EVM state trie at offset `key`, which will be `0` when not previously
@@ -119,6 +184,11 @@ pub fn msg_value(module: &mut Module) -> (FunctionId, ImportId) {
     get_or_insert_import(module, "msg_value", &[ValType::I32], &[])
 }
 
+/// Whether the current call is reentrant.
+pub fn msg_reentrant(module: &mut Module) -> (FunctionId, ImportId) {
+    get_or_insert_import(module, "msg_reentrant", &[], &[ValType::I32])
+}
+
 /// Gets a bounded estimate of the L1 block number at which the Sequencer sequenced the
 /// transaction. See [`Block Numbers and Time`] for more information on how this value is
 /// determined.
@@ -128,6 +198,81 @@ pub fn block_number(module: &mut Module) -> (FunctionId, ImportId) {
     get_or_insert_import(module, "block_number", &[], &[ValType::I64])
 }
 
+/// Unsigned 256-bit integer division. Performs a division, overwriting the first argument. Both
+/// values are in big-endian. The result is 0 if the divisor is 0, the same as the EVM's [`DIV`]
+/// opcode.
+///
+/// [`DIV`]: https://www.evm.codes/#04
+///
+/// params: value: *mut u8 (32 bytes), divisor: *const u8 (32 bytes)
+pub fn math_div(module: &mut Module) -> (FunctionId, ImportId) {
+    get_or_insert_import(module, "math_div", &[ValType::I32, ValType::I32], &[])
+}
+
+/// Modulo of unsigned 256-bit integers, overwriting the first argument. Both values are in
+/// big-endian. The result is 0 if the modulus is 0, the same as the EVM's [`MOD`] opcode.
+///
+/// [`MOD`]: https://www.evm.codes/#06
+///
+/// params: value: *mut u8 (32 bytes), modulus: *const u8 (32 bytes)
+pub fn math_mod(module: &mut Module) -> (FunctionId, ImportId) {
+    get_or_insert_import(module, "math_mod", &[ValType::I32, ValType::I32], &[])
+}
+
+/// Exponentiation of unsigned 256-bit integers, wrapping on overflow and overwriting the first
+/// argument. Both values are in big-endian. The semantics are equivalent to that of the EVM's
+/// [`EXP`] opcode.
+///
+/// [`EXP`]: https://www.evm.codes/#0a
+///
+/// params: value: *mut u8 (32 bytes), exponent: *const u8 (32 bytes)
+pub fn math_pow(module: &mut Module) -> (FunctionId, ImportId) {
+    get_or_insert_import(module, "math_pow", &[ValType::I32, ValType::I32], &[])
+}
+
+/// Adds two unsigned 256-bit integers modulo a third, overwriting the first argument. The values
+/// are in big-endian, and the sum is computed without overflowing. The semantics are equivalent
+/// to that of the EVM's [`ADDMOD`] opcode.
+///
+/// [`ADDMOD`]: https://www.evm.codes/#08
+///
+/// params: value: *mut u8 (32 bytes), addend: *const u8 (32 bytes), modulus: *const u8 (32 bytes)
+pub fn math_add_mod(module: &mut Module) -> (FunctionId, ImportId) {
+    get_or_insert_import(
+        module,
+        "math_add_mod",
+        &[ValType::I32, ValType::I32, ValType::I32],
+        &[],
+    )
+}
+
+/// Multiplies two unsigned 256-bit integers modulo a third, overwriting the first argument. The
+/// values are in big-endian, and the product is computed without overflowing. The semantics are
+/// equivalent to that of the EVM's [`MULMOD`] opcode.
+///
+/// [`MULMOD`]: https://www.evm.codes/#09
+///
+/// params: value: *mut u8 (32 bytes), multiplier: *const u8 (32 bytes), modulus: *const u8 (32
+/// bytes)
+pub fn math_mul_mod(module: &mut Module) -> (FunctionId, ImportId) {
+    get_or_insert_import(
+        module,
+        "math_mul_mod",
+        &[ValType::I32, ValType::I32, ValType::I32],
+        &[],
+    )
+}
+
+/// Gets the address of the current program. The semantics are equivalent to that of the EVM's
+/// [`ADDRESS`] opcode.
+///
+/// [`ADDRESS`]: https://www.evm.codes/#30
+///
+/// params: dest: *mut u8 (20 bytes)
+pub fn contract_address(module: &mut Module) -> (FunctionId, ImportId) {
+    get_or_insert_import(module, "contract_address", &[ValType::I32], &[])
+}
+
 /// Gets the basefee of the current block. The semantics are equivalent to that of the EVM's
 /// [`BASEFEE`] opcode.
 ///
@@ -136,6 +281,17 @@ pub fn block_basefee(module: &mut Module) -> (FunctionId, ImportId) {
     get_or_insert_import(module, "block_basefee", &[ValType::I32], &[])
 }
 
+/// Gets the coinbase of the current block, which on Arbitrum chains is the L1 batch poster's
+/// address. This differs from Ethereum where the validator including the transaction determines
+/// the coinbase. The semantics are equivalent to that of the EVM's [`COINBASE`] opcode.
+///
+/// [`COINBASE`]: https://www.evm.codes/#41
+///
+/// params: dest: *mut u8 (20 bytes)
+pub fn block_coinbase(module: &mut Module) -> (FunctionId, ImportId) {
+    get_or_insert_import(module, "block_coinbase", &[ValType::I32], &[])
+}
+
 /// Gets the gas limit of the current block. The semantics are equivalent to that of the EVM's
 /// [`GAS_LIMIT`] opcode. Note that as of the time of this writing, `evm.codes` incorrectly
 /// implies that the opcode returns the gas limit of the current transaction.  When in doubt,
@@ -156,6 +312,24 @@ pub fn block_timestamp(module: &mut Module) -> (FunctionId, ImportId) {
     get_or_insert_import(module, "block_timestamp", &[], &[ValType::I64])
 }
 
+/// Gets the amount of gas left after paying for the cost of this hostio. The semantics are
+/// equivalent to that of the EVM's [`GAS`] opcode.
+///
+/// [`GAS`]: https://www.evm.codes/#5a
+pub fn evm_gas_left(module: &mut Module) -> (FunctionId, ImportId) {
+    get_or_insert_import(module, "evm_gas_left", &[], &[ValType::I64])
+}
+
+/// Gets the amount of ink remaining after paying for the cost of this hostio. The semantics are
+/// equivalent to that of the EVM's [`GAS`] opcode, except the units are in ink. See [`Ink and
+/// Gas`] for more information on Stylus's compute pricing.
+///
+/// [`GAS`]: https://www.evm.codes/#5a
+/// [`Ink and Gas`]: https://docs.arbitrum.io/stylus/concepts/gas-metering
+pub fn evm_ink_left(module: &mut Module) -> (FunctionId, ImportId) {
+    get_or_insert_import(module, "evm_ink_left", &[], &[ValType::I64])
+}
+
 /// Gets the unique chain identifier of the Arbitrum chain. The semantics are equivalent to
 /// that of the EVM's [`CHAIN_ID`] opcode.
 ///
@@ -172,6 +346,245 @@ pub fn tx_gas_price(module: &mut Module) -> (FunctionId, ImportId) {
     get_or_insert_import(module, "tx_gas_price", &[ValType::I32], &[])
 }
 
+/// Gets the price of ink in evm gas basis points. See [`Ink and Gas`] for more information on
+/// Stylus's compute-pricing model.
+///
+/// [`Ink and Gas`]: https://docs.arbitrum.io/stylus/concepts/gas-metering
+pub fn tx_ink_price(module: &mut Module) -> (FunctionId, ImportId) {
+    get_or_insert_import(module, "tx_ink_price", &[], &[ValType::I32])
+}
+
+/// Calls the contract at the given address with the given calldata and value in wei, forwarding
+/// at most `gas` gas. The semantics are equivalent to that of the EVM's [`CALL`] opcode,
+/// including callvalue stipends and the 63/64 gas rule. This means that supplying the `u64::MAX`
+/// gas can be used to send as much as possible.
+///
+/// The length of the return data is written to `return_data_len`, and the data itself can be
+/// read with `read_return_data`. Returns 0 on success and a nonzero value if the call reverted.
+///
+/// [`CALL`]: https://www.evm.codes/#f1
+///
+/// params: contract: *const u8, calldata: *const u8, calldata_len: usize, value: *const u8 (32
+/// bytes, big-endian), gas: u64, return_data_len: *mut usize
+pub fn call_contract(module: &mut Module) -> (FunctionId, ImportId) {
+    get_or_insert_import(
+        module,
+        "call_contract",
+        &[
+            ValType::I32,
+            ValType::I32,
+            ValType::I32,
+            ValType::I32,
+            ValType::I64,
+            ValType::I32,
+        ],
+        &[ValType::I32],
+    )
+}
+
+/// Delegate calls the contract at the given address, with the option to limit the amount of gas
+/// supplied. The semantics are equivalent to that of the EVM's [`DELEGATE_CALL`] opcode,
+/// including the 63/64 gas rule: the code of the contract runs in the context of the current
+/// one, reading and writing its storage and seeing its caller and value.
+///
+/// The length of the return data is written to `return_data_len`, and the data itself can be
+/// read with `read_return_data`. Returns 0 on success and a nonzero value if the call reverted.
+///
+/// [`DELEGATE_CALL`]: https://www.evm.codes/#F4
+///
+/// params: contract: *const u8, calldata: *const u8, calldata_len: usize, gas: u64,
+/// return_data_len: *mut usize
+pub fn delegate_call_contract(module: &mut Module) -> (FunctionId, ImportId) {
+    get_or_insert_import(
+        module,
+        "delegate_call_contract",
+        &[
+            ValType::I32,
+            ValType::I32,
+            ValType::I32,
+            ValType::I64,
+            ValType::I32,
+        ],
+        &[ValType::I32],
+    )
+}
+
+/// Static calls the contract at the given address, with the option to limit the amount of gas
+/// supplied. The semantics are equivalent to that of the EVM's [`STATIC_CALL`] opcode, including
+/// the 63/64 gas rule: the call reverts if the called contract, or any contract it calls, tries
+/// to modify the state.
+///
+/// The length of the return data is written to `return_data_len`, and the data itself can be
+/// read with `read_return_data`. Returns 0 on success and a nonzero value if the call reverted.
+///
+/// [`STATIC_CALL`]: https://www.evm.codes/#FA
+///
+/// params: contract: *const u8, calldata: *const u8, calldata_len: usize, gas: u64,
+/// return_data_len: *mut usize
+pub fn static_call_contract(module: &mut Module) -> (FunctionId, ImportId) {
+    get_or_insert_import(
+        module,
+        "static_call_contract",
+        &[
+            ValType::I32,
+            ValType::I32,
+            ValType::I32,
+            ValType::I64,
+            ValType::I32,
+        ],
+        &[ValType::I32],
+    )
+}
+
+/// Deploys a new contract using the init code provided, which the EVM executes to construct the
+/// code of the newly deployed contract. The init code must be written in EVM bytecode, but the
+/// code it deploys can be that of a Stylus program. The semantics are equivalent to that of the
+/// EVM's [`CREATE`] opcode, which notably includes the exact address returned.
+///
+/// On success, the address of the new contract is written to `contract`. On failure, zero is
+/// written instead, and the length of the revert data to `revert_data_len`. The revert data can
+/// be read with `read_return_data`.
+///
+/// [`CREATE`]: https://www.evm.codes/#f0
+///
+/// params: code: *const u8, code_len: usize, endowment: *const u8 (32 bytes, big-endian),
+/// contract: *mut u8, revert_data_len: *mut usize
+pub fn create1(module: &mut Module) -> (FunctionId, ImportId) {
+    get_or_insert_import(
+        module,
+        "create1",
+        &[
+            ValType::I32,
+            ValType::I32,
+            ValType::I32,
+            ValType::I32,
+            ValType::I32,
+        ],
+        &[],
+    )
+}
+
+/// Deploys a new contract using the init code provided, which the EVM executes to construct the
+/// code of the newly deployed contract. The init code must be written in EVM bytecode, but the
+/// code it deploys can be that of a Stylus program. The semantics are equivalent to that of the
+/// EVM's [`CREATE2`] opcode, which notably includes the exact address returned.
+///
+/// On success, the address of the new contract is written to `contract`. On failure, zero is
+/// written instead, and the length of the revert data to `revert_data_len`. The revert data can
+/// be read with `read_return_data`.
+///
+/// [`CREATE2`]: https://www.evm.codes/#f5
+///
+/// params: code: *const u8, code_len: usize, endowment: *const u8 (32 bytes, big-endian),
+/// salt: *const u8 (32 bytes), contract: *mut u8, revert_data_len: *mut usize
+pub fn create2(module: &mut Module) -> (FunctionId, ImportId) {
+    get_or_insert_import(
+        module,
+        "create2",
+        &[
+            ValType::I32,
+            ValType::I32,
+            ValType::I32,
+            ValType::I32,
+            ValType::I32,
+            ValType::I32,
+        ],
+        &[],
+    )
+}
+
+/// Copies the bytes of the last EVM call or deployment return result. Does not revert if out of
+/// bounds, but rather copies the overlapping portion. The semantics are otherwise equivalent to
+/// that of the EVM's [`RETURN_DATA_COPY`] opcode.
+///
+/// Returns the number of bytes written.
+///
+/// [`RETURN_DATA_COPY`]: https://www.evm.codes/#3e
+///
+/// params: dest: *mut u8, offset: usize, size: usize
+pub fn read_return_data(module: &mut Module) -> (FunctionId, ImportId) {
+    get_or_insert_import(
+        module,
+        "read_return_data",
+        &[ValType::I32, ValType::I32, ValType::I32],
+        &[ValType::I32],
+    )
+}
+
+/// Returns the length of the last EVM call or deployment return result, or `0` if neither have
+/// happened during the program's execution. The semantics are equivalent to that of the EVM's
+/// [`RETURN_DATA_SIZE`] opcode.
+///
+/// [`RETURN_DATA_SIZE`]: https://www.evm.codes/#3d
+pub fn return_data_size(module: &mut Module) -> (FunctionId, ImportId) {
+    get_or_insert_import(module, "return_data_size", &[], &[ValType::I32])
+}
+
+/// Gets the ETH balance in wei of the account at the given address. The semantics are
+/// equivalent to that of the EVM's [`BALANCE`] opcode.
+///
+/// [`BALANCE`]: https://www.evm.codes/#31
+///
+/// params: address: *const u8, dest: *mut u8 (32 bytes, big-endian)
+pub fn account_balance(module: &mut Module) -> (FunctionId, ImportId) {
+    get_or_insert_import(
+        module,
+        "account_balance",
+        &[ValType::I32, ValType::I32],
+        &[],
+    )
+}
+
+/// Gets a subset of the code from the account at the given address. The semantics are identical
+/// to that of the EVM's [`EXT_CODE_COPY`] opcode, aside from one small detail: the write to the
+/// buffer `dest` will stop after the last byte is written. This is unlike the EVM, which right
+/// pads with zeros in this scenario.
+///
+/// Returns the number of bytes written.
+///
+/// [`EXT_CODE_COPY`]: https://www.evm.codes/#3C
+///
+/// params: address: *const u8, offset: usize, size: usize, dest: *mut u8
+pub fn account_code(module: &mut Module) -> (FunctionId, ImportId) {
+    get_or_insert_import(
+        module,
+        "account_code",
+        &[ValType::I32, ValType::I32, ValType::I32, ValType::I32],
+        &[ValType::I32],
+    )
+}
+
+/// Gets the size of the code in bytes at the given address. The semantics are equivalent to
+/// that of the EVM's [`EXT_CODESIZE`].
+///
+/// [`EXT_CODESIZE`]: https://www.evm.codes/#3B
+///
+/// params: address: *const u8
+pub fn account_code_size(module: &mut Module) -> (FunctionId, ImportId) {
+    get_or_insert_import(
+        module,
+        "account_code_size",
+        &[ValType::I32],
+        &[ValType::I32],
+    )
+}
+
+/// Gets the code hash of the account at the given address. The semantics are equivalent to that
+/// of the EVM's [`EXT_CODEHASH`] opcode. Note that the code hash of an account without code will
+/// be the empty hash `keccak("") = c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470`.
+///
+/// [`EXT_CODEHASH`]: https://www.evm.codes/#3F
+///
+/// params: address: *const u8, dest: *mut u8 (32 bytes)
+pub fn account_codehash(module: &mut Module) -> (FunctionId, ImportId) {
+    get_or_insert_import(
+        module,
+        "account_codehash",
+        &[ValType::I32, ValType::I32],
+        &[],
+    )
+}
+
 /// Efficiently computes the [`keccak256`] hash of the given preimage.
 /// The semantics are equivalent to that of the EVM's [`SHA3`] opcode.
 ///
@@ -187,13 +600,60 @@ pub fn native_keccak256(module: &mut Module) -> (FunctionId, ImportId) {
     )
 }
 
+/// Prints the UTF-8 text to the console. It is only available when the program runs in debug
+/// mode, such as in a local node, and deploying a program that imports it fails otherwise.
+///
+/// params: text: *const u8, len: usize
+pub fn log_txt(module: &mut Module) -> (FunctionId, ImportId) {
+    get_or_insert_import_from(
+        module,
+        "console",
+        "log_txt",
+        &[ValType::I32, ValType::I32],
+        &[],
+    )
+}
+
+/// Reports that a basic block of a Move function ran, for the coverage reports. It is provided by
+/// test runners only, so modules importing it can't be deployed.
+///
+/// params: function: u32, the function's id in the coverage map, block: u32, the offset of the
+/// block's first instruction
+pub fn coverage_hit(module: &mut Module) -> (FunctionId, ImportId) {
+    get_or_insert_import_from(
+        module,
+        "coverage",
+        "hit",
+        &[ValType::I32, ValType::I32],
+        &[],
+    )
+}
+
+/// Reports the code a Move function aborted with, to the unit test runners, and traps. It is
+/// provided by test runners only, so modules importing it can't be deployed.
+///
+/// params: code: u64
+pub fn unit_test_abort(module: &mut Module) -> (FunctionId, ImportId) {
+    get_or_insert_import_from(module, "unit_test", "abort", &[ValType::I64], &[])
+}
+
 fn get_or_insert_import(
     module: &mut walrus::Module,
     name: &str,
     params: &[walrus::ValType],
     results: &[walrus::ValType],
 ) -> (walrus::FunctionId, walrus::ImportId) {
-    if let Ok(function_id) = module.imports.get_func("vm_hooks", name) {
+    get_or_insert_import_from(module, "vm_hooks", name, params, results)
+}
+
+fn get_or_insert_import_from(
+    module: &mut walrus::Module,
+    import_module: &str,
+    name: &str,
+    params: &[walrus::ValType],
+    results: &[walrus::ValType],
+) -> (walrus::FunctionId, walrus::ImportId) {
+    if let Ok(function_id) = module.imports.get_func(import_module, name) {
         for import in module.imports.iter() {
             if let walrus::ImportKind::Function(func_id) = import.kind {
                 if func_id == function_id {
@@ -204,5 +664,5 @@ fn get_or_insert_import(
     }
 
     let ty = module.types.add(params, results);
-    module.add_import_func("vm_hooks", name, ty)
+    module.add_import_func(import_module, name, ty)
 }
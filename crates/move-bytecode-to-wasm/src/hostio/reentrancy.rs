@@ -0,0 +1,76 @@
+//! Reentrancy guard for entry functions.
+//!
+//! Stylus lets a program know if the current call reentered it through the `msg_reentrant` hook.
+//! Entry functions annotated in the Move source reject reentrant calls before their arguments
+//! are unpacked:
+//!
+//! ```move
+//! #[ext(non_reentrant)]
+//! public fun withdraw(vault: &mut Vault, amount: u256, ctx: &TxContext) { ... }
+//! ```
+//!
+//! The check is done by the entrypoint router, which returns `ERROR_REENTRANT_CALL` without
+//! executing the function. Packages translated with the `reentrancy_guard` option do the check
+//! once, before dispatching, for all their entry functions.
+//!
+//! Functions that need finer control can query the hook themselves with `TxContext::reentrant`.
+//!
+//! Attributes are not part of the compiled bytecode, so they are read from the module's source.
+use std::collections::HashSet;
+
+use walrus::{InstrSeqBuilder, Module};
+
+use crate::{runtime_error_codes::ERROR_REENTRANT_CALL, utils::parse_ext_flag};
+
+use super::host_functions;
+
+const NON_REENTRANT_ATTRIBUTE: &str = "non_reentrant";
+
+/// Searches the Move source for `non_reentrant` attributes and returns the names of the
+/// annotated functions.
+pub fn parse_non_reentrant_functions(source: &str) -> HashSet<String> {
+    parse_ext_flag(source, NON_REENTRANT_ATTRIBUTE)
+}
+
+/// Adds the instructions to return `ERROR_REENTRANT_CALL` from the entrypoint if the current
+/// call is reentrant.
+pub fn add_reentrancy_check_instructions(builder: &mut InstrSeqBuilder, module: &mut Module) {
+    let (msg_reentrant_function, _) = host_functions::msg_reentrant(module);
+
+    builder.call(msg_reentrant_function);
+    builder.if_else(
+        None,
+        |then| {
+            then.i32_const(ERROR_REENTRANT_CALL).return_();
+        },
+        |_| {},
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_non_reentrant_functions() {
+        let source = r#"
+module test::vault;
+
+// non_reentrant
+#[ext(non_reentrant)]
+entry fun withdraw(amount: u256) {}
+
+public fun non_reentrant_deposit(amount: u256) {}
+
+#[test_only, ext(non_reentrant)]
+public fun transfer(amount: u256) {}
+"#;
+
+        let functions = parse_non_reentrant_functions(source);
+
+        assert_eq!(
+            HashSet::from(["withdraw".to_owned(), "transfer".to_owned()]),
+            functions
+        );
+    }
+}
@@ -0,0 +1,340 @@
+//! View entry functions.
+//!
+//! Entry functions annotated in the Move source as views can only read the state:
+//!
+//! ```move
+//! #[ext(view)]
+//! public fun balance(vault: &Vault): u64 { vault.balance }
+//! ```
+//!
+//! The compilation fails if a view function, or any function it calls, writes the storage or
+//! emits a log. That is the case when it receives storage objects by mutable reference or by
+//! value, which are saved back once it returns, when it publishes, removes or mutably borrows a
+//! resource of the global storage, or when it calls a native function that modifies
+//! the state, such as the transfer functions, the mutable accesses to mappings, tables, bags and
+//! storage vectors, the creation of object ids, the event emission, or the calls to other
+//! contracts, their deployment and the ETH transfers. Other contracts can still be queried with
+//! static calls, that revert if they try to modify the state.
+//!
+//! Only the functions with the attribute are checked. The others are `nonpayable` in the ABI even
+//! if they only read the state, as it is not inferred from their code, so the functions meant to
+//! be called as views must be annotated to be declared and enforced as such.
+//!
+//! Attributes are not part of the compiled bytecode, so they are read from the module's source.
+use std::{collections::HashSet, fmt};
+
+use move_binary_format::file_format::Bytecode;
+
+use crate::{
+    CompilationContext, GlobalFunctionTable,
+    compilation_context::reserved_modules::STYLUS_FRAMEWORK_ADDRESS,
+    generics::replace_type_parameters,
+    native_functions::NativeFunction,
+    translation::{
+        functions::MappedFunction, intermediate_types::IntermediateType, table::FunctionId,
+    },
+    utils::parse_ext_flag,
+};
+
+const VIEW_ATTRIBUTE: &str = "view";
+
+/// Searches the Move source for `view` attributes and returns the names of the annotated
+/// functions.
+pub fn parse_view_functions(source: &str) -> HashSet<String> {
+    parse_ext_flag(source, VIEW_ATTRIBUTE)
+}
+
+/// Checks that a view function does not modify the state.
+///
+/// # Panics
+/// If the function receives a storage object by mutable reference or by value, or, directly or
+/// through other functions, writes the global storage or calls a native function that writes the
+/// storage or emits a log. The message points to the offending argument, bytecode or call.
+pub fn check_view_function(
+    function_information: &MappedFunction,
+    compilation_ctx: &CompilationContext,
+    function_definitions: &GlobalFunctionTable,
+) {
+    let function_id = &function_information.function_id;
+
+    for (index, argument) in function_information.signature.arguments.iter().enumerate() {
+        // Immutable references are not saved back
+        let inner = match argument {
+            IntermediateType::IRef(_) => continue,
+            IntermediateType::IMutRef(inner) => inner.as_ref(),
+            _ => argument,
+        };
+
+        if !matches!(
+            inner,
+            IntermediateType::IStruct { .. } | IntermediateType::IGenericStructInstance { .. }
+        ) {
+            continue;
+        }
+
+        let is_mutable_storage_object = compilation_ctx
+            .get_struct_by_intermediate_type(inner)
+            .is_ok_and(|struct_| struct_.saved_in_storage);
+
+        assert!(
+            !is_mutable_storage_object,
+            "view function {function_id} modifies the storage: argument {index} is a storage object taken by mutable reference or by value"
+        );
+    }
+
+    if let Some((caller, code_offset, change)) = find_state_modifying_call(
+        function_id,
+        compilation_ctx,
+        function_definitions,
+        &mut HashSet::new(),
+    ) {
+        panic!(
+            "view function {function_id} modifies the state: {caller} {change} at bytecode offset {code_offset}"
+        );
+    }
+}
+
+/// Instruction that modifies the state, found by `find_state_modifying_call`.
+#[derive(Debug, PartialEq)]
+enum StateChange {
+    /// Call to a native function that modifies the state
+    Call(FunctionId),
+    /// Global storage bytecode that writes the storage
+    GlobalStorage(&'static str),
+}
+
+impl fmt::Display for StateChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StateChange::Call(native) => write!(f, "calls {native}"),
+            StateChange::GlobalStorage(operation) => write!(f, "uses {operation}"),
+        }
+    }
+}
+
+/// Looks for an instruction that modifies the state in the function and the ones it calls: a
+/// call to a native function that modifies it, or a global storage bytecode that writes the
+/// storage. Returns the function with the instruction, its offset in the bytecode and the change.
+fn find_state_modifying_call(
+    function_id: &FunctionId,
+    compilation_ctx: &CompilationContext,
+    function_definitions: &GlobalFunctionTable,
+    visited: &mut HashSet<FunctionId>,
+) -> Option<(FunctionId, usize, StateChange)> {
+    if !visited.insert(function_id.clone()) {
+        return None;
+    }
+
+    let code = function_definitions
+        .get(&function_id.get_generic_fn_id())?
+        .code
+        .as_ref()?;
+
+    let module_data = if function_id.module_id == compilation_ctx.root_module_data.id {
+        compilation_ctx.root_module_data
+    } else {
+        compilation_ctx.deps_data.get(&function_id.module_id)?
+    };
+
+    for (code_offset, instruction) in code.code.iter().enumerate() {
+        if let Some(operation) = global_storage_write(instruction) {
+            return Some((
+                function_id.clone(),
+                code_offset,
+                StateChange::GlobalStorage(operation),
+            ));
+        }
+
+        let callee = match instruction {
+            Bytecode::Call(function_handle_index) => {
+                module_data.functions.calls[function_handle_index.into_index()].clone()
+            }
+            Bytecode::CallGeneric(function_instantiation_index) => {
+                let mut callee = module_data.functions.generic_calls
+                    [function_instantiation_index.into_index()]
+                .clone();
+
+                // Type parameters of the instantiation refer to the caller's ones
+                if let (Some(types), Some(caller_types)) = (
+                    callee.type_instantiations.as_mut(),
+                    function_id.type_instantiations.as_ref(),
+                ) {
+                    for itype in types.iter_mut() {
+                        *itype = replace_type_parameters(itype, caller_types);
+                    }
+                }
+
+                callee
+            }
+            _ => continue,
+        };
+
+        let is_native = function_definitions
+            .get(&callee.get_generic_fn_id())
+            .is_some_and(|f| f.code.is_none());
+        if is_native && modifies_state(&callee) {
+            return Some((function_id.clone(), code_offset, StateChange::Call(callee)));
+        }
+
+        if let Some(call) =
+            find_state_modifying_call(&callee, compilation_ctx, function_definitions, visited)
+        {
+            return Some(call);
+        }
+    }
+
+    None
+}
+
+/// Returns the Move operation of the global storage bytecodes that write the storage: publishing,
+/// removing and mutably borrowing a resource. `exists` and `borrow_global` only read it.
+fn global_storage_write(instruction: &Bytecode) -> Option<&'static str> {
+    match instruction {
+        Bytecode::MoveToDeprecated(_) | Bytecode::MoveToGenericDeprecated(_) => Some("move_to"),
+        Bytecode::MoveFromDeprecated(_) | Bytecode::MoveFromGenericDeprecated(_) => {
+            Some("move_from")
+        }
+        Bytecode::MutBorrowGlobalDeprecated(_) | Bytecode::MutBorrowGlobalGenericDeprecated(_) => {
+            Some("borrow_global_mut")
+        }
+        _ => None,
+    }
+}
+
+/// Returns true if the function is a native function of the framework that writes the storage,
+/// emits a log, or calls or deploys another contract, or one of this contract's entry functions,
+/// that can do both.
+fn modifies_state(function_id: &FunctionId) -> bool {
+    if function_id.module_id.address != STYLUS_FRAMEWORK_ADDRESS {
+        return false;
+    }
+
+    match function_id.identifier.as_str() {
+        NativeFunction::NATIVE_TRANSFER_OBJECT
+        | NativeFunction::NATIVE_SHARE_OBJECT
+        | NativeFunction::NATIVE_FREEZE_OBJECT
+        | NativeFunction::NATIVE_DELETE_OBJECT
+        | NativeFunction::NATIVE_FRESH_ID
+        | NativeFunction::NATIVE_EMIT
+        | NativeFunction::NATIVE_CALL_CONTRACT
+        | NativeFunction::NATIVE_DELEGATE_CALL_CONTRACT
+        | NativeFunction::NATIVE_CREATE1
+        | NativeFunction::NATIVE_CREATE2
+        | NativeFunction::NATIVE_TRANSFER_ETH
+        | NativeFunction::NATIVE_SELF_CALL
+        | NativeFunction::NATIVE_MAPPING_SET
+        | NativeFunction::NATIVE_MAPPING_REMOVE
+        | NativeFunction::NATIVE_TABLE_ADD
+        | NativeFunction::NATIVE_TABLE_BORROW_MUT
+        | NativeFunction::NATIVE_TABLE_REMOVE
+        | NativeFunction::NATIVE_BAG_ADD
+        | NativeFunction::NATIVE_BAG_BORROW_MUT
+        | NativeFunction::NATIVE_BAG_REMOVE
+        | NativeFunction::NATIVE_BAG_SET_TYPE_TAG
+        | NativeFunction::NATIVE_STORAGE_VEC_PUSH_BACK
+        | NativeFunction::NATIVE_STORAGE_VEC_BORROW_MUT
+        | NativeFunction::NATIVE_STORAGE_VEC_POP_BACK => true,
+        #[cfg(debug_assertions)]
+        NativeFunction::SAVE_IN_SLOT => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use move_binary_format::file_format::{
+        CodeUnit, FunctionDefinition, FunctionHandleIndex, SignatureIndex, StructDefinitionIndex,
+        Visibility,
+    };
+
+    use crate::{
+        compilation_context::{Interner, ModuleId},
+        test_compilation_context,
+        test_tools::build_module,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_parse_view_functions() {
+        let source = r#"
+module test::vault;
+
+// view
+#[ext(view)]
+public fun balance(vault: &Vault): u64 { vault.balance }
+
+public fun view_balance(vault: &Vault): u64 { vault.balance }
+
+#[ext(non_reentrant, view)]
+public fun owner(vault: &Vault): address { vault.owner }
+
+#[ext(preview)]
+public fun preview(vault: &Vault): u64 { vault.balance }
+"#;
+
+        let functions = parse_view_functions(source);
+
+        assert_eq!(
+            HashSet::from(["balance".to_owned(), "owner".to_owned()]),
+            functions
+        );
+    }
+
+    #[test]
+    fn test_global_storage_writes() {
+        let (_, allocator, memory_id) = build_module(None);
+        let compilation_ctx = test_compilation_context!(memory_id, allocator);
+        let function_id = FunctionId {
+            identifier: Interner::default().intern("publish"),
+            module_id: ModuleId::default(),
+            type_instantiations: None,
+        };
+
+        let resource = StructDefinitionIndex::new(0);
+        for (instruction, operation) in [
+            (Bytecode::MoveToDeprecated(resource), Some("move_to")),
+            (Bytecode::MoveFromDeprecated(resource), Some("move_from")),
+            (
+                Bytecode::MutBorrowGlobalDeprecated(resource),
+                Some("borrow_global_mut"),
+            ),
+            (Bytecode::ExistsDeprecated(resource), None),
+            (Bytecode::ImmBorrowGlobalDeprecated(resource), None),
+        ] {
+            let definition = FunctionDefinition {
+                function: FunctionHandleIndex::new(0),
+                visibility: Visibility::Public,
+                is_entry: false,
+                acquires_global_resources: vec![resource],
+                code: Some(CodeUnit {
+                    locals: SignatureIndex::new(0),
+                    code: vec![
+                        Bytecode::LdU64(1),
+                        Bytecode::Pop,
+                        instruction,
+                        Bytecode::Ret,
+                    ],
+                    jump_tables: vec![],
+                }),
+            };
+            let function_definitions =
+                GlobalFunctionTable::from([(function_id.clone(), &definition)]);
+
+            // The view is rejected at the offset of the write, and the reads are allowed
+            assert_eq!(
+                operation.map(|operation| (
+                    function_id.clone(),
+                    2,
+                    StateChange::GlobalStorage(operation)
+                )),
+                find_state_modifying_call(
+                    &function_id,
+                    &compilation_ctx,
+                    &function_definitions,
+                    &mut HashSet::new(),
+                )
+            );
+        }
+    }
+}
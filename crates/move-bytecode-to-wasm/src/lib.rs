@@ -1,47 +1,84 @@
+pub use abi_types::contract_abi::ContractAbi;
 use abi_types::public_function::PublicFunction;
+pub use activation::ActivationInfo;
+use anyhow::{Context, bail};
+use build_cache::{BuildCache, ModuleArtifacts};
+pub use code_size::{
+    CodeSize, FunctionKind, FunctionSize, MAX_COMPRESSED_WASM_SIZE, SizeBudget, SizeReport,
+};
 pub(crate) use compilation_context::{CompilationContext, UserDefinedType};
-use compilation_context::{ModuleData, ModuleId};
+use compilation_context::{Interner, ModuleData, ModuleId};
 use constructor::inject_constructor;
+pub use coverage_map::CoverageMap;
+pub use deployments::{DEPLOYMENTS_FILE_NAME, Deployment, Deployments};
+pub use diagnostics::{Diagnostic, MessageFormat, Severity};
 use move_binary_format::file_format::FunctionDefinition;
 use move_package::{
     compilation::compiled_package::{CompiledPackage, CompiledUnitWithSource},
     source_package::parsed_manifest::PackageName,
 };
+pub use output_options::{
+    Artifact, DEFAULT_CACHE_DIRECTORY, DEFAULT_OUTPUT_DIRECTORY, MODULE_NAME_PLACEHOLDER,
+    OutputOptions,
+};
+pub use package_config::{BuildSection, PACKAGE_CONFIG_FILE_NAME, PackageConfig};
+use source_map::DebugInfo;
+pub use source_map::SourceMap;
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     hash::{DefaultHasher, Hash, Hasher},
     path::Path,
 };
+pub use storage::{gas_report::StorageGasReport, layout::StorageLayout};
+pub use translation_cache::TranslationCache;
+pub use translation_options::{
+    ContractSelection, MemoryGrowth, Profile, StylusVersion, TranslationOptions,
+};
+pub use unit_tests::{ExpectedFailure, UnitTest};
+pub use wasm_opt::{OptimizationLevel, optimize_module};
+
 use translation::{
+    flow::FlowCache,
     intermediate_types::IntermediateType,
     table::{FunctionId, FunctionTable},
     translate_function,
 };
 
-use walrus::{Module, RefType};
+use walrus::{Module, ModuleConfig, RefType, ValType};
 use wasm_validation::validate_stylus_wasm;
 
 pub(crate) mod abi_types;
+mod activation;
+mod build_cache;
+mod code_size;
 mod compilation_context;
 mod constructor;
+mod coverage_map;
 mod data;
+mod deployments;
+mod diagnostics;
 mod generics;
 mod hostio;
 mod memory;
 mod native_functions;
+mod output_options;
+mod package_config;
 mod runtime;
 mod runtime_error_codes;
+mod source_map;
 mod storage;
 mod translation;
+mod translation_cache;
+mod translation_options;
+mod typescript_bindings;
+mod unit_tests;
 mod utils;
 mod vm_handled_types;
 mod wasm_builder_extensions;
 mod wasm_helpers;
+mod wasm_opt;
 mod wasm_validation;
 
-#[cfg(feature = "inject-host-debug-fns")]
-use walrus::ValType;
-
 #[cfg(test)]
 mod test_tools;
 
@@ -58,6 +95,83 @@ pub fn translate_package(
     package: CompiledPackage,
     module_name: Option<String>,
 ) -> HashMap<String, Module> {
+    translate_package_with_options(package, module_name, &TranslationOptions::default())
+}
+
+pub fn translate_package_with_options(
+    package: CompiledPackage,
+    module_name: Option<String>,
+    options: &TranslationOptions,
+) -> HashMap<String, Module> {
+    translate_package_with_artifacts(package, module_name, options)
+        .into_iter()
+        .map(|(module_name, translated_module)| (module_name, translated_module.module))
+        .collect()
+}
+
+/// A translated root module, alongside the artifacts generated for it.
+pub struct TranslatedModule {
+    pub module: Module,
+    /// Description of the storage used by the module, emitted as `<module>.storage.json`
+    pub storage_layout: StorageLayout,
+    /// Worst-case storage accesses of every entry function, emitted as `<module>.gas.json`
+    pub storage_gas_report: StorageGasReport,
+    /// Lines of the source the blocks reported by the coverage instrumentation span, emitted as
+    /// `<module>.coverage.json`. Only built with the `coverage` option.
+    pub coverage_map: Option<CoverageMap>,
+    /// Map from the offsets of the instructions to the source, emitted as `<module>.wasm.map`
+    /// once the module is emitted. Only built with the `debug_info` option.
+    pub source_map: Option<SourceMap>,
+    /// `#[test]` functions dispatched by the router. Only built with the `unit_tests` option.
+    pub unit_tests: Vec<UnitTest>,
+    /// Solidity JSON ABI of the entry functions, emitted as `<module>.abi.json`
+    pub abi: ContractAbi,
+}
+
+pub fn translate_package_with_artifacts(
+    package: CompiledPackage,
+    module_name: Option<String>,
+    options: &TranslationOptions,
+) -> HashMap<String, TranslatedModule> {
+    translate_package_incremental(
+        package,
+        module_name,
+        options,
+        &mut TranslationCache::default(),
+    )
+}
+
+/// Translates the root modules whose sources changed since they were translated with the cache,
+/// reusing the module data of the dependencies it holds, and saves them in it. The modules that
+/// didn't change are not returned.
+pub fn translate_package_incremental(
+    package: CompiledPackage,
+    module_name: Option<String>,
+    options: &TranslationOptions,
+    cache: &mut TranslationCache,
+) -> HashMap<String, TranslatedModule> {
+    translate_root_modules(
+        package,
+        module_name,
+        options,
+        cache,
+        &HashSet::new(),
+        MessageFormat::Human,
+    )
+}
+
+/// Translates the package as `translate_package_incremental` does, except the `skipped` root
+/// modules, whose module data is still built for the modules that depend on them. The progress is
+/// reported in `message_format`.
+fn translate_root_modules(
+    package: CompiledPackage,
+    module_name: Option<String>,
+    options: &TranslationOptions,
+    cache: &mut TranslationCache,
+    skipped: &HashSet<String>,
+    message_format: MessageFormat,
+) -> HashMap<String, TranslatedModule> {
+    let library_modules = library_modules(&package.root_compiled_units);
     let root_compiled_units: Vec<CompiledUnitWithSource> = if let Some(module_name) = module_name {
         package
             .root_compiled_units
@@ -76,32 +190,44 @@ pub fn translate_package(
     let mut modules = HashMap::new();
 
     // Contains the module data for all the root package and its dependencies
-    let mut modules_data: HashMap<ModuleId, ModuleData> = HashMap::new();
+    let mut modules_data: HashMap<ModuleId, ModuleData> =
+        std::mem::take(&mut cache.dependencies_data);
+    // The names of the module data of the dependencies are symbols of the same interner
+    let symbols = std::mem::take(&mut cache.symbols);
 
     // Contains all a reference for all functions definitions in case we need to process them and
     // statically link them
     let mut function_definitions: GlobalFunctionTable = HashMap::new();
 
-    // TODO: a lot of clones, we must create a symbol pool
-    for root_compiled_module in &root_compiled_units {
-        let module_name = root_compiled_module.unit.name.to_string();
-        println!("compiling module {module_name}...");
-        let root_compiled_module = &root_compiled_module.unit.module;
+    // The definitions of the cached dependencies are borrowed from this package
+    for (_, dependency_unit) in &package.deps_compiled_units {
+        let dependency_module = &dependency_unit.unit.module;
+        let module_id = ModuleId {
+            address: dependency_module.address().into_bytes().into(),
+            module_name: symbols.intern(dependency_unit.unit.name.as_str()),
+        };
+        if modules_data.contains_key(&module_id) {
+            ModuleData::register_function_definitions(
+                &module_id,
+                dependency_module,
+                &mut function_definitions,
+                &symbols,
+            );
+        }
+    }
+
+    // The functions shared by the root modules are relooped once, and their WASM emitted in every
+    // module that calls them
+    let flows = FlowCache::default();
+    for root_compiled_unit in &root_compiled_units {
+        let module_name = root_compiled_unit.unit.name.to_string();
+        let root_compiled_module = &root_compiled_unit.unit.module;
 
         let root_module_id = ModuleId {
             address: root_compiled_module.address().into_bytes().into(),
-            module_name: module_name.clone(),
+            module_name: symbols.intern(&module_name),
         };
 
-        let (mut module, allocator_func, memory_id) = hostio::new_module_with_host();
-
-        #[cfg(feature = "inject-host-debug-fns")]
-        inject_debug_fns(&mut module);
-
-        // Function table
-        let function_table_id = module.tables.add_local(false, 0, None, RefType::Funcref);
-        let mut function_table = FunctionTable::new(function_table_id);
-
         // Process the dependency tree
         process_dependency_tree(
             &mut modules_data,
@@ -109,26 +235,98 @@ pub fn translate_package(
             &root_compiled_units,
             &root_compiled_module.immediate_dependencies(),
             &mut function_definitions,
+            &symbols,
+            message_format,
         );
 
         let root_module_data = ModuleData::build_module_data(
             root_module_id.clone(),
-            root_compiled_module,
+            root_compiled_unit,
             &package.deps_compiled_units,
             &root_compiled_units,
             &mut function_definitions,
+            &symbols,
         );
 
-        let compilation_ctx =
-            CompilationContext::new(&root_module_data, &modules_data, memory_id, allocator_func);
+        // The module data of the modules not translated is still needed by the modules that
+        // depend on them
+        if !options
+            .contracts
+            .includes(&module_name, library_modules.contains(&module_name))
+        {
+            Diagnostic::note("progress", format!("skipping module {module_name}"))
+                .report(message_format);
+            modules_data.insert(root_module_id, root_module_data);
+            continue;
+        }
+
+        if skipped.contains(&module_name) {
+            modules_data.insert(root_module_id, root_module_data);
+            continue;
+        }
+
+        let source_hash = translation_cache::source_hash(root_compiled_unit, &root_compiled_units);
+        if cache.is_unchanged(&module_name, source_hash) {
+            Diagnostic::note("progress", format!("module {module_name} unchanged"))
+                .report(message_format);
+            modules_data.insert(root_module_id, root_module_data);
+            continue;
+        }
+        Diagnostic::note("progress", format!("compiling module {module_name}..."))
+            .report(message_format);
+        diagnostics::set_translating(Some(root_compiled_unit));
+
+        let mut config = ModuleConfig::new();
+        config.preserve_code_transform(options.debug_info);
+        let (mut module, allocator_func, memory_id) =
+            hostio::new_module_with_host(config, options.memory_growth);
+
+        if options.host_debug_functions || cfg!(feature = "inject-host-debug-fns") {
+            inject_debug_fns(&mut module);
+        }
+
+        let debug_info = options
+            .debug_info
+            .then(|| DebugInfo::new(root_compiled_unit));
+
+        // Function table
+        let function_table_id = module.tables.add_local(false, 0, None, RefType::Funcref);
+        let mut function_table = FunctionTable::new(function_table_id);
+
+        let compilation_ctx = CompilationContext::new(
+            &root_module_data,
+            &modules_data,
+            memory_id,
+            allocator_func,
+            options,
+            debug_info.as_ref(),
+            Some(&flows),
+        );
+
+        let unit_test_attributes = if options.unit_tests {
+            std::fs::read_to_string(&root_compiled_unit.source_path)
+                .map(|source| unit_tests::parse_unit_tests(&source))
+                .unwrap_or_default()
+        } else {
+            HashMap::new()
+        };
 
         let mut public_functions = Vec::new();
+        let mut unit_tests = Vec::new();
         for function_information in root_module_data
             .functions
             .information
             .iter()
             .filter(|fi| fi.function_id.module_id == root_module_id && !fi.is_generic)
         {
+            if function_information.is_view {
+                hostio::view::check_view_function(
+                    function_information,
+                    &compilation_ctx,
+                    &function_definitions,
+                );
+            }
+
             translate_and_link_functions(
                 &function_information.function_id,
                 &mut function_table,
@@ -144,14 +342,43 @@ pub fn translate_package(
                 .unwrap();
 
             if function_information.is_entry {
-                public_functions.push(PublicFunction::new(
+                public_functions.push(
+                    PublicFunction::new(
+                        wasm_function_id,
+                        &function_information.function_id.identifier,
+                        &function_information.signature,
+                        &compilation_ctx,
+                    )
+                    .with_lazy_storage_fields(function_information.lazy_storage_fields.clone())
+                    // The router already checks every call if the package is guarded
+                    .with_non_reentrant(
+                        function_information.non_reentrant && !options.reentrancy_guard,
+                    ),
+                );
+            }
+
+            // Unit tests don't take arguments, so they are dispatched as entry functions without
+            // arguments, unless they already are entry functions
+            if let Some(expected_failure) =
+                unit_test_attributes.get(function_information.function_id.identifier.as_str())
+            {
+                let public_function = PublicFunction::new(
                     wasm_function_id,
                     &function_information.function_id.identifier,
                     &function_information.signature,
                     &compilation_ctx,
-                ));
+                );
+                unit_tests.push(UnitTest {
+                    name: function_information.function_id.identifier.to_string(),
+                    selector: *public_function.get_selector(),
+                    expected_failure: *expected_failure,
+                });
+                if !function_information.is_entry {
+                    public_functions.push(public_function);
+                }
             }
         }
+        unit_tests.sort_by(|a, b| a.name.cmp(&b.name));
 
         // Inject constructor function.
         inject_constructor(
@@ -163,35 +390,469 @@ pub fn translate_package(
 
         hostio::build_entrypoint_router(&mut module, &public_functions, &compilation_ctx);
 
+        // Once every function is translated, we know the types of all the values that can be
+        // borrowed from the storage
+        runtime::finalize_storage_write_back(&mut module, &compilation_ctx);
+        storage::versioning::finalize_storage_migrations(
+            &mut module,
+            &compilation_ctx,
+            &function_table,
+        );
+
         function_table.ensure_all_functions_added().unwrap();
-        validate_stylus_wasm(&mut module).unwrap();
+        validate_stylus_wasm(&mut module, options.stylus_version).unwrap();
+        // The debug information is read with the names
+        if options.strip_names && !options.debug_info {
+            strip_names(&mut module);
+        }
+
+        let storage_layout = StorageLayout::new(&root_module_data, &compilation_ctx);
+        let storage_gas_report =
+            StorageGasReport::new(&root_module_data, &compilation_ctx, &function_definitions);
+        let coverage_map = options
+            .coverage
+            .then(|| CoverageMap::new(root_compiled_unit, &root_module_data));
+        let abi = ContractAbi::new(&root_module_data, &compilation_ctx);
+        let source_map = debug_info.map(|debug_info| debug_info.finish(&mut module, &module_name));
 
-        modules.insert(module_name, module);
+        modules.insert(
+            module_name.clone(),
+            TranslatedModule {
+                module,
+                storage_layout,
+                storage_gas_report,
+                coverage_map,
+                source_map,
+                unit_tests,
+                abi,
+            },
+        );
         modules_data.insert(root_module_id.clone(), root_module_data);
+        cache.record(module_name, source_hash);
+        diagnostics::set_translating(None);
     }
 
+    // Root modules are built again every time, since their sources change
+    for root_compiled_unit in &root_compiled_units {
+        modules_data.remove(&ModuleId {
+            address: root_compiled_unit.unit.module.address().into_bytes().into(),
+            module_name: symbols.intern(root_compiled_unit.unit.name.as_str()),
+        });
+    }
+    cache.dependencies_data = modules_data;
+    cache.symbols = symbols;
+    cache.relooped_functions = flows.relooped_functions();
+
     modules
 }
 
-pub fn translate_package_cli(package: CompiledPackage, rerooted_path: &Path) {
-    let build_directory = rerooted_path.join("build/wasm");
+/// Functions listed when a module is too big to deploy
+const LARGEST_FUNCTIONS_REPORTED: usize = 5;
+
+/// Constants listed when a module is too big to deploy
+const LARGEST_CONSTANTS_REPORTED: usize = 3;
+
+/// Translates the package and writes the artifacts of every root module selected by the output
+/// options, returning the WASM of every module as it is written. Fails if a module, compressed, is
+/// too big for Stylus to deploy, or does not fit the size budget, after writing it.
+pub fn translate_package_cli(
+    package: CompiledPackage,
+    rerooted_path: &Path,
+    options: &TranslationOptions,
+    output: &OutputOptions,
+) -> anyhow::Result<HashMap<String, Vec<u8>>> {
+    translate_package_cli_incremental(
+        package,
+        rerooted_path,
+        options,
+        output,
+        &mut TranslationCache::default(),
+    )
+}
+
+/// Same as `translate_package_cli`, but only translates and writes the modules that changed since
+/// they were written with the cache, leaving the artifacts of the rest as they are.
+pub fn translate_package_cli_incremental(
+    package: CompiledPackage,
+    rerooted_path: &Path,
+    options: &TranslationOptions,
+    output: &OutputOptions,
+    cache: &mut TranslationCache,
+) -> anyhow::Result<HashMap<String, Vec<u8>>> {
+    let translated_modules = match &options.contracts {
+        ContractSelection::Modules(module_names) => module_names.len(),
+        _ => package.root_compiled_units.len(),
+    };
+    if translated_modules > 1 && !output.file_name.contains(MODULE_NAME_PLACEHOLDER) {
+        bail!(
+            "the package has more than one module, so the artifact name {} must contain {MODULE_NAME_PLACEHOLDER}",
+            output.file_name
+        );
+    }
+
+    if let ContractSelection::Modules(module_names) = &options.contracts {
+        for module_name in module_names {
+            if !package
+                .root_compiled_units
+                .iter()
+                .any(|unit| unit.unit.name.as_str() == module_name)
+            {
+                bail!("module {module_name} not found in the package");
+            }
+        }
+    }
+
+    let build_directory = rerooted_path.join(&output.directory);
     // Create the build directory if it doesn't exist
-    std::fs::create_dir_all(&build_directory).unwrap();
+    std::fs::create_dir_all(&build_directory)
+        .with_context(|| format!("Failed to create {}", build_directory.display()))?;
 
-    let mut modules = translate_package(package, None);
-    for (module_name, module) in modules.iter_mut() {
-        module
-            .emit_wasm_file(build_directory.join(format!("{}.wasm", module_name)))
-            .unwrap();
+    let module_locations: HashMap<String, _> = package
+        .root_compiled_units
+        .iter()
+        .map(|unit| {
+            (
+                unit.unit.name.to_string(),
+                diagnostics::module_location(unit),
+            )
+        })
+        .collect();
+    let module_constants: HashMap<String, _> = package
+        .root_compiled_units
+        .iter()
+        .map(|unit| {
+            (
+                unit.unit.name.to_string(),
+                code_size::largest_constants(unit),
+            )
+        })
+        .collect();
 
-        // Convert to WAT format
-        let wat = wasmprinter::print_bytes(module.emit_wasm()).expect("Failed to generate WAT");
-        std::fs::write(
-            build_directory.join(format!("{}.wat", module_name)),
-            wat.as_bytes(),
-        )
-        .expect("Failed to write WAT file");
+    // The modules found in the build cache are not translated
+    let build_cache = output
+        .cache_directory
+        .as_ref()
+        .map(|directory| BuildCache::new(rerooted_path.join(directory)));
+    let mut cache_keys = HashMap::new();
+    let mut module_artifacts = Vec::new();
+    if let Some(build_cache) = &build_cache {
+        let library_modules = library_modules(&package.root_compiled_units);
+        for root_compiled_unit in &package.root_compiled_units {
+            let module_name = root_compiled_unit.unit.name.to_string();
+            if !options
+                .contracts
+                .includes(&module_name, library_modules.contains(&module_name))
+            {
+                continue;
+            }
+
+            // Left as they are written
+            let source_hash =
+                translation_cache::source_hash(root_compiled_unit, &package.root_compiled_units);
+            if cache.is_unchanged(&module_name, source_hash) {
+                continue;
+            }
+
+            let Some(key) = BuildCache::key(root_compiled_unit, &package, options, output.wasm_opt)
+            else {
+                continue;
+            };
+
+            match build_cache.load(&key) {
+                Some(artifacts) => {
+                    Diagnostic::note(
+                        "build-cache",
+                        format!("module {module_name} found in the build cache"),
+                    )
+                    .report(output.message_format);
+                    cache.record(module_name.clone(), source_hash);
+                    module_artifacts.push((module_name, artifacts));
+                }
+                None => {
+                    cache_keys.insert(module_name, key);
+                }
+            }
+        }
+    }
+    let cached_modules: HashSet<String> = module_artifacts
+        .iter()
+        .map(|(module_name, _)| module_name.clone())
+        .collect();
+
+    let mut modules = translate_root_modules(
+        package,
+        None,
+        options,
+        cache,
+        &cached_modules,
+        output.message_format,
+    );
+    for (
+        module_name,
+        TranslatedModule {
+            module,
+            storage_layout,
+            storage_gas_report,
+            coverage_map,
+            source_map,
+            abi,
+            ..
+        },
+    ) in modules.iter_mut()
+    {
+        let source_map_path = output.artifact_path(module_name, Artifact::SourceMap);
+        if source_map.is_some() {
+            let url = source_map_path.file_name().unwrap().to_string_lossy();
+            SourceMap::set_url(module, url.into_owned());
+        }
+
+        let mut wasm = module.emit_wasm();
+        let mut source_map = source_map.as_ref().map(SourceMap::to_json);
+        if let Some(level) = output.wasm_opt {
+            // wasm-opt optimizes the module in place, so it is written even if it is not emitted,
+            // and removed with the rest of the artifacts not emitted. So is the source map, which
+            // it updates to the optimized module.
+            let path = rerooted_path.join(output.artifact_path(module_name, Artifact::Wasm));
+            std::fs::write(&path, &wasm)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+            let source_map_path = rerooted_path.join(&source_map_path);
+            if let Some(source_map) = &source_map {
+                std::fs::write(&source_map_path, source_map)
+                    .with_context(|| format!("Failed to write {}", source_map_path.display()))?;
+            }
+
+            wasm = wasm_opt::optimize(
+                &path,
+                level,
+                options.debug || options.debug_info,
+                source_map.is_some().then_some(source_map_path.as_path()),
+            )?;
+            if source_map.is_some() {
+                source_map =
+                    Some(std::fs::read_to_string(&source_map_path).with_context(|| {
+                        format!("Failed to read the optimized {}", source_map_path.display())
+                    })?);
+            }
+        }
+
+        let artifacts = ModuleArtifacts {
+            wasm,
+            abi: abi.to_json(),
+            storage_layout: storage_layout.to_json(),
+            storage_gas_report: storage_gas_report.to_json(),
+            coverage_map: coverage_map.as_ref().map(CoverageMap::to_json),
+            source_map,
+        };
+        if let (Some(build_cache), Some(key)) = (&build_cache, cache_keys.get(module_name)) {
+            // The build does not need the cache, it is only slower without it
+            if let Err(e) = build_cache.store(key, &artifacts) {
+                Diagnostic::warning(
+                    "build-cache",
+                    format!("failed to cache {module_name}: {e:#}"),
+                )
+                .report(output.message_format);
+            }
+        }
+        module_artifacts.push((module_name.clone(), artifacts));
+    }
+
+    let mut oversized_modules = Vec::new();
+    let mut wasm_modules = HashMap::new();
+    for (
+        module_name,
+        ModuleArtifacts {
+            wasm,
+            abi,
+            storage_layout,
+            storage_gas_report,
+            coverage_map,
+            source_map,
+        },
+    ) in module_artifacts
+    {
+        let path = |artifact| rerooted_path.join(output.artifact_path(&module_name, artifact));
+        let write = |artifact, contents: &[u8]| {
+            let path = path(artifact);
+            std::fs::write(&path, contents)
+                .with_context(|| format!("Failed to write {}", path.display()))
+        };
+
+        if output.emits(Artifact::Wasm) {
+            write(Artifact::Wasm, &wasm)?;
+        }
+
+        if output.emits(Artifact::Wat) {
+            let wat = wasmprinter::print_bytes(&wasm).context("Failed to generate WAT")?;
+            write(Artifact::Wat, wat.as_bytes())?;
+        }
+
+        if output.emits(Artifact::Abi) {
+            write(Artifact::Abi, abi.as_bytes())?;
+        }
+
+        if output.emits(Artifact::TypeScript) {
+            let bindings = typescript_bindings::typescript_bindings(&module_name, &abi);
+            write(Artifact::TypeScript, bindings.as_bytes())?;
+        }
+
+        if output.emits(Artifact::StorageLayout) {
+            write(Artifact::StorageLayout, storage_layout.as_bytes())?;
+        }
+
+        if output.emits(Artifact::GasReport) {
+            write(Artifact::GasReport, storage_gas_report.as_bytes())?;
+        }
+
+        if let Some(coverage_map) = coverage_map
+            .as_ref()
+            .filter(|_| output.emits(Artifact::CoverageMap))
+        {
+            write(Artifact::CoverageMap, coverage_map.as_bytes())?;
+        }
+
+        if let Some(source_map) = source_map
+            .as_ref()
+            .filter(|_| output.emits(Artifact::SourceMap))
+        {
+            write(Artifact::SourceMap, source_map.as_bytes())?;
+        }
+
+        // Left by previous builds, they would not describe the module written
+        for artifact in Artifact::ALL {
+            let written = output.emits(artifact)
+                && match artifact {
+                    Artifact::CoverageMap => coverage_map.is_some(),
+                    Artifact::SourceMap => source_map.is_some(),
+                    _ => true,
+                };
+            if !written {
+                remove_artifact(&path(artifact))?;
+            }
+        }
+
+        let code_size = CodeSize::measure(&wasm).context("Failed to parse WASM")?;
+        Diagnostic::note("code-size", format!("{module_name}: {code_size}"))
+            .report(output.message_format);
+        let activation = ActivationInfo::new(&wasm).context("Failed to parse WASM")?;
+        // JSON builds read the code hash from the activation info
+        if output.message_format == MessageFormat::Human {
+            println!("{module_name}: code hash {}", activation.code_hash);
+        }
+        if output.emits(Artifact::Activation) {
+            write(Artifact::Activation, activation.to_json().as_bytes())?;
+        }
+        if output.emits(Artifact::SizeReport) {
+            write(
+                Artifact::SizeReport,
+                SizeReport::new(&code_size).to_json().as_bytes(),
+            )?;
+        }
+        let violations = code_size.budget_violations(&output.size_budget);
+        if !violations.is_empty() {
+            let listed = |items: &[(String, usize)], count| {
+                items
+                    .iter()
+                    .take(count)
+                    .map(|(item, size)| format!("\n  {item}: {size} bytes"))
+                    .collect::<String>()
+            };
+            let constants = &module_constants[&module_name];
+            let mut message = format!(
+                "{module_name} is {}. Its largest functions are:{}",
+                violations.join(" and "),
+                listed(&code_size.functions, LARGEST_FUNCTIONS_REPORTED)
+            );
+            if constants.iter().any(|(_, size)| *size > 0) {
+                message.push_str(&format!(
+                    "\nIts largest constants are:{}",
+                    listed(constants, LARGEST_CONSTANTS_REPORTED)
+                ));
+            }
+            let hints = code_size::trimming_hints(
+                &code_size,
+                constants,
+                output.wasm_opt.is_some(),
+                options.debug,
+            );
+            if !hints.is_empty() {
+                message.push_str("\nTo trim it:");
+                for hint in hints {
+                    message.push_str(&format!("\n  - {hint}"));
+                }
+            }
+
+            let diagnostic =
+                Diagnostic::error("code-size", message).at(module_locations[&module_name].clone());
+            // The instrumented modules are not deployed
+            if output.allow_oversized || options.coverage || options.unit_tests {
+                Diagnostic {
+                    severity: Severity::Warning,
+                    ..diagnostic
+                }
+                .report(output.message_format);
+            } else {
+                oversized_modules.push(diagnostic);
+            }
+        }
+
+        wasm_modules.insert(module_name.clone(), wasm);
     }
+
+    if !oversized_modules.is_empty() {
+        if output.message_format == MessageFormat::Json {
+            for diagnostic in &oversized_modules {
+                diagnostic.report(MessageFormat::Json);
+            }
+            bail!(
+                "{} modules don't fit the size budget or the limit of Stylus",
+                oversized_modules.len()
+            );
+        }
+
+        let messages: Vec<String> = oversized_modules
+            .into_iter()
+            .map(|diagnostic| diagnostic.message)
+            .collect();
+        bail!(messages.join("\n"));
+    }
+
+    Ok(wasm_modules)
+}
+
+/// Removes the artifact, if it exists.
+fn remove_artifact(path: &Path) -> anyhow::Result<()> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(e).with_context(|| format!("Failed to remove {}", path.display()))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Names of the root modules that are libraries: the modules other root modules depend on that
+/// have neither `entry` functions nor an `init` function, so they are only called by the modules
+/// they are linked into.
+fn library_modules(root_compiled_units: &[CompiledUnitWithSource]) -> HashSet<String> {
+    let root_dependencies: HashSet<_> = root_compiled_units
+        .iter()
+        .flat_map(|unit| unit.unit.module.immediate_dependencies())
+        .collect();
+
+    root_compiled_units
+        .iter()
+        .filter(|unit| {
+            let module = &unit.unit.module;
+            root_dependencies.contains(&module.self_id())
+                && !module.function_defs().iter().any(|function| {
+                    function.is_entry
+                        || module
+                            .identifier_at(module.function_handle_at(function.function).name)
+                            .as_str()
+                            == "init"
+                })
+        })
+        .map(|unit| unit.unit.name.to_string())
+        .collect()
 }
 
 /// This functions process the dependency tree for the root module.
@@ -203,20 +864,25 @@ pub fn process_dependency_tree<'move_package>(
     root_compiled_units: &'move_package [CompiledUnitWithSource],
     dependencies: &[move_core_types::language_storage::ModuleId],
     function_definitions: &mut GlobalFunctionTable<'move_package>,
+    symbols: &Interner,
+    message_format: MessageFormat,
 ) {
     for dependency in dependencies {
         let module_id = ModuleId {
-            module_name: dependency.name().to_string(),
+            module_name: symbols.intern(dependency.name().as_str()),
             address: dependency.address().into_bytes().into(),
         };
-        print!("\tprocessing dependency {module_id}...",);
         // If the HashMap contains the key, we already processed that dependency
         if dependencies_data.contains_key(&module_id) {
-            println!(" [cached]");
+            Diagnostic::note(
+                "progress",
+                format!("processing dependency {module_id}... [cached]"),
+            )
+            .report(message_format);
             continue;
-        } else {
-            println!();
         }
+        Diagnostic::note("progress", format!("processing dependency {module_id}..."))
+            .report(message_format);
 
         // Find the dependency inside Move's compiled package
         let dependency_module = deps_compiled_units
@@ -228,9 +894,7 @@ pub fn process_dependency_tree<'move_package>(
             .map(|(_, module)| module)
             .unwrap_or_else(|| panic!("could not find dependency {}", dependency.name()));
 
-        let dependency_module = &dependency_module.unit.module;
-
-        let immediate_dependencies = &dependency_module.immediate_dependencies();
+        let immediate_dependencies = &dependency_module.unit.module.immediate_dependencies();
         // If the the dependency has dependency, we process them first
         if !immediate_dependencies.is_empty() {
             process_dependency_tree(
@@ -239,6 +903,8 @@ pub fn process_dependency_tree<'move_package>(
                 root_compiled_units,
                 immediate_dependencies,
                 function_definitions,
+                symbols,
+                message_format,
             );
         }
 
@@ -248,6 +914,7 @@ pub fn process_dependency_tree<'move_package>(
             deps_compiled_units,
             root_compiled_units,
             function_definitions,
+            symbols,
         );
 
         let processed_dependency = dependencies_data.insert(module_id, dependency_module_data);
@@ -341,7 +1008,12 @@ fn translate_and_link_functions(
             .add_to_wasm_table(module, function_id, wasm_function_id)
             .expect("there was an error adding the module's functions to the function table");
 
-        // Recursively translate and link functions called by this function
+        // Recursively translate and link functions called by this function. They are added to the
+        // module in the order they are reached, so deterministic builds sort them first
+        let mut functions_to_link: Vec<FunctionId> = functions_to_link.into_iter().collect();
+        if compilation_ctx.options.deterministic {
+            functions_to_link.sort_by_cached_key(|function_id| format!("{function_id:?}"));
+        }
         functions_to_link.iter().for_each(|function_id| {
             translate_and_link_functions(
                 function_id,
@@ -366,27 +1038,33 @@ pub fn get_generic_function_name(base_name: &str, generics: &[&IntermediateType]
     format!("{base_name}_{hash}")
 }
 
-#[cfg(feature = "inject-host-debug-fns")]
 fn inject_debug_fns(module: &mut walrus::Module) {
-    if cfg!(feature = "inject-host-debug-fns") {
-        let func_ty = module.types.add(&[ValType::I32], &[]);
-        module.add_import_func("", "print_i32", func_ty);
+    let func_ty = module.types.add(&[ValType::I32], &[]);
+    module.add_import_func("", "print_i32", func_ty);
 
-        let func_ty = module.types.add(&[ValType::I32], &[]);
-        module.add_import_func("", "print_memory_from", func_ty);
+    let func_ty = module.types.add(&[ValType::I32], &[]);
+    module.add_import_func("", "print_memory_from", func_ty);
 
-        let func_ty = module.types.add(&[ValType::I64], &[]);
-        module.add_import_func("", "print_i64", func_ty);
+    let func_ty = module.types.add(&[ValType::I64], &[]);
+    module.add_import_func("", "print_i64", func_ty);
 
-        let func_ty = module.types.add(&[ValType::I32], &[]);
-        module.add_import_func("", "print_u128", func_ty);
+    let func_ty = module.types.add(&[ValType::I32], &[]);
+    module.add_import_func("", "print_u128", func_ty);
 
-        let func_ty = module.types.add(&[], &[]);
-        module.add_import_func("", "print_separator", func_ty);
+    let func_ty = module.types.add(&[], &[]);
+    module.add_import_func("", "print_separator", func_ty);
+
+    let func_ty = module.types.add(&[ValType::I32], &[]);
+    module.add_import_func("", "print_address", func_ty);
+}
 
-        let func_ty = module.types.add(&[ValType::I32], &[]);
-        module.add_import_func("", "print_address", func_ty);
+/// Removes the names of the functions, the only ones the translation sets, and the producers
+/// section, which the module is emitted with otherwise.
+fn strip_names(module: &mut walrus::Module) {
+    for function in module.funcs.iter_mut() {
+        function.name = None;
     }
+    module.producers.clear();
 }
 
 #[cfg(feature = "inject-host-debug-fns")]
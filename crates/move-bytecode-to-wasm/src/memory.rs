@@ -3,8 +3,14 @@ use walrus::{
     ir::{BinaryOp, Value},
 };
 
+use crate::translation_options::MemoryGrowth;
+
 const MEMORY_PAGE_SIZE: i32 = 65536;
 
+/// Name of the allocator function, so tools such as the testing sandbox can find it in the name
+/// section
+const ALLOCATOR_FUNCTION_NAME: &str = "allocator";
+
 /// Setup the module memory
 /// This function adds the following components to the module:
 /// - memory export
@@ -17,11 +23,15 @@ const MEMORY_PAGE_SIZE: i32 = 65536;
 /// Notes:
 ///     - Alignment is assumed to be 1 byte (no alignment)
 ///     - Alignment is not implemented in the current function
-///     - Memory is allocated in pages of 64KiB
+///     - Memory is allocated in pages of 64KiB, as many at once as `memory_growth` says
 ///     - Memory starts at offset 0
+///     - If `pay_for_memory_grow` is given, it is called with the number of pages before growing
+///       the memory
 pub fn setup_module_memory(
     module: &mut Module,
     initial_offset: Option<i32>,
+    memory_growth: MemoryGrowth,
+    pay_for_memory_grow: Option<FunctionId>,
 ) -> (FunctionId, MemoryId) {
     let memory_id = module.memories.add_local(false, false, 1, None, None);
     module.exports.add("memory", memory_id);
@@ -42,10 +52,12 @@ pub fn setup_module_memory(
 
     let mut func_builder =
         FunctionBuilder::new(&mut module.types, &[ValType::I32], &[ValType::I32]);
+    func_builder.name(ALLOCATOR_FUNCTION_NAME.to_owned());
 
     let requested_size = module.locals.add(ValType::I32);
     let memory_delta = module.locals.add(ValType::I32);
     let grow_pages = module.locals.add(ValType::I32);
+    let doubling_pages = module.locals.add(ValType::I32);
     let memory_pointer = module.locals.add(ValType::I32);
     let mut body = func_builder.func_body();
 
@@ -70,9 +82,34 @@ pub fn setup_module_memory(
             // Round up
             block.i32_const(1);
             block.binop(BinaryOp::I32Add);
-            block.local_tee(grow_pages);
+            block.local_set(grow_pages);
+
+            if let MemoryGrowth::Exponential { max_pages } = memory_growth {
+                // Doubling the memory grows it by its current size, up to max_pages
+                block.memory_size(memory_id);
+                block.i32_const(max_pages as i32);
+                block.memory_size(memory_id);
+                block.i32_const(max_pages as i32);
+                block.binop(BinaryOp::I32LtU);
+                block.select(None);
+                block.local_tee(doubling_pages);
+
+                // Unless the allocation needs more pages
+                block.local_get(grow_pages);
+                block.local_get(doubling_pages);
+                block.local_get(grow_pages);
+                block.binop(BinaryOp::I32GtU);
+                block.select(None);
+                block.local_set(grow_pages);
+            }
+
+            if let Some(pay_for_memory_grow) = pay_for_memory_grow {
+                block.local_get(grow_pages);
+                block.call(pay_for_memory_grow);
+            }
 
             // Grow the memory
+            block.local_get(grow_pages);
             block.memory_grow(memory_id);
             // Panic if memory growth failed
             block.i32_const(0);
@@ -183,4 +220,45 @@ mod tests {
             MEMORY_PAGE_SIZE - 2
         );
     }
+
+    #[test]
+    fn test_memory_allocator_exponential_growth() {
+        let mut raw_module = Module::with_config(walrus::ModuleConfig::new());
+        setup_module_memory(
+            &mut raw_module,
+            None,
+            MemoryGrowth::Exponential { max_pages: 2 },
+            None,
+        );
+
+        let engine = Engine::default();
+        let module = WasmModule::from_binary(&engine, &raw_module.emit_wasm()).unwrap();
+        let mut store = Store::new(&engine, ());
+        let instance = Instance::new(&mut store, &module, &[]).unwrap();
+
+        let allocator = instance
+            .get_typed_func::<i32, i32>(&mut store, "allocator")
+            .unwrap();
+
+        let memory_size = instance.get_memory(&mut store, "memory").unwrap();
+
+        // The first page is filled without growing the memory
+        allocator.call(&mut store, MEMORY_PAGE_SIZE).unwrap();
+        assert_eq!(memory_size.size(&mut store), 1);
+
+        // The memory doubles
+        allocator.call(&mut store, 1).unwrap();
+        assert_eq!(memory_size.size(&mut store), 2);
+
+        allocator.call(&mut store, MEMORY_PAGE_SIZE).unwrap();
+        assert_eq!(memory_size.size(&mut store), 4);
+
+        // Up to max_pages at once
+        allocator.call(&mut store, 3 * MEMORY_PAGE_SIZE).unwrap();
+        assert_eq!(memory_size.size(&mut store), 6);
+
+        // Unless the allocation needs more pages
+        allocator.call(&mut store, 5 * MEMORY_PAGE_SIZE).unwrap();
+        assert_eq!(memory_size.size(&mut store), 11);
+    }
 }
@@ -2,12 +2,25 @@
 //!
 //! Native functions in Move are functions directly implemented inside the Move VM. To emulate that
 //! mechanism, we direcly implement them in WASM and limk them into the file.
+mod bytes;
+mod contract_calls;
+mod debug;
 mod event;
+mod evm;
+mod hash;
+mod mapping;
+mod math;
+mod msg;
 mod object;
+mod precompiles;
+mod storage_vec;
+mod table;
 mod transaction;
 mod transfer;
 mod types;
 
+pub use debug::add_abort_log_instructions;
+
 use walrus::{FunctionId, Module};
 
 use crate::{
@@ -20,13 +33,22 @@ pub struct NativeFunction;
 impl NativeFunction {
     const NATIVE_SENDER: &str = "native_sender";
     const NATIVE_MSG_VALUE: &str = "native_msg_value";
+    const NATIVE_MSG_REENTRANT: &str = "native_msg_reentrant";
     const NATIVE_BLOCK_NUMBER: &str = "native_block_number";
     const NATIVE_BLOCK_BASEFEE: &str = "native_block_basefee";
+    const NATIVE_BLOCK_COINBASE: &str = "native_block_coinbase";
     const NATIVE_BLOCK_GAS_LIMIT: &str = "native_block_gas_limit";
     const NATIVE_BLOCK_TIMESTAMP: &str = "native_block_timestamp";
     const NATIVE_CHAIN_ID: &str = "native_chain_id";
     const NATIVE_GAS_PRICE: &str = "native_gas_price";
-    const NATIVE_FRESH_ID: &str = "fresh_id";
+    const NATIVE_INK_PRICE: &str = "native_ink_price";
+    const NATIVE_GAS_LEFT: &str = "native_gas_left";
+    const NATIVE_INK_LEFT: &str = "native_ink_left";
+    pub const NATIVE_FRESH_ID: &str = "fresh_id";
+
+    // Msg functions
+    pub const NATIVE_MSG_DATA: &str = "native_msg_data";
+    pub const NATIVE_MSG_SIG: &str = "native_msg_sig";
 
     // Transfer functions
     pub const NATIVE_TRANSFER_OBJECT: &str = "transfer";
@@ -37,22 +59,90 @@ impl NativeFunction {
     pub const NATIVE_IS_ONE_TIME_WITNESS: &str = "is_one_time_witness";
 
     // Storage
+    pub const NATIVE_STORAGE_FLUSH: &str = "native_flush";
     #[cfg(debug_assertions)]
     pub const SAVE_IN_SLOT: &str = "save_in_slot";
     #[cfg(debug_assertions)]
     pub const READ_SLOT: &str = "read_slot";
 
+    // Contract calls functions
+    pub const NATIVE_CALL_CONTRACT: &str = "native_call_contract";
+    pub const NATIVE_DELEGATE_CALL_CONTRACT: &str = "native_delegate_call_contract";
+    pub const NATIVE_STATIC_CALL_CONTRACT: &str = "native_static_call_contract";
+    pub const NATIVE_RETURN_DATA: &str = "native_return_data";
+    pub const NATIVE_CREATE1: &str = "native_create1";
+    pub const NATIVE_CREATE2: &str = "native_create2";
+    pub const NATIVE_TRANSFER_ETH: &str = "native_transfer_eth";
+    pub const NATIVE_SELF_CALL: &str = "native_self_call";
+
+    // EVM functions
+    pub const NATIVE_ACCOUNT_BALANCE: &str = "native_account_balance";
+    pub const NATIVE_ACCOUNT_CODE: &str = "native_account_code";
+    pub const NATIVE_ACCOUNT_CODE_SIZE: &str = "native_account_code_size";
+    pub const NATIVE_ACCOUNT_CODEHASH: &str = "native_account_codehash";
+    pub const NATIVE_CONTRACT_ADDRESS: &str = "native_contract_address";
+    pub const NATIVE_BLOCK_HASH: &str = "native_block_hash";
+
+    // Math functions
+    pub const NATIVE_ADD_MOD: &str = "native_add_mod";
+    pub const NATIVE_MUL_MOD: &str = "native_mul_mod";
+    pub const NATIVE_WRAPPING_POW: &str = "native_wrapping_pow";
+
+    // Hash functions
+    pub const NATIVE_KECCAK256: &str = "native_keccak256";
+
+    // Precompiles functions
+    pub const NATIVE_ECRECOVER: &str = "native_ecrecover";
+    pub const NATIVE_SHA256: &str = "native_sha256";
+    pub const NATIVE_RIPEMD160: &str = "native_ripemd160";
+
     // Event functions
-    const NATIVE_EMIT: &str = "emit";
+    pub const NATIVE_EMIT: &str = "emit";
+
+    // Debug functions
+    pub const NATIVE_DEBUG_LOG: &str = "native_log";
 
     // Object functions
     pub const NATIVE_DELETE_OBJECT: &str = "delete";
 
+    // Mapping functions
+    pub const NATIVE_MAPPING_CONTAINS: &str = "mapping_contains";
+    pub const NATIVE_MAPPING_GET: &str = "mapping_get";
+    pub const NATIVE_MAPPING_SET: &str = "mapping_set";
+    pub const NATIVE_MAPPING_REMOVE: &str = "mapping_remove";
+    pub const NATIVE_MAPPING_BORROW_MAPPING: &str = "mapping_borrow_mapping";
+
+    // Table functions
+    pub const NATIVE_TABLE_ADD: &str = "table_add";
+    pub const NATIVE_TABLE_BORROW: &str = "table_borrow";
+    pub const NATIVE_TABLE_BORROW_MUT: &str = "table_borrow_mut";
+    pub const NATIVE_TABLE_REMOVE: &str = "table_remove";
+
+    // Bag functions
+    pub const NATIVE_BAG_ADD: &str = "bag_add";
+    pub const NATIVE_BAG_BORROW: &str = "bag_borrow";
+    pub const NATIVE_BAG_BORROW_MUT: &str = "bag_borrow_mut";
+    pub const NATIVE_BAG_REMOVE: &str = "bag_remove";
+    pub const NATIVE_BAG_TYPE_TAG: &str = "bag_type_tag";
+    pub const NATIVE_BAG_GET_TYPE_TAG: &str = "bag_get_type_tag";
+    pub const NATIVE_BAG_SET_TYPE_TAG: &str = "bag_set_type_tag";
+
+    // StorageVec functions
+    pub const NATIVE_STORAGE_VEC_LENGTH: &str = "storage_vec_length";
+    pub const NATIVE_STORAGE_VEC_PUSH_BACK: &str = "storage_vec_push_back";
+    pub const NATIVE_STORAGE_VEC_BORROW: &str = "storage_vec_borrow";
+    pub const NATIVE_STORAGE_VEC_BORROW_MUT: &str = "storage_vec_borrow_mut";
+    pub const NATIVE_STORAGE_VEC_POP_BACK: &str = "storage_vec_pop_back";
+
     // Host functions
     const HOST_BLOCK_NUMBER: &str = "block_number";
     const HOST_BLOCK_GAS_LIMIT: &str = "block_gas_limit";
     const HOST_BLOCK_TIMESTAMP: &str = "block_timestamp";
     const HOST_CHAIN_ID: &str = "chainid";
+    const HOST_MSG_REENTRANT: &str = "msg_reentrant";
+    const HOST_TX_INK_PRICE: &str = "tx_ink_price";
+    const HOST_EVM_GAS_LEFT: &str = "evm_gas_left";
+    const HOST_EVM_INK_LEFT: &str = "evm_ink_left";
 
     /// Links the function into the module and returns its id. If the function is already present
     /// it just returns the id.
@@ -82,6 +172,22 @@ impl NativeFunction {
                         let (function_id, _) = hostio::host_functions::chain_id(module);
                         return function_id;
                     }
+                    Self::HOST_MSG_REENTRANT => {
+                        let (function_id, _) = hostio::host_functions::msg_reentrant(module);
+                        return function_id;
+                    }
+                    Self::HOST_TX_INK_PRICE => {
+                        let (function_id, _) = hostio::host_functions::tx_ink_price(module);
+                        return function_id;
+                    }
+                    Self::HOST_EVM_GAS_LEFT => {
+                        let (function_id, _) = hostio::host_functions::evm_gas_left(module);
+                        return function_id;
+                    }
+                    Self::HOST_EVM_INK_LEFT => {
+                        let (function_id, _) = hostio::host_functions::evm_ink_left(module);
+                        return function_id;
+                    }
                     _ => {
                         panic!("host function {host_fn_name} not supported yet");
                     }
@@ -100,10 +206,71 @@ impl NativeFunction {
                 Self::NATIVE_BLOCK_BASEFEE => {
                     transaction::add_native_block_basefee_fn(module, compilaton_ctx)
                 }
+                Self::NATIVE_BLOCK_COINBASE => {
+                    transaction::add_native_block_coinbase_fn(module, compilaton_ctx)
+                }
                 Self::NATIVE_GAS_PRICE => {
                     transaction::add_native_tx_gas_price_fn(module, compilaton_ctx)
                 }
                 Self::NATIVE_FRESH_ID => object::add_native_fresh_id_fn(module, compilaton_ctx),
+                // The native function implementation is the same as the runtime one, so we reuse
+                // the runtime function
+                Self::NATIVE_STORAGE_FLUSH => {
+                    RuntimeFunction::StorageCacheFlush.get(module, Some(compilaton_ctx))
+                }
+                Self::NATIVE_MSG_DATA => msg::add_native_msg_data_fn(module, compilaton_ctx),
+                Self::NATIVE_MSG_SIG => msg::add_native_msg_sig_fn(module, compilaton_ctx),
+                Self::NATIVE_CALL_CONTRACT => {
+                    contract_calls::add_native_call_contract_fn(module, compilaton_ctx)
+                }
+                Self::NATIVE_DELEGATE_CALL_CONTRACT => {
+                    contract_calls::add_native_delegate_call_contract_fn(module, compilaton_ctx)
+                }
+                Self::NATIVE_STATIC_CALL_CONTRACT => {
+                    contract_calls::add_native_static_call_contract_fn(module, compilaton_ctx)
+                }
+                Self::NATIVE_RETURN_DATA => {
+                    contract_calls::add_native_return_data_fn(module, compilaton_ctx)
+                }
+                Self::NATIVE_ACCOUNT_BALANCE => {
+                    evm::add_native_account_balance_fn(module, compilaton_ctx)
+                }
+                Self::NATIVE_ACCOUNT_CODE => {
+                    evm::add_native_account_code_fn(module, compilaton_ctx)
+                }
+                Self::NATIVE_ACCOUNT_CODE_SIZE => evm::add_native_account_code_size_fn(module),
+                Self::NATIVE_ACCOUNT_CODEHASH => {
+                    evm::add_native_account_codehash_fn(module, compilaton_ctx)
+                }
+                Self::NATIVE_CONTRACT_ADDRESS => {
+                    evm::add_native_contract_address_fn(module, compilaton_ctx)
+                }
+                Self::NATIVE_BLOCK_HASH => evm::add_native_block_hash_fn(module, compilaton_ctx),
+                Self::NATIVE_ADD_MOD => math::add_native_add_mod_fn(module, compilaton_ctx),
+                Self::NATIVE_MUL_MOD => math::add_native_mul_mod_fn(module, compilaton_ctx),
+                Self::NATIVE_WRAPPING_POW => {
+                    math::add_native_wrapping_pow_fn(module, compilaton_ctx)
+                }
+                Self::NATIVE_KECCAK256 => hash::add_native_keccak256_fn(module, compilaton_ctx),
+                Self::NATIVE_ECRECOVER => {
+                    precompiles::add_native_ecrecover_fn(module, compilaton_ctx)
+                }
+                Self::NATIVE_SHA256 => precompiles::add_native_sha256_fn(module, compilaton_ctx),
+                Self::NATIVE_RIPEMD160 => {
+                    precompiles::add_native_ripemd160_fn(module, compilaton_ctx)
+                }
+                Self::NATIVE_TRANSFER_ETH => {
+                    contract_calls::add_native_transfer_eth_fn(module, compilaton_ctx)
+                }
+                Self::NATIVE_CREATE1 => {
+                    contract_calls::add_native_create1_fn(module, compilaton_ctx)
+                }
+                Self::NATIVE_CREATE2 => {
+                    contract_calls::add_native_create2_fn(module, compilaton_ctx)
+                }
+                Self::NATIVE_SELF_CALL => {
+                    contract_calls::add_native_self_call_fn(module, compilaton_ctx)
+                }
                 _ => panic!("native function {name} not supported yet"),
             }
         }
@@ -177,6 +344,16 @@ impl NativeFunction {
 
                 event::add_emit_log_fn(module, compilation_ctx, &generics[0])
             }
+            Self::NATIVE_DEBUG_LOG => {
+                assert_eq!(
+                    1,
+                    generics.len(),
+                    "there was an error linking {name} expected 1 type parameter, found {}",
+                    generics.len(),
+                );
+
+                debug::add_debug_log_fn(module, compilation_ctx, &generics[0])
+            }
             // This native function is only available in debug mode to help with testing. It should
             // not be compiled in release mode.
             #[cfg(debug_assertions)]
@@ -226,6 +403,195 @@ impl NativeFunction {
 
                 types::add_is_one_time_witness_fn(module, compilation_ctx, &generics[0])
             }
+            Self::NATIVE_MAPPING_CONTAINS => {
+                assert_eq!(
+                    2,
+                    generics.len(),
+                    "there was an error linking {name} expected 2 type parameters, found {}",
+                    generics.len(),
+                );
+
+                mapping::add_mapping_contains_fn(
+                    module,
+                    compilation_ctx,
+                    &generics[0],
+                    &generics[1],
+                )
+            }
+            Self::NATIVE_MAPPING_GET => {
+                assert_eq!(
+                    2,
+                    generics.len(),
+                    "there was an error linking {name} expected 2 type parameters, found {}",
+                    generics.len(),
+                );
+
+                mapping::add_mapping_get_fn(module, compilation_ctx, &generics[0], &generics[1])
+            }
+            Self::NATIVE_MAPPING_SET => {
+                assert_eq!(
+                    2,
+                    generics.len(),
+                    "there was an error linking {name} expected 2 type parameters, found {}",
+                    generics.len(),
+                );
+
+                mapping::add_mapping_set_fn(module, compilation_ctx, &generics[0], &generics[1])
+            }
+            Self::NATIVE_MAPPING_REMOVE => {
+                assert_eq!(
+                    2,
+                    generics.len(),
+                    "there was an error linking {name} expected 2 type parameters, found {}",
+                    generics.len(),
+                );
+
+                mapping::add_mapping_remove_fn(module, compilation_ctx, &generics[0], &generics[1])
+            }
+            Self::NATIVE_MAPPING_BORROW_MAPPING => {
+                assert_eq!(
+                    3,
+                    generics.len(),
+                    "there was an error linking {name} expected 3 type parameters, found {}",
+                    generics.len(),
+                );
+
+                mapping::add_mapping_borrow_mapping_fn(module, compilation_ctx, &generics[0])
+            }
+            // Bags save their values the same way tables do, the type checks are done on the Move
+            // side
+            Self::NATIVE_TABLE_ADD | Self::NATIVE_BAG_ADD => {
+                assert_eq!(
+                    2,
+                    generics.len(),
+                    "there was an error linking {name} expected 2 type parameters, found {}",
+                    generics.len(),
+                );
+
+                table::add_table_add_fn(module, compilation_ctx, &generics[0], &generics[1])
+            }
+            Self::NATIVE_TABLE_BORROW | Self::NATIVE_BAG_BORROW => {
+                assert_eq!(
+                    2,
+                    generics.len(),
+                    "there was an error linking {name} expected 2 type parameters, found {}",
+                    generics.len(),
+                );
+
+                table::add_table_borrow_fn(
+                    module,
+                    compilation_ctx,
+                    &generics[0],
+                    &generics[1],
+                    false,
+                )
+            }
+            Self::NATIVE_TABLE_BORROW_MUT | Self::NATIVE_BAG_BORROW_MUT => {
+                assert_eq!(
+                    2,
+                    generics.len(),
+                    "there was an error linking {name} expected 2 type parameters, found {}",
+                    generics.len(),
+                );
+
+                table::add_table_borrow_fn(
+                    module,
+                    compilation_ctx,
+                    &generics[0],
+                    &generics[1],
+                    true,
+                )
+            }
+            Self::NATIVE_TABLE_REMOVE | Self::NATIVE_BAG_REMOVE => {
+                assert_eq!(
+                    2,
+                    generics.len(),
+                    "there was an error linking {name} expected 2 type parameters, found {}",
+                    generics.len(),
+                );
+
+                table::add_table_remove_fn(module, compilation_ctx, &generics[0], &generics[1])
+            }
+            Self::NATIVE_BAG_TYPE_TAG => {
+                assert_eq!(
+                    1,
+                    generics.len(),
+                    "there was an error linking {name} expected 1 type parameter, found {}",
+                    generics.len(),
+                );
+
+                table::add_bag_type_tag_fn(module, compilation_ctx, &generics[0])
+            }
+            Self::NATIVE_BAG_GET_TYPE_TAG => {
+                assert_eq!(
+                    1,
+                    generics.len(),
+                    "there was an error linking {name} expected 1 type parameter, found {}",
+                    generics.len(),
+                );
+
+                table::add_bag_get_type_tag_fn(module, compilation_ctx, &generics[0])
+            }
+            Self::NATIVE_BAG_SET_TYPE_TAG => {
+                assert_eq!(
+                    1,
+                    generics.len(),
+                    "there was an error linking {name} expected 1 type parameter, found {}",
+                    generics.len(),
+                );
+
+                table::add_bag_set_type_tag_fn(module, compilation_ctx, &generics[0])
+            }
+            Self::NATIVE_STORAGE_VEC_LENGTH => {
+                assert_eq!(
+                    1,
+                    generics.len(),
+                    "there was an error linking {name} expected 1 type parameter, found {}",
+                    generics.len(),
+                );
+
+                storage_vec::add_storage_vec_length_fn(module, compilation_ctx, &generics[0])
+            }
+            Self::NATIVE_STORAGE_VEC_PUSH_BACK => {
+                assert_eq!(
+                    1,
+                    generics.len(),
+                    "there was an error linking {name} expected 1 type parameter, found {}",
+                    generics.len(),
+                );
+
+                storage_vec::add_storage_vec_push_back_fn(module, compilation_ctx, &generics[0])
+            }
+            Self::NATIVE_STORAGE_VEC_BORROW => {
+                assert_eq!(
+                    1,
+                    generics.len(),
+                    "there was an error linking {name} expected 1 type parameter, found {}",
+                    generics.len(),
+                );
+
+                storage_vec::add_storage_vec_borrow_fn(module, compilation_ctx, &generics[0], false)
+            }
+            Self::NATIVE_STORAGE_VEC_BORROW_MUT => {
+                assert_eq!(
+                    1,
+                    generics.len(),
+                    "there was an error linking {name} expected 1 type parameter, found {}",
+                    generics.len(),
+                );
+
+                storage_vec::add_storage_vec_borrow_fn(module, compilation_ctx, &generics[0], true)
+            }
+            Self::NATIVE_STORAGE_VEC_POP_BACK => {
+                assert_eq!(
+                    1,
+                    generics.len(),
+                    "there was an error linking {name} expected 1 type parameter, found {}",
+                    generics.len(),
+                );
+
+                storage_vec::add_storage_vec_pop_back_fn(module, compilation_ctx, &generics[0])
+            }
             _ => panic!("generic native function {name} not supported yet"),
         }
     }
@@ -237,6 +603,10 @@ impl NativeFunction {
             Self::NATIVE_BLOCK_GAS_LIMIT => Some(Self::HOST_BLOCK_GAS_LIMIT),
             Self::NATIVE_BLOCK_TIMESTAMP => Some(Self::HOST_BLOCK_TIMESTAMP),
             Self::NATIVE_CHAIN_ID => Some(Self::HOST_CHAIN_ID),
+            Self::NATIVE_MSG_REENTRANT => Some(Self::HOST_MSG_REENTRANT),
+            Self::NATIVE_INK_PRICE => Some(Self::HOST_TX_INK_PRICE),
+            Self::NATIVE_GAS_LEFT => Some(Self::HOST_EVM_GAS_LEFT),
+            Self::NATIVE_INK_LEFT => Some(Self::HOST_EVM_INK_LEFT),
             _ => None,
         }
     }
@@ -0,0 +1,152 @@
+//! Helpers to move bytes between Move vectors and the buffers the host functions read and write.
+//!
+//! Move `vector<u8>` values keep every element in 4 bytes, so they are packed into a buffer before
+//! being passed to the host, and the bytes written by the host are unpacked into a new vector.
+use crate::{CompilationContext, translation::intermediate_types::vector::IVector};
+use walrus::{
+    InstrSeqBuilder, LocalId, Module, ValType,
+    ir::{BinaryOp, ExtendedLoad, LoadKind, MemArg, StoreKind},
+};
+
+/// Adds the instructions to copy the elements of the vector<u8> in `vector_ptr` to a new buffer,
+/// one byte each. The length of the vector is left in `len` and the buffer in `bytes_ptr`.
+pub fn add_pack_bytes_instructions(
+    builder: &mut InstrSeqBuilder,
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+    vector_ptr: LocalId,
+    len: LocalId,
+    bytes_ptr: LocalId,
+) {
+    let index = module.locals.add(ValType::I32);
+
+    builder
+        .local_get(vector_ptr)
+        .load(
+            compilation_ctx.memory_id,
+            LoadKind::I32 { atomic: false },
+            MemArg {
+                align: 0,
+                offset: 0,
+            },
+        )
+        .local_set(len);
+
+    builder
+        .local_get(len)
+        .call(compilation_ctx.allocator)
+        .local_set(bytes_ptr);
+
+    builder.i32_const(0).local_set(index);
+    builder.block(None, |block| {
+        let block_id = block.id();
+        block.loop_(None, |loop_| {
+            let loop_id = loop_.id();
+
+            loop_
+                .local_get(index)
+                .local_get(len)
+                .binop(BinaryOp::I32Eq)
+                .br_if(block_id);
+
+            // bytes[index] = vector[index]
+            loop_
+                .local_get(bytes_ptr)
+                .local_get(index)
+                .binop(BinaryOp::I32Add)
+                .local_get(vector_ptr)
+                .local_get(index)
+                .i32_const(4)
+                .binop(BinaryOp::I32Mul)
+                .binop(BinaryOp::I32Add)
+                .load(
+                    compilation_ctx.memory_id,
+                    LoadKind::I32 { atomic: false },
+                    MemArg {
+                        align: 0,
+                        offset: 8,
+                    },
+                )
+                .store(
+                    compilation_ctx.memory_id,
+                    StoreKind::I32_8 { atomic: false },
+                    MemArg {
+                        align: 0,
+                        offset: 0,
+                    },
+                );
+
+            loop_
+                .local_get(index)
+                .i32_const(1)
+                .binop(BinaryOp::I32Add)
+                .local_set(index)
+                .br(loop_id);
+        });
+    });
+}
+
+/// Adds the instructions to create a vector<u8> in `vector_ptr` with the `len` bytes in
+/// `bytes_ptr`.
+pub fn add_unpack_bytes_instructions(
+    builder: &mut InstrSeqBuilder,
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+    bytes_ptr: LocalId,
+    len: LocalId,
+    vector_ptr: LocalId,
+) {
+    let index = module.locals.add(ValType::I32);
+
+    IVector::allocate_vector_with_header(builder, compilation_ctx, vector_ptr, len, len, 4);
+
+    builder.i32_const(0).local_set(index);
+    builder.block(None, |block| {
+        let block_id = block.id();
+        block.loop_(None, |loop_| {
+            let loop_id = loop_.id();
+
+            loop_
+                .local_get(index)
+                .local_get(len)
+                .binop(BinaryOp::I32Eq)
+                .br_if(block_id);
+
+            // vector[index] = bytes[index]
+            loop_
+                .local_get(vector_ptr)
+                .local_get(index)
+                .i32_const(4)
+                .binop(BinaryOp::I32Mul)
+                .binop(BinaryOp::I32Add)
+                .local_get(bytes_ptr)
+                .local_get(index)
+                .binop(BinaryOp::I32Add)
+                .load(
+                    compilation_ctx.memory_id,
+                    LoadKind::I32_8 {
+                        kind: ExtendedLoad::ZeroExtend,
+                    },
+                    MemArg {
+                        align: 0,
+                        offset: 0,
+                    },
+                )
+                .store(
+                    compilation_ctx.memory_id,
+                    StoreKind::I32 { atomic: false },
+                    MemArg {
+                        align: 0,
+                        offset: 8,
+                    },
+                );
+
+            loop_
+                .local_get(index)
+                .i32_const(1)
+                .binop(BinaryOp::I32Add)
+                .local_set(index)
+                .br(loop_id);
+        });
+    });
+}
@@ -0,0 +1,609 @@
+//! This module contains the functions to call other contracts and to deploy new ones.
+use super::{
+    NativeFunction,
+    bytes::{add_pack_bytes_instructions, add_unpack_bytes_instructions},
+};
+use crate::{
+    CompilationContext,
+    data::DATA_CALLDATA_OFFSET,
+    hostio::host_functions::{
+        call_contract, create1, create2, delegate_call_contract, exit_early, return_data_size,
+        static_call_contract, write_result,
+    },
+    runtime::RuntimeFunction,
+    translation::intermediate_types::address::IAddress,
+};
+use walrus::{
+    FunctionBuilder, FunctionId, Module, ValType,
+    ir::{BinaryOp, LoadKind, MemArg, StoreKind, UnaryOp},
+};
+
+/// Calls the contract in `target` with `calldata` as input, sending `value` wei and forwarding all
+/// the gas available.
+///
+/// The storage cache is flushed before the call, so the called contract sees the slots written so
+/// far, and the ones it modifies are loaded again afterwards.
+///
+/// # Arguments
+/// - target: pointer to the address of the contract
+/// - calldata: pointer to a vector<u8>
+/// - value: pointer to an u256
+///
+/// # Returns
+/// - true if the call succeeded, false if it reverted
+pub fn add_native_call_contract_fn(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+) -> FunctionId {
+    let (call_contract_fn, _) = call_contract(module);
+    let swap_256_fn = RuntimeFunction::SwapI256Bytes.get(module, Some(compilation_ctx));
+    let flush_cache_fn = RuntimeFunction::StorageCacheFlush.get(module, Some(compilation_ctx));
+
+    let mut function = FunctionBuilder::new(
+        &mut module.types,
+        &[ValType::I32, ValType::I32, ValType::I32],
+        &[ValType::I32],
+    );
+
+    // Arguments
+    let target_ptr = module.locals.add(ValType::I32);
+    let calldata_ptr = module.locals.add(ValType::I32);
+    let value_ptr = module.locals.add(ValType::I32);
+
+    // Locals
+    let calldata_len = module.locals.add(ValType::I32);
+    let calldata_bytes_ptr = module.locals.add(ValType::I32);
+    let value_be_ptr = module.locals.add(ValType::I32);
+    let return_data_len_ptr = module.locals.add(ValType::I32);
+
+    let mut builder = function
+        .name(NativeFunction::NATIVE_CALL_CONTRACT.to_owned())
+        .func_body();
+
+    add_pack_bytes_instructions(
+        &mut builder,
+        module,
+        compilation_ctx,
+        calldata_ptr,
+        calldata_len,
+        calldata_bytes_ptr,
+    );
+
+    // The host expects the value in big-endian
+    builder
+        .i32_const(32)
+        .call(compilation_ctx.allocator)
+        .local_set(value_be_ptr);
+    builder
+        .local_get(value_ptr)
+        .local_get(value_be_ptr)
+        .call(swap_256_fn);
+
+    builder
+        .i32_const(4)
+        .call(compilation_ctx.allocator)
+        .local_set(return_data_len_ptr);
+
+    builder.i32_const(0).call(flush_cache_fn);
+
+    // Addresses are 32 bytes long, left-padded with 12 zeroes
+    builder
+        .local_get(target_ptr)
+        .i32_const(12)
+        .binop(BinaryOp::I32Add)
+        .local_get(calldata_bytes_ptr)
+        .local_get(calldata_len)
+        .local_get(value_be_ptr)
+        // Forward all the gas available
+        .i64_const(-1)
+        .local_get(return_data_len_ptr)
+        .call(call_contract_fn)
+        .unop(UnaryOp::I32Eqz);
+
+    function.finish(vec![target_ptr, calldata_ptr, value_ptr], &mut module.funcs)
+}
+
+/// Delegate calls the contract in `target` with `calldata` as input, forwarding all the gas
+/// available. Its code runs in the context of the current contract: it reads and writes this
+/// contract's storage, and sees the sender and the value of the current call.
+///
+/// As the called code works on the same storage, the storage cache is flushed before the call, so
+/// it sees the slots written so far, and the ones it modifies are loaded again afterwards.
+///
+/// # Arguments
+/// - target: pointer to the address of the contract
+/// - calldata: pointer to a vector<u8>
+///
+/// # Returns
+/// - true if the call succeeded, false if it reverted
+pub fn add_native_delegate_call_contract_fn(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+) -> FunctionId {
+    let (delegate_call_contract_fn, _) = delegate_call_contract(module);
+
+    add_call_without_value_fn(
+        module,
+        compilation_ctx,
+        NativeFunction::NATIVE_DELEGATE_CALL_CONTRACT,
+        delegate_call_contract_fn,
+    )
+}
+
+/// Static calls the contract in `target` with `calldata` as input, forwarding all the gas
+/// available. The call reverts if the called contract, or any contract it calls, tries to modify
+/// the state, so it can be done from view functions.
+///
+/// The storage cache is flushed before the call, so the called contract sees the slots written so
+/// far if it calls back into this contract.
+///
+/// # Arguments
+/// - target: pointer to the address of the contract
+/// - calldata: pointer to a vector<u8>
+///
+/// # Returns
+/// - true if the call succeeded, false if it reverted
+pub fn add_native_static_call_contract_fn(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+) -> FunctionId {
+    let (static_call_contract_fn, _) = static_call_contract(module);
+
+    add_call_without_value_fn(
+        module,
+        compilation_ctx,
+        NativeFunction::NATIVE_STATIC_CALL_CONTRACT,
+        static_call_contract_fn,
+    )
+}
+
+/// Adds a native function that calls a contract through `call_fn`, a hook that does not send
+/// value, such as `delegate_call_contract` and `static_call_contract`.
+fn add_call_without_value_fn(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+    name: &str,
+    call_fn: FunctionId,
+) -> FunctionId {
+    let flush_cache_fn = RuntimeFunction::StorageCacheFlush.get(module, Some(compilation_ctx));
+
+    let mut function = FunctionBuilder::new(
+        &mut module.types,
+        &[ValType::I32, ValType::I32],
+        &[ValType::I32],
+    );
+
+    // Arguments
+    let target_ptr = module.locals.add(ValType::I32);
+    let calldata_ptr = module.locals.add(ValType::I32);
+
+    // Locals
+    let calldata_len = module.locals.add(ValType::I32);
+    let calldata_bytes_ptr = module.locals.add(ValType::I32);
+    let return_data_len_ptr = module.locals.add(ValType::I32);
+
+    let mut builder = function.name(name.to_owned()).func_body();
+
+    add_pack_bytes_instructions(
+        &mut builder,
+        module,
+        compilation_ctx,
+        calldata_ptr,
+        calldata_len,
+        calldata_bytes_ptr,
+    );
+
+    builder
+        .i32_const(4)
+        .call(compilation_ctx.allocator)
+        .local_set(return_data_len_ptr);
+
+    builder.i32_const(0).call(flush_cache_fn);
+
+    // Addresses are 32 bytes long, left-padded with 12 zeroes
+    builder
+        .local_get(target_ptr)
+        .i32_const(12)
+        .binop(BinaryOp::I32Add)
+        .local_get(calldata_bytes_ptr)
+        .local_get(calldata_len)
+        // Forward all the gas available
+        .i64_const(-1)
+        .local_get(return_data_len_ptr)
+        .call(call_fn)
+        .unop(UnaryOp::I32Eqz);
+
+    function.finish(vec![target_ptr, calldata_ptr], &mut module.funcs)
+}
+
+/// Deploys a new contract with the `init_code`, sending it `endowment` wei. Its address is derived
+/// from the address of this contract and its nonce, as the EVM's `CREATE` does.
+///
+/// # Arguments
+/// - init_code: pointer to a vector<u8>
+/// - endowment: pointer to an u256
+///
+/// # Returns
+/// - pointer to the address of the new contract, or to the zero address if the deployment failed
+pub fn add_native_create1_fn(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+) -> FunctionId {
+    add_create_fn(module, compilation_ctx, false)
+}
+
+/// Deploys a new contract with the `init_code`, sending it `endowment` wei. Its address is derived
+/// from the address of this contract, the `salt` and the hash of the init code, as the EVM's
+/// `CREATE2` does.
+///
+/// # Arguments
+/// - init_code: pointer to a vector<u8>
+/// - endowment: pointer to an u256
+/// - salt: pointer to an u256
+///
+/// # Returns
+/// - pointer to the address of the new contract, or to the zero address if the deployment failed
+pub fn add_native_create2_fn(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+) -> FunctionId {
+    add_create_fn(module, compilation_ctx, true)
+}
+
+/// Adds the native function that deploys a contract through `create2` if `with_salt` is true, or
+/// through `create1` otherwise.
+///
+/// The storage cache is flushed before the deployment, since the constructor of the new contract
+/// can call back into this one.
+fn add_create_fn(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+    with_salt: bool,
+) -> FunctionId {
+    let (create_fn, name) = if with_salt {
+        (create2(module).0, NativeFunction::NATIVE_CREATE2)
+    } else {
+        (create1(module).0, NativeFunction::NATIVE_CREATE1)
+    };
+    let swap_256_fn = RuntimeFunction::SwapI256Bytes.get(module, Some(compilation_ctx));
+    let flush_cache_fn = RuntimeFunction::StorageCacheFlush.get(module, Some(compilation_ctx));
+
+    let arguments = if with_salt {
+        vec![ValType::I32, ValType::I32, ValType::I32]
+    } else {
+        vec![ValType::I32, ValType::I32]
+    };
+    let mut function = FunctionBuilder::new(&mut module.types, &arguments, &[ValType::I32]);
+
+    // Arguments
+    let init_code_ptr = module.locals.add(ValType::I32);
+    let endowment_ptr = module.locals.add(ValType::I32);
+    let salt_ptr = module.locals.add(ValType::I32);
+
+    // Locals
+    let code_len = module.locals.add(ValType::I32);
+    let code_bytes_ptr = module.locals.add(ValType::I32);
+    let endowment_be_ptr = module.locals.add(ValType::I32);
+    let salt_be_ptr = module.locals.add(ValType::I32);
+    let address_ptr = module.locals.add(ValType::I32);
+    let revert_data_len_ptr = module.locals.add(ValType::I32);
+
+    let mut builder = function.name(name.to_owned()).func_body();
+
+    add_pack_bytes_instructions(
+        &mut builder,
+        module,
+        compilation_ctx,
+        init_code_ptr,
+        code_len,
+        code_bytes_ptr,
+    );
+
+    // The host expects the endowment and the salt in big-endian
+    builder
+        .i32_const(32)
+        .call(compilation_ctx.allocator)
+        .local_set(endowment_be_ptr);
+    builder
+        .local_get(endowment_ptr)
+        .local_get(endowment_be_ptr)
+        .call(swap_256_fn);
+
+    if with_salt {
+        builder
+            .i32_const(32)
+            .call(compilation_ctx.allocator)
+            .local_set(salt_be_ptr);
+        builder
+            .local_get(salt_ptr)
+            .local_get(salt_be_ptr)
+            .call(swap_256_fn);
+    }
+
+    builder
+        .i32_const(IAddress::HEAP_SIZE)
+        .call(compilation_ctx.allocator)
+        .local_set(address_ptr);
+
+    builder
+        .i32_const(4)
+        .call(compilation_ctx.allocator)
+        .local_set(revert_data_len_ptr);
+
+    builder.i32_const(0).call(flush_cache_fn);
+
+    builder
+        .local_get(code_bytes_ptr)
+        .local_get(code_len)
+        .local_get(endowment_be_ptr);
+
+    if with_salt {
+        builder.local_get(salt_be_ptr);
+    }
+
+    // Addresses are 32 bytes long, left-padded with 12 zeroes
+    builder
+        .local_get(address_ptr)
+        .i32_const(12)
+        .binop(BinaryOp::I32Add)
+        .local_get(revert_data_len_ptr)
+        .call(create_fn);
+
+    builder.local_get(address_ptr);
+
+    let arguments = if with_salt {
+        vec![init_code_ptr, endowment_ptr, salt_ptr]
+    } else {
+        vec![init_code_ptr, endowment_ptr]
+    };
+    function.finish(arguments, &mut module.funcs)
+}
+
+/// Sends `amount` wei to `to`, calling it with empty calldata and forwarding all the gas
+/// available, so contracts receiving it can run their fallback code.
+///
+/// If the call fails, the transaction reverts with the revert data of the call, so the reason the
+/// recipient gave reaches the caller.
+///
+/// # Arguments
+/// - to: pointer to the address of the recipient
+/// - amount: pointer to an u256
+pub fn add_native_transfer_eth_fn(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+) -> FunctionId {
+    let (call_contract_fn, _) = call_contract(module);
+    let (return_data_size_fn, _) = return_data_size(module);
+    let (write_result_fn, _) = write_result(module);
+    let (exit_early_fn, _) = exit_early(module);
+    let swap_256_fn = RuntimeFunction::SwapI256Bytes.get(module, Some(compilation_ctx));
+    let flush_cache_fn = RuntimeFunction::StorageCacheFlush.get(module, Some(compilation_ctx));
+    let copy_return_data_fn = RuntimeFunction::CopyReturnData.get(module, Some(compilation_ctx));
+
+    let mut function = FunctionBuilder::new(&mut module.types, &[ValType::I32, ValType::I32], &[]);
+
+    // Arguments
+    let to_ptr = module.locals.add(ValType::I32);
+    let amount_ptr = module.locals.add(ValType::I32);
+
+    // Locals
+    let amount_be_ptr = module.locals.add(ValType::I32);
+    let return_data_len_ptr = module.locals.add(ValType::I32);
+    let revert_data_len = module.locals.add(ValType::I32);
+
+    let mut builder = function
+        .name(NativeFunction::NATIVE_TRANSFER_ETH.to_owned())
+        .func_body();
+
+    // The host expects the value in big-endian
+    builder
+        .i32_const(32)
+        .call(compilation_ctx.allocator)
+        .local_set(amount_be_ptr);
+    builder
+        .local_get(amount_ptr)
+        .local_get(amount_be_ptr)
+        .call(swap_256_fn);
+
+    builder
+        .i32_const(4)
+        .call(compilation_ctx.allocator)
+        .local_set(return_data_len_ptr);
+
+    builder.i32_const(0).call(flush_cache_fn);
+
+    // Addresses are 32 bytes long, left-padded with 12 zeroes
+    builder
+        .local_get(to_ptr)
+        .i32_const(12)
+        .binop(BinaryOp::I32Add)
+        // Empty calldata
+        .i32_const(0)
+        .i32_const(0)
+        .local_get(amount_be_ptr)
+        // Forward all the gas available
+        .i64_const(-1)
+        .local_get(return_data_len_ptr)
+        .call(call_contract_fn);
+
+    builder.if_else(
+        None,
+        |failed| {
+            failed.call(return_data_size_fn).local_set(revert_data_len);
+
+            failed
+                .i32_const(0)
+                .local_get(revert_data_len)
+                .call(copy_return_data_fn)
+                .local_get(revert_data_len)
+                .call(write_result_fn);
+
+            failed.i32_const(1).call(exit_early_fn).unreachable();
+        },
+        |_| {},
+    );
+
+    function.finish(vec![to_ptr, amount_ptr], &mut module.funcs)
+}
+
+/// Returns the data returned by the last call to another contract as a vector<u8>. It is empty if
+/// no contract was called.
+pub fn add_native_return_data_fn(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+) -> FunctionId {
+    let (return_data_size_fn, _) = return_data_size(module);
+    let copy_return_data_fn = RuntimeFunction::CopyReturnData.get(module, Some(compilation_ctx));
+
+    let mut function = FunctionBuilder::new(&mut module.types, &[], &[ValType::I32]);
+
+    // Locals
+    let len = module.locals.add(ValType::I32);
+    let bytes_ptr = module.locals.add(ValType::I32);
+    let vector_ptr = module.locals.add(ValType::I32);
+
+    let mut builder = function
+        .name(NativeFunction::NATIVE_RETURN_DATA.to_owned())
+        .func_body();
+
+    builder.call(return_data_size_fn).local_set(len);
+
+    builder
+        .i32_const(0)
+        .local_get(len)
+        .call(copy_return_data_fn)
+        .local_set(bytes_ptr);
+
+    // The return data buffer is reused by the next call, so the bytes are copied to a new vector
+    add_unpack_bytes_instructions(
+        &mut builder,
+        module,
+        compilation_ctx,
+        bytes_ptr,
+        len,
+        vector_ptr,
+    );
+
+    builder.local_get(vector_ptr);
+
+    function.finish(vec![], &mut module.funcs)
+}
+
+/// Calls the entry function of this contract matching the selector of `calldata`, without
+/// leaving the program. It costs the same as calling the function from Move, instead of an
+/// external call to the contract's own address.
+///
+/// The call runs in the current context: the sender and the value are the ones of the current
+/// call, and `msg::data` returns `calldata` until it returns. If the function aborts, or no
+/// entry function matches the selector, the whole transaction aborts.
+///
+/// # Arguments
+/// - calldata: pointer to a vector<u8>
+///
+/// # Returns
+/// - pointer to a vector<u8> with the ABI-encoded return values
+pub fn add_native_self_call_fn(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+) -> FunctionId {
+    let self_dispatch_fn = RuntimeFunction::SelfDispatch.get(module, Some(compilation_ctx));
+
+    let mut function = FunctionBuilder::new(&mut module.types, &[ValType::I32], &[ValType::I32]);
+
+    // Arguments
+    let calldata_ptr = module.locals.add(ValType::I32);
+
+    // Locals
+    let calldata_len = module.locals.add(ValType::I32);
+    let calldata_bytes_ptr = module.locals.add(ValType::I32);
+    let outer_calldata_ptr = module.locals.add(ValType::I32);
+    let outer_calldata_len = module.locals.add(ValType::I32);
+    let return_data_len_ptr = module.locals.add(ValType::I32);
+    let return_data_ptr = module.locals.add(ValType::I32);
+    let return_data_len = module.locals.add(ValType::I32);
+    let vector_ptr = module.locals.add(ValType::I32);
+
+    let mut builder = function
+        .name(NativeFunction::NATIVE_SELF_CALL.to_owned())
+        .func_body();
+
+    add_pack_bytes_instructions(
+        &mut builder,
+        module,
+        compilation_ctx,
+        calldata_ptr,
+        calldata_len,
+        calldata_bytes_ptr,
+    );
+
+    // The called function sees its calldata in msg::data, and the current one is restored after
+    for (local, offset) in [(outer_calldata_ptr, 0), (outer_calldata_len, 4)] {
+        builder
+            .i32_const(DATA_CALLDATA_OFFSET)
+            .load(
+                compilation_ctx.memory_id,
+                LoadKind::I32 { atomic: false },
+                MemArg { align: 0, offset },
+            )
+            .local_set(local);
+    }
+
+    for (local, offset) in [(calldata_bytes_ptr, 0), (calldata_len, 4)] {
+        builder
+            .i32_const(DATA_CALLDATA_OFFSET)
+            .local_get(local)
+            .store(
+                compilation_ctx.memory_id,
+                StoreKind::I32 { atomic: false },
+                MemArg { align: 0, offset },
+            );
+    }
+
+    builder
+        .i32_const(4)
+        .call(compilation_ctx.allocator)
+        .local_set(return_data_len_ptr);
+
+    builder
+        .local_get(calldata_bytes_ptr)
+        .local_get(calldata_len)
+        .local_get(return_data_len_ptr)
+        .call(self_dispatch_fn)
+        .local_set(return_data_ptr);
+
+    for (local, offset) in [(outer_calldata_ptr, 0), (outer_calldata_len, 4)] {
+        builder
+            .i32_const(DATA_CALLDATA_OFFSET)
+            .local_get(local)
+            .store(
+                compilation_ctx.memory_id,
+                StoreKind::I32 { atomic: false },
+                MemArg { align: 0, offset },
+            );
+    }
+
+    builder
+        .local_get(return_data_len_ptr)
+        .load(
+            compilation_ctx.memory_id,
+            LoadKind::I32 { atomic: false },
+            MemArg {
+                align: 0,
+                offset: 0,
+            },
+        )
+        .local_set(return_data_len);
+
+    add_unpack_bytes_instructions(
+        &mut builder,
+        module,
+        compilation_ctx,
+        return_data_ptr,
+        return_data_len,
+        vector_ptr,
+    );
+
+    builder.local_get(vector_ptr);
+
+    function.finish(vec![calldata_ptr], &mut module.funcs)
+}
@@ -0,0 +1,444 @@
+//! This module contains the functions to print debug messages while testing the contracts.
+use walrus::{
+    DataKind, FunctionBuilder, FunctionId, InstrSeqBuilder, LocalId, Module, ValType,
+    ir::{BinaryOp, ExtendedLoad, LoadKind, MemArg, StoreKind, UnaryOp},
+};
+
+use super::{NativeFunction, bytes::add_pack_bytes_instructions};
+use crate::{
+    CompilationContext, get_generic_function_name, hostio::host_functions::log_txt,
+    translation::intermediate_types::IntermediateType,
+};
+
+/// Bytes needed to print the longest value, an u256 in hexadecimal: the `0x` prefix and two digits
+/// per byte
+const MAX_VALUE_LEN: i32 = 2 + 64;
+
+/// Prints `<msg>: <value>` through the console's `log_txt` hook. Booleans are printed as `true`
+/// or `false`, the integers up to u64 in decimal, and u128, u256 and addresses in hexadecimal.
+///
+/// The console is only available in debug mode, so the function does nothing unless the package
+/// is translated with the `debug` option, and the module does not import it.
+///
+/// # Arguments
+/// - msg: pointer to a vector<u8>
+/// - value: the value to print
+pub fn add_debug_log_fn(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+    itype: &IntermediateType,
+) -> FunctionId {
+    let name = get_generic_function_name(NativeFunction::NATIVE_DEBUG_LOG, &[itype]);
+    if let Some(function) = module.funcs.by_name(&name) {
+        return function;
+    };
+
+    let mut function = FunctionBuilder::new(
+        &mut module.types,
+        &[ValType::I32, ValType::from(itype)],
+        &[],
+    );
+
+    // Arguments
+    let msg_ptr = module.locals.add(ValType::I32);
+    let value = module.locals.add(ValType::from(itype));
+
+    function.name(name);
+
+    if !compilation_ctx.options.debug {
+        return function.finish(vec![msg_ptr, value], &mut module.funcs);
+    }
+
+    // Locals
+    let msg_len = module.locals.add(ValType::I32);
+    let msg_bytes_ptr = module.locals.add(ValType::I32);
+    let text_ptr = module.locals.add(ValType::I32);
+    let writer = module.locals.add(ValType::I32);
+
+    let mut builder = function.func_body();
+
+    let (log_txt_fn, _) = log_txt(module);
+
+    add_pack_bytes_instructions(
+        &mut builder,
+        module,
+        compilation_ctx,
+        msg_ptr,
+        msg_len,
+        msg_bytes_ptr,
+    );
+
+    // [msg][": "][value]
+    builder
+        .local_get(msg_len)
+        .i32_const(2 + MAX_VALUE_LEN)
+        .binop(BinaryOp::I32Add)
+        .call(compilation_ctx.allocator)
+        .local_tee(text_ptr)
+        .local_get(msg_bytes_ptr)
+        .local_get(msg_len)
+        .memory_copy(compilation_ctx.memory_id, compilation_ctx.memory_id);
+
+    builder
+        .local_get(text_ptr)
+        .local_get(msg_len)
+        .binop(BinaryOp::I32Add)
+        .local_tee(writer)
+        .i32_const(i32::from(u16::from_le_bytes(*b": ")))
+        .store(
+            compilation_ctx.memory_id,
+            StoreKind::I32_16 { atomic: false },
+            MemArg {
+                align: 0,
+                offset: 0,
+            },
+        );
+
+    builder
+        .local_get(writer)
+        .i32_const(2)
+        .binop(BinaryOp::I32Add)
+        .local_set(writer);
+
+    match itype {
+        IntermediateType::IBool => {
+            add_write_bool_instructions(&mut builder, compilation_ctx, value, writer);
+        }
+        IntermediateType::IU8 | IntermediateType::IU16 | IntermediateType::IU32 => {
+            let number = module.locals.add(ValType::I64);
+            builder
+                .local_get(value)
+                .unop(UnaryOp::I64ExtendUI32)
+                .local_set(number);
+
+            add_write_decimal_instructions(&mut builder, module, compilation_ctx, number, writer);
+        }
+        IntermediateType::IU64 => {
+            add_write_decimal_instructions(&mut builder, module, compilation_ctx, value, writer);
+        }
+        // Heap integers are little-endian, so their bytes are printed from the last one
+        IntermediateType::IU128 => {
+            add_write_hex_instructions(
+                &mut builder,
+                module,
+                compilation_ctx,
+                value,
+                (15, -1, 16),
+                writer,
+            );
+        }
+        IntermediateType::IU256 => {
+            add_write_hex_instructions(
+                &mut builder,
+                module,
+                compilation_ctx,
+                value,
+                (31, -1, 32),
+                writer,
+            );
+        }
+        // Addresses are big-endian and left-padded to 32 bytes
+        IntermediateType::IAddress => {
+            add_write_hex_instructions(
+                &mut builder,
+                module,
+                compilation_ctx,
+                value,
+                (12, 1, 20),
+                writer,
+            );
+        }
+        _ => panic!("debug::log does not support values of type {itype:?}"),
+    }
+
+    builder
+        .local_get(text_ptr)
+        .local_get(writer)
+        .local_get(text_ptr)
+        .binop(BinaryOp::I32Sub)
+        .call(log_txt_fn);
+
+    function.finish(vec![msg_ptr, value], &mut module.funcs)
+}
+
+/// Prints `abort in <function>: <code>` through the console's `log_txt` hook, with the abort code
+/// on top of the stack, and leaves the code there for the abort. Only added for the modules
+/// translated with the `debug` option.
+pub fn add_abort_log_instructions(
+    builder: &mut InstrSeqBuilder,
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+    function_name: &str,
+) {
+    let code = module.locals.add(ValType::I64);
+    let number = module.locals.add(ValType::I64);
+    let text_ptr = module.locals.add(ValType::I32);
+    let writer = module.locals.add(ValType::I32);
+
+    let (log_txt_fn, _) = log_txt(module);
+
+    // Copied from a passive segment, so the text does not take memory until the function aborts
+    let prefix = format!("abort in {function_name}: ").into_bytes();
+    let prefix_len = prefix.len() as i32;
+    let prefix_data = module.data.add(DataKind::Passive, prefix);
+
+    builder.local_tee(code).local_set(number);
+
+    builder
+        .i32_const(prefix_len + MAX_VALUE_LEN)
+        .call(compilation_ctx.allocator)
+        .local_tee(text_ptr)
+        .i32_const(0)
+        .i32_const(prefix_len)
+        .memory_init(compilation_ctx.memory_id, prefix_data);
+
+    builder
+        .local_get(text_ptr)
+        .i32_const(prefix_len)
+        .binop(BinaryOp::I32Add)
+        .local_set(writer);
+
+    add_write_decimal_instructions(builder, module, compilation_ctx, number, writer);
+
+    builder
+        .local_get(text_ptr)
+        .local_get(writer)
+        .local_get(text_ptr)
+        .binop(BinaryOp::I32Sub)
+        .call(log_txt_fn)
+        .local_get(code);
+}
+
+/// Writes `true` or `false` and advances the writer.
+fn add_write_bool_instructions(
+    builder: &mut InstrSeqBuilder,
+    compilation_ctx: &CompilationContext,
+    value: LocalId,
+    writer: LocalId,
+) {
+    builder.local_get(value).if_else(
+        None,
+        |then| {
+            then.local_get(writer)
+                .i32_const(i32::from_le_bytes(*b"true"))
+                .store(
+                    compilation_ctx.memory_id,
+                    StoreKind::I32 { atomic: false },
+                    MemArg {
+                        align: 0,
+                        offset: 0,
+                    },
+                )
+                .local_get(writer)
+                .i32_const(4)
+                .binop(BinaryOp::I32Add)
+                .local_set(writer);
+        },
+        |else_| {
+            else_
+                .local_get(writer)
+                .i32_const(i32::from_le_bytes(*b"fals"))
+                .store(
+                    compilation_ctx.memory_id,
+                    StoreKind::I32 { atomic: false },
+                    MemArg {
+                        align: 0,
+                        offset: 0,
+                    },
+                )
+                .local_get(writer)
+                .i32_const(i32::from(b'e'))
+                .store(
+                    compilation_ctx.memory_id,
+                    StoreKind::I32_8 { atomic: false },
+                    MemArg {
+                        align: 0,
+                        offset: 4,
+                    },
+                )
+                .local_get(writer)
+                .i32_const(5)
+                .binop(BinaryOp::I32Add)
+                .local_set(writer);
+        },
+    );
+}
+
+/// Writes the digits of the u64 in `number`, and advances the writer. The number is consumed.
+///
+/// The digits are computed from the least significant one, so they are written backwards at the
+/// end of the space reserved for the value, and then moved to the writer.
+fn add_write_decimal_instructions(
+    builder: &mut InstrSeqBuilder,
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+    number: LocalId,
+    writer: LocalId,
+) {
+    let digits_end = module.locals.add(ValType::I32);
+    let cursor = module.locals.add(ValType::I32);
+
+    builder
+        .local_get(writer)
+        .i32_const(MAX_VALUE_LEN)
+        .binop(BinaryOp::I32Add)
+        .local_tee(digits_end)
+        .local_set(cursor);
+
+    builder.loop_(None, |loop_| {
+        let loop_id = loop_.id();
+
+        loop_
+            .local_get(cursor)
+            .i32_const(1)
+            .binop(BinaryOp::I32Sub)
+            .local_tee(cursor)
+            .local_get(number)
+            .i64_const(10)
+            .binop(BinaryOp::I64RemU)
+            .unop(UnaryOp::I32WrapI64)
+            .i32_const(i32::from(b'0'))
+            .binop(BinaryOp::I32Add)
+            .store(
+                compilation_ctx.memory_id,
+                StoreKind::I32_8 { atomic: false },
+                MemArg {
+                    align: 0,
+                    offset: 0,
+                },
+            );
+
+        loop_
+            .local_get(number)
+            .i64_const(10)
+            .binop(BinaryOp::I64DivU)
+            .local_tee(number)
+            .unop(UnaryOp::I64Eqz)
+            .unop(UnaryOp::I32Eqz)
+            .br_if(loop_id);
+    });
+
+    // The regions can overlap, which memory.copy supports
+    builder
+        .local_get(writer)
+        .local_get(cursor)
+        .local_get(digits_end)
+        .local_get(cursor)
+        .binop(BinaryOp::I32Sub)
+        .memory_copy(compilation_ctx.memory_id, compilation_ctx.memory_id);
+
+    builder
+        .local_get(writer)
+        .local_get(digits_end)
+        .local_get(cursor)
+        .binop(BinaryOp::I32Sub)
+        .binop(BinaryOp::I32Add)
+        .local_set(writer);
+}
+
+/// Writes `0x` followed by two hexadecimal digits for each of the `count` bytes read from
+/// `value_ptr`, starting at `first` and moving `step` bytes after each one, and advances the
+/// writer.
+fn add_write_hex_instructions(
+    builder: &mut InstrSeqBuilder,
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+    value_ptr: LocalId,
+    (first, step, count): (i32, i32, i32),
+    writer: LocalId,
+) {
+    let byte_ptr = module.locals.add(ValType::I32);
+    let remaining = module.locals.add(ValType::I32);
+    let byte = module.locals.add(ValType::I32);
+    let nibble = module.locals.add(ValType::I32);
+
+    builder
+        .local_get(writer)
+        .i32_const(i32::from(u16::from_le_bytes(*b"0x")))
+        .store(
+            compilation_ctx.memory_id,
+            StoreKind::I32_16 { atomic: false },
+            MemArg {
+                align: 0,
+                offset: 0,
+            },
+        )
+        .local_get(writer)
+        .i32_const(2)
+        .binop(BinaryOp::I32Add)
+        .local_set(writer);
+
+    builder
+        .local_get(value_ptr)
+        .i32_const(first)
+        .binop(BinaryOp::I32Add)
+        .local_set(byte_ptr);
+
+    builder.i32_const(count).local_set(remaining);
+
+    builder.loop_(None, |loop_| {
+        let loop_id = loop_.id();
+
+        loop_
+            .local_get(byte_ptr)
+            .load(
+                compilation_ctx.memory_id,
+                LoadKind::I32_8 {
+                    kind: ExtendedLoad::ZeroExtend,
+                },
+                MemArg {
+                    align: 0,
+                    offset: 0,
+                },
+            )
+            .local_set(byte);
+
+        // The high nibble goes first
+        for (shift, offset) in [(4, 0), (0, 1)] {
+            loop_
+                .local_get(byte)
+                .i32_const(shift)
+                .binop(BinaryOp::I32ShrU)
+                .i32_const(0x0f)
+                .binop(BinaryOp::I32And)
+                .local_set(nibble);
+
+            // The digit is '0' + nibble, or 'a' + nibble - 10 if the nibble is bigger than 9
+            loop_
+                .local_get(writer)
+                .local_get(nibble)
+                .i32_const(i32::from(b'0'))
+                .binop(BinaryOp::I32Add)
+                .local_get(nibble)
+                .i32_const(9)
+                .binop(BinaryOp::I32GtU)
+                .i32_const(i32::from(b'a' - b'0' - 10))
+                .binop(BinaryOp::I32Mul)
+                .binop(BinaryOp::I32Add)
+                .store(
+                    compilation_ctx.memory_id,
+                    StoreKind::I32_8 { atomic: false },
+                    MemArg { align: 0, offset },
+                );
+        }
+
+        loop_
+            .local_get(writer)
+            .i32_const(2)
+            .binop(BinaryOp::I32Add)
+            .local_set(writer);
+
+        loop_
+            .local_get(byte_ptr)
+            .i32_const(step)
+            .binop(BinaryOp::I32Add)
+            .local_set(byte_ptr);
+
+        loop_
+            .local_get(remaining)
+            .i32_const(1)
+            .binop(BinaryOp::I32Sub)
+            .local_tee(remaining)
+            .br_if(loop_id);
+    });
+}
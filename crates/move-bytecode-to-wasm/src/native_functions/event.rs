@@ -1,12 +1,19 @@
-use walrus::{FunctionBuilder, FunctionId, Module, ValType, ir::BinaryOp};
+use walrus::{
+    FunctionBuilder, FunctionId, Module, ValType,
+    ir::{BinaryOp, MemArg, StoreKind},
+};
 
 use crate::{
-    CompilationContext, get_generic_function_name, hostio::host_functions::emit_log,
+    CompilationContext, abi_types::function_encoding::move_struct_to_event_topic,
+    get_generic_function_name, hostio::host_functions::emit_log,
     translation::intermediate_types::IntermediateType,
 };
 
 use super::NativeFunction;
 
+/// Emits the struct as an event. Following the EVM log layout, the log starts with its topics, 32
+/// bytes each, followed by the data. The only topic is the hash of the event signature, as in
+/// Solidity's non-anonymous events, and the data is the ABI-encoded struct.
 pub fn add_emit_log_fn(
     module: &mut Module,
     compilation_ctx: &CompilationContext,
@@ -30,9 +37,30 @@ pub fn add_emit_log_fn(
     let struct_ptr = module.locals.add(ValType::I32);
 
     // Locals
+    let topics_begin = module.locals.add(ValType::I32);
     let writer_pointer = module.locals.add(ValType::I32);
     let calldata_reference_pointer = module.locals.add(ValType::I32);
-    let packed_data_begin = module.locals.add(ValType::I32);
+
+    // The topic is computed at compile time and written before the data
+    let topic = move_struct_to_event_topic(&struct_, compilation_ctx);
+    builder
+        .i32_const(32)
+        .call(compilation_ctx.allocator)
+        .local_set(topics_begin);
+
+    for (offset, chunk) in topic.chunks_exact(8).enumerate() {
+        builder
+            .local_get(topics_begin)
+            .i64_const(i64::from_le_bytes(chunk.try_into().unwrap()))
+            .store(
+                compilation_ctx.memory_id,
+                StoreKind::I64 { atomic: false },
+                MemArg {
+                    align: 0,
+                    offset: offset as u32 * 8,
+                },
+            );
+    }
 
     let size = if struct_.solidity_abi_encode_is_dynamic(compilation_ctx) {
         32
@@ -44,8 +72,7 @@ pub fn add_emit_log_fn(
         .i32_const(size)
         .call(compilation_ctx.allocator)
         .local_tee(writer_pointer)
-        .local_tee(calldata_reference_pointer)
-        .local_set(packed_data_begin);
+        .local_set(calldata_reference_pointer);
 
     // ABI pack the struct before emitting the event
     if struct_.solidity_abi_encode_is_dynamic(compilation_ctx) {
@@ -70,20 +97,18 @@ pub fn add_emit_log_fn(
         );
     }
 
-    // Emit the event with the ABI packed struct
-
-    // Beginning of the packed data
-    builder.local_get(packed_data_begin);
+    // Emit the event with the topic and the ABI packed struct, that follows it in memory
+    builder.local_get(topics_begin);
 
     // Use the allocator to get a pointer to the end of the calldata
     builder
         .i32_const(0)
         .call(compilation_ctx.allocator)
-        .local_get(packed_data_begin)
+        .local_get(topics_begin)
         .binop(BinaryOp::I32Sub);
 
-    // Log 0
-    builder.i32_const(0).call(emit_log_fn);
+    // Log 1
+    builder.i32_const(1).call(emit_log_fn);
 
     function.finish(vec![struct_ptr], &mut module.funcs)
 }
@@ -0,0 +1,397 @@
+//! This module contains the functions to query the state of the EVM, such as the balance and the
+//! code of the accounts.
+use super::{NativeFunction, bytes::add_unpack_bytes_instructions};
+use crate::{
+    CompilationContext,
+    hostio::host_functions::{
+        account_balance, account_code, account_code_size, account_codehash, contract_address,
+        static_call_contract,
+    },
+    runtime::RuntimeFunction,
+    translation::intermediate_types::{address::IAddress, heap_integers::IU256},
+};
+use alloy_primitives::keccak256;
+use walrus::{
+    FunctionBuilder, FunctionId, Module, ValType,
+    ir::{BinaryOp, MemArg, StoreKind, UnaryOp},
+};
+
+/// Address of ArbSys, the Arbitrum precompile that exposes the chain's system information
+const ARB_SYS_ADDRESS: i32 = 0x64;
+
+/// Returns the balance in wei of an account.
+///
+/// # Arguments
+/// - address: pointer to the address of the account
+///
+/// # Returns
+/// - pointer to an u256
+pub fn add_native_account_balance_fn(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+) -> FunctionId {
+    let (account_balance_fn, _) = account_balance(module);
+    let swap_256_fn = RuntimeFunction::SwapI256Bytes.get(module, Some(compilation_ctx));
+
+    let mut function = FunctionBuilder::new(&mut module.types, &[ValType::I32], &[ValType::I32]);
+
+    // Arguments
+    let address_ptr = module.locals.add(ValType::I32);
+
+    // Locals
+    let balance_be_ptr = module.locals.add(ValType::I32);
+    let balance_ptr = module.locals.add(ValType::I32);
+
+    let mut builder = function
+        .name(NativeFunction::NATIVE_ACCOUNT_BALANCE.to_owned())
+        .func_body();
+
+    builder
+        .i32_const(IU256::HEAP_SIZE)
+        .call(compilation_ctx.allocator)
+        .local_set(balance_be_ptr);
+
+    // Addresses are 32 bytes long, left-padded with 12 zeroes
+    builder
+        .local_get(address_ptr)
+        .i32_const(12)
+        .binop(BinaryOp::I32Add)
+        .local_get(balance_be_ptr)
+        .call(account_balance_fn);
+
+    // The host writes the balance in big-endian
+    builder
+        .i32_const(IU256::HEAP_SIZE)
+        .call(compilation_ctx.allocator)
+        .local_set(balance_ptr);
+    builder
+        .local_get(balance_be_ptr)
+        .local_get(balance_ptr)
+        .call(swap_256_fn);
+
+    builder.local_get(balance_ptr);
+
+    function.finish(vec![address_ptr], &mut module.funcs)
+}
+
+/// Returns the code of an account as a vector<u8>. It is empty if the account is not a contract.
+///
+/// # Arguments
+/// - address: pointer to the address of the account
+///
+/// # Returns
+/// - pointer to a vector<u8>
+pub fn add_native_account_code_fn(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+) -> FunctionId {
+    let (account_code_fn, _) = account_code(module);
+    let (account_code_size_fn, _) = account_code_size(module);
+
+    let mut function = FunctionBuilder::new(&mut module.types, &[ValType::I32], &[ValType::I32]);
+
+    // Arguments
+    let address_ptr = module.locals.add(ValType::I32);
+
+    // Locals
+    let raw_address_ptr = module.locals.add(ValType::I32);
+    let len = module.locals.add(ValType::I32);
+    let bytes_ptr = module.locals.add(ValType::I32);
+    let vector_ptr = module.locals.add(ValType::I32);
+
+    let mut builder = function
+        .name(NativeFunction::NATIVE_ACCOUNT_CODE.to_owned())
+        .func_body();
+
+    // Addresses are 32 bytes long, left-padded with 12 zeroes
+    builder
+        .local_get(address_ptr)
+        .i32_const(12)
+        .binop(BinaryOp::I32Add)
+        .local_tee(raw_address_ptr)
+        .call(account_code_size_fn)
+        .local_set(len);
+
+    builder
+        .local_get(len)
+        .call(compilation_ctx.allocator)
+        .local_set(bytes_ptr);
+
+    // The host writes at most the size of the code, so the written length is the one we use
+    builder
+        .local_get(raw_address_ptr)
+        .i32_const(0)
+        .local_get(len)
+        .local_get(bytes_ptr)
+        .call(account_code_fn)
+        .local_set(len);
+
+    add_unpack_bytes_instructions(
+        &mut builder,
+        module,
+        compilation_ctx,
+        bytes_ptr,
+        len,
+        vector_ptr,
+    );
+
+    builder.local_get(vector_ptr);
+
+    function.finish(vec![address_ptr], &mut module.funcs)
+}
+
+/// Returns the size in bytes of the code of an account, 0 if it is not a contract.
+///
+/// # Arguments
+/// - address: pointer to the address of the account
+///
+/// # Returns
+/// - u64 size
+pub fn add_native_account_code_size_fn(module: &mut Module) -> FunctionId {
+    let (account_code_size_fn, _) = account_code_size(module);
+
+    let mut function = FunctionBuilder::new(&mut module.types, &[ValType::I32], &[ValType::I64]);
+
+    // Arguments
+    let address_ptr = module.locals.add(ValType::I32);
+
+    let mut builder = function
+        .name(NativeFunction::NATIVE_ACCOUNT_CODE_SIZE.to_owned())
+        .func_body();
+
+    builder
+        .local_get(address_ptr)
+        .i32_const(12)
+        .binop(BinaryOp::I32Add)
+        .call(account_code_size_fn)
+        .unop(UnaryOp::I64ExtendUI32);
+
+    function.finish(vec![address_ptr], &mut module.funcs)
+}
+
+/// Returns the keccak256 hash of the code of an account as a 32 bytes vector<u8>.
+///
+/// # Arguments
+/// - address: pointer to the address of the account
+///
+/// # Returns
+/// - pointer to a vector<u8>
+pub fn add_native_account_codehash_fn(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+) -> FunctionId {
+    let (account_codehash_fn, _) = account_codehash(module);
+
+    let mut function = FunctionBuilder::new(&mut module.types, &[ValType::I32], &[ValType::I32]);
+
+    // Arguments
+    let address_ptr = module.locals.add(ValType::I32);
+
+    // Locals
+    let len = module.locals.add(ValType::I32);
+    let hash_ptr = module.locals.add(ValType::I32);
+    let vector_ptr = module.locals.add(ValType::I32);
+
+    let mut builder = function
+        .name(NativeFunction::NATIVE_ACCOUNT_CODEHASH.to_owned())
+        .func_body();
+
+    builder
+        .i32_const(32)
+        .call(compilation_ctx.allocator)
+        .local_set(hash_ptr);
+
+    builder
+        .local_get(address_ptr)
+        .i32_const(12)
+        .binop(BinaryOp::I32Add)
+        .local_get(hash_ptr)
+        .call(account_codehash_fn);
+
+    builder.i32_const(32).local_set(len);
+    add_unpack_bytes_instructions(
+        &mut builder,
+        module,
+        compilation_ctx,
+        hash_ptr,
+        len,
+        vector_ptr,
+    );
+
+    builder.local_get(vector_ptr);
+
+    function.finish(vec![address_ptr], &mut module.funcs)
+}
+
+/// Returns the address of the contract being executed.
+///
+/// # Returns
+/// - pointer to the address
+pub fn add_native_contract_address_fn(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+) -> FunctionId {
+    let (contract_address_fn, _) = contract_address(module);
+
+    let mut function = FunctionBuilder::new(&mut module.types, &[], &[ValType::I32]);
+
+    let address_ptr = module.locals.add(ValType::I32);
+
+    let mut builder = function
+        .name(NativeFunction::NATIVE_CONTRACT_ADDRESS.to_owned())
+        .func_body();
+
+    builder
+        .i32_const(IAddress::HEAP_SIZE)
+        .call(compilation_ctx.allocator)
+        .local_tee(address_ptr)
+        .i32_const(12)
+        .binop(BinaryOp::I32Add)
+        .call(contract_address_fn)
+        .local_get(address_ptr);
+
+    function.finish(vec![], &mut module.funcs)
+}
+
+/// Returns the hash of the block `n`, the same as Solidity's `blockhash(n)`.
+///
+/// Stylus has no hook to read block hashes, so they are queried from ArbSys' `arbBlockHash` with
+/// a static call. ArbSys only knows the hashes of the 256 most recent blocks and reverts for the
+/// rest, in which case the zero hash is returned, as Solidity does.
+///
+/// # Arguments
+/// - n: u64
+///
+/// # Returns
+/// - pointer to a vector<u8> of 32 elements
+pub fn add_native_block_hash_fn(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+) -> FunctionId {
+    let (static_call_contract_fn, _) = static_call_contract(module);
+    let swap_64_fn = RuntimeFunction::SwapI64Bytes.get(module, None);
+    let copy_return_data_fn = RuntimeFunction::CopyReturnData.get(module, Some(compilation_ctx));
+
+    let selector: [u8; 4] = keccak256("arbBlockHash(uint256)")[..4].try_into().unwrap();
+
+    let mut function = FunctionBuilder::new(&mut module.types, &[ValType::I64], &[ValType::I32]);
+
+    // Arguments
+    let n = module.locals.add(ValType::I64);
+
+    // Locals
+    let address_ptr = module.locals.add(ValType::I32);
+    let calldata_ptr = module.locals.add(ValType::I32);
+    let return_data_len_ptr = module.locals.add(ValType::I32);
+    let hash_ptr = module.locals.add(ValType::I32);
+    let len = module.locals.add(ValType::I32);
+    let vector_ptr = module.locals.add(ValType::I32);
+
+    let mut builder = function
+        .name(NativeFunction::NATIVE_BLOCK_HASH.to_owned())
+        .func_body();
+
+    // The address is 20 bytes long, with ArbSys' number in the last one
+    builder
+        .i32_const(20)
+        .call(compilation_ctx.allocator)
+        .local_tee(address_ptr)
+        .i32_const(0)
+        .i32_const(20)
+        .memory_fill(compilation_ctx.memory_id);
+
+    builder
+        .local_get(address_ptr)
+        .i32_const(ARB_SYS_ADDRESS)
+        .store(
+            compilation_ctx.memory_id,
+            StoreKind::I32_8 { atomic: false },
+            MemArg {
+                align: 0,
+                offset: 19,
+            },
+        );
+
+    // [selector: 4 bytes][n as a big-endian uint256: 32 bytes]
+    builder
+        .i32_const(36)
+        .call(compilation_ctx.allocator)
+        .local_tee(calldata_ptr)
+        .i32_const(0)
+        .i32_const(36)
+        .memory_fill(compilation_ctx.memory_id);
+
+    builder
+        .local_get(calldata_ptr)
+        .i32_const(i32::from_le_bytes(selector))
+        .store(
+            compilation_ctx.memory_id,
+            StoreKind::I32 { atomic: false },
+            MemArg {
+                align: 0,
+                offset: 0,
+            },
+        );
+
+    builder
+        .local_get(calldata_ptr)
+        .local_get(n)
+        .call(swap_64_fn)
+        .store(
+            compilation_ctx.memory_id,
+            StoreKind::I64 { atomic: false },
+            MemArg {
+                align: 0,
+                offset: 28,
+            },
+        );
+
+    builder
+        .i32_const(4)
+        .call(compilation_ctx.allocator)
+        .local_set(return_data_len_ptr);
+
+    // ArbSys does not read the storage, so the cache does not need to be flushed
+    builder
+        .local_get(address_ptr)
+        .local_get(calldata_ptr)
+        .i32_const(36)
+        // Forward all the gas available
+        .i64_const(-1)
+        .local_get(return_data_len_ptr)
+        .call(static_call_contract_fn)
+        .if_else(
+            None,
+            |reverted| {
+                reverted
+                    .i32_const(32)
+                    .call(compilation_ctx.allocator)
+                    .local_tee(hash_ptr)
+                    .i32_const(0)
+                    .i32_const(32)
+                    .memory_fill(compilation_ctx.memory_id);
+            },
+            |succeeded| {
+                succeeded
+                    .i32_const(0)
+                    .i32_const(32)
+                    .call(copy_return_data_fn)
+                    .local_set(hash_ptr);
+            },
+        );
+
+    builder.i32_const(32).local_set(len);
+
+    add_unpack_bytes_instructions(
+        &mut builder,
+        module,
+        compilation_ctx,
+        hash_ptr,
+        len,
+        vector_ptr,
+    );
+
+    builder.local_get(vector_ptr);
+
+    function.finish(vec![n], &mut module.funcs)
+}
@@ -0,0 +1,72 @@
+//! This module contains the hash functions computed by the host.
+use super::{
+    NativeFunction,
+    bytes::{add_pack_bytes_instructions, add_unpack_bytes_instructions},
+};
+use crate::{CompilationContext, hostio::host_functions::native_keccak256};
+use walrus::{FunctionBuilder, FunctionId, Module, ValType};
+
+/// Computes the keccak256 hash of the data.
+///
+/// # Arguments
+/// - data: pointer to a vector<u8>
+///
+/// # Returns
+/// - pointer to a vector<u8> of 32 elements
+pub fn add_native_keccak256_fn(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+) -> FunctionId {
+    let (native_keccak_fn, _) = native_keccak256(module);
+
+    let mut function = FunctionBuilder::new(&mut module.types, &[ValType::I32], &[ValType::I32]);
+
+    // Arguments
+    let data_ptr = module.locals.add(ValType::I32);
+
+    // Locals
+    let data_len = module.locals.add(ValType::I32);
+    let data_bytes_ptr = module.locals.add(ValType::I32);
+    let hash_ptr = module.locals.add(ValType::I32);
+    let len = module.locals.add(ValType::I32);
+    let vector_ptr = module.locals.add(ValType::I32);
+
+    let mut builder = function
+        .name(NativeFunction::NATIVE_KECCAK256.to_owned())
+        .func_body();
+
+    add_pack_bytes_instructions(
+        &mut builder,
+        module,
+        compilation_ctx,
+        data_ptr,
+        data_len,
+        data_bytes_ptr,
+    );
+
+    builder
+        .i32_const(32)
+        .call(compilation_ctx.allocator)
+        .local_set(hash_ptr);
+
+    builder
+        .local_get(data_bytes_ptr)
+        .local_get(data_len)
+        .local_get(hash_ptr)
+        .call(native_keccak_fn);
+
+    builder.i32_const(32).local_set(len);
+
+    add_unpack_bytes_instructions(
+        &mut builder,
+        module,
+        compilation_ctx,
+        hash_ptr,
+        len,
+        vector_ptr,
+    );
+
+    builder.local_get(vector_ptr);
+
+    function.finish(vec![data_ptr], &mut module.funcs)
+}
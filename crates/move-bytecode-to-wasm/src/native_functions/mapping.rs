@@ -0,0 +1,526 @@
+//! Native functions of the `stylus::mapping` module.
+//!
+//! Mappings follow the Solidity storage layout: the value corresponding to the key `k` of a
+//! mapping occupying the slot `p` is located at `keccak256(h(k) . p)`. If the value is a struct,
+//! its fields are spread across the consecutive slots starting at the derived one.
+//!
+//! To reuse the storage encoding and decoding logic, single values are treated as if they were a
+//! struct with only one field.
+use std::collections::HashMap;
+
+use move_binary_format::file_format::StructDefinitionIndex;
+use walrus::{
+    FunctionBuilder, FunctionId, InstrSeqBuilder, LocalId, Module, ValType,
+    ir::{BinaryOp, LoadKind, MemArg, StoreKind},
+};
+
+use crate::{
+    CompilationContext,
+    data::DATA_SLOT_DATA_PTR_OFFSET,
+    get_generic_function_name,
+    runtime::RuntimeFunction,
+    storage::encoding::{
+        add_clear_storage_struct_instructions,
+        add_encode_and_save_into_storage_struct_instructions,
+        add_read_and_decode_storage_struct_instructions, struct_storage_slots,
+    },
+    translation::intermediate_types::{IntermediateType, structs::IStruct},
+    wasm_builder_extensions::WasmBuilderExtension,
+};
+
+use super::NativeFunction;
+
+/// Adds a function that checks if there is a non-zero value saved under the given key. Every slot
+/// the value occupies is checked, so a struct whose first slot is zero is still found.
+///
+/// Arguments:
+/// - mapping reference
+/// - key
+///
+/// Returns:
+/// - 1 if a value is found, 0 otherwise
+pub fn add_mapping_contains_fn(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+    key_type: &IntermediateType,
+    value_type: &IntermediateType,
+) -> FunctionId {
+    let name = get_generic_function_name(
+        NativeFunction::NATIVE_MAPPING_CONTAINS,
+        &[key_type, value_type],
+    );
+    if let Some(function) = module.funcs.by_name(&name) {
+        return function;
+    };
+
+    let is_zero_fn = RuntimeFunction::IsZero.get(module, Some(compilation_ctx));
+    let storage_load = RuntimeFunction::StorageCachedLoad.get(module, Some(compilation_ctx));
+
+    let mut function = FunctionBuilder::new(
+        &mut module.types,
+        &[ValType::I32, ValType::from(key_type)],
+        &[ValType::I32],
+    );
+    let mut builder = function.name(name).func_body();
+
+    // Arguments
+    let mapping_ref = module.locals.add(ValType::I32);
+    let key = module.locals.add(ValType::from(key_type));
+
+    let slot_ptr = add_derive_value_slot_instructions(
+        &mut builder,
+        module,
+        compilation_ctx,
+        mapping_ref,
+        key,
+        key_type,
+    );
+
+    let slots = struct_storage_slots(&value_wrapper_struct(value_type), compilation_ctx) as i32;
+    if slots == 1 {
+        builder
+            .local_get(slot_ptr)
+            .i32_const(DATA_SLOT_DATA_PTR_OFFSET)
+            .call(storage_load);
+
+        builder
+            .i32_const(DATA_SLOT_DATA_PTR_OFFSET)
+            .i32_const(32)
+            .call(is_zero_fn)
+            .negate();
+    } else {
+        let load_slots_fn =
+            RuntimeFunction::StorageCachedLoadSlots.get(module, Some(compilation_ctx));
+        let slots_ptr = module.locals.add(ValType::I32);
+
+        builder
+            .i32_const(slots * 32)
+            .call(compilation_ctx.allocator)
+            .local_set(slots_ptr);
+
+        // The derived slot is not used afterwards, so it can be advanced in place
+        builder
+            .local_get(slot_ptr)
+            .local_get(slots_ptr)
+            .i32_const(slots)
+            .call(load_slots_fn);
+
+        builder
+            .local_get(slots_ptr)
+            .i32_const(slots * 32)
+            .call(is_zero_fn)
+            .negate();
+    }
+
+    function.finish(vec![mapping_ref, key], &mut module.funcs)
+}
+
+/// Adds a function that reads and decodes the value saved under the given key.
+///
+/// Arguments:
+/// - mapping reference
+/// - key
+///
+/// Returns:
+/// - the decoded value
+pub fn add_mapping_get_fn(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+    key_type: &IntermediateType,
+    value_type: &IntermediateType,
+) -> FunctionId {
+    let name =
+        get_generic_function_name(NativeFunction::NATIVE_MAPPING_GET, &[key_type, value_type]);
+    if let Some(function) = module.funcs.by_name(&name) {
+        return function;
+    };
+
+    let mut function = FunctionBuilder::new(
+        &mut module.types,
+        &[ValType::I32, ValType::from(key_type)],
+        &[ValType::from(value_type)],
+    );
+    let mut builder = function.name(name).func_body();
+
+    // Arguments
+    let mapping_ref = module.locals.add(ValType::I32);
+    let key = module.locals.add(ValType::from(key_type));
+
+    let slot_ptr = add_derive_value_slot_instructions(
+        &mut builder,
+        module,
+        compilation_ctx,
+        mapping_ref,
+        key,
+        key_type,
+    );
+
+    let (wrapper_ptr, _) = add_read_and_decode_storage_struct_instructions(
+        module,
+        &mut builder,
+        compilation_ctx,
+        slot_ptr,
+        &value_wrapper_struct(value_type),
+        false,
+        0,
+    );
+
+    add_unwrap_value_instructions(&mut builder, compilation_ctx, wrapper_ptr, value_type);
+
+    function.finish(vec![mapping_ref, key], &mut module.funcs)
+}
+
+/// Adds a function that encodes and saves a value under the given key.
+///
+/// Arguments:
+/// - mapping mutable reference
+/// - key
+/// - value
+pub fn add_mapping_set_fn(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+    key_type: &IntermediateType,
+    value_type: &IntermediateType,
+) -> FunctionId {
+    let name =
+        get_generic_function_name(NativeFunction::NATIVE_MAPPING_SET, &[key_type, value_type]);
+    if let Some(function) = module.funcs.by_name(&name) {
+        return function;
+    };
+
+    let mut function = FunctionBuilder::new(
+        &mut module.types,
+        &[
+            ValType::I32,
+            ValType::from(key_type),
+            ValType::from(value_type),
+        ],
+        &[],
+    );
+    let mut builder = function.name(name).func_body();
+
+    // Arguments
+    let mapping_ref = module.locals.add(ValType::I32);
+    let key = module.locals.add(ValType::from(key_type));
+    let value = module.locals.add(ValType::from(value_type));
+
+    let slot_ptr = add_derive_value_slot_instructions(
+        &mut builder,
+        module,
+        compilation_ctx,
+        mapping_ref,
+        key,
+        key_type,
+    );
+
+    // Boxing the value gives us the same memory layout as a struct with a single field
+    let wrapper_ptr = module.locals.add(ValType::I32);
+    builder.local_get(value);
+    value_type.box_local_instructions(module, &mut builder, compilation_ctx, wrapper_ptr);
+
+    // Wipe the slot data placeholder, the first slot is not wiped by the encoding function
+    builder
+        .i32_const(DATA_SLOT_DATA_PTR_OFFSET)
+        .i32_const(0)
+        .i32_const(32)
+        .memory_fill(compilation_ctx.memory_id);
+
+    add_encode_and_save_into_storage_struct_instructions(
+        module,
+        &mut builder,
+        compilation_ctx,
+        wrapper_ptr,
+        slot_ptr,
+        &value_wrapper_struct(value_type),
+        0,
+    );
+
+    function.finish(vec![mapping_ref, key, value], &mut module.funcs)
+}
+
+/// Adds a function that removes the value saved under the given key, writing zero to every slot it
+/// occupies.
+///
+/// Arguments:
+/// - mapping mutable reference
+/// - key
+pub fn add_mapping_remove_fn(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+    key_type: &IntermediateType,
+    value_type: &IntermediateType,
+) -> FunctionId {
+    let name = get_generic_function_name(
+        NativeFunction::NATIVE_MAPPING_REMOVE,
+        &[key_type, value_type],
+    );
+    if let Some(function) = module.funcs.by_name(&name) {
+        return function;
+    };
+
+    let mut function = FunctionBuilder::new(
+        &mut module.types,
+        &[ValType::I32, ValType::from(key_type)],
+        &[],
+    );
+    let mut builder = function.name(name).func_body();
+
+    // Arguments
+    let mapping_ref = module.locals.add(ValType::I32);
+    let key = module.locals.add(ValType::from(key_type));
+
+    let slot_ptr = add_derive_value_slot_instructions(
+        &mut builder,
+        module,
+        compilation_ctx,
+        mapping_ref,
+        key,
+        key_type,
+    );
+
+    add_clear_storage_struct_instructions(
+        module,
+        &mut builder,
+        compilation_ctx,
+        slot_ptr,
+        &value_wrapper_struct(value_type),
+        0,
+    );
+
+    function.finish(vec![mapping_ref, key], &mut module.funcs)
+}
+
+/// Adds a function that returns the inner mapping saved under the given key. The inner mapping
+/// occupies the slot derived from the key, so the returned `Mapping` struct just points to it.
+///
+/// Arguments:
+/// - mapping reference
+/// - key
+///
+/// Returns:
+/// - pointer to the inner `Mapping` struct
+pub fn add_mapping_borrow_mapping_fn(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+    key_type: &IntermediateType,
+) -> FunctionId {
+    let name =
+        get_generic_function_name(NativeFunction::NATIVE_MAPPING_BORROW_MAPPING, &[key_type]);
+    if let Some(function) = module.funcs.by_name(&name) {
+        return function;
+    };
+
+    let mut function = FunctionBuilder::new(
+        &mut module.types,
+        &[ValType::I32, ValType::from(key_type)],
+        &[ValType::I32],
+    );
+    let mut builder = function.name(name).func_body();
+
+    // Arguments
+    let mapping_ref = module.locals.add(ValType::I32);
+    let key = module.locals.add(ValType::from(key_type));
+
+    // Locals
+    let inner_mapping_ptr = module.locals.add(ValType::I32);
+
+    let slot_ptr = add_derive_value_slot_instructions(
+        &mut builder,
+        module,
+        compilation_ctx,
+        mapping_ref,
+        key,
+        key_type,
+    );
+
+    // Mapping { slot: <derived slot> }
+    builder
+        .i32_const(4)
+        .call(compilation_ctx.allocator)
+        .local_tee(inner_mapping_ptr)
+        .local_get(slot_ptr)
+        .store(
+            compilation_ctx.memory_id,
+            StoreKind::I32 { atomic: false },
+            MemArg {
+                align: 0,
+                offset: 0,
+            },
+        );
+
+    builder.local_get(inner_mapping_ptr);
+
+    function.finish(vec![mapping_ref, key], &mut module.funcs)
+}
+
+/// Adds the instructions to compute the slot where the value corresponding to `key` is saved.
+///
+/// The key is padded to 32 bytes and encoded in big-endian, as Solidity does for value types.
+///
+/// # Returns
+/// local holding a pointer to the derived slot
+pub fn add_derive_value_slot_instructions(
+    builder: &mut InstrSeqBuilder,
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+    mapping_ref: LocalId,
+    key: LocalId,
+    key_type: &IntermediateType,
+) -> LocalId {
+    let derive_mapping_slot_fn =
+        RuntimeFunction::DeriveMappingSlot.get(module, Some(compilation_ctx));
+
+    let key_ptr = module.locals.add(ValType::I32);
+    let derived_slot_ptr = module.locals.add(ValType::I32);
+
+    builder
+        .i32_const(32)
+        .call(compilation_ctx.allocator)
+        .local_set(key_ptr);
+
+    match key_type {
+        IntermediateType::IBool
+        | IntermediateType::IU8
+        | IntermediateType::IU16
+        | IntermediateType::IU32 => {
+            let swap_fn = RuntimeFunction::SwapI32Bytes.get(module, None);
+            builder
+                .local_get(key_ptr)
+                .local_get(key)
+                .call(swap_fn)
+                .store(
+                    compilation_ctx.memory_id,
+                    StoreKind::I32 { atomic: false },
+                    MemArg {
+                        align: 0,
+                        offset: 28,
+                    },
+                );
+        }
+        IntermediateType::IU64 => {
+            let swap_fn = RuntimeFunction::SwapI64Bytes.get(module, None);
+            builder
+                .local_get(key_ptr)
+                .local_get(key)
+                .call(swap_fn)
+                .store(
+                    compilation_ctx.memory_id,
+                    StoreKind::I64 { atomic: false },
+                    MemArg {
+                        align: 0,
+                        offset: 24,
+                    },
+                );
+        }
+        IntermediateType::IU128 => {
+            let swap_fn = RuntimeFunction::SwapI128Bytes.get(module, Some(compilation_ctx));
+            builder
+                .local_get(key)
+                .local_get(key_ptr)
+                .i32_const(16)
+                .binop(BinaryOp::I32Add)
+                .call(swap_fn);
+        }
+        IntermediateType::IU256 => {
+            let swap_fn = RuntimeFunction::SwapI256Bytes.get(module, Some(compilation_ctx));
+            builder.local_get(key).local_get(key_ptr).call(swap_fn);
+        }
+        // Addresses are already saved as 32 bytes big-endian values
+        IntermediateType::IAddress => {
+            builder
+                .local_get(key_ptr)
+                .local_get(key)
+                .i32_const(32)
+                .memory_copy(compilation_ctx.memory_id, compilation_ctx.memory_id);
+        }
+        _ => panic!("type {key_type:?} can't be used as a mapping key"),
+    }
+
+    // The reference points to the mapping struct pointer, and the mapping's first field points
+    // to the slot number
+    builder
+        .local_get(mapping_ref)
+        .load(
+            compilation_ctx.memory_id,
+            LoadKind::I32 { atomic: false },
+            MemArg {
+                align: 0,
+                offset: 0,
+            },
+        )
+        .load(
+            compilation_ctx.memory_id,
+            LoadKind::I32 { atomic: false },
+            MemArg {
+                align: 0,
+                offset: 0,
+            },
+        );
+
+    builder
+        .local_get(key_ptr)
+        .i32_const(32)
+        .call(compilation_ctx.allocator)
+        .local_tee(derived_slot_ptr)
+        .call(derive_mapping_slot_fn);
+
+    derived_slot_ptr
+}
+
+/// Adds the instructions to leave in the stack the value wrapped in a single field struct (see
+/// `value_wrapper_struct`), as it would be represented in a local.
+pub fn add_unwrap_value_instructions(
+    builder: &mut InstrSeqBuilder,
+    compilation_ctx: &CompilationContext,
+    wrapper_ptr: LocalId,
+    value_type: &IntermediateType,
+) {
+    builder.local_get(wrapper_ptr).load(
+        compilation_ctx.memory_id,
+        LoadKind::I32 { atomic: false },
+        MemArg {
+            align: 0,
+            offset: 0,
+        },
+    );
+
+    match value_type {
+        IntermediateType::IBool
+        | IntermediateType::IU8
+        | IntermediateType::IU16
+        | IntermediateType::IU32 => {
+            builder.load(
+                compilation_ctx.memory_id,
+                LoadKind::I32 { atomic: false },
+                MemArg {
+                    align: 0,
+                    offset: 0,
+                },
+            );
+        }
+        IntermediateType::IU64 => {
+            builder.load(
+                compilation_ctx.memory_id,
+                LoadKind::I64 { atomic: false },
+                MemArg {
+                    align: 0,
+                    offset: 0,
+                },
+            );
+        }
+        // Heap types are represented by the pointer itself
+        _ => {}
+    }
+}
+
+/// Builds the structural information of a struct wrapping a single value of type `value_type`.
+pub fn value_wrapper_struct(value_type: &IntermediateType) -> IStruct {
+    IStruct::new(
+        StructDefinitionIndex::new(0),
+        "MappingValue".to_owned(),
+        vec![(None, value_type.clone())],
+        HashMap::new(),
+        false,
+        false,
+    )
+}
@@ -0,0 +1,138 @@
+//! This module contains the u256 arithmetic functions computed by Stylus' math hooks.
+use super::NativeFunction;
+use crate::{
+    CompilationContext,
+    hostio::host_functions::{math_add_mod, math_mul_mod, math_pow},
+    runtime::RuntimeFunction,
+    translation::intermediate_types::heap_integers::IU256,
+};
+use walrus::{FunctionBuilder, FunctionId, Module, ValType};
+
+/// Computes `(a + b) % modulus` without overflowing in the intermediate sum.
+///
+/// # Arguments
+/// - a: pointer to an u256
+/// - b: pointer to an u256
+/// - modulus: pointer to an u256
+///
+/// # Returns
+/// - pointer to the result
+pub fn add_native_add_mod_fn(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+) -> FunctionId {
+    let (math_add_mod_fn, _) = math_add_mod(module);
+    add_math_hook_fn(
+        module,
+        compilation_ctx,
+        NativeFunction::NATIVE_ADD_MOD,
+        math_add_mod_fn,
+        3,
+    )
+}
+
+/// Computes `(a * b) % modulus` without overflowing in the intermediate product.
+///
+/// # Arguments
+/// - a: pointer to an u256
+/// - b: pointer to an u256
+/// - modulus: pointer to an u256
+///
+/// # Returns
+/// - pointer to the result
+pub fn add_native_mul_mod_fn(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+) -> FunctionId {
+    let (math_mul_mod_fn, _) = math_mul_mod(module);
+    add_math_hook_fn(
+        module,
+        compilation_ctx,
+        NativeFunction::NATIVE_MUL_MOD,
+        math_mul_mod_fn,
+        3,
+    )
+}
+
+/// Computes `base ^ exponent`, wrapping on overflow.
+///
+/// # Arguments
+/// - base: pointer to an u256
+/// - exponent: pointer to an u256
+///
+/// # Returns
+/// - pointer to the result
+pub fn add_native_wrapping_pow_fn(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+) -> FunctionId {
+    let (math_pow_fn, _) = math_pow(module);
+    add_math_hook_fn(
+        module,
+        compilation_ctx,
+        NativeFunction::NATIVE_WRAPPING_POW,
+        math_pow_fn,
+        2,
+    )
+}
+
+/// Builds a function that calls a math hook with `arity` u256 operands. The hooks work with
+/// big-endian values and overwrite the first one with the result, so every operand is swapped to
+/// a new buffer before the call, and the first one is swapped back to a new u256 after it.
+fn add_math_hook_fn(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+    name: &str,
+    hook_fn: FunctionId,
+    arity: usize,
+) -> FunctionId {
+    let swap_256_fn = RuntimeFunction::SwapI256Bytes.get(module, Some(compilation_ctx));
+
+    let mut function = FunctionBuilder::new(
+        &mut module.types,
+        &vec![ValType::I32; arity],
+        &[ValType::I32],
+    );
+
+    // Arguments
+    let operands: Vec<_> = (0..arity)
+        .map(|_| module.locals.add(ValType::I32))
+        .collect();
+
+    // Locals
+    let operands_be: Vec<_> = (0..arity)
+        .map(|_| module.locals.add(ValType::I32))
+        .collect();
+    let result_ptr = module.locals.add(ValType::I32);
+
+    let mut builder = function.name(name.to_owned()).func_body();
+
+    for (operand, operand_be) in operands.iter().zip(&operands_be) {
+        builder
+            .i32_const(IU256::HEAP_SIZE)
+            .call(compilation_ctx.allocator)
+            .local_set(*operand_be);
+        builder
+            .local_get(*operand)
+            .local_get(*operand_be)
+            .call(swap_256_fn);
+    }
+
+    for operand_be in &operands_be {
+        builder.local_get(*operand_be);
+    }
+    builder.call(hook_fn);
+
+    builder
+        .i32_const(IU256::HEAP_SIZE)
+        .call(compilation_ctx.allocator)
+        .local_set(result_ptr);
+    builder
+        .local_get(operands_be[0])
+        .local_get(result_ptr)
+        .call(swap_256_fn);
+
+    builder.local_get(result_ptr);
+
+    function.finish(operands, &mut module.funcs)
+}
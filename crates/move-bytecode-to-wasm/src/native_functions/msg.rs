@@ -0,0 +1,99 @@
+//! This module contains the functions to read the calldata of the current call.
+use super::{NativeFunction, bytes::add_unpack_bytes_instructions};
+use crate::{CompilationContext, data::DATA_CALLDATA_OFFSET};
+use walrus::{
+    FunctionBuilder, FunctionId, Module, ValType,
+    ir::{LoadKind, MemArg},
+};
+
+/// Returns the calldata of the current call, saved by the entrypoint router.
+///
+/// # Returns
+/// - pointer to a vector<u8>
+pub fn add_native_msg_data_fn(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+) -> FunctionId {
+    add_calldata_fn(
+        module,
+        compilation_ctx,
+        NativeFunction::NATIVE_MSG_DATA,
+        None,
+    )
+}
+
+/// Returns the function selector of the current call, the first 4 bytes of its calldata. The
+/// entrypoint router rejects calls with shorter calldata, so it is always 4 bytes long.
+///
+/// # Returns
+/// - pointer to a vector<u8> of 4 elements
+pub fn add_native_msg_sig_fn(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+) -> FunctionId {
+    add_calldata_fn(
+        module,
+        compilation_ctx,
+        NativeFunction::NATIVE_MSG_SIG,
+        Some(4),
+    )
+}
+
+/// Builds a function that copies the first `len` bytes of the calldata to a new vector<u8>, or
+/// all of them if `len` is `None`.
+fn add_calldata_fn(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+    name: &str,
+    len: Option<i32>,
+) -> FunctionId {
+    let mut function = FunctionBuilder::new(&mut module.types, &[], &[ValType::I32]);
+
+    // Locals
+    let calldata_ptr = module.locals.add(ValType::I32);
+    let calldata_len = module.locals.add(ValType::I32);
+    let vector_ptr = module.locals.add(ValType::I32);
+
+    let mut builder = function.name(name.to_owned()).func_body();
+
+    builder
+        .i32_const(DATA_CALLDATA_OFFSET)
+        .load(
+            compilation_ctx.memory_id,
+            LoadKind::I32 { atomic: false },
+            MemArg {
+                align: 0,
+                offset: 0,
+            },
+        )
+        .local_set(calldata_ptr);
+
+    if let Some(len) = len {
+        builder.i32_const(len).local_set(calldata_len);
+    } else {
+        builder
+            .i32_const(DATA_CALLDATA_OFFSET)
+            .load(
+                compilation_ctx.memory_id,
+                LoadKind::I32 { atomic: false },
+                MemArg {
+                    align: 0,
+                    offset: 4,
+                },
+            )
+            .local_set(calldata_len);
+    }
+
+    add_unpack_bytes_instructions(
+        &mut builder,
+        module,
+        compilation_ctx,
+        calldata_ptr,
+        calldata_len,
+        vector_ptr,
+    );
+
+    builder.local_get(vector_ptr);
+
+    function.finish(vec![], &mut module.funcs)
+}
@@ -1,10 +1,8 @@
 use super::NativeFunction;
 use crate::{
     CompilationContext,
-    hostio::host_functions::{
-        block_number, block_timestamp, emit_log, native_keccak256, storage_cache_bytes32,
-        storage_flush_cache, storage_load_bytes32,
-    },
+    hostio::host_functions::{block_number, block_timestamp, emit_log, native_keccak256},
+    runtime::RuntimeFunction,
     translation::intermediate_types::address::IAddress,
     utils::keccak_string_to_memory,
 };
@@ -20,9 +18,8 @@ pub fn add_native_fresh_id_fn(
     let (native_keccak, _) = native_keccak256(module);
     let (block_number, _) = block_number(module);
     let (block_timestamp, _) = block_timestamp(module);
-    let (storage_load_fn, _) = storage_load_bytes32(module);
-    let (storage_cache_fn, _) = storage_cache_bytes32(module);
-    let (storage_flush_cache_fn, _) = storage_flush_cache(module);
+    let storage_load_fn = RuntimeFunction::StorageCachedLoad.get(module, Some(compilation_ctx));
+    let storage_cache_fn = RuntimeFunction::StorageCachedStore.get(module, Some(compilation_ctx));
     let (emit_log_fn, _) = emit_log(module);
 
     let mut function = FunctionBuilder::new(&mut module.types, &[], &[ValType::I32]);
@@ -167,13 +164,11 @@ pub fn add_native_fresh_id_fn(
         .local_get(id_ptr)
         .call(native_keccak);
 
-    // Update storage, flushing the cache
+    // Update storage
     builder
         .local_get(counter_key_ptr)
         .local_get(counter_value_ptr)
-        .call(storage_cache_fn)
-        .i32_const(1)
-        .call(storage_flush_cache_fn);
+        .call(storage_cache_fn);
 
     // Emit log with the ID
     builder
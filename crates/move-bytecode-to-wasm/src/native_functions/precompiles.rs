@@ -0,0 +1,226 @@
+//! This module contains the functions computed by the EVM precompiles.
+use super::{
+    NativeFunction,
+    bytes::{add_pack_bytes_instructions, add_unpack_bytes_instructions},
+};
+use crate::{
+    CompilationContext, runtime::RuntimeFunction,
+    translation::intermediate_types::address::IAddress,
+};
+use walrus::{
+    FunctionBuilder, FunctionId, Module, ValType,
+    ir::{BinaryOp, MemArg, StoreKind},
+};
+
+/// Address of the precompile that recovers the signer of a message
+const ECRECOVER_PRECOMPILE: i32 = 0x01;
+/// Address of the precompile that computes the SHA-256 hash of its input
+const SHA256_PRECOMPILE: i32 = 0x02;
+/// Address of the precompile that computes the RIPEMD-160 hash of its input
+const RIPEMD160_PRECOMPILE: i32 = 0x03;
+
+/// Recovers the address that signed a message hash with the secp256k1 signature (v, r, s), the
+/// same as Solidity's `ecrecover`.
+///
+/// The precompile receives the hash, v, r and s as 32 bytes words, and returns the address
+/// left-padded to 32 bytes, the same layout as a Move address. If the signature is invalid it
+/// returns no data, and the zeroed output is the zero address.
+///
+/// # Arguments
+/// - hash: pointer to a vector<u8> of 32 elements
+/// - v: u8
+/// - r: pointer to a vector<u8> of 32 elements
+/// - s: pointer to a vector<u8> of 32 elements
+///
+/// # Returns
+/// - pointer to the address
+pub fn add_native_ecrecover_fn(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+) -> FunctionId {
+    let call_precompile_fn = RuntimeFunction::CallPrecompile.get(module, Some(compilation_ctx));
+
+    let mut function = FunctionBuilder::new(
+        &mut module.types,
+        &[ValType::I32, ValType::I32, ValType::I32, ValType::I32],
+        &[ValType::I32],
+    );
+
+    // Arguments
+    let hash_ptr = module.locals.add(ValType::I32);
+    let v = module.locals.add(ValType::I32);
+    let r_ptr = module.locals.add(ValType::I32);
+    let s_ptr = module.locals.add(ValType::I32);
+
+    // Locals
+    let input_ptr = module.locals.add(ValType::I32);
+    let len = module.locals.add(ValType::I32);
+    let bytes_ptr = module.locals.add(ValType::I32);
+    let address_ptr = module.locals.add(ValType::I32);
+
+    let mut builder = function
+        .name(NativeFunction::NATIVE_ECRECOVER.to_owned())
+        .func_body();
+
+    // [hash: 32 bytes][v: 32 bytes][r: 32 bytes][s: 32 bytes]
+    builder
+        .i32_const(128)
+        .call(compilation_ctx.allocator)
+        .local_tee(input_ptr)
+        .i32_const(0)
+        .i32_const(128)
+        .memory_fill(compilation_ctx.memory_id);
+
+    // The words are 32 bytes long, which is checked by the Move wrapper
+    for (word_ptr, offset) in [(hash_ptr, 0), (r_ptr, 64), (s_ptr, 96)] {
+        add_pack_bytes_instructions(
+            &mut builder,
+            module,
+            compilation_ctx,
+            word_ptr,
+            len,
+            bytes_ptr,
+        );
+
+        builder
+            .local_get(input_ptr)
+            .i32_const(offset)
+            .binop(BinaryOp::I32Add)
+            .local_get(bytes_ptr)
+            .local_get(len)
+            .memory_copy(compilation_ctx.memory_id, compilation_ctx.memory_id);
+    }
+
+    // v is big-endian, so it goes in the last byte of its word
+    builder.local_get(input_ptr).local_get(v).store(
+        compilation_ctx.memory_id,
+        StoreKind::I32_8 { atomic: false },
+        MemArg {
+            align: 0,
+            offset: 63,
+        },
+    );
+
+    builder
+        .i32_const(IAddress::HEAP_SIZE)
+        .call(compilation_ctx.allocator)
+        .local_set(address_ptr);
+
+    builder
+        .local_get(address_ptr)
+        .i32_const(ECRECOVER_PRECOMPILE)
+        .local_get(input_ptr)
+        .i32_const(128)
+        .i32_const(IAddress::HEAP_SIZE)
+        .call(call_precompile_fn)
+        .i32_const(IAddress::HEAP_SIZE)
+        .memory_copy(compilation_ctx.memory_id, compilation_ctx.memory_id);
+
+    builder.local_get(address_ptr);
+
+    function.finish(vec![hash_ptr, v, r_ptr, s_ptr], &mut module.funcs)
+}
+
+/// Computes the SHA-256 hash of the data.
+///
+/// # Arguments
+/// - data: pointer to a vector<u8>
+///
+/// # Returns
+/// - pointer to a vector<u8> of 32 elements
+pub fn add_native_sha256_fn(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+) -> FunctionId {
+    add_hash_precompile_fn(
+        module,
+        compilation_ctx,
+        NativeFunction::NATIVE_SHA256,
+        SHA256_PRECOMPILE,
+        0,
+        32,
+    )
+}
+
+/// Computes the RIPEMD-160 hash of the data. The precompile returns it left-padded to 32 bytes,
+/// and only the last 20 are kept.
+///
+/// # Arguments
+/// - data: pointer to a vector<u8>
+///
+/// # Returns
+/// - pointer to a vector<u8> of 20 elements
+pub fn add_native_ripemd160_fn(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+) -> FunctionId {
+    add_hash_precompile_fn(
+        module,
+        compilation_ctx,
+        NativeFunction::NATIVE_RIPEMD160,
+        RIPEMD160_PRECOMPILE,
+        12,
+        20,
+    )
+}
+
+/// Builds a function that hashes a vector<u8> with a precompile that returns a 32 bytes word, and
+/// returns the `hash_len` bytes of the word starting at `hash_offset` as a new vector<u8>.
+fn add_hash_precompile_fn(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+    name: &str,
+    precompile: i32,
+    hash_offset: i32,
+    hash_len: i32,
+) -> FunctionId {
+    let call_precompile_fn = RuntimeFunction::CallPrecompile.get(module, Some(compilation_ctx));
+
+    let mut function = FunctionBuilder::new(&mut module.types, &[ValType::I32], &[ValType::I32]);
+
+    // Arguments
+    let data_ptr = module.locals.add(ValType::I32);
+
+    // Locals
+    let data_len = module.locals.add(ValType::I32);
+    let data_bytes_ptr = module.locals.add(ValType::I32);
+    let hash_ptr = module.locals.add(ValType::I32);
+    let len = module.locals.add(ValType::I32);
+    let vector_ptr = module.locals.add(ValType::I32);
+
+    let mut builder = function.name(name.to_owned()).func_body();
+
+    add_pack_bytes_instructions(
+        &mut builder,
+        module,
+        compilation_ctx,
+        data_ptr,
+        data_len,
+        data_bytes_ptr,
+    );
+
+    builder
+        .i32_const(precompile)
+        .local_get(data_bytes_ptr)
+        .local_get(data_len)
+        .i32_const(32)
+        .call(call_precompile_fn)
+        .i32_const(hash_offset)
+        .binop(BinaryOp::I32Add)
+        .local_set(hash_ptr);
+
+    builder.i32_const(hash_len).local_set(len);
+
+    add_unpack_bytes_instructions(
+        &mut builder,
+        module,
+        compilation_ctx,
+        hash_ptr,
+        len,
+        vector_ptr,
+    );
+
+    builder.local_get(vector_ptr);
+
+    function.finish(vec![data_ptr], &mut module.funcs)
+}
@@ -0,0 +1,512 @@
+//! Native functions of the `stylus::storage_vec` module.
+//!
+//! A `StorageVec` follows the Solidity storage layout for dynamic arrays: the slot `p` it occupies
+//! holds its length and the elements are saved starting at `keccak256(p)`. Every element is
+//! encoded as a struct with a single field (see `mapping::value_wrapper_struct`) starting at its
+//! own slot, so all of them occupy the same number of slots `n` and the element `i` starts at
+//! `keccak256(p) + i * n`.
+//!
+//! As with tables, references to elements are obtained by decoding them into memory, and elements
+//! borrowed mutably are written back into the storage once the borrow ends. Every native of this
+//! module writes back the pending values before touching the storage.
+use walrus::{
+    FunctionBuilder, FunctionId, InstrSeqBuilder, LocalId, Module, ValType,
+    ir::{BinaryOp, LoadKind, MemArg, StoreKind},
+};
+
+use crate::{
+    CompilationContext,
+    data::DATA_SLOT_DATA_PTR_OFFSET,
+    get_generic_function_name,
+    hostio::host_functions,
+    runtime::{RuntimeFunction, add_register_write_back_instructions},
+    storage::encoding::{
+        add_clear_storage_struct_instructions,
+        add_encode_and_save_into_storage_struct_instructions,
+        add_read_and_decode_storage_struct_instructions, struct_storage_slots,
+    },
+    translation::intermediate_types::IntermediateType,
+};
+
+use super::{
+    NativeFunction,
+    mapping::{add_unwrap_value_instructions, value_wrapper_struct},
+};
+
+/// Adds a function that returns the length of the vector.
+///
+/// Arguments:
+/// - vector reference
+///
+/// Returns:
+/// - the length (u64)
+pub fn add_storage_vec_length_fn(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+    element_type: &IntermediateType,
+) -> FunctionId {
+    let name =
+        get_generic_function_name(NativeFunction::NATIVE_STORAGE_VEC_LENGTH, &[element_type]);
+    if let Some(function) = module.funcs.by_name(&name) {
+        return function;
+    };
+
+    let mut function = FunctionBuilder::new(&mut module.types, &[ValType::I32], &[ValType::I64]);
+    let mut builder = function.name(name).func_body();
+
+    // Arguments
+    let vec_ref = module.locals.add(ValType::I32);
+
+    let vec_slot_ptr = add_vec_slot_instructions(&mut builder, module, compilation_ctx, vec_ref);
+    let length = add_read_length_instructions(&mut builder, module, compilation_ctx, vec_slot_ptr);
+    builder.local_get(length);
+
+    function.finish(vec![vec_ref], &mut module.funcs)
+}
+
+/// Adds a function that saves a value at the end of the vector.
+///
+/// Arguments:
+/// - vector mutable reference
+/// - value
+pub fn add_storage_vec_push_back_fn(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+    element_type: &IntermediateType,
+) -> FunctionId {
+    let name = get_generic_function_name(
+        NativeFunction::NATIVE_STORAGE_VEC_PUSH_BACK,
+        &[element_type],
+    );
+    if let Some(function) = module.funcs.by_name(&name) {
+        return function;
+    };
+
+    let write_back_fn = RuntimeFunction::StorageWriteBack.get(module, Some(compilation_ctx));
+
+    let mut function = FunctionBuilder::new(
+        &mut module.types,
+        &[ValType::I32, ValType::from(element_type)],
+        &[],
+    );
+    let mut builder = function.name(name).func_body();
+
+    // Arguments
+    let vec_ref = module.locals.add(ValType::I32);
+    let value = module.locals.add(ValType::from(element_type));
+
+    builder.call(write_back_fn);
+
+    let vec_slot_ptr = add_vec_slot_instructions(&mut builder, module, compilation_ctx, vec_ref);
+    let length = add_read_length_instructions(&mut builder, module, compilation_ctx, vec_slot_ptr);
+
+    let element_slot_ptr = add_derive_element_slot_instructions(
+        &mut builder,
+        module,
+        compilation_ctx,
+        vec_slot_ptr,
+        length,
+        element_type,
+    );
+
+    // Boxing the value gives us the same memory layout as a struct with a single field
+    let wrapper_ptr = module.locals.add(ValType::I32);
+    builder.local_get(value);
+    element_type.box_local_instructions(module, &mut builder, compilation_ctx, wrapper_ptr);
+
+    // Wipe the slot data placeholder, the first slot is not wiped by the encoding function
+    builder
+        .i32_const(DATA_SLOT_DATA_PTR_OFFSET)
+        .i32_const(0)
+        .i32_const(32)
+        .memory_fill(compilation_ctx.memory_id);
+
+    add_encode_and_save_into_storage_struct_instructions(
+        module,
+        &mut builder,
+        compilation_ctx,
+        wrapper_ptr,
+        element_slot_ptr,
+        &value_wrapper_struct(element_type),
+        0,
+    );
+
+    builder
+        .local_get(length)
+        .i64_const(1)
+        .binop(BinaryOp::I64Add);
+    add_write_length_instructions(&mut builder, module, compilation_ctx, vec_slot_ptr);
+
+    function.finish(vec![vec_ref, value], &mut module.funcs)
+}
+
+/// Adds a function that returns a reference to the element at the given index. The index must be
+/// checked against the length by the caller.
+///
+/// If `mutable` is true, the element is written back into the storage once the borrow ends.
+///
+/// Arguments:
+/// - vector reference
+/// - index
+///
+/// Returns:
+/// - reference to the decoded element
+pub fn add_storage_vec_borrow_fn(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+    element_type: &IntermediateType,
+    mutable: bool,
+) -> FunctionId {
+    let base_name = if mutable {
+        NativeFunction::NATIVE_STORAGE_VEC_BORROW_MUT
+    } else {
+        NativeFunction::NATIVE_STORAGE_VEC_BORROW
+    };
+    let name = get_generic_function_name(base_name, &[element_type]);
+    if let Some(function) = module.funcs.by_name(&name) {
+        return function;
+    };
+
+    let write_back_fn = RuntimeFunction::StorageWriteBack.get(module, Some(compilation_ctx));
+
+    let mut function = FunctionBuilder::new(
+        &mut module.types,
+        &[ValType::I32, ValType::I64],
+        &[ValType::I32],
+    );
+    let mut builder = function.name(name).func_body();
+
+    // Arguments
+    let vec_ref = module.locals.add(ValType::I32);
+    let index = module.locals.add(ValType::I64);
+
+    builder.call(write_back_fn);
+
+    let vec_slot_ptr = add_vec_slot_instructions(&mut builder, module, compilation_ctx, vec_ref);
+    let element_slot_ptr = add_derive_element_slot_instructions(
+        &mut builder,
+        module,
+        compilation_ctx,
+        vec_slot_ptr,
+        index,
+        element_type,
+    );
+
+    // Decoding advances the slot pointer, so we keep a copy pointing to the first slot to know
+    // where to write the element back
+    let write_back_slot_ptr = module.locals.add(ValType::I32);
+    if mutable {
+        builder
+            .i32_const(32)
+            .call(compilation_ctx.allocator)
+            .local_tee(write_back_slot_ptr)
+            .local_get(element_slot_ptr)
+            .i32_const(32)
+            .memory_copy(compilation_ctx.memory_id, compilation_ctx.memory_id);
+    }
+
+    // The struct wrapping the element holds a pointer to it, which is exactly how a reference is
+    // represented
+    let (element_ref, _) = add_read_and_decode_storage_struct_instructions(
+        module,
+        &mut builder,
+        compilation_ctx,
+        element_slot_ptr,
+        &value_wrapper_struct(element_type),
+        false,
+        0,
+    );
+
+    if mutable {
+        add_register_write_back_instructions(
+            &mut builder,
+            module,
+            compilation_ctx,
+            element_ref,
+            write_back_slot_ptr,
+            element_type,
+        );
+    }
+
+    builder.local_get(element_ref);
+
+    function.finish(vec![vec_ref, index], &mut module.funcs)
+}
+
+/// Adds a function that removes the last element of the vector and returns it. The slots the
+/// element occupied are cleared. The vector must be checked to not be empty by the caller.
+///
+/// Arguments:
+/// - vector mutable reference
+///
+/// Returns:
+/// - the decoded element
+pub fn add_storage_vec_pop_back_fn(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+    element_type: &IntermediateType,
+) -> FunctionId {
+    let name =
+        get_generic_function_name(NativeFunction::NATIVE_STORAGE_VEC_POP_BACK, &[element_type]);
+    if let Some(function) = module.funcs.by_name(&name) {
+        return function;
+    };
+
+    let write_back_fn = RuntimeFunction::StorageWriteBack.get(module, Some(compilation_ctx));
+
+    let mut function = FunctionBuilder::new(
+        &mut module.types,
+        &[ValType::I32],
+        &[ValType::from(element_type)],
+    );
+    let mut builder = function.name(name).func_body();
+
+    // Arguments
+    let vec_ref = module.locals.add(ValType::I32);
+
+    // Locals
+    let last_index = module.locals.add(ValType::I64);
+    let clear_slot_ptr = module.locals.add(ValType::I32);
+
+    builder.call(write_back_fn);
+
+    let vec_slot_ptr = add_vec_slot_instructions(&mut builder, module, compilation_ctx, vec_ref);
+    let length = add_read_length_instructions(&mut builder, module, compilation_ctx, vec_slot_ptr);
+
+    builder
+        .local_get(length)
+        .i64_const(1)
+        .binop(BinaryOp::I64Sub)
+        .local_set(last_index);
+
+    let element_slot_ptr = add_derive_element_slot_instructions(
+        &mut builder,
+        module,
+        compilation_ctx,
+        vec_slot_ptr,
+        last_index,
+        element_type,
+    );
+
+    // Decoding advances the slot pointer, so we keep a copy pointing to the first slot to know
+    // where to start clearing
+    builder
+        .i32_const(32)
+        .call(compilation_ctx.allocator)
+        .local_tee(clear_slot_ptr)
+        .local_get(element_slot_ptr)
+        .i32_const(32)
+        .memory_copy(compilation_ctx.memory_id, compilation_ctx.memory_id);
+
+    let element_type_wrapper = value_wrapper_struct(element_type);
+    let (wrapper_ptr, _) = add_read_and_decode_storage_struct_instructions(
+        module,
+        &mut builder,
+        compilation_ctx,
+        element_slot_ptr,
+        &element_type_wrapper,
+        false,
+        0,
+    );
+
+    add_clear_storage_struct_instructions(
+        module,
+        &mut builder,
+        compilation_ctx,
+        clear_slot_ptr,
+        &element_type_wrapper,
+        0,
+    );
+
+    builder.local_get(last_index);
+    add_write_length_instructions(&mut builder, module, compilation_ctx, vec_slot_ptr);
+
+    add_unwrap_value_instructions(&mut builder, compilation_ctx, wrapper_ptr, element_type);
+
+    function.finish(vec![vec_ref], &mut module.funcs)
+}
+
+/// Adds the instructions to get the slot occupied by the vector.
+///
+/// # Returns
+/// local holding a pointer to the vector slot
+fn add_vec_slot_instructions(
+    builder: &mut InstrSeqBuilder,
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+    vec_ref: LocalId,
+) -> LocalId {
+    let vec_slot_ptr = module.locals.add(ValType::I32);
+
+    // The reference points to the StorageVec struct pointer, and its first field points to the
+    // slot number
+    builder
+        .local_get(vec_ref)
+        .load(
+            compilation_ctx.memory_id,
+            LoadKind::I32 { atomic: false },
+            MemArg {
+                align: 0,
+                offset: 0,
+            },
+        )
+        .load(
+            compilation_ctx.memory_id,
+            LoadKind::I32 { atomic: false },
+            MemArg {
+                align: 0,
+                offset: 0,
+            },
+        )
+        .local_set(vec_slot_ptr);
+
+    vec_slot_ptr
+}
+
+/// Adds the instructions to read the length saved in the vector slot. The length is saved as a
+/// big-endian u256, as Solidity does.
+///
+/// # Returns
+/// local holding the length (u64)
+fn add_read_length_instructions(
+    builder: &mut InstrSeqBuilder,
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+    vec_slot_ptr: LocalId,
+) -> LocalId {
+    let storage_load = RuntimeFunction::StorageCachedLoad.get(module, Some(compilation_ctx));
+    let swap_fn = RuntimeFunction::SwapI64Bytes.get(module, None);
+
+    let length = module.locals.add(ValType::I64);
+
+    builder
+        .local_get(vec_slot_ptr)
+        .i32_const(DATA_SLOT_DATA_PTR_OFFSET)
+        .call(storage_load);
+
+    builder
+        .i32_const(DATA_SLOT_DATA_PTR_OFFSET)
+        .load(
+            compilation_ctx.memory_id,
+            LoadKind::I64 { atomic: false },
+            MemArg {
+                align: 0,
+                offset: 24,
+            },
+        )
+        .call(swap_fn)
+        .local_set(length);
+
+    length
+}
+
+/// Adds the instructions to save the length in the stack (u64) into the vector slot.
+fn add_write_length_instructions(
+    builder: &mut InstrSeqBuilder,
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+    vec_slot_ptr: LocalId,
+) {
+    let storage_cache = RuntimeFunction::StorageCachedStore.get(module, Some(compilation_ctx));
+    let swap_fn = RuntimeFunction::SwapI64Bytes.get(module, None);
+
+    let length = module.locals.add(ValType::I64);
+    builder.local_set(length);
+
+    builder
+        .i32_const(DATA_SLOT_DATA_PTR_OFFSET)
+        .i32_const(0)
+        .i32_const(32)
+        .memory_fill(compilation_ctx.memory_id);
+
+    builder
+        .i32_const(DATA_SLOT_DATA_PTR_OFFSET)
+        .local_get(length)
+        .call(swap_fn)
+        .store(
+            compilation_ctx.memory_id,
+            StoreKind::I64 { atomic: false },
+            MemArg {
+                align: 0,
+                offset: 24,
+            },
+        );
+
+    builder
+        .local_get(vec_slot_ptr)
+        .i32_const(DATA_SLOT_DATA_PTR_OFFSET)
+        .call(storage_cache);
+}
+
+/// Adds the instructions to compute the first slot of the element at `index`:
+/// `keccak256(p) + index * n`, where `n` is the number of slots every element occupies.
+///
+/// # Returns
+/// local holding a pointer to the derived slot
+fn add_derive_element_slot_instructions(
+    builder: &mut InstrSeqBuilder,
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+    vec_slot_ptr: LocalId,
+    index: LocalId,
+    element_type: &IntermediateType,
+) -> LocalId {
+    let (native_keccak, _) = host_functions::native_keccak256(module);
+    let swap_256_fn = RuntimeFunction::SwapI256Bytes.get(module, Some(compilation_ctx));
+    let add_u256_fn = RuntimeFunction::HeapIntSum.get(module, Some(compilation_ctx));
+
+    let element_slots = struct_storage_slots(&value_wrapper_struct(element_type), compilation_ctx);
+
+    let element_slot_ptr = module.locals.add(ValType::I32);
+    let offset_ptr = module.locals.add(ValType::I32);
+
+    // keccak256(p)
+    builder
+        .i32_const(32)
+        .call(compilation_ctx.allocator)
+        .local_set(element_slot_ptr);
+
+    builder
+        .local_get(vec_slot_ptr)
+        .i32_const(32)
+        .local_get(element_slot_ptr)
+        .call(native_keccak);
+
+    // index * n, as a little-endian u256
+    builder
+        .i32_const(32)
+        .call(compilation_ctx.allocator)
+        .local_tee(offset_ptr)
+        .local_get(index)
+        .i64_const(element_slots as i64)
+        .binop(BinaryOp::I64Mul)
+        .store(
+            compilation_ctx.memory_id,
+            StoreKind::I64 { atomic: false },
+            MemArg {
+                align: 0,
+                offset: 0,
+            },
+        );
+
+    // BE to LE so we can make the addition, and back to BE so we can use the storage functions
+    builder
+        .local_get(element_slot_ptr)
+        .local_get(element_slot_ptr)
+        .call(swap_256_fn);
+
+    builder
+        .local_get(element_slot_ptr)
+        .local_get(offset_ptr)
+        .local_get(element_slot_ptr)
+        .i32_const(32)
+        .call(add_u256_fn)
+        .drop();
+
+    builder
+        .local_get(element_slot_ptr)
+        .local_get(element_slot_ptr)
+        .call(swap_256_fn);
+
+    element_slot_ptr
+}
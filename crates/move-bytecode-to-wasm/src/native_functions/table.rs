@@ -0,0 +1,260 @@
+//! Native functions of the `stylus::table` and `stylus::bag` modules.
+//!
+//! Both collections save their values in a `Mapping`, using the same layout as the mapping
+//! natives. Which keys are present (and, for bags, the type of the value saved under them) is
+//! tracked from the Move side.
+//!
+//! References to values are obtained by decoding them into memory. Values borrowed mutably are
+//! registered to be written back into the storage (see `runtime::storage_write_back`), and every
+//! native of this module writes back the pending values before touching the storage, so they
+//! always read the latest version.
+use walrus::{FunctionBuilder, FunctionId, Module, ValType};
+
+use crate::{
+    CompilationContext, get_generic_function_name,
+    runtime::{RuntimeFunction, add_register_write_back_instructions},
+    storage::{
+        encoding::add_read_and_decode_storage_struct_instructions,
+        type_tag::add_type_tag_instructions,
+    },
+    translation::intermediate_types::IntermediateType,
+};
+
+use super::{
+    NativeFunction,
+    mapping::{
+        add_derive_value_slot_instructions, add_mapping_get_fn, add_mapping_remove_fn,
+        add_mapping_set_fn, value_wrapper_struct,
+    },
+};
+
+/// Adds a function that saves a value under the given key.
+///
+/// Arguments:
+/// - mapping mutable reference
+/// - key
+/// - value
+pub fn add_table_add_fn(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+    key_type: &IntermediateType,
+    value_type: &IntermediateType,
+) -> FunctionId {
+    let name = get_generic_function_name(NativeFunction::NATIVE_TABLE_ADD, &[key_type, value_type]);
+    if let Some(function) = module.funcs.by_name(&name) {
+        return function;
+    };
+
+    let write_back_fn = RuntimeFunction::StorageWriteBack.get(module, Some(compilation_ctx));
+    let mapping_set_fn = add_mapping_set_fn(module, compilation_ctx, key_type, value_type);
+
+    let mut function = FunctionBuilder::new(
+        &mut module.types,
+        &[
+            ValType::I32,
+            ValType::from(key_type),
+            ValType::from(value_type),
+        ],
+        &[],
+    );
+    let mut builder = function.name(name).func_body();
+
+    // Arguments
+    let mapping_ref = module.locals.add(ValType::I32);
+    let key = module.locals.add(ValType::from(key_type));
+    let value = module.locals.add(ValType::from(value_type));
+
+    builder
+        .call(write_back_fn)
+        .local_get(mapping_ref)
+        .local_get(key)
+        .local_get(value)
+        .call(mapping_set_fn);
+
+    function.finish(vec![mapping_ref, key, value], &mut module.funcs)
+}
+
+/// Adds a function that returns a reference to the value saved under the given key.
+///
+/// If `mutable` is true, the value is written back into the storage once the borrow ends.
+///
+/// Arguments:
+/// - mapping reference
+/// - key
+///
+/// Returns:
+/// - reference to the decoded value
+pub fn add_table_borrow_fn(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+    key_type: &IntermediateType,
+    value_type: &IntermediateType,
+    mutable: bool,
+) -> FunctionId {
+    let base_name = if mutable {
+        NativeFunction::NATIVE_TABLE_BORROW_MUT
+    } else {
+        NativeFunction::NATIVE_TABLE_BORROW
+    };
+    let name = get_generic_function_name(base_name, &[key_type, value_type]);
+    if let Some(function) = module.funcs.by_name(&name) {
+        return function;
+    };
+
+    let write_back_fn = RuntimeFunction::StorageWriteBack.get(module, Some(compilation_ctx));
+
+    let mut function = FunctionBuilder::new(
+        &mut module.types,
+        &[ValType::I32, ValType::from(key_type)],
+        &[ValType::I32],
+    );
+    let mut builder = function.name(name).func_body();
+
+    // Arguments
+    let mapping_ref = module.locals.add(ValType::I32);
+    let key = module.locals.add(ValType::from(key_type));
+
+    builder.call(write_back_fn);
+
+    let slot_ptr = add_derive_value_slot_instructions(
+        &mut builder,
+        module,
+        compilation_ctx,
+        mapping_ref,
+        key,
+        key_type,
+    );
+
+    // Decoding advances the slot pointer, so we keep a copy pointing to the first slot to know
+    // where to write the value back
+    let write_back_slot_ptr = module.locals.add(ValType::I32);
+    if mutable {
+        builder
+            .i32_const(32)
+            .call(compilation_ctx.allocator)
+            .local_tee(write_back_slot_ptr)
+            .local_get(slot_ptr)
+            .i32_const(32)
+            .memory_copy(compilation_ctx.memory_id, compilation_ctx.memory_id);
+    }
+
+    // The struct wrapping the value holds a pointer to it, which is exactly how a reference is
+    // represented
+    let (value_ref, _) = add_read_and_decode_storage_struct_instructions(
+        module,
+        &mut builder,
+        compilation_ctx,
+        slot_ptr,
+        &value_wrapper_struct(value_type),
+        false,
+        0,
+    );
+
+    if mutable {
+        add_register_write_back_instructions(
+            &mut builder,
+            module,
+            compilation_ctx,
+            value_ref,
+            write_back_slot_ptr,
+            value_type,
+        );
+    }
+
+    builder.local_get(value_ref);
+
+    function.finish(vec![mapping_ref, key], &mut module.funcs)
+}
+
+/// Adds a function that reads the value saved under the given key and returns it. The slots the
+/// value occupied are cleared.
+///
+/// Arguments:
+/// - mapping mutable reference
+/// - key
+///
+/// Returns:
+/// - the decoded value
+pub fn add_table_remove_fn(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+    key_type: &IntermediateType,
+    value_type: &IntermediateType,
+) -> FunctionId {
+    let name =
+        get_generic_function_name(NativeFunction::NATIVE_TABLE_REMOVE, &[key_type, value_type]);
+    if let Some(function) = module.funcs.by_name(&name) {
+        return function;
+    };
+
+    let write_back_fn = RuntimeFunction::StorageWriteBack.get(module, Some(compilation_ctx));
+    let mapping_get_fn = add_mapping_get_fn(module, compilation_ctx, key_type, value_type);
+    let mapping_remove_fn = add_mapping_remove_fn(module, compilation_ctx, key_type, value_type);
+
+    let mut function = FunctionBuilder::new(
+        &mut module.types,
+        &[ValType::I32, ValType::from(key_type)],
+        &[ValType::from(value_type)],
+    );
+    let mut builder = function.name(name).func_body();
+
+    // Arguments
+    let mapping_ref = module.locals.add(ValType::I32);
+    let key = module.locals.add(ValType::from(key_type));
+
+    builder
+        .call(write_back_fn)
+        .local_get(mapping_ref)
+        .local_get(key)
+        .call(mapping_get_fn);
+
+    // The value is left in the stack, and the slots it occupied are cleared
+    builder
+        .local_get(mapping_ref)
+        .local_get(key)
+        .call(mapping_remove_fn);
+
+    function.finish(vec![mapping_ref, key], &mut module.funcs)
+}
+
+/// Adds a function that returns the tag identifying the type `value_type` inside a bag (see
+/// `storage::type_tag`). Being a hash, it is never zero in practice.
+///
+/// Returns:
+/// - pointer to the tag (u256)
+pub fn add_bag_type_tag_fn(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+    value_type: &IntermediateType,
+) -> FunctionId {
+    let name = get_generic_function_name(NativeFunction::NATIVE_BAG_TYPE_TAG, &[value_type]);
+    if let Some(function) = module.funcs.by_name(&name) {
+        return function;
+    };
+
+    let mut function = FunctionBuilder::new(&mut module.types, &[], &[ValType::I32]);
+    let mut builder = function.name(name).func_body();
+
+    let tag_ptr = add_type_tag_instructions(&mut builder, module, compilation_ctx, value_type);
+    builder.local_get(tag_ptr);
+
+    function.finish(vec![], &mut module.funcs)
+}
+
+/// Adds a function that reads the type tag saved under the given key of a bag.
+pub fn add_bag_get_type_tag_fn(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+    key_type: &IntermediateType,
+) -> FunctionId {
+    add_mapping_get_fn(module, compilation_ctx, key_type, &IntermediateType::IU256)
+}
+
+/// Adds a function that saves the type tag under the given key of a bag.
+pub fn add_bag_set_type_tag_fn(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+    key_type: &IntermediateType,
+) -> FunctionId {
+    add_mapping_set_fn(module, compilation_ctx, key_type, &IntermediateType::IU256)
+}
@@ -2,7 +2,7 @@
 use super::NativeFunction;
 use crate::{
     CompilationContext,
-    hostio::host_functions::{block_basefee, msg_sender, msg_value, tx_gas_price},
+    hostio::host_functions::{block_basefee, block_coinbase, msg_sender, msg_value, tx_gas_price},
     translation::intermediate_types::{address::IAddress, heap_integers::IU256},
 };
 use walrus::{FunctionBuilder, FunctionId, Module, ValType, ir::BinaryOp};
@@ -61,6 +61,32 @@ pub fn add_native_sender_fn(
     function.finish(vec![], &mut module.funcs)
 }
 
+pub fn add_native_block_coinbase_fn(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+) -> FunctionId {
+    let (block_coinbase_function_id, _) = block_coinbase(module);
+
+    let mut function = FunctionBuilder::new(&mut module.types, &[], &[ValType::I32]);
+
+    let address_ptr = module.locals.add(ValType::I32);
+
+    let mut builder = function
+        .name(NativeFunction::NATIVE_BLOCK_COINBASE.to_owned())
+        .func_body();
+
+    builder
+        .i32_const(IAddress::HEAP_SIZE)
+        .call(compilation_ctx.allocator)
+        .local_tee(address_ptr)
+        .i32_const(12)
+        .binop(BinaryOp::I32Add)
+        .call(block_coinbase_function_id)
+        .local_get(address_ptr);
+
+    function.finish(vec![], &mut module.funcs)
+}
+
 define_host_fn_native_fn_wrapper!(
     add_native_msg_value_fn,
     msg_value,
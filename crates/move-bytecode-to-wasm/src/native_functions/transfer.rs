@@ -11,6 +11,7 @@ use crate::{
     },
     get_generic_function_name,
     runtime::RuntimeFunction,
+    storage::namespace::add_objects_root_slot_instructions,
     translation::intermediate_types::IntermediateType,
 };
 
@@ -37,6 +38,10 @@ pub fn add_transfer_object_fn(
     let delete_object_fn =
         RuntimeFunction::DeleteFromStorage.get_generic(module, compilation_ctx, &[itype]);
 
+    let struct_ = compilation_ctx
+        .get_struct_by_intermediate_type(itype)
+        .unwrap();
+
     // Function declaration
     let mut function = FunctionBuilder::new(&mut module.types, &[ValType::I32, ValType::I32], &[]);
     let mut builder = function.name(name).func_body();
@@ -109,6 +114,7 @@ pub fn add_transfer_object_fn(
         .local_set(id_bytes_ptr);
 
     // Calculate the slot number corresponding to the (recipient, struct_id) tuple
+    add_objects_root_slot_instructions(&mut builder, module, compilation_ctx, &struct_);
     builder
         .local_get(recipient_ptr)
         .local_get(id_bytes_ptr)
@@ -143,6 +149,10 @@ pub fn add_share_object_fn(
     let delete_object_fn =
         RuntimeFunction::DeleteFromStorage.get_generic(module, compilation_ctx, &[itype]);
 
+    let struct_ = compilation_ctx
+        .get_struct_by_intermediate_type(itype)
+        .unwrap();
+
     // Function declaration
     let mut function = FunctionBuilder::new(&mut module.types, &[ValType::I32], &[]);
     let mut builder = function.name(name).func_body();
@@ -193,6 +203,7 @@ pub fn add_share_object_fn(
                     .memory_copy(compilation_ctx.memory_id, compilation_ctx.memory_id);
 
                 // Calculate the slot number in the shared objects mapping
+                add_objects_root_slot_instructions(else_, module, compilation_ctx, &struct_);
                 else_
                     .i32_const(DATA_SHARED_OBJECTS_KEY_OFFSET)
                     .local_get(struct_ptr)
@@ -231,6 +242,10 @@ pub fn add_freeze_object_fn(
     let delete_object_fn =
         RuntimeFunction::DeleteFromStorage.get_generic(module, compilation_ctx, &[itype]);
 
+    let struct_ = compilation_ctx
+        .get_struct_by_intermediate_type(itype)
+        .unwrap();
+
     // Function declaration
     let mut function = FunctionBuilder::new(&mut module.types, &[ValType::I32], &[]);
     let mut builder = function.name(name).func_body();
@@ -289,6 +304,7 @@ pub fn add_freeze_object_fn(
                     .memory_copy(compilation_ctx.memory_id, compilation_ctx.memory_id);
 
                 // Calculate the struct slot in the frozen objects mapping
+                add_objects_root_slot_instructions(else_, module, compilation_ctx, &struct_);
                 else_
                     .i32_const(DATA_FROZEN_OBJECTS_KEY_OFFSET)
                     .local_get(struct_ptr)
@@ -0,0 +1,216 @@
+//! Options that select where `translate_package_cli` writes the translated modules, and which of
+//! their artifacts it writes. The artifacts of a translated module that are not written are
+//! removed, so the output directory never holds artifacts of an older build of the module.
+
+use std::{fmt, path::PathBuf, str::FromStr};
+
+use crate::{code_size::SizeBudget, diagnostics::MessageFormat, wasm_opt::OptimizationLevel};
+
+/// Directory the artifacts are written to by default, relative to the package root
+pub const DEFAULT_OUTPUT_DIRECTORY: &str = "build/wasm";
+
+/// Directory the translated modules are cached in by the CLI, relative to the package root
+pub const DEFAULT_CACHE_DIRECTORY: &str = "build/wasm-cache";
+
+/// Placeholder of the artifact names replaced by the name of the module
+pub const MODULE_NAME_PLACEHOLDER: &str = "{module}";
+
+#[derive(Debug, Clone)]
+pub struct OutputOptions {
+    /// Directory the artifacts are written to, relative to the package root. It is created if it
+    /// does not exist.
+    pub directory: PathBuf,
+    /// Name of the artifacts of a module, before their extension, where `{module}` is replaced by
+    /// the name of the module. Packages with more than one module need the placeholder, so the
+    /// modules don't overwrite each other's artifacts.
+    pub file_name: String,
+    /// Artifacts written for every module, `Artifact::DEFAULT` by default. The coverage map is
+    /// only written for the modules translated with the `coverage` option, and the source map for
+    /// the ones translated with `debug_info`.
+    pub artifacts: Vec<Artifact>,
+    /// If true, the modules too big for Stylus to deploy, once compressed, or over the size budget
+    /// only raise a warning instead of failing the build.
+    pub allow_oversized: bool,
+    /// Sizes the modules must fit, failing the build as the modules too big for Stylus do, the
+    /// limit of Stylus by default.
+    pub size_budget: SizeBudget,
+    /// Level `wasm-opt` optimizes the modules with after they are emitted, or `None` to write them
+    /// as translated. The other artifacts describe the optimized module.
+    pub wasm_opt: Option<OptimizationLevel>,
+    /// Directory the translated modules are cached in, relative to the package root, or `None` to
+    /// translate every module. The modules whose bytecode, and the bytecode of their
+    /// dependencies, did not change since they were cached are written from it without being
+    /// translated.
+    pub cache_directory: Option<PathBuf>,
+    /// Format the warnings and the errors of the build are reported in.
+    pub message_format: MessageFormat,
+}
+
+/// Files written for a translated module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Artifact {
+    /// The WASM module, `<name>.wasm`
+    Wasm,
+    /// The WASM module in text format, `<name>.wat`
+    Wat,
+    /// The Solidity JSON ABI of the entry functions, `<name>.abi.json`
+    Abi,
+    /// The storage layout, `<name>.storage.json`
+    StorageLayout,
+    /// The worst-case storage accesses of every entry function, `<name>.gas.json`
+    GasReport,
+    /// The map from the blocks reported by the coverage instrumentation to the source,
+    /// `<name>.coverage.json`
+    CoverageMap,
+    /// The size of every function of the WASM module, `<name>.size.json`
+    SizeReport,
+    /// The TypeScript bindings of the ABI, for viem and ethers, `<name>.ts`
+    TypeScript,
+    /// The source map from the offsets of the instructions of the WASM module to the Move source,
+    /// `<name>.wasm.map`
+    SourceMap,
+    /// The code hash of the contract and the sizes its activation depends on,
+    /// `<name>.activation.json`
+    Activation,
+}
+
+impl Artifact {
+    pub const ALL: [Artifact; 10] = [
+        Artifact::Wasm,
+        Artifact::Wat,
+        Artifact::Abi,
+        Artifact::StorageLayout,
+        Artifact::GasReport,
+        Artifact::CoverageMap,
+        Artifact::SizeReport,
+        Artifact::TypeScript,
+        Artifact::SourceMap,
+        Artifact::Activation,
+    ];
+
+    /// Artifacts written unless told otherwise, all but the WAT, which is as big as the rest
+    /// together and only read to debug the translation, and the TypeScript bindings, only read by
+    /// frontends.
+    pub const DEFAULT: [Artifact; 8] = [
+        Artifact::Wasm,
+        Artifact::Abi,
+        Artifact::StorageLayout,
+        Artifact::GasReport,
+        Artifact::CoverageMap,
+        Artifact::SizeReport,
+        Artifact::SourceMap,
+        Artifact::Activation,
+    ];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Artifact::Wasm => "wasm",
+            Artifact::Wat => "wat",
+            Artifact::Abi => "abi.json",
+            Artifact::StorageLayout => "storage.json",
+            Artifact::GasReport => "gas.json",
+            Artifact::CoverageMap => "coverage.json",
+            Artifact::SizeReport => "size.json",
+            Artifact::TypeScript => "ts",
+            Artifact::SourceMap => "wasm.map",
+            Artifact::Activation => "activation.json",
+        }
+    }
+}
+
+impl fmt::Display for Artifact {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Artifact::Wasm => write!(f, "wasm"),
+            Artifact::Wat => write!(f, "wat"),
+            Artifact::Abi => write!(f, "abi"),
+            Artifact::StorageLayout => write!(f, "storage"),
+            Artifact::GasReport => write!(f, "gas"),
+            Artifact::CoverageMap => write!(f, "coverage"),
+            Artifact::SizeReport => write!(f, "size"),
+            Artifact::TypeScript => write!(f, "ts"),
+            Artifact::SourceMap => write!(f, "source-map"),
+            Artifact::Activation => write!(f, "activation"),
+        }
+    }
+}
+
+impl FromStr for Artifact {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Artifact::ALL
+            .into_iter()
+            .find(|artifact| artifact.to_string() == s)
+            .ok_or_else(|| {
+                format!(
+                    "unknown artifact {s}, expected one of {}",
+                    Artifact::ALL
+                        .map(|artifact| artifact.to_string())
+                        .join(", ")
+                )
+            })
+    }
+}
+
+impl OutputOptions {
+    pub fn emits(&self, artifact: Artifact) -> bool {
+        self.artifacts.contains(&artifact)
+    }
+
+    /// Path of an artifact of a module, relative to the package root.
+    pub fn artifact_path(&self, module_name: &str, artifact: Artifact) -> PathBuf {
+        self.directory.join(format!(
+            "{}.{}",
+            self.file_name.replace(MODULE_NAME_PLACEHOLDER, module_name),
+            artifact.extension()
+        ))
+    }
+}
+
+impl Default for OutputOptions {
+    fn default() -> Self {
+        Self {
+            directory: PathBuf::from(DEFAULT_OUTPUT_DIRECTORY),
+            file_name: MODULE_NAME_PLACEHOLDER.to_owned(),
+            artifacts: Artifact::DEFAULT.to_vec(),
+            allow_oversized: false,
+            size_budget: SizeBudget::default(),
+            wasm_opt: None,
+            cache_directory: None,
+            message_format: MessageFormat::Human,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_artifact_path() {
+        let output = OutputOptions::default();
+        assert_eq!(
+            PathBuf::from("build/wasm/counter.abi.json"),
+            output.artifact_path("counter", Artifact::Abi)
+        );
+
+        let output = OutputOptions {
+            directory: PathBuf::from("out"),
+            file_name: "{module}-v2".to_owned(),
+            ..Default::default()
+        };
+        assert_eq!(
+            PathBuf::from("out/counter-v2.wasm"),
+            output.artifact_path("counter", Artifact::Wasm)
+        );
+    }
+
+    #[test]
+    fn test_parse_artifact() {
+        for artifact in Artifact::ALL {
+            assert_eq!(Ok(artifact), artifact.to_string().parse());
+        }
+        assert!("bytecode".parse::<Artifact>().is_err());
+    }
+}
@@ -0,0 +1,340 @@
+//! Compiler configuration of a package, read from the `Stylus.toml` file next to its manifest, so
+//! the options a package must be built with are kept with it instead of repeated in every command.
+//!
+//! ```toml
+//! [build]
+//! profile = "release"
+//! wasm-opt = "z"
+//! stylus-version = "arbos32"
+//! artifacts = ["wasm", "abi", "storage"]
+//! storage-packing = true
+//! reentrancy-guard = false
+//! math-hooks = true
+//! memory-growth-cap = 16
+//! debug = false
+//! debug-info = false
+//! deterministic = true
+//! allow-oversized = false
+//! max-size = 65536
+//! max-compressed-size = 20480
+//!
+//! [target.arbos31]
+//! profile = "release"
+//! stylus-version = "arbos31"
+//! ```
+//!
+//! Every key is optional, the missing ones keep the default of `TranslationOptions` and
+//! `OutputOptions`, or the one of the profile if it is set. Unknown keys are rejected, so a
+//! misspelled option does not go unnoticed.
+//!
+//! The `[target.<name>]` tables take the keys of `[build]`, and override them when the package is
+//! built for the target, into its own directory, so a single build writes the artifacts of many
+//! configurations.
+use std::{collections::BTreeMap, fs, path::Path, str::FromStr};
+
+use anyhow::{Context, bail};
+use serde::{Deserialize, Deserializer, de};
+
+use crate::{
+    code_size::SizeBudget,
+    output_options::{Artifact, OutputOptions},
+    translation_options::{MemoryGrowth, Profile, StylusVersion, TranslationOptions},
+    wasm_opt::OptimizationLevel,
+};
+
+/// Name of the configuration file, in the package root
+pub const PACKAGE_CONFIG_FILE_NAME: &str = "Stylus.toml";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PackageConfig {
+    #[serde(default)]
+    pub build: BuildSection,
+    /// Options of every target, by name
+    #[serde(default)]
+    pub target: BTreeMap<String, BuildSection>,
+}
+
+/// Options of the `[build]` table, `None` when the file does not set them.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct BuildSection {
+    #[serde(default, deserialize_with = "from_str")]
+    pub profile: Option<Profile>,
+    #[serde(default, deserialize_with = "from_str")]
+    pub wasm_opt: Option<OptimizationLevel>,
+    #[serde(default, deserialize_with = "from_str")]
+    pub stylus_version: Option<StylusVersion>,
+    #[serde(default, deserialize_with = "from_str_list")]
+    pub artifacts: Option<Vec<Artifact>>,
+    pub storage_packing: Option<bool>,
+    pub reentrancy_guard: Option<bool>,
+    pub math_hooks: Option<bool>,
+    pub memory_growth_cap: Option<u32>,
+    pub debug: Option<bool>,
+    pub debug_info: Option<bool>,
+    pub deterministic: Option<bool>,
+    pub allow_oversized: Option<bool>,
+    /// Largest size of every module, in bytes
+    pub max_size: Option<usize>,
+    /// Largest size of every module compressed, in bytes, at most the limit of Stylus
+    pub max_compressed_size: Option<usize>,
+}
+
+impl PackageConfig {
+    /// Reads the configuration of the package at `package_root`, the default one if the package
+    /// has no `Stylus.toml`.
+    pub fn load(package_root: &Path) -> anyhow::Result<Self> {
+        let path = package_root.join(PACKAGE_CONFIG_FILE_NAME);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        contents
+            .parse()
+            .with_context(|| format!("Invalid {}", path.display()))
+    }
+
+    /// Configuration of the target named `name`, with its table as the `[build]` one: the table of
+    /// the file or, if the file has none, the profile of the same name.
+    pub fn target(&self, name: &str) -> anyhow::Result<PackageConfig> {
+        let build = match self.target.get(name) {
+            Some(section) => section.clone(),
+            None => match name.parse::<Profile>() {
+                Ok(profile) => BuildSection {
+                    profile: Some(profile),
+                    ..Default::default()
+                },
+                Err(_) => bail!("unknown target {name}, not defined in {PACKAGE_CONFIG_FILE_NAME}"),
+            },
+        };
+        Ok(PackageConfig {
+            build,
+            target: BTreeMap::new(),
+        })
+    }
+
+    /// Options the package is translated with, the defaults overridden by the file.
+    pub fn translation_options(&self) -> TranslationOptions {
+        let build = &self.build;
+        let mut defaults = TranslationOptions::default();
+        if let Some(profile) = build.profile {
+            profile.apply_to_translation(&mut defaults);
+        }
+        TranslationOptions {
+            storage_packing: build.storage_packing.unwrap_or(defaults.storage_packing),
+            reentrancy_guard: build.reentrancy_guard.unwrap_or(defaults.reentrancy_guard),
+            math_hooks: build.math_hooks.unwrap_or(defaults.math_hooks),
+            memory_growth: build
+                .memory_growth_cap
+                .map_or(defaults.memory_growth, |max_pages| {
+                    MemoryGrowth::Exponential { max_pages }
+                }),
+            stylus_version: build.stylus_version.unwrap_or(defaults.stylus_version),
+            debug: build.debug.unwrap_or(defaults.debug),
+            debug_info: build.debug_info.unwrap_or(defaults.debug_info),
+            deterministic: build.deterministic.unwrap_or(defaults.deterministic),
+            ..defaults
+        }
+    }
+
+    /// Artifacts written for the package, the defaults overridden by the file.
+    pub fn output_options(&self) -> OutputOptions {
+        let build = &self.build;
+        let mut defaults = OutputOptions::default();
+        if let Some(profile) = build.profile {
+            profile.apply_to_output(&mut defaults);
+        }
+        OutputOptions {
+            artifacts: build.artifacts.clone().unwrap_or(defaults.artifacts),
+            allow_oversized: build.allow_oversized.unwrap_or(defaults.allow_oversized),
+            size_budget: SizeBudget {
+                max_size: build.max_size.or(defaults.size_budget.max_size),
+                max_compressed_size: build
+                    .max_compressed_size
+                    .unwrap_or(defaults.size_budget.max_compressed_size),
+            },
+            wasm_opt: build.wasm_opt.or(defaults.wasm_opt),
+            ..defaults
+        }
+    }
+}
+
+impl FromStr for PackageConfig {
+    type Err = toml::de::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        toml::from_str(s)
+    }
+}
+
+/// Deserializes the options written as the strings their `FromStr` parses, as the CLI flags.
+fn from_str<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr<Err = String>,
+{
+    Option::<String>::deserialize(deserializer)?
+        .map(|value| value.parse().map_err(de::Error::custom))
+        .transpose()
+}
+
+fn from_str_list<'de, D, T>(deserializer: D) -> Result<Option<Vec<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr<Err = String>,
+{
+    Option::<Vec<String>>::deserialize(deserializer)?
+        .map(|values| {
+            values
+                .iter()
+                .map(|value| value.parse().map_err(de::Error::custom))
+                .collect()
+        })
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_package_config() {
+        let config: PackageConfig = r#"
+            [build]
+            wasm-opt = "z"
+            stylus-version = "arbos31"
+            artifacts = ["wasm", "abi"]
+            storage-packing = false
+            memory-growth-cap = 16
+            max-compressed-size = 20480
+        "#
+        .parse()
+        .unwrap();
+
+        let translation = config.translation_options();
+        assert!(!translation.storage_packing);
+        assert_eq!(StylusVersion::ArbOs31, translation.stylus_version);
+        assert_eq!(
+            MemoryGrowth::Exponential { max_pages: 16 },
+            translation.memory_growth
+        );
+        assert!(!translation.reentrancy_guard);
+
+        let output = config.output_options();
+        assert_eq!(Some(OptimizationLevel::Oz), output.wasm_opt);
+        assert_eq!(vec![Artifact::Wasm, Artifact::Abi], output.artifacts);
+        assert!(!output.allow_oversized);
+        assert_eq!(
+            SizeBudget {
+                max_size: None,
+                max_compressed_size: 20480,
+            },
+            output.size_budget
+        );
+    }
+
+    #[test]
+    fn test_package_config_profile() {
+        let config: PackageConfig = "[build]\nprofile = \"release\"".parse().unwrap();
+        let translation = config.translation_options();
+        assert!(translation.strip_names);
+        assert!(!translation.debug && !translation.debug_info);
+        assert!(translation.deterministic);
+        assert_eq!(
+            Some(OptimizationLevel::Oz),
+            config.output_options().wasm_opt
+        );
+
+        // The keys of the file override the profile
+        let config: PackageConfig =
+            "[build]\nprofile = \"release\"\nwasm-opt = \"2\"\ndebug = true"
+                .parse()
+                .unwrap();
+        assert!(config.translation_options().debug);
+        assert_eq!(
+            Some(OptimizationLevel::O2),
+            config.output_options().wasm_opt
+        );
+
+        let config: PackageConfig = "[build]\nprofile = \"debug\"".parse().unwrap();
+        let translation = config.translation_options();
+        assert!(translation.debug && translation.host_debug_functions);
+        assert!(translation.debug_info);
+        assert!(!translation.strip_names);
+        assert_eq!(None, config.output_options().wasm_opt);
+
+        assert!(
+            "[build]\nprofile = \"fast\""
+                .parse::<PackageConfig>()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_package_config_targets() {
+        let config: PackageConfig = r#"
+            [build]
+            math-hooks = true
+
+            [target.arbos31]
+            profile = "release"
+            stylus-version = "arbos31"
+        "#
+        .parse()
+        .unwrap();
+
+        let target = config.target("arbos31").unwrap();
+        assert_eq!(Some(Profile::Release), target.build.profile);
+        assert_eq!(
+            StylusVersion::ArbOs31,
+            target.translation_options().stylus_version
+        );
+        // The keys of `[build]` are not repeated in the targets
+        assert_eq!(None, target.build.math_hooks);
+
+        // The profiles are targets too
+        assert_eq!(
+            Some(Profile::Debug),
+            config.target("debug").unwrap().build.profile
+        );
+        assert!(config.target("arbos30").is_err());
+    }
+
+    #[test]
+    fn test_empty_package_config() {
+        let config: PackageConfig = "".parse().unwrap();
+        assert_eq!(PackageConfig::default(), config);
+        assert_eq!(
+            Artifact::DEFAULT.to_vec(),
+            config.output_options().artifacts
+        );
+    }
+
+    #[test]
+    fn test_invalid_package_config() {
+        assert!(
+            "[build]\nwasm-opt = \"fast\""
+                .parse::<PackageConfig>()
+                .is_err()
+        );
+        assert!(
+            "[build]\nartifacts = [\"bytecode\"]"
+                .parse::<PackageConfig>()
+                .is_err()
+        );
+        // Misspelled options are rejected
+        assert!(
+            "[build]\nmath-hook = true"
+                .parse::<PackageConfig>()
+                .is_err()
+        );
+        assert!(
+            "[deploy]\nendpoint = \"\""
+                .parse::<PackageConfig>()
+                .is_err()
+        );
+    }
+}
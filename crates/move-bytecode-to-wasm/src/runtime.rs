@@ -10,11 +10,20 @@ use crate::{
 
 mod copy;
 mod equality;
+mod global_storage;
 mod integers;
+mod precompiles;
+mod return_data;
+mod self_dispatch;
+mod slot_derivation_cache;
 mod storage;
+mod storage_cache;
+mod storage_write_back;
 mod swap;
 mod vector;
 
+pub use storage_write_back::{add_register_write_back_instructions, finalize_storage_write_back};
+
 #[derive(PartialEq)]
 pub enum RuntimeFunction {
     // Integer operations
@@ -31,6 +40,7 @@ pub enum RuntimeFunction {
     SubU64,
     HeapIntSub,
     HeapIntDivMod,
+    U256DivModWithHooks,
     MulU32,
     MulU64,
     HeapIntMul,
@@ -66,6 +76,36 @@ pub enum RuntimeFunction {
     EncodeAndSaveInStorage,
     DecodeAndReadFromStorage,
     DeleteFromStorage,
+    EncodeAndSaveBytesInStorage,
+    DecodeAndReadBytesFromStorage,
+    ClearBytesFromStorage,
+    // Storage cache
+    StorageCacheFindEntry,
+    StorageCachedLoad,
+    StorageCachedStore,
+    StorageCachedLoadSlots,
+    StorageCachedStoreSlots,
+    StorageCacheCommit,
+    StorageCacheFlush,
+    StorageCacheEvict,
+    // Slot derivation cache
+    SlotDerivationCacheFindEntry,
+    // Storage write back
+    StorageWriteBack,
+    StorageSaveBorrowed,
+    // Return data
+    ReturnDataBuffer,
+    CopyReturnData,
+    // Precompiles
+    CallPrecompile,
+    // Self dispatch
+    SelfDispatch,
+    // Global storage
+    GlobalMoveTo,
+    GlobalMoveFrom,
+    GlobalExists,
+    GlobalBorrow,
+    GlobalBorrowMut,
 }
 
 impl RuntimeFunction {
@@ -86,6 +126,7 @@ impl RuntimeFunction {
             Self::MulU64 => "mul_u64",
             Self::HeapIntMul => "heap_integer_mul",
             Self::HeapIntDivMod => "heap_integer_div_mod",
+            Self::U256DivModWithHooks => "u256_div_mod_with_hooks",
             Self::LessThan => "less_than",
             // Bitwise
             Self::HeapIntShiftLeft => "heap_integer_shift_left",
@@ -121,6 +162,36 @@ impl RuntimeFunction {
             Self::EncodeAndSaveInStorage => "encode_and_save_in_storage",
             Self::DecodeAndReadFromStorage => "decode_and_read_from_storage",
             Self::DeleteFromStorage => "delete_from_storage",
+            Self::EncodeAndSaveBytesInStorage => "encode_and_save_bytes_in_storage",
+            Self::DecodeAndReadBytesFromStorage => "decode_and_read_bytes_from_storage",
+            Self::ClearBytesFromStorage => "clear_bytes_from_storage",
+            // Storage cache
+            Self::StorageCacheFindEntry => "storage_cache_find_entry",
+            Self::StorageCachedLoad => "storage_cached_load",
+            Self::StorageCachedStore => "storage_cached_store",
+            Self::StorageCachedLoadSlots => "storage_cached_load_slots",
+            Self::StorageCachedStoreSlots => "storage_cached_store_slots",
+            Self::StorageCacheCommit => "storage_cache_commit",
+            Self::StorageCacheFlush => "storage_cache_flush",
+            Self::StorageCacheEvict => "storage_cache_evict",
+            // Slot derivation cache
+            Self::SlotDerivationCacheFindEntry => "slot_derivation_cache_find_entry",
+            // Storage write back
+            Self::StorageWriteBack => "storage_write_back",
+            Self::StorageSaveBorrowed => "storage_save_borrowed",
+            // Return data
+            Self::ReturnDataBuffer => "return_data_buffer",
+            Self::CopyReturnData => "copy_return_data",
+            // Precompiles
+            Self::CallPrecompile => "call_precompile",
+            // Self dispatch
+            Self::SelfDispatch => "self_dispatch",
+            // Global storage
+            Self::GlobalMoveTo => "global_move_to",
+            Self::GlobalMoveFrom => "global_move_from",
+            Self::GlobalExists => "global_exists",
+            Self::GlobalBorrow => "global_borrow",
+            Self::GlobalBorrowMut => "global_borrow_mut",
         }
     }
 
@@ -158,6 +229,9 @@ impl RuntimeFunction {
                 (Self::HeapIntDivMod, Some(ctx)) => {
                     integers::div::heap_integers_div_mod(module, ctx)
                 }
+                (Self::U256DivModWithHooks, Some(ctx)) => {
+                    integers::div::u256_div_mod_with_hooks(module, ctx)
+                }
                 (Self::LessThan, Some(ctx)) => integers::check_if_a_less_than_b(module, ctx),
                 // Swap
                 (Self::SwapI32Bytes, _) => swap::swap_i32_bytes_function(module),
@@ -227,6 +301,56 @@ impl RuntimeFunction {
                 (Self::LocateStorageData, Some(ctx)) => storage::locate_storage_data(module, ctx),
                 (Self::LocateStructSlot, Some(ctx)) => storage::locate_struct_slot(module, ctx),
                 (Self::GetIdBytesPtr, Some(ctx)) => storage::get_id_bytes_ptr(module, ctx),
+                (Self::EncodeAndSaveBytesInStorage, Some(ctx)) => {
+                    storage::encode_and_save_bytes_in_storage(module, ctx)
+                }
+                (Self::DecodeAndReadBytesFromStorage, Some(ctx)) => {
+                    storage::decode_and_read_bytes_from_storage(module, ctx)
+                }
+                (Self::ClearBytesFromStorage, Some(ctx)) => {
+                    storage::clear_bytes_from_storage(module, ctx)
+                }
+                // Storage cache
+                (Self::StorageCacheFindEntry, Some(ctx)) => {
+                    storage_cache::storage_cache_find_entry(module, ctx)
+                }
+                (Self::StorageCachedLoad, Some(ctx)) => {
+                    storage_cache::storage_cached_load(module, ctx)
+                }
+                (Self::StorageCachedStore, Some(ctx)) => {
+                    storage_cache::storage_cached_store(module, ctx)
+                }
+                (Self::StorageCachedLoadSlots, Some(ctx)) => {
+                    storage_cache::storage_cached_load_slots(module, ctx)
+                }
+                (Self::StorageCachedStoreSlots, Some(ctx)) => {
+                    storage_cache::storage_cached_store_slots(module, ctx)
+                }
+                (Self::StorageCacheCommit, Some(ctx)) => {
+                    storage_cache::storage_cache_commit(module, ctx)
+                }
+                (Self::StorageCacheFlush, Some(ctx)) => {
+                    storage_cache::storage_cache_flush(module, ctx)
+                }
+                (Self::StorageCacheEvict, Some(ctx)) => {
+                    storage_cache::storage_cache_evict(module, ctx)
+                }
+                // Slot derivation cache
+                (Self::SlotDerivationCacheFindEntry, Some(ctx)) => {
+                    slot_derivation_cache::slot_derivation_cache_find_entry(module, ctx)
+                }
+                // Storage write back
+                (Self::StorageWriteBack, Some(ctx)) => {
+                    storage_write_back::storage_write_back(module, ctx)
+                }
+                (Self::StorageSaveBorrowed, _) => storage_write_back::storage_save_borrowed(module),
+                // Return data
+                (Self::ReturnDataBuffer, Some(ctx)) => return_data::return_data_buffer(module, ctx),
+                (Self::CopyReturnData, Some(ctx)) => return_data::copy_return_data(module, ctx),
+                // Precompiles
+                (Self::CallPrecompile, Some(ctx)) => precompiles::call_precompile(module, ctx),
+                // Self dispatch
+                (Self::SelfDispatch, _) => self_dispatch::self_dispatch(module),
                 // Error
                 _ => panic!(
                     r#"there was an error linking "{}" runtime function, missing compilation context?"#,
@@ -281,6 +405,61 @@ impl RuntimeFunction {
 
                 storage::add_delete_struct_from_storage_fn(module, compilation_ctx, generics[0])
             }
+            Self::GlobalMoveTo => {
+                assert_eq!(
+                    1,
+                    generics.len(),
+                    "there was an error linking {} expected 1 type parameter, found {}",
+                    self.name(),
+                    generics.len(),
+                );
+
+                global_storage::add_global_move_to_fn(module, compilation_ctx, generics[0])
+            }
+            Self::GlobalMoveFrom => {
+                assert_eq!(
+                    1,
+                    generics.len(),
+                    "there was an error linking {} expected 1 type parameter, found {}",
+                    self.name(),
+                    generics.len(),
+                );
+
+                global_storage::add_global_move_from_fn(module, compilation_ctx, generics[0])
+            }
+            Self::GlobalExists => {
+                assert_eq!(
+                    1,
+                    generics.len(),
+                    "there was an error linking {} expected 1 type parameter, found {}",
+                    self.name(),
+                    generics.len(),
+                );
+
+                global_storage::add_global_exists_fn(module, compilation_ctx, generics[0])
+            }
+            Self::GlobalBorrow => {
+                assert_eq!(
+                    1,
+                    generics.len(),
+                    "there was an error linking {} expected 1 type parameter, found {}",
+                    self.name(),
+                    generics.len(),
+                );
+
+                global_storage::add_global_borrow_fn(module, compilation_ctx, generics[0], false)
+            }
+            Self::GlobalBorrowMut => {
+                assert_eq!(
+                    1,
+                    generics.len(),
+                    "there was an error linking {} expected 1 type parameter, found {}",
+                    self.name(),
+                    generics.len(),
+                );
+
+                global_storage::add_global_borrow_fn(module, compilation_ctx, generics[0], true)
+            }
             _ => panic!(
                 r#"there was an error linking "{}" runtime function, is this function generic?"#,
                 self.name()
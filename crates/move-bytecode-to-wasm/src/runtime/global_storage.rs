@@ -0,0 +1,421 @@
+//! Global storage operations (`move_to`, `move_from`, `exists`, `borrow_global` and
+//! `borrow_global_mut`).
+//!
+//! A resource of type `T` published under the address `a` is saved at the slots derived as a
+//! mapping entry: `s = keccak256(a . type_tag(T))` (see `storage::type_tag`). Slot `s` holds a
+//! flag marking the resource as published, so resources whose fields are all zero can still be
+//! found, and the struct is encoded starting at slot `s + 1`.
+//!
+//! Mutable borrows are saved back into the storage through the storage write back list, the same
+//! way values borrowed from tables are.
+use walrus::{
+    FunctionBuilder, FunctionId, InstrSeqBuilder, LocalId, Module, ValType,
+    ir::{LoadKind, MemArg, StoreKind, UnaryOp},
+};
+
+use super::{RuntimeFunction, add_register_write_back_instructions};
+use crate::{
+    CompilationContext,
+    data::DATA_SLOT_DATA_PTR_OFFSET,
+    get_generic_function_name,
+    storage::{
+        encoding::{
+            add_clear_storage_struct_instructions,
+            add_encode_and_save_into_storage_struct_instructions,
+            add_read_and_decode_storage_struct_instructions,
+        },
+        type_tag::add_type_tag_instructions,
+    },
+    translation::intermediate_types::IntermediateType,
+};
+
+/// Generates a function that publishes a resource under the signer's address.
+///
+/// Aborts if a resource of the same type is already published under that address.
+///
+/// Arguments:
+/// - signer reference
+/// - struct_ptr
+pub fn add_global_move_to_fn(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+    itype: &IntermediateType,
+) -> FunctionId {
+    let name = get_generic_function_name(RuntimeFunction::GlobalMoveTo.name(), &[itype]);
+    if let Some(function) = module.funcs.by_name(&name) {
+        return function;
+    }
+
+    let struct_ = compilation_ctx
+        .get_struct_by_intermediate_type(itype)
+        .unwrap();
+
+    let write_back_fn = RuntimeFunction::StorageWriteBack.get(module, Some(compilation_ctx));
+    let storage_cache = RuntimeFunction::StorageCachedStore.get(module, Some(compilation_ctx));
+
+    let mut function = FunctionBuilder::new(&mut module.types, &[ValType::I32, ValType::I32], &[]);
+    let mut builder = function.name(name).func_body();
+
+    // Arguments
+    let signer_ref = module.locals.add(ValType::I32);
+    let struct_ptr = module.locals.add(ValType::I32);
+
+    let address_ptr = module.locals.add(ValType::I32);
+    builder
+        .local_get(signer_ref)
+        .load(
+            compilation_ctx.memory_id,
+            LoadKind::I32 { atomic: false },
+            MemArg {
+                align: 0,
+                offset: 0,
+            },
+        )
+        .local_set(address_ptr);
+
+    builder.call(write_back_fn);
+
+    let (flag_slot_ptr, data_slot_ptr) =
+        add_resource_slots_instructions(&mut builder, module, compilation_ctx, itype, address_ptr);
+
+    add_resource_exists_instructions(&mut builder, module, compilation_ctx, flag_slot_ptr);
+    builder.if_else(None, |then| then.unreachable(), |_| {});
+
+    // Mark the resource as published
+    builder
+        .i32_const(DATA_SLOT_DATA_PTR_OFFSET)
+        .i32_const(0)
+        .i32_const(32)
+        .memory_fill(compilation_ctx.memory_id);
+
+    builder
+        .i32_const(DATA_SLOT_DATA_PTR_OFFSET)
+        .i32_const(1)
+        .store(
+            compilation_ctx.memory_id,
+            StoreKind::I32_8 { atomic: false },
+            MemArg {
+                align: 0,
+                offset: 31,
+            },
+        );
+
+    builder
+        .local_get(flag_slot_ptr)
+        .i32_const(DATA_SLOT_DATA_PTR_OFFSET)
+        .call(storage_cache);
+
+    // Wipe the slot data placeholder, the first slot is not wiped by the encoding function
+    builder
+        .i32_const(DATA_SLOT_DATA_PTR_OFFSET)
+        .i32_const(0)
+        .i32_const(32)
+        .memory_fill(compilation_ctx.memory_id);
+
+    add_encode_and_save_into_storage_struct_instructions(
+        module,
+        &mut builder,
+        compilation_ctx,
+        struct_ptr,
+        data_slot_ptr,
+        &struct_,
+        0,
+    );
+
+    function.finish(vec![signer_ref, struct_ptr], &mut module.funcs)
+}
+
+/// Generates a function that removes a resource published under an address and returns it.
+///
+/// Aborts if there is no resource of that type published under the address. Every slot the
+/// resource occupied is cleared.
+///
+/// Arguments:
+/// - address_ptr
+///
+/// Returns:
+/// - struct_ptr
+pub fn add_global_move_from_fn(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+    itype: &IntermediateType,
+) -> FunctionId {
+    let name = get_generic_function_name(RuntimeFunction::GlobalMoveFrom.name(), &[itype]);
+    if let Some(function) = module.funcs.by_name(&name) {
+        return function;
+    }
+
+    let struct_ = compilation_ctx
+        .get_struct_by_intermediate_type(itype)
+        .unwrap();
+
+    let write_back_fn = RuntimeFunction::StorageWriteBack.get(module, Some(compilation_ctx));
+    let storage_cache = RuntimeFunction::StorageCachedStore.get(module, Some(compilation_ctx));
+
+    let mut function = FunctionBuilder::new(&mut module.types, &[ValType::I32], &[ValType::I32]);
+    let mut builder = function.name(name).func_body();
+
+    // Arguments
+    let address_ptr = module.locals.add(ValType::I32);
+
+    builder.call(write_back_fn);
+
+    let (flag_slot_ptr, data_slot_ptr) =
+        add_resource_slots_instructions(&mut builder, module, compilation_ctx, itype, address_ptr);
+
+    add_resource_exists_instructions(&mut builder, module, compilation_ctx, flag_slot_ptr);
+    builder
+        .unop(UnaryOp::I32Eqz)
+        .if_else(None, |then| then.unreachable(), |_| {});
+
+    // Decoding advances the slot pointer, so we keep a copy pointing to the first slot to clear
+    // the resource afterwards
+    let clear_slot_ptr = module.locals.add(ValType::I32);
+    builder
+        .i32_const(32)
+        .call(compilation_ctx.allocator)
+        .local_tee(clear_slot_ptr)
+        .local_get(data_slot_ptr)
+        .i32_const(32)
+        .memory_copy(compilation_ctx.memory_id, compilation_ctx.memory_id);
+
+    let (struct_ptr, _) = add_read_and_decode_storage_struct_instructions(
+        module,
+        &mut builder,
+        compilation_ctx,
+        data_slot_ptr,
+        &struct_,
+        false,
+        0,
+    );
+
+    // Unpublish the resource, writing zero to the flag and every slot the struct occupied
+    add_clear_storage_struct_instructions(
+        module,
+        &mut builder,
+        compilation_ctx,
+        clear_slot_ptr,
+        &struct_,
+        0,
+    );
+
+    builder
+        .i32_const(DATA_SLOT_DATA_PTR_OFFSET)
+        .i32_const(0)
+        .i32_const(32)
+        .memory_fill(compilation_ctx.memory_id);
+
+    builder
+        .local_get(flag_slot_ptr)
+        .i32_const(DATA_SLOT_DATA_PTR_OFFSET)
+        .call(storage_cache);
+
+    builder.local_get(struct_ptr);
+
+    function.finish(vec![address_ptr], &mut module.funcs)
+}
+
+/// Generates a function that checks if a resource is published under an address.
+///
+/// Arguments:
+/// - address_ptr
+///
+/// Returns:
+/// - 1 if the resource exists, 0 otherwise
+pub fn add_global_exists_fn(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+    itype: &IntermediateType,
+) -> FunctionId {
+    let name = get_generic_function_name(RuntimeFunction::GlobalExists.name(), &[itype]);
+    if let Some(function) = module.funcs.by_name(&name) {
+        return function;
+    }
+
+    let mut function = FunctionBuilder::new(&mut module.types, &[ValType::I32], &[ValType::I32]);
+    let mut builder = function.name(name).func_body();
+
+    // Arguments
+    let address_ptr = module.locals.add(ValType::I32);
+
+    let (flag_slot_ptr, _) =
+        add_resource_slots_instructions(&mut builder, module, compilation_ctx, itype, address_ptr);
+
+    add_resource_exists_instructions(&mut builder, module, compilation_ctx, flag_slot_ptr);
+
+    function.finish(vec![address_ptr], &mut module.funcs)
+}
+
+/// Generates a function that borrows a resource published under an address.
+///
+/// If `mutable` is true, the resource is written back into the storage once the borrow ends.
+///
+/// Aborts if there is no resource of that type published under the address.
+///
+/// Arguments:
+/// - address_ptr
+///
+/// Returns:
+/// - reference to the resource
+pub fn add_global_borrow_fn(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+    itype: &IntermediateType,
+    mutable: bool,
+) -> FunctionId {
+    let base_name = if mutable {
+        RuntimeFunction::GlobalBorrowMut.name()
+    } else {
+        RuntimeFunction::GlobalBorrow.name()
+    };
+    let name = get_generic_function_name(base_name, &[itype]);
+    if let Some(function) = module.funcs.by_name(&name) {
+        return function;
+    }
+
+    let struct_ = compilation_ctx
+        .get_struct_by_intermediate_type(itype)
+        .unwrap();
+
+    let write_back_fn = RuntimeFunction::StorageWriteBack.get(module, Some(compilation_ctx));
+
+    let mut function = FunctionBuilder::new(&mut module.types, &[ValType::I32], &[ValType::I32]);
+    let mut builder = function.name(name).func_body();
+
+    // Arguments
+    let address_ptr = module.locals.add(ValType::I32);
+
+    builder.call(write_back_fn);
+
+    let (flag_slot_ptr, data_slot_ptr) =
+        add_resource_slots_instructions(&mut builder, module, compilation_ctx, itype, address_ptr);
+
+    add_resource_exists_instructions(&mut builder, module, compilation_ctx, flag_slot_ptr);
+    builder
+        .unop(UnaryOp::I32Eqz)
+        .if_else(None, |then| then.unreachable(), |_| {});
+
+    // Decoding advances the slot pointer, so we keep a copy pointing to the first slot to know
+    // where to write the resource back
+    let write_back_slot_ptr = module.locals.add(ValType::I32);
+    if mutable {
+        builder
+            .i32_const(32)
+            .call(compilation_ctx.allocator)
+            .local_tee(write_back_slot_ptr)
+            .local_get(data_slot_ptr)
+            .i32_const(32)
+            .memory_copy(compilation_ctx.memory_id, compilation_ctx.memory_id);
+    }
+
+    let (struct_ptr, _) = add_read_and_decode_storage_struct_instructions(
+        module,
+        &mut builder,
+        compilation_ctx,
+        data_slot_ptr,
+        &struct_,
+        false,
+        0,
+    );
+
+    // A reference points to the struct pointer
+    let struct_ref = module.locals.add(ValType::I32);
+    builder
+        .i32_const(4)
+        .call(compilation_ctx.allocator)
+        .local_tee(struct_ref)
+        .local_get(struct_ptr)
+        .store(
+            compilation_ctx.memory_id,
+            StoreKind::I32 { atomic: false },
+            MemArg {
+                align: 0,
+                offset: 0,
+            },
+        );
+
+    if mutable {
+        add_register_write_back_instructions(
+            &mut builder,
+            module,
+            compilation_ctx,
+            struct_ref,
+            write_back_slot_ptr,
+            itype,
+        );
+    }
+
+    builder.local_get(struct_ref);
+
+    function.finish(vec![address_ptr], &mut module.funcs)
+}
+
+/// Adds the instructions to derive the slots where the resource of type `itype` published under
+/// `address_ptr` is saved.
+///
+/// # Returns
+/// locals holding pointers to the slot of the published flag and to the first slot of the struct
+fn add_resource_slots_instructions(
+    builder: &mut InstrSeqBuilder,
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+    itype: &IntermediateType,
+    address_ptr: LocalId,
+) -> (LocalId, LocalId) {
+    let derive_mapping_slot_fn =
+        RuntimeFunction::DeriveMappingSlot.get(module, Some(compilation_ctx));
+    let next_slot_fn = RuntimeFunction::StorageNextSlot.get(module, Some(compilation_ctx));
+
+    let flag_slot_ptr = module.locals.add(ValType::I32);
+    let data_slot_ptr = module.locals.add(ValType::I32);
+
+    let type_tag_ptr = add_type_tag_instructions(builder, module, compilation_ctx, itype);
+
+    // Addresses are already saved as 32 bytes big-endian values, so they are used as the key
+    // directly
+    builder
+        .local_get(type_tag_ptr)
+        .local_get(address_ptr)
+        .i32_const(32)
+        .call(compilation_ctx.allocator)
+        .local_tee(flag_slot_ptr)
+        .call(derive_mapping_slot_fn);
+
+    builder
+        .i32_const(32)
+        .call(compilation_ctx.allocator)
+        .local_tee(data_slot_ptr)
+        .local_get(flag_slot_ptr)
+        .i32_const(32)
+        .memory_copy(compilation_ctx.memory_id, compilation_ctx.memory_id);
+
+    builder
+        .local_get(data_slot_ptr)
+        .call(next_slot_fn)
+        .local_set(data_slot_ptr);
+
+    (flag_slot_ptr, data_slot_ptr)
+}
+
+/// Adds the instructions to check the published flag of a resource. Leaves 1 in the stack if the
+/// resource is published, 0 otherwise.
+fn add_resource_exists_instructions(
+    builder: &mut InstrSeqBuilder,
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+    flag_slot_ptr: LocalId,
+) {
+    let storage_load = RuntimeFunction::StorageCachedLoad.get(module, Some(compilation_ctx));
+    let is_zero_fn = RuntimeFunction::IsZero.get(module, Some(compilation_ctx));
+
+    builder
+        .local_get(flag_slot_ptr)
+        .i32_const(DATA_SLOT_DATA_PTR_OFFSET)
+        .call(storage_load);
+
+    builder
+        .i32_const(DATA_SLOT_DATA_PTR_OFFSET)
+        .i32_const(32)
+        .call(is_zero_fn)
+        .unop(UnaryOp::I32Eqz);
+}
@@ -3,7 +3,11 @@ use walrus::{
     ir::{BinaryOp, LoadKind, MemArg, StoreKind},
 };
 
-use crate::{CompilationContext, runtime::RuntimeFunction};
+use crate::{
+    CompilationContext,
+    hostio::host_functions::{math_div, math_mod},
+    runtime::RuntimeFunction,
+};
 
 // Auxiliary function names
 const F_SHIFT_64BITS_RIGHT: &str = "shift_64bits_right";
@@ -804,3 +808,121 @@ mod tests {
         assert_eq!(remainder_result_memory_data, remainder.to_le_bytes::<32>());
     }
 }
+
+/// Divides two u256 with Stylus' `math_div` and `math_mod` hooks. The hooks work with big-endian
+/// values, so the operands are swapped before calling them, and the result after.
+///
+/// The hooks return 0 when dividing by 0, so the divisor is checked first to trap the same way as
+/// `heap_integers_div_mod`.
+///
+/// # Arguments
+/// - Pointer to the dividend
+/// - Pointer to the divisor
+/// - Whether return remainder or quotient. 1 for quotient, 0 for remainder.
+///
+/// # Returns
+/// - Pointer to the result
+pub fn u256_div_mod_with_hooks(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+) -> FunctionId {
+    let (math_div_fn, _) = math_div(module);
+    let (math_mod_fn, _) = math_mod(module);
+    let swap_256_fn = RuntimeFunction::SwapI256Bytes.get(module, Some(compilation_ctx));
+
+    let mut function = FunctionBuilder::new(
+        &mut module.types,
+        &[ValType::I32, ValType::I32, ValType::I32],
+        &[ValType::I32],
+    );
+
+    // Function arguments
+    let dividend_ptr = module.locals.add(ValType::I32);
+    let divisor_ptr = module.locals.add(ValType::I32);
+    let quotient_or_reminder = module.locals.add(ValType::I32);
+
+    // Locals
+    let value_be_ptr = module.locals.add(ValType::I32);
+    let divisor_be_ptr = module.locals.add(ValType::I32);
+    let result_ptr = module.locals.add(ValType::I32);
+
+    let mut builder = function
+        .name(RuntimeFunction::U256DivModWithHooks.name().to_owned())
+        .func_body();
+
+    // If the four chunks of the divisor are 0 we divide by 0 to cause a runtime error
+    for offset in [0, 8, 16, 24] {
+        builder.local_get(divisor_ptr).load(
+            compilation_ctx.memory_id,
+            LoadKind::I64 { atomic: false },
+            MemArg { align: 0, offset },
+        );
+    }
+    builder
+        .binop(BinaryOp::I64Or)
+        .binop(BinaryOp::I64Or)
+        .binop(BinaryOp::I64Or)
+        .i64_const(0)
+        .binop(BinaryOp::I64Eq)
+        .if_else(
+            None,
+            |then| {
+                then.i32_const(1)
+                    .i32_const(0)
+                    .binop(BinaryOp::I32DivU)
+                    .drop();
+            },
+            |_| {},
+        );
+
+    // The hook overwrites the value, so the operands are swapped into new buffers
+    builder
+        .i32_const(32)
+        .call(compilation_ctx.allocator)
+        .local_set(value_be_ptr);
+    builder
+        .local_get(dividend_ptr)
+        .local_get(value_be_ptr)
+        .call(swap_256_fn);
+
+    builder
+        .i32_const(32)
+        .call(compilation_ctx.allocator)
+        .local_set(divisor_be_ptr);
+    builder
+        .local_get(divisor_ptr)
+        .local_get(divisor_be_ptr)
+        .call(swap_256_fn);
+
+    builder.local_get(quotient_or_reminder).if_else(
+        None,
+        |quotient| {
+            quotient
+                .local_get(value_be_ptr)
+                .local_get(divisor_be_ptr)
+                .call(math_div_fn);
+        },
+        |remainder| {
+            remainder
+                .local_get(value_be_ptr)
+                .local_get(divisor_be_ptr)
+                .call(math_mod_fn);
+        },
+    );
+
+    builder
+        .i32_const(32)
+        .call(compilation_ctx.allocator)
+        .local_set(result_ptr);
+    builder
+        .local_get(value_be_ptr)
+        .local_get(result_ptr)
+        .call(swap_256_fn);
+
+    builder.local_get(result_ptr);
+
+    function.finish(
+        vec![dividend_ptr, divisor_ptr, quotient_or_reminder],
+        &mut module.funcs,
+    )
+}
@@ -0,0 +1,100 @@
+//! Calls to the EVM precompiles.
+//!
+//! Precompiles are contracts at fixed addresses, from 0x01 onwards, whose code is implemented by
+//! the node. They do not modify the state, so they are called through `static_call_contract`,
+//! and their results are read from the return data.
+use walrus::{
+    FunctionBuilder, FunctionId, Module, ValType,
+    ir::{MemArg, StoreKind},
+};
+
+use super::RuntimeFunction;
+use crate::{CompilationContext, hostio::host_functions::static_call_contract};
+
+/// Calls a precompile and copies `output_len` bytes of its output to the return data buffer. The
+/// bytes it does not return are zeroed, as in the precompiles that signal errors by returning no
+/// data, such as ecrecover.
+///
+/// It aborts if the call fails, which only happens if the precompile runs out of gas.
+///
+/// # Arguments
+/// - precompile: number of the precompile, which is the last byte of its address
+/// - input_ptr: pointer to the input
+/// - input_len: length of the input
+/// - output_len: number of bytes of the output copied
+///
+/// # Returns
+/// - pointer to the return data buffer
+pub fn call_precompile(module: &mut Module, compilation_ctx: &CompilationContext) -> FunctionId {
+    let (static_call_contract_fn, _) = static_call_contract(module);
+    let copy_return_data_fn = RuntimeFunction::CopyReturnData.get(module, Some(compilation_ctx));
+
+    let mut function = FunctionBuilder::new(
+        &mut module.types,
+        &[ValType::I32, ValType::I32, ValType::I32, ValType::I32],
+        &[ValType::I32],
+    );
+    let mut builder = function
+        .name(RuntimeFunction::CallPrecompile.name().to_owned())
+        .func_body();
+
+    // Arguments
+    let precompile = module.locals.add(ValType::I32);
+    let input_ptr = module.locals.add(ValType::I32);
+    let input_len = module.locals.add(ValType::I32);
+    let output_len = module.locals.add(ValType::I32);
+
+    // Locals
+    let address_ptr = module.locals.add(ValType::I32);
+    let return_data_len_ptr = module.locals.add(ValType::I32);
+
+    // The address is 20 bytes long, with the precompile number in the last one
+    builder
+        .i32_const(20)
+        .call(compilation_ctx.allocator)
+        .local_tee(address_ptr)
+        .i32_const(0)
+        .i32_const(20)
+        .memory_fill(compilation_ctx.memory_id);
+
+    builder.local_get(address_ptr).local_get(precompile).store(
+        compilation_ctx.memory_id,
+        StoreKind::I32_8 { atomic: false },
+        MemArg {
+            align: 0,
+            offset: 19,
+        },
+    );
+
+    builder
+        .i32_const(4)
+        .call(compilation_ctx.allocator)
+        .local_set(return_data_len_ptr);
+
+    // Precompiles do not read the storage, so the cache does not need to be flushed
+    builder
+        .local_get(address_ptr)
+        .local_get(input_ptr)
+        .local_get(input_len)
+        // Forward all the gas available
+        .i64_const(-1)
+        .local_get(return_data_len_ptr)
+        .call(static_call_contract_fn)
+        .if_else(
+            None,
+            |then| {
+                then.unreachable();
+            },
+            |_| {},
+        );
+
+    builder
+        .i32_const(0)
+        .local_get(output_len)
+        .call(copy_return_data_fn);
+
+    function.finish(
+        vec![precompile, input_ptr, input_len, output_len],
+        &mut module.funcs,
+    )
+}
@@ -0,0 +1,171 @@
+//! Buffer for the data returned by the calls to other contracts.
+//!
+//! The return data of a call can be of any length, and it is only known after the call is done.
+//! It is copied to a buffer whose pointer and capacity are saved in DATA_RETURN_DATA_BUFFER_OFFSET:
+//!
+//! [buffer ptr: 4 bytes][capacity: 4 bytes]
+//!
+//! The same buffer is reused by every call, and it is only reallocated, at least doubling its
+//! capacity, when the data does not fit in it. Its contents are only valid until the next call,
+//! so the data must be copied out of it, for example into a vector, before calling another
+//! contract.
+use walrus::{
+    FunctionBuilder, FunctionId, Module, ValType,
+    ir::{BinaryOp, LoadKind, MemArg, StoreKind},
+};
+
+use super::RuntimeFunction;
+use crate::{
+    CompilationContext, data::DATA_RETURN_DATA_BUFFER_OFFSET,
+    hostio::host_functions::read_return_data,
+};
+
+const BUFFER_PTR_OFFSET: u32 = 0;
+const BUFFER_CAPACITY_OFFSET: u32 = 4;
+
+/// Returns the return data buffer, making sure it can hold `size` bytes.
+///
+/// # Arguments
+/// - size: number of bytes the buffer must hold
+///
+/// # Returns
+/// - pointer to the buffer
+pub fn return_data_buffer(module: &mut Module, compilation_ctx: &CompilationContext) -> FunctionId {
+    let mut function = FunctionBuilder::new(&mut module.types, &[ValType::I32], &[ValType::I32]);
+    let mut builder = function
+        .name(RuntimeFunction::ReturnDataBuffer.name().to_owned())
+        .func_body();
+
+    // Arguments
+    let size = module.locals.add(ValType::I32);
+
+    // Locals
+    let capacity = module.locals.add(ValType::I32);
+
+    builder
+        .i32_const(DATA_RETURN_DATA_BUFFER_OFFSET)
+        .load(
+            compilation_ctx.memory_id,
+            LoadKind::I32 { atomic: false },
+            MemArg {
+                align: 0,
+                offset: BUFFER_CAPACITY_OFFSET,
+            },
+        )
+        .local_tee(capacity)
+        .local_get(size)
+        .binop(BinaryOp::I32LtU)
+        .if_else(
+            None,
+            |then| {
+                // The new capacity is the biggest of the size and twice the current capacity
+                then.local_get(capacity)
+                    .i32_const(1)
+                    .binop(BinaryOp::I32Shl)
+                    .local_tee(capacity)
+                    .local_get(size)
+                    .binop(BinaryOp::I32LtU)
+                    .if_else(
+                        None,
+                        |smaller| {
+                            smaller.local_get(size).local_set(capacity);
+                        },
+                        |_| {},
+                    );
+
+                then.i32_const(DATA_RETURN_DATA_BUFFER_OFFSET)
+                    .local_get(capacity)
+                    .call(compilation_ctx.allocator)
+                    .store(
+                        compilation_ctx.memory_id,
+                        StoreKind::I32 { atomic: false },
+                        MemArg {
+                            align: 0,
+                            offset: BUFFER_PTR_OFFSET,
+                        },
+                    );
+
+                then.i32_const(DATA_RETURN_DATA_BUFFER_OFFSET)
+                    .local_get(capacity)
+                    .store(
+                        compilation_ctx.memory_id,
+                        StoreKind::I32 { atomic: false },
+                        MemArg {
+                            align: 0,
+                            offset: BUFFER_CAPACITY_OFFSET,
+                        },
+                    );
+            },
+            |_| {},
+        );
+
+    builder.i32_const(DATA_RETURN_DATA_BUFFER_OFFSET).load(
+        compilation_ctx.memory_id,
+        LoadKind::I32 { atomic: false },
+        MemArg {
+            align: 0,
+            offset: BUFFER_PTR_OFFSET,
+        },
+    );
+
+    function.finish(vec![size], &mut module.funcs)
+}
+
+/// Copies `size` bytes of the data returned by the last call, starting at `offset`, to the return
+/// data buffer. The bytes past the end of the return data are zeroed, so results expected to have
+/// a fixed size can be read even if the call returned less data.
+///
+/// # Arguments
+/// - offset: offset in the return data of the first byte copied
+/// - size: number of bytes copied
+///
+/// # Returns
+/// - pointer to the return data buffer
+pub fn copy_return_data(module: &mut Module, compilation_ctx: &CompilationContext) -> FunctionId {
+    let (read_return_data_fn, _) = read_return_data(module);
+    let buffer_fn = RuntimeFunction::ReturnDataBuffer.get(module, Some(compilation_ctx));
+
+    let mut function = FunctionBuilder::new(
+        &mut module.types,
+        &[ValType::I32, ValType::I32],
+        &[ValType::I32],
+    );
+    let mut builder = function
+        .name(RuntimeFunction::CopyReturnData.name().to_owned())
+        .func_body();
+
+    // Arguments
+    let offset = module.locals.add(ValType::I32);
+    let size = module.locals.add(ValType::I32);
+
+    // Locals
+    let buffer_ptr = module.locals.add(ValType::I32);
+    let written = module.locals.add(ValType::I32);
+
+    builder
+        .local_get(size)
+        .call(buffer_fn)
+        .local_set(buffer_ptr);
+
+    // The host only copies the bytes it has
+    builder
+        .local_get(buffer_ptr)
+        .local_get(offset)
+        .local_get(size)
+        .call(read_return_data_fn)
+        .local_set(written);
+
+    builder
+        .local_get(buffer_ptr)
+        .local_get(written)
+        .binop(BinaryOp::I32Add)
+        .i32_const(0)
+        .local_get(size)
+        .local_get(written)
+        .binop(BinaryOp::I32Sub)
+        .memory_fill(compilation_ctx.memory_id);
+
+    builder.local_get(buffer_ptr);
+
+    function.finish(vec![offset, size], &mut module.funcs)
+}
@@ -0,0 +1,37 @@
+//! Calls from the contract to its own entry functions.
+//!
+//! `self_dispatch` runs the entry function matching the selector of a calldata, the same as the
+//! entrypoint router, but in the same execution instead of through an external call. The entry
+//! functions are only known once the whole module is translated, so the body of `self_dispatch`
+//! is generated at the end by the router.
+use walrus::{FunctionBuilder, FunctionId, Module, ValType};
+
+use super::RuntimeFunction;
+
+/// Declares the function that dispatches a calldata to the entry function matching its
+/// selector. Its body is generated by `build_self_dispatch`, once the entry functions are known.
+///
+/// # Arguments
+/// - calldata_ptr: pointer to the calldata
+/// - calldata_len: length of the calldata
+/// - return_data_len_ptr: pointer where the length of the return data is saved
+///
+/// # Returns
+/// - pointer to the ABI-encoded return data
+pub fn self_dispatch(module: &mut Module) -> FunctionId {
+    let mut function = FunctionBuilder::new(
+        &mut module.types,
+        &[ValType::I32, ValType::I32, ValType::I32],
+        &[ValType::I32],
+    );
+    function.name(RuntimeFunction::SelfDispatch.name().to_owned());
+
+    let calldata_ptr = module.locals.add(ValType::I32);
+    let calldata_len = module.locals.add(ValType::I32);
+    let return_data_len_ptr = module.locals.add(ValType::I32);
+
+    function.finish(
+        vec![calldata_ptr, calldata_len, return_data_len_ptr],
+        &mut module.funcs,
+    )
+}
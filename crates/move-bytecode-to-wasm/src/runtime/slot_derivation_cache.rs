@@ -0,0 +1,145 @@
+//! In-WASM cache of the mapping slots derived during a transaction.
+//!
+//! Deriving the slot of a mapping value hashes the key and the mapping slot with keccak256. The
+//! same derivation is repeated every time a value is accessed, for example in every iteration of a
+//! loop over a mapping, or in every access to an object in the objects mapping. Derived slots are
+//! kept in memory in a linked list whose head is saved in DATA_SLOT_DERIVATION_CACHE_HEAD_OFFSET,
+//! and each entry has the following layout:
+//!
+//! [next entry ptr: 4 bytes][key: 32 bytes][mapping slot: 32 bytes][derived slot: 32 bytes]
+//!
+//! The key and the mapping slot are contiguous, so they are hashed in place when the entry is
+//! created. New entries are inserted at the head of the list, and only the most recent ones are
+//! looked up, so the search stays cheaper than the hash.
+use walrus::{
+    FunctionBuilder, FunctionId, Module, ValType,
+    ir::{BinaryOp, LoadKind, MemArg, UnaryOp},
+};
+
+use super::RuntimeFunction;
+use crate::{CompilationContext, data::DATA_SLOT_DERIVATION_CACHE_HEAD_OFFSET};
+
+pub const ENTRY_NEXT_OFFSET: u32 = 0;
+pub const ENTRY_KEY_OFFSET: i32 = 4;
+pub const ENTRY_MAPPING_SLOT_OFFSET: i32 = 36;
+pub const ENTRY_DERIVED_SLOT_OFFSET: i32 = 68;
+pub const ENTRY_SIZE: i32 = 100;
+
+/// Number of entries, starting from the most recent one, compared before giving up
+const MAX_LOOKUPS: i32 = 16;
+
+/// Looks for the cache entry of a mapping slot derivation.
+///
+/// # Arguments
+/// - mapping_slot_ptr: pointer to the mapping slot (32 bytes)
+/// - key_ptr: pointer to the key (32 bytes)
+///
+/// # Returns
+/// - pointer to the entry, or 0 if the derivation is not cached
+pub fn slot_derivation_cache_find_entry(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+) -> FunctionId {
+    let mut function = FunctionBuilder::new(
+        &mut module.types,
+        &[ValType::I32, ValType::I32],
+        &[ValType::I32],
+    );
+    let mut builder = function
+        .name(
+            RuntimeFunction::SlotDerivationCacheFindEntry
+                .name()
+                .to_owned(),
+        )
+        .func_body();
+
+    // Arguments
+    let mapping_slot_ptr = module.locals.add(ValType::I32);
+    let key_ptr = module.locals.add(ValType::I32);
+
+    // Locals
+    let entry_ptr = module.locals.add(ValType::I32);
+    let lookups_left = module.locals.add(ValType::I32);
+
+    let equality_fn = RuntimeFunction::HeapTypeEquality.get(module, Some(compilation_ctx));
+
+    builder
+        .i32_const(DATA_SLOT_DERIVATION_CACHE_HEAD_OFFSET)
+        .load(
+            compilation_ctx.memory_id,
+            LoadKind::I32 { atomic: false },
+            MemArg {
+                align: 0,
+                offset: 0,
+            },
+        )
+        .local_set(entry_ptr);
+
+    builder.i32_const(MAX_LOOKUPS).local_set(lookups_left);
+
+    builder.block(None, |block| {
+        let block_id = block.id();
+        block.loop_(None, |loop_| {
+            let loop_id = loop_.id();
+
+            // End of the list, or of the entries we look up
+            loop_
+                .local_get(entry_ptr)
+                .unop(UnaryOp::I32Eqz)
+                .local_get(lookups_left)
+                .unop(UnaryOp::I32Eqz)
+                .binop(BinaryOp::I32Or)
+                .br_if(block_id);
+
+            loop_
+                .local_get(entry_ptr)
+                .i32_const(ENTRY_KEY_OFFSET)
+                .binop(BinaryOp::I32Add)
+                .local_get(key_ptr)
+                .i32_const(32)
+                .call(equality_fn)
+                .if_else(
+                    None,
+                    |then| {
+                        then.local_get(entry_ptr)
+                            .i32_const(ENTRY_MAPPING_SLOT_OFFSET)
+                            .binop(BinaryOp::I32Add)
+                            .local_get(mapping_slot_ptr)
+                            .i32_const(32)
+                            .call(equality_fn)
+                            .if_else(
+                                None,
+                                |found| {
+                                    found.local_get(entry_ptr).return_();
+                                },
+                                |_| {},
+                            );
+                    },
+                    |_| {},
+                );
+
+            loop_
+                .local_get(lookups_left)
+                .i32_const(1)
+                .binop(BinaryOp::I32Sub)
+                .local_set(lookups_left);
+
+            loop_
+                .local_get(entry_ptr)
+                .load(
+                    compilation_ctx.memory_id,
+                    LoadKind::I32 { atomic: false },
+                    MemArg {
+                        align: 0,
+                        offset: ENTRY_NEXT_OFFSET,
+                    },
+                )
+                .local_set(entry_ptr)
+                .br(loop_id);
+        });
+    });
+
+    builder.i32_const(0);
+
+    function.finish(vec![mapping_slot_ptr, key_ptr], &mut module.funcs)
+}
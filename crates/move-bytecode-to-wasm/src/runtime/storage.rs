@@ -1,22 +1,24 @@
-use super::RuntimeFunction;
+use super::{RuntimeFunction, slot_derivation_cache};
 use crate::data::{
     DATA_FROZEN_OBJECTS_KEY_OFFSET, DATA_OBJECTS_MAPPING_SLOT_NUMBER_OFFSET,
-    DATA_OBJECTS_SLOT_OFFSET, DATA_SHARED_OBJECTS_KEY_OFFSET, DATA_SLOT_DATA_PTR_OFFSET,
-    DATA_STORAGE_OBJECT_OWNER_OFFSET,
+    DATA_SHARED_OBJECTS_KEY_OFFSET, DATA_SLOT_DATA_PTR_OFFSET,
+    DATA_SLOT_DERIVATION_CACHE_HEAD_OFFSET, DATA_STORAGE_OBJECT_OWNER_OFFSET,
 };
-use crate::hostio::host_functions::{self, storage_cache_bytes32, storage_load_bytes32, tx_origin};
+use crate::hostio::host_functions::{self, tx_origin};
 use crate::storage::encoding::{
     add_encode_and_save_into_storage_struct_instructions,
     add_read_and_decode_storage_struct_instructions,
 };
+use crate::storage::namespace::add_objects_root_slot_instructions;
 use crate::translation::intermediate_types::IntermediateType;
 use crate::translation::intermediate_types::heap_integers::IU256;
+use crate::translation::intermediate_types::vector::IVector;
 use crate::wasm_builder_extensions::WasmBuilderExtension;
 use crate::{CompilationContext, data::DATA_U256_ONE_OFFSET};
 use crate::{get_generic_function_name, storage};
 use walrus::{
     FunctionBuilder, FunctionId, Module, ValType,
-    ir::{BinaryOp, LoadKind, MemArg, StoreKind},
+    ir::{BinaryOp, ExtendedLoad, LoadKind, MemArg, StoreKind, UnaryOp},
 };
 
 /// Looks for an struct inside the objects mappings. The objects mappings follows the solidity notation:
@@ -38,7 +40,9 @@ use walrus::{
 /// When the data is found, the owner's ID is written in DATA_STORAGE_OBJECT_OWNER_OFFSET
 ///
 /// # Arguments
+/// - root slot of the objects mapping (see `add_objects_root_slot_instructions`)
 /// - object id
+/// - search in frozen objects
 pub fn locate_storage_data(
     module: &mut Module,
     compilation_ctx: &CompilationContext,
@@ -49,15 +53,20 @@ pub fn locate_storage_data(
 
     // Host functions
     let (tx_origin, _) = tx_origin(module);
-    let (storage_load, _) = storage_load_bytes32(module);
+    let storage_load = RuntimeFunction::StorageCachedLoad.get(module, Some(compilation_ctx));
 
     // Function declaration
-    let mut function = FunctionBuilder::new(&mut module.types, &[ValType::I32, ValType::I32], &[]);
+    let mut function = FunctionBuilder::new(
+        &mut module.types,
+        &[ValType::I32, ValType::I32, ValType::I32],
+        &[],
+    );
     let mut builder = function
         .name(RuntimeFunction::LocateStorageData.name().to_owned())
         .func_body();
 
     // Arguments
+    let root_slot_ptr = module.locals.add(ValType::I32);
     let uid_ptr = module.locals.add(ValType::I32);
     let search_frozen = module.locals.add(ValType::I32);
 
@@ -80,6 +89,7 @@ pub fn locate_storage_data(
         // Signer's objects
         // ==
         block
+            .local_get(root_slot_ptr)
             .i32_const(DATA_STORAGE_OBJECT_OWNER_OFFSET)
             .local_get(uid_ptr)
             .call(write_object_slot_fn);
@@ -110,6 +120,7 @@ pub fn locate_storage_data(
             .memory_copy(compilation_ctx.memory_id, compilation_ctx.memory_id);
 
         block
+            .local_get(root_slot_ptr)
             .i32_const(DATA_STORAGE_OBJECT_OWNER_OFFSET)
             .local_get(uid_ptr)
             .call(write_object_slot_fn);
@@ -147,6 +158,7 @@ pub fn locate_storage_data(
                 .memory_copy(compilation_ctx.memory_id, compilation_ctx.memory_id);
 
             frozen_block
+                .local_get(root_slot_ptr)
                 .i32_const(DATA_FROZEN_OBJECTS_KEY_OFFSET)
                 .local_get(uid_ptr)
                 .call(write_object_slot_fn);
@@ -170,7 +182,10 @@ pub fn locate_storage_data(
         block.unreachable();
     });
 
-    function.finish(vec![uid_ptr, search_frozen], &mut module.funcs)
+    function.finish(
+        vec![root_slot_ptr, uid_ptr, search_frozen],
+        &mut module.funcs,
+    )
 }
 
 /// Computes the storage slot number where the struct should be persisted.
@@ -182,17 +197,21 @@ pub fn locate_storage_data(
 /// The slot number is written in DATA_OBJECTS_MAPPING_SLOT_NUMBER_OFFSET.
 ///
 /// # Arguments
+/// - root_slot_ptr - root slot of the objects mapping (see `add_objects_root_slot_instructions`)
 /// - struct_ptr - pointer to the struct
 pub fn locate_struct_slot(module: &mut Module, compilation_ctx: &CompilationContext) -> FunctionId {
-    let mut function = FunctionBuilder::new(&mut module.types, &[ValType::I32], &[]);
+    let mut function = FunctionBuilder::new(&mut module.types, &[ValType::I32, ValType::I32], &[]);
     let mut builder = function
         .name(RuntimeFunction::LocateStructSlot.name().to_owned())
         .func_body();
 
     let write_object_slot_fn = RuntimeFunction::WriteObjectSlot.get(module, Some(compilation_ctx));
     let get_id_bytes_ptr_fn = RuntimeFunction::GetIdBytesPtr.get(module, Some(compilation_ctx));
+    let root_slot_ptr = module.locals.add(ValType::I32);
     let struct_ptr = module.locals.add(ValType::I32);
 
+    builder.local_get(root_slot_ptr);
+
     // Obtain this object's owner
     builder
         .local_get(struct_ptr)
@@ -205,16 +224,26 @@ pub fn locate_struct_slot(module: &mut Module, compilation_ctx: &CompilationCont
     // Compute the slot where it should be saved
     builder.call(write_object_slot_fn);
 
-    function.finish(vec![struct_ptr], &mut module.funcs)
+    function.finish(vec![root_slot_ptr, struct_ptr], &mut module.funcs)
 }
 
 /// Calculates the slot from the slot mapping
+///
+/// # Arguments
+/// - root_slot_ptr - root slot of the objects mapping (see `add_objects_root_slot_instructions`)
+/// - owner_ptr - pointer to the owner's id
+/// - uid_ptr - pointer to the object's id
 pub fn write_object_slot(module: &mut Module, compilation_ctx: &CompilationContext) -> FunctionId {
-    let mut function = FunctionBuilder::new(&mut module.types, &[ValType::I32, ValType::I32], &[]);
+    let mut function = FunctionBuilder::new(
+        &mut module.types,
+        &[ValType::I32, ValType::I32, ValType::I32],
+        &[],
+    );
     let mut builder = function
         .name(RuntimeFunction::WriteObjectSlot.name().to_owned())
         .func_body();
 
+    let root_slot_ptr = module.locals.add(ValType::I32);
     let uid_ptr = module.locals.add(ValType::I32);
     let owner_ptr = module.locals.add(ValType::I32);
 
@@ -223,7 +252,7 @@ pub fn write_object_slot(module: &mut Module, compilation_ctx: &CompilationConte
 
     // Derive the slot for the first mapping
     builder
-        .i32_const(DATA_OBJECTS_SLOT_OFFSET)
+        .local_get(root_slot_ptr)
         .local_get(owner_ptr)
         .i32_const(DATA_OBJECTS_MAPPING_SLOT_NUMBER_OFFSET)
         .call(derive_slot_fn);
@@ -235,7 +264,7 @@ pub fn write_object_slot(module: &mut Module, compilation_ctx: &CompilationConte
         .i32_const(DATA_OBJECTS_MAPPING_SLOT_NUMBER_OFFSET)
         .call(derive_slot_fn);
 
-    function.finish(vec![owner_ptr, uid_ptr], &mut module.funcs)
+    function.finish(vec![root_slot_ptr, owner_ptr, uid_ptr], &mut module.funcs)
 }
 
 pub fn storage_next_slot_function(
@@ -332,6 +361,9 @@ pub fn get_id_bytes_ptr(module: &mut Module, compilation_ctx: &CompilationContex
 /// - for value types, h pads the value to 32 bytes in the same way as when storing the value in memory.
 /// - for strings and byte arrays, h(k) is just the unpadded data.
 ///
+/// Derived slots are memoized (see `slot_derivation_cache`), so repeated derivations during a
+/// transaction don't hash again.
+///
 /// Arguments:
 /// - `mapping_slot_ptr`: pointer to the mapping slot (32 bytes)
 /// - `key_ptr`: pointer to the key (32 bytes)
@@ -356,36 +388,91 @@ pub fn derive_mapping_slot(
     let derived_slot_ptr = module.locals.add(ValType::I32);
 
     let (native_keccak, _) = host_functions::native_keccak256(module);
+    let find_entry_fn =
+        RuntimeFunction::SlotDerivationCacheFindEntry.get(module, Some(compilation_ctx));
 
-    // Allocate memory for the hash data
-    let data_ptr = module.locals.add(ValType::I32);
+    let entry_ptr = module.locals.add(ValType::I32);
 
     builder
-        .i32_const(64) // For now this is always 64 bytes as we are not dealing with dynamic keys yet
-        .call(compilation_ctx.allocator)
-        .local_set(data_ptr);
-
-    builder
-        .local_get(data_ptr)
+        .local_get(mapping_slot_ptr)
         .local_get(key_ptr)
-        .i32_const(32) // copy 32 bytes, for now fixed size
-        .memory_copy(compilation_ctx.memory_id, compilation_ctx.memory_id);
+        .call(find_entry_fn)
+        .local_tee(entry_ptr)
+        .unop(UnaryOp::I32Eqz)
+        .if_else(
+            None,
+            |then| {
+                // The key and the mapping slot are hashed in place in the new cache entry
+                then.i32_const(slot_derivation_cache::ENTRY_SIZE)
+                    .call(compilation_ctx.allocator)
+                    .local_set(entry_ptr);
+
+                then.local_get(entry_ptr)
+                    .i32_const(slot_derivation_cache::ENTRY_KEY_OFFSET)
+                    .binop(BinaryOp::I32Add)
+                    .local_get(key_ptr)
+                    .i32_const(32) // copy 32 bytes, for now fixed size
+                    .memory_copy(compilation_ctx.memory_id, compilation_ctx.memory_id);
+
+                then.local_get(entry_ptr)
+                    .i32_const(slot_derivation_cache::ENTRY_MAPPING_SLOT_OFFSET)
+                    .binop(BinaryOp::I32Add)
+                    .local_get(mapping_slot_ptr)
+                    .i32_const(32)
+                    .memory_copy(compilation_ctx.memory_id, compilation_ctx.memory_id);
+
+                // Hash the data, this is the mapping slot we are looking for -> v = keccak256(h(k) . p)
+                then.local_get(entry_ptr)
+                    .i32_const(slot_derivation_cache::ENTRY_KEY_OFFSET)
+                    .binop(BinaryOp::I32Add)
+                    .i32_const(64)
+                    .local_get(entry_ptr)
+                    .i32_const(slot_derivation_cache::ENTRY_DERIVED_SLOT_OFFSET)
+                    .binop(BinaryOp::I32Add)
+                    .call(native_keccak);
+
+                // Insert the entry at the head of the list
+                then.local_get(entry_ptr)
+                    .i32_const(DATA_SLOT_DERIVATION_CACHE_HEAD_OFFSET)
+                    .load(
+                        compilation_ctx.memory_id,
+                        LoadKind::I32 { atomic: false },
+                        MemArg {
+                            align: 0,
+                            offset: 0,
+                        },
+                    )
+                    .store(
+                        compilation_ctx.memory_id,
+                        StoreKind::I32 { atomic: false },
+                        MemArg {
+                            align: 0,
+                            offset: slot_derivation_cache::ENTRY_NEXT_OFFSET,
+                        },
+                    );
+
+                then.i32_const(DATA_SLOT_DERIVATION_CACHE_HEAD_OFFSET)
+                    .local_get(entry_ptr)
+                    .store(
+                        compilation_ctx.memory_id,
+                        StoreKind::I32 { atomic: false },
+                        MemArg {
+                            align: 0,
+                            offset: 0,
+                        },
+                    );
+            },
+            |_| {},
+        );
 
     builder
-        .local_get(data_ptr)
+        .local_get(derived_slot_ptr)
+        .local_get(entry_ptr)
+        .i32_const(slot_derivation_cache::ENTRY_DERIVED_SLOT_OFFSET)
+        .binop(BinaryOp::I32Add)
         .i32_const(32)
-        .binop(BinaryOp::I32Add) // data_ptr + 32
-        .local_get(mapping_slot_ptr)
-        .i32_const(32) // copy 32 bytes
         .memory_copy(compilation_ctx.memory_id, compilation_ctx.memory_id);
 
-    // Hash the data, this is the mapping slot we are looking for -> v = keccak256(h(k) . p)
-    builder
-        .local_get(data_ptr)
-        .i32_const(64)
-        .local_get(derived_slot_ptr)
-        .call(native_keccak);
-
     function.finish(
         vec![mapping_slot_ptr, key_ptr, derived_slot_ptr],
         &mut module.funcs,
@@ -689,7 +776,8 @@ pub fn add_read_struct_from_storage_fn(
 /// This function:
 /// 1. Validates the object is not frozen (frozen objects cannot be deleted).
 /// 2. Locates the storage slot of the object.
-/// 3. Clears the storage slot and any additional slots occupied by the struct fields.
+/// 3. Clears the storage slot and any additional slots occupied by the struct fields, nested
+///    structs and dynamic data.
 /// 4. Flushes the cache to finalize the deletion.
 ///
 /// Arguments:
@@ -708,13 +796,10 @@ pub fn add_delete_struct_from_storage_fn(
         .get_struct_by_intermediate_type(itype)
         .unwrap();
 
-    let next_slot_fn = RuntimeFunction::StorageNextSlot.get(module, Some(compilation_ctx));
     let locate_struct_slot_fn =
         RuntimeFunction::LocateStructSlot.get(module, Some(compilation_ctx));
     let equality_fn = RuntimeFunction::HeapTypeEquality.get(module, Some(compilation_ctx));
 
-    let (storage_cache, _) = storage_cache_bytes32(module);
-
     let mut function = FunctionBuilder::new(&mut module.types, &[ValType::I32], &[]);
     let mut builder = function.name(name).func_body();
 
@@ -738,48 +823,534 @@ pub fn add_delete_struct_from_storage_fn(
         },
         |else_| {
             // Calculate the object slot in the storage (saved in DATA_OBJECTS_MAPPING_SLOT_NUMBER_OFFSET)
+            add_objects_root_slot_instructions(else_, module, compilation_ctx, &struct_);
             else_
                 .local_get(struct_ptr)
                 .call(locate_struct_slot_fn)
                 .i32_const(DATA_OBJECTS_MAPPING_SLOT_NUMBER_OFFSET)
                 .local_set(slot_ptr);
 
-            // Wipe the slot data placeholder. We will use it to erase the slots in the storage
-            else_
-                .i32_const(DATA_SLOT_DATA_PTR_OFFSET)
-                .i32_const(0)
-                .i32_const(32)
-                .memory_fill(compilation_ctx.memory_id);
-
-            // Wipe out the first slot
-            else_
-                .local_get(slot_ptr)
-                .i32_const(DATA_SLOT_DATA_PTR_OFFSET)
-                .call(storage_cache);
-
-            // Loop through each field in the struct and clear the corresponding storage slots.
-            let mut slot_used_bytes = 0;
-            for field in struct_.fields.iter() {
-                let field_size = storage::encoding::field_size(field, compilation_ctx);
-                if slot_used_bytes + field_size > 32 {
-                    else_
-                        .local_get(slot_ptr)
-                        .call(next_slot_fn)
-                        .local_tee(slot_ptr)
-                        .i32_const(DATA_SLOT_DATA_PTR_OFFSET)
-                        .call(storage_cache);
-
-                    slot_used_bytes = field_size;
-                } else {
-                    slot_used_bytes += field_size;
-                }
-            }
+            // Write zero to every slot occupied by the struct, including the ones of its nested
+            // structs and the data of its dynamic fields
+            storage::encoding::add_clear_storage_struct_instructions(
+                module,
+                else_,
+                compilation_ctx,
+                slot_ptr,
+                &struct_,
+                0,
+            );
         },
     );
 
     function.finish(vec![struct_ptr], &mut module.funcs)
 }
 
+/// Encodes and saves a `vector<u8>` into the storage following the Solidity encoding for `bytes`
+/// and `string`:
+/// - If the data is at most 31 bytes long, it is saved in the slot itself, left aligned, and the
+///   lowest-order byte stores `length * 2`.
+/// - Otherwise, the slot stores `length * 2 + 1` and the data is saved in the slots starting at
+///   `keccak256(slot)`.
+///
+/// The header slot is written into DATA_SLOT_DATA_PTR_OFFSET, it is the caller's responsibility to
+/// cache it.
+///
+/// Arguments:
+/// - vector_ptr
+/// - slot_ptr
+pub fn encode_and_save_bytes_in_storage(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+) -> FunctionId {
+    let mut function = FunctionBuilder::new(&mut module.types, &[ValType::I32, ValType::I32], &[]);
+    let mut builder = function
+        .name(
+            RuntimeFunction::EncodeAndSaveBytesInStorage
+                .name()
+                .to_owned(),
+        )
+        .func_body();
+
+    // Arguments
+    let vector_ptr = module.locals.add(ValType::I32);
+    let slot_ptr = module.locals.add(ValType::I32);
+
+    // Locals
+    let len = module.locals.add(ValType::I32);
+    let index = module.locals.add(ValType::I32);
+    let data_slot_ptr = module.locals.add(ValType::I32);
+    let chunk_ptr = module.locals.add(ValType::I32);
+
+    let swap_i32_fn = RuntimeFunction::SwapI32Bytes.get(module, None);
+    let next_slot_fn = RuntimeFunction::StorageNextSlot.get(module, Some(compilation_ctx));
+    let (native_keccak, _) = host_functions::native_keccak256(module);
+    let storage_cache = RuntimeFunction::StorageCachedStore.get(module, Some(compilation_ctx));
+    let clear_bytes_fn = RuntimeFunction::ClearBytesFromStorage.get(module, Some(compilation_ctx));
+
+    builder
+        .local_get(vector_ptr)
+        .load(
+            compilation_ctx.memory_id,
+            LoadKind::I32 { atomic: false },
+            MemArg {
+                align: 0,
+                offset: 0,
+            },
+        )
+        .local_set(len);
+
+    // If the previous data was longer, the slots it no longer needs are cleared
+    builder
+        .local_get(slot_ptr)
+        .local_get(len)
+        .call(clear_bytes_fn);
+
+    // Wipe the slot data, the unused bytes must be zero
+    builder
+        .i32_const(DATA_SLOT_DATA_PTR_OFFSET)
+        .i32_const(0)
+        .i32_const(32)
+        .memory_fill(compilation_ctx.memory_id);
+
+    builder
+        .local_get(len)
+        .i32_const(32)
+        .binop(BinaryOp::I32LtU)
+        .if_else(
+            None,
+            |short| {
+                // Short encoding: the bytes are saved in the slot itself
+                short.block(None, |block| {
+                    let block_id = block.id();
+                    block.loop_(None, |loop_| {
+                        let loop_id = loop_.id();
+
+                        loop_
+                            .local_get(index)
+                            .local_get(len)
+                            .binop(BinaryOp::I32Eq)
+                            .br_if(block_id);
+
+                        // DATA_SLOT_DATA_PTR_OFFSET[index] = vector[index]
+                        loop_
+                            .i32_const(DATA_SLOT_DATA_PTR_OFFSET)
+                            .local_get(index)
+                            .binop(BinaryOp::I32Add)
+                            .local_get(vector_ptr)
+                            .local_get(index)
+                            .i32_const(4)
+                            .binop(BinaryOp::I32Mul)
+                            .binop(BinaryOp::I32Add)
+                            .load(
+                                compilation_ctx.memory_id,
+                                LoadKind::I32 { atomic: false },
+                                MemArg {
+                                    align: 0,
+                                    offset: 8,
+                                },
+                            )
+                            .store(
+                                compilation_ctx.memory_id,
+                                StoreKind::I32_8 { atomic: false },
+                                MemArg {
+                                    align: 0,
+                                    offset: 0,
+                                },
+                            );
+
+                        loop_
+                            .local_get(index)
+                            .i32_const(1)
+                            .binop(BinaryOp::I32Add)
+                            .local_set(index)
+                            .br(loop_id);
+                    });
+                });
+
+                // The last byte holds length * 2
+                short
+                    .i32_const(DATA_SLOT_DATA_PTR_OFFSET)
+                    .local_get(len)
+                    .i32_const(1)
+                    .binop(BinaryOp::I32Shl)
+                    .store(
+                        compilation_ctx.memory_id,
+                        StoreKind::I32_8 { atomic: false },
+                        MemArg {
+                            align: 0,
+                            offset: 31,
+                        },
+                    );
+            },
+            |long| {
+                // Long encoding: the slot holds length * 2 + 1 (big endian)
+                long.i32_const(DATA_SLOT_DATA_PTR_OFFSET)
+                    .local_get(len)
+                    .i32_const(1)
+                    .binop(BinaryOp::I32Shl)
+                    .i32_const(1)
+                    .binop(BinaryOp::I32Or)
+                    .call(swap_i32_fn)
+                    .store(
+                        compilation_ctx.memory_id,
+                        StoreKind::I32 { atomic: false },
+                        MemArg {
+                            align: 0,
+                            offset: 28,
+                        },
+                    );
+
+                // The data starts at keccak256(slot)
+                long.i32_const(32)
+                    .call(compilation_ctx.allocator)
+                    .local_set(data_slot_ptr);
+
+                long.local_get(slot_ptr)
+                    .i32_const(32)
+                    .local_get(data_slot_ptr)
+                    .call(native_keccak);
+
+                long.i32_const(32)
+                    .call(compilation_ctx.allocator)
+                    .local_set(chunk_ptr);
+
+                long.block(None, |block| {
+                    let block_id = block.id();
+                    block.loop_(None, |loop_| {
+                        let loop_id = loop_.id();
+
+                        loop_
+                            .local_get(index)
+                            .local_get(len)
+                            .binop(BinaryOp::I32Eq)
+                            .br_if(block_id);
+
+                        // chunk[index % 32] = vector[index]
+                        loop_
+                            .local_get(chunk_ptr)
+                            .local_get(index)
+                            .i32_const(31)
+                            .binop(BinaryOp::I32And)
+                            .binop(BinaryOp::I32Add)
+                            .local_get(vector_ptr)
+                            .local_get(index)
+                            .i32_const(4)
+                            .binop(BinaryOp::I32Mul)
+                            .binop(BinaryOp::I32Add)
+                            .load(
+                                compilation_ctx.memory_id,
+                                LoadKind::I32 { atomic: false },
+                                MemArg {
+                                    align: 0,
+                                    offset: 8,
+                                },
+                            )
+                            .store(
+                                compilation_ctx.memory_id,
+                                StoreKind::I32_8 { atomic: false },
+                                MemArg {
+                                    align: 0,
+                                    offset: 0,
+                                },
+                            );
+
+                        loop_
+                            .local_get(index)
+                            .i32_const(1)
+                            .binop(BinaryOp::I32Add)
+                            .local_set(index);
+
+                        // If the chunk is full or we processed all the data, we save the chunk,
+                        // wipe it and move to the next slot
+                        loop_
+                            .local_get(index)
+                            .i32_const(31)
+                            .binop(BinaryOp::I32And)
+                            .i32_const(0)
+                            .binop(BinaryOp::I32Eq)
+                            .local_get(index)
+                            .local_get(len)
+                            .binop(BinaryOp::I32Eq)
+                            .binop(BinaryOp::I32Or)
+                            .if_else(
+                                None,
+                                |then| {
+                                    then.local_get(data_slot_ptr)
+                                        .local_get(chunk_ptr)
+                                        .call(storage_cache);
+
+                                    then.local_get(chunk_ptr)
+                                        .i32_const(0)
+                                        .i32_const(32)
+                                        .memory_fill(compilation_ctx.memory_id);
+
+                                    then.local_get(data_slot_ptr)
+                                        .call(next_slot_fn)
+                                        .local_set(data_slot_ptr);
+                                },
+                                |_| {},
+                            );
+
+                        loop_.br(loop_id);
+                    });
+                });
+            },
+        );
+
+    function.finish(vec![vector_ptr, slot_ptr], &mut module.funcs)
+}
+
+/// Reads and decodes from the storage a `vector<u8>` saved with the Solidity encoding for `bytes`
+/// and `string` (see [`encode_and_save_bytes_in_storage`]).
+///
+/// This function expects the header slot to be already loaded in DATA_SLOT_DATA_PTR_OFFSET.
+///
+/// Arguments:
+/// - slot_ptr
+///
+/// Returns:
+/// - vector_ptr
+pub fn decode_and_read_bytes_from_storage(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+) -> FunctionId {
+    let mut function = FunctionBuilder::new(&mut module.types, &[ValType::I32], &[ValType::I32]);
+    let mut builder = function
+        .name(
+            RuntimeFunction::DecodeAndReadBytesFromStorage
+                .name()
+                .to_owned(),
+        )
+        .func_body();
+
+    // Arguments
+    let slot_ptr = module.locals.add(ValType::I32);
+
+    // Locals
+    let vector_ptr = module.locals.add(ValType::I32);
+    let len = module.locals.add(ValType::I32);
+    let index = module.locals.add(ValType::I32);
+    let data_slot_ptr = module.locals.add(ValType::I32);
+    let chunk_ptr = module.locals.add(ValType::I32);
+
+    let swap_i32_fn = RuntimeFunction::SwapI32Bytes.get(module, None);
+    let next_slot_fn = RuntimeFunction::StorageNextSlot.get(module, Some(compilation_ctx));
+    let (native_keccak, _) = host_functions::native_keccak256(module);
+    let storage_load = RuntimeFunction::StorageCachedLoad.get(module, Some(compilation_ctx));
+
+    // If the lowest-order bit is set the data is long encoded, otherwise it is saved in the slot
+    // itself
+    builder
+        .i32_const(DATA_SLOT_DATA_PTR_OFFSET)
+        .load(
+            compilation_ctx.memory_id,
+            LoadKind::I32_8 {
+                kind: ExtendedLoad::ZeroExtend,
+            },
+            MemArg {
+                align: 0,
+                offset: 31,
+            },
+        )
+        .i32_const(1)
+        .binop(BinaryOp::I32And)
+        .if_else(
+            None,
+            |long| {
+                // The slot holds length * 2 + 1 (big endian)
+                long.i32_const(DATA_SLOT_DATA_PTR_OFFSET)
+                    .load(
+                        compilation_ctx.memory_id,
+                        LoadKind::I32 { atomic: false },
+                        MemArg {
+                            align: 0,
+                            offset: 28,
+                        },
+                    )
+                    .call(swap_i32_fn)
+                    .i32_const(1)
+                    .binop(BinaryOp::I32ShrU)
+                    .local_set(len);
+
+                IVector::allocate_vector_with_header(
+                    long,
+                    compilation_ctx,
+                    vector_ptr,
+                    len,
+                    len,
+                    4,
+                );
+
+                // The data starts at keccak256(slot)
+                long.i32_const(32)
+                    .call(compilation_ctx.allocator)
+                    .local_set(data_slot_ptr);
+
+                long.local_get(slot_ptr)
+                    .i32_const(32)
+                    .local_get(data_slot_ptr)
+                    .call(native_keccak);
+
+                long.i32_const(32)
+                    .call(compilation_ctx.allocator)
+                    .local_set(chunk_ptr);
+
+                long.block(None, |block| {
+                    let block_id = block.id();
+                    block.loop_(None, |loop_| {
+                        let loop_id = loop_.id();
+
+                        loop_
+                            .local_get(index)
+                            .local_get(len)
+                            .binop(BinaryOp::I32Eq)
+                            .br_if(block_id);
+
+                        // Every 32 bytes we load the next chunk
+                        loop_
+                            .local_get(index)
+                            .i32_const(31)
+                            .binop(BinaryOp::I32And)
+                            .i32_const(0)
+                            .binop(BinaryOp::I32Eq)
+                            .if_else(
+                                None,
+                                |then| {
+                                    then.local_get(data_slot_ptr)
+                                        .local_get(chunk_ptr)
+                                        .call(storage_load);
+
+                                    then.local_get(data_slot_ptr)
+                                        .call(next_slot_fn)
+                                        .local_set(data_slot_ptr);
+                                },
+                                |_| {},
+                            );
+
+                        // vector[index] = chunk[index % 32]
+                        loop_
+                            .local_get(vector_ptr)
+                            .local_get(index)
+                            .i32_const(4)
+                            .binop(BinaryOp::I32Mul)
+                            .binop(BinaryOp::I32Add)
+                            .local_get(chunk_ptr)
+                            .local_get(index)
+                            .i32_const(31)
+                            .binop(BinaryOp::I32And)
+                            .binop(BinaryOp::I32Add)
+                            .load(
+                                compilation_ctx.memory_id,
+                                LoadKind::I32_8 {
+                                    kind: ExtendedLoad::ZeroExtend,
+                                },
+                                MemArg {
+                                    align: 0,
+                                    offset: 0,
+                                },
+                            )
+                            .store(
+                                compilation_ctx.memory_id,
+                                StoreKind::I32 { atomic: false },
+                                MemArg {
+                                    align: 0,
+                                    offset: 8,
+                                },
+                            );
+
+                        loop_
+                            .local_get(index)
+                            .i32_const(1)
+                            .binop(BinaryOp::I32Add)
+                            .local_set(index)
+                            .br(loop_id);
+                    });
+                });
+            },
+            |short| {
+                // The last byte holds length * 2
+                short
+                    .i32_const(DATA_SLOT_DATA_PTR_OFFSET)
+                    .load(
+                        compilation_ctx.memory_id,
+                        LoadKind::I32_8 {
+                            kind: ExtendedLoad::ZeroExtend,
+                        },
+                        MemArg {
+                            align: 0,
+                            offset: 31,
+                        },
+                    )
+                    .i32_const(1)
+                    .binop(BinaryOp::I32ShrU)
+                    .local_set(len);
+
+                IVector::allocate_vector_with_header(
+                    short,
+                    compilation_ctx,
+                    vector_ptr,
+                    len,
+                    len,
+                    4,
+                );
+
+                short.block(None, |block| {
+                    let block_id = block.id();
+                    block.loop_(None, |loop_| {
+                        let loop_id = loop_.id();
+
+                        loop_
+                            .local_get(index)
+                            .local_get(len)
+                            .binop(BinaryOp::I32Eq)
+                            .br_if(block_id);
+
+                        // vector[index] = DATA_SLOT_DATA_PTR_OFFSET[index]
+                        loop_
+                            .local_get(vector_ptr)
+                            .local_get(index)
+                            .i32_const(4)
+                            .binop(BinaryOp::I32Mul)
+                            .binop(BinaryOp::I32Add)
+                            .i32_const(DATA_SLOT_DATA_PTR_OFFSET)
+                            .local_get(index)
+                            .binop(BinaryOp::I32Add)
+                            .load(
+                                compilation_ctx.memory_id,
+                                LoadKind::I32_8 {
+                                    kind: ExtendedLoad::ZeroExtend,
+                                },
+                                MemArg {
+                                    align: 0,
+                                    offset: 0,
+                                },
+                            )
+                            .store(
+                                compilation_ctx.memory_id,
+                                StoreKind::I32 { atomic: false },
+                                MemArg {
+                                    align: 0,
+                                    offset: 8,
+                                },
+                            );
+
+                        loop_
+                            .local_get(index)
+                            .i32_const(1)
+                            .binop(BinaryOp::I32Add)
+                            .local_set(index)
+                            .br(loop_id);
+                    });
+                });
+            },
+        );
+
+    builder.local_get(vector_ptr);
+
+    function.finish(vec![slot_ptr], &mut module.funcs)
+}
+
 // The expected slot values were calculated using Remix to ensure the tests are correct.
 #[cfg(test)]
 mod tests {
@@ -1220,3 +1791,172 @@ mod tests {
         assert_eq!(result, expected);
     }
 }
+
+/// Clears the data slots of a `vector<u8>` saved with the Solidity encoding for `bytes` and
+/// `string` (see [`encode_and_save_bytes_in_storage`]) that are not needed to save `keep_len`
+/// bytes. Writing zero to vacated slots avoids leaving stale data behind and gives back the
+/// storage refund.
+///
+/// When the bytes are truncated, `keep_len` is the new length. When they are removed, it is zero.
+/// The header slot is not modified, it is the caller's responsibility to overwrite it.
+///
+/// Arguments:
+/// - slot_ptr
+/// - keep_len
+pub fn clear_bytes_from_storage(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+) -> FunctionId {
+    let mut function = FunctionBuilder::new(&mut module.types, &[ValType::I32, ValType::I32], &[]);
+    let mut builder = function
+        .name(RuntimeFunction::ClearBytesFromStorage.name().to_owned())
+        .func_body();
+
+    // Arguments
+    let slot_ptr = module.locals.add(ValType::I32);
+    let keep_len = module.locals.add(ValType::I32);
+
+    // Locals
+    let header_ptr = module.locals.add(ValType::I32);
+    let old_slots = module.locals.add(ValType::I32);
+    let kept_slots = module.locals.add(ValType::I32);
+    let index = module.locals.add(ValType::I32);
+    let data_slot_ptr = module.locals.add(ValType::I32);
+    let zero_ptr = module.locals.add(ValType::I32);
+
+    let swap_i32_fn = RuntimeFunction::SwapI32Bytes.get(module, None);
+    let next_slot_fn = RuntimeFunction::StorageNextSlot.get(module, Some(compilation_ctx));
+    let (native_keccak, _) = host_functions::native_keccak256(module);
+    let storage_load = RuntimeFunction::StorageCachedLoad.get(module, Some(compilation_ctx));
+    let storage_cache = RuntimeFunction::StorageCachedStore.get(module, Some(compilation_ctx));
+
+    // The slot data placeholder may be in use by the caller, so the old header is read somewhere
+    // else
+    builder
+        .i32_const(32)
+        .call(compilation_ctx.allocator)
+        .local_set(header_ptr);
+
+    builder
+        .local_get(slot_ptr)
+        .local_get(header_ptr)
+        .call(storage_load);
+
+    // Short encoded data lives in the header slot, so there is nothing to clear
+    builder
+        .local_get(header_ptr)
+        .load(
+            compilation_ctx.memory_id,
+            LoadKind::I32_8 {
+                kind: ExtendedLoad::ZeroExtend,
+            },
+            MemArg {
+                align: 0,
+                offset: 31,
+            },
+        )
+        .i32_const(1)
+        .binop(BinaryOp::I32And)
+        .if_else(
+            None,
+            |long| {
+                // old_slots = ceil((header >> 1) / 32)
+                long.local_get(header_ptr)
+                    .load(
+                        compilation_ctx.memory_id,
+                        LoadKind::I32 { atomic: false },
+                        MemArg {
+                            align: 0,
+                            offset: 28,
+                        },
+                    )
+                    .call(swap_i32_fn)
+                    .i32_const(1)
+                    .binop(BinaryOp::I32ShrU)
+                    .i32_const(31)
+                    .binop(BinaryOp::I32Add)
+                    .i32_const(5)
+                    .binop(BinaryOp::I32ShrU)
+                    .local_set(old_slots);
+
+                // Data shorter than 32 bytes is saved in the header slot
+                long.local_get(keep_len)
+                    .i32_const(32)
+                    .binop(BinaryOp::I32LtU)
+                    .if_else(
+                        ValType::I32,
+                        |then| {
+                            then.i32_const(0);
+                        },
+                        |else_| {
+                            else_
+                                .local_get(keep_len)
+                                .i32_const(31)
+                                .binop(BinaryOp::I32Add)
+                                .i32_const(5)
+                                .binop(BinaryOp::I32ShrU);
+                        },
+                    )
+                    .local_set(kept_slots);
+
+                // The data starts at keccak256(slot)
+                long.i32_const(32)
+                    .call(compilation_ctx.allocator)
+                    .local_set(data_slot_ptr);
+
+                long.local_get(slot_ptr)
+                    .i32_const(32)
+                    .local_get(data_slot_ptr)
+                    .call(native_keccak);
+
+                long.i32_const(32)
+                    .call(compilation_ctx.allocator)
+                    .local_tee(zero_ptr)
+                    .i32_const(0)
+                    .i32_const(32)
+                    .memory_fill(compilation_ctx.memory_id);
+
+                long.block(None, |block| {
+                    let block_id = block.id();
+                    block.loop_(None, |loop_| {
+                        let loop_id = loop_.id();
+
+                        loop_
+                            .local_get(index)
+                            .local_get(old_slots)
+                            .binop(BinaryOp::I32GeU)
+                            .br_if(block_id);
+
+                        loop_
+                            .local_get(index)
+                            .local_get(kept_slots)
+                            .binop(BinaryOp::I32GeU)
+                            .if_else(
+                                None,
+                                |then| {
+                                    then.local_get(data_slot_ptr)
+                                        .local_get(zero_ptr)
+                                        .call(storage_cache);
+                                },
+                                |_| {},
+                            );
+
+                        loop_
+                            .local_get(data_slot_ptr)
+                            .call(next_slot_fn)
+                            .local_set(data_slot_ptr);
+
+                        loop_
+                            .local_get(index)
+                            .i32_const(1)
+                            .binop(BinaryOp::I32Add)
+                            .local_set(index)
+                            .br(loop_id);
+                    });
+                });
+            },
+            |_| {},
+        );
+
+    function.finish(vec![slot_ptr, keep_len], &mut module.funcs)
+}
@@ -0,0 +1,581 @@
+//! In-WASM write-back cache for the storage.
+//!
+//! During a transaction, every slot read or written is kept in memory in a linked list whose head
+//! is saved in DATA_STORAGE_CACHE_HEAD_OFFSET. Each entry has the following layout:
+//!
+//! [next entry ptr: 4 bytes][dirty flag: 4 bytes][key: 32 bytes][value: 32 bytes]
+//!
+//! Repeated reads of the same slot are served from memory, and repeated writes only update the
+//! cached value. Dirty entries are sent to the host in a single `storage_cache_bytes32` call per
+//! slot when the cache is committed, right before the transaction ends, or flushed, before calling
+//! another contract or when the Move code calls `storage::flush`.
+//!
+//! The number of entries is kept after the head, and the cache holds at most `MAX_ENTRIES`, so
+//! looking up a slot never scans more than them. Once it is full, the dirty entries are sent to
+//! the host, which caches them too until the storage is flushed, and the cache starts over.
+use walrus::{
+    FunctionBuilder, FunctionId, InstrSeqBuilder, LocalId, Module, ValType,
+    ir::{BinaryOp, LoadKind, MemArg, StoreKind, UnaryOp},
+};
+
+use super::RuntimeFunction;
+use crate::{
+    CompilationContext,
+    data::DATA_STORAGE_CACHE_HEAD_OFFSET,
+    hostio::host_functions::{storage_cache_bytes32, storage_flush_cache, storage_load_bytes32},
+};
+
+const ENTRY_NEXT_OFFSET: u32 = 0;
+const ENTRY_DIRTY_OFFSET: u32 = 4;
+const ENTRY_KEY_OFFSET: i32 = 8;
+const ENTRY_VALUE_OFFSET: i32 = 40;
+const ENTRY_SIZE: i32 = 72;
+
+/// Offset of the number of entries, after the head of the list
+const LENGTH_OFFSET: u32 = 4;
+
+/// Number of entries the cache holds before they are evicted
+const MAX_ENTRIES: i32 = 32;
+
+/// Looks for the cache entry of a slot.
+///
+/// # Arguments
+/// - key_ptr: pointer to the slot number (32 bytes)
+///
+/// # Returns
+/// - pointer to the entry, or 0 if the slot is not cached
+pub fn storage_cache_find_entry(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+) -> FunctionId {
+    let mut function = FunctionBuilder::new(&mut module.types, &[ValType::I32], &[ValType::I32]);
+    let mut builder = function
+        .name(RuntimeFunction::StorageCacheFindEntry.name().to_owned())
+        .func_body();
+
+    // Arguments
+    let key_ptr = module.locals.add(ValType::I32);
+
+    // Locals
+    let entry_ptr = module.locals.add(ValType::I32);
+
+    let equality_fn = RuntimeFunction::HeapTypeEquality.get(module, Some(compilation_ctx));
+
+    builder
+        .i32_const(DATA_STORAGE_CACHE_HEAD_OFFSET)
+        .load(
+            compilation_ctx.memory_id,
+            LoadKind::I32 { atomic: false },
+            MemArg {
+                align: 0,
+                offset: 0,
+            },
+        )
+        .local_set(entry_ptr);
+
+    builder.block(None, |block| {
+        let block_id = block.id();
+        block.loop_(None, |loop_| {
+            let loop_id = loop_.id();
+
+            // End of the list, the slot is not cached
+            loop_
+                .local_get(entry_ptr)
+                .unop(UnaryOp::I32Eqz)
+                .br_if(block_id);
+
+            loop_
+                .local_get(entry_ptr)
+                .i32_const(ENTRY_KEY_OFFSET)
+                .binop(BinaryOp::I32Add)
+                .local_get(key_ptr)
+                .i32_const(32)
+                .call(equality_fn)
+                .if_else(
+                    None,
+                    |then| {
+                        then.local_get(entry_ptr).return_();
+                    },
+                    |_| {},
+                );
+
+            loop_
+                .local_get(entry_ptr)
+                .load(
+                    compilation_ctx.memory_id,
+                    LoadKind::I32 { atomic: false },
+                    MemArg {
+                        align: 0,
+                        offset: ENTRY_NEXT_OFFSET,
+                    },
+                )
+                .local_set(entry_ptr)
+                .br(loop_id);
+        });
+    });
+
+    builder.i32_const(0);
+
+    function.finish(vec![key_ptr], &mut module.funcs)
+}
+
+/// Returns the cache entry of a slot, creating it if the slot is not cached. New entries are
+/// inserted at the head of the list, once the cache is evicted if it is full.
+///
+/// If `load_value` is true, the value of new entries is read from the storage.
+fn add_get_or_insert_entry_instructions(
+    builder: &mut InstrSeqBuilder,
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+    key_ptr: LocalId,
+    entry_ptr: LocalId,
+    load_value: bool,
+) {
+    let find_entry_fn = RuntimeFunction::StorageCacheFindEntry.get(module, Some(compilation_ctx));
+    let evict_fn = RuntimeFunction::StorageCacheEvict.get(module, Some(compilation_ctx));
+    let (storage_load, _) = storage_load_bytes32(module);
+
+    builder
+        .local_get(key_ptr)
+        .call(find_entry_fn)
+        .local_tee(entry_ptr)
+        .unop(UnaryOp::I32Eqz)
+        .if_else(
+            None,
+            |then| {
+                then.i32_const(DATA_STORAGE_CACHE_HEAD_OFFSET)
+                    .load(
+                        compilation_ctx.memory_id,
+                        LoadKind::I32 { atomic: false },
+                        MemArg {
+                            align: 0,
+                            offset: LENGTH_OFFSET,
+                        },
+                    )
+                    .i32_const(MAX_ENTRIES)
+                    .binop(BinaryOp::I32GeU)
+                    .if_else(
+                        None,
+                        |full| {
+                            full.call(evict_fn);
+                        },
+                        |_| {},
+                    );
+
+                then.i32_const(ENTRY_SIZE)
+                    .call(compilation_ctx.allocator)
+                    .local_set(entry_ptr);
+
+                // Copy the key
+                then.local_get(entry_ptr)
+                    .i32_const(ENTRY_KEY_OFFSET)
+                    .binop(BinaryOp::I32Add)
+                    .local_get(key_ptr)
+                    .i32_const(32)
+                    .memory_copy(compilation_ctx.memory_id, compilation_ctx.memory_id);
+
+                if load_value {
+                    then.local_get(key_ptr)
+                        .local_get(entry_ptr)
+                        .i32_const(ENTRY_VALUE_OFFSET)
+                        .binop(BinaryOp::I32Add)
+                        .call(storage_load);
+                }
+
+                // Insert the entry at the head of the list
+                then.local_get(entry_ptr)
+                    .i32_const(DATA_STORAGE_CACHE_HEAD_OFFSET)
+                    .load(
+                        compilation_ctx.memory_id,
+                        LoadKind::I32 { atomic: false },
+                        MemArg {
+                            align: 0,
+                            offset: 0,
+                        },
+                    )
+                    .store(
+                        compilation_ctx.memory_id,
+                        StoreKind::I32 { atomic: false },
+                        MemArg {
+                            align: 0,
+                            offset: ENTRY_NEXT_OFFSET,
+                        },
+                    );
+
+                then.i32_const(DATA_STORAGE_CACHE_HEAD_OFFSET)
+                    .local_get(entry_ptr)
+                    .store(
+                        compilation_ctx.memory_id,
+                        StoreKind::I32 { atomic: false },
+                        MemArg {
+                            align: 0,
+                            offset: 0,
+                        },
+                    );
+
+                then.i32_const(DATA_STORAGE_CACHE_HEAD_OFFSET)
+                    .i32_const(DATA_STORAGE_CACHE_HEAD_OFFSET)
+                    .load(
+                        compilation_ctx.memory_id,
+                        LoadKind::I32 { atomic: false },
+                        MemArg {
+                            align: 0,
+                            offset: LENGTH_OFFSET,
+                        },
+                    )
+                    .i32_const(1)
+                    .binop(BinaryOp::I32Add)
+                    .store(
+                        compilation_ctx.memory_id,
+                        StoreKind::I32 { atomic: false },
+                        MemArg {
+                            align: 0,
+                            offset: LENGTH_OFFSET,
+                        },
+                    );
+            },
+            |_| {},
+        );
+}
+
+/// Reads a slot through the cache. If the slot is not cached, it is read from the storage.
+///
+/// Same interface as the `storage_load_bytes32` host function.
+///
+/// # Arguments
+/// - key_ptr: pointer to the slot number (32 bytes)
+/// - dest_ptr: where the 32 bytes of the slot are written
+pub fn storage_cached_load(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+) -> FunctionId {
+    let mut function = FunctionBuilder::new(&mut module.types, &[ValType::I32, ValType::I32], &[]);
+    let mut builder = function
+        .name(RuntimeFunction::StorageCachedLoad.name().to_owned())
+        .func_body();
+
+    // Arguments
+    let key_ptr = module.locals.add(ValType::I32);
+    let dest_ptr = module.locals.add(ValType::I32);
+
+    // Locals
+    let entry_ptr = module.locals.add(ValType::I32);
+
+    add_get_or_insert_entry_instructions(
+        &mut builder,
+        module,
+        compilation_ctx,
+        key_ptr,
+        entry_ptr,
+        true,
+    );
+
+    builder
+        .local_get(dest_ptr)
+        .local_get(entry_ptr)
+        .i32_const(ENTRY_VALUE_OFFSET)
+        .binop(BinaryOp::I32Add)
+        .i32_const(32)
+        .memory_copy(compilation_ctx.memory_id, compilation_ctx.memory_id);
+
+    function.finish(vec![key_ptr, dest_ptr], &mut module.funcs)
+}
+
+/// Writes a slot in the cache and marks it as dirty. It is saved in the storage when the cache
+/// is committed.
+///
+/// Same interface as the `storage_cache_bytes32` host function.
+///
+/// # Arguments
+/// - key_ptr: pointer to the slot number (32 bytes)
+/// - value_ptr: pointer to the 32 bytes to write
+pub fn storage_cached_store(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+) -> FunctionId {
+    let mut function = FunctionBuilder::new(&mut module.types, &[ValType::I32, ValType::I32], &[]);
+    let mut builder = function
+        .name(RuntimeFunction::StorageCachedStore.name().to_owned())
+        .func_body();
+
+    // Arguments
+    let key_ptr = module.locals.add(ValType::I32);
+    let value_ptr = module.locals.add(ValType::I32);
+
+    // Locals
+    let entry_ptr = module.locals.add(ValType::I32);
+
+    // The whole slot is overwritten, so there's no need to read its current value
+    add_get_or_insert_entry_instructions(
+        &mut builder,
+        module,
+        compilation_ctx,
+        key_ptr,
+        entry_ptr,
+        false,
+    );
+
+    builder
+        .local_get(entry_ptr)
+        .i32_const(ENTRY_VALUE_OFFSET)
+        .binop(BinaryOp::I32Add)
+        .local_get(value_ptr)
+        .i32_const(32)
+        .memory_copy(compilation_ctx.memory_id, compilation_ctx.memory_id);
+
+    builder.local_get(entry_ptr).i32_const(1).store(
+        compilation_ctx.memory_id,
+        StoreKind::I32 { atomic: false },
+        MemArg {
+            align: 0,
+            offset: ENTRY_DIRTY_OFFSET,
+        },
+    );
+
+    function.finish(vec![key_ptr, value_ptr], &mut module.funcs)
+}
+
+/// Reads `n` contiguous slots from the cache, starting at `key_ptr`, into `dest_ptr`. The slot
+/// `i` is written at `dest_ptr + 32 * i`.
+///
+/// As with `StorageNextSlot`, the slot number is advanced in place, so after the call `key_ptr`
+/// points to the last slot read.
+///
+/// # Arguments
+/// - key_ptr: pointer to the first slot number (32 bytes)
+/// - dest_ptr: where the `n * 32` bytes of the slots are written
+/// - n: number of slots to read, at least one
+pub fn storage_cached_load_slots(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+) -> FunctionId {
+    add_slots_loop_function(
+        module,
+        compilation_ctx,
+        RuntimeFunction::StorageCachedLoadSlots,
+        RuntimeFunction::StorageCachedLoad,
+    )
+}
+
+/// Writes `n` contiguous slots in the cache, starting at `key_ptr`, from `value_ptr`. The slot
+/// `i` is read from `value_ptr + 32 * i`.
+///
+/// As with `StorageNextSlot`, the slot number is advanced in place, so after the call `key_ptr`
+/// points to the last slot written.
+///
+/// # Arguments
+/// - key_ptr: pointer to the first slot number (32 bytes)
+/// - value_ptr: pointer to the `n * 32` bytes to write
+/// - n: number of slots to write, at least one
+pub fn storage_cached_store_slots(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+) -> FunctionId {
+    add_slots_loop_function(
+        module,
+        compilation_ctx,
+        RuntimeFunction::StorageCachedStoreSlots,
+        RuntimeFunction::StorageCachedStore,
+    )
+}
+
+/// Builds a function that calls `slot_fn` (`StorageCachedLoad` or `StorageCachedStore`) for `n`
+/// contiguous slots, moving the data pointer 32 bytes forward after each one.
+fn add_slots_loop_function(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+    function_name: RuntimeFunction,
+    slot_fn: RuntimeFunction,
+) -> FunctionId {
+    let mut function = FunctionBuilder::new(
+        &mut module.types,
+        &[ValType::I32, ValType::I32, ValType::I32],
+        &[],
+    );
+    let mut builder = function.name(function_name.name().to_owned()).func_body();
+
+    // Arguments
+    let key_ptr = module.locals.add(ValType::I32);
+    let data_ptr = module.locals.add(ValType::I32);
+    let n = module.locals.add(ValType::I32);
+
+    let slot_fn = slot_fn.get(module, Some(compilation_ctx));
+    let next_slot_fn = RuntimeFunction::StorageNextSlot.get(module, Some(compilation_ctx));
+
+    builder.block(None, |block| {
+        let block_id = block.id();
+        block.loop_(None, |loop_| {
+            let loop_id = loop_.id();
+
+            loop_.local_get(key_ptr).local_get(data_ptr).call(slot_fn);
+
+            // n -= 1, and stop after the last slot so the key points to it
+            loop_
+                .local_get(n)
+                .i32_const(1)
+                .binop(BinaryOp::I32Sub)
+                .local_tee(n)
+                .unop(UnaryOp::I32Eqz)
+                .br_if(block_id);
+
+            loop_
+                .local_get(key_ptr)
+                .call(next_slot_fn)
+                .local_set(key_ptr);
+
+            loop_
+                .local_get(data_ptr)
+                .i32_const(32)
+                .binop(BinaryOp::I32Add)
+                .local_set(data_ptr)
+                .br(loop_id);
+        });
+    });
+
+    function.finish(vec![key_ptr, data_ptr, n], &mut module.funcs)
+}
+
+/// Saves the values borrowed from the storage and flushes the cache.
+///
+/// This must be called once, before the transaction ends.
+pub fn storage_cache_commit(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+) -> FunctionId {
+    let mut function = FunctionBuilder::new(&mut module.types, &[], &[]);
+    let mut builder = function
+        .name(RuntimeFunction::StorageCacheCommit.name().to_owned())
+        .func_body();
+
+    let write_back_fn = RuntimeFunction::StorageWriteBack.get(module, Some(compilation_ctx));
+    let flush_fn = RuntimeFunction::StorageCacheFlush.get(module, Some(compilation_ctx));
+
+    builder.call(write_back_fn).i32_const(0).call(flush_fn);
+
+    function.finish(vec![], &mut module.funcs)
+}
+
+/// Saves the values borrowed from the storage, sends every dirty slot of the cache to the host,
+/// flushes the host's storage cache and empties the cache.
+///
+/// Besides committing the cache, this is called before handing the execution to another contract,
+/// so it sees the slots written so far, and the slots it writes, for example in a reentrant call,
+/// are loaded again afterwards, and by `storage::flush`. The borrowed values stay registered, as
+/// they can still be modified after the call, and are saved again when the transaction ends.
+///
+/// # Arguments
+/// - clear: if true, the host's storage cache is dropped too
+pub fn storage_cache_flush(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+) -> FunctionId {
+    let mut function = FunctionBuilder::new(&mut module.types, &[ValType::I32], &[]);
+    let mut builder = function
+        .name(RuntimeFunction::StorageCacheFlush.name().to_owned())
+        .func_body();
+
+    // Arguments
+    let clear = module.locals.add(ValType::I32);
+
+    let save_borrowed_fn = RuntimeFunction::StorageSaveBorrowed.get(module, Some(compilation_ctx));
+    let evict_fn = RuntimeFunction::StorageCacheEvict.get(module, Some(compilation_ctx));
+    let (storage_flush_cache, _) = storage_flush_cache(module);
+
+    builder.call(save_borrowed_fn).call(evict_fn);
+    builder.local_get(clear).call(storage_flush_cache);
+
+    function.finish(vec![clear], &mut module.funcs)
+}
+
+/// Sends every dirty slot of the cache to the host and empties the cache. The host caches the
+/// slots until its storage cache is flushed, so the slots read afterwards get the values written.
+pub fn storage_cache_evict(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+) -> FunctionId {
+    let mut function = FunctionBuilder::new(&mut module.types, &[], &[]);
+    let mut builder = function
+        .name(RuntimeFunction::StorageCacheEvict.name().to_owned())
+        .func_body();
+
+    // Locals
+    let entry_ptr = module.locals.add(ValType::I32);
+
+    let (storage_cache, _) = storage_cache_bytes32(module);
+
+    builder
+        .i32_const(DATA_STORAGE_CACHE_HEAD_OFFSET)
+        .load(
+            compilation_ctx.memory_id,
+            LoadKind::I32 { atomic: false },
+            MemArg {
+                align: 0,
+                offset: 0,
+            },
+        )
+        .local_set(entry_ptr);
+
+    builder.block(None, |block| {
+        let block_id = block.id();
+        block.loop_(None, |loop_| {
+            let loop_id = loop_.id();
+
+            loop_
+                .local_get(entry_ptr)
+                .unop(UnaryOp::I32Eqz)
+                .br_if(block_id);
+
+            loop_
+                .local_get(entry_ptr)
+                .load(
+                    compilation_ctx.memory_id,
+                    LoadKind::I32 { atomic: false },
+                    MemArg {
+                        align: 0,
+                        offset: ENTRY_DIRTY_OFFSET,
+                    },
+                )
+                .if_else(
+                    None,
+                    |then| {
+                        then.local_get(entry_ptr)
+                            .i32_const(ENTRY_KEY_OFFSET)
+                            .binop(BinaryOp::I32Add)
+                            .local_get(entry_ptr)
+                            .i32_const(ENTRY_VALUE_OFFSET)
+                            .binop(BinaryOp::I32Add)
+                            .call(storage_cache);
+                    },
+                    |_| {},
+                );
+
+            loop_
+                .local_get(entry_ptr)
+                .load(
+                    compilation_ctx.memory_id,
+                    LoadKind::I32 { atomic: false },
+                    MemArg {
+                        align: 0,
+                        offset: ENTRY_NEXT_OFFSET,
+                    },
+                )
+                .local_set(entry_ptr)
+                .br(loop_id);
+        });
+    });
+
+    // Empty the cache, clearing the head and the number of entries at once
+    builder
+        .i32_const(DATA_STORAGE_CACHE_HEAD_OFFSET)
+        .i64_const(0)
+        .store(
+            compilation_ctx.memory_id,
+            StoreKind::I64 { atomic: false },
+            MemArg {
+                align: 0,
+                offset: 0,
+            },
+        );
+
+    function.finish(vec![], &mut module.funcs)
+}
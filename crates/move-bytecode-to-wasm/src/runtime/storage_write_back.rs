@@ -0,0 +1,273 @@
+//! Write-back of values mutably borrowed from the storage.
+//!
+//! Collections such as `Table` and `Bag` hand out `&mut V` references to values that live in
+//! derived storage slots. The value is decoded into memory and the reference points to it, so the
+//! changes made through the reference must be encoded back into the storage once the borrow ends.
+//!
+//! Every mutable borrow is registered in a linked list whose head is saved in
+//! DATA_STORAGE_WRITE_BACK_HEAD_OFFSET. Each entry has the following layout:
+//!
+//! [next entry ptr: 4 bytes][write back function tag: 4 bytes][value ref: 4 bytes][slot ptr: 4 bytes]
+//!
+//! The list is drained by `storage_write_back`, which must be called before the borrowed values
+//! are accessed again (the Move borrow checker guarantees the reference is no longer alive at that
+//! point) and before the storage cache is committed.
+//!
+//! Before an external call the values are saved by `storage_save_borrowed` instead, which keeps
+//! the list: the called contract, or a reentrant call, must see them, but the references can
+//! still be alive, and the changes made through them after the call are saved again later.
+//!
+//! Because the type of the borrowed values is only known when the borrow is translated, the body
+//! of `storage_save_borrowed` is filled at the end of the translation by
+//! `finalize_storage_write_back`, dispatching each entry to the encoding function of its type.
+use std::collections::HashMap;
+
+use move_binary_format::file_format::StructDefinitionIndex;
+use walrus::{
+    FunctionBuilder, FunctionId, FunctionKind, InstrSeqBuilder, LocalId, Module, ValType,
+    ir::{BinaryOp, LoadKind, MemArg, StoreKind, UnaryOp},
+};
+
+use super::RuntimeFunction;
+use crate::{
+    CompilationContext,
+    data::DATA_SLOT_DATA_PTR_OFFSET,
+    data::DATA_STORAGE_WRITE_BACK_HEAD_OFFSET,
+    get_generic_function_name,
+    storage::encoding::add_encode_and_save_into_storage_struct_instructions,
+    translation::intermediate_types::{IntermediateType, structs::IStruct},
+};
+
+const ENTRY_NEXT_OFFSET: u32 = 0;
+const ENTRY_TAG_OFFSET: u32 = 4;
+const ENTRY_VALUE_OFFSET: u32 = 8;
+const ENTRY_SLOT_OFFSET: u32 = 12;
+const ENTRY_SIZE: i32 = 16;
+
+const WRITE_BACK_VALUE_FN_NAME: &str = "storage_write_back_value";
+
+/// Saves in the storage all the values registered with `add_register_write_back_instructions`, and
+/// empties the list.
+pub fn storage_write_back(module: &mut Module, compilation_ctx: &CompilationContext) -> FunctionId {
+    let save_borrowed_fn = RuntimeFunction::StorageSaveBorrowed.get(module, Some(compilation_ctx));
+
+    let mut function = FunctionBuilder::new(&mut module.types, &[], &[]);
+    let mut builder = function
+        .name(RuntimeFunction::StorageWriteBack.name().to_owned())
+        .func_body();
+
+    builder.call(save_borrowed_fn);
+
+    // Empty the list
+    builder
+        .i32_const(DATA_STORAGE_WRITE_BACK_HEAD_OFFSET)
+        .i32_const(0);
+    store_field(&mut builder, compilation_ctx, 0);
+
+    function.finish(vec![], &mut module.funcs)
+}
+
+/// Declares the function that saves in the storage all the values registered with
+/// `add_register_write_back_instructions`, keeping them registered. Its body is generated by
+/// `finalize_storage_write_back`.
+pub fn storage_save_borrowed(module: &mut Module) -> FunctionId {
+    let mut function = FunctionBuilder::new(&mut module.types, &[], &[]);
+    function.name(RuntimeFunction::StorageSaveBorrowed.name().to_owned());
+
+    function.finish(vec![], &mut module.funcs)
+}
+
+/// Adds the instructions to register a value borrowed from the storage, so it is saved back into
+/// `slot_ptr` the next time `storage_write_back` is called.
+///
+/// # Arguments
+/// - `value_ref` - reference to the borrowed value, as returned to the Move code
+/// - `slot_ptr` - pointer to the first slot where the value is saved
+pub fn add_register_write_back_instructions(
+    builder: &mut InstrSeqBuilder,
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+    value_ref: LocalId,
+    slot_ptr: LocalId,
+    value_type: &IntermediateType,
+) {
+    let write_back_value_fn = add_write_back_value_fn(module, compilation_ctx, value_type);
+    let entry_ptr = module.locals.add(ValType::I32);
+
+    builder
+        .i32_const(ENTRY_SIZE)
+        .call(compilation_ctx.allocator)
+        .local_set(entry_ptr);
+
+    // entry.next = head
+    builder
+        .local_get(entry_ptr)
+        .i32_const(DATA_STORAGE_WRITE_BACK_HEAD_OFFSET);
+    load_field(builder, compilation_ctx, 0);
+    store_field(builder, compilation_ctx, ENTRY_NEXT_OFFSET);
+
+    // The function id identifies the encoding function to use
+    builder
+        .local_get(entry_ptr)
+        .i32_const(write_back_value_fn.index() as i32);
+    store_field(builder, compilation_ctx, ENTRY_TAG_OFFSET);
+
+    builder.local_get(entry_ptr).local_get(value_ref);
+    store_field(builder, compilation_ctx, ENTRY_VALUE_OFFSET);
+
+    builder.local_get(entry_ptr).local_get(slot_ptr);
+    store_field(builder, compilation_ctx, ENTRY_SLOT_OFFSET);
+
+    // head = entry
+    builder
+        .i32_const(DATA_STORAGE_WRITE_BACK_HEAD_OFFSET)
+        .local_get(entry_ptr);
+    store_field(builder, compilation_ctx, 0);
+}
+
+/// Adds a function that encodes a value of type `value_type` and saves it in the storage.
+///
+/// Arguments:
+/// - value reference
+/// - slot pointer
+fn add_write_back_value_fn(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+    value_type: &IntermediateType,
+) -> FunctionId {
+    let name = get_generic_function_name(WRITE_BACK_VALUE_FN_NAME, &[value_type]);
+    if let Some(function) = module.funcs.by_name(&name) {
+        return function;
+    };
+
+    let mut function = FunctionBuilder::new(&mut module.types, &[ValType::I32, ValType::I32], &[]);
+    let mut builder = function.name(name).func_body();
+
+    // Arguments
+    let value_ref = module.locals.add(ValType::I32);
+    let slot_ptr = module.locals.add(ValType::I32);
+
+    // Wipe the slot data placeholder, the first slot is not wiped by the encoding function
+    builder
+        .i32_const(DATA_SLOT_DATA_PTR_OFFSET)
+        .i32_const(0)
+        .i32_const(32)
+        .memory_fill(compilation_ctx.memory_id);
+
+    // A reference points to the value pointer, which is the same memory layout as a struct with
+    // a single field
+    let wrapper = IStruct::new(
+        StructDefinitionIndex::new(0),
+        "BorrowedValue".to_owned(),
+        vec![(None, value_type.clone())],
+        HashMap::new(),
+        false,
+        false,
+    );
+
+    add_encode_and_save_into_storage_struct_instructions(
+        module,
+        &mut builder,
+        compilation_ctx,
+        value_ref,
+        slot_ptr,
+        &wrapper,
+        0,
+    );
+
+    function.finish(vec![value_ref, slot_ptr], &mut module.funcs)
+}
+
+/// Generates the body of `storage_save_borrowed`. It must be called once all the functions of the
+/// module are translated, so every registered value type is known.
+///
+/// If `storage_save_borrowed` was never linked, nothing is done.
+pub fn finalize_storage_write_back(module: &mut Module, compilation_ctx: &CompilationContext) {
+    let Some(save_borrowed_fn) = module
+        .funcs
+        .by_name(RuntimeFunction::StorageSaveBorrowed.name())
+    else {
+        return;
+    };
+
+    let write_back_value_fns: Vec<FunctionId> = module
+        .funcs
+        .iter()
+        .filter(|f| {
+            f.name
+                .as_deref()
+                .is_some_and(|name| name.starts_with(WRITE_BACK_VALUE_FN_NAME))
+        })
+        .map(|f| f.id())
+        .collect();
+
+    // Locals
+    let entry_ptr = module.locals.add(ValType::I32);
+    let tag = module.locals.add(ValType::I32);
+
+    let FunctionKind::Local(function) = &mut module.funcs.get_mut(save_borrowed_fn).kind else {
+        panic!(
+            "{} is not a local function",
+            RuntimeFunction::StorageSaveBorrowed.name()
+        );
+    };
+    let mut builder = function.builder_mut().func_body();
+
+    builder.i32_const(DATA_STORAGE_WRITE_BACK_HEAD_OFFSET);
+    load_field(&mut builder, compilation_ctx, 0);
+    builder.local_set(entry_ptr);
+
+    builder.block(None, |block| {
+        let block_id = block.id();
+        block.loop_(None, |loop_| {
+            let loop_id = loop_.id();
+
+            loop_
+                .local_get(entry_ptr)
+                .unop(UnaryOp::I32Eqz)
+                .br_if(block_id);
+
+            loop_.local_get(entry_ptr);
+            load_field(loop_, compilation_ctx, ENTRY_TAG_OFFSET);
+            loop_.local_set(tag);
+
+            for write_back_value_fn in &write_back_value_fns {
+                loop_
+                    .local_get(tag)
+                    .i32_const(write_back_value_fn.index() as i32)
+                    .binop(BinaryOp::I32Eq)
+                    .if_else(
+                        None,
+                        |then| {
+                            then.local_get(entry_ptr);
+                            load_field(then, compilation_ctx, ENTRY_VALUE_OFFSET);
+                            then.local_get(entry_ptr);
+                            load_field(then, compilation_ctx, ENTRY_SLOT_OFFSET);
+                            then.call(*write_back_value_fn);
+                        },
+                        |_| {},
+                    );
+            }
+
+            loop_.local_get(entry_ptr);
+            load_field(loop_, compilation_ctx, ENTRY_NEXT_OFFSET);
+            loop_.local_set(entry_ptr).br(loop_id);
+        });
+    });
+}
+
+fn load_field(builder: &mut InstrSeqBuilder, compilation_ctx: &CompilationContext, offset: u32) {
+    builder.load(
+        compilation_ctx.memory_id,
+        LoadKind::I32 { atomic: false },
+        MemArg { align: 0, offset },
+    );
+}
+
+fn store_field(builder: &mut InstrSeqBuilder, compilation_ctx: &CompilationContext, offset: u32) {
+    builder.store(
+        compilation_ctx.memory_id,
+        StoreKind::I32 { atomic: false },
+        MemArg { align: 0, offset },
+    );
+}
@@ -1 +1,2 @@
 pub const ERROR_NO_FUNCTION_MATCH: i32 = -100;
+pub const ERROR_REENTRANT_CALL: i32 = -101;
@@ -0,0 +1,325 @@
+//! Debug information of the root modules, built with the `debug_info` option, so the stack traces
+//! of nodes and debuggers point to the Move source: the locals of the functions are named after
+//! the Move parameters and locals in the name section, and `<module>.wasm.map`, a source map in
+//! the version 3 format, maps the offsets of the instructions in the WASM to the Move source.
+//!
+//! The first WASM instruction translated from every Move instruction is tagged with the id of its
+//! location. When the module is emitted, walrus hands the offsets the tagged instructions are
+//! emitted at to the `sourceMappingURL` section, which points debuggers to the source map. Only
+//! the functions of the root module are mapped, as in the coverage map.
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    sync::{Arc, Mutex},
+};
+
+use move_binary_format::file_format::FunctionDefinitionIndex;
+use move_package::compilation::compiled_package::CompiledUnitWithSource;
+use serde::Serialize;
+use walrus::{
+    CodeTransform, CustomSection, IdsToIndices, InstrLocId, InstrSeqBuilder, LocalId, Module,
+};
+
+/// Section pointing to the source map, the one browsers and debuggers look for
+const SOURCE_MAPPING_URL_SECTION: &str = "sourceMappingURL";
+
+/// Offsets the tagged instructions are emitted at, in the whole module
+type EmittedOffsets = Arc<Mutex<Vec<(InstrLocId, usize)>>>;
+
+/// Locations of the instructions of the root module, collected while it is translated.
+pub(crate) struct DebugInfo<'a> {
+    move_unit: &'a CompiledUnitWithSource,
+    /// Function and offset of the Move instruction of every id the WASM instructions are tagged
+    /// with, the id being its position
+    locations: RefCell<Vec<(FunctionDefinitionIndex, u16)>>,
+}
+
+impl<'a> DebugInfo<'a> {
+    pub(crate) fn new(move_unit: &'a CompiledUnitWithSource) -> Self {
+        Self {
+            move_unit,
+            locations: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Index of the definition of the root module's function named `identifier`.
+    pub(crate) fn function_index(&self, identifier: &str) -> Option<FunctionDefinitionIndex> {
+        let move_module = &self.move_unit.unit.module;
+        move_module
+            .function_defs()
+            .iter()
+            .position(|function_def| {
+                let handle = move_module.function_handle_at(function_def.function);
+                move_module.identifier_at(handle.name).as_str() == identifier
+            })
+            .map(|index| FunctionDefinitionIndex::new(index as u16))
+    }
+
+    /// Names the locals of the function, its arguments followed by its declared locals, after the
+    /// Move ones, without the suffixes the compiler adds to tell apart the shadowed ones.
+    pub(crate) fn name_locals(
+        &self,
+        module: &mut Module,
+        function: FunctionDefinitionIndex,
+        locals: &[LocalId],
+    ) {
+        let Ok(function_source_map) = self
+            .move_unit
+            .unit
+            .source_map
+            .get_function_source_map(function)
+        else {
+            return;
+        };
+
+        for (index, local) in locals.iter().enumerate() {
+            if let Some((name, _)) = function_source_map.get_parameter_or_local_name(index as u64) {
+                let name = name.split('#').next().unwrap_or(&name).to_owned();
+                module.locals.get_mut(*local).name = Some(name);
+            }
+        }
+    }
+
+    /// Tags the first instruction the Move instruction at `offset` of the function was translated
+    /// to, the one at `first` in the block, if it was translated to any.
+    pub(crate) fn tag_instruction(
+        &self,
+        block: &mut InstrSeqBuilder,
+        first: usize,
+        function: FunctionDefinitionIndex,
+        offset: u16,
+    ) {
+        let Some((_, location_id)) = block.instrs_mut().get_mut(first) else {
+            return;
+        };
+
+        let mut locations = self.locations.borrow_mut();
+        *location_id = InstrLocId::new(locations.len() as u32);
+        locations.push((function, offset));
+    }
+
+    /// Resolves the lines and columns of the instructions tagged, and adds the section pointing
+    /// to the source map to the module, which collects their offsets when it is emitted.
+    pub(crate) fn finish(self, module: &mut Module, module_name: &str) -> SourceMap {
+        let source = std::fs::read_to_string(&self.move_unit.source_path).unwrap_or_default();
+        let source_map = &self.move_unit.unit.source_map;
+
+        let locations = self
+            .locations
+            .into_inner()
+            .into_iter()
+            .map(|(function, offset)| {
+                source_map
+                    .get_function_source_map(function)
+                    .ok()?
+                    .get_code_location(offset)
+                    .map(|loc| line_column_at(&source, loc.start()))
+            })
+            .collect();
+
+        let offsets = EmittedOffsets::default();
+        module.customs.add(SourceMappingUrl {
+            url: format!("{module_name}.wasm.map"),
+            offsets: Arc::clone(&offsets),
+        });
+
+        SourceMap {
+            source_path: self.move_unit.source_path.to_string_lossy().into_owned(),
+            locations,
+            offsets,
+        }
+    }
+}
+
+/// Map from the offsets of the instructions of a module to the Move source, complete once the
+/// module is emitted.
+#[derive(Debug)]
+pub struct SourceMap {
+    /// Path of the module's source file
+    source_path: String,
+    /// Line and column, starting from 0, of the instruction tagged with every id, `None` if the
+    /// source map of the Move module does not have it
+    locations: Vec<Option<(u32, u32)>>,
+    offsets: EmittedOffsets,
+}
+
+/// Source map in the version 3 format, with the byte offsets in the module as columns of its only
+/// line.
+#[derive(Serialize)]
+struct SourceMapV3<'a> {
+    version: u32,
+    sources: [&'a str; 1],
+    names: [&'a str; 0],
+    mappings: String,
+}
+
+impl SourceMap {
+    /// Sets the URL the module points to its source map with, the name of the map, next to the
+    /// module, by default. It must be set before the module is emitted.
+    pub fn set_url(module: &mut Module, url: String) {
+        if let Some(section) = module.customs.get_typed_mut::<SourceMappingUrl>() {
+            section.url = url;
+        }
+    }
+
+    /// Serializes the map of the module last emitted.
+    pub fn to_json(&self) -> String {
+        let mut segments: Vec<(usize, u32, u32)> = self
+            .offsets
+            .lock()
+            .expect("the offsets of the source map are not poisoned")
+            .iter()
+            .filter_map(|(location_id, offset)| {
+                let (line, column) = (*self.locations.get(location_id.data() as usize)?)?;
+                Some((*offset, line, column))
+            })
+            .collect();
+        segments.sort();
+
+        // Every field of a segment is relative to the one of the previous segment
+        let mut mappings = String::new();
+        let mut previous = (0, 0, 0);
+        for (offset, line, column) in segments {
+            if !mappings.is_empty() {
+                mappings.push(',');
+            }
+            encode_vlq(&mut mappings, offset as i64 - previous.0 as i64);
+            // The only source
+            encode_vlq(&mut mappings, 0);
+            encode_vlq(&mut mappings, line as i64 - previous.1 as i64);
+            encode_vlq(&mut mappings, column as i64 - previous.2 as i64);
+            previous = (offset, line, column);
+        }
+
+        serde_json::to_string_pretty(&SourceMapV3 {
+            version: 3,
+            sources: [&self.source_path],
+            names: [],
+            mappings,
+        })
+        .expect("failed to serialize the source map")
+    }
+}
+
+/// Section holding the URL of the source map, which keeps the offsets of the tagged instructions
+/// walrus emits the module with.
+#[derive(Debug)]
+struct SourceMappingUrl {
+    url: String,
+    offsets: EmittedOffsets,
+}
+
+impl CustomSection for SourceMappingUrl {
+    fn name(&self) -> &str {
+        SOURCE_MAPPING_URL_SECTION
+    }
+
+    fn data(&self, _ids_to_indices: &IdsToIndices) -> Cow<'_, [u8]> {
+        // A WASM string: the length of the URL, in LEB128, followed by it
+        let mut data = Vec::new();
+        let mut length = self.url.len();
+        loop {
+            let byte = (length & 0x7f) as u8;
+            length >>= 7;
+            if length == 0 {
+                data.push(byte);
+                break;
+            }
+            data.push(byte | 0x80);
+        }
+        data.extend_from_slice(self.url.as_bytes());
+        Cow::Owned(data)
+    }
+
+    fn apply_code_transform(&mut self, transform: &CodeTransform) {
+        *self
+            .offsets
+            .lock()
+            .expect("the offsets of the source map are not poisoned") =
+            transform.instruction_map.clone();
+    }
+}
+
+/// Appends the value to the mappings in base64 VLQ, as the version 3 format encodes them.
+fn encode_vlq(mappings: &mut String, value: i64) {
+    const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    // The sign is kept in the lowest bit
+    let mut vlq = if value < 0 {
+        (value.unsigned_abs() << 1) | 1
+    } else {
+        (value as u64) << 1
+    };
+    loop {
+        let mut digit = (vlq & 0b1_1111) as usize;
+        vlq >>= 5;
+        if vlq > 0 {
+            // Continued in the next digit
+            digit |= 0b10_0000;
+        }
+        mappings.push(BASE64[digit] as char);
+        if vlq == 0 {
+            break;
+        }
+    }
+}
+
+/// Returns the line and the column, starting from 0, of the byte at `offset` in the source.
+fn line_column_at(source: &str, offset: u32) -> (u32, u32) {
+    let offset = (offset as usize).min(source.len());
+    let before = &source.as_bytes()[..offset];
+    let line_start = before
+        .iter()
+        .rposition(|byte| *byte == b'\n')
+        .map_or(0, |newline| newline + 1);
+    let line = before.iter().filter(|byte| **byte == b'\n').count();
+    (line as u32, (offset - line_start) as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_vlq() {
+        for (value, expected) in [
+            (0, "A"),
+            (1, "C"),
+            (-1, "D"),
+            (15, "e"),
+            (16, "gB"),
+            (-17, "jB"),
+        ] {
+            let mut mappings = String::new();
+            encode_vlq(&mut mappings, value);
+            assert_eq!(expected, mappings, "{value}");
+        }
+    }
+
+    #[test]
+    fn test_line_column_at() {
+        let source = "module a::b;\n\nfun f() {\n    abort 1\n}\n";
+        assert_eq!((0, 0), line_column_at(source, 0));
+        assert_eq!((0, 7), line_column_at(source, 7));
+        assert_eq!((3, 4), line_column_at(source, 28));
+    }
+
+    #[test]
+    fn test_source_map_to_json() {
+        let source_map = SourceMap {
+            source_path: "sources/counter.move".to_owned(),
+            locations: vec![Some((3, 4)), None, Some((4, 8))],
+            offsets: Arc::new(Mutex::new(vec![
+                (InstrLocId::new(2), 120),
+                (InstrLocId::new(0), 100),
+                (InstrLocId::new(1), 110),
+            ])),
+        };
+
+        let json: serde_json::Value = serde_json::from_str(&source_map.to_json()).unwrap();
+        assert_eq!(3, json["version"]);
+        assert_eq!("sources/counter.move", json["sources"][0]);
+        // Offset 100 at 3:4, then offset 120 at 4:8
+        assert_eq!("oGAGI,oBACI", json["mappings"]);
+    }
+}
@@ -1 +1,7 @@
 pub mod encoding;
+pub mod gas_report;
+pub mod layout;
+pub mod namespace;
+pub mod pinned_slots;
+pub mod type_tag;
+pub mod versioning;
@@ -11,14 +11,20 @@ use walrus::{
 use crate::{
     CompilationContext,
     data::{DATA_SLOT_DATA_PTR_OFFSET, DATA_STORAGE_OBJECT_OWNER_OFFSET},
-    hostio::host_functions::{storage_cache_bytes32, storage_load_bytes32},
     runtime::RuntimeFunction,
+    storage::{
+        pinned_slots::add_pin_slot_instructions,
+        versioning::{
+            STORAGE_VERSION_SIZE, add_migrate_instructions, add_read_version_instructions,
+            add_write_version_instructions,
+        },
+    },
     translation::intermediate_types::{
         IntermediateType,
         heap_integers::{IU128, IU256},
         structs::IStruct,
     },
-    vm_handled_types::{VmHandledType, uid::Uid},
+    vm_handled_types::{VmHandledType, mapping::Mapping, storage_vec::StorageVec, uid::Uid},
 };
 
 /// Adds the instructions to encode and save into storage an specific struct.
@@ -43,7 +49,78 @@ pub fn add_encode_and_save_into_storage_struct_instructions(
     struct_: &IStruct,
     written_bytes_in_slot: u32,
 ) -> u32 {
-    let (storage_cache, _) = storage_cache_bytes32(module);
+    // Versioned structs save their version before the first field
+    let written_bytes_in_slot = match struct_.storage_version {
+        Some(version) if written_bytes_in_slot == 0 => {
+            add_write_version_instructions(builder, compilation_ctx, version);
+            STORAGE_VERSION_SIZE
+        }
+        _ => written_bytes_in_slot,
+    };
+
+    let mut slot_data = add_slot_data_instructions(
+        module,
+        builder,
+        compilation_ctx,
+        struct_,
+        written_bytes_in_slot,
+    );
+
+    let written_bytes_in_slot = add_encode_struct_fields_instructions(
+        module,
+        builder,
+        compilation_ctx,
+        struct_ptr,
+        slot_ptr,
+        struct_,
+        written_bytes_in_slot,
+        &mut slot_data,
+    );
+
+    match slot_data {
+        SlotData::Placeholder => {
+            let storage_cache =
+                RuntimeFunction::StorageCachedStore.get(module, Some(compilation_ctx));
+            builder
+                .local_get(slot_ptr)
+                .i32_const(DATA_SLOT_DATA_PTR_OFFSET)
+                .call(storage_cache);
+        }
+        SlotData::Buffer { ptr, slot } => {
+            let store_slots_fn =
+                RuntimeFunction::StorageCachedStoreSlots.get(module, Some(compilation_ctx));
+            builder
+                .local_get(slot_ptr)
+                .local_get(ptr)
+                .i32_const(slot as i32 + 1)
+                .call(store_slots_fn);
+
+            add_copy_last_buffered_slot_instructions(builder, compilation_ctx, ptr, slot);
+        }
+    }
+
+    written_bytes_in_slot
+}
+
+/// Adds the instructions to encode the fields of a struct into `slot_data`. Every time a slot is
+/// complete it is saved in the storage cache, except the last one, that is saved by the caller.
+/// Nested structs are encoded in the same slot data, so the slot they share with their parent is
+/// saved once.
+///
+/// # Returns
+/// The written_bytes_in_slot value. Used to update the caller of the recursive call
+#[allow(clippy::too_many_arguments)]
+fn add_encode_struct_fields_instructions(
+    module: &mut Module,
+    builder: &mut InstrSeqBuilder,
+    compilation_ctx: &CompilationContext,
+    struct_ptr: LocalId,
+    slot_ptr: LocalId,
+    struct_: &IStruct,
+    written_bytes_in_slot: u32,
+    slot_data: &mut SlotData,
+) -> u32 {
+    let storage_cache = RuntimeFunction::StorageCachedStore.get(module, Some(compilation_ctx));
 
     // Locals
     let val_32 = module.locals.add(ValType::I32);
@@ -51,26 +128,51 @@ pub fn add_encode_and_save_into_storage_struct_instructions(
 
     let mut written_bytes_in_slot = written_bytes_in_slot;
     for (index, field) in struct_.fields.iter().enumerate() {
-        let field_size = field_size(field, compilation_ctx);
-        if written_bytes_in_slot + field_size > 32 {
-            // Save previous slot (maybe not needed...)
+        // Pinned fields start at their own slot, so we save the current one and jump to it. Structs
+        // with pinned fields are never buffered
+        if let Some(slot) = struct_.pinned_slots.get(&index) {
             builder
                 .local_get(slot_ptr)
                 .i32_const(DATA_SLOT_DATA_PTR_OFFSET)
                 .call(storage_cache);
 
-            // Wipe the data so we can fill it with new data
             builder
                 .i32_const(DATA_SLOT_DATA_PTR_OFFSET)
                 .i32_const(0)
                 .i32_const(32)
                 .memory_fill(compilation_ctx.memory_id);
 
-            let next_slot_fn = RuntimeFunction::StorageNextSlot.get(module, Some(compilation_ctx));
-            builder
-                .local_get(slot_ptr)
-                .call(next_slot_fn)
-                .local_set(slot_ptr);
+            add_pin_slot_instructions(builder, compilation_ctx, slot_ptr, slot);
+            written_bytes_in_slot = 0;
+        }
+
+        let field_size = field_size(field, compilation_ctx);
+        if field_needs_next_slot(written_bytes_in_slot, field_size, compilation_ctx) {
+            match slot_data {
+                SlotData::Placeholder => {
+                    // Save previous slot
+                    builder
+                        .local_get(slot_ptr)
+                        .i32_const(DATA_SLOT_DATA_PTR_OFFSET)
+                        .call(storage_cache);
+
+                    // Wipe the data so we can fill it with new data
+                    builder
+                        .i32_const(DATA_SLOT_DATA_PTR_OFFSET)
+                        .i32_const(0)
+                        .i32_const(32)
+                        .memory_fill(compilation_ctx.memory_id);
+
+                    let next_slot_fn =
+                        RuntimeFunction::StorageNextSlot.get(module, Some(compilation_ctx));
+                    builder
+                        .local_get(slot_ptr)
+                        .call(next_slot_fn)
+                        .local_set(slot_ptr);
+                }
+                // The buffer is already zeroed and the slots are saved at the end
+                SlotData::Buffer { slot, .. } => *slot += 1,
+            }
 
             written_bytes_in_slot = field_size;
         } else {
@@ -146,26 +248,21 @@ pub fn add_encode_and_save_into_storage_struct_instructions(
                 };
 
                 // Save the value in slot data
-                builder
-                    .i32_const(DATA_SLOT_DATA_PTR_OFFSET)
-                    .local_get(val)
-                    .store(
-                        compilation_ctx.memory_id,
-                        store_kind,
-                        MemArg {
-                            align: 0,
-                            offset: 32 - written_bytes_in_slot,
-                        },
-                    );
+                slot_data.push_base(builder);
+                builder.local_get(val).store(
+                    compilation_ctx.memory_id,
+                    store_kind,
+                    MemArg {
+                        align: 0,
+                        offset: slot_data.offset(32 - written_bytes_in_slot),
+                    },
+                );
             }
             IntermediateType::IU128 => {
                 let swap_fn = RuntimeFunction::SwapI128Bytes.get(module, Some(compilation_ctx));
 
                 // Slot data plus offset as dest ptr
-                builder
-                    .i32_const(DATA_SLOT_DATA_PTR_OFFSET)
-                    .i32_const(32 - written_bytes_in_slot as i32)
-                    .binop(BinaryOp::I32Add);
+                slot_data.push_address(builder, 32 - written_bytes_in_slot);
 
                 // Transform to BE
                 builder.call(swap_fn);
@@ -175,7 +272,7 @@ pub fn add_encode_and_save_into_storage_struct_instructions(
 
                 // Slot data plus offset as dest ptr (offset should be zero because data is already
                 // 32 bytes in size)
-                builder.i32_const(DATA_SLOT_DATA_PTR_OFFSET);
+                slot_data.push_address(builder, 0);
 
                 // Transform to BE
                 builder.call(swap_fn);
@@ -188,10 +285,7 @@ pub fn add_encode_and_save_into_storage_struct_instructions(
                 // Load the memory address
 
                 // Slot data plus offset as dest ptr
-                builder
-                    .i32_const(DATA_SLOT_DATA_PTR_OFFSET)
-                    .i32_const(32 - written_bytes_in_slot as i32)
-                    .binop(BinaryOp::I32Add);
+                slot_data.push_address(builder, 32 - written_bytes_in_slot);
 
                 // Grab the last 20 bytes of the address
                 builder.local_get(tmp).i32_const(12).binop(BinaryOp::I32Add);
@@ -201,6 +295,19 @@ pub fn add_encode_and_save_into_storage_struct_instructions(
 
                 builder.memory_copy(compilation_ctx.memory_id, compilation_ctx.memory_id);
             }
+            // Bytes (and strings, that wrap a vector<u8>) follow the Solidity encoding for
+            // `bytes`. The header is written in the slot data and cached below
+            IntermediateType::IVector(inner) if **inner == IntermediateType::IU8 => {
+                let encode_bytes_fn =
+                    RuntimeFunction::EncodeAndSaveBytesInStorage.get(module, Some(compilation_ctx));
+
+                let tmp = module.locals.add(ValType::I32);
+                builder
+                    .local_set(tmp)
+                    .local_get(tmp)
+                    .local_get(slot_ptr)
+                    .call(encode_bytes_fn);
+            }
             IntermediateType::IStruct {
                 module_id, index, ..
             } if Uid::is_vm_type(module_id, *index, compilation_ctx) => {
@@ -237,10 +344,8 @@ pub fn add_encode_and_save_into_storage_struct_instructions(
                     .local_set(tmp);
 
                 // Load the memory address
-                builder
-                    .i32_const(DATA_SLOT_DATA_PTR_OFFSET)
-                    .local_get(tmp)
-                    .i32_const(32);
+                slot_data.push_address(builder, 0);
+                builder.local_get(tmp).i32_const(32);
 
                 builder.memory_copy(compilation_ctx.memory_id, compilation_ctx.memory_id);
             }
@@ -253,7 +358,7 @@ pub fn add_encode_and_save_into_storage_struct_instructions(
                 let tmp = module.locals.add(ValType::I32);
                 builder.local_set(tmp);
 
-                written_bytes_in_slot = add_encode_and_save_into_storage_struct_instructions(
+                written_bytes_in_slot = add_encode_struct_fields_instructions(
                     module,
                     builder,
                     compilation_ctx,
@@ -261,8 +366,29 @@ pub fn add_encode_and_save_into_storage_struct_instructions(
                     slot_ptr,
                     child_struct,
                     written_bytes_in_slot,
+                    slot_data,
                 );
             }
+            IntermediateType::IGenericStructInstance {
+                module_id, index, ..
+            } if Mapping::is_vm_type(module_id, *index, compilation_ctx) => {
+                // Mappings reserve a whole slot that is left empty, the values are saved in the
+                // slots derived from it
+                builder.drop();
+            }
+            IntermediateType::IGenericStructInstance {
+                module_id, index, ..
+            } if StorageVec::is_vm_type(module_id, *index, compilation_ctx) => {
+                // The slot of a StorageVec holds its length, which is only modified through the
+                // StorageVec functions, so we keep the value it already has
+                let storage_load =
+                    RuntimeFunction::StorageCachedLoad.get(module, Some(compilation_ctx));
+                builder
+                    .drop()
+                    .local_get(slot_ptr)
+                    .i32_const(DATA_SLOT_DATA_PTR_OFFSET)
+                    .call(storage_load);
+            }
             IntermediateType::IGenericStructInstance {
                 module_id,
                 index,
@@ -277,7 +403,7 @@ pub fn add_encode_and_save_into_storage_struct_instructions(
                 let tmp = module.locals.add(ValType::I32);
                 builder.local_set(tmp);
 
-                written_bytes_in_slot = add_encode_and_save_into_storage_struct_instructions(
+                written_bytes_in_slot = add_encode_struct_fields_instructions(
                     module,
                     builder,
                     compilation_ctx,
@@ -285,6 +411,7 @@ pub fn add_encode_and_save_into_storage_struct_instructions(
                     slot_ptr,
                     &child_struct,
                     written_bytes_in_slot,
+                    slot_data,
                 );
             }
 
@@ -292,12 +419,161 @@ pub fn add_encode_and_save_into_storage_struct_instructions(
         };
     }
 
+    written_bytes_in_slot
+}
+
+/// Adds the instructions to clear from the storage every slot occupied by a struct, writing zero
+/// to them. Slots holding the data of `vector<u8>` fields saved with the long encoding are cleared
+/// too.
+///
+/// Values saved in mappings are not cleared: their keys are not known, so the occupied slots can't
+/// be found. The length of a `StorageVec` is set to zero, but its elements are not cleared.
+///
+/// # Arguments
+/// `module` - walrus module
+/// `builder` - insturctions sequence builder
+/// `slot_ptr` - storage's slot where the struct is saved. It is advanced to the last slot occupied
+/// by the struct
+/// `struct_` - structural information of the struct to be cleared
+/// `used_bytes_in_slot` - number of bytes used in the slot before the struct. This will be != 0
+/// if this function is recusively called to clear a struct inside another struct.
+///
+/// # Returns
+/// The used_bytes_in_slot value. Used to update the caller of the recursive call
+pub fn add_clear_storage_struct_instructions(
+    module: &mut Module,
+    builder: &mut InstrSeqBuilder,
+    compilation_ctx: &CompilationContext,
+    slot_ptr: LocalId,
+    struct_: &IStruct,
+    used_bytes_in_slot: u32,
+) -> u32 {
+    let storage_cache = RuntimeFunction::StorageCachedStore.get(module, Some(compilation_ctx));
+
+    // The slot data placeholder is used as the zero value written to the slots
+    builder
+        .i32_const(DATA_SLOT_DATA_PTR_OFFSET)
+        .i32_const(0)
+        .i32_const(32)
+        .memory_fill(compilation_ctx.memory_id);
+
+    // The version of versioned structs is cleared with the first slot
+    let used_bytes_in_slot = match struct_.storage_version {
+        Some(_) if used_bytes_in_slot == 0 => STORAGE_VERSION_SIZE,
+        _ => used_bytes_in_slot,
+    };
+
+    let used_bytes_in_slot = add_clear_struct_fields_instructions(
+        module,
+        builder,
+        compilation_ctx,
+        slot_ptr,
+        struct_,
+        used_bytes_in_slot,
+    );
+
     builder
         .local_get(slot_ptr)
         .i32_const(DATA_SLOT_DATA_PTR_OFFSET)
         .call(storage_cache);
 
-    written_bytes_in_slot
+    used_bytes_in_slot
+}
+
+/// Adds the instructions to clear the slots occupied by the fields of a struct. Every slot is
+/// cleared when the next one is reached, except the last one, that is cleared by the caller.
+///
+/// # Returns
+/// The used_bytes_in_slot value. Used to update the caller of the recursive call
+fn add_clear_struct_fields_instructions(
+    module: &mut Module,
+    builder: &mut InstrSeqBuilder,
+    compilation_ctx: &CompilationContext,
+    slot_ptr: LocalId,
+    struct_: &IStruct,
+    used_bytes_in_slot: u32,
+) -> u32 {
+    let storage_cache = RuntimeFunction::StorageCachedStore.get(module, Some(compilation_ctx));
+    let next_slot_fn = RuntimeFunction::StorageNextSlot.get(module, Some(compilation_ctx));
+
+    let mut used_bytes_in_slot = used_bytes_in_slot;
+    for (index, field) in struct_.fields.iter().enumerate() {
+        if let Some(slot) = struct_.pinned_slots.get(&index) {
+            builder
+                .local_get(slot_ptr)
+                .i32_const(DATA_SLOT_DATA_PTR_OFFSET)
+                .call(storage_cache);
+
+            add_pin_slot_instructions(builder, compilation_ctx, slot_ptr, slot);
+            used_bytes_in_slot = 0;
+        }
+
+        let field_size = field_size(field, compilation_ctx);
+        if field_needs_next_slot(used_bytes_in_slot, field_size, compilation_ctx) {
+            builder
+                .local_get(slot_ptr)
+                .i32_const(DATA_SLOT_DATA_PTR_OFFSET)
+                .call(storage_cache);
+
+            builder
+                .local_get(slot_ptr)
+                .call(next_slot_fn)
+                .local_set(slot_ptr);
+
+            used_bytes_in_slot = field_size;
+        } else {
+            used_bytes_in_slot += field_size;
+        }
+
+        match field {
+            IntermediateType::IVector(inner) if **inner == IntermediateType::IU8 => {
+                let clear_bytes_fn =
+                    RuntimeFunction::ClearBytesFromStorage.get(module, Some(compilation_ctx));
+                builder
+                    .local_get(slot_ptr)
+                    .i32_const(0)
+                    .call(clear_bytes_fn);
+            }
+            IntermediateType::IStruct { module_id, index }
+                if !Uid::is_vm_type(module_id, *index, compilation_ctx) =>
+            {
+                let child_struct = compilation_ctx
+                    .get_struct_by_index(module_id, *index)
+                    .unwrap();
+                used_bytes_in_slot = add_clear_struct_fields_instructions(
+                    module,
+                    builder,
+                    compilation_ctx,
+                    slot_ptr,
+                    child_struct,
+                    used_bytes_in_slot,
+                );
+            }
+            IntermediateType::IGenericStructInstance {
+                module_id,
+                index,
+                types,
+            } if !Mapping::is_vm_type(module_id, *index, compilation_ctx)
+                && !StorageVec::is_vm_type(module_id, *index, compilation_ctx) =>
+            {
+                let child_struct = compilation_ctx
+                    .get_struct_by_index(module_id, *index)
+                    .unwrap()
+                    .instantiate(types);
+                used_bytes_in_slot = add_clear_struct_fields_instructions(
+                    module,
+                    builder,
+                    compilation_ctx,
+                    slot_ptr,
+                    &child_struct,
+                    used_bytes_in_slot,
+                );
+            }
+            _ => {}
+        }
+    }
+
+    used_bytes_in_slot
 }
 
 /// Adds the instructions to read, decode from storage and build in memory a structure.
@@ -321,14 +597,7 @@ pub fn add_read_and_decode_storage_struct_instructions(
     reading_nested_struct: bool,
     read_bytes_in_slot: u32,
 ) -> (LocalId, u32) {
-    let (storage_load, _) = storage_load_bytes32(module);
-
-    let struct_ptr = module.locals.add(ValType::I32);
-
-    // Locals
-    let field_ptr = module.locals.add(ValType::I32);
-    let val_64 = module.locals.add(ValType::I64);
-    let val_32 = module.locals.add(ValType::I32);
+    let storage_load = RuntimeFunction::StorageCachedLoad.get(module, Some(compilation_ctx));
 
     // If we are reading an struct from the storage, means this struct has an owner and that owner
     // is saved in the DATA_STORAGE_OBJECT_OWNER_OFFSET piece of reserved memory. To be able to
@@ -344,245 +613,505 @@ pub fn add_read_and_decode_storage_struct_instructions(
             .memory_copy(compilation_ctx.memory_id, compilation_ctx.memory_id);
     }
 
+    // Versioned structs save their version before the first field
+    let version = struct_.storage_version.filter(|_| !reading_nested_struct);
+    let read_bytes_in_slot = if version.is_some() {
+        STORAGE_VERSION_SIZE
+    } else {
+        read_bytes_in_slot
+    };
+
+    // Nested structs are read from the slot data already loaded by the caller
+    let mut slot_data = if reading_nested_struct {
+        SlotData::Placeholder
+    } else {
+        add_slot_data_instructions(
+            module,
+            builder,
+            compilation_ctx,
+            struct_,
+            read_bytes_in_slot,
+        )
+    };
+
+    // Load data from slot
+    match slot_data {
+        SlotData::Placeholder if !reading_nested_struct => {
+            builder
+                .local_get(slot_ptr)
+                .i32_const(DATA_SLOT_DATA_PTR_OFFSET)
+                .call(storage_load);
+        }
+        SlotData::Placeholder => {}
+        SlotData::Buffer { ptr, .. } => {
+            let load_slots_fn =
+                RuntimeFunction::StorageCachedLoadSlots.get(module, Some(compilation_ctx));
+            builder
+                .local_get(slot_ptr)
+                .local_get(ptr)
+                .i32_const(struct_storage_slots(struct_, compilation_ctx) as i32)
+                .call(load_slots_fn);
+        }
+    }
+
+    let stored_version = module.locals.add(ValType::I32);
+    if version.is_some() {
+        add_read_version_instructions(builder, compilation_ctx, stored_version);
+    }
+
+    let (struct_ptr, read_bytes_in_slot) = add_decode_struct_fields_instructions(
+        module,
+        builder,
+        compilation_ctx,
+        slot_ptr,
+        struct_,
+        read_bytes_in_slot,
+        &mut slot_data,
+    );
+
+    if let SlotData::Buffer { ptr, slot } = slot_data {
+        add_copy_last_buffered_slot_instructions(builder, compilation_ctx, ptr, slot);
+    }
+
+    if let Some(version) = version {
+        add_migrate_instructions(
+            builder,
+            module,
+            struct_,
+            version,
+            struct_ptr,
+            stored_version,
+        );
+    }
+
+    (struct_ptr, read_bytes_in_slot)
+}
+
+/// Adds the instructions to decode the fields of a struct from `slot_data` and build it in
+/// memory. Nested structs are decoded from the same slot data.
+///
+/// # Returns
+/// pointer where the read struct is allocated and the updated read_bytes_in_slot value
+fn add_decode_struct_fields_instructions(
+    module: &mut Module,
+    builder: &mut InstrSeqBuilder,
+    compilation_ctx: &CompilationContext,
+    slot_ptr: LocalId,
+    struct_: &IStruct,
+    read_bytes_in_slot: u32,
+    slot_data: &mut SlotData,
+) -> (LocalId, u32) {
+    let storage_load = RuntimeFunction::StorageCachedLoad.get(module, Some(compilation_ctx));
+
+    let struct_ptr = module.locals.add(ValType::I32);
+
     // Allocate space for the struct
     builder
         .i32_const(struct_.heap_size as i32)
         .call(compilation_ctx.allocator)
         .local_set(struct_ptr);
 
-    // Load data from slot
-    if !reading_nested_struct {
-        builder
-            .local_get(slot_ptr)
-            .i32_const(DATA_SLOT_DATA_PTR_OFFSET)
-            .call(storage_load);
-    }
-
     let mut read_bytes_in_slot = read_bytes_in_slot;
     for (index, field) in struct_.fields.iter().enumerate() {
-        let field_size = field_size(field, compilation_ctx);
-        if read_bytes_in_slot + field_size > 32 {
-            let next_slot_fn = RuntimeFunction::StorageNextSlot.get(module, Some(compilation_ctx));
+        // Pinned fields start at their own slot, so we jump to it and load it. Structs with
+        // pinned fields are never buffered
+        if let Some(slot) = struct_.pinned_slots.get(&index) {
+            add_pin_slot_instructions(builder, compilation_ctx, slot_ptr, slot);
+            builder
+                .local_get(slot_ptr)
+                .i32_const(DATA_SLOT_DATA_PTR_OFFSET)
+                .call(storage_load);
+            read_bytes_in_slot = 0;
+        }
+
+        let (field_ptr, read_bytes) = add_read_and_decode_storage_field_instructions(
+            module,
+            builder,
+            compilation_ctx,
+            slot_ptr,
+            field,
+            read_bytes_in_slot,
+            slot_data,
+        );
+
+        read_bytes_in_slot = read_bytes;
+
+        // Save the ptr value to the struct
+        builder.local_get(struct_ptr).local_get(field_ptr).store(
+            compilation_ctx.memory_id,
+            StoreKind::I32 { atomic: false },
+            MemArg {
+                align: 0,
+                offset: index as u32 * 4,
+            },
+        );
+    }
+
+    (struct_ptr, read_bytes_in_slot)
+}
+
+/// Adds the instructions to read from storage only the `fields` of a storage struct, instead of
+/// the whole struct. Slots that do not back any of the requested fields are not loaded.
+///
+/// The fields that are not read are left uninitialized, so the resulting struct must only be used
+/// to access the requested fields. Structs with pinned slots are read whole.
+///
+/// # Arguments
+/// `module` - walrus module
+/// `builder` - insturctions sequence builder
+/// `slot_ptr` - storage's slot where the struct is saved
+/// `struct_` - structural information of the struct to be read
+/// `fields` - indexes of the fields to read, in ascending order
+///
+/// # Returns
+/// pointer where the read struct is allocated
+pub fn add_read_and_decode_storage_struct_fields_instructions(
+    module: &mut Module,
+    builder: &mut InstrSeqBuilder,
+    compilation_ctx: &CompilationContext,
+    slot_ptr: LocalId,
+    struct_: &IStruct,
+    fields: &[usize],
+) -> LocalId {
+    // The position of the fields following a pinned one is not relative to the struct's first
+    // slot, and versioned structs may have to be migrated, so in those cases the whole struct is
+    // read
+    if !struct_.pinned_slots.is_empty() || struct_.storage_version.is_some() {
+        let (struct_ptr, _) = add_read_and_decode_storage_struct_instructions(
+            module,
+            builder,
+            compilation_ctx,
+            slot_ptr,
+            struct_,
+            false,
+            0,
+        );
+        return struct_ptr;
+    }
+
+    let storage_load = RuntimeFunction::StorageCachedLoad.get(module, Some(compilation_ctx));
+    let next_slot_fn = RuntimeFunction::StorageNextSlot.get(module, Some(compilation_ctx));
+
+    let struct_ptr = module.locals.add(ValType::I32);
+
+    // Same as when reading the whole struct, we save the owner's id before the struct
+    builder
+        .i32_const(32)
+        .call(compilation_ctx.allocator)
+        .i32_const(DATA_STORAGE_OBJECT_OWNER_OFFSET)
+        .i32_const(32)
+        .memory_copy(compilation_ctx.memory_id, compilation_ctx.memory_id);
+
+    builder
+        .i32_const(struct_.heap_size as i32)
+        .call(compilation_ctx.allocator)
+        .local_set(struct_ptr);
+
+    let (positions, end_position) = storage_fields_positions(struct_, compilation_ctx, 0, 0);
+
+    // Slot (relative to the struct's first slot) slot_ptr is pointing to, and the slot currenly
+    // loaded in DATA_SLOT_DATA_PTR_OFFSET
+    let mut current_slot = 0;
+    let mut loaded_slot = None;
+    for &index in fields {
+        let field = &struct_.fields[index];
+        let (field_slot, read_bytes_in_slot) = positions[index];
+
+        while current_slot < field_slot {
             builder
                 .local_get(slot_ptr)
                 .call(next_slot_fn)
                 .local_set(slot_ptr);
+            current_slot += 1;
+        }
 
-            // Load the slot data
+        // If the field starts in the next slot, the read function will load it, so we don't need
+        // to load the current one
+        let field_size = field_size(field, compilation_ctx);
+        let starts_in_next_slot =
+            field_needs_next_slot(read_bytes_in_slot, field_size, compilation_ctx);
+        if !starts_in_next_slot && loaded_slot != Some(current_slot) {
             builder
                 .local_get(slot_ptr)
                 .i32_const(DATA_SLOT_DATA_PTR_OFFSET)
                 .call(storage_load);
-
-            read_bytes_in_slot = field_size;
-        } else {
-            read_bytes_in_slot += field_size;
+            loaded_slot = Some(current_slot);
         }
 
-        match field {
-            IntermediateType::IBool
-            | IntermediateType::IU8
-            | IntermediateType::IU16
-            | IntermediateType::IU32
-            | IntermediateType::IU64 => {
-                let data_size = field.stack_data_size();
-                let (val, store_kind, swap_fn) = if data_size == 8 {
-                    let swap_fn = RuntimeFunction::SwapI64Bytes.get(module, None);
-                    (val_64, StoreKind::I64 { atomic: false }, swap_fn)
-                } else {
-                    let swap_fn = RuntimeFunction::SwapI32Bytes.get(module, None);
-                    (val_32, StoreKind::I32 { atomic: false }, swap_fn)
-                };
+        let (field_ptr, _) = add_read_and_decode_storage_field_instructions(
+            module,
+            builder,
+            compilation_ctx,
+            slot_ptr,
+            field,
+            read_bytes_in_slot,
+            &mut SlotData::Placeholder,
+        );
+
+        // After reading the field, slot_ptr points to the last slot it occupies, and that slot is
+        // the one loaded
+        let (last_slot, _) = positions.get(index + 1).copied().unwrap_or(end_position);
+        current_slot = last_slot;
+        loaded_slot = Some(last_slot);
 
-                // Create a pointer for the value
+        builder.local_get(struct_ptr).local_get(field_ptr).store(
+            compilation_ctx.memory_id,
+            StoreKind::I32 { atomic: false },
+            MemArg {
+                align: 0,
+                offset: index as u32 * 4,
+            },
+        );
+    }
+
+    struct_ptr
+}
+
+/// Adds the instructions to read and decode from storage a single field of a struct. If the field
+/// does not fit in the current slot, the next slot is loaded before reading it.
+///
+/// # Returns
+/// The local holding the pointer to the read field and the updated read_bytes_in_slot value
+fn add_read_and_decode_storage_field_instructions(
+    module: &mut Module,
+    builder: &mut InstrSeqBuilder,
+    compilation_ctx: &CompilationContext,
+    slot_ptr: LocalId,
+    field: &IntermediateType,
+    read_bytes_in_slot: u32,
+    slot_data: &mut SlotData,
+) -> (LocalId, u32) {
+    let storage_load = RuntimeFunction::StorageCachedLoad.get(module, Some(compilation_ctx));
+
+    // Locals
+    let field_ptr = module.locals.add(ValType::I32);
+    let val_64 = module.locals.add(ValType::I64);
+    let val_32 = module.locals.add(ValType::I32);
+
+    let mut read_bytes_in_slot = read_bytes_in_slot;
+    let field_size = field_size(field, compilation_ctx);
+    if field_needs_next_slot(read_bytes_in_slot, field_size, compilation_ctx) {
+        match slot_data {
+            SlotData::Placeholder => {
+                let next_slot_fn =
+                    RuntimeFunction::StorageNextSlot.get(module, Some(compilation_ctx));
                 builder
-                    .i32_const(data_size as i32)
-                    .call(compilation_ctx.allocator)
-                    .local_tee(field_ptr);
-
-                // Read the value from the slot
-                let load_kind = match field_size {
-                    1 => LoadKind::I32_8 {
-                        kind: ExtendedLoad::ZeroExtend,
-                    },
-                    2 => LoadKind::I32_16 {
-                        kind: ExtendedLoad::ZeroExtend,
-                    },
-                    4 => LoadKind::I32 { atomic: false },
-                    8 => LoadKind::I64 { atomic: false },
-                    _ => panic!("invalid field size {field_size} for type {field:?}"),
-                };
+                    .local_get(slot_ptr)
+                    .call(next_slot_fn)
+                    .local_set(slot_ptr);
 
-                // Read the value and transform it to LE
+                // Load the slot data
                 builder
+                    .local_get(slot_ptr)
                     .i32_const(DATA_SLOT_DATA_PTR_OFFSET)
-                    .load(
-                        compilation_ctx.memory_id,
-                        load_kind,
-                        MemArg {
-                            align: 0,
-                            offset: 32 - read_bytes_in_slot,
-                        },
-                    )
-                    .local_tee(val)
-                    .call(swap_fn)
-                    .local_set(val);
+                    .call(storage_load);
+            }
+            // Every slot was already loaded in the buffer
+            SlotData::Buffer { slot, .. } => *slot += 1,
+        }
 
-                // If the field size are less than 4 or 8 bytes we need to shift them before
-                // saving
-                if field_size == 1 {
-                    builder
-                        .local_get(val)
-                        .i32_const(24)
-                        .binop(BinaryOp::I32ShrU)
-                        .local_set(val);
-                } else if field_size == 2 {
-                    builder
-                        .local_get(val)
-                        .i32_const(16)
-                        .binop(BinaryOp::I32ShrU)
-                        .local_set(val);
-                }
+        read_bytes_in_slot = field_size;
+    } else {
+        read_bytes_in_slot += field_size;
+    }
 
-                // Save it to the struct
-                builder.local_get(val).store(
+    match field {
+        IntermediateType::IBool
+        | IntermediateType::IU8
+        | IntermediateType::IU16
+        | IntermediateType::IU32
+        | IntermediateType::IU64 => {
+            let data_size = field.stack_data_size();
+            let (val, store_kind, swap_fn) = if data_size == 8 {
+                let swap_fn = RuntimeFunction::SwapI64Bytes.get(module, None);
+                (val_64, StoreKind::I64 { atomic: false }, swap_fn)
+            } else {
+                let swap_fn = RuntimeFunction::SwapI32Bytes.get(module, None);
+                (val_32, StoreKind::I32 { atomic: false }, swap_fn)
+            };
+
+            // Create a pointer for the value
+            builder
+                .i32_const(data_size as i32)
+                .call(compilation_ctx.allocator)
+                .local_tee(field_ptr);
+
+            // Read the value from the slot
+            let load_kind = match field_size {
+                1 => LoadKind::I32_8 {
+                    kind: ExtendedLoad::ZeroExtend,
+                },
+                2 => LoadKind::I32_16 {
+                    kind: ExtendedLoad::ZeroExtend,
+                },
+                4 => LoadKind::I32 { atomic: false },
+                8 => LoadKind::I64 { atomic: false },
+                _ => panic!("invalid field size {field_size} for type {field:?}"),
+            };
+
+            // Read the value and transform it to LE
+            slot_data.push_base(builder);
+            builder
+                .load(
                     compilation_ctx.memory_id,
-                    store_kind,
+                    load_kind,
                     MemArg {
                         align: 0,
-                        offset: 0,
+                        offset: slot_data.offset(32 - read_bytes_in_slot),
                     },
-                );
-            }
-            IntermediateType::IU128 => {
-                // Create a pointer for the value
+                )
+                .local_tee(val)
+                .call(swap_fn)
+                .local_set(val);
+
+            // If the field size are less than 4 or 8 bytes we need to shift them before
+            // saving
+            if field_size == 1 {
                 builder
-                    .i32_const(IU128::HEAP_SIZE)
-                    .call(compilation_ctx.allocator)
-                    .local_tee(field_ptr);
-
-                // Source address (plus offset)
+                    .local_get(val)
+                    .i32_const(24)
+                    .binop(BinaryOp::I32ShrU)
+                    .local_set(val);
+            } else if field_size == 2 {
                 builder
-                    .i32_const(DATA_SLOT_DATA_PTR_OFFSET)
-                    .i32_const(32 - read_bytes_in_slot as i32)
-                    .binop(BinaryOp::I32Add);
+                    .local_get(val)
+                    .i32_const(16)
+                    .binop(BinaryOp::I32ShrU)
+                    .local_set(val);
+            }
 
-                // Number of bytes to copy
-                builder.i32_const(IU128::HEAP_SIZE);
+            // Save it to the struct
+            builder.local_get(val).store(
+                compilation_ctx.memory_id,
+                store_kind,
+                MemArg {
+                    align: 0,
+                    offset: 0,
+                },
+            );
+        }
+        IntermediateType::IU128 => {
+            // Create a pointer for the value
+            builder
+                .i32_const(IU128::HEAP_SIZE)
+                .call(compilation_ctx.allocator)
+                .local_tee(field_ptr);
 
-                // Copy the chunk of memory
-                builder.memory_copy(compilation_ctx.memory_id, compilation_ctx.memory_id);
+            // Source address (plus offset)
+            slot_data.push_address(builder, 32 - read_bytes_in_slot);
 
-                let swap_fn = RuntimeFunction::SwapI128Bytes.get(module, Some(compilation_ctx));
+            // Number of bytes to copy
+            builder.i32_const(IU128::HEAP_SIZE);
 
-                // Transform it to LE
-                builder
-                    .local_get(field_ptr)
-                    .local_get(field_ptr)
-                    .call(swap_fn);
-            }
-            IntermediateType::IU256 => {
-                // Create a pointer for the value
-                builder
-                    .i32_const(IU256::HEAP_SIZE)
-                    .call(compilation_ctx.allocator)
-                    .local_tee(field_ptr);
+            // Copy the chunk of memory
+            builder.memory_copy(compilation_ctx.memory_id, compilation_ctx.memory_id);
 
-                // Source address (plus offset)
-                builder.i32_const(DATA_SLOT_DATA_PTR_OFFSET);
+            let swap_fn = RuntimeFunction::SwapI128Bytes.get(module, Some(compilation_ctx));
 
-                // Number of bytes to copy
-                builder.i32_const(32);
+            // Transform it to LE
+            builder
+                .local_get(field_ptr)
+                .local_get(field_ptr)
+                .call(swap_fn);
+        }
+        IntermediateType::IU256 => {
+            // Create a pointer for the value
+            builder
+                .i32_const(IU256::HEAP_SIZE)
+                .call(compilation_ctx.allocator)
+                .local_tee(field_ptr);
 
-                // Copy the chunk of memory
-                builder.memory_copy(compilation_ctx.memory_id, compilation_ctx.memory_id);
+            // Source address (plus offset)
+            slot_data.push_address(builder, 0);
 
-                let swap_fn = RuntimeFunction::SwapI256Bytes.get(module, Some(compilation_ctx));
+            // Number of bytes to copy
+            builder.i32_const(32);
 
-                // Transform it to LE
-                builder
-                    .local_get(field_ptr)
-                    .local_get(field_ptr)
-                    .call(swap_fn);
-            }
-            IntermediateType::IAddress | IntermediateType::ISigner => {
-                // Create a pointer for the value
-                builder
-                    .i32_const(32)
-                    .call(compilation_ctx.allocator)
-                    .local_tee(field_ptr);
+            // Copy the chunk of memory
+            builder.memory_copy(compilation_ctx.memory_id, compilation_ctx.memory_id);
 
-                // Add 12 to the offset to write the last 20 bytes of the address
-                builder.i32_const(12).binop(BinaryOp::I32Add);
+            let swap_fn = RuntimeFunction::SwapI256Bytes.get(module, Some(compilation_ctx));
 
-                // Source address (plus offset)
-                builder
-                    .i32_const(DATA_SLOT_DATA_PTR_OFFSET)
-                    .i32_const(32 - read_bytes_in_slot as i32)
-                    .binop(BinaryOp::I32Add);
+            // Transform it to LE
+            builder
+                .local_get(field_ptr)
+                .local_get(field_ptr)
+                .call(swap_fn);
+        }
+        IntermediateType::IAddress | IntermediateType::ISigner => {
+            // Create a pointer for the value
+            builder
+                .i32_const(32)
+                .call(compilation_ctx.allocator)
+                .local_tee(field_ptr);
 
-                // Number of bytes to copy
-                builder.i32_const(20);
+            // Add 12 to the offset to write the last 20 bytes of the address
+            builder.i32_const(12).binop(BinaryOp::I32Add);
 
-                // Copy the chunk of memory
-                builder.memory_copy(compilation_ctx.memory_id, compilation_ctx.memory_id);
-            }
-            IntermediateType::IStruct {
-                module_id, index, ..
-            } if Uid::is_vm_type(module_id, *index, compilation_ctx) => {
-                // Here we need to reconstruct the UID struct. To do that we first allocate 4 bytes
-                // that will contain the pointer to the UID struct data
-                //
-                // After that we need to create the ID struct. So we allocate 4 bytes for the first
-                // field's pointer, and 32 bytes that will hold the actual data.
+            // Source address (plus offset)
+            slot_data.push_address(builder, 32 - read_bytes_in_slot);
 
-                // Create a pointer for the value. This pointer will point to the struct ID
-                builder
-                    .i32_const(4)
-                    .call(compilation_ctx.allocator)
-                    .local_set(field_ptr);
+            // Number of bytes to copy
+            builder.i32_const(20);
 
-                let id_struct_ptr = module.locals.add(ValType::I32);
-                let id_field_ptr = module.locals.add(ValType::I32);
+            // Copy the chunk of memory
+            builder.memory_copy(compilation_ctx.memory_id, compilation_ctx.memory_id);
+        }
+        IntermediateType::IVector(inner) if **inner == IntermediateType::IU8 => {
+            let decode_bytes_fn =
+                RuntimeFunction::DecodeAndReadBytesFromStorage.get(module, Some(compilation_ctx));
 
-                // Recreate the ID struct
+            builder
+                .local_get(slot_ptr)
+                .call(decode_bytes_fn)
+                .local_set(field_ptr);
+        }
+        IntermediateType::IStruct {
+            module_id, index, ..
+        } if Uid::is_vm_type(module_id, *index, compilation_ctx) => {
+            // Here we need to reconstruct the UID struct. To do that we first allocate 4 bytes
+            // that will contain the pointer to the UID struct data
+            //
+            // After that we need to create the ID struct. So we allocate 4 bytes for the first
+            // field's pointer, and 32 bytes that will hold the actual data.
+
+            // Create a pointer for the value. This pointer will point to the struct ID
+            builder
+                .i32_const(4)
+                .call(compilation_ctx.allocator)
+                .local_set(field_ptr);
 
-                // First, 4 bytes for the pointer that points to the ID
-                builder
-                    .i32_const(4)
-                    .call(compilation_ctx.allocator)
-                    .local_set(id_struct_ptr);
+            let id_struct_ptr = module.locals.add(ValType::I32);
+            let id_field_ptr = module.locals.add(ValType::I32);
 
-                // 32 bytes to save the actual id
-                builder
-                    .i32_const(32)
-                    .call(compilation_ctx.allocator)
-                    .local_tee(id_field_ptr);
+            // Recreate the ID struct
 
-                // Source address (plus offset)
-                builder.i32_const(DATA_SLOT_DATA_PTR_OFFSET);
+            // First, 4 bytes for the pointer that points to the ID
+            builder
+                .i32_const(4)
+                .call(compilation_ctx.allocator)
+                .local_set(id_struct_ptr);
 
-                // Number of bytes to copy
-                builder.i32_const(32);
+            // 32 bytes to save the actual id
+            builder
+                .i32_const(32)
+                .call(compilation_ctx.allocator)
+                .local_tee(id_field_ptr);
 
-                // Copy the chunk of memory
-                builder.memory_copy(compilation_ctx.memory_id, compilation_ctx.memory_id);
+            // Source address (plus offset)
+            slot_data.push_address(builder, 0);
 
-                // Point the id_field_ptr to the data
-                builder
-                    .local_get(id_struct_ptr)
-                    .local_get(id_field_ptr)
-                    .store(
-                        compilation_ctx.memory_id,
-                        StoreKind::I32 { atomic: false },
-                        MemArg {
-                            align: 0,
-                            offset: 0,
-                        },
-                    );
+            // Number of bytes to copy
+            builder.i32_const(32);
 
-                // Write the field_ptr with the address of the ID struct
-                builder.local_get(field_ptr).local_get(id_struct_ptr).store(
+            // Copy the chunk of memory
+            builder.memory_copy(compilation_ctx.memory_id, compilation_ctx.memory_id);
+
+            // Point the id_field_ptr to the data
+            builder
+                .local_get(id_struct_ptr)
+                .local_get(id_field_ptr)
+                .store(
                     compilation_ctx.memory_id,
                     StoreKind::I32 { atomic: false },
                     MemArg {
@@ -590,70 +1119,326 @@ pub fn add_read_and_decode_storage_struct_instructions(
                         offset: 0,
                     },
                 );
+
+            // Write the field_ptr with the address of the ID struct
+            builder.local_get(field_ptr).local_get(id_struct_ptr).store(
+                compilation_ctx.memory_id,
+                StoreKind::I32 { atomic: false },
+                MemArg {
+                    align: 0,
+                    offset: 0,
+                },
+            );
+        }
+        IntermediateType::IStruct { module_id, index } => {
+            let child_struct = compilation_ctx
+                .get_struct_by_index(module_id, *index)
+                .unwrap();
+
+            // Read the child struct
+            let (child_struct_ptr, read_bytes) = add_decode_struct_fields_instructions(
+                module,
+                builder,
+                compilation_ctx,
+                slot_ptr,
+                child_struct,
+                read_bytes_in_slot,
+                slot_data,
+            );
+
+            read_bytes_in_slot = read_bytes;
+
+            builder.local_get(child_struct_ptr).local_set(field_ptr);
+        }
+        IntermediateType::IGenericStructInstance {
+            module_id, index, ..
+        } if Mapping::is_vm_type(module_id, *index, compilation_ctx)
+            || StorageVec::is_vm_type(module_id, *index, compilation_ctx) =>
+        {
+            // The mapping is not read from the slot. Instead, we reconstruct the Mapping
+            // struct with the number of the slot it occupies, so the values can be derived
+            // from it. The same is done for a StorageVec, whose slot holds the length
+            //
+            // Mapping { slot: <slot number> } / StorageVec { slot: <slot number> }
+            builder
+                .i32_const(4)
+                .call(compilation_ctx.allocator)
+                .local_set(field_ptr);
+
+            let slot_number_ptr = module.locals.add(ValType::I32);
+            builder
+                .i32_const(32)
+                .call(compilation_ctx.allocator)
+                .local_tee(slot_number_ptr);
+
+            // Copy the current slot number
+            builder
+                .local_get(slot_ptr)
+                .i32_const(32)
+                .memory_copy(compilation_ctx.memory_id, compilation_ctx.memory_id);
+
+            builder
+                .local_get(field_ptr)
+                .local_get(slot_number_ptr)
+                .store(
+                    compilation_ctx.memory_id,
+                    StoreKind::I32 { atomic: false },
+                    MemArg {
+                        align: 0,
+                        offset: 0,
+                    },
+                );
+        }
+        IntermediateType::IGenericStructInstance {
+            module_id,
+            index,
+            types,
+        } => {
+            let child_struct = compilation_ctx
+                .get_struct_by_index(module_id, *index)
+                .unwrap();
+            let child_struct = child_struct.instantiate(types);
+
+            // Read the child struct
+            let (child_struct_ptr, read_bytes) = add_decode_struct_fields_instructions(
+                module,
+                builder,
+                compilation_ctx,
+                slot_ptr,
+                &child_struct,
+                read_bytes_in_slot,
+                slot_data,
+            );
+
+            read_bytes_in_slot = read_bytes;
+
+            builder.local_get(child_struct_ptr).local_set(field_ptr);
+        }
+
+        _ => todo!(),
+    };
+
+    (field_ptr, read_bytes_in_slot)
+}
+
+/// Where the data of the slots being encoded or decoded is kept.
+#[derive(Debug, Clone, Copy)]
+enum SlotData {
+    /// The slot data placeholder (DATA_SLOT_DATA_PTR_OFFSET), that holds one slot at a time. Each
+    /// slot is loaded or saved when the encoding reaches it
+    Placeholder,
+    /// A buffer holding every slot of the struct, loaded or saved with a single call. `slot` is the
+    /// index of the slot being processed
+    Buffer { ptr: LocalId, slot: u32 },
+}
+
+impl SlotData {
+    /// Pushes to the stack the address the offsets of the current slot are relative to.
+    fn push_base(&self, builder: &mut InstrSeqBuilder) {
+        match self {
+            SlotData::Placeholder => builder.i32_const(DATA_SLOT_DATA_PTR_OFFSET),
+            SlotData::Buffer { ptr, .. } => builder.local_get(*ptr),
+        };
+    }
+
+    /// Returns the offset, relative to `push_base`, of the byte `offset` of the current slot.
+    fn offset(&self, offset: u32) -> u32 {
+        match self {
+            SlotData::Placeholder => offset,
+            SlotData::Buffer { slot, .. } => slot * 32 + offset,
+        }
+    }
+
+    /// Pushes to the stack the address of the byte `offset` of the current slot.
+    fn push_address(&self, builder: &mut InstrSeqBuilder, offset: u32) {
+        self.push_base(builder);
+        let offset = self.offset(offset);
+        if offset != 0 {
+            builder.i32_const(offset as i32).binop(BinaryOp::I32Add);
+        }
+    }
+}
+
+/// Chooses where the slots of a struct are encoded or decoded.
+///
+/// A struct that starts at the beginning of a slot, occupies more than one slot and has a static
+/// layout (see `has_static_storage_layout`) is processed in a zeroed buffer holding all its slots,
+/// so they are loaded or saved by a single loop-based runtime function instead of one inline
+/// sequence per slot. Otherwise the slot data placeholder is used.
+fn add_slot_data_instructions(
+    module: &mut Module,
+    builder: &mut InstrSeqBuilder,
+    compilation_ctx: &CompilationContext,
+    struct_: &IStruct,
+    used_bytes_in_slot: u32,
+) -> SlotData {
+    if used_bytes_in_slot != 0 || !has_static_storage_layout(struct_, compilation_ctx) {
+        return SlotData::Placeholder;
+    }
+
+    let slots = struct_storage_slots(struct_, compilation_ctx);
+    if slots < 2 {
+        return SlotData::Placeholder;
+    }
+
+    let ptr = module.locals.add(ValType::I32);
+    builder
+        .i32_const(slots as i32 * 32)
+        .call(compilation_ctx.allocator)
+        .local_tee(ptr)
+        .i32_const(0)
+        .i32_const(slots as i32 * 32)
+        .memory_fill(compilation_ctx.memory_id);
+
+    SlotData::Buffer { ptr, slot: 0 }
+}
+
+/// Copies the last slot of a buffer into the slot data placeholder, so after a buffered struct is
+/// processed the placeholder holds the slot `slot_ptr` points to, as in the slot by slot encoding.
+fn add_copy_last_buffered_slot_instructions(
+    builder: &mut InstrSeqBuilder,
+    compilation_ctx: &CompilationContext,
+    buffer_ptr: LocalId,
+    last_slot: u32,
+) {
+    builder.i32_const(DATA_SLOT_DATA_PTR_OFFSET);
+    SlotData::Buffer {
+        ptr: buffer_ptr,
+        slot: last_slot,
+    }
+    .push_address(builder, 0);
+    builder
+        .i32_const(32)
+        .memory_copy(compilation_ctx.memory_id, compilation_ctx.memory_id);
+}
+
+/// Returns true if every slot of the struct is fully described by its fields: there are no pinned
+/// slots and every field, including the ones of nested structs, is a value saved inside the slots
+/// (no bytes, mappings or storage vectors, whose data lives in derived slots).
+pub fn has_static_storage_layout(struct_: &IStruct, compilation_ctx: &CompilationContext) -> bool {
+    struct_.pinned_slots.is_empty()
+        && struct_.fields.iter().all(|field| match field {
+            IntermediateType::IBool
+            | IntermediateType::IU8
+            | IntermediateType::IU16
+            | IntermediateType::IU32
+            | IntermediateType::IU64
+            | IntermediateType::IU128
+            | IntermediateType::IU256
+            | IntermediateType::IAddress
+            | IntermediateType::ISigner => true,
+            IntermediateType::IStruct { module_id, index }
+                if Uid::is_vm_type(module_id, *index, compilation_ctx) =>
+            {
+                true
             }
             IntermediateType::IStruct { module_id, index } => {
                 let child_struct = compilation_ctx
                     .get_struct_by_index(module_id, *index)
                     .unwrap();
-
-                // Read the child struct
-                let (child_struct_ptr, read_bytes) =
-                    add_read_and_decode_storage_struct_instructions(
-                        module,
-                        builder,
-                        compilation_ctx,
-                        slot_ptr,
-                        child_struct,
-                        true,
-                        read_bytes_in_slot,
-                    );
-
-                read_bytes_in_slot = read_bytes;
-
-                builder.local_get(child_struct_ptr).local_set(field_ptr);
+                has_static_storage_layout(child_struct, compilation_ctx)
             }
             IntermediateType::IGenericStructInstance {
                 module_id,
                 index,
                 types,
-            } => {
+            } if !Mapping::is_vm_type(module_id, *index, compilation_ctx)
+                && !StorageVec::is_vm_type(module_id, *index, compilation_ctx) =>
+            {
                 let child_struct = compilation_ctx
                     .get_struct_by_index(module_id, *index)
-                    .unwrap();
-                let child_struct = child_struct.instantiate(types);
-
-                // Read the child struct
-                let (child_struct_ptr, read_bytes) =
-                    add_read_and_decode_storage_struct_instructions(
-                        module,
-                        builder,
-                        compilation_ctx,
-                        slot_ptr,
-                        &child_struct,
-                        true,
-                        read_bytes_in_slot,
-                    );
-
-                read_bytes_in_slot = read_bytes;
-
-                builder.local_get(child_struct_ptr).local_set(field_ptr);
+                    .unwrap()
+                    .instantiate(types);
+                has_static_storage_layout(&child_struct, compilation_ctx)
             }
+            _ => false,
+        })
+}
+
+/// Computes, for each field of the struct, the slot (relative to the first one) and the bytes
+/// already used in it before the field is read or written, following the same steps as the
+/// encoding and decoding functions.
+///
+/// # Returns
+/// The position of every field and the position after the last field
+pub fn storage_fields_positions(
+    struct_: &IStruct,
+    compilation_ctx: &CompilationContext,
+    slot: u32,
+    used_bytes_in_slot: u32,
+) -> (Vec<(u32, u32)>, (u32, u32)) {
+    let mut positions = Vec::with_capacity(struct_.fields.len());
+    let mut position = (slot, used_bytes_in_slot);
+
+    for field in &struct_.fields {
+        positions.push(position);
 
-            _ => todo!(),
+        let field_size = field_size(field, compilation_ctx);
+        let (slot, used_bytes_in_slot) = position;
+        position = if field_needs_next_slot(used_bytes_in_slot, field_size, compilation_ctx) {
+            (slot + 1, field_size)
+        } else {
+            (slot, used_bytes_in_slot + field_size)
         };
 
-        // Save the ptr value to the struct
-        builder.local_get(struct_ptr).local_get(field_ptr).store(
-            compilation_ctx.memory_id,
-            StoreKind::I32 { atomic: false },
-            MemArg {
-                align: 0,
-                offset: index as u32 * 4,
-            },
-        );
+        // Nested structs are not saved by themselves, their fields are
+        match field {
+            IntermediateType::IStruct { module_id, index }
+                if !Uid::is_vm_type(module_id, *index, compilation_ctx) =>
+            {
+                let child_struct = compilation_ctx
+                    .get_struct_by_index(module_id, *index)
+                    .unwrap();
+                (_, position) =
+                    storage_fields_positions(child_struct, compilation_ctx, position.0, position.1);
+            }
+            IntermediateType::IGenericStructInstance {
+                module_id,
+                index,
+                types,
+            } if !Mapping::is_vm_type(module_id, *index, compilation_ctx)
+                && !StorageVec::is_vm_type(module_id, *index, compilation_ctx) =>
+            {
+                let child_struct = compilation_ctx
+                    .get_struct_by_index(module_id, *index)
+                    .unwrap()
+                    .instantiate(types);
+                (_, position) = storage_fields_positions(
+                    &child_struct,
+                    compilation_ctx,
+                    position.0,
+                    position.1,
+                );
+            }
+            _ => {}
+        }
     }
 
-    (struct_ptr, read_bytes_in_slot)
+    (positions, position)
+}
+
+/// Returns the number of slots a struct occupies when it is saved starting at the beginning of a
+/// slot.
+pub fn struct_storage_slots(struct_: &IStruct, compilation_ctx: &CompilationContext) -> u32 {
+    let (_, (last_slot, _)) = storage_fields_positions(struct_, compilation_ctx, 0, 0);
+    last_slot + 1
+}
+
+/// Returns true if a field of `field_size` bytes can't be saved in the current slot, that already
+/// has `used_bytes_in_slot` bytes occupied, and must be saved in the next one.
+///
+/// If storage packing is disabled, every field starts a new slot.
+pub fn field_needs_next_slot(
+    used_bytes_in_slot: u32,
+    field_size: u32,
+    compilation_ctx: &CompilationContext,
+) -> bool {
+    if compilation_ctx.options.storage_packing {
+        used_bytes_in_slot + field_size > 32
+    } else {
+        // Nested structs have size 0 because they are not saved by themselves, their fields are
+        used_bytes_in_slot > 0 && field_size > 0
+    }
 }
 
 /// Return the storage-encoded field size in bytes
@@ -673,6 +1458,16 @@ pub fn field_size(field: &IntermediateType, compilation_ctx: &CompilationContext
             module_id, index, ..
         } if Uid::is_vm_type(module_id, *index, compilation_ctx) => 32,
 
+        // Mappings and StorageVecs occupy a whole slot, but the values are saved in the slots
+        // derived from it
+        IntermediateType::IGenericStructInstance {
+            module_id, index, ..
+        } if Mapping::is_vm_type(module_id, *index, compilation_ctx)
+            || StorageVec::is_vm_type(module_id, *index, compilation_ctx) =>
+        {
+            32
+        }
+
         // Structs are 0 because we don't know how much they will occupy, this depends on the
         // fields of the child struct, whether they are dynamic or static. The store function
         // called will take care of this.
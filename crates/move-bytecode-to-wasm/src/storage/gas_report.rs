@@ -0,0 +1,559 @@
+//! Static estimation of the storage accesses of every entry function of a module, so developers
+//! can budget the gas of a call before deploying.
+//!
+//! The estimation follows the worst case of the EVM warm/cold model (EIP-2929):
+//! - The storage cache reads each slot from the host once per call, so every slot read is cold.
+//! - The cache writes each modified slot once, when the call finishes. A write is warm if the slot
+//!   was read before in the same call, and cold otherwise. Every write is priced as setting a slot
+//!   that held zero, the most expensive store.
+//!
+//! The accesses come from the storage objects received as arguments (located, decoded and, for
+//! mutable references and values, saved back) and from the calls, direct or through other
+//! functions, to the natives that touch the storage: transfers, deletions, mappings, tables, bags
+//! and storage vectors, and from the global storage bytecodes, that access the published flag and
+//! the slots of the resource. Each call site is counted once. When the number of accesses depends on
+//! runtime values (calls inside loops, recursion, or values with dynamic data such as vectors) the
+//! estimation is only a lower bound, and it is flagged as not bounded. That is also the case when
+//! the function calls other contracts, as the cache is flushed before the calls, and delegated
+//! code accesses the storage of the contract.
+use std::collections::BTreeSet;
+
+use move_binary_format::file_format::Bytecode;
+use serde::Serialize;
+
+use crate::{
+    CompilationContext, GlobalFunctionTable,
+    compilation_context::{ModuleData, reserved_modules::STYLUS_FRAMEWORK_ADDRESS},
+    generics::replace_type_parameters,
+    native_functions::NativeFunction,
+    translation::{
+        intermediate_types::{IntermediateType, structs::IStruct},
+        table::FunctionId,
+    },
+    vm_handled_types::{VmHandledType, mapping::Mapping, storage_vec::StorageVec, uid::Uid},
+};
+
+use super::{
+    encoding::{
+        field_needs_next_slot, field_size, has_static_storage_layout, storage_fields_positions,
+    },
+    versioning::STORAGE_VERSION_SIZE,
+};
+
+/// Gas of reading a slot not accessed before in the transaction
+pub const COLD_SLOAD_GAS: u64 = 2100;
+/// Gas of setting a slot that holds zero
+pub const SSTORE_SET_GAS: u64 = 20000;
+
+#[derive(Debug, Default, Serialize)]
+pub struct StorageGasReport {
+    pub contract: String,
+    pub functions: Vec<FunctionStorageGas>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct FunctionStorageGas {
+    pub function: String,
+    /// Slots read from the storage, all of them cold
+    pub sloads: u32,
+    /// Slots written after being read in the same call
+    pub warm_sstores: u32,
+    /// Slots written without being read before in the same call
+    pub cold_sstores: u32,
+    /// Worst-case gas spent in the storage accesses
+    pub gas: u64,
+    /// False if the accesses depend on runtime values, in which case the counts are a lower bound
+    pub bounded: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct StorageAccesses {
+    sloads: u32,
+    warm_sstores: u32,
+    cold_sstores: u32,
+    bounded: bool,
+}
+
+impl Default for StorageAccesses {
+    fn default() -> Self {
+        Self {
+            sloads: 0,
+            warm_sstores: 0,
+            cold_sstores: 0,
+            bounded: true,
+        }
+    }
+}
+
+impl StorageAccesses {
+    fn new(sloads: u32, warm_sstores: u32, cold_sstores: u32) -> Self {
+        Self {
+            sloads,
+            warm_sstores,
+            cold_sstores,
+            bounded: true,
+        }
+    }
+
+    fn add(&mut self, other: StorageAccesses) {
+        self.sloads += other.sloads;
+        self.warm_sstores += other.warm_sstores;
+        self.cold_sstores += other.cold_sstores;
+        self.bounded &= other.bounded;
+    }
+
+    fn is_empty(&self) -> bool {
+        self.sloads == 0 && self.warm_sstores == 0 && self.cold_sstores == 0
+    }
+
+    fn gas(&self) -> u64 {
+        self.sloads as u64 * COLD_SLOAD_GAS
+            + self.warm_sstores as u64 * SSTORE_SET_GAS
+            + self.cold_sstores as u64 * (SSTORE_SET_GAS + COLD_SLOAD_GAS)
+    }
+}
+
+impl StorageGasReport {
+    /// Estimates the storage accesses of every entry function defined in the module.
+    pub(crate) fn new(
+        module_data: &ModuleData,
+        compilation_ctx: &CompilationContext,
+        function_definitions: &GlobalFunctionTable,
+    ) -> Self {
+        let estimator = Estimator {
+            compilation_ctx,
+            function_definitions,
+        };
+
+        let functions = module_data
+            .functions
+            .information
+            .iter()
+            .filter(|f| f.function_id.module_id == module_data.id && f.is_entry && !f.is_generic)
+            .map(|function_information| {
+                let mut accesses = StorageAccesses::default();
+
+                for (index, argument) in function_information.signature.arguments.iter().enumerate()
+                {
+                    accesses.add(estimator.storage_argument_accesses(
+                        argument,
+                        function_information.lazy_storage_fields.get(&index),
+                    ));
+                }
+
+                accesses.add(
+                    estimator.function_accesses(&function_information.function_id, &mut vec![]),
+                );
+
+                FunctionStorageGas {
+                    function: function_information.function_id.identifier.to_string(),
+                    sloads: accesses.sloads,
+                    warm_sstores: accesses.warm_sstores,
+                    cold_sstores: accesses.cold_sstores,
+                    gas: accesses.gas(),
+                    bounded: accesses.bounded,
+                }
+            })
+            .collect();
+
+        Self {
+            contract: module_data.id.to_string(),
+            functions,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("failed to serialize the storage gas report")
+    }
+}
+
+struct Estimator<'a> {
+    compilation_ctx: &'a CompilationContext<'a>,
+    function_definitions: &'a GlobalFunctionTable<'a>,
+}
+
+impl Estimator<'_> {
+    /// Accesses needed to load a storage object received as argument and to save it back.
+    fn storage_argument_accesses(
+        &self,
+        argument: &IntermediateType,
+        lazy_fields: Option<&Vec<usize>>,
+    ) -> StorageAccesses {
+        // Objects are looked up in the signer's objects and in the shared ones. Immutable
+        // references are also looked up in the frozen objects
+        let (inner, owner_candidates, saved_back) = match argument {
+            IntermediateType::IRef(inner) => (inner.as_ref(), 3, false),
+            IntermediateType::IMutRef(inner) => (inner.as_ref(), 2, true),
+            _ => (argument, 2, true),
+        };
+
+        if !matches!(
+            inner,
+            IntermediateType::IStruct { .. } | IntermediateType::IGenericStructInstance { .. }
+        ) {
+            return StorageAccesses::default();
+        }
+
+        let Ok(struct_) = self.compilation_ctx.get_struct_by_intermediate_type(inner) else {
+            return StorageAccesses::default();
+        };
+        if !struct_.saved_in_storage {
+            return StorageAccesses::default();
+        }
+
+        let (slots, bounded) = self.object_slots(&struct_);
+
+        // In the worst case the object is found in the last place it is looked up in. The first
+        // slot of the object is the one read there, the rest are read while decoding
+        let read_slots = match lazy_fields {
+            Some(fields) if struct_.storage_version.is_none() => {
+                self.lazy_fields_slots(&struct_, fields)
+            }
+            _ => slots,
+        };
+
+        StorageAccesses {
+            sloads: owner_candidates - 1 + read_slots,
+            warm_sstores: if saved_back { slots } else { 0 },
+            cold_sstores: 0,
+            bounded,
+        }
+    }
+
+    /// Accesses of the calls to storage natives the function does, directly or through the
+    /// functions it calls.
+    ///
+    /// `call_stack` holds the functions being estimated, to detect recursion.
+    fn function_accesses(
+        &self,
+        function_id: &FunctionId,
+        call_stack: &mut Vec<FunctionId>,
+    ) -> StorageAccesses {
+        let Some(function_definition) = self
+            .function_definitions
+            .get(&function_id.get_generic_fn_id())
+        else {
+            return StorageAccesses::default();
+        };
+
+        let Some(code) = function_definition.code.as_ref() else {
+            return self.native_accesses(function_id);
+        };
+
+        if call_stack.contains(function_id) {
+            return StorageAccesses {
+                bounded: false,
+                ..Default::default()
+            };
+        }
+
+        let module_data = if function_id.module_id == self.compilation_ctx.root_module_data.id {
+            self.compilation_ctx.root_module_data
+        } else if let Some(module_data) = self.compilation_ctx.deps_data.get(&function_id.module_id)
+        {
+            module_data
+        } else {
+            return StorageAccesses::default();
+        };
+
+        call_stack.push(function_id.clone());
+
+        let mut accesses = StorageAccesses::default();
+        let mut has_loop = false;
+        for (pc, instruction) in code.code.iter().enumerate() {
+            match instruction {
+                Bytecode::Call(function_handle_index) => {
+                    let callee = &module_data.functions.calls[function_handle_index.into_index()];
+                    accesses.add(self.function_accesses(callee, call_stack));
+                }
+                Bytecode::CallGeneric(function_instantiation_index) => {
+                    let mut callee = module_data.functions.generic_calls
+                        [function_instantiation_index.into_index()]
+                    .clone();
+
+                    // Type parameters of the instantiation refer to the caller's ones
+                    if let (Some(types), Some(caller_types)) = (
+                        callee.type_instantiations.as_mut(),
+                        function_id.type_instantiations.as_ref(),
+                    ) {
+                        for itype in types.iter_mut() {
+                            *itype = replace_type_parameters(itype, caller_types);
+                        }
+                    }
+
+                    accesses.add(self.function_accesses(&callee, call_stack));
+                }
+                Bytecode::MoveToDeprecated(_)
+                | Bytecode::MoveToGenericDeprecated(_)
+                | Bytecode::MoveFromDeprecated(_)
+                | Bytecode::MoveFromGenericDeprecated(_)
+                | Bytecode::ExistsDeprecated(_)
+                | Bytecode::ExistsGenericDeprecated(_)
+                | Bytecode::ImmBorrowGlobalDeprecated(_)
+                | Bytecode::ImmBorrowGlobalGenericDeprecated(_)
+                | Bytecode::MutBorrowGlobalDeprecated(_)
+                | Bytecode::MutBorrowGlobalGenericDeprecated(_) => {
+                    accesses.add(self.global_storage_accesses(
+                        instruction,
+                        module_data,
+                        function_id,
+                    ));
+                }
+                Bytecode::Branch(code_offset)
+                | Bytecode::BrTrue(code_offset)
+                | Bytecode::BrFalse(code_offset)
+                    if *code_offset as usize <= pc =>
+                {
+                    has_loop = true;
+                }
+                _ => {}
+            }
+        }
+
+        call_stack.pop();
+
+        if has_loop && !accesses.is_empty() {
+            accesses.bounded = false;
+        }
+
+        accesses
+    }
+
+    /// Accesses of a global storage bytecode. The resource is saved after the slot holding its
+    /// published flag (see `runtime::global_storage`).
+    fn global_storage_accesses(
+        &self,
+        instruction: &Bytecode,
+        module_data: &ModuleData,
+        function_id: &FunctionId,
+    ) -> StorageAccesses {
+        let resource = match instruction {
+            // Only the published flag is read
+            Bytecode::ExistsDeprecated(_) | Bytecode::ExistsGenericDeprecated(_) => {
+                return StorageAccesses::new(1, 0, 0);
+            }
+            Bytecode::MoveToDeprecated(struct_definition_index)
+            | Bytecode::MoveFromDeprecated(struct_definition_index)
+            | Bytecode::ImmBorrowGlobalDeprecated(struct_definition_index)
+            | Bytecode::MutBorrowGlobalDeprecated(struct_definition_index) => {
+                IntermediateType::IStruct {
+                    module_id: module_data.id.clone(),
+                    index: struct_definition_index.0,
+                }
+            }
+            Bytecode::MoveToGenericDeprecated(struct_definition_index)
+            | Bytecode::MoveFromGenericDeprecated(struct_definition_index)
+            | Bytecode::ImmBorrowGlobalGenericDeprecated(struct_definition_index)
+            | Bytecode::MutBorrowGlobalGenericDeprecated(struct_definition_index) => {
+                let Ok(types) = module_data
+                    .structs
+                    .get_generic_struct_types_instances(struct_definition_index)
+                else {
+                    return StorageAccesses::default();
+                };
+
+                // Type parameters of the instantiation refer to the function's ones
+                let types = match function_id.type_instantiations.as_ref() {
+                    Some(caller_types) => types
+                        .iter()
+                        .map(|itype| replace_type_parameters(itype, caller_types))
+                        .collect(),
+                    None => types.to_vec(),
+                };
+
+                IntermediateType::IGenericStructInstance {
+                    module_id: module_data.id.clone(),
+                    index: module_data
+                        .structs
+                        .get_generic_struct_idx_by_struct_definition_idx(struct_definition_index),
+                    types,
+                }
+            }
+            _ => return StorageAccesses::default(),
+        };
+
+        let (n, bounded) = self.value_slots(&resource);
+
+        let mut accesses = match instruction {
+            // The flag is checked to be unset, and then set, and the resource is saved
+            Bytecode::MoveToDeprecated(_) | Bytecode::MoveToGenericDeprecated(_) => {
+                StorageAccesses::new(1, 1, n)
+            }
+            // The resource is read, and the flag and every slot of the resource are cleared
+            Bytecode::MoveFromDeprecated(_) | Bytecode::MoveFromGenericDeprecated(_) => {
+                StorageAccesses::new(1 + n, 1 + n, 0)
+            }
+            // Mutably borrowed resources are written back when the call finishes
+            Bytecode::MutBorrowGlobalDeprecated(_)
+            | Bytecode::MutBorrowGlobalGenericDeprecated(_) => StorageAccesses::new(1 + n, n, 0),
+            Bytecode::ImmBorrowGlobalDeprecated(_)
+            | Bytecode::ImmBorrowGlobalGenericDeprecated(_) => StorageAccesses::new(1 + n, 0, 0),
+            _ => return StorageAccesses::default(),
+        };
+
+        accesses.bounded = bounded;
+        accesses
+    }
+
+    /// Accesses of a single call to a native function of the framework. `n` are the slots
+    /// occupied by the value the native reads or writes.
+    fn native_accesses(&self, function_id: &FunctionId) -> StorageAccesses {
+        if function_id.module_id.address != STYLUS_FRAMEWORK_ADDRESS {
+            return StorageAccesses::default();
+        }
+
+        // New object ids are derived from a counter kept in the storage
+        if function_id.identifier == NativeFunction::NATIVE_FRESH_ID {
+            return StorageAccesses::new(1, 1, 0);
+        }
+
+        // Calls to other contracts, and their deployment, flush the storage cache, so the slots
+        // are read and written again afterwards, and delegated code accesses this contract's
+        // storage
+        if function_id.identifier == NativeFunction::NATIVE_CALL_CONTRACT
+            || function_id.identifier == NativeFunction::NATIVE_DELEGATE_CALL_CONTRACT
+            || function_id.identifier == NativeFunction::NATIVE_STATIC_CALL_CONTRACT
+            || function_id.identifier == NativeFunction::NATIVE_CREATE1
+            || function_id.identifier == NativeFunction::NATIVE_CREATE2
+            || function_id.identifier == NativeFunction::NATIVE_TRANSFER_ETH
+        {
+            return StorageAccesses {
+                bounded: false,
+                ..Default::default()
+            };
+        }
+
+        let Some(value_type) = function_id
+            .type_instantiations
+            .as_ref()
+            .and_then(|types| types.last())
+        else {
+            return StorageAccesses::default();
+        };
+
+        let (n, bounded) = self.value_slots(value_type);
+
+        let mut accesses = match function_id.identifier.as_str() {
+            // The object is deleted from where it was saved, and saved for its new owner
+            NativeFunction::NATIVE_TRANSFER_OBJECT
+            | NativeFunction::NATIVE_SHARE_OBJECT
+            | NativeFunction::NATIVE_FREEZE_OBJECT => StorageAccesses::new(0, n, n),
+            NativeFunction::NATIVE_DELETE_OBJECT => StorageAccesses::new(0, n, 0),
+            #[cfg(debug_assertions)]
+            NativeFunction::READ_SLOT => StorageAccesses::new(n, 0, 0),
+            #[cfg(debug_assertions)]
+            NativeFunction::SAVE_IN_SLOT => StorageAccesses::new(0, 0, n),
+            NativeFunction::NATIVE_MAPPING_CONTAINS
+            | NativeFunction::NATIVE_STORAGE_VEC_LENGTH
+            | NativeFunction::NATIVE_BAG_TYPE_TAG
+            | NativeFunction::NATIVE_BAG_GET_TYPE_TAG => StorageAccesses::new(1, 0, 0),
+            NativeFunction::NATIVE_BAG_SET_TYPE_TAG => StorageAccesses::new(0, 0, 1),
+            NativeFunction::NATIVE_MAPPING_GET
+            | NativeFunction::NATIVE_TABLE_BORROW
+            | NativeFunction::NATIVE_BAG_BORROW => StorageAccesses::new(n, 0, 0),
+            NativeFunction::NATIVE_MAPPING_SET
+            | NativeFunction::NATIVE_TABLE_ADD
+            | NativeFunction::NATIVE_BAG_ADD => StorageAccesses::new(0, 0, n),
+            // Borrowed values are written back when the call finishes, and removed ones are
+            // cleared
+            NativeFunction::NATIVE_TABLE_BORROW_MUT
+            | NativeFunction::NATIVE_BAG_BORROW_MUT
+            | NativeFunction::NATIVE_MAPPING_REMOVE
+            | NativeFunction::NATIVE_TABLE_REMOVE
+            | NativeFunction::NATIVE_BAG_REMOVE => StorageAccesses::new(n, n, 0),
+            // Storage vectors also read, and update, their length
+            NativeFunction::NATIVE_STORAGE_VEC_BORROW => StorageAccesses::new(1 + n, 0, 0),
+            NativeFunction::NATIVE_STORAGE_VEC_BORROW_MUT => StorageAccesses::new(1 + n, n, 0),
+            NativeFunction::NATIVE_STORAGE_VEC_PUSH_BACK => StorageAccesses::new(1, 1, n),
+            NativeFunction::NATIVE_STORAGE_VEC_POP_BACK => StorageAccesses::new(1 + n, 1 + n, 0),
+            _ => return StorageAccesses::default(),
+        };
+
+        accesses.bounded = bounded;
+        accesses
+    }
+
+    /// Returns the slots a value saved in the storage occupies, and whether that number is known
+    /// at compile time.
+    fn value_slots(&self, itype: &IntermediateType) -> (u32, bool) {
+        match itype {
+            IntermediateType::IVector(_) => (1, false),
+            IntermediateType::IStruct { module_id, index }
+                if Uid::is_vm_type(module_id, *index, self.compilation_ctx) =>
+            {
+                (1, true)
+            }
+            IntermediateType::IGenericStructInstance {
+                module_id, index, ..
+            } if Mapping::is_vm_type(module_id, *index, self.compilation_ctx)
+                || StorageVec::is_vm_type(module_id, *index, self.compilation_ctx) =>
+            {
+                (1, false)
+            }
+            IntermediateType::IStruct { .. } | IntermediateType::IGenericStructInstance { .. } => {
+                match self.compilation_ctx.get_struct_by_intermediate_type(itype) {
+                    Ok(struct_) => self.object_slots(&struct_),
+                    Err(_) => (1, false),
+                }
+            }
+            IntermediateType::ITypeParameter(_) => (1, false),
+            _ => (1, true),
+        }
+    }
+
+    /// Returns the slots a struct occupies when saved as an object, and whether that number is
+    /// known at compile time.
+    fn object_slots(&self, struct_: &IStruct) -> (u32, bool) {
+        let (_, (last_slot, _)) = storage_fields_positions(
+            struct_,
+            self.compilation_ctx,
+            0,
+            self.first_field_offset(struct_),
+        );
+
+        (
+            last_slot + 1,
+            has_static_storage_layout(struct_, self.compilation_ctx),
+        )
+    }
+
+    /// Returns how many slots are read to decode only the given fields of a struct: the slots
+    /// holding them and the first one, that is read when the object is located.
+    fn lazy_fields_slots(&self, struct_: &IStruct, fields: &[usize]) -> u32 {
+        let (positions, end) = storage_fields_positions(
+            struct_,
+            self.compilation_ctx,
+            0,
+            self.first_field_offset(struct_),
+        );
+
+        let mut slots = BTreeSet::from([0]);
+        for &field_index in fields {
+            let (slot, used_bytes_in_slot) = positions[field_index];
+            let size = field_size(&struct_.fields[field_index], self.compilation_ctx);
+            let first_slot =
+                if field_needs_next_slot(used_bytes_in_slot, size, self.compilation_ctx) {
+                    slot + 1
+                } else {
+                    slot
+                };
+            let last_slot = positions
+                .get(field_index + 1)
+                .map_or(end.0, |(next_slot, _)| *next_slot)
+                .max(first_slot);
+
+            slots.extend(first_slot..=last_slot);
+        }
+
+        slots.len() as u32
+    }
+
+    fn first_field_offset(&self, struct_: &IStruct) -> u32 {
+        if struct_.storage_version.is_some() {
+            STORAGE_VERSION_SIZE
+        } else {
+            0
+        }
+    }
+}
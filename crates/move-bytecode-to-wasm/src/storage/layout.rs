@@ -0,0 +1,430 @@
+//! Storage layout description of a module, following the shape of the `storageLayout` output of
+//! solc, so the tooling built around it (explorers, indexers, audit tools) can read it.
+//!
+//! Objects are not saved in fixed slots: every struct with the `key` ability is saved in the
+//! objects mapping, `mapping(bytes32 owner => mapping(bytes32 id => T))`, rooted at slot 0 or at
+//! the struct's ERC-7201 namespace root. Each of those structs is described as a storage variable
+//! of that mapping type. Fields pinned to absolute slots (see `storage::pinned_slots`), and the
+//! ones following them, are described as storage variables of their own.
+//!
+//! Fields of nested structs are packed with the fields of the struct containing them, so they are
+//! described flattened, as members named `<field>.<nested field>`. The version of versioned structs
+//! (see `storage::versioning`) is described as their first member.
+use std::collections::BTreeMap;
+
+use alloy_primitives::U256;
+use serde::Serialize;
+
+use crate::{
+    CompilationContext,
+    compilation_context::ModuleData,
+    translation::intermediate_types::{IntermediateType, structs::IStruct},
+    vm_handled_types::{VmHandledType, mapping::Mapping, storage_vec::StorageVec, uid::Uid},
+};
+
+use super::{
+    encoding::{field_needs_next_slot, field_size},
+    versioning::STORAGE_VERSION_SIZE,
+};
+
+/// Label of the member describing the version of a versioned struct
+const STORAGE_VERSION_LABEL: &str = "(storage_version)";
+
+#[derive(Debug, Default, Serialize)]
+pub struct StorageLayout {
+    pub storage: Vec<StorageEntry>,
+    pub types: BTreeMap<String, StorageType>,
+}
+
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub struct StorageEntry {
+    pub contract: String,
+    pub label: String,
+    /// Number of bytes used in the slot before this entry
+    pub offset: u32,
+    /// Slot number, in decimal
+    pub slot: String,
+    #[serde(rename = "type")]
+    pub type_id: String,
+}
+
+#[derive(Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageType {
+    pub encoding: &'static str,
+    pub label: String,
+    pub number_of_bytes: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub members: Option<Vec<StorageEntry>>,
+}
+
+impl StorageLayout {
+    /// Describes the storage used by the structs with the `key` ability defined in the module.
+    pub(crate) fn new(module_data: &ModuleData, compilation_ctx: &CompilationContext) -> Self {
+        let mut builder = LayoutBuilder {
+            contract: module_data.id.to_string(),
+            compilation_ctx,
+            layout: StorageLayout::default(),
+        };
+
+        for struct_ in module_data
+            .structs
+            .structs
+            .iter()
+            .filter(|s| s.saved_in_storage)
+        {
+            builder.add_object(struct_);
+        }
+
+        builder.layout
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("failed to serialize the storage layout")
+    }
+}
+
+struct LayoutBuilder<'a> {
+    contract: String,
+    compilation_ctx: &'a CompilationContext<'a>,
+    layout: StorageLayout,
+}
+
+impl LayoutBuilder<'_> {
+    fn add_object(&mut self, struct_: &IStruct) {
+        // Fields before the first pinned one are saved relative to the object's slot
+        let first_pinned = struct_.pinned_slots.keys().min().copied();
+        let relative_fields = first_pinned.unwrap_or(struct_.fields.len());
+
+        let mut members = Vec::new();
+
+        // Versioned structs save their version before the first field
+        let start_position = if struct_.storage_version.is_some() {
+            self.add_type("t_uint8", "uint8", "inplace", 1);
+            members.push(StorageEntry {
+                contract: self.contract.clone(),
+                label: STORAGE_VERSION_LABEL.to_owned(),
+                offset: 0,
+                slot: "0".to_owned(),
+                type_id: "t_uint8".to_owned(),
+            });
+            (U256::ZERO, STORAGE_VERSION_SIZE)
+        } else {
+            (U256::ZERO, 0)
+        };
+
+        let (end_slot, _) = self.add_members(
+            struct_,
+            0..relative_fields,
+            "",
+            start_position,
+            &mut members,
+        );
+
+        let struct_type_id = format!("t_struct({})_storage", struct_.identifier);
+        self.layout.types.insert(
+            struct_type_id.clone(),
+            StorageType {
+                encoding: "inplace",
+                label: format!("struct {}", struct_.identifier),
+                number_of_bytes: ((end_slot + U256::from(1)) * U256::from(32)).to_string(),
+                key: None,
+                value: None,
+                members: Some(members),
+            },
+        );
+
+        self.add_type("t_bytes32", "bytes32", "inplace", 32);
+        let ids_mapping_type_id = format!("t_mapping(t_bytes32,{struct_type_id})");
+        self.layout.types.insert(
+            ids_mapping_type_id.clone(),
+            StorageType {
+                encoding: "mapping",
+                label: format!("mapping(bytes32 => struct {})", struct_.identifier),
+                number_of_bytes: "32".to_owned(),
+                key: Some("t_bytes32".to_owned()),
+                value: Some(struct_type_id),
+                members: None,
+            },
+        );
+
+        let objects_mapping_type_id = format!("t_mapping(t_bytes32,{ids_mapping_type_id})");
+        self.layout.types.insert(
+            objects_mapping_type_id.clone(),
+            StorageType {
+                encoding: "mapping",
+                label: format!(
+                    "mapping(bytes32 => mapping(bytes32 => struct {}))",
+                    struct_.identifier
+                ),
+                number_of_bytes: "32".to_owned(),
+                key: Some("t_bytes32".to_owned()),
+                value: Some(ids_mapping_type_id),
+                members: None,
+            },
+        );
+
+        let root_slot = struct_
+            .namespace_root_slot
+            .map(U256::from_be_bytes)
+            .unwrap_or(U256::ZERO);
+        self.layout.storage.push(StorageEntry {
+            contract: self.contract.clone(),
+            label: struct_.identifier.clone(),
+            offset: 0,
+            slot: root_slot.to_string(),
+            type_id: objects_mapping_type_id,
+        });
+
+        // Pinned fields are saved at absolute slots, as storage variables
+        if let Some(first_pinned) = first_pinned {
+            let mut entries = Vec::new();
+            self.add_members(
+                struct_,
+                first_pinned..struct_.fields.len(),
+                &format!("{}.", struct_.identifier),
+                (U256::ZERO, 0),
+                &mut entries,
+            );
+            self.layout.storage.extend(entries);
+        }
+    }
+
+    /// Describes the fields in `range`, starting at `position` (slot and bytes used in it). The
+    /// position is advanced following the same steps as the encoding functions.
+    ///
+    /// # Returns
+    /// The position after the last field
+    fn add_members(
+        &mut self,
+        struct_: &IStruct,
+        range: std::ops::Range<usize>,
+        prefix: &str,
+        position: (U256, u32),
+        members: &mut Vec<StorageEntry>,
+    ) -> (U256, u32) {
+        let (mut slot, mut used_bytes_in_slot) = position;
+        for index in range {
+            let field = &struct_.fields[index];
+            let label = format!(
+                "{prefix}{}",
+                struct_
+                    .field_names
+                    .get(index)
+                    .cloned()
+                    .unwrap_or_else(|| index.to_string())
+            );
+
+            if let Some(pinned_slot) = struct_.pinned_slots.get(&index) {
+                slot = U256::from_be_bytes(*pinned_slot);
+                used_bytes_in_slot = 0;
+            }
+
+            let field_size = field_size(field, self.compilation_ctx);
+            if field_needs_next_slot(used_bytes_in_slot, field_size, self.compilation_ctx) {
+                slot += U256::from(1);
+                used_bytes_in_slot = field_size;
+            } else {
+                used_bytes_in_slot += field_size;
+            }
+
+            if let Some(child_struct) = self.nested_struct(field) {
+                (slot, used_bytes_in_slot) = self.add_members(
+                    &child_struct,
+                    0..child_struct.fields.len(),
+                    &format!("{label}."),
+                    (slot, used_bytes_in_slot),
+                    members,
+                );
+                continue;
+            }
+
+            members.push(StorageEntry {
+                contract: self.contract.clone(),
+                label,
+                offset: used_bytes_in_slot - field_size,
+                slot: slot.to_string(),
+                type_id: self.type_id(field),
+            });
+        }
+
+        (slot, used_bytes_in_slot)
+    }
+
+    /// Returns the struct if `field` is a struct whose fields are saved inline
+    fn nested_struct(&self, field: &IntermediateType) -> Option<IStruct> {
+        match field {
+            IntermediateType::IStruct { module_id, index }
+                if !Uid::is_vm_type(module_id, *index, self.compilation_ctx) =>
+            {
+                self.compilation_ctx
+                    .get_struct_by_index(module_id, *index)
+                    .ok()
+                    .cloned()
+            }
+            IntermediateType::IGenericStructInstance {
+                module_id,
+                index,
+                types,
+            } if !Mapping::is_vm_type(module_id, *index, self.compilation_ctx)
+                && !StorageVec::is_vm_type(module_id, *index, self.compilation_ctx) =>
+            {
+                self.compilation_ctx
+                    .get_struct_by_index(module_id, *index)
+                    .ok()
+                    .map(|s| s.instantiate(types))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the id of the type of a field, adding its description to the layout types.
+    fn type_id(&mut self, field: &IntermediateType) -> String {
+        match field {
+            IntermediateType::IBool => self.add_type("t_bool", "bool", "inplace", 1),
+            IntermediateType::IU8 => self.add_type("t_uint8", "uint8", "inplace", 1),
+            IntermediateType::IU16 => self.add_type("t_uint16", "uint16", "inplace", 2),
+            IntermediateType::IU32 => self.add_type("t_uint32", "uint32", "inplace", 4),
+            IntermediateType::IU64 => self.add_type("t_uint64", "uint64", "inplace", 8),
+            IntermediateType::IU128 => self.add_type("t_uint128", "uint128", "inplace", 16),
+            IntermediateType::IU256 => self.add_type("t_uint256", "uint256", "inplace", 32),
+            IntermediateType::IAddress | IntermediateType::ISigner => {
+                self.add_type("t_address", "address", "inplace", 20)
+            }
+            IntermediateType::IEnum(_) => self.add_type("t_enum", "enum", "inplace", 1),
+            IntermediateType::IVector(inner) if **inner == IntermediateType::IU8 => {
+                self.add_type("t_bytes_storage", "bytes", "bytes", 32)
+            }
+            IntermediateType::IVector(inner) => {
+                let inner_type_id = self.type_id(inner);
+                let type_id = format!("t_array({inner_type_id})dyn_storage");
+                let label = format!("{}[]", self.layout.types[&inner_type_id].label);
+                self.layout
+                    .types
+                    .entry(type_id.clone())
+                    .or_insert(StorageType {
+                        encoding: "dynamic_array",
+                        label,
+                        number_of_bytes: "32".to_owned(),
+                        key: None,
+                        value: Some(inner_type_id),
+                        members: None,
+                    });
+                type_id
+            }
+            IntermediateType::IStruct {
+                module_id, index, ..
+            } if Uid::is_vm_type(module_id, *index, self.compilation_ctx) => {
+                self.add_type("t_bytes32", "bytes32", "inplace", 32)
+            }
+            IntermediateType::IGenericStructInstance {
+                module_id,
+                index,
+                types,
+            } if Mapping::is_vm_type(module_id, *index, self.compilation_ctx) => {
+                let key_type_id = self.type_id(&types[0]);
+                let value_type_id = self.value_type_id(&types[1]);
+                let type_id = format!("t_mapping({key_type_id},{value_type_id})");
+                let label = format!(
+                    "mapping({} => {})",
+                    self.layout.types[&key_type_id].label, self.layout.types[&value_type_id].label
+                );
+                self.layout
+                    .types
+                    .entry(type_id.clone())
+                    .or_insert(StorageType {
+                        encoding: "mapping",
+                        label,
+                        number_of_bytes: "32".to_owned(),
+                        key: Some(key_type_id),
+                        value: Some(value_type_id),
+                        members: None,
+                    });
+                type_id
+            }
+            IntermediateType::IGenericStructInstance {
+                module_id,
+                index,
+                types,
+            } if StorageVec::is_vm_type(module_id, *index, self.compilation_ctx) => {
+                let value_type_id = self.value_type_id(&types[0]);
+                let type_id = format!("t_array({value_type_id})dyn_storage");
+                let label = format!("{}[]", self.layout.types[&value_type_id].label);
+                self.layout
+                    .types
+                    .entry(type_id.clone())
+                    .or_insert(StorageType {
+                        encoding: "dynamic_array",
+                        label,
+                        number_of_bytes: "32".to_owned(),
+                        key: None,
+                        value: Some(value_type_id),
+                        members: None,
+                    });
+                type_id
+            }
+            _ => panic!("unexpected storage field type {field:?}"),
+        }
+    }
+
+    /// Returns the id of the type of a mapping value or a StorageVec element. Structs saved in
+    /// mappings and StorageVecs start at the derived slot, so they are described with their own
+    /// members.
+    fn value_type_id(&mut self, value: &IntermediateType) -> String {
+        let Some(struct_) = self.nested_struct(value) else {
+            return self.type_id(value);
+        };
+
+        let type_id = format!("t_struct({})_storage", struct_.identifier);
+        if !self.layout.types.contains_key(&type_id) {
+            let mut members = Vec::new();
+            let (end_slot, _) = self.add_members(
+                &struct_,
+                0..struct_.fields.len(),
+                "",
+                (U256::ZERO, 0),
+                &mut members,
+            );
+
+            self.layout.types.insert(
+                type_id.clone(),
+                StorageType {
+                    encoding: "inplace",
+                    label: format!("struct {}", struct_.identifier),
+                    number_of_bytes: ((end_slot + U256::from(1)) * U256::from(32)).to_string(),
+                    key: None,
+                    value: None,
+                    members: Some(members),
+                },
+            );
+        }
+
+        type_id
+    }
+
+    fn add_type(
+        &mut self,
+        type_id: &str,
+        label: &str,
+        encoding: &'static str,
+        number_of_bytes: u32,
+    ) -> String {
+        self.layout
+            .types
+            .entry(type_id.to_owned())
+            .or_insert(StorageType {
+                encoding,
+                label: label.to_owned(),
+                number_of_bytes: number_of_bytes.to_string(),
+                key: None,
+                value: None,
+                members: None,
+            });
+
+        type_id.to_owned()
+    }
+}
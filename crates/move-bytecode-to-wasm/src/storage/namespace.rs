@@ -0,0 +1,164 @@
+//! ERC-7201 namespaced storage roots.
+//!
+//! By default every object lives in the objects mapping rooted at slot 0. A struct with the `key`
+//! ability can be moved to its own root by annotating it in the Move source:
+//!
+//! ```move
+//! #[ext(storage_namespace = b"example.main")]
+//! public struct Config has key { ... }
+//! ```
+//!
+//! The objects mapping for that struct is then rooted at
+//! `keccak256(keccak256("example.main") - 1) & ~0xff`, so upgradeable contracts don't collide with
+//! the slots used by proxies.
+//!
+//! Attributes are not part of the compiled bytecode, so they are read from the module's source.
+use std::collections::HashMap;
+
+use alloy_primitives::{U256, keccak256};
+use walrus::{
+    InstrSeqBuilder, Module, ValType,
+    ir::{MemArg, StoreKind},
+};
+
+use crate::{
+    CompilationContext, data::DATA_OBJECTS_SLOT_OFFSET,
+    translation::intermediate_types::structs::IStruct,
+};
+
+const STORAGE_NAMESPACE_ATTRIBUTE: &str = "storage_namespace";
+
+/// Computes the ERC-7201 root slot for the given namespace id.
+pub fn erc7201_root_slot(namespace: &str) -> [u8; 32] {
+    let namespace_hash = U256::from_be_bytes(keccak256(namespace.as_bytes()).0);
+    let mut slot = keccak256((namespace_hash - U256::from(1)).to_be_bytes::<32>()).0;
+    slot[31] = 0;
+    slot
+}
+
+/// Searches the Move source for `storage_namespace` attributes and returns, for every annotated
+/// struct, its namespace id.
+pub fn parse_storage_namespaces(source: &str) -> HashMap<String, String> {
+    let mut namespaces = HashMap::new();
+
+    for (position, _) in source.match_indices(STORAGE_NAMESPACE_ATTRIBUTE) {
+        // Only consider the name when it is the first element of an attribute, so we skip
+        // comments and identifiers containing it
+        let before = source[..position].trim_end();
+        if !before.ends_with("#[") && !before.ends_with("ext(") {
+            continue;
+        }
+
+        let rest = &source[position + STORAGE_NAMESPACE_ATTRIBUTE.len()..];
+        let Some(rest) = rest.trim_start().strip_prefix('=') else {
+            continue;
+        };
+        let rest = rest.trim_start();
+        let rest = rest.strip_prefix('b').unwrap_or(rest);
+        let Some(rest) = rest.strip_prefix('"') else {
+            continue;
+        };
+        let Some(end) = rest.find('"') else {
+            continue;
+        };
+        let namespace = &rest[..end];
+
+        // The attribute applies to the struct declared right after it
+        let identifier = rest[end + 1..]
+            .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .filter(|t| !t.is_empty())
+            .skip_while(|t| *t != "struct")
+            .nth(1);
+
+        if let Some(identifier) = identifier {
+            namespaces.insert(identifier.to_owned(), namespace.to_owned());
+        }
+    }
+
+    namespaces
+}
+
+/// Pushes to the stack a pointer to the root slot of the objects mapping `struct_` is saved in.
+///
+/// Structs with a storage namespace (ERC-7201) have their own root; the rest share the default
+/// objects mapping at slot 0.
+pub fn add_objects_root_slot_instructions(
+    builder: &mut InstrSeqBuilder,
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+    struct_: &IStruct,
+) {
+    let Some(root_slot) = struct_.namespace_root_slot else {
+        builder.i32_const(DATA_OBJECTS_SLOT_OFFSET);
+        return;
+    };
+
+    let root_slot_ptr = module.locals.add(ValType::I32);
+    builder
+        .i32_const(32)
+        .call(compilation_ctx.allocator)
+        .local_set(root_slot_ptr);
+
+    // The slot is kept in big endian, as the storage functions expect it
+    for (i, chunk) in root_slot.chunks_exact(8).enumerate() {
+        builder
+            .local_get(root_slot_ptr)
+            .i64_const(i64::from_le_bytes(chunk.try_into().unwrap()))
+            .store(
+                compilation_ctx.memory_id,
+                StoreKind::I64 { atomic: false },
+                MemArg {
+                    align: 0,
+                    offset: i as u32 * 8,
+                },
+            );
+    }
+
+    builder.local_get(root_slot_ptr);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::hex;
+
+    #[test]
+    fn test_erc7201_root_slot() {
+        // Example taken from the ERC-7201 specification
+        assert_eq!(
+            hex!("183a6125c38840424c4a85fa12bab2ab606c4b6d0e7cc73c0c06ba5300eab500"),
+            erc7201_root_slot("example.main")
+        );
+    }
+
+    #[test]
+    fn test_parse_storage_namespaces() {
+        let source = r#"
+module test::namespace;
+
+use stylus::object::UID;
+
+// storage_namespace = b"not.an.attribute"
+#[ext(storage_namespace = b"example.main")]
+public struct Config has key {
+    id: UID,
+    value: u64,
+}
+
+public struct Plain has key {
+    id: UID,
+}
+
+#[ext(storage_namespace = b"example.other")]
+public struct Other has key {
+    id: UID,
+}
+"#;
+
+        let namespaces = parse_storage_namespaces(source);
+
+        assert_eq!(2, namespaces.len());
+        assert_eq!("example.main", namespaces["Config"]);
+        assert_eq!("example.other", namespaces["Other"]);
+    }
+}
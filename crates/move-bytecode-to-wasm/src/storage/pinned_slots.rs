@@ -0,0 +1,227 @@
+//! Storage slots pinned from the Move source.
+//!
+//! The fields of a struct are saved in consecutive slots, starting at the slot derived for the
+//! object. To adopt the layout of an already deployed Solidity contract (for example, when
+//! migrating an ERC-20 that lives behind a proxy), the fields of a struct with the `key` ability
+//! can be pinned to absolute slots:
+//!
+//! ```move
+//! // balances is saved at slot 0, allowances at slot 1 and total_supply at slot 2
+//! #[ext(storage_slot = 0x0)]
+//! public struct Token has key {
+//!     id: UID,
+//!     balances: Mapping<address, u256>,
+//!     allowances: Mapping<address, Mapping<address, u256>>,
+//!     total_supply: u256,
+//! }
+//!
+//! #[ext(storage_slot(total_supply = 0x2, owner = 0x5))]
+//! public struct Other has key { ... }
+//! ```
+//!
+//! `storage_slot = <slot>` pins the first field after the UID, `storage_slot(<field> = <slot>)`
+//! pins the named fields. The fields declared after a pinned one follow it, packed the same way
+//! as the rest of the storage.
+//!
+//! Pinned slots are absolute, so only one object of a pinned struct can be saved at a time.
+//!
+//! Attributes are not part of the compiled bytecode, so they are read from the module's source.
+use std::collections::HashMap;
+
+use alloy_primitives::U256;
+use walrus::{
+    InstrSeqBuilder, LocalId,
+    ir::{MemArg, StoreKind},
+};
+
+use crate::CompilationContext;
+
+const STORAGE_SLOT_ATTRIBUTE: &str = "storage_slot";
+
+/// Slots pinned in a struct.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct PinnedSlots {
+    /// Slot where the first field after the UID is saved
+    pub struct_slot: Option<[u8; 32]>,
+
+    /// Slot where each of the named fields is saved
+    pub field_slots: HashMap<String, [u8; 32]>,
+}
+
+/// Searches the Move source for `storage_slot` attributes and returns, for every annotated
+/// struct, the slots pinned in it.
+///
+/// # Panics
+/// If a slot is not a valid u256 literal.
+pub fn parse_pinned_slots(source: &str) -> HashMap<String, PinnedSlots> {
+    let mut pinned_slots: HashMap<String, PinnedSlots> = HashMap::new();
+
+    for (position, _) in source.match_indices(STORAGE_SLOT_ATTRIBUTE) {
+        // Only consider the name when it is an element of an attribute, so we skip comments and
+        // identifiers containing it
+        let before = source[..position].trim_end();
+        if !before.ends_with("#[") && !before.ends_with("ext(") && !before.ends_with(',') {
+            continue;
+        }
+
+        let rest = source[position + STORAGE_SLOT_ATTRIBUTE.len()..].trim_start();
+        let (pinned, rest) = if let Some(rest) = rest.strip_prefix('=') {
+            let Some((slot, rest)) = parse_slot(rest) else {
+                continue;
+            };
+            (
+                PinnedSlots {
+                    struct_slot: Some(slot),
+                    ..Default::default()
+                },
+                rest,
+            )
+        } else if let Some(rest) = rest.strip_prefix('(') {
+            let Some(end) = rest.find(')') else {
+                continue;
+            };
+
+            let mut field_slots = HashMap::new();
+            for entry in rest[..end].split(',').filter(|e| !e.trim().is_empty()) {
+                let Some((field, slot)) = entry.split_once('=') else {
+                    panic!("invalid storage_slot entry: {entry}");
+                };
+                let Some((slot, _)) = parse_slot(slot) else {
+                    panic!("invalid storage slot for field {}: {slot}", field.trim());
+                };
+                field_slots.insert(field.trim().to_owned(), slot);
+            }
+
+            (
+                PinnedSlots {
+                    struct_slot: None,
+                    field_slots,
+                },
+                &rest[end + 1..],
+            )
+        } else {
+            continue;
+        };
+
+        // The attribute applies to the struct declared right after it
+        let identifier = rest
+            .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .filter(|t| !t.is_empty())
+            .skip_while(|t| *t != "struct")
+            .nth(1);
+
+        if let Some(identifier) = identifier {
+            let entry = pinned_slots.entry(identifier.to_owned()).or_default();
+            entry.struct_slot = pinned.struct_slot.or(entry.struct_slot);
+            entry.field_slots.extend(pinned.field_slots);
+        }
+    }
+
+    pinned_slots
+}
+
+/// Parses a slot literal (hexadecimal with the `0x` prefix, or decimal) at the start of `source`,
+/// returning it in big endian and the rest of the source.
+fn parse_slot(source: &str) -> Option<([u8; 32], &str)> {
+    let source = source.trim_start();
+    let end = source
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .unwrap_or(source.len());
+
+    let literal = source[..end].replace('_', "");
+    let literal = literal
+        .strip_suffix("u256")
+        .map(str::to_owned)
+        .unwrap_or(literal);
+    let slot = literal.parse::<U256>().ok()?;
+
+    Some((slot.to_be_bytes(), &source[end..]))
+}
+
+/// Adds the instructions to point `slot_ptr` to the pinned `slot`. As with
+/// `StorageNextSlot`, the slot is overwritten in place.
+pub fn add_pin_slot_instructions(
+    builder: &mut InstrSeqBuilder,
+    compilation_ctx: &CompilationContext,
+    slot_ptr: LocalId,
+    slot: &[u8; 32],
+) {
+    // The slot is kept in big endian, as the storage functions expect it
+    for (i, chunk) in slot.chunks_exact(8).enumerate() {
+        builder
+            .local_get(slot_ptr)
+            .i64_const(i64::from_le_bytes(chunk.try_into().unwrap()))
+            .store(
+                compilation_ctx.memory_id,
+                StoreKind::I64 { atomic: false },
+                MemArg {
+                    align: 0,
+                    offset: i as u32 * 8,
+                },
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slot(n: u64) -> [u8; 32] {
+        U256::from(n).to_be_bytes()
+    }
+
+    #[test]
+    fn test_parse_pinned_slots() {
+        let source = r#"
+module test::pinned;
+
+use stylus::object::UID;
+
+// storage_slot = 0x1
+#[ext(storage_slot = 0x0)]
+public struct Token has key {
+    id: UID,
+    total_supply: u256,
+}
+
+public struct Plain has key {
+    id: UID,
+}
+
+#[ext(storage_slot(total_supply = 0x2, owner = 5))]
+public struct Other has key {
+    id: UID,
+    total_supply: u256,
+    owner: address,
+}
+
+#[ext(storage_namespace = b"example.main", storage_slot = 0x10)]
+public struct Namespaced has key {
+    id: UID,
+    value: u8,
+}
+"#;
+
+        let pinned_slots = parse_pinned_slots(source);
+
+        assert_eq!(3, pinned_slots.len());
+        assert_eq!(
+            PinnedSlots {
+                struct_slot: Some(slot(0)),
+                field_slots: HashMap::new(),
+            },
+            pinned_slots["Token"]
+        );
+        assert_eq!(
+            PinnedSlots {
+                struct_slot: None,
+                field_slots: HashMap::from([
+                    ("total_supply".to_owned(), slot(2)),
+                    ("owner".to_owned(), slot(5)),
+                ]),
+            },
+            pinned_slots["Other"]
+        );
+        assert_eq!(Some(slot(16)), pinned_slots["Namespaced"].struct_slot);
+    }
+}
@@ -0,0 +1,98 @@
+//! Type tags identifying Move types in the storage.
+//!
+//! A type tag is the keccak256 hash of the fully qualified name of the type, for example
+//! `0x...::module::Struct<u64, address>`. They are used where values of different types share the
+//! same storage space: bags keep the tag of each saved value, and global storage derives the slot
+//! of a resource from the tag of its type.
+use alloy_primitives::keccak256;
+use walrus::{
+    InstrSeqBuilder, LocalId, Module, ValType,
+    ir::{MemArg, StoreKind},
+};
+
+use crate::{CompilationContext, translation::intermediate_types::IntermediateType};
+
+/// Computes the type tag of `itype`.
+pub fn type_tag(itype: &IntermediateType, compilation_ctx: &CompilationContext) -> [u8; 32] {
+    keccak256(type_name(itype, compilation_ctx).as_bytes()).0
+}
+
+/// Adds the instructions to allocate the type tag of `itype` in memory.
+///
+/// # Returns
+/// local holding a pointer to the type tag
+pub fn add_type_tag_instructions(
+    builder: &mut InstrSeqBuilder,
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+    itype: &IntermediateType,
+) -> LocalId {
+    let tag = type_tag(itype, compilation_ctx);
+    let tag_ptr = module.locals.add(ValType::I32);
+
+    builder
+        .i32_const(32)
+        .call(compilation_ctx.allocator)
+        .local_set(tag_ptr);
+
+    for (i, chunk) in tag.chunks_exact(8).enumerate() {
+        builder
+            .local_get(tag_ptr)
+            .i64_const(i64::from_le_bytes(chunk.try_into().unwrap()))
+            .store(
+                compilation_ctx.memory_id,
+                StoreKind::I64 { atomic: false },
+                MemArg {
+                    align: 0,
+                    offset: i as u32 * 8,
+                },
+            );
+    }
+
+    tag_ptr
+}
+
+/// Returns the fully qualified name of the type.
+fn type_name(itype: &IntermediateType, compilation_ctx: &CompilationContext) -> String {
+    match itype {
+        IntermediateType::IBool => "bool".to_owned(),
+        IntermediateType::IU8 => "u8".to_owned(),
+        IntermediateType::IU16 => "u16".to_owned(),
+        IntermediateType::IU32 => "u32".to_owned(),
+        IntermediateType::IU64 => "u64".to_owned(),
+        IntermediateType::IU128 => "u128".to_owned(),
+        IntermediateType::IU256 => "u256".to_owned(),
+        IntermediateType::IAddress => "address".to_owned(),
+        IntermediateType::ISigner => "signer".to_owned(),
+        IntermediateType::IVector(inner) => {
+            format!("vector<{}>", type_name(inner, compilation_ctx))
+        }
+        IntermediateType::IStruct { module_id, index } => {
+            let struct_ = compilation_ctx
+                .get_struct_by_index(module_id, *index)
+                .unwrap();
+            format!("{module_id}::{}", struct_.identifier)
+        }
+        IntermediateType::IGenericStructInstance {
+            module_id,
+            index,
+            types,
+        } => {
+            let struct_ = compilation_ctx
+                .get_struct_by_index(module_id, *index)
+                .unwrap();
+            let types = types
+                .iter()
+                .map(|t| type_name(t, compilation_ctx))
+                .collect::<Vec<String>>()
+                .join(", ");
+            format!("{module_id}::{}<{types}>", struct_.identifier)
+        }
+        IntermediateType::IEnum(index) => format!("enum_{index}"),
+        IntermediateType::IRef(_)
+        | IntermediateType::IMutRef(_)
+        | IntermediateType::ITypeParameter(_) => {
+            panic!("type {itype:?} has no type tag")
+        }
+    }
+}
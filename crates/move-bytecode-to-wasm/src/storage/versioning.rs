@@ -0,0 +1,314 @@
+//! Versioned storage structs.
+//!
+//! A struct with the `key` ability can opt in to versioning by annotating it in the Move source.
+//! Its version is then saved in the lowest-order byte of the object's first slot, and the fields
+//! follow it, packed the same way as the rest of the storage:
+//!
+//! ```move
+//! #[ext(storage_version = 2)]
+//! public struct Config has key {
+//!     id: UID,
+//!     fee: u64,
+//!     // Added in version 2
+//!     max_fee: u64,
+//! }
+//!
+//! #[ext(storage_migration)]
+//! fun migrate_config(config: &mut Config, from_version: u8) {
+//!     if (from_version < 2) {
+//!         config.max_fee = config.fee * 2;
+//!     }
+//! }
+//! ```
+//!
+//! When an object saved with an older version is loaded, its fields are decoded with the current
+//! layout and the migration function of the struct, if any, is called with the version it was
+//! saved with. New fields must be added after the existing ones, so they are decoded as zero from
+//! the slots the older version never wrote. The object is saved with the current version the
+//! next time it is written, for example when it is borrowed mutably. Loading an object saved with
+//! a newer version than the current one aborts.
+//!
+//! Attributes are not part of the compiled bytecode, so they are read from the module's source.
+use std::collections::{HashMap, HashSet};
+
+use walrus::{
+    FunctionBuilder, FunctionId, FunctionKind, InstrSeqBuilder, LocalId, Module, ValType,
+    ir::{BinaryOp, ExtendedLoad, LoadKind, MemArg, StoreKind},
+};
+
+use crate::{
+    CompilationContext,
+    data::DATA_SLOT_DATA_PTR_OFFSET,
+    translation::{
+        intermediate_types::{IntermediateType, structs::IStruct},
+        table::FunctionTable,
+    },
+    utils::parse_ext_flag,
+};
+
+const STORAGE_VERSION_ATTRIBUTE: &str = "storage_version";
+const STORAGE_MIGRATION_ATTRIBUTE: &str = "storage_migration";
+
+const MIGRATE_FN_NAME: &str = "storage_migrate";
+
+/// Number of bytes the version occupies in the first slot of a versioned struct
+pub const STORAGE_VERSION_SIZE: u32 = 1;
+
+/// Searches the Move source for `storage_version` attributes and returns, for every annotated
+/// struct, its version.
+///
+/// # Panics
+/// If a version is not a valid u8 literal.
+pub fn parse_storage_versions(source: &str) -> HashMap<String, u8> {
+    let mut versions = HashMap::new();
+
+    for (position, _) in source.match_indices(STORAGE_VERSION_ATTRIBUTE) {
+        // Only consider the name when it is an element of an attribute, so we skip comments and
+        // identifiers containing it
+        let before = source[..position].trim_end();
+        if !before.ends_with("#[") && !before.ends_with("ext(") && !before.ends_with(',') {
+            continue;
+        }
+
+        let rest = source[position + STORAGE_VERSION_ATTRIBUTE.len()..].trim_start();
+        let Some(rest) = rest.strip_prefix('=') else {
+            continue;
+        };
+        let rest = rest.trim_start();
+        let end = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+
+        let literal = rest[..end].replace('_', "");
+        let literal = literal.strip_suffix("u8").unwrap_or(&literal);
+        let version = literal
+            .parse::<u8>()
+            .unwrap_or_else(|_| panic!("invalid storage version: {}", &rest[..end]));
+
+        // The attribute applies to the struct declared right after it
+        let identifier = rest[end..]
+            .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .filter(|t| !t.is_empty())
+            .skip_while(|t| *t != "struct")
+            .nth(1);
+
+        if let Some(identifier) = identifier {
+            versions.insert(identifier.to_owned(), version);
+        }
+    }
+
+    versions
+}
+
+/// Searches the Move source for `storage_migration` attributes and returns the names of the
+/// annotated functions.
+pub fn parse_storage_migrations(source: &str) -> HashSet<String> {
+    parse_ext_flag(source, STORAGE_MIGRATION_ATTRIBUTE)
+}
+
+/// Adds the instructions to write `version` in the slot data placeholder, where the first slot of
+/// a versioned struct is being encoded.
+pub fn add_write_version_instructions(
+    builder: &mut InstrSeqBuilder,
+    compilation_ctx: &CompilationContext,
+    version: u8,
+) {
+    builder
+        .i32_const(DATA_SLOT_DATA_PTR_OFFSET)
+        .i32_const(version as i32)
+        .store(
+            compilation_ctx.memory_id,
+            StoreKind::I32_8 { atomic: false },
+            MemArg {
+                align: 0,
+                offset: 32 - STORAGE_VERSION_SIZE,
+            },
+        );
+}
+
+/// Adds the instructions to read the version saved in the first slot of a versioned struct, that
+/// must be loaded in the slot data placeholder, into `stored_version`.
+pub fn add_read_version_instructions(
+    builder: &mut InstrSeqBuilder,
+    compilation_ctx: &CompilationContext,
+    stored_version: LocalId,
+) {
+    builder
+        .i32_const(DATA_SLOT_DATA_PTR_OFFSET)
+        .load(
+            compilation_ctx.memory_id,
+            LoadKind::I32_8 {
+                kind: ExtendedLoad::ZeroExtend,
+            },
+            MemArg {
+                align: 0,
+                offset: 32 - STORAGE_VERSION_SIZE,
+            },
+        )
+        .local_set(stored_version);
+}
+
+/// Adds the instructions to migrate a decoded struct saved with `stored_version`. If it is older
+/// than the current version the migration function of the struct is called, and if it is newer
+/// the execution aborts.
+pub fn add_migrate_instructions(
+    builder: &mut InstrSeqBuilder,
+    module: &mut Module,
+    struct_: &IStruct,
+    version: u8,
+    struct_ptr: LocalId,
+    stored_version: LocalId,
+) {
+    let migrate_fn = migrate_function(module, struct_);
+
+    builder
+        .local_get(stored_version)
+        .i32_const(version as i32)
+        .binop(BinaryOp::I32GtU)
+        .if_else(
+            None,
+            |then| {
+                then.unreachable();
+            },
+            |_| {},
+        );
+
+    builder
+        .local_get(stored_version)
+        .i32_const(version as i32)
+        .binop(BinaryOp::I32LtU)
+        .if_else(
+            None,
+            |then| {
+                then.local_get(struct_ptr)
+                    .local_get(stored_version)
+                    .call(migrate_fn);
+            },
+            |_| {},
+        );
+}
+
+/// Declares the function that migrates a struct loaded from the storage. Its body, that calls the
+/// migration function defined in the Move source, is generated by `finalize_storage_migrations`.
+/// Structs without a migration function are left as they were decoded.
+///
+/// Arguments:
+/// - struct pointer
+/// - version the struct was saved with
+fn migrate_function(module: &mut Module, struct_: &IStruct) -> FunctionId {
+    let name = format!("{MIGRATE_FN_NAME}_{}", struct_.identifier);
+    if let Some(function) = module.funcs.by_name(&name) {
+        return function;
+    };
+
+    let mut function = FunctionBuilder::new(&mut module.types, &[ValType::I32, ValType::I32], &[]);
+    function.name(name);
+
+    let struct_ptr = module.locals.add(ValType::I32);
+    let stored_version = module.locals.add(ValType::I32);
+
+    function.finish(vec![struct_ptr, stored_version], &mut module.funcs)
+}
+
+/// Generates the body of the migration functions declared while translating the module, calling
+/// the functions annotated with `storage_migration`. It must be called once all the functions of
+/// the module are translated.
+pub fn finalize_storage_migrations(
+    module: &mut Module,
+    compilation_ctx: &CompilationContext,
+    function_table: &FunctionTable,
+) {
+    for function_information in compilation_ctx
+        .root_module_data
+        .functions
+        .information
+        .iter()
+        .filter(|f| f.storage_migration)
+    {
+        let Some(IntermediateType::IMutRef(inner)) =
+            function_information.signature.arguments.first()
+        else {
+            continue;
+        };
+        let struct_ = compilation_ctx
+            .get_struct_by_intermediate_type(inner)
+            .unwrap();
+
+        let Some(migrate_fn) = module
+            .funcs
+            .by_name(&format!("{MIGRATE_FN_NAME}_{}", struct_.identifier))
+        else {
+            continue;
+        };
+
+        let migration_fn = function_table
+            .get_by_function_id(&function_information.function_id)
+            .and_then(|entry| entry.wasm_function_id)
+            .unwrap_or_else(|| {
+                panic!(
+                    "storage migration function {} was not translated",
+                    function_information.function_id
+                )
+            });
+
+        let FunctionKind::Local(function) = &mut module.funcs.get_mut(migrate_fn).kind else {
+            panic!(
+                "{MIGRATE_FN_NAME}_{} is not a local function",
+                struct_.identifier
+            );
+        };
+        let (struct_ptr, stored_version) = (function.args[0], function.args[1]);
+
+        // A mutable reference to a struct is the struct pointer
+        function
+            .builder_mut()
+            .func_body()
+            .local_get(struct_ptr)
+            .local_get(stored_version)
+            .call(migration_fn);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_storage_versions() {
+        let source = r#"
+module test::versioned;
+
+use stylus::object::UID;
+
+// storage_version = 7
+#[ext(storage_version = 2)]
+public struct Config has key {
+    id: UID,
+    fee: u64,
+}
+
+public struct Plain has key {
+    id: UID,
+}
+
+#[ext(storage_namespace = b"example.main", storage_version = 3u8)]
+public struct Namespaced has key {
+    id: UID,
+}
+
+#[ext(storage_migration)]
+fun migrate_config(config: &mut Config, from_version: u8) {}
+
+fun storage_migration_helper() {}
+"#;
+
+        let versions = parse_storage_versions(source);
+        assert_eq!(
+            HashMap::from([("Config".to_owned(), 2), ("Namespaced".to_owned(), 3)]),
+            versions
+        );
+
+        let migrations = parse_storage_migrations(source);
+        assert_eq!(HashSet::from(["migrate_config".to_owned()]), migrations);
+    }
+}
@@ -3,12 +3,17 @@
 use walrus::{FunctionId, MemoryId, Module, ModuleConfig, ValType};
 use wasmtime::{Caller, Engine, Instance, Linker, Module as WasmModule, Store, TypedFunc};
 
-use crate::memory::setup_module_memory;
+use crate::{memory::setup_module_memory, translation_options::MemoryGrowth};
 
 pub fn build_module(initial_memory_offset: Option<i32>) -> (Module, FunctionId, MemoryId) {
     let config = ModuleConfig::new();
     let mut module = Module::with_config(config);
-    let (allocator_func, memory_id) = setup_module_memory(&mut module, initial_memory_offset);
+    let (allocator_func, memory_id) = setup_module_memory(
+        &mut module,
+        initial_memory_offset,
+        MemoryGrowth::default(),
+        None,
+    );
 
     (module, allocator_func, memory_id)
 }
@@ -150,6 +155,9 @@ macro_rules! test_compilation_context {
             deps_data: &std::collections::HashMap::new(),
             memory_id: $memory_id,
             allocator: $allocator,
+            options: &$crate::TranslationOptions::default(),
+            debug_info: None,
+            flows: None,
         }
     };
 }
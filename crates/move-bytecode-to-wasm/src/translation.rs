@@ -14,14 +14,16 @@ pub mod table;
 use crate::{
     CompilationContext,
     compilation_context::ModuleData,
+    coverage_map::coverage_function_id,
     data::DATA_OBJECTS_MAPPING_SLOT_NUMBER_OFFSET,
     generics::{
         extract_type_instances_from_stack, instantiate_vec_type_parameters,
         replace_type_parameters, type_contains_generics,
     },
-    hostio::host_functions::storage_flush_cache,
-    native_functions::NativeFunction,
+    hostio::host_functions::{coverage_hit, unit_test_abort},
+    native_functions::{NativeFunction, add_abort_log_instructions},
     runtime::RuntimeFunction,
+    storage::namespace::add_objects_root_slot_instructions,
     wasm_builder_extensions::WasmBuilderExtension,
 };
 use anyhow::Result;
@@ -37,11 +39,14 @@ use intermediate_types::{
     vector::IVector,
 };
 use move_binary_format::{
-    file_format::{Bytecode, CodeUnit},
+    file_format::{Bytecode, CodeUnit, FunctionDefinitionIndex, StructDefInstantiationIndex},
     internals::ModuleIndex,
 };
 use relooper::BranchMode;
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
 use table::{FunctionId, FunctionTable, TableEntry};
 use types_stack::TypesStack;
 use walrus::TableId;
@@ -94,6 +99,11 @@ struct TranslateFlowContext<'a> {
     function_table: &'a mut FunctionTable,
     function_locals: &'a Vec<LocalId>,
     branch_targets: &'a mut BranchTargets,
+    /// Id of the function in the coverage map, if its blocks report their execution
+    coverage_function: Option<i32>,
+    /// Definition of the function in the root module, if its instructions are mapped to the
+    /// source
+    debug_function: Option<FunctionDefinitionIndex>,
 }
 
 /// Translates a move function to WASM
@@ -134,12 +144,35 @@ pub fn translate_function(
         function_information,
     );
 
-    let flow = Flow::new(move_bytecode, function_information);
+    // Only the functions of the root module are mapped to the source
+    let debug_function = compilation_ctx
+        .debug_info
+        .filter(|_| module_data.id == compilation_ctx.root_module_data.id)
+        .and_then(|debug_info| {
+            debug_info.function_index(&function_information.function_id.identifier)
+        });
+    if let (Some(debug_info), Some(debug_function)) = (compilation_ctx.debug_info, debug_function) {
+        debug_info.name_locals(module, debug_function, &function_locals);
+    }
+
+    let flow = match compilation_ctx.flows {
+        Some(flows) => flows.get_or_build(move_bytecode, function_information),
+        None => Rc::new(Flow::new(move_bytecode, function_information)),
+    };
 
     let mut branch_targets = BranchTargets::new();
     let mut types_stack = TypesStack::new();
     let mut functions_to_link = HashSet::new();
 
+    // Only the functions of the root module are covered
+    let coverage_function = if compilation_ctx.options.coverage
+        && module_data.id == compilation_ctx.root_module_data.id
+    {
+        coverage_function_id(module_data, &function_information.function_id.identifier)
+    } else {
+        None
+    };
+
     let mut ctx = TranslateFlowContext {
         compilation_ctx,
         module_data,
@@ -148,6 +181,8 @@ pub fn translate_function(
         function_locals: &function_locals,
         types_stack: &mut types_stack,
         branch_targets: &mut branch_targets,
+        coverage_function,
+        debug_function,
     };
 
     translate_flow(
@@ -173,12 +208,12 @@ fn translate_flow(
 ) {
     match flow {
         Flow::Simple {
+            label,
             instructions,
             stack,
             branches,
             immediate,
             next,
-            ..
         } => {
             let ty = InstrSeqType::new(&mut module.types, &[], stack);
             builder.block(ty, |block| {
@@ -195,8 +230,17 @@ fn translate_flow(
                     }
                 }
 
+                if let Some(coverage_function) = ctx.coverage_function {
+                    let (coverage_hit_function, _) = coverage_hit(module);
+                    block
+                        .i32_const(coverage_function)
+                        .i32_const(i32::from(*label))
+                        .call(coverage_hit_function);
+                }
+
                 // First translate the instuctions associated with the simple flow itself
-                for instruction in instructions {
+                for (index, instruction) in instructions.iter().enumerate() {
+                    let first_instruction = block.instrs().len();
                     let mut fns_to_link = translate_instruction(
                         instruction,
                         ctx.compilation_ctx,
@@ -214,6 +258,17 @@ fn translate_flow(
                         panic!("there was an error translating instruction {instruction:?}.\n{e}")
                     });
 
+                    if let (Some(debug_info), Some(debug_function)) =
+                        (ctx.compilation_ctx.debug_info, ctx.debug_function)
+                    {
+                        debug_info.tag_instruction(
+                            block,
+                            first_instruction,
+                            debug_function,
+                            label + index as u16,
+                        );
+                    }
+
                     functions_to_link.extend(fns_to_link.drain(..));
                 }
                 // Then translate instructions of the immediate block, inside the current block
@@ -1238,6 +1293,13 @@ fn translate_instruction(
                             let locate_struct_fn = RuntimeFunction::LocateStructSlot
                                 .get(module, Some(compilation_ctx));
 
+                            add_objects_root_slot_instructions(
+                                builder,
+                                module,
+                                compilation_ctx,
+                                &struct_,
+                            );
+
                             let struct_ptr = module.locals.add(ValType::I32);
                             builder
                                 .local_get(function_locals[arg_index])
@@ -1263,9 +1325,6 @@ fn translate_instruction(
                                 .local_get(struct_ptr)
                                 .i32_const(DATA_OBJECTS_MAPPING_SLOT_NUMBER_OFFSET)
                                 .call(save_in_slot_fn);
-
-                            let (storage_flush_cache, _) = storage_flush_cache(module);
-                            builder.i32_const(1).call(storage_flush_cache);
                         }
                     }
                 }
@@ -1731,7 +1790,21 @@ fn translate_instruction(
         }
         Bytecode::Abort => {
             types_stack.pop_expecting(&IntermediateType::IU64)?;
-            builder.return_();
+            if compilation_ctx.options.unit_tests {
+                // The hook traps, reporting the abort code to the test runner
+                let (unit_test_abort_function, _) = unit_test_abort(module);
+                builder.call(unit_test_abort_function).unreachable();
+            } else {
+                if compilation_ctx.options.debug {
+                    add_abort_log_instructions(
+                        builder,
+                        module,
+                        compilation_ctx,
+                        &mapped_function.function_id.to_string(),
+                    );
+                }
+                builder.return_();
+            }
         }
         Bytecode::Xor => {
             let [t1, t2] = types_stack.pop_n_from_stack()?;
@@ -2002,6 +2075,150 @@ fn translate_instruction(
 
             types_stack.push(IntermediateType::IEnum(enum_.index));
         }
+        //**
+        // Global storage
+        //**
+        // Resources are saved in slots derived from the address and the type (see
+        // `runtime::global_storage`)
+        Bytecode::MoveToDeprecated(struct_definition_index) => {
+            let itype = IntermediateType::IStruct {
+                module_id: module_data.id.clone(),
+                index: struct_definition_index.0,
+            };
+            bytecodes::global_storage::move_to(
+                &itype,
+                module,
+                builder,
+                compilation_ctx,
+                types_stack,
+            )?;
+        }
+        Bytecode::MoveToGenericDeprecated(struct_definition_index) => {
+            let itype = generic_struct_instance_type(
+                struct_definition_index,
+                module_data,
+                mapped_function,
+            )?;
+            bytecodes::global_storage::move_to(
+                &itype,
+                module,
+                builder,
+                compilation_ctx,
+                types_stack,
+            )?;
+        }
+        Bytecode::MoveFromDeprecated(struct_definition_index) => {
+            let itype = IntermediateType::IStruct {
+                module_id: module_data.id.clone(),
+                index: struct_definition_index.0,
+            };
+            bytecodes::global_storage::move_from(
+                &itype,
+                module,
+                builder,
+                compilation_ctx,
+                types_stack,
+            )?;
+        }
+        Bytecode::MoveFromGenericDeprecated(struct_definition_index) => {
+            let itype = generic_struct_instance_type(
+                struct_definition_index,
+                module_data,
+                mapped_function,
+            )?;
+            bytecodes::global_storage::move_from(
+                &itype,
+                module,
+                builder,
+                compilation_ctx,
+                types_stack,
+            )?;
+        }
+        Bytecode::ExistsDeprecated(struct_definition_index) => {
+            let itype = IntermediateType::IStruct {
+                module_id: module_data.id.clone(),
+                index: struct_definition_index.0,
+            };
+            bytecodes::global_storage::exists(
+                &itype,
+                module,
+                builder,
+                compilation_ctx,
+                types_stack,
+            )?;
+        }
+        Bytecode::ExistsGenericDeprecated(struct_definition_index) => {
+            let itype = generic_struct_instance_type(
+                struct_definition_index,
+                module_data,
+                mapped_function,
+            )?;
+            bytecodes::global_storage::exists(
+                &itype,
+                module,
+                builder,
+                compilation_ctx,
+                types_stack,
+            )?;
+        }
+        Bytecode::ImmBorrowGlobalDeprecated(struct_definition_index) => {
+            let itype = IntermediateType::IStruct {
+                module_id: module_data.id.clone(),
+                index: struct_definition_index.0,
+            };
+            bytecodes::global_storage::borrow_global(
+                &itype,
+                false,
+                module,
+                builder,
+                compilation_ctx,
+                types_stack,
+            )?;
+        }
+        Bytecode::ImmBorrowGlobalGenericDeprecated(struct_definition_index) => {
+            let itype = generic_struct_instance_type(
+                struct_definition_index,
+                module_data,
+                mapped_function,
+            )?;
+            bytecodes::global_storage::borrow_global(
+                &itype,
+                false,
+                module,
+                builder,
+                compilation_ctx,
+                types_stack,
+            )?;
+        }
+        Bytecode::MutBorrowGlobalDeprecated(struct_definition_index) => {
+            let itype = IntermediateType::IStruct {
+                module_id: module_data.id.clone(),
+                index: struct_definition_index.0,
+            };
+            bytecodes::global_storage::borrow_global(
+                &itype,
+                true,
+                module,
+                builder,
+                compilation_ctx,
+                types_stack,
+            )?;
+        }
+        Bytecode::MutBorrowGlobalGenericDeprecated(struct_definition_index) => {
+            let itype = generic_struct_instance_type(
+                struct_definition_index,
+                module_data,
+                mapped_function,
+            )?;
+            bytecodes::global_storage::borrow_global(
+                &itype,
+                true,
+                module,
+                builder,
+                compilation_ctx,
+                types_stack,
+            )?;
+        }
         b => Err(TranslationError::UnsupportedOperation {
             operation: b.clone(),
         })?,
@@ -2010,6 +2227,42 @@ fn translate_instruction(
     Ok(functions_calls_to_link)
 }
 
+/// Returns the type of the generic struct instantiated by `struct_definition_index`. If the
+/// instantiation contains type parameters, they are replaced by the caller's type instantiations.
+fn generic_struct_instance_type(
+    struct_definition_index: &StructDefInstantiationIndex,
+    module_data: &ModuleData,
+    mapped_function: &MappedFunction,
+) -> Result<IntermediateType, TranslationError> {
+    let index = module_data
+        .structs
+        .get_generic_struct_idx_by_struct_definition_idx(struct_definition_index);
+
+    let types = module_data
+        .structs
+        .get_generic_struct_types_instances(struct_definition_index)?;
+
+    let types = if types.iter().any(type_contains_generics) {
+        let Some(caller_type_instances) = &mapped_function.function_id.type_instantiations else {
+            // This should never happen
+            panic!("could not instantiate generic types");
+        };
+
+        types
+            .iter()
+            .map(|t| replace_type_parameters(t, caller_type_instances))
+            .collect()
+    } else {
+        types.to_vec()
+    };
+
+    Ok(IntermediateType::IGenericStructInstance {
+        module_id: module_data.id.clone(),
+        index,
+        types,
+    })
+}
+
 fn call_indirect(
     function_entry: &TableEntry,
     function_returns: &[IntermediateType],
@@ -4,5 +4,6 @@
 
 pub mod constants;
 pub mod enums;
+pub mod global_storage;
 pub mod structs;
 pub mod vectors;
@@ -0,0 +1,101 @@
+use walrus::{InstrSeqBuilder, Module};
+
+use crate::{
+    CompilationContext,
+    runtime::RuntimeFunction,
+    translation::{
+        TranslationError, intermediate_types::IntermediateType, types_stack::TypesStack,
+    },
+};
+
+/// Publishes the struct on top of the stack under the signer's address.
+///
+/// Used with the MoveTo and MoveToGeneric bytecodes.
+pub fn move_to(
+    itype: &IntermediateType,
+    module: &mut Module,
+    builder: &mut InstrSeqBuilder,
+    compilation_ctx: &CompilationContext,
+    types_stack: &mut TypesStack,
+) -> Result<(), TranslationError> {
+    types_stack.pop_expecting(itype)?;
+    types_stack.pop_expecting(&IntermediateType::IRef(Box::new(IntermediateType::ISigner)))?;
+
+    let move_to_fn = RuntimeFunction::GlobalMoveTo.get_generic(module, compilation_ctx, &[itype]);
+    builder.call(move_to_fn);
+
+    Ok(())
+}
+
+/// Removes the struct published under the address on top of the stack and pushes it to the stack.
+///
+/// Used with the MoveFrom and MoveFromGeneric bytecodes.
+pub fn move_from(
+    itype: &IntermediateType,
+    module: &mut Module,
+    builder: &mut InstrSeqBuilder,
+    compilation_ctx: &CompilationContext,
+    types_stack: &mut TypesStack,
+) -> Result<(), TranslationError> {
+    types_stack.pop_expecting(&IntermediateType::IAddress)?;
+
+    let move_from_fn =
+        RuntimeFunction::GlobalMoveFrom.get_generic(module, compilation_ctx, &[itype]);
+    builder.call(move_from_fn);
+
+    types_stack.push(itype.clone());
+
+    Ok(())
+}
+
+/// Checks if a struct is published under the address on top of the stack.
+///
+/// Used with the Exists and ExistsGeneric bytecodes.
+pub fn exists(
+    itype: &IntermediateType,
+    module: &mut Module,
+    builder: &mut InstrSeqBuilder,
+    compilation_ctx: &CompilationContext,
+    types_stack: &mut TypesStack,
+) -> Result<(), TranslationError> {
+    types_stack.pop_expecting(&IntermediateType::IAddress)?;
+
+    let exists_fn = RuntimeFunction::GlobalExists.get_generic(module, compilation_ctx, &[itype]);
+    builder.call(exists_fn);
+
+    types_stack.push(IntermediateType::IBool);
+
+    Ok(())
+}
+
+/// Borrows the struct published under the address on top of the stack.
+///
+/// Used with the ImmBorrowGlobal, ImmBorrowGlobalGeneric, MutBorrowGlobal and
+/// MutBorrowGlobalGeneric bytecodes.
+pub fn borrow_global(
+    itype: &IntermediateType,
+    mutable: bool,
+    module: &mut Module,
+    builder: &mut InstrSeqBuilder,
+    compilation_ctx: &CompilationContext,
+    types_stack: &mut TypesStack,
+) -> Result<(), TranslationError> {
+    types_stack.pop_expecting(&IntermediateType::IAddress)?;
+
+    let (borrow_fn, ref_type) = if mutable {
+        (
+            RuntimeFunction::GlobalBorrowMut.get_generic(module, compilation_ctx, &[itype]),
+            IntermediateType::IMutRef(Box::new(itype.clone())),
+        )
+    } else {
+        (
+            RuntimeFunction::GlobalBorrow.get_generic(module, compilation_ctx, &[itype]),
+            IntermediateType::IRef(Box::new(itype.clone())),
+        )
+    };
+    builder.call(borrow_fn);
+
+    types_stack.push(ref_type);
+
+    Ok(())
+}
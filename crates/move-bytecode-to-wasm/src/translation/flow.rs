@@ -1,13 +1,52 @@
-use crate::translation::functions::MappedFunction;
+use crate::translation::{functions::MappedFunction, table::FunctionId};
 use move_abstract_interpreter::control_flow_graph::{ControlFlowGraph, VMControlFlowGraph};
 use move_binary_format::file_format::{Bytecode, CodeUnit};
 use relooper::{BranchMode, ShapedBlock};
-use std::collections::{HashMap, HashSet};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
 use walrus::ValType;
 
+/// Flows of the functions translated, shared by the root modules of a package, which translate the
+/// functions of their dependencies again into their own modules. The flow of a function only
+/// depends on its bytecode and its signature, so it is relooped once, while its WASM, whose ids
+/// belong to the module, is emitted in every module that calls it.
+#[derive(Default)]
+pub(crate) struct FlowCache {
+    flows: RefCell<HashMap<FunctionId, Rc<Flow>>>,
+}
+
+impl FlowCache {
+    /// Flow of the function, built the first time it is translated.
+    pub(crate) fn get_or_build(
+        &self,
+        code_unit: &CodeUnit,
+        function_information: &MappedFunction,
+    ) -> Rc<Flow> {
+        if let Some(flow) = self.flows.borrow().get(&function_information.function_id) {
+            return Rc::clone(flow);
+        }
+
+        let flow = Rc::new(Flow::new(code_unit, function_information));
+        self.flows
+            .borrow_mut()
+            .insert(function_information.function_id.clone(), Rc::clone(&flow));
+        flow
+    }
+
+    /// Number of functions relooped.
+    pub(crate) fn relooped_functions(&self) -> usize {
+        self.flows.borrow().len()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum Flow {
     Simple {
+        /// Offset of the block's first instruction
+        label: u16,
         stack: Vec<ValType>,
         instructions: Vec<Bytecode>,
         immediate: Box<Flow>,
@@ -112,6 +151,7 @@ impl Flow {
                 );
 
                 Flow::Simple {
+                    label: simple_block.label,
                     stack: [block_ctx.1.clone(), immediate_flow.get_stack()].concat(),
                     instructions: block_ctx.0.clone(),
                     immediate: Box::new(immediate_flow),
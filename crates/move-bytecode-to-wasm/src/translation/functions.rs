@@ -33,6 +33,19 @@ pub struct MappedFunction {
 
     /// Flag that tells us if the function contains generic arguments or return values
     pub is_generic: bool,
+
+    /// For the arguments that are immutable references to structs, the fields the function
+    /// accesses. Used to only read those fields when the struct is loaded from storage.
+    pub lazy_storage_fields: HashMap<usize, Vec<usize>>,
+
+    /// Flag that tells us if the entrypoint must reject reentrant calls to the function
+    pub non_reentrant: bool,
+
+    /// Flag that tells us if the function is declared as a view, so it can't modify the state
+    pub is_view: bool,
+
+    /// Flag that tells us if the function migrates a versioned struct loaded from the storage
+    pub storage_migration: bool,
 }
 
 impl MappedFunction {
@@ -69,6 +82,10 @@ impl MappedFunction {
             is_entry: function_definition.visibility == Visibility::Public,
             is_native: function_definition.is_native(),
             is_generic,
+            lazy_storage_fields: HashMap::new(),
+            non_reentrant: false,
+            is_view: false,
+            storage_migration: false,
         }
     }
 }
@@ -115,8 +132,13 @@ impl MappedFunction {
             signature,
             results,
             locals,
+            is_entry: self.is_entry,
+            is_native: self.is_native,
             is_generic: false,
-            ..*self
+            lazy_storage_fields: self.lazy_storage_fields.clone(),
+            non_reentrant: self.non_reentrant,
+            is_view: self.is_view,
+            storage_migration: self.storage_migration,
         }
     }
 }
@@ -596,6 +596,13 @@ impl IU256 {
         module: &mut walrus::Module,
         compilation_ctx: &CompilationContext,
     ) {
+        if compilation_ctx.options.math_hooks {
+            let div_mod_function_id =
+                RuntimeFunction::U256DivModWithHooks.get(module, Some(compilation_ctx));
+            builder.i32_const(1).call(div_mod_function_id);
+            return;
+        }
+
         let div_mod_function_id = RuntimeFunction::HeapIntDivMod.get(module, Some(compilation_ctx));
 
         builder
@@ -609,6 +616,13 @@ impl IU256 {
         module: &mut walrus::Module,
         compilation_ctx: &CompilationContext,
     ) {
+        if compilation_ctx.options.math_hooks {
+            let div_mod_function_id =
+                RuntimeFunction::U256DivModWithHooks.get(module, Some(compilation_ctx));
+            builder.i32_const(0).call(div_mod_function_id);
+            return;
+        }
+
         let div_mod_function_id = RuntimeFunction::HeapIntDivMod.get(module, Some(compilation_ctx));
 
         builder
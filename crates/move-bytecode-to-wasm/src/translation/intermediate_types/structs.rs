@@ -65,6 +65,10 @@ pub struct IStruct {
     /// Field's types ordered by index
     pub fields: Vec<IntermediateType>,
 
+    /// Field's names ordered by index. Only used to describe the struct (for example, in the
+    /// storage layout), so it can be empty for structs created by the compiler
+    pub field_names: Vec<String>,
+
     /// Map between handles and fields types
     pub fields_types: HashMap<FieldHandleIndex, IntermediateType>,
 
@@ -87,6 +91,18 @@ pub struct IStruct {
     pub saved_in_storage: bool,
 
     pub is_one_time_witness: bool,
+
+    /// ERC-7201 root slot of the objects mapping where this struct is saved. If None, the struct
+    /// is saved in the default objects mapping (slot 0).
+    pub namespace_root_slot: Option<[u8; 32]>,
+
+    /// Absolute storage slots, by field index, where the fields pinned with the `storage_slot`
+    /// attribute are saved (see `storage::pinned_slots`).
+    pub pinned_slots: HashMap<usize, [u8; 32]>,
+
+    /// Version saved in the first slot of the struct, set with the `storage_version` attribute
+    /// (see `storage::versioning`). If None, the struct is not versioned.
+    pub storage_version: Option<u8>,
 }
 
 impl IStruct {
@@ -116,8 +132,12 @@ impl IStruct {
             field_offsets,
             fields_types,
             fields: ir_fields,
+            field_names: Vec::new(),
             saved_in_storage,
             is_one_time_witness,
+            namespace_root_slot: None,
+            pinned_slots: HashMap::new(),
+            storage_version: None,
         }
     }
 
@@ -510,6 +530,8 @@ impl IStruct {
             struct_definition_index: StructDefinitionIndex::new(
                 self.struct_definition_index.into_index() as u16,
             ),
+            field_names: self.field_names.clone(),
+            pinned_slots: self.pinned_slots.clone(),
             ..*self
         }
     }
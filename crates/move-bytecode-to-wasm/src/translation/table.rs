@@ -6,14 +6,14 @@ use walrus::{
     ir::Value,
 };
 
-use crate::compilation_context::ModuleId;
+use crate::compilation_context::{ModuleId, Symbol};
 
 use super::{functions::MappedFunction, intermediate_types::IntermediateType};
 
 /// Identifies a function inside a module
 #[derive(PartialEq, Eq, Hash, Debug, Clone)]
 pub struct FunctionId {
-    pub identifier: String,
+    pub identifier: Symbol,
 
     pub module_id: ModuleId,
 
@@ -23,10 +23,11 @@ pub struct FunctionId {
 impl FunctionId {
     /// Returns the generic function ID corresponding to a function ID with type instantiations.
     pub fn get_generic_fn_id(&self) -> Self {
-        // TODO: clone...
-        let mut id = self.clone();
-        id.type_instantiations = None;
-        id
+        Self {
+            identifier: self.identifier.clone(),
+            module_id: self.module_id.clone(),
+            type_instantiations: None,
+        }
     }
 }
 
@@ -111,6 +112,13 @@ impl FunctionTable {
 
         entry.wasm_function_id = Some(wasm_function_id);
 
+        // Name the function in the name section after the Move function, so the tools that read
+        // the module (profilers, debuggers) can attribute its code to the source
+        let function = module.funcs.get_mut(wasm_function_id);
+        if function.name.is_none() {
+            function.name = Some(function_id.to_string());
+        }
+
         Ok(())
     }
 
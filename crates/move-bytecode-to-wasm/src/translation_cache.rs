@@ -0,0 +1,85 @@
+//! State kept between the translations of a package, so rebuilding it, as `move build --watch`
+//! does, only translates the modules that changed.
+//!
+//! The dependencies of the package don't change between rebuilds, so their module data is built
+//! once and reused. A root module is translated again only if its source, or the source of a root
+//! module it depends on, changed since it was last translated, because the functions it calls are
+//! linked into it. The cache must be cleared when the dependencies change, and only used to
+//! translate the package with the same options.
+use std::{
+    collections::{BTreeSet, HashMap},
+    hash::{DefaultHasher, Hash, Hasher},
+    path::PathBuf,
+};
+
+use move_package::compilation::compiled_package::CompiledUnitWithSource;
+
+use crate::compilation_context::{Interner, ModuleData, ModuleId};
+
+#[derive(Default)]
+pub struct TranslationCache {
+    /// Module data of the dependencies of the package
+    pub(crate) dependencies_data: HashMap<ModuleId, ModuleData>,
+    /// Names of the modules and the functions of the package, which the module data refers to
+    pub(crate) symbols: Interner,
+    /// Hash of the sources every root module was last translated from
+    pub(crate) source_hashes: HashMap<String, u64>,
+    /// Functions relooped by the last translation, each one once whatever the number of root
+    /// modules that translate it
+    pub(crate) relooped_functions: usize,
+}
+
+impl TranslationCache {
+    pub fn clear(&mut self) {
+        self.dependencies_data.clear();
+        self.symbols.clear();
+        self.source_hashes.clear();
+        self.relooped_functions = 0;
+    }
+
+    /// Number of functions whose control flow the last translation relooped.
+    pub fn relooped_functions(&self) -> usize {
+        self.relooped_functions
+    }
+
+    /// If true, the module was last translated from the same sources.
+    pub(crate) fn is_unchanged(&self, module_name: &str, source_hash: Option<u64>) -> bool {
+        source_hash.is_some() && self.source_hashes.get(module_name) == source_hash.as_ref()
+    }
+
+    pub(crate) fn record(&mut self, module_name: String, source_hash: Option<u64>) {
+        match source_hash {
+            Some(source_hash) => self.source_hashes.insert(module_name, source_hash),
+            None => self.source_hashes.remove(&module_name),
+        };
+    }
+}
+
+/// Hash of the source of the root module and of the sources of the root modules it depends on,
+/// transitively, or `None` if a source can't be read, so the module is always translated.
+pub(crate) fn source_hash(
+    root_compiled_unit: &CompiledUnitWithSource,
+    root_compiled_units: &[CompiledUnitWithSource],
+) -> Option<u64> {
+    let mut source_paths: BTreeSet<&PathBuf> = BTreeSet::new();
+    let mut pending = vec![root_compiled_unit];
+    while let Some(unit) = pending.pop() {
+        if !source_paths.insert(&unit.source_path) {
+            continue;
+        }
+
+        for dependency in unit.unit.module.immediate_dependencies() {
+            pending.extend(
+                root_compiled_units
+                    .iter()
+                    .filter(|root_unit| root_unit.unit.module.self_id() == dependency),
+            );
+        }
+    }
+
+    let mut hasher = DefaultHasher::new();
+    for source_path in source_paths {
+        std::fs::read(source_path).ok()?.hash(&mut hasher);
+    }
+    Some(hasher.finish())
+}
@@ -0,0 +1,206 @@
+//! Options that modify how a Move package is translated to WASM.
+
+use std::{fmt, str::FromStr};
+
+use crate::{output_options::OutputOptions, wasm_opt::OptimizationLevel};
+
+#[derive(Debug, Clone)]
+pub struct TranslationOptions {
+    /// If true, storage fields smaller than 32 bytes (u8, u16, u32, bool, etc) share slots,
+    /// following Solidity's storage layout. This cuts the number of SSTOREs needed to save an
+    /// object.
+    ///
+    /// If false, every field occupies its own slot. Useful to keep compatibility with storage
+    /// layouts that do not pack values.
+    pub storage_packing: bool,
+    /// If true, the entrypoint rejects every reentrant call, as if all the entry functions were
+    /// annotated with `#[ext(non_reentrant)]`.
+    pub reentrancy_guard: bool,
+    /// If true, u256 divisions and remainders are computed by Stylus' math hooks instead of the
+    /// long division implemented in WASM, spending less ink.
+    pub math_hooks: bool,
+    /// How many pages the allocator grows the memory by when it runs out of it.
+    pub memory_growth: MemoryGrowth,
+    /// Version of Stylus the package is deployed to. The translation fails if the module imports
+    /// a host function that the version does not provide.
+    pub stylus_version: StylusVersion,
+    /// If true, `debug::log` prints its messages through the console's `log_txt` hook, and so does
+    /// `abort`, with its code and the function aborting. Only nodes running in debug mode provide
+    /// it, so otherwise the calls to `debug::log` do nothing and the module does not import it.
+    pub debug: bool,
+    /// If true, the modules import the `print_*` functions used to debug the translation, as with
+    /// the `inject-host-debug-fns` feature. Only test runners provide them, so the modules can't
+    /// be deployed.
+    pub host_debug_functions: bool,
+    /// If true, the modules are emitted without the names of their functions and without the
+    /// producers section, so they are smaller. The WAT and the size reports of the modules name
+    /// the functions by their index.
+    pub strip_names: bool,
+    /// If true, the locals of the root modules' functions are named after the Move locals, the
+    /// modules keep their names even with `strip_names`, and the translation emits the source map
+    /// from the offsets of their instructions to the Move source, so the stack traces of nodes and
+    /// debuggers point to it.
+    pub debug_info: bool,
+    /// If true, the functions called by every function are translated and linked in the order of
+    /// their ids instead of the iteration order of a hash set, which changes from run to run, so
+    /// two builds of the same package are byte-identical, as `move-stylus verify` needs.
+    pub deterministic: bool,
+    /// If true, every basic block of the root module's functions reports its execution through
+    /// the `coverage::hit` hook, and the translation emits the map from the blocks to the Move
+    /// source. Only test runners provide the hook, so the module can't be deployed.
+    pub coverage: bool,
+    /// If true, the router dispatches the `#[test]` functions of the root modules, and `abort`
+    /// reports its code through the `unit_test::abort` hook, so a test runner can run the Move
+    /// unit tests in WASM. The package must be compiled in test mode. Only test runners provide
+    /// the hook, so the module can't be deployed.
+    pub unit_tests: bool,
+    /// Root modules translated to contracts. The rest are only linked into the modules that use
+    /// them.
+    pub contracts: ContractSelection,
+}
+
+/// Root modules of the package translated, with their router and artifacts.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum ContractSelection {
+    #[default]
+    All,
+    /// Every module but the libraries: the modules other modules of the package depend on that
+    /// have neither `entry` functions nor an `init` function.
+    SkipLibraries,
+    /// Only the modules named.
+    Modules(Vec<String>),
+}
+
+impl ContractSelection {
+    /// If true, the root module is translated.
+    pub fn includes(&self, module_name: &str, is_library: bool) -> bool {
+        match self {
+            ContractSelection::All => true,
+            ContractSelection::SkipLibraries => !is_library,
+            ContractSelection::Modules(module_names) => {
+                module_names.iter().any(|name| name == module_name)
+            }
+        }
+    }
+}
+
+/// Policy the allocator follows to grow the memory. Stylus charges every page the memory grows
+/// by, so growing it in bigger steps saves calls to `memory.grow` at the cost of paying for pages
+/// that may not be used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MemoryGrowth {
+    /// Grow only the pages needed by the allocation.
+    #[default]
+    Exact,
+    /// Double the memory, growing it by at most `max_pages` pages, or by the pages needed by the
+    /// allocation if there are more.
+    Exponential { max_pages: u32 },
+}
+
+/// Versions of Stylus, named after the ArbOS release that shipped them. Every version provides the
+/// host functions of the previous ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum StylusVersion {
+    /// First release of Stylus.
+    ArbOs31,
+    /// Adds the math hooks (`math_div`, `math_mod`, `math_pow`, `math_add_mod` and
+    /// `math_mul_mod`).
+    #[default]
+    ArbOs32,
+}
+
+impl fmt::Display for StylusVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StylusVersion::ArbOs31 => write!(f, "arbos31"),
+            StylusVersion::ArbOs32 => write!(f, "arbos32"),
+        }
+    }
+}
+
+impl FromStr for StylusVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "arbos31" => Ok(StylusVersion::ArbOs31),
+            "arbos32" => Ok(StylusVersion::ArbOs32),
+            _ => Err(format!(
+                "unknown Stylus version {s}, expected arbos31 or arbos32"
+            )),
+        }
+    }
+}
+
+/// Presets of the options a package is built with, as the profiles of Cargo.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Profile {
+    /// To test the package: `debug::log` and `abort` print through the console, the modules
+    /// import the host debug functions, and they keep their names, with the ones of the locals,
+    /// and are emitted with their source maps.
+    Debug,
+    /// To deploy the package: the modules print nothing, import nothing but the host functions
+    /// of Stylus, are built deterministically, and are stripped of their names and optimized with
+    /// `wasm-opt -Oz`, unless another level is set.
+    Release,
+}
+
+impl Profile {
+    /// Sets the translation options the profile decides, leaving the rest as they are.
+    pub fn apply_to_translation(self, options: &mut TranslationOptions) {
+        let debug = self == Profile::Debug;
+        options.debug = debug;
+        options.host_debug_functions = debug;
+        options.strip_names = !debug;
+        options.debug_info = debug;
+        options.deterministic = !debug;
+    }
+
+    /// Sets the output options the profile decides, leaving the rest as they are.
+    pub fn apply_to_output(self, output: &mut OutputOptions) {
+        if self == Profile::Release {
+            output.wasm_opt = output.wasm_opt.or(Some(OptimizationLevel::Oz));
+        }
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Profile::Debug => write!(f, "debug"),
+            Profile::Release => write!(f, "release"),
+        }
+    }
+}
+
+impl FromStr for Profile {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "debug" => Ok(Profile::Debug),
+            "release" => Ok(Profile::Release),
+            _ => Err(format!("unknown profile {s}, expected debug or release")),
+        }
+    }
+}
+
+impl Default for TranslationOptions {
+    fn default() -> Self {
+        Self {
+            storage_packing: true,
+            reentrancy_guard: false,
+            math_hooks: false,
+            memory_growth: MemoryGrowth::Exact,
+            stylus_version: StylusVersion::default(),
+            debug: false,
+            host_debug_functions: false,
+            strip_names: false,
+            debug_info: false,
+            deterministic: false,
+            coverage: false,
+            unit_tests: false,
+            contracts: ContractSelection::All,
+        }
+    }
+}
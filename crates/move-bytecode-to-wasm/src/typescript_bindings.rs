@@ -0,0 +1,49 @@
+//! TypeScript bindings of a module, generated from its ABI, so frontends can call the contract
+//! without writing its ABI by hand.
+//!
+//! The ABI is exported `as const`, so viem, wagmi and abitype infer the types of the arguments and
+//! return values of every function from it, and ethers takes it as it is. The bindings also export
+//! a function returning the typed viem contract deployed at an address.
+use crate::utils::snake_to_camel;
+
+/// Bindings of the module named `module_name`, from its ABI as `ContractAbi::to_json` writes it.
+pub(crate) fn typescript_bindings(module_name: &str, abi_json: &str) -> String {
+    let abi_name = format!("{}Abi", snake_to_camel(module_name));
+    let contract_type = pascal_case(module_name);
+
+    format!(
+        r#"// Generated by move-stylus from the ABI of the `{module_name}` module, do not edit.
+import {{ type Address, type Client, getContract }} from "viem";
+
+export const {abi_name} = {abi_json} as const;
+
+/** The `{module_name}` contract deployed at `address`, with typed reads and writes. */
+export function get{contract_type}Contract<TClient extends Client>(address: Address, client: TClient) {{
+  return getContract({{ address, abi: {abi_name}, client }});
+}}
+"#
+    )
+}
+
+fn pascal_case(module_name: &str) -> String {
+    let mut camel_case = snake_to_camel(module_name);
+    if let Some(first) = camel_case.get_mut(..1) {
+        first.make_ascii_uppercase();
+    }
+    camel_case
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_typescript_bindings() {
+        let bindings = typescript_bindings("my_token", "[]");
+        assert!(bindings.contains("export const myTokenAbi = [] as const;"));
+        assert!(bindings.contains(
+            "export function getMyTokenContract<TClient extends Client>(address: Address, client: TClient) {"
+        ));
+        assert!(bindings.contains("getContract({ address, abi: myTokenAbi, client })"));
+    }
+}
@@ -0,0 +1,217 @@
+//! Move unit tests run in WASM, for the packages translated with the `unit_tests` option.
+//!
+//! The router dispatches the `#[test]` functions of the root modules as it does the entry
+//! functions, so a test runner can call every test in the sandbox and check its outcome against
+//! its `#[expected_failure]` attribute:
+//!
+//! ```move
+//! #[test]
+//! #[expected_failure(abort_code = EInsufficientBalance)]
+//! fun test_withdraw_too_much() { ... }
+//! ```
+//!
+//! `abort` reports its code through the `unit_test::abort` hook, which traps. Tests that take
+//! arguments, declared with `#[test(<argument> = <value>)]`, are not supported.
+//!
+//! Attributes are not part of the compiled bytecode, so they are read from the module's source.
+use std::collections::HashMap;
+
+const TEST_ATTRIBUTE: &str = "test";
+const EXPECTED_FAILURE_ATTRIBUTE: &str = "expected_failure";
+const ABORT_CODE_ARGUMENT: &str = "abort_code";
+
+/// A `#[test]` function of a root module.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnitTest {
+    pub name: String,
+    /// Selector the router dispatches the test with
+    pub selector: [u8; 4],
+    /// How the test is expected to fail, or `None` if it is expected to return
+    pub expected_failure: Option<ExpectedFailure>,
+}
+
+/// Failure a test is expected to end with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpectedFailure {
+    /// Any abort or trap, `#[expected_failure]`. The failures the attribute names other than by
+    /// their abort code, such as `arithmetic_error`, are accepted as any failure.
+    Any,
+    /// An abort with the code, `#[expected_failure(abort_code = <code>)]`
+    AbortCode(u64),
+}
+
+/// Searches the Move source for `test` attributes and returns, for every annotated function, how
+/// it is expected to fail, if it is.
+///
+/// # Panics
+/// If an abort code is neither a u64 literal nor a constant of the module.
+pub fn parse_unit_tests(source: &str) -> HashMap<String, Option<ExpectedFailure>> {
+    let mut tests = HashMap::new();
+
+    for (position, _) in source.match_indices(TEST_ATTRIBUTE) {
+        // Only consider the name when it is an element of an attribute, so we skip comments,
+        // identifiers containing it and the `test_only` attributes
+        let before = source[..position].trim_end();
+        if !before.ends_with("#[") && !before.ends_with(',') {
+            continue;
+        }
+        let line_start = before.rfind('\n').map_or(0, |end| end + 1);
+        if before[line_start..].contains("//") {
+            continue;
+        }
+
+        let rest = source[position + TEST_ATTRIBUTE.len()..].trim_start();
+        if !rest.starts_with(']') && !rest.starts_with(',') {
+            continue;
+        }
+
+        // The attribute applies to the function declared right after it, and the other attributes
+        // of the function are declared between the previous item and the function
+        let Some(function) = find_keyword(rest, "fun") else {
+            continue;
+        };
+        let Some(identifier) = rest[function + "fun".len()..]
+            .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .find(|t| !t.is_empty())
+        else {
+            continue;
+        };
+
+        let attributes_start = before.rfind(['}', ';']).map_or(0, |end| end + 1);
+        let attributes = &source[attributes_start..source.len() - rest.len() + function];
+
+        tests.insert(
+            identifier.to_owned(),
+            parse_expected_failure(attributes, source),
+        );
+    }
+
+    tests
+}
+
+fn parse_expected_failure(attributes: &str, source: &str) -> Option<ExpectedFailure> {
+    let position = attributes.find(EXPECTED_FAILURE_ATTRIBUTE)?;
+    let rest = attributes[position + EXPECTED_FAILURE_ATTRIBUTE.len()..].trim_start();
+
+    let Some(arguments) = rest.strip_prefix('(') else {
+        return Some(ExpectedFailure::Any);
+    };
+    let arguments = &arguments[..arguments.find(')').unwrap_or(arguments.len())];
+
+    let abort_code = arguments.split(',').find_map(|argument| {
+        let (name, value) = argument.split_once('=')?;
+        (name.trim() == ABORT_CODE_ARGUMENT).then_some(value.trim())
+    });
+
+    match abort_code {
+        Some(abort_code) => Some(ExpectedFailure::AbortCode(resolve_abort_code(
+            abort_code, source,
+        ))),
+        None => Some(ExpectedFailure::Any),
+    }
+}
+
+/// Resolves an abort code, a u64 literal or a constant, qualified with its module or not, of the
+/// module.
+fn resolve_abort_code(abort_code: &str, source: &str) -> u64 {
+    if let Some(code) = parse_u64_literal(abort_code) {
+        return code;
+    }
+
+    let constant = abort_code.rsplit("::").next().unwrap_or(abort_code);
+    for (position, _) in source.match_indices("const") {
+        let declaration = source[position + "const".len()..].trim_start();
+        let Some(declaration) = declaration.strip_prefix(constant) else {
+            continue;
+        };
+        // Skip the constants whose name starts with the one we look for
+        if !declaration.trim_start().starts_with(':') {
+            continue;
+        }
+        let Some((_, value)) = declaration.split_once('=') else {
+            continue;
+        };
+        let value = value.split(';').next().unwrap_or(value).trim();
+
+        if let Some(code) = parse_u64_literal(value) {
+            return code;
+        }
+    }
+
+    panic!("invalid abort code: {abort_code}, expected a u64 literal or a constant of the module")
+}
+
+fn parse_u64_literal(literal: &str) -> Option<u64> {
+    let literal = literal.replace('_', "");
+    let literal = literal.strip_suffix("u64").unwrap_or(&literal);
+
+    match literal.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => literal.parse().ok(),
+    }
+}
+
+/// Returns the position of the first occurrence of the keyword as a whole word.
+fn find_keyword(source: &str, keyword: &str) -> Option<usize> {
+    let is_identifier = |c: char| c.is_alphanumeric() || c == '_';
+
+    source.match_indices(keyword).map(|(i, _)| i).find(|i| {
+        !source[..*i].ends_with(is_identifier)
+            && !source[i + keyword.len()..].starts_with(is_identifier)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_unit_tests() {
+        let source = r#"
+module test::vault;
+
+const EInsufficientBalance: u64 = 2;
+
+#[test_only]
+fun setup() {}
+
+#[test]
+fun test_deposit() {}
+
+#[test, expected_failure]
+fun test_overflow() {}
+
+#[test]
+#[expected_failure(abort_code = EInsufficientBalance)]
+fun test_withdraw_too_much() {}
+
+#[expected_failure(abort_code = 0x10, location = Self)]
+#[test]
+public fun test_transfer_to_self() {}
+
+#[test(admin = @0x1)]
+fun test_admin(admin: address) {}
+
+// #[test]
+fun contest() {}
+"#;
+
+        let tests = parse_unit_tests(source);
+
+        assert_eq!(
+            HashMap::from([
+                ("test_deposit".to_owned(), None),
+                ("test_overflow".to_owned(), Some(ExpectedFailure::Any)),
+                (
+                    "test_withdraw_too_much".to_owned(),
+                    Some(ExpectedFailure::AbortCode(2))
+                ),
+                (
+                    "test_transfer_to_self".to_owned(),
+                    Some(ExpectedFailure::AbortCode(16))
+                ),
+            ]),
+            tests
+        );
+    }
+}
@@ -1,3 +1,5 @@
+use std::collections::HashSet;
+
 use crate::CompilationContext;
 use alloy_primitives::keccak256;
 use walrus::{
@@ -15,6 +17,41 @@ pub fn display_module(module: &mut Module) {
     println!("{}", wat.replace("\\n", "\n"));
 }
 
+/// Searches the Move source for an attribute without a value, such as `view` in `#[ext(view)]`,
+/// and returns the names of the annotated functions. The attribute can be on its own or among
+/// others, as in `#[ext(non_reentrant, view)]`, and only counts as an element of an attribute, so
+/// comments and identifiers containing it are skipped.
+///
+/// Attributes are not part of the compiled bytecode, so they are read from the module's source.
+pub fn parse_ext_flag(source: &str, attribute: &str) -> HashSet<String> {
+    let mut functions = HashSet::new();
+
+    for (position, _) in source.match_indices(attribute) {
+        let before = source[..position].trim_end();
+        if !before.ends_with("#[") && !before.ends_with("ext(") && !before.ends_with(',') {
+            continue;
+        }
+
+        let rest = source[position + attribute.len()..].trim_start();
+        if !rest.starts_with(')') && !rest.starts_with(',') && !rest.starts_with(']') {
+            continue;
+        }
+
+        // The attribute applies to the function declared right after it
+        let identifier = rest
+            .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .filter(|t| !t.is_empty())
+            .skip_while(|t| *t != "fun")
+            .nth(1);
+
+        if let Some(identifier) = identifier {
+            functions.insert(identifier.to_owned());
+        }
+    }
+
+    functions
+}
+
 /// Converts the input string to camel case.
 pub fn snake_to_camel(input: &str) -> String {
     let mut result = String::with_capacity(input.len());
@@ -2,7 +2,9 @@
 //! automatically injected by the VM, such as the primitive type Signer or the TxContext struct
 //! from the stylus framework.
 
+pub mod mapping;
 pub mod signer;
+pub mod storage_vec;
 pub mod tx_context;
 pub mod uid;
 
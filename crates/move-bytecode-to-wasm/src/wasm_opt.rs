@@ -0,0 +1,219 @@
+//! Optimization of the emitted modules with binaryen's `wasm-opt`, which usually cuts their size
+//! and the ink they spend. It runs as a subprocess, so binaryen must be installed, with
+//! `make install-wasm-opt` or the package manager.
+//!
+//! `wasm-opt` only uses the WASM features Stylus supports, and the optimized module is validated
+//! again, so a broken optimization fails the build instead of the deployment.
+use std::{fmt, path::Path, process::Command, str::FromStr};
+
+use anyhow::{Context, bail};
+
+/// Features of the modules, the only ones `wasm-opt` may use
+const FEATURES: [&str; 4] = [
+    "--enable-bulk-memory",
+    "--enable-multivalue",
+    "--enable-mutable-globals",
+    "--enable-sign-ext",
+];
+
+/// Optimization level `wasm-opt` runs, as its `-O` flags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptimizationLevel {
+    /// No optimization, `-O0`
+    O0,
+    O1,
+    O2,
+    O3,
+    O4,
+    /// Optimize for size, `-Os`
+    Os,
+    /// Optimize aggressively for size, `-Oz`
+    Oz,
+}
+
+impl fmt::Display for OptimizationLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OptimizationLevel::O0 => write!(f, "0"),
+            OptimizationLevel::O1 => write!(f, "1"),
+            OptimizationLevel::O2 => write!(f, "2"),
+            OptimizationLevel::O3 => write!(f, "3"),
+            OptimizationLevel::O4 => write!(f, "4"),
+            OptimizationLevel::Os => write!(f, "s"),
+            OptimizationLevel::Oz => write!(f, "z"),
+        }
+    }
+}
+
+impl FromStr for OptimizationLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "0" => Ok(OptimizationLevel::O0),
+            "1" => Ok(OptimizationLevel::O1),
+            "2" => Ok(OptimizationLevel::O2),
+            "3" => Ok(OptimizationLevel::O3),
+            "4" => Ok(OptimizationLevel::O4),
+            "s" => Ok(OptimizationLevel::Os),
+            "z" => Ok(OptimizationLevel::Oz),
+            _ => Err(format!(
+                "unknown optimization level {s}, expected 0, 1, 2, 3, 4, s or z"
+            )),
+        }
+    }
+}
+
+/// Optimizes the module in the file, replacing it, and returns the optimized module. The names of
+/// the functions are kept if `keep_names` is true, and stripped otherwise. The source map of the
+/// module, if any, is replaced by the one of the optimized module.
+pub(crate) fn optimize(
+    path: &Path,
+    level: OptimizationLevel,
+    keep_names: bool,
+    source_map: Option<&Path>,
+) -> anyhow::Result<Vec<u8>> {
+    let output = wasm_opt_command(path, level, keep_names, source_map)
+        .output()
+        .context("Failed to run wasm-opt, install binaryen with `make install-wasm-opt`")?;
+
+    if !output.status.success() {
+        bail!(
+            "wasm-opt failed to optimize {}: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    let wasm = std::fs::read(path)
+        .with_context(|| format!("Failed to read the optimized {}", path.display()))?;
+    wasmparser::Validator::new()
+        .validate_all(&wasm)
+        .with_context(|| format!("wasm-opt emitted an invalid module for {}", path.display()))?;
+
+    Ok(wasm)
+}
+
+/// Optimizes a module that is not written anywhere, through a temporary file, and returns the
+/// optimized module. The names of the functions are kept as in `optimize`.
+pub fn optimize_module(
+    wasm: &[u8],
+    level: OptimizationLevel,
+    keep_names: bool,
+) -> anyhow::Result<Vec<u8>> {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_nanos());
+    let path = std::env::temp_dir().join(format!(
+        "move-stylus-wasm-opt-{}-{nanos}.wasm",
+        std::process::id()
+    ));
+    std::fs::write(&path, wasm).with_context(|| format!("Failed to write {}", path.display()))?;
+
+    let optimized = optimize(&path, level, keep_names, None);
+    // The module is returned, the file is not needed anymore
+    let _ = std::fs::remove_file(&path);
+    optimized
+}
+
+fn wasm_opt_command(
+    path: &Path,
+    level: OptimizationLevel,
+    keep_names: bool,
+    source_map: Option<&Path>,
+) -> Command {
+    let mut command = Command::new("wasm-opt");
+    command
+        .arg(format!("-O{level}"))
+        .args(FEATURES)
+        .arg(path)
+        .arg("-o")
+        .arg(path);
+    if keep_names {
+        command.arg("--debuginfo");
+    }
+    if let Some(source_map) = source_map {
+        // The module keeps pointing to the map by its name, next to it
+        command
+            .arg("--input-source-map")
+            .arg(source_map)
+            .arg("--output-source-map")
+            .arg(source_map);
+        if let Some(url) = source_map.file_name() {
+            command.arg("--output-source-map-url").arg(url);
+        }
+    }
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_optimization_level() {
+        for level in [
+            OptimizationLevel::O0,
+            OptimizationLevel::O1,
+            OptimizationLevel::O2,
+            OptimizationLevel::O3,
+            OptimizationLevel::O4,
+            OptimizationLevel::Os,
+            OptimizationLevel::Oz,
+        ] {
+            assert_eq!(Ok(level), level.to_string().parse());
+        }
+        assert!("fast".parse::<OptimizationLevel>().is_err());
+    }
+
+    #[test]
+    fn test_wasm_opt_command() {
+        let command =
+            wasm_opt_command(Path::new("counter.wasm"), OptimizationLevel::Oz, true, None);
+
+        let args: Vec<&str> = command
+            .get_args()
+            .map(|arg| arg.to_str().unwrap())
+            .collect();
+        assert_eq!(
+            vec![
+                "-Oz",
+                "--enable-bulk-memory",
+                "--enable-multivalue",
+                "--enable-mutable-globals",
+                "--enable-sign-ext",
+                "counter.wasm",
+                "-o",
+                "counter.wasm",
+                "--debuginfo",
+            ],
+            args
+        );
+    }
+
+    #[test]
+    fn test_wasm_opt_command_with_source_map() {
+        let command = wasm_opt_command(
+            Path::new("build/counter.wasm"),
+            OptimizationLevel::O2,
+            true,
+            Some(Path::new("build/counter.wasm.map")),
+        );
+
+        let args: Vec<&str> = command
+            .get_args()
+            .map(|arg| arg.to_str().unwrap())
+            .collect();
+        assert_eq!(
+            [
+                "--input-source-map",
+                "build/counter.wasm.map",
+                "--output-source-map",
+                "build/counter.wasm.map",
+                "--output-source-map-url",
+                "counter.wasm.map",
+            ],
+            args[args.len() - 6..]
+        );
+    }
+}
@@ -1,5 +1,7 @@
 use walrus::{ExportItem, ImportKind, Module, ValType};
 
+use crate::{hostio::host_functions::host_function_version, translation_options::StylusVersion};
+
 pub enum WasmValidationError {
     InvalidWasm(String),
     InvalidStylusInterface(String),
@@ -19,8 +21,12 @@ impl std::fmt::Debug for WasmValidationError {
 /// Validate the Wasm module
 ///
 /// This function validates the Wasm module consistency using the wasmparser crate.
-/// It also validates the stylus interface requirements.
-pub fn validate_stylus_wasm(module: &mut Module) -> Result<(), WasmValidationError> {
+/// It also validates the stylus interface requirements, and that the host functions imported are
+/// provided by the Stylus version targeted.
+pub fn validate_stylus_wasm(
+    module: &mut Module,
+    stylus_version: StylusVersion,
+) -> Result<(), WasmValidationError> {
     let mut validator = wasmparser::Validator::new();
 
     validator
@@ -30,6 +36,7 @@ pub fn validate_stylus_wasm(module: &mut Module) -> Result<(), WasmValidationErr
     validate_entrypoint_function(module)?;
     validate_memory_export(module)?;
     validate_pay_for_memory_grow_import(module)?;
+    validate_host_function_imports(module, stylus_version)?;
 
     Ok(())
 }
@@ -178,11 +185,45 @@ fn validate_pay_for_memory_grow_import(module: &mut Module) -> Result<(), WasmVa
     Ok(())
 }
 
+/// Every function imported from `vm_hooks` should be a host function provided by the Stylus
+/// version targeted
+fn validate_host_function_imports(
+    module: &Module,
+    stylus_version: StylusVersion,
+) -> Result<(), WasmValidationError> {
+    for import in module
+        .imports
+        .iter()
+        .filter(|import| import.module == "vm_hooks")
+    {
+        match host_function_version(&import.name) {
+            Some(version) if version <= stylus_version => {}
+            Some(version) => {
+                return Err(WasmValidationError::InvalidStylusInterface(format!(
+                    "host function {} is not available in {stylus_version}, it requires {version} or later",
+                    import.name
+                )));
+            }
+            None => {
+                return Err(WasmValidationError::InvalidStylusInterface(format!(
+                    "unknown host function {}",
+                    import.name
+                )));
+            }
+        }
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use walrus::{FunctionBuilder, FunctionId, Module, ModuleConfig, ValType};
 
-    use crate::hostio;
+    use crate::{
+        hostio::{self, host_functions::math_div},
+        translation_options::MemoryGrowth,
+    };
 
     use super::*;
 
@@ -220,20 +261,22 @@ mod tests {
 
     #[test]
     fn test_validate_stylus_wasm() {
-        let (mut module, _, _) = hostio::new_module_with_host();
+        let (mut module, _, _) =
+            hostio::new_module_with_host(ModuleConfig::new(), MemoryGrowth::default());
         let factorial = add_valid_wasm_function(&mut module);
         hostio::add_entrypoint(&mut module, factorial);
 
-        validate_stylus_wasm(&mut module).unwrap();
+        validate_stylus_wasm(&mut module, StylusVersion::default()).unwrap();
     }
 
     #[test]
     fn test_validate_invalid_wasm() {
-        let (mut module, _, _) = hostio::new_module_with_host();
+        let (mut module, _, _) =
+            hostio::new_module_with_host(ModuleConfig::new(), MemoryGrowth::default());
         let factorial = add_invalid_wasm_function(&mut module);
         hostio::add_entrypoint(&mut module, factorial);
 
-        let result = validate_stylus_wasm(&mut module);
+        let result = validate_stylus_wasm(&mut module, StylusVersion::default());
         assert!(result.is_err());
 
         let WasmValidationError::InvalidWasm(s) = result.err().unwrap() else {
@@ -255,7 +298,7 @@ mod tests {
         let factorial = add_valid_wasm_function(&mut module);
         hostio::add_entrypoint(&mut module, factorial);
 
-        let result = validate_stylus_wasm(&mut module);
+        let result = validate_stylus_wasm(&mut module, StylusVersion::default());
         assert!(result.is_err());
 
         let WasmValidationError::InvalidStylusInterface(s) = result.err().unwrap() else {
@@ -278,7 +321,7 @@ mod tests {
         let factorial = add_valid_wasm_function(&mut module);
         hostio::add_entrypoint(&mut module, factorial);
 
-        let result = validate_stylus_wasm(&mut module);
+        let result = validate_stylus_wasm(&mut module, StylusVersion::default());
         assert!(result.is_err());
 
         let WasmValidationError::InvalidStylusInterface(s) = result.err().unwrap() else {
@@ -300,7 +343,7 @@ mod tests {
         let factorial = add_valid_wasm_function(&mut module);
         hostio::add_entrypoint(&mut module, factorial);
 
-        let result = validate_stylus_wasm(&mut module);
+        let result = validate_stylus_wasm(&mut module, StylusVersion::default());
         assert!(result.is_err());
 
         let WasmValidationError::InvalidStylusInterface(s) = result.err().unwrap() else {
@@ -311,11 +354,12 @@ mod tests {
 
     #[test]
     fn test_validate_invalid_user_entrypoint_export() {
-        let (mut module, _, _) = hostio::new_module_with_host();
+        let (mut module, _, _) =
+            hostio::new_module_with_host(ModuleConfig::new(), MemoryGrowth::default());
         add_valid_wasm_function(&mut module);
         // We are not adding the user_entrypoint export
 
-        let result = validate_stylus_wasm(&mut module);
+        let result = validate_stylus_wasm(&mut module, StylusVersion::default());
         assert!(result.is_err());
 
         let WasmValidationError::InvalidStylusInterface(s) = result.err().unwrap() else {
@@ -323,4 +367,43 @@ mod tests {
         };
         assert!(s.contains("user_entrypoint export not found"));
     }
+
+    #[test]
+    fn test_validate_host_function_not_available() {
+        let (mut module, _, _) =
+            hostio::new_module_with_host(ModuleConfig::new(), MemoryGrowth::default());
+        let factorial = add_valid_wasm_function(&mut module);
+        hostio::add_entrypoint(&mut module, factorial);
+
+        math_div(&mut module);
+
+        validate_stylus_wasm(&mut module, StylusVersion::ArbOs32).unwrap();
+
+        let result = validate_stylus_wasm(&mut module, StylusVersion::ArbOs31);
+        assert!(result.is_err());
+
+        let WasmValidationError::InvalidStylusInterface(s) = result.err().unwrap() else {
+            panic!("Expected InvalidStylusInterface error");
+        };
+        assert!(s.contains("host function math_div is not available in arbos31"));
+    }
+
+    #[test]
+    fn test_validate_unknown_host_function() {
+        let (mut module, _, _) =
+            hostio::new_module_with_host(ModuleConfig::new(), MemoryGrowth::default());
+        let factorial = add_valid_wasm_function(&mut module);
+        hostio::add_entrypoint(&mut module, factorial);
+
+        let unknown_type = module.types.add(&[], &[]);
+        module.add_import_func("vm_hooks", "unknown_hook", unknown_type);
+
+        let result = validate_stylus_wasm(&mut module, StylusVersion::default());
+        assert!(result.is_err());
+
+        let WasmValidationError::InvalidStylusInterface(s) = result.err().unwrap() else {
+            panic!("Expected InvalidStylusInterface error");
+        };
+        assert!(s.contains("unknown host function unknown_hook"));
+    }
 }
@@ -0,0 +1,363 @@
+//! Property-based ABI round-trip tests: random values of the types of an echo function's
+//! parameters, generated from its Move signature, are ABI-encoded with alloy and sent to the
+//! function in the sandbox, which must return them encoded byte for byte the same, so any
+//! asymmetry between the unpacking of the arguments and the packing of the return values fails.
+use alloy_dyn_abi::{DynSolType, DynSolValue};
+use alloy_primitives::{Address, U256, keccak256};
+use common::{compile_test_package, snake_to_camel, translate_test_package_abi};
+use move_binary_format::{
+    CompiledModule,
+    file_format::{DatatypeHandleIndex, SignatureToken, Visibility},
+};
+use move_bytecode_to_wasm::translate_single_module;
+use move_stylus_testing::{RuntimeSandbox, call_result::CallResult};
+use proptest::{
+    collection::vec,
+    prelude::*,
+    test_runner::{Config, TestRunner},
+};
+
+mod common;
+
+/// Random values every function is called with
+const CASES: u32 = 64;
+
+/// Longest vector generated
+const MAX_VECTOR_LENGTH: usize = 4;
+
+/// Generator of the values of a parameter, and the Solidity type they are encoded as.
+struct ParameterStrategy {
+    sol_type: DynSolType,
+    values: BoxedStrategy<DynSolValue>,
+}
+
+/// Runs the echo functions of a module, translated, in the sandbox.
+struct AbiRoundTripHarness {
+    module: CompiledModule,
+    sandbox: RuntimeSandbox,
+}
+
+impl AbiRoundTripHarness {
+    fn new(source_path: &str, module_name: &str) -> Self {
+        let package = compile_test_package(source_path);
+        let module = package
+            .root_compiled_units
+            .iter()
+            .find(|unit| unit.unit.name.to_string() == module_name)
+            .expect("Module not compiled")
+            .unit
+            .module
+            .clone();
+
+        let mut translated_package = translate_single_module(package, module_name);
+
+        Self {
+            module,
+            sandbox: RuntimeSandbox::new(&mut translated_package),
+        }
+    }
+
+    /// Names of the module's public functions whose name starts with `echo`.
+    fn echo_functions(&self) -> Vec<String> {
+        self.module
+            .function_defs()
+            .iter()
+            .filter(|definition| definition.visibility == Visibility::Public)
+            .map(|definition| {
+                let handle = self.module.function_handle_at(definition.function);
+                self.module.identifier_at(handle.name).to_string()
+            })
+            .filter(|name| name.starts_with("echo"))
+            .collect()
+    }
+
+    /// Calls the function with random arguments, panicking if it does not return them encoded the
+    /// same as they were sent.
+    fn assert_round_trip(&self, function: &str) {
+        let handle = self
+            .module
+            .function_defs()
+            .iter()
+            .map(|definition| self.module.function_handle_at(definition.function))
+            .find(|handle| self.module.identifier_at(handle.name).as_str() == function)
+            .unwrap_or_else(|| panic!("function {function} not found"));
+
+        let parameters: Vec<ParameterStrategy> = self
+            .module
+            .signature_at(handle.parameters)
+            .0
+            .iter()
+            .map(|token| self.parameter_strategy(token, &[]))
+            .collect();
+
+        let signature = self.signature(function);
+        let selector = &keccak256(signature)[..4];
+
+        let arguments: Vec<BoxedStrategy<DynSolValue>> = parameters
+            .into_iter()
+            .map(|parameter| parameter.values)
+            .collect();
+
+        let mut runner = TestRunner::new(Config {
+            cases: CASES,
+            ..Config::default()
+        });
+        runner
+            .run(&arguments, |arguments| {
+                let encoded_arguments = DynSolValue::Tuple(arguments).abi_encode_params();
+                let calldata = [selector, &encoded_arguments[..]].concat();
+
+                match self.sandbox.call(calldata) {
+                    CallResult::Success(return_data) => {
+                        prop_assert_eq!(encoded_arguments, return_data);
+                    }
+                    result => {
+                        prop_assert!(false, "the call failed: {}", result);
+                    }
+                }
+                Ok(())
+            })
+            .unwrap_or_else(|e| panic!("{function} does not round-trip its arguments: {e}"));
+    }
+
+    /// Solidity signature of the function, from the types of its parameters.
+    fn signature(&self, function: &str) -> String {
+        let handle = self
+            .module
+            .function_defs()
+            .iter()
+            .map(|definition| self.module.function_handle_at(definition.function))
+            .find(|handle| self.module.identifier_at(handle.name).as_str() == function)
+            .unwrap_or_else(|| panic!("function {function} not found"));
+
+        format!(
+            "{}({})",
+            snake_to_camel(function),
+            self.module
+                .signature_at(handle.parameters)
+                .0
+                .iter()
+                .map(|token| self
+                    .parameter_strategy(token, &[])
+                    .sol_type
+                    .sol_type_name()
+                    .into_owned())
+                .collect::<Vec<_>>()
+                .join(",")
+        )
+    }
+
+    /// Builds the generator of the values of a type, given the types its type parameters are
+    /// instantiated with.
+    fn parameter_strategy(
+        &self,
+        token: &SignatureToken,
+        type_arguments: &[SignatureToken],
+    ) -> ParameterStrategy {
+        let uint = |bits: usize, values: BoxedStrategy<U256>| ParameterStrategy {
+            sol_type: DynSolType::Uint(bits),
+            values: values
+                .prop_map(move |value| DynSolValue::Uint(value, bits))
+                .boxed(),
+        };
+
+        match token {
+            SignatureToken::Bool => ParameterStrategy {
+                sol_type: DynSolType::Bool,
+                values: any::<bool>().prop_map(DynSolValue::Bool).boxed(),
+            },
+            SignatureToken::U8 => uint(8, any::<u8>().prop_map(U256::from).boxed()),
+            SignatureToken::U16 => uint(16, any::<u16>().prop_map(U256::from).boxed()),
+            SignatureToken::U32 => uint(32, any::<u32>().prop_map(U256::from).boxed()),
+            SignatureToken::U64 => uint(64, any::<u64>().prop_map(U256::from).boxed()),
+            SignatureToken::U128 => uint(128, any::<u128>().prop_map(U256::from).boxed()),
+            SignatureToken::U256 => {
+                uint(256, any::<[u8; 32]>().prop_map(U256::from_be_bytes).boxed())
+            }
+            SignatureToken::Address => ParameterStrategy {
+                sol_type: DynSolType::Address,
+                values: any::<[u8; 20]>()
+                    .prop_map(|address| DynSolValue::Address(Address::new(address)))
+                    .boxed(),
+            },
+            SignatureToken::Vector(inner) => {
+                let inner = self.parameter_strategy(inner, type_arguments);
+                ParameterStrategy {
+                    sol_type: DynSolType::Array(Box::new(inner.sol_type)),
+                    values: vec(inner.values, 0..=MAX_VECTOR_LENGTH)
+                        .prop_map(DynSolValue::Array)
+                        .boxed(),
+                }
+            }
+            // References are sent as the values they reference
+            SignatureToken::Reference(inner) | SignatureToken::MutableReference(inner) => {
+                self.parameter_strategy(inner, type_arguments)
+            }
+            SignatureToken::TypeParameter(index) => {
+                self.parameter_strategy(&type_arguments[*index as usize], &[])
+            }
+            SignatureToken::Datatype(handle) => self.datatype_strategy(*handle, &[]),
+            SignatureToken::DatatypeInstantiation(instantiation) => {
+                let (handle, datatype_arguments) = instantiation.as_ref();
+                let datatype_arguments: Vec<SignatureToken> = datatype_arguments
+                    .iter()
+                    .map(|argument| instantiate(argument, type_arguments))
+                    .collect();
+                self.datatype_strategy(*handle, &datatype_arguments)
+            }
+            token => panic!("ABI round-trip tests do not support parameters of type {token:?}"),
+        }
+    }
+
+    /// Structs are encoded as the tuple of their fields, and enums as their variant's index.
+    fn datatype_strategy(
+        &self,
+        handle: DatatypeHandleIndex,
+        type_arguments: &[SignatureToken],
+    ) -> ParameterStrategy {
+        if let Some(struct_definition) = self
+            .module
+            .struct_defs()
+            .iter()
+            .find(|definition| definition.struct_handle == handle)
+        {
+            let fields: Vec<ParameterStrategy> = struct_definition
+                .fields()
+                .expect("native structs can't be sent to entry functions")
+                .iter()
+                .map(|field| self.parameter_strategy(&field.signature.0, type_arguments))
+                .collect();
+
+            let sol_types = fields.iter().map(|field| field.sol_type.clone()).collect();
+            let values: Vec<BoxedStrategy<DynSolValue>> =
+                fields.into_iter().map(|field| field.values).collect();
+
+            return ParameterStrategy {
+                sol_type: DynSolType::Tuple(sol_types),
+                values: values.prop_map(DynSolValue::Tuple).boxed(),
+            };
+        }
+
+        let enum_definition = self
+            .module
+            .enum_defs()
+            .iter()
+            .find(|definition| definition.enum_handle == handle)
+            .expect("the datatype is not defined in the module");
+        let variants = enum_definition.variants.len() as u8;
+
+        ParameterStrategy {
+            sol_type: DynSolType::Uint(8),
+            values: (0..variants)
+                .prop_map(|variant| DynSolValue::Uint(U256::from(variant), 8))
+                .boxed(),
+        }
+    }
+}
+
+/// Replaces the type parameters of a type with the types they are instantiated with.
+fn instantiate(token: &SignatureToken, type_arguments: &[SignatureToken]) -> SignatureToken {
+    match token {
+        SignatureToken::TypeParameter(index) => type_arguments[*index as usize].clone(),
+        SignatureToken::Vector(inner) => {
+            SignatureToken::Vector(Box::new(instantiate(inner, type_arguments)))
+        }
+        SignatureToken::DatatypeInstantiation(instantiation) => {
+            let (handle, arguments) = instantiation.as_ref();
+            SignatureToken::DatatypeInstantiation(Box::new((
+                *handle,
+                arguments
+                    .iter()
+                    .map(|argument| instantiate(argument, type_arguments))
+                    .collect(),
+            )))
+        }
+        token => token.clone(),
+    }
+}
+
+/// Canonical type of a parameter of the JSON ABI, with its tuples expanded from their components.
+fn canonical_type(parameter: &serde_json::Value) -> String {
+    let type_name = parameter["type"].as_str().unwrap();
+    match type_name.strip_prefix("tuple") {
+        Some(dimensions) => {
+            let components: Vec<String> = parameter["components"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(canonical_type)
+                .collect();
+            format!("({}){dimensions}", components.join(","))
+        }
+        None => type_name.to_owned(),
+    }
+}
+
+#[test]
+fn test_contract_abi() {
+    let harness = AbiRoundTripHarness::new("tests/abi-round-trip/echo.move", "echo");
+    let abi = translate_test_package_abi("tests/abi-round-trip/echo.move", "echo");
+    let abi: serde_json::Value = serde_json::from_str(&abi.to_json()).unwrap();
+    let functions = abi.as_array().unwrap();
+
+    // Every function of the ABI is called with the selector the router dispatches
+    assert_eq!(harness.echo_functions().len(), functions.len());
+    for function in harness.echo_functions() {
+        let name = snake_to_camel(&function);
+        let entry = functions
+            .iter()
+            .find(|entry| entry["name"] == name.as_str())
+            .unwrap_or_else(|| panic!("{name} not in the ABI"));
+        let inputs: Vec<String> = entry["inputs"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(canonical_type)
+            .collect();
+
+        assert_eq!("function", entry["type"]);
+        assert_eq!("nonpayable", entry["stateMutability"]);
+        assert_eq!(
+            harness.signature(&function),
+            format!("{name}({})", inputs.join(","))
+        );
+        // The functions echo their arguments
+        assert_eq!(entry["inputs"], entry["outputs"]);
+    }
+
+    let echo_dynamic_struct = functions
+        .iter()
+        .find(|entry| entry["name"] == "echoDynamicStruct")
+        .unwrap();
+    assert_eq!(
+        serde_json::json!([{
+            "name": "",
+            "type": "tuple",
+            "components": [
+                { "name": "a", "type": "uint32[]" },
+                { "name": "b", "type": "bool" },
+                {
+                    "name": "c",
+                    "type": "tuple",
+                    "components": [
+                        { "name": "a", "type": "uint16" },
+                        { "name": "b", "type": "address" },
+                        { "name": "c", "type": "uint256" },
+                    ],
+                },
+            ],
+        }]),
+        echo_dynamic_struct["inputs"]
+    );
+}
+
+#[test]
+fn test_abi_round_trip() {
+    let harness = AbiRoundTripHarness::new("tests/abi-round-trip/echo.move", "echo");
+
+    let functions = harness.echo_functions();
+    assert!(!functions.is_empty());
+    for function in functions {
+        harness.assert_round_trip(&function);
+    }
+}
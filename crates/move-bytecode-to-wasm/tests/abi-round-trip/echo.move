@@ -0,0 +1,100 @@
+module 0x00::echo;
+
+public struct Static has drop {
+    a: u16,
+    b: address,
+    c: u256,
+}
+
+public struct Dynamic has drop {
+    a: vector<u32>,
+    b: bool,
+    c: Static,
+}
+
+public struct Pair<T> has drop {
+    first: T,
+    second: T,
+}
+
+public enum Color has drop {
+    Red,
+    Green,
+    Blue,
+}
+
+public fun echo_bool(x: bool): bool {
+    x
+}
+
+public fun echo_u8(x: u8): u8 {
+    x
+}
+
+public fun echo_u16(x: u16): u16 {
+    x
+}
+
+public fun echo_u32(x: u32): u32 {
+    x
+}
+
+public fun echo_u64(x: u64): u64 {
+    x
+}
+
+public fun echo_u128(x: u128): u128 {
+    x
+}
+
+public fun echo_u256(x: u256): u256 {
+    x
+}
+
+public fun echo_address(x: address): address {
+    x
+}
+
+public fun echo_vec_u8(x: vector<u8>): vector<u8> {
+    x
+}
+
+public fun echo_vec_u128(x: vector<u128>): vector<u128> {
+    x
+}
+
+public fun echo_vec_address(x: vector<address>): vector<address> {
+    x
+}
+
+public fun echo_vec_vec_u32(x: vector<vector<u32>>): vector<vector<u32>> {
+    x
+}
+
+public fun echo_static_struct(x: Static): Static {
+    x
+}
+
+public fun echo_dynamic_struct(x: Dynamic): Dynamic {
+    x
+}
+
+public fun echo_generic_struct(x: Pair<vector<u64>>): Pair<vector<u64>> {
+    x
+}
+
+public fun echo_enum(x: Color): Color {
+    x
+}
+
+public fun echo_ref(x: &vector<u64>): vector<u64> {
+    *x
+}
+
+public fun echo_mut_ref(x: &mut u128): u128 {
+    *x
+}
+
+public fun echo_many(a: u8, b: vector<u16>, c: Static, d: bool, e: vector<vector<u256>>): (u8, vector<u16>, Static, bool, vector<vector<u256>>) {
+    (a, b, c, d, e)
+}
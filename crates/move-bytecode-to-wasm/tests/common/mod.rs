@@ -2,15 +2,18 @@ use std::{
     collections::{BTreeMap, HashMap},
     fs,
     path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
 };
 
-use move_bytecode_to_wasm::{translate_package, translate_single_module};
-use move_package::{BuildConfig, LintFlag};
+use move_binary_format::{CompiledModule, file_format::Bytecode};
+use move_bytecode_to_wasm::{
+    ContractAbi, StorageGasReport, StorageLayout, TranslationOptions, UnitTest, translate_package,
+    translate_package_with_artifacts, translate_package_with_options, translate_single_module,
+};
+use move_package::{BuildConfig, LintFlag, compilation::compiled_package::CompiledPackage};
 use move_packages_build::implicit_dependencies;
 use walrus::Module;
 
-pub mod runtime_sandbox;
-
 fn copy_dir_recursive(src: &Path, dst: &Path) -> std::io::Result<()> {
     if !dst.exists() {
         fs::create_dir_all(dst)?;
@@ -32,40 +35,36 @@ fn copy_dir_recursive(src: &Path, dst: &Path) -> std::io::Result<()> {
     Ok(())
 }
 
-pub fn reroot_path(path: &Path) -> PathBuf {
-    // Copy files to temp to avoid file locks
-    // Use a more unique identifier to prevent conflicts between concurrent tests
-    use std::thread;
-    use std::time::{SystemTime, UNIX_EPOCH};
-
-    let timestamp = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_nanos();
-
-    // Use a random number instead of thread ID for uniqueness
-    use std::collections::hash_map::DefaultHasher;
-    use std::hash::{Hash, Hasher};
+/// Directory of the crate, the paths of the test sources are relative to. Tests must not depend on
+/// the working directory, since the harness runs them in parallel.
+fn manifest_dir() -> &'static Path {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+}
 
-    let mut hasher = DefaultHasher::new();
-    thread::current().id().hash(&mut hasher);
-    let thread_hash = hasher.finish();
+pub fn reroot_path(path: &Path) -> PathBuf {
+    // Copy files to temp to avoid file locks. Every package gets its own directory, unique within
+    // the process, so concurrent tests don't overwrite each other's sources or build outputs
+    static NEXT_PACKAGE: AtomicUsize = AtomicUsize::new(0);
 
+    let path = manifest_dir().join(path);
     let temp_install_directory = std::env::temp_dir()
         .join("move-bytecode-to-wasm")
         .join(format!(
             "{}_{}_{}",
             path.file_name().unwrap().to_string_lossy(),
-            timestamp,
-            thread_hash
+            std::process::id(),
+            NEXT_PACKAGE.fetch_add(1, Ordering::Relaxed)
         ));
 
+    // Left over by a previous process with the same id
+    let _ = fs::remove_dir_all(&temp_install_directory);
+
     // copy source file to dir
     let _ = fs::create_dir_all(temp_install_directory.join("sources"));
 
     // If the path is a directory, we copy all the move files to the temp dir
     if path.is_dir() {
-        for entry in fs::read_dir(path).unwrap() {
+        for entry in fs::read_dir(&path).unwrap() {
             let entry = entry.unwrap();
             let filepath = entry.path();
             if filepath.is_file() {
@@ -80,7 +79,7 @@ pub fn reroot_path(path: &Path) -> PathBuf {
         }
     } else {
         std::fs::copy(
-            path,
+            &path,
             temp_install_directory
                 .join("sources")
                 .join(path.file_name().unwrap()),
@@ -108,7 +107,7 @@ test = "0x0"
 
 fn create_move_toml_with_framework(install_dir: &Path, framework_dir: &str) {
     copy_dir_recursive(
-        &PathBuf::from(framework_dir),
+        &manifest_dir().join(framework_dir),
         &install_dir.join("stylus-framework"),
     )
     .unwrap();
@@ -168,6 +167,18 @@ pub fn translate_test_package(path: &str, module_name: &str) -> Module {
     translate_single_module(package, module_name)
 }
 
+#[allow(dead_code)]
+/// Compiles a single test module without translating it
+pub fn compile_test_package(path: &str) -> CompiledPackage {
+    let path = Path::new(path);
+    let rerooted_path = reroot_path(path);
+    create_move_toml(&rerooted_path);
+
+    get_build_confing()
+        .compile_package(&rerooted_path, &mut Vec::new())
+        .unwrap()
+}
+
 #[allow(dead_code)]
 /// Translates a complete package. It outputs all the corresponding wasm modules
 pub fn translate_test_complete_package(path: &str) -> HashMap<String, Module> {
@@ -196,3 +207,322 @@ pub fn translate_test_package_with_framework(path: &str, module_name: &str) -> M
 
     translate_single_module(package, module_name)
 }
+
+#[allow(dead_code)]
+/// Translates a single test module with custom translation options
+pub fn translate_test_package_with_framework_and_options(
+    path: &str,
+    module_name: &str,
+    options: &TranslationOptions,
+) -> Module {
+    let path = Path::new(path);
+    let rerooted_path = reroot_path(path);
+    create_move_toml_with_framework(&rerooted_path, "../../stylus-framework");
+
+    let package = get_build_confing()
+        .compile_package(&rerooted_path, &mut Vec::new())
+        .unwrap();
+
+    let mut modules =
+        translate_package_with_options(package, Some(module_name.to_string()), options);
+
+    modules.remove(module_name).expect("Module not compiled")
+}
+
+#[allow(dead_code)]
+/// Translates a single test module and returns its storage layout
+pub fn translate_test_package_storage_layout(path: &str, module_name: &str) -> StorageLayout {
+    let path = Path::new(path);
+    let rerooted_path = reroot_path(path);
+    create_move_toml_with_framework(&rerooted_path, "../../stylus-framework");
+
+    let package = get_build_confing()
+        .compile_package(&rerooted_path, &mut Vec::new())
+        .unwrap();
+
+    let mut modules = translate_package_with_artifacts(
+        package,
+        Some(module_name.to_string()),
+        &TranslationOptions::default(),
+    );
+
+    modules
+        .remove(module_name)
+        .expect("Module not compiled")
+        .storage_layout
+}
+
+#[allow(dead_code)]
+/// Translates a single test module and returns its storage gas report
+pub fn translate_test_package_storage_gas_report(
+    path: &str,
+    module_name: &str,
+) -> StorageGasReport {
+    translate_test_package_storage_gas_report_with_code(path, module_name, |_| vec![])
+}
+
+#[allow(dead_code)]
+/// Translates a single test module and returns its storage gas report, replacing first the code of
+/// the functions returned by `code`. Used to estimate bytecodes the compiler doesn't emit
+pub fn translate_test_package_storage_gas_report_with_code(
+    path: &str,
+    module_name: &str,
+    code: impl FnOnce(&CompiledModule) -> Vec<(&'static str, Vec<Bytecode>)>,
+) -> StorageGasReport {
+    let path = Path::new(path);
+    let rerooted_path = reroot_path(path);
+    create_move_toml_with_framework(&rerooted_path, "../../stylus-framework");
+
+    let mut package = get_build_confing()
+        .compile_package(&rerooted_path, &mut Vec::new())
+        .unwrap();
+
+    let module = &mut package
+        .root_compiled_units
+        .iter_mut()
+        .find(|unit| unit.unit.name.to_string() == module_name)
+        .expect("Module not compiled")
+        .unit
+        .module;
+
+    for (function_name, function_code) in code(module) {
+        let definition_index = module
+            .function_defs
+            .iter()
+            .position(|definition| {
+                let handle = module.function_handle_at(definition.function);
+                module.identifier_at(handle.name).as_str() == function_name
+            })
+            .expect("Function not found");
+
+        module.function_defs[definition_index]
+            .code
+            .as_mut()
+            .expect("Function without code")
+            .code = function_code;
+    }
+
+    let mut modules = translate_package_with_artifacts(
+        package,
+        Some(module_name.to_string()),
+        &TranslationOptions::default(),
+    );
+
+    modules
+        .remove(module_name)
+        .expect("Module not compiled")
+        .storage_gas_report
+}
+
+#[allow(dead_code)]
+/// Translates a single test module and returns its Solidity JSON ABI
+pub fn translate_test_package_abi(path: &str, module_name: &str) -> ContractAbi {
+    let translated_module = translate_package_with_artifacts(
+        compile_test_package(path),
+        Some(module_name.to_string()),
+        &TranslationOptions::default(),
+    )
+    .remove(module_name)
+    .expect("Module not compiled");
+
+    translated_module.abi
+}
+
+#[allow(dead_code)]
+/// Translates a single test module with the coverage instrumentation, returning its coverage map
+/// as JSON
+pub fn translate_test_package_with_coverage(path: &str, module_name: &str) -> (Module, String) {
+    let path = Path::new(path);
+    let rerooted_path = reroot_path(path);
+    create_move_toml(&rerooted_path);
+
+    let package = get_build_confing()
+        .compile_package(&rerooted_path, &mut Vec::new())
+        .unwrap();
+
+    let options = TranslationOptions {
+        coverage: true,
+        ..Default::default()
+    };
+    let translated_module =
+        translate_package_with_artifacts(package, Some(module_name.to_string()), &options)
+            .remove(module_name)
+            .expect("Module not compiled");
+
+    let coverage_map = translated_module
+        .coverage_map
+        .expect("The coverage map is built with the coverage option")
+        .to_json();
+
+    (translated_module.module, coverage_map)
+}
+
+#[allow(dead_code)]
+/// Translates a single test module with debug information, returning its WASM, as it is emitted,
+/// and its source map as JSON
+pub fn translate_test_package_with_debug_info(path: &str, module_name: &str) -> (Vec<u8>, String) {
+    let path = Path::new(path);
+    let rerooted_path = reroot_path(path);
+    create_move_toml(&rerooted_path);
+
+    let package = get_build_confing()
+        .compile_package(&rerooted_path, &mut Vec::new())
+        .unwrap();
+
+    let options = TranslationOptions {
+        debug_info: true,
+        ..Default::default()
+    };
+    let mut translated_module =
+        translate_package_with_artifacts(package, Some(module_name.to_string()), &options)
+            .remove(module_name)
+            .expect("Module not compiled");
+
+    // The source map is complete once the module is emitted
+    let wasm = translated_module.module.emit_wasm();
+    let source_map = translated_module
+        .source_map
+        .expect("The source map is built with the debug_info option")
+        .to_json();
+
+    (wasm, source_map)
+}
+
+#[allow(dead_code)]
+/// Compiles a single test module in test mode and translates it with its `#[test]` functions
+/// dispatched by the router, returning them
+pub fn translate_test_package_with_unit_tests(
+    path: &str,
+    module_name: &str,
+) -> (Module, Vec<UnitTest>) {
+    let path = Path::new(path);
+    let rerooted_path = reroot_path(path);
+    create_move_toml(&rerooted_path);
+
+    let package = BuildConfig {
+        test_mode: true,
+        ..get_build_confing()
+    }
+    .compile_package(&rerooted_path, &mut Vec::new())
+    .unwrap();
+
+    let options = TranslationOptions {
+        unit_tests: true,
+        ..Default::default()
+    };
+    let translated_module =
+        translate_package_with_artifacts(package, Some(module_name.to_string()), &options)
+            .remove(module_name)
+            .expect("Module not compiled");
+
+    (translated_module.module, translated_module.unit_tests)
+}
+
+#[allow(dead_code)]
+/// Converts the name of a Move function to the name of its entry function, as the compiler does.
+pub fn snake_to_camel(name: &str) -> String {
+    let mut parts = name.split('_').filter(|part| !part.is_empty());
+    let mut result = parts.next().unwrap_or_default().to_owned();
+    for part in parts {
+        let mut chars = part.chars();
+        if let Some(first) = chars.next() {
+            result.push(first.to_ascii_uppercase());
+            result.extend(chars);
+        }
+    }
+
+    result
+}
+
+/// Directory of the golden WAT files, relative to the crate
+const GOLDEN_WAT_DIRECTORY: &str = "tests/golden";
+
+/// Set to write the WAT of the modules to their golden files instead of comparing them
+const UPDATE_GOLDEN_VARIABLE: &str = "UPDATE_GOLDEN";
+
+#[allow(dead_code)]
+/// Compares the normalized WAT of a module with its golden file, `tests/golden/<name>.wat`,
+/// panicking with the first lines that differ, so the changes to the generated code show up in
+/// review as changes to the golden files.
+///
+/// The golden files are checked in: the tests fail if one is missing, so they never record their
+/// own baseline. With the `UPDATE_GOLDEN` environment variable set, the golden file is written
+/// instead, to record it or accept the changes.
+pub fn assert_wat_snapshot(module: &mut Module, name: &str) {
+    let wat = normalize_wat(&wasmprinter::print_bytes(module.emit_wasm()).unwrap());
+    let path = manifest_dir()
+        .join(GOLDEN_WAT_DIRECTORY)
+        .join(format!("{name}.wat"));
+
+    if std::env::var_os(UPDATE_GOLDEN_VARIABLE).is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, wat).unwrap();
+        return;
+    }
+
+    assert!(
+        path.exists(),
+        "the golden file {} of {name} is missing, run the tests with {UPDATE_GOLDEN_VARIABLE}=1 to \
+         record it and check it in",
+        path.display()
+    );
+
+    let golden = fs::read_to_string(&path).unwrap();
+    if golden == wat {
+        return;
+    }
+
+    let golden_lines: Vec<&str> = golden.lines().collect();
+    let lines: Vec<&str> = wat.lines().collect();
+    let first_difference = golden_lines
+        .iter()
+        .zip(&lines)
+        .position(|(golden_line, line)| golden_line != line)
+        .unwrap_or(golden_lines.len().min(lines.len()));
+
+    // A few lines around the first difference
+    let context = |lines: &[&str]| {
+        lines
+            .iter()
+            .enumerate()
+            .skip(first_difference.saturating_sub(3))
+            .take(7)
+            .map(|(i, line)| format!("{:>6} | {line}", i + 1))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    panic!(
+        "the WAT of {name} differs from {} at line {}, run the tests with {UPDATE_GOLDEN_VARIABLE}=1 \
+         to accept the changes\n\nexpected:\n{}\n\nfound:\n{}",
+        path.display(),
+        first_difference + 1,
+        context(&golden_lines),
+        context(&lines),
+    );
+}
+
+/// Normalizes the WAT printed for a module so it only changes when the generated code does: drops
+/// the producers section, that names the version of walrus, and the trailing whitespace.
+fn normalize_wat(wat: &str) -> String {
+    let mut normalized = String::new();
+    // Parentheses left open in the producers section, while it is skipped
+    let mut producers_depth = None;
+
+    for line in wat.lines() {
+        if producers_depth.is_none() && line.trim_start().starts_with("(@producers") {
+            producers_depth = Some(0);
+        }
+
+        if let Some(depth) = producers_depth {
+            let depth = depth + line.matches('(').count() - line.matches(')').count();
+            producers_depth = (depth > 0).then_some(depth);
+            continue;
+        }
+
+        normalized.push_str(line.trim_end());
+        normalized.push('\n');
+    }
+
+    normalized
+}
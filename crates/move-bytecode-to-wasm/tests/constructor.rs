@@ -1,6 +1,7 @@
 mod common;
 
-use common::{runtime_sandbox::RuntimeSandbox, translate_test_package_with_framework};
+use common::translate_test_package_with_framework;
+use move_stylus_testing::RuntimeSandbox;
 use rstest::{fixture, rstest};
 
 mod constructor {
@@ -35,7 +36,7 @@ mod constructor {
         assert_eq!(0, result);
 
         // Read the object id emmited from the contract's events
-        let object_id = runtime.log_events.lock().unwrap().recv().unwrap();
+        let object_id = runtime.log_events.lock().unwrap().recv().unwrap().data;
         let object_id = FixedBytes::<32>::from_slice(&object_id);
 
         // Read initial value (should be 101)
@@ -62,6 +63,40 @@ mod constructor {
         assert_eq!(102, return_data);
         assert_eq!(0, result);
     }
+
+    #[rstest]
+    fn test_deploy(runtime: RuntimeSandbox) {
+        runtime.deploy().unwrap();
+
+        let object_id = runtime.log_events.lock().unwrap().recv().unwrap().data;
+        let object_id = FixedBytes::<32>::from_slice(&object_id);
+
+        let call_data = setValueCall::new((object_id, 102)).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        // The one-time guard makes the second deployment a no-op
+        runtime.deploy().unwrap();
+        assert!(runtime.get_logs().is_empty());
+
+        let call_data = readValueCall::new((object_id,)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert_eq!(
+            102,
+            readValueCall::abi_decode_returns(&return_data).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_deploy_without_init() {
+        let mut translated_package =
+            translate_test_package_with_framework("tests/storage/counter.move", "counter");
+        let runtime = RuntimeSandbox::new(&mut translated_package);
+
+        runtime.deploy().unwrap();
+        assert!(runtime.get_logs().is_empty());
+    }
 }
 
 mod constructor_with_otw {
@@ -96,7 +131,7 @@ mod constructor_with_otw {
         assert_eq!(0, result);
 
         // Read the object id emmited from the contract's events
-        let object_id = runtime.log_events.lock().unwrap().recv().unwrap();
+        let object_id = runtime.log_events.lock().unwrap().recv().unwrap().data;
         let object_id = FixedBytes::<32>::from_slice(&object_id);
 
         // Read initial value (should be 101)
@@ -125,6 +160,75 @@ mod constructor_with_otw {
     }
 }
 
+mod constructor_with_args {
+    use alloy_primitives::{FixedBytes, address};
+    use alloy_sol_types::{SolCall, sol};
+
+    use super::*;
+
+    #[fixture]
+    fn runtime() -> RuntimeSandbox {
+        const MODULE_NAME: &str = "constructor_with_args";
+        const SOURCE_PATH: &str = "tests/constructor/constructor_with_args.move";
+
+        let mut translated_package =
+            translate_test_package_with_framework(SOURCE_PATH, MODULE_NAME);
+
+        RuntimeSandbox::new(&mut translated_package)
+    }
+
+    sol!(
+        #[allow(missing_docs)]
+        function constructor(address owner, uint64 fee) public;
+        function readValue(bytes32 id) public view returns (uint64);
+        function owner(bytes32 id) public view returns (address);
+        function fee(bytes32 id) public view returns (uint64);
+    );
+
+    #[rstest]
+    fn test_constructor_with_args(runtime: RuntimeSandbox) {
+        let owner = address!("0x00000000000000000000000000000000deadbeef");
+        runtime
+            .deploy_with(constructorCall::new((owner, 25)).abi_encode())
+            .unwrap();
+
+        // `init` creates its object before the `#[ext(constructor)]` function
+        let foo_id = runtime.log_events.lock().unwrap().recv().unwrap().data;
+        let foo_id = FixedBytes::<32>::from_slice(&foo_id);
+        let config_id = runtime.log_events.lock().unwrap().recv().unwrap().data;
+        let config_id = FixedBytes::<32>::from_slice(&config_id);
+
+        let call_data = readValueCall::new((foo_id,)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert_eq!(
+            101,
+            readValueCall::abi_decode_returns(&return_data).unwrap()
+        );
+
+        let call_data = ownerCall::new((config_id,)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert_eq!(owner, ownerCall::abi_decode_returns(&return_data).unwrap());
+
+        let call_data = feeCall::new((config_id,)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert_eq!(25, feeCall::abi_decode_returns(&return_data).unwrap());
+
+        // The one-time guard makes the second deployment a no-op, whatever its arguments
+        runtime
+            .deploy_with(constructorCall::new((owner, 50)).abi_encode())
+            .unwrap();
+        assert!(runtime.get_logs().is_empty());
+
+        let call_data = feeCall::new((config_id,)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert_eq!(25, feeCall::abi_decode_returns(&return_data).unwrap());
+    }
+}
+
 mod constructor_with_return {
     use alloy_sol_types::{SolCall, sol};
 
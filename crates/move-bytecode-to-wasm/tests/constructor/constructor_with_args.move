@@ -0,0 +1,50 @@
+module test::constructor_with_args;
+
+use stylus::tx_context::TxContext;
+use stylus::object as object;
+use stylus::object::UID;
+use stylus::transfer as transfer;
+
+public struct Foo has key {
+    id: UID,
+    value: u64
+}
+
+public struct Config has key {
+    id: UID,
+    owner: address,
+    fee: u64
+}
+
+fun init(ctx: &mut TxContext) {
+  let foo = Foo {
+    id: object::new(ctx),
+    value: 101,
+  };
+
+  transfer::share_object(foo);
+}
+
+// Called after init, with the arguments of the constructor
+#[ext(constructor)]
+fun setup(owner: address, fee: u64, ctx: &mut TxContext) {
+  let config = Config {
+    id: object::new(ctx),
+    owner,
+    fee,
+  };
+
+  transfer::share_object(config);
+}
+
+public fun read_value(foo: &Foo): u64 {
+    foo.value
+}
+
+public fun owner(config: &Config): address {
+    config.owner
+}
+
+public fun fee(config: &Config): u64 {
+    config.fee
+}
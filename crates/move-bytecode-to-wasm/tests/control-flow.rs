@@ -1,6 +1,10 @@
 use alloy_sol_types::{SolCall, SolType, sol};
 use anyhow::Result;
-use common::{runtime_sandbox::RuntimeSandbox, translate_test_package};
+use common::{
+    translate_test_package, translate_test_package_with_coverage,
+    translate_test_package_with_debug_info,
+};
+use move_stylus_testing::{ExecutionTimeout, RuntimeSandbox};
 use rstest::{fixture, rstest};
 
 mod common;
@@ -114,6 +118,7 @@ mod control_flow_u64 {
         function fibonacci(uint64 n) external returns (uint64);
         function isPrime(uint64 i) external returns (bool);
         function sumSpecial(uint64 n) external returns (uint64);
+        function spin(uint64 x) external returns (uint64);
     );
 
     #[rstest]
@@ -159,4 +164,138 @@ mod control_flow_u64 {
         )
         .unwrap();
     }
+
+    #[test]
+    fn test_execution_timeout() {
+        let mut translated_package = translate_test_package(
+            "tests/control-flow/control_flow_u64.move",
+            "control_flow_u64",
+        );
+        let runtime = RuntimeSandbox::new(&mut translated_package);
+        runtime.set_execution_budget(1_000_000);
+
+        run_test(
+            &runtime,
+            spinCall::new((0u64,)).abi_encode(),
+            <sol!((uint64,))>::abi_encode(&(0u64,)),
+        )
+        .unwrap();
+
+        let error = runtime
+            .call_entrypoint(spinCall::new((1u64,)).abi_encode())
+            .unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<ExecutionTimeout>(),
+            Some(&ExecutionTimeout { budget: 1_000_000 })
+        );
+    }
+
+    #[test]
+    fn test_coverage() {
+        let (mut translated_package, coverage_map) = translate_test_package_with_coverage(
+            "tests/control-flow/control_flow_u64.move",
+            "control_flow_u64",
+        );
+        let runtime = RuntimeSandbox::new(&mut translated_package);
+
+        run_test(
+            &runtime,
+            fibonacciCall::new((5u64,)).abi_encode(),
+            <sol!((uint64,))>::abi_encode(&(5u64,)),
+        )
+        .unwrap();
+
+        let report = runtime.lcov_report(&coverage_map).unwrap();
+        assert!(report.contains("SF:"));
+        assert!(report.contains(",fibonacci\n"));
+        assert!(report.contains("FNDA:1,fibonacci\n"));
+        assert!(report.contains("FNDA:0,is_prime\n"));
+        assert!(report.contains("\nDA:"));
+        assert!(report.ends_with("end_of_record\n"));
+
+        // The loop's body runs once per number after the first two
+        let hits = runtime.get_coverage_hits();
+        assert!(hits.values().any(|hits| *hits >= 4));
+    }
+
+    #[test]
+    fn test_debug_info() {
+        let (wasm, source_map) = translate_test_package_with_debug_info(
+            "tests/control-flow/control_flow_u64.move",
+            "control_flow_u64",
+        );
+
+        // The locals of `fibonacci` are named after the Move ones
+        let wat = wasmprinter::print_bytes(&wasm).unwrap();
+        assert!(wat.contains("$count"));
+        assert!(wat.contains("$temp"));
+        // The module points to its source map
+        let url = b"control_flow_u64.wasm.map";
+        assert!(wasm.windows(url.len()).any(|bytes| bytes == url));
+
+        let source_map: serde_json::Value = serde_json::from_str(&source_map).unwrap();
+        assert_eq!(3, source_map["version"]);
+        assert!(
+            source_map["sources"][0]
+                .as_str()
+                .unwrap()
+                .ends_with("control_flow_u64.move")
+        );
+        assert!(!source_map["mappings"].as_str().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_instruction_profile() {
+        let mut translated_package = translate_test_package(
+            "tests/control-flow/control_flow_u64.move",
+            "control_flow_u64",
+        );
+        assert!(
+            RuntimeSandbox::new(&mut translated_package)
+                .get_instruction_profile()
+                .is_none()
+        );
+
+        let runtime = RuntimeSandbox::builder(&mut translated_package)
+            .profile_instructions()
+            .build();
+        let fibonacci_instructions = |runtime: &RuntimeSandbox| {
+            runtime
+                .get_instruction_profile()
+                .unwrap()
+                .by_function()
+                .into_iter()
+                .find(|(function, _)| function.ends_with("::control_flow_u64::fibonacci"))
+                .map_or(0, |(_, instructions)| instructions)
+        };
+
+        run_test(
+            &runtime,
+            fibonacciCall::new((10u64,)).abi_encode(),
+            <sol!((uint64,))>::abi_encode(&(55u64,)),
+        )
+        .unwrap();
+        let after_short_call = fibonacci_instructions(&runtime);
+        assert!(after_short_call > 0);
+
+        // The router runs the function, and the loop dominates the instructions run
+        let profile = runtime.get_instruction_profile().unwrap();
+        assert!(
+            profile
+                .folded()
+                .lines()
+                .any(|line| line.starts_with("user_entrypoint;")
+                    && line.contains("::control_flow_u64::fibonacci "))
+        );
+        assert!(profile.total() > after_short_call);
+
+        run_test(
+            &runtime,
+            fibonacciCall::new((40u64,)).abi_encode(),
+            <sol!((uint64,))>::abi_encode(&(102_334_155u64,)),
+        )
+        .unwrap();
+        let after_long_call = fibonacci_instructions(&runtime);
+        assert!(after_long_call > 2 * after_short_call);
+    }
 }
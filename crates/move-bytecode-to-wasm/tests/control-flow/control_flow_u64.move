@@ -78,3 +78,11 @@ public fun sum_special(n: u64): u64 {
 
     total
 }
+
+// Never ends for a non-zero x
+public fun spin(mut x: u64): u64 {
+    while (x != 0) {
+        x = x | 1;
+    };
+    x
+}
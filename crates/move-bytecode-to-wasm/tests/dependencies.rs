@@ -1,6 +1,13 @@
 use alloy_sol_types::{SolCall, SolType, SolValue, abi::TokenSeq, sol};
 use anyhow::Result;
-use common::{runtime_sandbox::RuntimeSandbox, translate_test_complete_package};
+use common::{compile_test_package, translate_test_complete_package};
+use std::{collections::HashSet, path::PathBuf};
+
+use move_bytecode_to_wasm::{
+    ContractSelection, OutputOptions, TranslationCache, TranslationOptions, translate_package_cli,
+    translate_package_incremental, translate_package_with_artifacts,
+};
+use move_stylus_testing::RuntimeSandbox;
 use rstest::{fixture, rstest};
 
 mod common;
@@ -81,3 +88,185 @@ fn test_dependencies<T: SolCall, V: SolValue>(
     )
     .unwrap();
 }
+
+/// Translates the package with the cache, after appending a comment to the sources of the modules,
+/// returning the names of the modules translated.
+fn translate_incremental(cache: &mut TranslationCache, changed_modules: &[&str]) -> Vec<String> {
+    let package = compile_test_package("tests/dependencies");
+    for unit in &package.root_compiled_units {
+        if changed_modules.contains(&unit.unit.name.as_str()) {
+            let source = std::fs::read_to_string(&unit.source_path).unwrap();
+            std::fs::write(&unit.source_path, format!("{source}\n// changed\n")).unwrap();
+        }
+    }
+
+    let mut modules: Vec<String> =
+        translate_package_incremental(package, None, &TranslationOptions::default(), cache)
+            .into_keys()
+            .collect();
+    modules.sort();
+    modules
+}
+
+#[test]
+fn test_incremental_translation() {
+    let mut cache = TranslationCache::default();
+    let all_modules = vec!["another_mod", "main", "other_mod"];
+
+    assert_eq!(all_modules, translate_incremental(&mut cache, &[]));
+    assert!(translate_incremental(&mut cache, &[]).is_empty());
+
+    // Only the module that changed
+    assert_eq!(vec!["main"], translate_incremental(&mut cache, &["main"]));
+
+    // The modules that depend on the one that changed link its functions
+    assert_eq!(
+        vec!["main", "other_mod"],
+        translate_incremental(&mut cache, &["other_mod"])
+    );
+    assert_eq!(
+        all_modules,
+        translate_incremental(&mut cache, &["another_mod"])
+    );
+
+    cache.clear();
+    assert_eq!(all_modules, translate_incremental(&mut cache, &[]));
+}
+
+fn translated_modules(contracts: ContractSelection) -> Vec<String> {
+    let options = TranslationOptions {
+        contracts,
+        ..Default::default()
+    };
+    let mut modules: Vec<String> = translate_package_with_artifacts(
+        compile_test_package("tests/dependencies"),
+        None,
+        &options,
+    )
+    .into_keys()
+    .collect();
+    modules.sort();
+    modules
+}
+
+#[test]
+fn test_contract_selection() {
+    assert_eq!(
+        vec!["another_mod", "main", "other_mod"],
+        translated_modules(ContractSelection::All)
+    );
+
+    // other_mod and another_mod are only used by the modules that depend on them
+    assert_eq!(
+        vec!["main"],
+        translated_modules(ContractSelection::SkipLibraries)
+    );
+
+    assert_eq!(
+        vec!["other_mod"],
+        translated_modules(ContractSelection::Modules(vec!["other_mod".to_owned()]))
+    );
+}
+
+#[test]
+fn test_deterministic_build() {
+    let options = TranslationOptions {
+        deterministic: true,
+        ..Default::default()
+    };
+    // Every build iterates its hash sets in another order
+    let build = || {
+        let mut modules: Vec<(String, Vec<u8>)> = translate_package_with_artifacts(
+            compile_test_package("tests/dependencies"),
+            None,
+            &options,
+        )
+        .into_iter()
+        .map(|(module_name, mut translated_module)| {
+            (module_name, translated_module.module.emit_wasm())
+        })
+        .collect();
+        modules.sort();
+        modules
+    };
+
+    let modules = build();
+    assert_eq!(3, modules.len());
+    for _ in 0..3 {
+        assert!(modules == build(), "the builds are not byte-identical");
+    }
+}
+
+#[test]
+fn test_shared_functions_relooped_once() {
+    let mut cache = TranslationCache::default();
+    let modules = translate_package_incremental(
+        compile_test_package("tests/dependencies"),
+        None,
+        &TranslationOptions::default(),
+        &mut cache,
+    );
+    assert_eq!(3, modules.len());
+
+    // The functions translated from Move are named `<address>::<module>::<function>`
+    let translated: Vec<String> = modules
+        .values()
+        .flat_map(|translated_module| translated_module.module.funcs.iter())
+        .filter(|function| matches!(function.kind, walrus::FunctionKind::Local(_)))
+        .filter_map(|function| function.name.clone())
+        .filter(|name| name.contains("::"))
+        .collect();
+    let distinct: HashSet<&String> = translated.iter().collect();
+
+    // `another_mod::get_value` is translated into the three modules, but relooped once
+    assert!(translated.len() > distinct.len());
+    assert_eq!(distinct.len(), cache.relooped_functions());
+}
+
+#[test]
+fn test_build_cache() {
+    let package_path =
+        std::env::temp_dir().join(format!("move-stylus-build-cache-{}", std::process::id()));
+    let output = OutputOptions {
+        cache_directory: Some(PathBuf::from("cache")),
+        ..Default::default()
+    };
+    let build = || {
+        translate_package_cli(
+            compile_test_package("tests/dependencies"),
+            &package_path,
+            &TranslationOptions::default(),
+            &output,
+        )
+        .unwrap()
+    };
+
+    let modules = build();
+    let mut cached_files: Vec<PathBuf> = std::fs::read_dir(package_path.join("cache"))
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    cached_files.sort();
+    // The WASM, the ABI, the storage layout and the gas report of every module
+    assert_eq!(modules.len() * 4, cached_files.len());
+
+    // The same bytecode is written from the cache, without translating it
+    let empty_module = b"\0asm\x01\0\0\0".to_vec();
+    for path in &cached_files {
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "wasm")
+        {
+            std::fs::write(path, &empty_module).unwrap();
+        }
+    }
+    let cached_modules = build();
+    assert_eq!(modules.len(), cached_modules.len());
+    assert!(cached_modules.values().all(|wasm| *wasm == empty_module));
+    assert_eq!(
+        empty_module,
+        std::fs::read(package_path.join("build/wasm/main.wasm")).unwrap()
+    );
+
+    std::fs::remove_dir_all(package_path).unwrap();
+}
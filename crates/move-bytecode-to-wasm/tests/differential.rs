@@ -0,0 +1,297 @@
+//! Differential tests: the same Move function is run on the Move VM and, translated, on the
+//! sandbox, and both executions must return the same values or both abort.
+//!
+//! The translated code does not report abort codes yet, so the codes are only compared when both
+//! executions report them.
+use alloy_dyn_abi::{DynSolType, DynSolValue};
+use alloy_primitives::{Address, U256, keccak256};
+use common::{compile_test_package, snake_to_camel};
+use move_binary_format::{CompiledModule, file_format::SignatureToken};
+use move_bytecode_to_wasm::translate_single_module;
+use move_core_types::{
+    account_address::AccountAddress,
+    identifier::IdentStr,
+    runtime_value::MoveValue,
+    u256::U256 as MoveU256,
+    vm_status::{StatusCode, StatusType},
+};
+use move_stdlib_natives::{GasParameters, all_natives};
+use move_stylus_testing::{RuntimeSandbox, call_result::CallResult};
+use move_vm_runtime::move_vm::MoveVM;
+use move_vm_test_utils::{InMemoryStorage, gas_schedule::GasStatus};
+
+mod common;
+
+/// Result of an execution, comparable between the Move VM and the sandbox.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Outcome {
+    /// The return values, ABI-encoded as the entry functions return them
+    Returned(Vec<u8>),
+    /// The abort code, or `None` if the execution does not report it
+    Aborted(Option<u64>),
+}
+
+impl Outcome {
+    fn matches(&self, other: &Outcome) -> bool {
+        match (self, other) {
+            (Outcome::Aborted(Some(code)), Outcome::Aborted(Some(other_code))) => {
+                code == other_code
+            }
+            (Outcome::Aborted(_), Outcome::Aborted(_)) => true,
+            _ => self == other,
+        }
+    }
+}
+
+/// Runs the functions of a module on both the Move VM and the sandbox.
+struct DifferentialHarness {
+    vm: MoveVM,
+    storage: InMemoryStorage,
+    module: CompiledModule,
+    sandbox: RuntimeSandbox,
+}
+
+impl DifferentialHarness {
+    fn new(source_path: &str, module_name: &str) -> Self {
+        let package = compile_test_package(source_path);
+
+        // The Move VM loads the module's dependencies from the storage
+        let mut storage = InMemoryStorage::new();
+        let units = package
+            .root_compiled_units
+            .iter()
+            .chain(package.deps_compiled_units.iter().map(|(_, unit)| unit));
+        for unit in units {
+            let module = &unit.unit.module;
+            let mut bytes = Vec::new();
+            module
+                .serialize_with_version(module.version, &mut bytes)
+                .unwrap();
+            storage.publish_or_overwrite_module(module.self_id(), bytes);
+        }
+
+        let module = package
+            .root_compiled_units
+            .iter()
+            .find(|unit| unit.unit.name.to_string() == module_name)
+            .expect("Module not compiled")
+            .unit
+            .module
+            .clone();
+
+        let mut translated_package = translate_single_module(package, module_name);
+
+        let natives = all_natives(AccountAddress::ONE, GasParameters::zeros(), false);
+
+        Self {
+            vm: MoveVM::new(natives).unwrap(),
+            storage,
+            module,
+            sandbox: RuntimeSandbox::new(&mut translated_package),
+        }
+    }
+
+    /// Runs the function on both executions, panicking if their outcomes differ.
+    fn assert_same(&self, function: &str, args: &[MoveValue]) {
+        let expected = self.run_move_vm(function, args);
+        let result = self.run_sandbox(function, args);
+
+        assert!(
+            expected.matches(&result),
+            "{function}({args:?}) diverged:\nMove VM: {expected:?}\nsandbox: {result:?}"
+        );
+    }
+
+    fn run_move_vm(&self, function: &str, args: &[MoveValue]) -> Outcome {
+        let mut session = self.vm.new_session(&self.storage);
+        let serialized_args = args
+            .iter()
+            .map(|arg| arg.simple_serialize().unwrap())
+            .collect::<Vec<_>>();
+
+        let result = session.execute_function_bypass_visibility(
+            &self.module.self_id(),
+            IdentStr::new(function).unwrap(),
+            vec![],
+            serialized_args,
+            &mut GasStatus::new_unmetered(),
+            None,
+        );
+
+        match result {
+            Ok(return_values) => {
+                let values = return_values
+                    .return_values
+                    .iter()
+                    .map(|(bytes, layout)| {
+                        to_sol_value(&MoveValue::simple_deserialize(bytes, layout).unwrap())
+                    })
+                    .collect();
+
+                Outcome::Returned(DynSolValue::Tuple(values).abi_encode_params())
+            }
+            // Arithmetic errors and out of bounds accesses abort without a code
+            Err(e) if e.major_status().status_type() == StatusType::Execution => {
+                if e.major_status() == StatusCode::ABORTED {
+                    Outcome::Aborted(e.sub_status())
+                } else {
+                    Outcome::Aborted(None)
+                }
+            }
+            Err(e) => panic!("the Move VM failed to run {function}: {e:?}"),
+        }
+    }
+
+    fn run_sandbox(&self, function: &str, args: &[MoveValue]) -> Outcome {
+        let args = args.iter().map(to_sol_value).collect();
+
+        let mut calldata = self.selector(function).to_vec();
+        calldata.extend(DynSolValue::Tuple(args).abi_encode_params());
+
+        match self.sandbox.call(calldata) {
+            CallResult::Success(return_data) => Outcome::Returned(return_data),
+            CallResult::Revert(_) | CallResult::Trap(_) => Outcome::Aborted(None),
+        }
+    }
+
+    /// Computes the selector the entry function is dispatched with, from its Move signature.
+    fn selector(&self, function: &str) -> [u8; 4] {
+        let handle = self
+            .module
+            .function_defs()
+            .iter()
+            .map(|definition| self.module.function_handle_at(definition.function))
+            .find(|handle| self.module.identifier_at(handle.name).as_str() == function)
+            .unwrap_or_else(|| panic!("function {function} not found"));
+
+        let parameters = self
+            .module
+            .signature_at(handle.parameters)
+            .0
+            .iter()
+            .map(|token| to_sol_type(token).sol_type_name().into_owned())
+            .collect::<Vec<_>>();
+
+        let signature = format!("{}({})", snake_to_camel(function), parameters.join(","));
+        keccak256(signature)[..4].try_into().unwrap()
+    }
+}
+
+fn to_sol_type(token: &SignatureToken) -> DynSolType {
+    match token {
+        SignatureToken::Bool => DynSolType::Bool,
+        SignatureToken::U8 => DynSolType::Uint(8),
+        SignatureToken::U16 => DynSolType::Uint(16),
+        SignatureToken::U32 => DynSolType::Uint(32),
+        SignatureToken::U64 => DynSolType::Uint(64),
+        SignatureToken::U128 => DynSolType::Uint(128),
+        SignatureToken::U256 => DynSolType::Uint(256),
+        SignatureToken::Address => DynSolType::Address,
+        SignatureToken::Vector(inner) => DynSolType::Array(Box::new(to_sol_type(inner))),
+        token => panic!("differential tests do not support parameters of type {token:?}"),
+    }
+}
+
+fn to_sol_value(value: &MoveValue) -> DynSolValue {
+    match value {
+        MoveValue::Bool(value) => DynSolValue::Bool(*value),
+        MoveValue::U8(value) => DynSolValue::Uint(U256::from(*value), 8),
+        MoveValue::U16(value) => DynSolValue::Uint(U256::from(*value), 16),
+        MoveValue::U32(value) => DynSolValue::Uint(U256::from(*value), 32),
+        MoveValue::U64(value) => DynSolValue::Uint(U256::from(*value), 64),
+        MoveValue::U128(value) => DynSolValue::Uint(U256::from(*value), 128),
+        MoveValue::U256(value) => DynSolValue::Uint(U256::from_le_bytes(value.to_le_bytes()), 256),
+        // Move addresses are 32 bytes long, and the ABI's only hold the last 20
+        MoveValue::Address(address) => {
+            DynSolValue::Address(Address::from_slice(&address.into_bytes()[12..]))
+        }
+        MoveValue::Vector(values) => DynSolValue::Array(values.iter().map(to_sol_value).collect()),
+        value => panic!("differential tests do not support values like {value:?}"),
+    }
+}
+
+#[test]
+fn test_uint_64_arithmetic() {
+    let harness = DifferentialHarness::new("tests/primitives/uint_64.move", "uint_64");
+
+    let operands = [0, 1, 2, 7, u32::MAX as u64, u64::MAX / 2, u64::MAX];
+    for function in ["sum", "sub", "mul", "div", "mod_"] {
+        for x in operands {
+            for y in operands {
+                harness.assert_same(function, &[MoveValue::U64(x), MoveValue::U64(y)]);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_uint_256_arithmetic() {
+    let harness = DifferentialHarness::new("tests/primitives/uint_256.move", "uint_256");
+
+    let operands = [
+        MoveU256::zero(),
+        MoveU256::one(),
+        MoveU256::from(u64::MAX),
+        MoveU256::from(u128::MAX),
+        MoveU256::max_value(),
+    ];
+    for function in ["sum", "sub", "mul", "div", "mod_"] {
+        for x in operands {
+            for y in operands {
+                harness.assert_same(function, &[MoveValue::U256(x), MoveValue::U256(y)]);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_casts() {
+    let harness = DifferentialHarness::new("tests/operations-cast/uint_64.move", "uint_64");
+
+    for x in [0, 1, u64::MAX as u128, u64::MAX as u128 + 1, u128::MAX] {
+        harness.assert_same("cast_from_u128", &[MoveValue::U128(x)]);
+    }
+}
+
+#[test]
+fn test_control_flow() {
+    let harness = DifferentialHarness::new(
+        "tests/control-flow/control_flow_u64.move",
+        "control_flow_u64",
+    );
+
+    for n in [1, 2, 3, 27, 97] {
+        harness.assert_same("collatz", &[MoveValue::U64(n)]);
+    }
+
+    // Fibonacci overflows an u64 from the 94th number
+    for n in [0, 1, 2, 10, 93, 94] {
+        harness.assert_same("fibonacci", &[MoveValue::U64(n)]);
+    }
+
+    for n in [0, 1, 2, 9, 97, 7919] {
+        harness.assert_same("is_prime", &[MoveValue::U64(n)]);
+    }
+}
+
+#[test]
+fn test_vectors() {
+    let harness = DifferentialHarness::new("tests/primitives/vec_64.move", "vec_64");
+
+    let vectors = [vec![], vec![1], vec![1, 2, 3]];
+    for vector in vectors {
+        let vector = MoveValue::Vector(vector.into_iter().map(MoveValue::U64).collect());
+
+        harness.assert_same("vec_len", &[vector.clone()]);
+        harness.assert_same("vec_pop_back", &[vector.clone()]);
+        harness.assert_same("vec_push_back", &[vector.clone(), MoveValue::U64(4)]);
+
+        // Out of bounds indices abort
+        for (i, j) in [(0, 1), (0, 2), (1, 3)] {
+            harness.assert_same(
+                "vec_swap",
+                &[vector.clone(), MoveValue::U64(i), MoveValue::U64(j)],
+            );
+        }
+    }
+}
@@ -2,7 +2,7 @@ use alloy_sol_types::SolValue;
 use alloy_sol_types::abi::TokenSeq;
 use alloy_sol_types::{SolCall, SolType, sol};
 use anyhow::Result;
-use common::runtime_sandbox::RuntimeSandbox;
+use move_stylus_testing::RuntimeSandbox;
 use rstest::{fixture, rstest};
 
 mod common;
@@ -22,16 +22,15 @@ fn run_test(runtime: &RuntimeSandbox, call_data: Vec<u8>, expected_result: Vec<u
 }
 
 mod tx_context {
-    use alloy_primitives::{Address, hex};
-
-    use crate::common::{
-        runtime_sandbox::constants::{
-            BLOCK_BASEFEE, BLOCK_GAS_LIMIT, BLOCK_NUMBER, BLOCK_TIMESTAMP, GAS_PRICE,
-            MSG_SENDER_ADDRESS, MSG_VALUE,
-        },
-        translate_test_package_with_framework,
+    use alloy_primitives::{Address, U256, hex};
+
+    use move_stylus_testing::constants::{
+        BLOCK_BASEFEE, BLOCK_COINBASE, BLOCK_GAS_LIMIT, BLOCK_NUMBER, BLOCK_TIMESTAMP, GAS_PRICE,
+        INK_PER_GAS, MSG_SENDER_ADDRESS, MSG_VALUE,
     };
 
+    use crate::common::translate_test_package_with_framework;
+
     use super::*;
 
     #[fixture]
@@ -50,22 +49,34 @@ mod tx_context {
         #[allow(missing_docs)]
         function getSender() external returns (address);
         function getMsgValue() external returns (uint256);
+        function isReentrant() external returns (bool);
         function getBlockNumber() external returns (uint64);
         function getBlockBasefee() external returns (uint256);
+        function getBlockCoinbase() external returns (address);
         function getBlockGasLimit() external returns (uint64);
         function getBlockTimestamp() external returns (uint64);
+        function getChainId() external returns (uint64);
         function getGasPrice() external returns (uint256);
+        function getInkPrice() external returns (uint32);
+        function inkToGas(uint64 ink) external returns (uint64);
+        function gasToInk(uint64 gas) external returns (uint64);
         function getFreshObjectAddress() external returns (address, address, address);
     );
 
     #[rstest]
     #[case(getSenderCall::new(()), (Address::new(MSG_SENDER_ADDRESS),))]
     #[case(getMsgValueCall::new(()), (MSG_VALUE,))]
+    #[case(isReentrantCall::new(()), (false,))]
     #[case(getBlockNumberCall::new(()), (BLOCK_NUMBER,))]
     #[case(getBlockBasefeeCall::new(()), (BLOCK_BASEFEE,))]
+    #[case(getBlockCoinbaseCall::new(()), (Address::new(BLOCK_COINBASE),))]
     #[case(getBlockGasLimitCall::new(()), (BLOCK_GAS_LIMIT,))]
     #[case(getBlockTimestampCall::new(()), (BLOCK_TIMESTAMP,))]
     #[case(getGasPriceCall::new(()), (GAS_PRICE,))]
+    #[case(getInkPriceCall::new(()), (INK_PER_GAS as u32,))]
+    #[case(inkToGasCall::new((25_000_001,)), (2_500u64,))]
+    #[case(gasToInkCall::new((2_500,)), (25_000_000u64,))]
+    #[case(gasToInkCall::new((u64::MAX / 2,)), (u64::MAX,))]
     fn test_tx_context<T: SolCall, V: SolValue>(
         #[by_ref] runtime: &RuntimeSandbox,
         #[case] call_data: T,
@@ -108,10 +119,153 @@ mod tx_context {
         )
         .unwrap();
     }
+
+    #[rstest]
+    fn test_block_coinbase() {
+        // The coinbase is changed, so the shared runtime is not used
+        let mut translated_package =
+            translate_test_package_with_framework("tests/framework/tx_context.move", "tx_context");
+        let runtime = RuntimeSandbox::new(&mut translated_package);
+
+        let coinbase = Address::repeat_byte(0xcb);
+        runtime.set_block_coinbase(coinbase.into_array());
+
+        run_test(
+            &runtime,
+            getBlockCoinbaseCall::new(()).abi_encode(),
+            (coinbase,).abi_encode(),
+        )
+        .unwrap();
+    }
+
+    #[rstest]
+    #[case(U256::ZERO)]
+    #[case(U256::from(1_000_000_000_000_000_000u128))]
+    fn test_msg_value(#[case] value: U256) {
+        // The sender pays the value, so the shared runtime is not used
+        let mut translated_package =
+            translate_test_package_with_framework("tests/framework/tx_context.move", "tx_context");
+        let runtime = RuntimeSandbox::new(&mut translated_package);
+        runtime.set_balance(MSG_SENDER_ADDRESS, value);
+
+        let (result, return_data) = runtime
+            .call_entrypoint_with_value(getMsgValueCall::new(()).abi_encode(), value)
+            .unwrap();
+
+        assert_eq!(0, result);
+        assert_eq!((value,).abi_encode(), return_data);
+    }
+
+    #[test]
+    fn test_block_environment() {
+        // The shared runtime keeps the default block
+        let mut translated_package =
+            translate_test_package_with_framework("tests/framework/tx_context.move", "tx_context");
+        let runtime = RuntimeSandbox::new(&mut translated_package);
+
+        runtime.set_block_number(100);
+        runtime.set_block_timestamp(1_700_000_000);
+        runtime.set_block_basefee(U256::from(7));
+        runtime.set_block_gas_limit(1_000);
+        runtime.set_chain_id(1);
+
+        let call = |call_data: Vec<u8>, expected_result: Vec<u8>| {
+            run_test(&runtime, call_data, expected_result).unwrap();
+        };
+
+        call(
+            getBlockNumberCall::new(()).abi_encode(),
+            (100u64,).abi_encode(),
+        );
+        call(
+            getBlockTimestampCall::new(()).abi_encode(),
+            (1_700_000_000u64,).abi_encode(),
+        );
+        call(
+            getBlockBasefeeCall::new(()).abi_encode(),
+            (U256::from(7),).abi_encode(),
+        );
+        call(
+            getBlockGasLimitCall::new(()).abi_encode(),
+            (1_000u64,).abi_encode(),
+        );
+        call(getChainIdCall::new(()).abi_encode(), (1u64,).abi_encode());
+
+        runtime.warp(3_600);
+        runtime.roll(5);
+
+        call(
+            getBlockNumberCall::new(()).abi_encode(),
+            (105u64,).abi_encode(),
+        );
+        call(
+            getBlockTimestampCall::new(()).abi_encode(),
+            (1_700_003_600u64,).abi_encode(),
+        );
+    }
+
+    #[test]
+    fn test_tracing() {
+        // Tracing is enabled per sandbox, so the shared runtime is not used
+        let mut translated_package =
+            translate_test_package_with_framework("tests/framework/tx_context.move", "tx_context");
+        let runtime = RuntimeSandbox::new(&mut translated_package);
+        runtime.set_chain_id(42);
+
+        assert_eq!(None, runtime.get_trace());
+
+        runtime.enable_tracing();
+        run_test(
+            &runtime,
+            getChainIdCall::new(()).abi_encode(),
+            (42u64,).abi_encode(),
+        )
+        .unwrap();
+
+        let trace = runtime.get_trace().unwrap();
+        assert!(trace.dispatched::<getChainIdCall>());
+        assert!(!trace.dispatched::<getBlockNumberCall>());
+
+        // The router reads the calldata, and the entry function writes the result at the end
+        let names = trace.host_call_names();
+        let position = |name| names.iter().position(|n| *n == name).unwrap();
+        assert!(position("read_args") < position("chainid"));
+        assert!(position("chainid") < position("write_result"));
+
+        let chain_id = trace
+            .host_calls
+            .iter()
+            .find(|host_call| host_call.name == "chainid")
+            .unwrap();
+        assert_eq!(Vec::<i64>::new(), chain_id.args);
+        assert_eq!(vec![42], chain_id.results);
+
+        // Each call replaces the trace of the previous one
+        run_test(
+            &runtime,
+            getBlockNumberCall::new(()).abi_encode(),
+            (BLOCK_NUMBER,).abi_encode(),
+        )
+        .unwrap();
+
+        let trace = runtime.get_trace().unwrap();
+        assert!(trace.dispatched::<getBlockNumberCall>());
+        assert!(!trace.host_call_names().contains(&"chainid"));
+
+        runtime.disable_tracing();
+        run_test(
+            &runtime,
+            getChainIdCall::new(()).abi_encode(),
+            (42u64,).abi_encode(),
+        )
+        .unwrap();
+
+        assert_eq!(None, runtime.get_trace());
+    }
 }
 
 mod event {
-    use alloy_primitives::address;
+    use alloy_primitives::{address, keccak256};
 
     use crate::common::translate_test_package_with_framework;
 
@@ -178,8 +332,19 @@ mod event {
         function emitGenericEvent2(uint64 n, bool o, TestEvent1 p, TestEvent2 q) external;
     );
 
+    // The events are named after the structs, so they are declared apart
+    mod events {
+        alloy_sol_types::sol!(
+            #[derive(Debug, PartialEq)]
+            event TestEvent1(uint32 n);
+
+            #[derive(Debug, PartialEq)]
+            event TestEvent3(uint32 a, address b, uint128 c, uint8[] d);
+        );
+    }
+
     #[rstest]
-    #[case(emitTestEvent1Call::new((42,)), TestEvent1 { n: 42 })]
+    #[case(emitTestEvent1Call::new((42,)), TestEvent1 { n: 42 }, "TestEvent1(uint32)")]
     #[case(emitTestEvent2Call::new((
         42,
         address!("0xcafe000000000000000000000000000000007357"),
@@ -188,7 +353,7 @@ mod event {
         a: 42,
         b: address!("0xcafe000000000000000000000000000000007357"),
         c: u128::MAX,
-    })]
+    }, "TestEvent2(uint32,address,uint128)")]
     #[case(emitTestEvent3Call::new((
         42,
         address!("0xcafe000000000000000000000000000000007357"),
@@ -199,7 +364,7 @@ mod event {
         b: address!("0xcafe000000000000000000000000000000007357"),
         c: u128::MAX,
         d: vec![1, 2, 3, 4, 5],
-    })]
+    }, "TestEvent3(uint32,address,uint128,uint8[])")]
     #[case(emitTestEvent4Call::new((
         42,
         address!("0xcafe000000000000000000000000000000007357"),
@@ -220,7 +385,7 @@ mod event {
             b: address!("0xcafe000000000000000000000000000000007357"),
             c: u128::MAX,
         }
-    })]
+    }, "TestEvent4(uint32,address,uint128,uint8[],(uint32,address,uint128))")]
     #[case(emitGenericEvent1Call::new((
         vec![1, 2, 3, 4, 5], false, address!("0xcafe000000000000000000000000000000007357"), u128::MAX
     )), GenericEvent1 {
@@ -228,7 +393,7 @@ mod event {
         o: false,
         p: address!("0xcafe000000000000000000000000000000007357"),
         q: u128::MAX
-    })]
+    }, "TestEventGeneric(uint32[],bool,address,uint128)")]
     #[case(emitGenericEvent2Call::new((
         u64::MAX, true, TestEvent1 { n: 42 }, TestEvent2 { a: 42, b: address!("0xcafe000000000000000000000000000000007357"), c: u128::MAX }
     )), GenericEvent2 {
@@ -236,18 +401,1321 @@ mod event {
         o: true,
         p: TestEvent1 { n: 42 },
         q: TestEvent2 { a: 42, b: address!("0xcafe000000000000000000000000000000007357"), c: u128::MAX }
-    })]
+    }, "TestEventGeneric(uint64,bool,(uint32),(uint32,address,uint128))")]
     fn test_emit_event<T: SolCall, V: SolValue>(
         runtime: RuntimeSandbox,
         #[case] call_data: T,
         #[case] expected_result: V,
+        #[case] signature: &str,
     ) where
         for<'a> <V::SolType as SolType>::Token<'a>: TokenSeq<'a>,
     {
         let (result, _) = runtime.call_entrypoint(call_data.abi_encode()).unwrap();
         assert_eq!(result, 0, "Function returned non-zero exit code: {result}");
 
+        // The only topic is the hash of the event signature
         let event = runtime.log_events.lock().unwrap().recv().unwrap();
-        assert_eq!(event, expected_result.abi_encode());
+        assert_eq!(event.topics, vec![keccak256(signature).0]);
+        assert_eq!(event.data, expected_result.abi_encode());
+    }
+
+    #[rstest]
+    fn test_assert_emitted(runtime: RuntimeSandbox) {
+        let (result, _) = runtime
+            .call_entrypoint(emitTestEvent1Call::new((42,)).abi_encode())
+            .unwrap();
+        assert_eq!(0, result);
+
+        runtime.assert_emitted(&events::TestEvent1 { n: 42 });
+        runtime.assert_not_emitted::<events::TestEvent3>();
+
+        // The data of the events with dynamic fields starts with an offset
+        let b = address!("0xcafe000000000000000000000000000000007357");
+        let (result, _) = runtime
+            .call_entrypoint(emitTestEvent3Call::new((7, b, u128::MAX, vec![1, 2, 3])).abi_encode())
+            .unwrap();
+        assert_eq!(0, result);
+
+        assert_eq!(
+            vec![events::TestEvent3 {
+                a: 7,
+                b,
+                c: u128::MAX,
+                d: vec![1, 2, 3],
+            }],
+            runtime.get_events::<events::TestEvent3>()
+        );
+        assert_eq!(1, runtime.get_logs().len());
+    }
+
+    #[rstest]
+    #[should_panic(expected = "was not emitted")]
+    fn test_assert_emitted_fails(runtime: RuntimeSandbox) {
+        let (result, _) = runtime
+            .call_entrypoint(emitTestEvent1Call::new((42,)).abi_encode())
+            .unwrap();
+        assert_eq!(0, result);
+
+        runtime.assert_emitted(&events::TestEvent1 { n: 43 });
+    }
+}
+
+mod contract_calls {
+    use alloy_primitives::{Address, U256, address};
+
+    use move_stylus_testing::{
+        CallKind, ContractCreation, ExternalCall,
+        constants::{CONTRACT_ADDRESS, MSG_SENDER_ADDRESS, MSG_VALUE},
+    };
+
+    use crate::common::translate_test_package_with_framework;
+
+    use super::*;
+
+    #[fixture]
+    fn runtime() -> RuntimeSandbox {
+        const MODULE_NAME: &str = "contract_calls";
+        const SOURCE_PATH: &str = "tests/framework/contract_calls.move";
+
+        let mut translated_package =
+            translate_test_package_with_framework(SOURCE_PATH, MODULE_NAME);
+
+        RuntimeSandbox::new(&mut translated_package)
+    }
+
+    sol!(
+        #[allow(missing_docs)]
+        function callContract(address target, uint8[] data, uint256 value) external returns (bool, uint8[]);
+        function callContractTwice(address target, uint8[] first, uint8[] second) external returns (bool, uint8[]);
+        function delegateTo(address target, uint8[] data) external returns (bool, uint8[]);
+        function query(address target, uint8[] data) external returns (bool, uint8[]);
+        function deploy(uint8[] init_code, uint256 endowment) external returns (address, uint8[]);
+        function deployWithSalt(uint8[] init_code, uint256 endowment, uint256 salt) external returns (address, uint8[]);
+        function increment(uint64 n) external returns (uint64);
+        function currentSig() external returns (uint8[]);
+        function multicall(uint8[][] calls) external returns (uint8[][]);
+        function getSender() external returns (address);
+        function getBlockNumber() external returns (uint64);
+        function create() external;
+    );
+
+    const TARGET: Address = address!("0xcafe000000000000000000000000000000007357");
+    const CALLEE: Address = address!("0x00000000000000000000000000000000beef0001");
+
+    #[rstest]
+    #[case(true, vec![])]
+    #[case(true, vec![1, 2, 3, 4, 5])]
+    #[case(false, vec![0x08, 0xc3, 0x79, 0xa0])]
+    // Unpacked into a vector bigger than a memory page
+    #[case(true, (0..20_000).map(|i| i as u8).collect())]
+    fn test_external_call(
+        runtime: RuntimeSandbox,
+        #[case] success: bool,
+        #[case] return_data: Vec<u8>,
+    ) {
+        runtime.set_external_call_response(success, return_data.clone());
+
+        let calldata = vec![0xde, 0xad, 0xbe, 0xef, 0x00, 0x2a];
+        let value = U256::from(1_000_000_000_000_000_000u128);
+
+        // The contract pays the value it sends
+        let contract = CONTRACT_ADDRESS.parse::<Address>().unwrap();
+        runtime.set_balance(contract.into_array(), value);
+
+        run_test(
+            &runtime,
+            callContractCall::new((TARGET, calldata.clone(), value)).abi_encode(),
+            (success, return_data).abi_encode(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            vec![ExternalCall {
+                kind: CallKind::Call,
+                target: TARGET.into_array(),
+                calldata,
+                value: value.to_be_bytes(),
+            }],
+            runtime.get_external_calls()
+        );
+    }
+
+    #[rstest]
+    fn test_external_call_many_times(runtime: RuntimeSandbox) {
+        runtime.set_external_call_response(true, vec![7; 40]);
+
+        run_test(
+            &runtime,
+            callContractTwiceCall::new((TARGET, vec![1], vec![2, 3])).abi_encode(),
+            (true, vec![7u8; 40]).abi_encode(),
+        )
+        .unwrap();
+
+        let calls = runtime.get_external_calls();
+        assert_eq!(2, calls.len());
+        assert_eq!(vec![1], calls[0].calldata);
+        assert_eq!(vec![2, 3], calls[1].calldata);
+        assert_eq!([0; 32], calls[1].value);
+    }
+
+    #[rstest]
+    #[case(true, vec![0, 0, 0, 1])]
+    #[case(false, vec![0x08, 0xc3, 0x79, 0xa0])]
+    fn test_delegate_call(
+        runtime: RuntimeSandbox,
+        #[case] success: bool,
+        #[case] return_data: Vec<u8>,
+    ) {
+        runtime.set_external_call_response(success, return_data.clone());
+
+        let calldata = vec![0xde, 0xad, 0xbe, 0xef];
+
+        run_test(
+            &runtime,
+            delegateToCall::new((TARGET, calldata.clone())).abi_encode(),
+            (success, return_data).abi_encode(),
+        )
+        .unwrap();
+
+        // The delegated code runs with the value of the current call
+        assert_eq!(
+            vec![ExternalCall {
+                kind: CallKind::DelegateCall,
+                target: TARGET.into_array(),
+                calldata,
+                value: MSG_VALUE.to_be_bytes(),
+            }],
+            runtime.get_external_calls()
+        );
+    }
+
+    #[rstest]
+    #[case(true, vec![0, 0, 0, 0, 0, 0, 0, 42])]
+    #[case(false, vec![0x08, 0xc3, 0x79, 0xa0])]
+    fn test_static_call(
+        runtime: RuntimeSandbox,
+        #[case] success: bool,
+        #[case] return_data: Vec<u8>,
+    ) {
+        runtime.set_external_call_response(success, return_data.clone());
+
+        // balanceOf(address)
+        let calldata = vec![0x70, 0xa0, 0x82, 0x31];
+
+        // Reverts are returned to the caller instead of aborting
+        run_test(
+            &runtime,
+            queryCall::new((TARGET, calldata.clone())).abi_encode(),
+            (success, return_data).abi_encode(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            vec![ExternalCall {
+                kind: CallKind::StaticCall,
+                target: TARGET.into_array(),
+                calldata,
+                value: [0; 32],
+            }],
+            runtime.get_external_calls()
+        );
+    }
+
+    #[rstest]
+    // identity
+    #[case(address!("0x0000000000000000000000000000000000000004"), vec![1, 2, 3], true, vec![1, 2, 3])]
+    // modexp, 3 ^ 5 % 7
+    #[case(
+        address!("0x0000000000000000000000000000000000000005"),
+        modexp_input(&[3], &[5], &[7]),
+        true,
+        vec![5]
+    )]
+    // The result is as long as the modulus
+    #[case(
+        address!("0x0000000000000000000000000000000000000005"),
+        modexp_input(&[3], &[5], &[0, 0, 7]),
+        true,
+        vec![0, 0, 5]
+    )]
+    // The operands longer than 1024 bytes are rejected
+    #[case(
+        address!("0x0000000000000000000000000000000000000005"),
+        modexp_input(&[3], &[5], &[7; 1025]),
+        false,
+        vec![]
+    )]
+    fn test_static_call_precompile(
+        runtime: RuntimeSandbox,
+        #[case] precompile: Address,
+        #[case] calldata: Vec<u8>,
+        #[case] success: bool,
+        #[case] output: Vec<u8>,
+    ) {
+        run_test(
+            &runtime,
+            queryCall::new((precompile, calldata)).abi_encode(),
+            (success, output).abi_encode(),
+        )
+        .unwrap();
+
+        // The precompiles run in the sandbox instead of being sent as external calls
+        assert!(runtime.get_external_calls().is_empty());
+    }
+
+    /// Input of the modexp precompile: the lengths of the operands, followed by the operands.
+    fn modexp_input(base: &[u8], exponent: &[u8], modulus: &[u8]) -> Vec<u8> {
+        [base, exponent, modulus]
+            .iter()
+            .flat_map(|operand| U256::from(operand.len()).to_be_bytes::<32>())
+            .chain([base, exponent, modulus].concat())
+            .collect()
+    }
+
+    #[rstest]
+    #[case(Some(address!("0x1111000000000000000000000000000000002222")), vec![])]
+    #[case(None, vec![0x08, 0xc3, 0x79, 0xa0])]
+    fn test_create(
+        runtime: RuntimeSandbox,
+        #[case] contract: Option<Address>,
+        #[case] revert_data: Vec<u8>,
+    ) {
+        runtime.set_create_response(contract.map(Address::into_array), revert_data.clone());
+
+        let init_code = vec![0x60, 0x80, 0x60, 0x40, 0x52];
+        let endowment = U256::from(42);
+
+        // The contract pays the endowment
+        let contract_address = CONTRACT_ADDRESS.parse::<Address>().unwrap().into_array();
+        runtime.set_balance(contract_address, endowment);
+
+        run_test(
+            &runtime,
+            deployCall::new((init_code.clone(), endowment)).abi_encode(),
+            (contract.unwrap_or(Address::ZERO), revert_data).abi_encode(),
+        )
+        .unwrap();
+
+        // It is only moved to the new contract if the deployment succeeds
+        let deployed_balance = contract.map_or(U256::ZERO, |contract| {
+            runtime.get_balance(contract.into_array())
+        });
+        assert_eq!(
+            endowment,
+            runtime.get_balance(contract_address) + deployed_balance
+        );
+
+        assert_eq!(
+            vec![ContractCreation {
+                init_code,
+                endowment: endowment.to_be_bytes(),
+                salt: None,
+            }],
+            runtime.get_contract_creations()
+        );
+    }
+
+    #[rstest]
+    #[case(Some(address!("0x1111000000000000000000000000000000002222")), vec![])]
+    #[case(None, vec![0x08, 0xc3, 0x79, 0xa0])]
+    fn test_create2(
+        runtime: RuntimeSandbox,
+        #[case] contract: Option<Address>,
+        #[case] revert_data: Vec<u8>,
+    ) {
+        runtime.set_create_response(contract.map(Address::into_array), revert_data.clone());
+
+        let init_code = vec![0x60, 0x80, 0x60, 0x40, 0x52];
+        let endowment = U256::ZERO;
+        let salt = U256::from(0xcafe);
+
+        run_test(
+            &runtime,
+            deployWithSaltCall::new((init_code.clone(), endowment, salt)).abi_encode(),
+            (contract.unwrap_or(Address::ZERO), revert_data).abi_encode(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            vec![ContractCreation {
+                init_code,
+                endowment: endowment.to_be_bytes(),
+                salt: Some(salt.to_be_bytes()),
+            }],
+            runtime.get_contract_creations()
+        );
+    }
+
+    #[rstest]
+    fn test_self_call(runtime: RuntimeSandbox) {
+        let calls = vec![
+            incrementCall::new((41,)).abi_encode(),
+            incrementCall::new((u64::MAX - 1,)).abi_encode(),
+            // The called function sees its own calldata
+            currentSigCall::new(()).abi_encode(),
+        ];
+        let results = vec![
+            (42u64,).abi_encode(),
+            (u64::MAX,).abi_encode(),
+            (currentSigCall::SELECTOR.to_vec(),).abi_encode(),
+        ];
+
+        run_test(
+            &runtime,
+            multicallCall::new((calls,)).abi_encode(),
+            (results,).abi_encode(),
+        )
+        .unwrap();
+
+        // The calls are dispatched without leaving the program
+        assert!(runtime.get_external_calls().is_empty());
+    }
+
+    #[rstest]
+    #[case(incrementCall::new((u64::MAX,)).abi_encode())]
+    #[case(vec![0xde, 0xad, 0xbe, 0xef])]
+    #[case(vec![0x01])]
+    fn test_self_call_aborts(runtime: RuntimeSandbox, #[case] call: Vec<u8>) {
+        let calls = vec![incrementCall::new((1,)).abi_encode(), call];
+
+        assert!(
+            runtime
+                .call_entrypoint(multicallCall::new((calls,)).abi_encode())
+                .is_err()
+        );
+    }
+
+    #[rstest]
+    #[case(false, incrementCall::new((41,)).abi_encode(), true, (42u64,).abi_encode())]
+    #[case(true, incrementCall::new((41,)).abi_encode(), true, (42u64,).abi_encode())]
+    // The callee aborts
+    #[case(false, incrementCall::new((u64::MAX,)).abi_encode(), false, vec![])]
+    #[case(false, vec![0xde, 0xad, 0xbe, 0xef], false, vec![])]
+    fn test_call_registered_contract(
+        runtime: RuntimeSandbox,
+        #[from(runtime)] callee: RuntimeSandbox,
+        #[case] is_static: bool,
+        #[case] calldata: Vec<u8>,
+        #[case] success: bool,
+        #[case] return_data: Vec<u8>,
+    ) {
+        runtime.register_contract(CALLEE.into_array(), callee);
+
+        let call_data = if is_static {
+            queryCall::new((CALLEE, calldata)).abi_encode()
+        } else {
+            callContractCall::new((CALLEE, calldata, U256::ZERO)).abi_encode()
+        };
+
+        run_test(&runtime, call_data, (success, return_data).abi_encode()).unwrap();
+    }
+
+    #[rstest]
+    fn test_call_with_value(runtime: RuntimeSandbox) {
+        let contract = CONTRACT_ADDRESS.parse::<Address>().unwrap().into_array();
+        let value = U256::from(100);
+        runtime.set_balance(MSG_SENDER_ADDRESS, value);
+
+        // The value is returned if the call reverts
+        let call_data = incrementCall::new((u64::MAX,)).abi_encode();
+        assert!(
+            runtime
+                .call_entrypoint_with_value(call_data, value)
+                .is_err()
+        );
+        assert_eq!(value, runtime.get_balance(MSG_SENDER_ADDRESS));
+        assert_eq!(U256::ZERO, runtime.get_balance(contract));
+
+        let call_data = incrementCall::new((1,)).abi_encode();
+        let (result, _) = runtime
+            .call_entrypoint_with_value(call_data.clone(), value)
+            .unwrap();
+        assert_eq!(0, result);
+        assert_eq!(U256::ZERO, runtime.get_balance(MSG_SENDER_ADDRESS));
+        assert_eq!(value, runtime.get_balance(contract));
+
+        // The sender can't pay more than its balance
+        assert!(
+            runtime
+                .call_entrypoint_with_value(call_data, value)
+                .is_err()
+        );
+    }
+
+    #[rstest]
+    fn test_call_registered_contract_with_value(
+        runtime: RuntimeSandbox,
+        #[from(runtime)] callee: RuntimeSandbox,
+    ) {
+        let contract = CONTRACT_ADDRESS.parse::<Address>().unwrap().into_array();
+        let value = U256::from(100);
+        runtime.set_balance(contract, value);
+        runtime.register_contract(CALLEE.into_array(), callee);
+
+        // The value is only moved if the callee succeeds
+        run_test(
+            &runtime,
+            callContractCall::new((CALLEE, incrementCall::new((u64::MAX,)).abi_encode(), value))
+                .abi_encode(),
+            (false, Vec::<u8>::new()).abi_encode(),
+        )
+        .unwrap();
+
+        let callee = runtime.get_contract(CALLEE.into_array()).unwrap();
+        assert_eq!(value, runtime.get_balance(contract));
+        assert_eq!(U256::ZERO, callee.get_balance(CALLEE.into_array()));
+
+        run_test(
+            &runtime,
+            callContractCall::new((CALLEE, incrementCall::new((1,)).abi_encode(), value))
+                .abi_encode(),
+            (true, (2u64,).abi_encode()).abi_encode(),
+        )
+        .unwrap();
+
+        assert_eq!(U256::ZERO, runtime.get_balance(contract));
+        assert_eq!(value, runtime.get_balance(CALLEE.into_array()));
+        assert_eq!(value, callee.get_balance(CALLEE.into_array()));
+    }
+
+    #[rstest]
+    fn test_call_registered_contract_sender(runtime: RuntimeSandbox) {
+        let mut translated_package =
+            translate_test_package_with_framework("tests/framework/tx_context.move", "tx_context");
+        runtime.register_contract(
+            CALLEE.into_array(),
+            RuntimeSandbox::new(&mut translated_package),
+        );
+
+        // The callee is called by the contract, not by the sender of the transaction
+        let contract = CONTRACT_ADDRESS.parse::<Address>().unwrap();
+        run_test(
+            &runtime,
+            callContractCall::new((CALLEE, getSenderCall::new(()).abi_encode(), U256::ZERO))
+                .abi_encode(),
+            (true, (contract,).abi_encode()).abi_encode(),
+        )
+        .unwrap();
+    }
+
+    #[rstest]
+    fn test_call_registered_contract_block(runtime: RuntimeSandbox) {
+        let mut translated_package =
+            translate_test_package_with_framework("tests/framework/tx_context.move", "tx_context");
+        let callee = RuntimeSandbox::new(&mut translated_package);
+        callee.set_block_number(7);
+        runtime.register_contract(CALLEE.into_array(), callee);
+        runtime.set_block_number(100);
+
+        // The callee runs in the caller's block
+        run_test(
+            &runtime,
+            callContractCall::new((CALLEE, getBlockNumberCall::new(()).abi_encode(), U256::ZERO))
+                .abi_encode(),
+            (true, (100u64,).abi_encode()).abi_encode(),
+        )
+        .unwrap();
+
+        // And gets its own back once the call returns
+        let callee = runtime.get_contract(CALLEE.into_array()).unwrap();
+        run_test(
+            &callee,
+            getBlockNumberCall::new(()).abi_encode(),
+            (7u64,).abi_encode(),
+        )
+        .unwrap();
+    }
+
+    #[rstest]
+    fn test_static_call_registered_contract_writes(runtime: RuntimeSandbox) {
+        let mut translated_package =
+            translate_test_package_with_framework("tests/storage/counter.move", "counter");
+        runtime.register_contract(
+            CALLEE.into_array(),
+            RuntimeSandbox::new(&mut translated_package),
+        );
+
+        // The static call fails at the first write, which is reverted
+        run_test(
+            &runtime,
+            queryCall::new((CALLEE, createCall::new(()).abi_encode())).abi_encode(),
+            (false, Vec::<u8>::new()).abi_encode(),
+        )
+        .unwrap();
+
+        let callee = runtime.get_contract(CALLEE.into_array()).unwrap();
+        assert!(callee.get_storage().is_empty());
+
+        // The callee can be written by the calls that are not static afterwards
+        run_test(
+            &runtime,
+            callContractCall::new((CALLEE, createCall::new(()).abi_encode(), U256::ZERO))
+                .abi_encode(),
+            (true, Vec::<u8>::new()).abi_encode(),
+        )
+        .unwrap();
+        assert!(!callee.get_storage().is_empty());
+    }
+}
+
+mod math {
+    use alloy_primitives::U256;
+
+    use move_stylus_testing::{
+        call_result::CallResult,
+        fuzz::{fuzz_arguments, fuzz_entrypoint},
+    };
+
+    use crate::common::translate_test_package_with_framework;
+
+    use super::*;
+
+    #[fixture]
+    #[once]
+    fn runtime() -> RuntimeSandbox {
+        const MODULE_NAME: &str = "math";
+        const SOURCE_PATH: &str = "tests/framework/math.move";
+
+        let mut translated_package =
+            translate_test_package_with_framework(SOURCE_PATH, MODULE_NAME);
+
+        RuntimeSandbox::new(&mut translated_package)
+    }
+
+    sol!(
+        #[allow(missing_docs)]
+        function addMod(uint256 a, uint256 b, uint256 modulus) external returns (uint256);
+        function mulMod(uint256 a, uint256 b, uint256 modulus) external returns (uint256);
+        function wrappingPow(uint256 base, uint256 exponent) external returns (uint256);
+    );
+
+    #[rstest]
+    #[case(addModCall::new((U256::from(5), U256::from(9), U256::from(4))), (U256::from(2),))]
+    // The sum overflows an u256
+    #[case(addModCall::new((U256::MAX, U256::from(2), U256::from(10))), (U256::from(7),))]
+    #[case(mulModCall::new((U256::from(7), U256::from(9), U256::from(10))), (U256::from(3),))]
+    // The product overflows an u256
+    #[case(mulModCall::new((U256::MAX, U256::MAX, U256::from(12))), (U256::from(9),))]
+    #[case(wrappingPowCall::new((U256::from(3), U256::from(5))), (U256::from(243),))]
+    #[case(wrappingPowCall::new((U256::from(2), U256::from(255))), (U256::from(1) << 255,))]
+    #[case(wrappingPowCall::new((U256::from(2), U256::from(256))), (U256::ZERO,))]
+    fn test_math<T: SolCall, V: SolValue>(
+        #[by_ref] runtime: &RuntimeSandbox,
+        #[case] call_data: T,
+        #[case] expected_result: V,
+    ) where
+        for<'a> <V::SolType as SolType>::Token<'a>: TokenSeq<'a>,
+    {
+        run_test(
+            runtime,
+            call_data.abi_encode(),
+            expected_result.abi_encode(),
+        )
+        .unwrap();
+    }
+
+    #[rstest]
+    fn test_math_zero_modulus(#[by_ref] runtime: &RuntimeSandbox) {
+        // Remainders modulo zero abort
+        let call_data = addModCall::new((U256::from(1), U256::from(2), U256::ZERO)).abi_encode();
+        assert!(runtime.call_entrypoint(call_data).is_err());
+
+        let call_data = mulModCall::new((U256::from(1), U256::from(2), U256::ZERO)).abi_encode();
+        assert!(runtime.call_entrypoint(call_data).is_err());
+    }
+
+    #[rstest]
+    #[case("addMod(uint256,uint256,uint256)", Some(2))]
+    #[case("mulMod(uint256,uint256,uint256)", Some(2))]
+    #[case("wrappingPow(uint256,uint256)", None)]
+    fn test_math_fuzz(
+        #[by_ref] runtime: &RuntimeSandbox,
+        #[case] signature: &str,
+        #[case] modulus_index: Option<usize>,
+    ) {
+        // Deterministic inputs, so the test is reproducible
+        let mut seed = 0x2545_f491_4f6c_dd1du64;
+        for _ in 0..64 {
+            let input: Vec<u8> = (0..96)
+                .map(|_| {
+                    seed ^= seed << 13;
+                    seed ^= seed >> 7;
+                    seed ^= seed << 17;
+                    seed as u8
+                })
+                .collect();
+
+            // Only a zero modulus aborts
+            let args = fuzz_arguments(signature, &input).unwrap();
+            let zero_modulus = modulus_index
+                .is_some_and(|index| args[index].as_uint().is_some_and(|(v, _)| v.is_zero()));
+            match fuzz_entrypoint(runtime, signature, &input).unwrap() {
+                CallResult::Success(_) if !zero_modulus => {}
+                CallResult::Trap(_) if zero_modulus => {}
+                result => panic!("{signature} gave {result} for the arguments {args:?}"),
+            }
+        }
+    }
+}
+
+mod crypto {
+    use alloy_primitives::{Address, address, hex};
+
+    use crate::common::translate_test_package_with_framework;
+
+    use super::*;
+
+    #[fixture]
+    #[once]
+    fn runtime() -> RuntimeSandbox {
+        const MODULE_NAME: &str = "crypto";
+        const SOURCE_PATH: &str = "tests/framework/crypto.move";
+
+        let mut translated_package =
+            translate_test_package_with_framework(SOURCE_PATH, MODULE_NAME);
+
+        RuntimeSandbox::new(&mut translated_package)
+    }
+
+    sol!(
+        #[allow(missing_docs)]
+        function recover(uint8[] hash, uint8 v, uint8[] r, uint8[] s) external returns (address);
+        function sha256(uint8[] data) external returns (uint8[]);
+        function ripemd160(uint8[] data) external returns (uint8[]);
+    );
+
+    const HASH: [u8; 32] = hex!("38d18acb67d25c8bb9942764b62f18e17054f66a817bd4295423adf9ed98873e");
+    const R: [u8; 32] = hex!("38d18acb67d25c8bb9942764b62f18e17054f66a817bd4295423adf9ed98873e");
+    const S: [u8; 32] = hex!("789d1dd423d25f0772d2748d60f7e4b81bb14d086eba8e8e8efb6dcff8a4ae02");
+    const SIGNER: Address = address!("ceaccac640adf55b2028469bd36ba501f28b699d");
+
+    #[rstest]
+    #[case(HASH, 27, R, S, SIGNER)]
+    // Invalid signatures recover the zero address
+    #[case(HASH, 29, R, S, Address::ZERO)]
+    #[case(HASH, 27, [0; 32], S, Address::ZERO)]
+    #[case(HASH, 27, R, [0; 32], Address::ZERO)]
+    fn test_ecrecover(
+        #[by_ref] runtime: &RuntimeSandbox,
+        #[case] hash: [u8; 32],
+        #[case] v: u8,
+        #[case] r: [u8; 32],
+        #[case] s: [u8; 32],
+        #[case] expected_result: Address,
+    ) {
+        let call_data = recoverCall::new((hash.to_vec(), v, r.to_vec(), s.to_vec())).abi_encode();
+        run_test(runtime, call_data, expected_result.abi_encode()).unwrap();
+    }
+
+    #[rstest]
+    fn test_ecrecover_invalid_length(#[by_ref] runtime: &RuntimeSandbox) {
+        // Hashes and signature components shorter than 32 bytes abort
+        let call_data =
+            recoverCall::new((HASH[..31].to_vec(), 27, R.to_vec(), S.to_vec())).abi_encode();
+        assert!(runtime.call_entrypoint(call_data).is_err());
+    }
+
+    #[rstest]
+    #[case(
+        sha256Call::new((b"".to_vec(),)),
+        (hex!("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855").to_vec(),)
+    )]
+    #[case(
+        sha256Call::new((b"abc".to_vec(),)),
+        (hex!("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad").to_vec(),)
+    )]
+    #[case(
+        ripemd160Call::new((b"".to_vec(),)),
+        (hex!("9c1185a5c5e9fc54612808977ee8f548b2258d31").to_vec(),)
+    )]
+    #[case(
+        ripemd160Call::new((b"abc".to_vec(),)),
+        (hex!("8eb208f7e05d987a9b044a8e98c6b087f15a0bfc").to_vec(),)
+    )]
+    fn test_hash<T: SolCall, V: SolValue>(
+        #[by_ref] runtime: &RuntimeSandbox,
+        #[case] call_data: T,
+        #[case] expected_result: V,
+    ) where
+        for<'a> <V::SolType as SolType>::Token<'a>: TokenSeq<'a>,
+    {
+        run_test(
+            runtime,
+            call_data.abi_encode(),
+            expected_result.abi_encode(),
+        )
+        .unwrap();
+    }
+}
+
+mod hash {
+    use alloy_primitives::keccak256;
+
+    use crate::common::translate_test_package_with_framework;
+
+    use super::*;
+
+    #[fixture]
+    #[once]
+    fn runtime() -> RuntimeSandbox {
+        const MODULE_NAME: &str = "hash";
+        const SOURCE_PATH: &str = "tests/framework/hash.move";
+
+        let mut translated_package =
+            translate_test_package_with_framework(SOURCE_PATH, MODULE_NAME);
+
+        RuntimeSandbox::new(&mut translated_package)
+    }
+
+    sol!(
+        #[allow(missing_docs)]
+        function keccak256(uint8[] data) external returns (uint8[]);
+    );
+
+    #[rstest]
+    #[case(vec![])]
+    #[case(b"abc".to_vec())]
+    // Packed from a vector bigger than a memory page
+    #[case((0..20_000).map(|i| i as u8).collect())]
+    fn test_keccak256(#[by_ref] runtime: &RuntimeSandbox, #[case] data: Vec<u8>) {
+        let expected_result = keccak256(&data).to_vec();
+
+        run_test(
+            runtime,
+            keccak256Call::new((data,)).abi_encode(),
+            (expected_result,).abi_encode(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_keccak256_stub() {
+        let mut translated_package =
+            translate_test_package_with_framework("tests/framework/hash.move", "hash");
+
+        // native_keccak256(input_ptr, input_len, output_ptr)
+        let runtime = RuntimeSandbox::builder(&mut translated_package)
+            .override_hook("native_keccak256", |call| {
+                let input = call.read_memory(call.param_u32(0), call.param_u32(1) as usize);
+                let mut hash = [0xaa; 32];
+                hash[31] = input.len() as u8;
+                call.write_memory(call.param_u32(2), &hash);
+                Ok(())
+            })
+            .build();
+
+        let mut expected_result = vec![0xaa; 32];
+        expected_result[31] = 3;
+        run_test(
+            &runtime,
+            keccak256Call::new((b"abc".to_vec(),)).abi_encode(),
+            (expected_result,).abi_encode(),
+        )
+        .unwrap();
+    }
+}
+
+mod msg {
+    use crate::common::translate_test_package_with_framework;
+
+    use super::*;
+
+    #[fixture]
+    #[once]
+    fn runtime() -> RuntimeSandbox {
+        const MODULE_NAME: &str = "msg";
+        const SOURCE_PATH: &str = "tests/framework/msg.move";
+
+        let mut translated_package =
+            translate_test_package_with_framework(SOURCE_PATH, MODULE_NAME);
+
+        RuntimeSandbox::new(&mut translated_package)
+    }
+
+    sol!(
+        #[allow(missing_docs)]
+        function data(uint64 a, uint8[] b) external returns (uint8[]);
+        function sig() external returns (uint8[]);
+    );
+
+    #[rstest]
+    #[case(42, vec![])]
+    #[case(u64::MAX, vec![1, 2, 3, 4, 5])]
+    fn test_msg_data(#[by_ref] runtime: &RuntimeSandbox, #[case] a: u64, #[case] b: Vec<u8>) {
+        let call_data = dataCall::new((a, b)).abi_encode();
+
+        run_test(runtime, call_data.clone(), (call_data,).abi_encode()).unwrap();
+    }
+
+    #[rstest]
+    fn test_msg_sig(#[by_ref] runtime: &RuntimeSandbox) {
+        run_test(
+            runtime,
+            sigCall::new(()).abi_encode(),
+            (sigCall::SELECTOR.to_vec(),).abi_encode(),
+        )
+        .unwrap();
+    }
+}
+
+mod debug {
+    use alloy_primitives::{U256, address};
+    use move_bytecode_to_wasm::TranslationOptions;
+
+    use crate::common::translate_test_package_with_framework_and_options;
+
+    use super::*;
+
+    const MODULE_NAME: &str = "debug";
+    const SOURCE_PATH: &str = "tests/framework/debug.move";
+
+    #[fixture]
+    fn runtime() -> RuntimeSandbox {
+        let options = TranslationOptions {
+            debug: true,
+            ..Default::default()
+        };
+
+        let mut translated_package =
+            translate_test_package_with_framework_and_options(SOURCE_PATH, MODULE_NAME, &options);
+
+        RuntimeSandbox::new(&mut translated_package)
+    }
+
+    sol!(
+        #[allow(missing_docs)]
+        function logValues(bool flag, uint8 small, uint64 count, uint256 amount, address owner) external;
+        function logBalance(uint128 balance) external returns (uint128);
+        function checkCount(uint64 count) external;
+    );
+
+    #[rstest]
+    #[case(true, 7, 1_234_567_890, U256::from(0x1234), vec![
+        "flag: true".to_owned(),
+        "small: 7".to_owned(),
+        "count: 1234567890".to_owned(),
+        format!("amount: 0x{:064x}", 0x1234),
+        "owner: 0xcafe000000000000000000000000000000007357".to_owned(),
+    ])]
+    #[case(false, 0, u64::MAX, U256::MAX, vec![
+        "flag: false".to_owned(),
+        "small: 0".to_owned(),
+        "count: 18446744073709551615".to_owned(),
+        format!("amount: 0x{}", "f".repeat(64)),
+        "owner: 0xcafe000000000000000000000000000000007357".to_owned(),
+    ])]
+    fn test_log(
+        runtime: RuntimeSandbox,
+        #[case] flag: bool,
+        #[case] small: u8,
+        #[case] count: u64,
+        #[case] amount: U256,
+        #[case] expected_logs: Vec<String>,
+    ) {
+        let owner = address!("0xcafe000000000000000000000000000000007357");
+
+        run_test(
+            &runtime,
+            logValuesCall::new((flag, small, count, amount, owner)).abi_encode(),
+            vec![],
+        )
+        .unwrap();
+
+        assert_eq!(expected_logs, runtime.get_debug_logs());
+    }
+
+    #[rstest]
+    fn test_log_u128(runtime: RuntimeSandbox) {
+        let balance = 0x0102_0304_0506_0708_090a_0b0c_0d0e_0f10u128;
+
+        run_test(
+            &runtime,
+            logBalanceCall::new((balance,)).abi_encode(),
+            (balance,).abi_encode(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            vec!["balance: 0x0102030405060708090a0b0c0d0e0f10".to_owned()],
+            runtime.get_debug_logs()
+        );
+    }
+
+    #[rstest]
+    fn test_log_abort(runtime: RuntimeSandbox) {
+        run_test(&runtime, checkCountCall::new((1,)).abi_encode(), vec![]).unwrap();
+        assert!(runtime.get_debug_logs().is_empty());
+
+        assert!(run_test(&runtime, checkCountCall::new((10,)).abi_encode(), vec![]).is_err());
+
+        let logs = runtime.get_debug_logs();
+        assert_eq!(1, logs.len());
+        assert!(logs[0].starts_with("abort in "));
+        assert!(logs[0].ends_with("::debug::check_count: 7"));
+    }
+
+    #[rstest]
+    fn test_log_without_debug() {
+        let mut translated_package = translate_test_package_with_framework_and_options(
+            SOURCE_PATH,
+            MODULE_NAME,
+            &TranslationOptions::default(),
+        );
+
+        // The console is not imported, so the module can be deployed to any node
+        assert!(
+            !translated_package
+                .imports
+                .iter()
+                .any(|import| import.module == "console")
+        );
+
+        let runtime = RuntimeSandbox::new(&mut translated_package);
+        let balance = 42u128;
+
+        run_test(
+            &runtime,
+            logBalanceCall::new((balance,)).abi_encode(),
+            (balance,).abi_encode(),
+        )
+        .unwrap();
+
+        assert!(runtime.get_debug_logs().is_empty());
+    }
+}
+
+mod evm {
+    use alloy_primitives::{U256, address, keccak256};
+    use alloy_sol_types::{Panic, PanicKind, Revert, SolError};
+
+    use move_stylus_testing::{
+        CallKind, ExternalCall,
+        call_result::{CallResult, RevertReason},
+    };
+
+    use crate::common::translate_test_package_with_framework;
+
+    use super::*;
+
+    #[fixture]
+    fn runtime() -> RuntimeSandbox {
+        const MODULE_NAME: &str = "evm";
+        const SOURCE_PATH: &str = "tests/framework/evm.move";
+
+        let mut translated_package =
+            translate_test_package_with_framework(SOURCE_PATH, MODULE_NAME);
+
+        RuntimeSandbox::new(&mut translated_package)
+    }
+
+    sol!(
+        #[allow(missing_docs)]
+        function balanceOf(address account) external returns (uint256);
+        function canPay(address account, uint256 amount) external returns (bool);
+        function codeOf(address account) external returns (uint8[]);
+        function codeSizeOf(address account) external returns (uint64);
+        function codeHashOf(address account) external returns (uint8[]);
+        function isContract(address account) external returns (bool);
+        function selfAddress() external returns (address);
+        function selfBalance() external returns (uint256);
+        function inkLeft() external returns (uint64);
+        function blockHash(uint64 n) external returns (uint8[]);
+        function pay(address to, uint256 amount) external;
+        function processBatch(uint64 count, uint64 min_gas) external returns (uint64);
+    );
+
+    #[rstest]
+    fn test_account_balance(runtime: RuntimeSandbox) {
+        let account = address!("0xcafe000000000000000000000000000000007357");
+        let empty_account = address!("0x1111000000000000000000000000000000002222");
+        let balance = U256::from(1_500_000_000_000_000_000u128);
+
+        runtime.set_balance(account.into_array(), balance);
+
+        run_test(
+            &runtime,
+            balanceOfCall::new((account,)).abi_encode(),
+            (balance,).abi_encode(),
+        )
+        .unwrap();
+
+        run_test(
+            &runtime,
+            balanceOfCall::new((empty_account,)).abi_encode(),
+            (U256::ZERO,).abi_encode(),
+        )
+        .unwrap();
+
+        run_test(
+            &runtime,
+            canPayCall::new((account, balance)).abi_encode(),
+            (true,).abi_encode(),
+        )
+        .unwrap();
+
+        run_test(
+            &runtime,
+            canPayCall::new((account, balance + U256::from(1))).abi_encode(),
+            (false,).abi_encode(),
+        )
+        .unwrap();
+    }
+
+    #[rstest]
+    fn test_account_code(runtime: RuntimeSandbox) {
+        let contract = address!("0xcafe000000000000000000000000000000007357");
+        let account = address!("0x1111000000000000000000000000000000002222");
+        // Minimal proxy (EIP-1167) runtime code prefix
+        let code = vec![0x36, 0x3d, 0x3d, 0x37, 0x3d, 0x3d, 0x3d, 0x36, 0x3d, 0x73];
+
+        runtime.set_code(contract.into_array(), code.clone());
+
+        run_test(
+            &runtime,
+            codeOfCall::new((contract,)).abi_encode(),
+            (code.clone(),).abi_encode(),
+        )
+        .unwrap();
+
+        run_test(
+            &runtime,
+            codeSizeOfCall::new((contract,)).abi_encode(),
+            (code.len() as u64,).abi_encode(),
+        )
+        .unwrap();
+
+        run_test(
+            &runtime,
+            codeHashOfCall::new((contract,)).abi_encode(),
+            (keccak256(&code).to_vec(),).abi_encode(),
+        )
+        .unwrap();
+
+        run_test(
+            &runtime,
+            isContractCall::new((contract,)).abi_encode(),
+            (true,).abi_encode(),
+        )
+        .unwrap();
+
+        // Accounts without code
+        run_test(
+            &runtime,
+            codeOfCall::new((account,)).abi_encode(),
+            (Vec::<u8>::new(),).abi_encode(),
+        )
+        .unwrap();
+
+        run_test(
+            &runtime,
+            codeHashOfCall::new((account,)).abi_encode(),
+            (keccak256(b"").to_vec(),).abi_encode(),
+        )
+        .unwrap();
+
+        run_test(
+            &runtime,
+            isContractCall::new((account,)).abi_encode(),
+            (false,).abi_encode(),
+        )
+        .unwrap();
+    }
+
+    #[rstest]
+    fn test_self_address(runtime: RuntimeSandbox) {
+        let contract = address!("0xcafe000000000000000000000000000000007357");
+        let balance = U256::from(42);
+
+        runtime.set_balance(contract.into_array(), balance);
+
+        run_test(
+            &runtime,
+            selfAddressCall::new(()).abi_encode(),
+            (contract,).abi_encode(),
+        )
+        .unwrap();
+
+        run_test(
+            &runtime,
+            selfBalanceCall::new(()).abi_encode(),
+            (balance,).abi_encode(),
+        )
+        .unwrap();
+    }
+
+    #[rstest]
+    fn test_transfer_eth(runtime: RuntimeSandbox) {
+        let contract = address!("0xcafe000000000000000000000000000000007357");
+        let recipient = address!("0x1111000000000000000000000000000000002222");
+
+        runtime.set_balance(contract.into_array(), U256::from(100));
+
+        run_test(
+            &runtime,
+            payCall::new((recipient, U256::from(40))).abi_encode(),
+            vec![],
+        )
+        .unwrap();
+
+        assert_eq!(
+            vec![ExternalCall {
+                kind: CallKind::Call,
+                target: recipient.into_array(),
+                calldata: vec![],
+                value: U256::from(40).to_be_bytes(),
+            }],
+            runtime.get_external_calls()
+        );
+        assert_eq!(U256::from(60), runtime.get_balance(contract.into_array()));
+        assert_eq!(U256::from(40), runtime.get_balance(recipient.into_array()));
+
+        // Not enough balance
+        let (result, return_data) = runtime
+            .call_entrypoint(payCall::new((recipient, U256::from(61))).abi_encode())
+            .unwrap();
+        assert_eq!(1, result);
+        assert!(return_data.is_empty());
+        assert_eq!(U256::from(60), runtime.get_balance(contract.into_array()));
+
+        // The revert data of the recipient is propagated
+        let revert_data = vec![0x08, 0xc3, 0x79, 0xa0, 0x01, 0x02];
+        runtime.set_external_call_response(false, revert_data.clone());
+
+        let (result, return_data) = runtime
+            .call_entrypoint(payCall::new((recipient, U256::from(10))).abi_encode())
+            .unwrap();
+        assert_eq!(1, result);
+        assert_eq!(revert_data, return_data);
+        assert_eq!(U256::from(60), runtime.get_balance(contract.into_array()));
+        assert_eq!(U256::from(40), runtime.get_balance(recipient.into_array()));
+    }
+
+    sol!(
+        #[derive(Debug, PartialEq)]
+        error NotAllowed(address account);
+    );
+
+    #[rstest]
+    #[case(
+        Revert::from("not enough funds").abi_encode(),
+        RevertReason::Error("not enough funds".to_owned())
+    )]
+    #[case(
+        Panic::from(PanicKind::UnderOverflow).abi_encode(),
+        RevertReason::Panic(U256::from(0x11))
+    )]
+    #[case(
+        NotAllowed { account: address!("0x1111000000000000000000000000000000002222") }.abi_encode(),
+        RevertReason::Custom {
+            selector: NotAllowed::SELECTOR,
+            data: address!("0x1111000000000000000000000000000000002222").abi_encode(),
+        }
+    )]
+    #[case(vec![], RevertReason::Raw(vec![]))]
+    fn test_revert_reason(
+        runtime: RuntimeSandbox,
+        #[case] revert_data: Vec<u8>,
+        #[case] expected_reason: RevertReason,
+    ) {
+        let contract = address!("0xcafe000000000000000000000000000000007357");
+        let recipient = address!("0x1111000000000000000000000000000000002222");
+
+        runtime.set_balance(contract.into_array(), U256::from(100));
+        runtime.set_external_call_response(false, revert_data);
+
+        let result = runtime.call(payCall::new((recipient, U256::from(10))).abi_encode());
+        assert_eq!(Some(&expected_reason), result.revert_reason());
+
+        if let RevertReason::Custom { .. } = expected_reason {
+            assert_eq!(
+                Some(NotAllowed { account: recipient }),
+                expected_reason.custom::<NotAllowed>()
+            );
+        }
+    }
+
+    #[rstest]
+    fn test_revert_reason_trap(runtime: RuntimeSandbox) {
+        // Calldata without a selector aborts the execution
+        let result = runtime.call(vec![0xde]);
+        assert!(matches!(result, CallResult::Trap(_)));
+    }
+
+    #[rstest]
+    fn test_gas_left(runtime: RuntimeSandbox) {
+        runtime.set_gas_left(100_000);
+
+        // Every query consumes 5000 gas, so it is above 50000 for the first 9 of them
+        run_test(
+            &runtime,
+            processBatchCall::new((100, 50_000)).abi_encode(),
+            (9u64,).abi_encode(),
+        )
+        .unwrap();
+
+        run_test(
+            &runtime,
+            processBatchCall::new((3, 50_000)).abi_encode(),
+            (3u64,).abi_encode(),
+        )
+        .unwrap();
+
+        run_test(
+            &runtime,
+            inkLeftCall::new(()).abi_encode(),
+            (950_000_000u64,).abi_encode(),
+        )
+        .unwrap();
+    }
+
+    #[rstest]
+    fn test_block_hash(runtime: RuntimeSandbox) {
+        let hash = keccak256(b"block 1000");
+        runtime.set_block_hash(1000, hash.0);
+
+        run_test(
+            &runtime,
+            blockHashCall::new((1000,)).abi_encode(),
+            (hash.to_vec(),).abi_encode(),
+        )
+        .unwrap();
+
+        // ArbSys reverts for the blocks without a hash, and the zero hash is returned
+        run_test(
+            &runtime,
+            blockHashCall::new((1,)).abi_encode(),
+            (vec![0u8; 32],).abi_encode(),
+        )
+        .unwrap();
+
+        // Queries to ArbSys are not recorded as calls to other contracts
+        assert!(runtime.get_external_calls().is_empty());
+    }
+
+    #[rstest]
+    fn test_block_hash_seed(runtime: RuntimeSandbox) {
+        let seed = keccak256(b"seed");
+        runtime.set_block_hash_seed(seed.0);
+        runtime.set_block_number(1000);
+
+        let explicit_hash = keccak256(b"block 999");
+        runtime.set_block_hash(999, explicit_hash.0);
+
+        let block_hash = |number: u64| {
+            let (result, return_data) = runtime
+                .call_entrypoint(blockHashCall::new((number,)).abi_encode())
+                .unwrap();
+            assert_eq!(0, result);
+            blockHashCall::abi_decode_returns(&return_data).unwrap()
+        };
+
+        // The recent blocks have the hash of the seed and their number
+        let seeded_hash = keccak256([&seed[..], &744u64.to_be_bytes()].concat());
+        assert_eq!(seeded_hash.to_vec(), block_hash(744));
+        assert_eq!(explicit_hash.to_vec(), block_hash(999));
+
+        // The current block and the ones older than the 256 most recent have no hash
+        assert_eq!(vec![0u8; 32], block_hash(1000));
+        assert_eq!(vec![0u8; 32], block_hash(743));
     }
 }
@@ -0,0 +1,49 @@
+module test::contract_calls;
+
+use stylus::contract_calls::{create, create2, delegate_call, external_call, self_call, static_call};
+use stylus::msg;
+
+public fun call_contract(target: address, calldata: vector<u8>, value: u256): (bool, vector<u8>) {
+    external_call(target, calldata, value)
+}
+
+public fun call_contract_twice(target: address, first: vector<u8>, second: vector<u8>): (bool, vector<u8>) {
+    let (success, _) = external_call(target, first, 0);
+    assert!(success, 1);
+    external_call(target, second, 0)
+}
+
+public fun delegate_to(target: address, calldata: vector<u8>): (bool, vector<u8>) {
+    delegate_call(target, calldata)
+}
+
+#[ext(view)]
+public fun query(target: address, calldata: vector<u8>): (bool, vector<u8>) {
+    static_call(target, calldata)
+}
+
+public fun deploy(init_code: vector<u8>, endowment: u256): (address, vector<u8>) {
+    create(init_code, endowment)
+}
+
+public fun deploy_with_salt(init_code: vector<u8>, endowment: u256, salt: u256): (address, vector<u8>) {
+    create2(init_code, endowment, salt)
+}
+
+public fun increment(n: u64): u64 {
+    n + 1
+}
+
+public fun current_sig(): vector<u8> {
+    msg::sig()
+}
+
+public fun multicall(calls: vector<vector<u8>>): vector<vector<u8>> {
+    let mut results = vector[];
+    let mut i = 0;
+    while (i < calls.length()) {
+        results.push_back(self_call(calls[i]));
+        i = i + 1;
+    };
+    results
+}
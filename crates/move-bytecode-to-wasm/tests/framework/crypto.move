@@ -0,0 +1,15 @@
+module test::crypto;
+
+use stylus::crypto;
+
+public fun recover(hash: vector<u8>, v: u8, r: vector<u8>, s: vector<u8>): address {
+    crypto::ecrecover(hash, v, r, s)
+}
+
+public fun sha256(data: vector<u8>): vector<u8> {
+    crypto::sha256(data)
+}
+
+public fun ripemd160(data: vector<u8>): vector<u8> {
+    crypto::ripemd160(data)
+}
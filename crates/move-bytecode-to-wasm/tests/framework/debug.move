@@ -0,0 +1,21 @@
+module test::debug;
+
+use std::string::utf8;
+use stylus::debug;
+
+public fun log_values(flag: bool, small: u8, count: u64, amount: u256, owner: address) {
+    debug::log(utf8(b"flag"), flag);
+    debug::log(utf8(b"small"), small);
+    debug::log(utf8(b"count"), count);
+    debug::log(utf8(b"amount"), amount);
+    debug::log(utf8(b"owner"), owner);
+}
+
+public fun log_balance(balance: u128): u128 {
+    debug::log(utf8(b"balance"), balance);
+    balance
+}
+
+public fun check_count(count: u64) {
+    assert!(count < 10, 7);
+}
@@ -0,0 +1,57 @@
+module test::evm;
+
+use stylus::evm;
+
+public fun balance_of(account: address): u256 {
+    evm::balance(account)
+}
+
+public fun can_pay(account: address, amount: u256): bool {
+    evm::balance(account) >= amount
+}
+
+public fun code_of(account: address): vector<u8> {
+    evm::code(account)
+}
+
+public fun code_size_of(account: address): u64 {
+    evm::code_size(account)
+}
+
+public fun code_hash_of(account: address): vector<u8> {
+    evm::code_hash(account)
+}
+
+public fun is_contract(account: address): bool {
+    evm::is_contract(account)
+}
+
+public fun self_address(): address {
+    evm::self_address()
+}
+
+public fun self_balance(): u256 {
+    evm::balance(evm::self_address())
+}
+
+public fun pay(to: address, amount: u256) {
+    evm::transfer_eth(to, amount)
+}
+
+public fun block_hash(n: u64): vector<u8> {
+    evm::block_hash(n)
+}
+
+public fun ink_left(): u64 {
+    evm::ink_left()
+}
+
+/// Processes up to `count` items, stopping once the gas left is not bigger than `min_gas`.
+/// Returns the number of items processed.
+public fun process_batch(count: u64, min_gas: u64): u64 {
+    let mut processed = 0;
+    while (processed < count && evm::gas_left() > min_gas) {
+        processed = processed + 1;
+    };
+    processed
+}
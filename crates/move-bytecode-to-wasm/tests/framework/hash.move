@@ -0,0 +1,7 @@
+module test::hash;
+
+use stylus::hash;
+
+public fun keccak256(data: vector<u8>): vector<u8> {
+    hash::keccak256(data)
+}
@@ -0,0 +1,15 @@
+module test::math;
+
+use stylus::math;
+
+public fun add_mod(a: u256, b: u256, modulus: u256): u256 {
+    math::add_mod(a, b, modulus)
+}
+
+public fun mul_mod(a: u256, b: u256, modulus: u256): u256 {
+    math::mul_mod(a, b, modulus)
+}
+
+public fun wrapping_pow(base: u256, exponent: u256): u256 {
+    math::wrapping_pow(base, exponent)
+}
@@ -0,0 +1,11 @@
+module test::msg;
+
+use stylus::msg;
+
+public fun data(_a: u64, _b: vector<u8>): vector<u8> {
+    msg::data()
+}
+
+public fun sig(): vector<u8> {
+    msg::sig()
+}
@@ -10,6 +10,10 @@ public fun get_msg_value(ctx: &TxContext): u256 {
     ctx.msg_value()
 }
 
+public fun is_reentrant(ctx: &TxContext): bool {
+    ctx.reentrant()
+}
+
 public fun get_block_number(ctx: &TxContext): u64 {
     ctx.block_number()
 }
@@ -18,6 +22,10 @@ public fun get_block_basefee(ctx: &TxContext): u256 {
     ctx.block_basefee()
 }
 
+public fun get_block_coinbase(ctx: &TxContext): address {
+    ctx.block_coinbase()
+}
+
 public fun get_block_gas_limit(ctx: &TxContext): u64 {
     ctx.block_gas_limit()
 }
@@ -34,6 +42,18 @@ public fun get_gas_price(ctx: &TxContext): u256 {
     ctx.gas_price()
 }
 
+public fun get_ink_price(ctx: &TxContext): u32 {
+    ctx.ink_price()
+}
+
+public fun ink_to_gas(ctx: &TxContext, ink: u64): u64 {
+    ctx.ink_to_gas(ink)
+}
+
+public fun gas_to_ink(ctx: &TxContext, gas: u64): u64 {
+    ctx.gas_to_ink(gas)
+}
+
 public fun get_fresh_object_address(ctx: &mut TxContext): (address, address, address) {
     (
         ctx.fresh_object_address(),
@@ -0,0 +1,13 @@
+mod common;
+
+use common::{assert_wat_snapshot, translate_test_package};
+use rstest::rstest;
+
+#[rstest]
+#[case("tests/primitives/bool.move", "bool_type")]
+#[case("tests/primitives/signer.move", "signer_type")]
+#[case("tests/control-flow/control_flow_u8.move", "control_flow_u8")]
+fn test_golden_wat(#[case] source_path: &str, #[case] module_name: &str) {
+    let mut translated_package = translate_test_package(source_path, module_name);
+    assert_wat_snapshot(&mut translated_package, module_name);
+}
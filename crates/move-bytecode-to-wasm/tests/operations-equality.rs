@@ -1,7 +1,8 @@
 use alloy_primitives::{U256, address};
 use alloy_sol_types::{SolCall, SolType, sol};
 use anyhow::Result;
-use common::{runtime_sandbox::RuntimeSandbox, translate_test_package};
+use common::translate_test_package;
+use move_stylus_testing::RuntimeSandbox;
 use rstest::{fixture, rstest};
 
 mod common;
@@ -1,7 +1,8 @@
 use alloy_primitives::{U256, address};
 use alloy_sol_types::{SolCall, SolType, SolValue, abi::TokenSeq, sol};
 use anyhow::Result;
-use common::{runtime_sandbox::RuntimeSandbox, translate_test_package};
+use common::translate_test_package;
+use move_stylus_testing::RuntimeSandbox;
 use rstest::{fixture, rstest};
 
 mod common;
@@ -1293,6 +1294,79 @@ mod uint_256 {
     }
 }
 
+mod uint_256_math_hooks {
+    use move_bytecode_to_wasm::TranslationOptions;
+
+    use crate::common::translate_test_package_with_framework_and_options;
+
+    use super::*;
+
+    const MODULE_NAME: &str = "uint_256";
+    const SOURCE_PATH: &str = "tests/primitives/uint_256.move";
+
+    #[fixture]
+    #[once]
+    fn runtime() -> RuntimeSandbox {
+        let options = TranslationOptions {
+            math_hooks: true,
+            ..Default::default()
+        };
+
+        let mut translated_package =
+            translate_test_package_with_framework_and_options(SOURCE_PATH, MODULE_NAME, &options);
+        RuntimeSandbox::new(&mut translated_package)
+    }
+
+    sol!(
+        #[allow(missing_docs)]
+        function div(uint256 x, uint256 y) external returns (uint256);
+        function mod(uint256 x, uint256 y) external returns (uint256);
+    );
+
+    #[rstest]
+    #[case(U256::from(350), U256::from(13), U256::from(26))]
+    #[case(U256::from(0), U256::from(2), U256::from(0))]
+    #[case(U256::MAX, U256::from(1), U256::MAX)]
+    #[case(U256::MAX, U256::MAX, U256::from(1))]
+    #[case(U256::MAX, U256::from(u128::MAX), U256::from(u128::MAX) + U256::from(2))]
+    #[should_panic(expected = "wasm trap: integer divide by zero")]
+    #[case(U256::from(10), U256::from(0), U256::from(0))]
+    fn test_uint_256_div(
+        #[by_ref] runtime: &RuntimeSandbox,
+        #[case] dividend: U256,
+        #[case] divisor: U256,
+        #[case] expected_result: U256,
+    ) {
+        run_test(
+            runtime,
+            divCall::new((dividend, divisor)).abi_encode(),
+            (expected_result,).abi_encode(),
+        )
+        .unwrap();
+    }
+
+    #[rstest]
+    #[case(U256::from(350), U256::from(13), U256::from(12))]
+    #[case(U256::from(0), U256::from(2), U256::from(0))]
+    #[case(U256::MAX, U256::from(u128::MAX) + U256::from(1), U256::from(u128::MAX))]
+    #[case(U256::MAX - U256::from(1), U256::MAX, U256::MAX - U256::from(1))]
+    #[should_panic(expected = "wasm trap: integer divide by zero")]
+    #[case(U256::from(10), U256::from(0), U256::from(0))]
+    fn test_uint_256_mod(
+        #[by_ref] runtime: &RuntimeSandbox,
+        #[case] dividend: U256,
+        #[case] divisor: U256,
+        #[case] expected_result: U256,
+    ) {
+        run_test(
+            runtime,
+            modCall::new((dividend, divisor)).abi_encode(),
+            (expected_result,).abi_encode(),
+        )
+        .unwrap();
+    }
+}
+
 #[test]
 fn test_multi_values_return() {
     const MODULE_NAME: &str = "multi_values_return";
@@ -1442,6 +1516,117 @@ mod vec_32 {
     }
 }
 
+mod vec_32_memory_growth {
+    use move_bytecode_to_wasm::{MemoryGrowth, TranslationOptions};
+
+    use crate::common::translate_test_package_with_framework_and_options;
+
+    use super::*;
+
+    const MODULE_NAME: &str = "vec_32";
+    const SOURCE_PATH: &str = "tests/primitives/vec_32.move";
+
+    fn runtime(memory_growth: MemoryGrowth) -> RuntimeSandbox {
+        let options = TranslationOptions {
+            memory_growth,
+            ..Default::default()
+        };
+
+        let mut translated_package =
+            translate_test_package_with_framework_and_options(SOURCE_PATH, MODULE_NAME, &options);
+        RuntimeSandbox::new(&mut translated_package)
+    }
+
+    sol!(
+        #[allow(missing_docs)]
+        function echo(uint32[] x) external returns (uint32[]);
+        function allocateVectors(uint32 n) external returns (uint32);
+    );
+
+    #[rstest]
+    fn test_memory_growth() {
+        // Thousands of small vectors fill dozens of pages, one after the other
+        let n = 50_000u32;
+        let call_data = allocateVectorsCall::new((n,)).abi_encode();
+        let expected_result = (n * (n - 1) / 2).abi_encode();
+
+        let exact = runtime(MemoryGrowth::Exact);
+        run_test(&exact, call_data.clone(), expected_result.clone()).unwrap();
+        let exact_grows = exact.get_memory_grows();
+
+        let exponential = runtime(MemoryGrowth::Exponential { max_pages: 16 });
+        run_test(&exponential, call_data, expected_result).unwrap();
+        let exponential_grows = exponential.get_memory_grows();
+
+        // No allocation takes a page, so the exact growth pays for one page at a time
+        assert!(exact_grows.len() > 16);
+        assert!(exact_grows.iter().all(|pages| *pages == 1));
+
+        // The memory starts with a page and doubles, by 16 pages at most, every time it is filled
+        let pages_filled = exact_grows.len() as u32 + 1;
+        let mut pages = 1;
+        let mut expected_grows = Vec::new();
+        while pages < pages_filled {
+            expected_grows.push(pages.min(16));
+            pages += pages.min(16);
+        }
+
+        assert_eq!(expected_grows, exponential_grows);
+        assert!(exponential_grows.len() < exact_grows.len());
+    }
+
+    #[rstest]
+    fn test_memory_growth_large_allocation() {
+        // The calldata, the vector and the return data take dozens of pages
+        let x: Vec<u32> = (0..50_000).collect();
+        let call_data = echoCall::new((x.clone(),)).abi_encode();
+
+        let exact = runtime(MemoryGrowth::Exact);
+        run_test(&exact, call_data.clone(), x.abi_encode()).unwrap();
+        let exact_grows = exact.get_memory_grows();
+
+        let exponential = runtime(MemoryGrowth::Exponential { max_pages: 16 });
+        run_test(&exponential, call_data, x.abi_encode()).unwrap();
+        let exponential_grows = exponential.get_memory_grows();
+
+        // Allocations larger than the doubling grow the memory by the pages they need
+        assert!(exact_grows.iter().any(|pages| *pages > 16));
+        assert!(exponential_grows.len() <= exact_grows.len());
+        assert!(exponential_grows.iter().sum::<u32>() >= exact_grows.iter().sum::<u32>());
+    }
+
+    #[rstest]
+    fn test_memory_stats() {
+        let runtime = runtime(MemoryGrowth::Exact);
+
+        let x: Vec<u32> = (0..3).collect();
+        run_test(
+            &runtime,
+            echoCall::new((x.clone(),)).abi_encode(),
+            x.abi_encode(),
+        )
+        .unwrap();
+        let small = runtime.get_memory_stats().unwrap();
+
+        let x: Vec<u32> = (0..50_000).collect();
+        run_test(
+            &runtime,
+            echoCall::new((x.clone(),)).abi_encode(),
+            x.abi_encode(),
+        )
+        .unwrap();
+        let large = runtime.get_memory_stats().unwrap();
+
+        assert!(small.allocations > 0);
+        assert!(small.peak_memory_pointer > 0);
+
+        // Every call starts with a fresh memory, so the stats are the ones of the last call
+        assert!(large.pages_grown >= 1);
+        assert!(large.peak_memory_pointer > small.peak_memory_pointer);
+        assert!(large.pages_grown > small.pages_grown);
+    }
+}
+
 mod vec_64 {
     use super::*;
 
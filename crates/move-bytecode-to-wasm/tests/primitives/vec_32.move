@@ -85,3 +85,15 @@ public fun vec_unpack(x: vector<u32>): vector<u32> {
     x.do!(|e| z.push_back(e));
     z
 }
+
+// Allocates a vector in every iteration, so the memory grows a page at a time
+public fun allocate_vectors(n: u32): u32 {
+  let mut sum = 0;
+  let mut i = 0;
+  while (i < n) {
+    let v = vector[i];
+    sum = sum + v[0];
+    i = i + 1;
+  };
+  sum
+}
@@ -2,7 +2,8 @@ use alloy_primitives::U256;
 use alloy_sol_types::abi::TokenSeq;
 use alloy_sol_types::{SolCall, SolType, SolValue, sol};
 use anyhow::Result;
-use common::{runtime_sandbox::RuntimeSandbox, translate_test_package};
+use common::translate_test_package;
+use move_stylus_testing::RuntimeSandbox;
 use rstest::{fixture, rstest};
 
 mod common;
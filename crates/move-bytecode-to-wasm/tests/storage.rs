@@ -1,10 +1,19 @@
 mod common;
 
-use common::runtime_sandbox::constants::SIGNER_ADDRESS;
-use common::{runtime_sandbox::RuntimeSandbox, translate_test_package_with_framework};
+use common::translate_test_package_with_framework;
+use move_stylus_testing::{
+    RuntimeSandbox,
+    constants::SIGNER_ADDRESS,
+    recording::{Divergence, DivergenceKind},
+};
 use rstest::{fixture, rstest};
 
 mod counter {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    };
+
     use alloy_primitives::{FixedBytes, address};
     use alloy_sol_types::{SolCall, sol};
 
@@ -33,6 +42,42 @@ mod counter {
         function setValue(bytes32 id, uint64 value) public view;
     );
 
+    #[test]
+    fn test_storage_load_failure() {
+        let mut translated_package =
+            translate_test_package_with_framework("tests/storage/counter.move", "counter");
+
+        let unavailable = Arc::new(AtomicBool::new(false));
+        let unavailable_for_load = unavailable.clone();
+        let runtime = RuntimeSandbox::builder(&mut translated_package)
+            .override_hook("storage_load_bytes32", move |call| {
+                anyhow::ensure!(
+                    !unavailable_for_load.load(Ordering::Relaxed),
+                    "storage unavailable"
+                );
+                call.call_original()
+            })
+            .build();
+
+        let call_data = createCall::new(()).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        let object_id = runtime.log_events.lock().unwrap().recv().unwrap().data;
+        let object_id = FixedBytes::<32>::from_slice(&object_id);
+
+        // The failure of the hook traps the execution
+        unavailable.store(true, Ordering::Relaxed);
+        let call_data = readCall::new((object_id,)).abi_encode();
+        let error = runtime.call_entrypoint(call_data.clone()).unwrap_err();
+        assert!(format!("{error:?}").contains("storage unavailable"));
+
+        unavailable.store(false, Ordering::Relaxed);
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert_eq!(25, readCall::abi_decode_returns(&return_data).unwrap());
+    }
+
     #[rstest]
     fn test_storage_counter(runtime: RuntimeSandbox) {
         // Create a new counter
@@ -41,7 +86,7 @@ mod counter {
         assert_eq!(0, result);
 
         // Read the object id emmited from the contract's events
-        let object_id = runtime.log_events.lock().unwrap().recv().unwrap();
+        let object_id = runtime.log_events.lock().unwrap().recv().unwrap().data;
         let object_id = FixedBytes::<32>::from_slice(&object_id);
 
         // Read initial value (should be 25)
@@ -102,13 +147,180 @@ mod counter {
         assert_eq!(43, return_data);
         assert_eq!(0, result);
     }
+
+    #[rstest]
+    fn test_storage_counter_snapshot(runtime: RuntimeSandbox) {
+        let call_data = createCall::new(()).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        let object_id = runtime.log_events.lock().unwrap().recv().unwrap().data;
+        let object_id = FixedBytes::<32>::from_slice(&object_id);
+
+        let read = || {
+            let call_data = readCall::new((object_id,)).abi_encode();
+            let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+            assert_eq!(0, result);
+            readCall::abi_decode_returns(&return_data).unwrap()
+        };
+        let increment = || {
+            let call_data = incrementCall::new((object_id,)).abi_encode();
+            let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+            assert_eq!(0, result);
+        };
+
+        let snapshot = runtime.snapshot();
+
+        increment();
+        let incremented = runtime.snapshot();
+        increment();
+        assert_eq!(27, read());
+
+        // The snapshots can be restored more than once
+        assert!(runtime.revert_to(snapshot));
+        assert_eq!(25, read());
+        increment();
+        assert_eq!(26, read());
+        assert!(runtime.revert_to(snapshot));
+        assert_eq!(25, read());
+
+        // The snapshots taken after the restored one are discarded
+        assert!(!runtime.revert_to(incremented));
+    }
+
+    #[rstest]
+    fn test_storage_counter_dump_state(runtime: RuntimeSandbox) {
+        let call_data = createCall::new(()).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        let object_id = runtime.log_events.lock().unwrap().recv().unwrap().data;
+        let object_id = FixedBytes::<32>::from_slice(&object_id);
+
+        let call_data = setValueCall::new((object_id, 42)).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        let path = std::env::temp_dir().join(format!(
+            "move-stylus-testing-counter-{}.json",
+            std::process::id()
+        ));
+        runtime.dump_state(&path).unwrap();
+
+        // A new deployment of the contract reads the loaded state
+        let mut translated_package =
+            translate_test_package_with_framework("tests/storage/counter.move", "counter");
+        let loaded = RuntimeSandbox::new(&mut translated_package);
+        loaded.load_state(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let call_data = readCall::new((object_id,)).abi_encode();
+        let (result, return_data) = loaded.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert_eq!(42, readCall::abi_decode_returns(&return_data).unwrap());
+    }
+
+    #[rstest]
+    fn test_storage_counter_replay(runtime: RuntimeSandbox) {
+        runtime.start_recording();
+
+        let call_data = createCall::new(()).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        let object_id = runtime.log_events.lock().unwrap().recv().unwrap().data;
+        let object_id = FixedBytes::<32>::from_slice(&object_id);
+
+        for call_data in [
+            incrementCall::new((object_id,)).abi_encode(),
+            setValueCall::new((object_id, 42)).abi_encode(),
+            readCall::new((object_id,)).abi_encode(),
+        ] {
+            let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+            assert_eq!(0, result);
+        }
+
+        let path = std::env::temp_dir().join(format!(
+            "move-stylus-testing-counter-recording-{}.json",
+            std::process::id()
+        ));
+        runtime.save_recording(&path).unwrap();
+
+        // A new build of the same contract behaves the same
+        let mut translated_package =
+            translate_test_package_with_framework("tests/storage/counter.move", "counter");
+        let replayed = RuntimeSandbox::new(&mut translated_package);
+        assert_eq!(Vec::<Divergence>::new(), replayed.replay(&path).unwrap());
+
+        // The read is flagged once its recorded result differs
+        let mut recording: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        let recorded_read = readCall::abi_encode_returns(&43);
+        recording["calls"][3]["outcome"]["return_data"] =
+            serde_json::Value::String(alloy_primitives::hex::encode_prefixed(&recorded_read));
+        std::fs::write(&path, recording.to_string()).unwrap();
+
+        let divergences = replayed.replay(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            vec![Divergence {
+                call: 3,
+                kind: DivergenceKind::ReturnData {
+                    recorded: recorded_read,
+                    replayed: readCall::abi_encode_returns(&42),
+                },
+            }],
+            divergences
+        );
+    }
+
+    #[rstest]
+    fn test_storage_counter_resolver(runtime: RuntimeSandbox) {
+        let call_data = createCall::new(()).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        let object_id = runtime.log_events.lock().unwrap().recv().unwrap().data;
+        let object_id = FixedBytes::<32>::from_slice(&object_id);
+
+        // The new deployment reads the slots it misses from the first one
+        let source = runtime.get_storage();
+        let resolved = Arc::new(AtomicUsize::new(0));
+        let resolved_for_resolver = resolved.clone();
+
+        let mut translated_package =
+            translate_test_package_with_framework("tests/storage/counter.move", "counter");
+        let forked = RuntimeSandbox::new(&mut translated_package);
+        forked.set_storage_resolver(move |slot: [u8; 32]| -> anyhow::Result<[u8; 32]> {
+            resolved_for_resolver.fetch_add(1, Ordering::Relaxed);
+            Ok(source.get(&slot).copied().unwrap_or([0; 32]))
+        });
+
+        let read = || {
+            let call_data = readCall::new((object_id,)).abi_encode();
+            let (result, return_data) = forked.call_entrypoint(call_data).unwrap();
+            assert_eq!(0, result);
+            readCall::abi_decode_returns(&return_data).unwrap()
+        };
+
+        assert_eq!(25, read());
+        let resolved_slots = resolved.load(Ordering::Relaxed);
+        assert!(resolved_slots > 0);
+
+        // The resolved slots are cached
+        assert_eq!(25, read());
+        assert_eq!(resolved_slots, resolved.load(Ordering::Relaxed));
+
+        // Apart from the storage, as they were read and not written
+        assert!(forked.get_storage().is_empty());
+    }
 }
 
 mod capability {
     use alloy_primitives::{FixedBytes, address};
     use alloy_sol_types::{SolCall, sol};
 
-    use crate::common::runtime_sandbox::constants::SIGNER_ADDRESS;
+    use move_stylus_testing::constants::SIGNER_ADDRESS;
 
     use super::*;
 
@@ -145,7 +357,7 @@ mod capability {
         assert_eq!(0, result);
 
         // Read the object id emmited from the contract's events
-        let object_id = runtime.log_events.lock().unwrap().recv().unwrap();
+        let object_id = runtime.log_events.lock().unwrap().recv().unwrap().data;
         let object_id = FixedBytes::<32>::from_slice(&object_id);
 
         // Set value to 111 with a sender that is not the owner
@@ -227,13 +439,22 @@ mod storage_transfer {
     /// mapping(address => mapping(bytes32 => V)) at base slot 0
     /// slot(owner, id) = keccak256( pad32(id) || keccak256( pad32(owner) || pad32(0) ) )
     pub fn derive_object_slot(owner: &[u8], object_id: &[u8]) -> FixedBytes<32> {
-        // parent = keccak256( pad32(owner) || pad32(0) )
+        derive_object_slot_with_root(&[0u8; 32], owner, object_id)
+    }
+
+    /// mapping(address => mapping(bytes32 => V)) at base slot `root`
+    /// slot(owner, id) = keccak256( pad32(id) || keccak256( pad32(owner) || root ) )
+    pub fn derive_object_slot_with_root(
+        root: &[u8; 32],
+        owner: &[u8],
+        object_id: &[u8],
+    ) -> FixedBytes<32> {
+        // parent = keccak256( pad32(owner) || root )
         let owner_padded = pad32_right(owner);
-        let zero_slot = [0u8; 32];
 
         let mut buf = [0u8; 64];
         buf[..32].copy_from_slice(&owner_padded);
-        buf[32..].copy_from_slice(&zero_slot);
+        buf[32..].copy_from_slice(root);
         let parent = keccak256(buf);
 
         // slot = keccak256( pad32(id) || pad32(parent) )
@@ -252,7 +473,7 @@ mod storage_transfer {
         assert_eq!(0, result);
 
         // Read the object id emmited from the contract's events
-        let object_id = runtime.log_events.lock().unwrap().recv().unwrap();
+        let object_id = runtime.log_events.lock().unwrap().recv().unwrap().data;
         let object_id = FixedBytes::<32>::from_slice(&object_id);
 
         // Read initial value (should be 101)
@@ -352,7 +573,7 @@ mod storage_transfer {
         assert_eq!(0, result);
 
         // Read the object id emmited from the contract's events
-        let object_id = runtime.log_events.lock().unwrap().recv().unwrap();
+        let object_id = runtime.log_events.lock().unwrap().recv().unwrap().data;
         let object_id = FixedBytes::<32>::from_slice(&object_id);
 
         // Read initial value (should be 101)
@@ -424,7 +645,7 @@ mod storage_transfer {
         assert_eq!(0, result);
 
         // Read the object id emmited from the contract's events
-        let object_id = runtime.log_events.lock().unwrap().recv().unwrap();
+        let object_id = runtime.log_events.lock().unwrap().recv().unwrap().data;
         let object_id = FixedBytes::<32>::from_slice(&object_id);
 
         // Compute the object slot using the owner and the object id
@@ -484,7 +705,7 @@ mod storage_transfer {
         assert_eq!(0, result);
 
         // Read the object id emmited from the contract's events
-        let object_id = runtime.log_events.lock().unwrap().recv().unwrap();
+        let object_id = runtime.log_events.lock().unwrap().recv().unwrap().data;
         let object_id = FixedBytes::<32>::from_slice(&object_id);
 
         // Compute the object slot using the owner and the object id
@@ -552,7 +773,7 @@ mod storage_transfer {
         assert_eq!(0, result);
 
         // Read the object id emmited from the contract's events
-        let object_id = runtime.log_events.lock().unwrap().recv().unwrap();
+        let object_id = runtime.log_events.lock().unwrap().recv().unwrap().data;
         let object_id = FixedBytes::<32>::from_slice(&object_id);
 
         // Read initial value (should be 101)
@@ -579,7 +800,7 @@ mod storage_transfer {
         assert_eq!(0, result);
 
         // Read the object id emmited from the contract's events
-        let object_id = runtime.log_events.lock().unwrap().recv().unwrap();
+        let object_id = runtime.log_events.lock().unwrap().recv().unwrap().data;
         let object_id = FixedBytes::<32>::from_slice(&object_id);
 
         runtime.set_tx_origin(address!("0x00000000000000000000000000000000abababab").0.0);
@@ -599,7 +820,7 @@ mod storage_transfer {
         assert_eq!(0, result);
 
         // Read the object id emmited from the contract's events
-        let object_id = runtime.log_events.lock().unwrap().recv().unwrap();
+        let object_id = runtime.log_events.lock().unwrap().recv().unwrap().data;
         let object_id = FixedBytes::<32>::from_slice(&object_id);
 
         // Freeze the object. Only possible if the object is owned by the signer!
@@ -619,7 +840,7 @@ mod storage_transfer {
         assert_eq!(0, result);
 
         // Read the object id emmited from the contract's events
-        let object_id = runtime.log_events.lock().unwrap().recv().unwrap();
+        let object_id = runtime.log_events.lock().unwrap().recv().unwrap().data;
         let object_id = FixedBytes::<32>::from_slice(&object_id);
 
         // Freeze the object. Only possible if the object is owned by the signer!
@@ -646,7 +867,7 @@ mod storage_transfer {
         assert_eq!(0, result);
 
         // Read the object id emmited from the contract's events
-        let object_id = runtime.log_events.lock().unwrap().recv().unwrap();
+        let object_id = runtime.log_events.lock().unwrap().recv().unwrap().data;
         let object_id = FixedBytes::<32>::from_slice(&object_id);
 
         // Set value to 111 with a sender that is not the owner
@@ -664,7 +885,7 @@ mod storage_transfer {
 }
 
 mod storage_encoding {
-    use alloy_primitives::{U256, address};
+    use alloy_primitives::{U256, address, keccak256};
     use alloy_sol_types::SolValue;
     use alloy_sol_types::{SolCall, sol};
 
@@ -740,6 +961,18 @@ mod storage_encoding {
             address e;
         }
 
+        struct AsciiString {
+            uint8[] bytes;
+        }
+
+        struct DynamicFields {
+            UID id;
+            uint32 a;
+            uint8[] b;
+            AsciiString c;
+            uint64 d;
+        }
+
         function saveStaticFields(
             UID id,
             uint256 a,
@@ -781,6 +1014,15 @@ mod storage_encoding {
             uint32 g
         ) public view;
         function readStaticNestedStruct() public view returns (StaticNestedStruct);
+
+        function saveDynamicFields(
+            UID id,
+            uint32 a,
+            uint8[] b,
+            uint8[] c,
+            uint64 d
+        ) public view;
+        function readDynamicFields() public view returns (DynamicFields);
     );
 
     #[rstest]
@@ -999,4 +1241,2336 @@ mod storage_encoding {
         assert_eq!(0, result);
         assert_eq!(expected_decode.abi_encode(), result_data);
     }
+
+    /// Returns the expected content of the slots used to save `data` in `slot` following the
+    /// Solidity encoding for `bytes`/`string`
+    fn expected_bytes_slots(slot: U256, data: &[u8]) -> Vec<(U256, [u8; 32])> {
+        let mut header = [0; 32];
+        if data.len() < 32 {
+            header[..data.len()].copy_from_slice(data);
+            header[31] = data.len() as u8 * 2;
+
+            return vec![(slot, header)];
+        }
+
+        header = U256::from(data.len() * 2 + 1).to_be_bytes();
+        let mut slots = vec![(slot, header)];
+
+        let data_slot = U256::from_be_bytes(keccak256(slot.to_be_bytes::<32>()).0);
+        for (i, chunk) in data.chunks(32).enumerate() {
+            let mut value = [0; 32];
+            value[..chunk.len()].copy_from_slice(chunk);
+            slots.push((data_slot + U256::from(i), value));
+        }
+
+        slots
+    }
+
+    #[rstest]
+    #[case(b"hello".to_vec(), b"world".to_vec())]
+    #[case(vec![], vec![])]
+    #[case(vec![0xab; 31], b"a".to_vec())]
+    #[case(vec![0xab; 32], b"short".to_vec())]
+    #[case(vec![0xab; 40], b"this string is long enough to need more than two slots".to_vec())]
+    fn test_dynamic_fields(runtime: RuntimeSandbox, #[case] b: Vec<u8>, #[case] c: Vec<u8>) {
+        let id = UID {
+            id: ID {
+                bytes: address!("0x0000000000000000000000000000000000000000"),
+            },
+        };
+        let call_data =
+            saveDynamicFieldsCall::new((id, 0xdddddddd, b.clone(), c.clone(), 42)).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        // The id and a occupy the first two slots, b and c their own header slots and d the last
+        // one
+        let mut expected = vec![
+            (U256::from(0), [0x00; 32]),
+            (U256::from(1), U256::from(0xdddddddd_u32).to_be_bytes()),
+            (U256::from(4), U256::from(42).to_be_bytes()),
+        ];
+        expected.extend(expected_bytes_slots(U256::from(2), &b));
+        expected.extend(expected_bytes_slots(U256::from(3), &c));
+
+        for (slot, value) in expected {
+            let storage = runtime.get_storage_at_slot(slot.to_be_bytes());
+            assert_eq!(value, storage, "Mismatch at slot {slot}");
+        }
+
+        let (result, result_data) = runtime
+            .call_entrypoint(readDynamicFieldsCall::new(()).abi_encode())
+            .unwrap();
+        assert_eq!(0, result);
+
+        let expected_decode = DynamicFields {
+            id: UID {
+                id: ID {
+                    bytes: address!("0x0000000000000000000000000000000000000000"),
+                },
+            },
+            a: 0xdddddddd,
+            b,
+            c: AsciiString { bytes: c },
+            d: 42,
+        };
+        assert_eq!(expected_decode.abi_encode(), result_data);
+    }
+
+    #[rstest]
+    #[case(vec![0xab; 100], b"short".to_vec())]
+    #[case(vec![0xab; 100], vec![0xcd; 40])]
+    #[case(vec![0xab; 100], vec![])]
+    fn test_truncated_bytes_are_cleared(
+        runtime: RuntimeSandbox,
+        #[case] long: Vec<u8>,
+        #[case] short: Vec<u8>,
+    ) {
+        for b in [long.clone(), short.clone()] {
+            let id = UID {
+                id: ID {
+                    bytes: address!("0x0000000000000000000000000000000000000000"),
+                },
+            };
+            let call_data =
+                saveDynamicFieldsCall::new((id, 0xdddddddd, b, vec![], 42)).abi_encode();
+            let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+            assert_eq!(0, result);
+        }
+
+        // The data slots the shorter value does not use anymore are zeroed
+        let expected = expected_bytes_slots(U256::from(2), &short);
+        let data_slot = U256::from_be_bytes(keccak256(U256::from(2).to_be_bytes::<32>()).0);
+        for i in 0..long.len().div_ceil(32) {
+            let slot = data_slot + U256::from(i);
+            let value = expected
+                .iter()
+                .find(|(s, _)| *s == slot)
+                .map(|(_, v)| *v)
+                .unwrap_or([0; 32]);
+            let storage = runtime.get_storage_at_slot(slot.to_be_bytes());
+            assert_eq!(value, storage, "Mismatch at slot {slot}");
+        }
+    }
+
+    #[test]
+    fn test_multi_slot_structs_use_batched_helpers() {
+        let translated_package = translate_test_package_with_framework(
+            "tests/storage/encoding.move",
+            "storage_encoding",
+        );
+
+        // The static structs span several slots, so they are saved and read by the loop-based
+        // helpers instead of one inline sequence per slot
+        assert!(
+            translated_package
+                .funcs
+                .by_name("storage_cached_store_slots")
+                .is_some()
+        );
+        assert!(
+            translated_package
+                .funcs
+                .by_name("storage_cached_load_slots")
+                .is_some()
+        );
+    }
+}
+
+mod storage_encoding_without_packing {
+    use alloy_primitives::{U256, address};
+    use alloy_sol_types::{SolCall, SolValue, sol};
+    use move_bytecode_to_wasm::TranslationOptions;
+
+    use crate::common::translate_test_package_with_framework_and_options;
+
+    use super::*;
+
+    #[fixture]
+    fn runtime() -> RuntimeSandbox {
+        const MODULE_NAME: &str = "storage_encoding";
+        const SOURCE_PATH: &str = "tests/storage/encoding.move";
+
+        let options = TranslationOptions {
+            storage_packing: false,
+            ..Default::default()
+        };
+
+        let mut translated_package =
+            translate_test_package_with_framework_and_options(SOURCE_PATH, MODULE_NAME, &options);
+
+        RuntimeSandbox::new(&mut translated_package)
+    }
+
+    sol!(
+        #[allow(missing_docs)]
+
+        #[derive(Debug)]
+        struct ID {
+           address bytes;
+        }
+
+        #[derive(Debug)]
+        struct UID {
+           ID id;
+        }
+
+        struct StaticFields2 {
+            UID id;
+            uint8 a;
+            address b;
+            uint64 c;
+            uint16 d;
+            uint8 e;
+        }
+
+        function saveStaticFields2(
+            UID id,
+            uint8 a,
+            address b,
+            uint64 c,
+            uint16 d,
+            uint8 e
+        ) public view;
+        function readStaticFields2() public view returns (StaticFields2);
+    );
+
+    #[rstest]
+    fn test_every_field_in_its_own_slot(runtime: RuntimeSandbox) {
+        let call_data = saveStaticFields2Call::new((
+            UID {
+                id: ID {
+                    bytes: address!("0x0000000000000000000000000000000000000000"),
+                },
+            },
+            0xff,
+            address!("0xcafecafecafecafecafecafecafecafecafecafe"),
+            0xcccccccccccccccc,
+            0xeeee,
+            0xff,
+        ))
+        .abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        let expected_encode: Vec<[u8; 32]> = vec![
+            [0x00; 32],
+            U256::from(0xff).to_be_bytes(),
+            U256::from_str_radix("cafecafecafecafecafecafecafecafecafecafe", 16)
+                .unwrap()
+                .to_be_bytes(),
+            U256::from(0xcccccccccccccccc_u64).to_be_bytes(),
+            U256::from(0xeeee).to_be_bytes(),
+            U256::from(0xff).to_be_bytes(),
+        ];
+
+        for (i, expected) in expected_encode.iter().enumerate() {
+            let storage = runtime.get_storage_at_slot(U256::from(i).to_be_bytes());
+            assert_eq!(expected, &storage, "Mismatch at slot {}", i);
+        }
+
+        let expected_decode = StaticFields2 {
+            id: UID {
+                id: ID {
+                    bytes: address!("0x0000000000000000000000000000000000000000"),
+                },
+            },
+            a: 0xff,
+            b: address!("0xcafecafecafecafecafecafecafecafecafecafe"),
+            c: 0xcccccccccccccccc,
+            d: 0xeeee,
+            e: 0xff,
+        };
+
+        let (result, result_data) = runtime
+            .call_entrypoint(readStaticFields2Call::new(()).abi_encode())
+            .unwrap();
+        assert_eq!(0, result);
+        assert_eq!(expected_decode.abi_encode(), result_data);
+    }
+}
+
+mod mapping {
+    use alloy_primitives::{FixedBytes, U256, address, keccak256};
+    use alloy_sol_types::{SolCall, sol};
+
+    use super::*;
+    use crate::storage_transfer::derive_object_slot;
+
+    #[fixture]
+    fn runtime() -> RuntimeSandbox {
+        const MODULE_NAME: &str = "mapping";
+        const SOURCE_PATH: &str = "tests/storage/mapping.move";
+
+        let mut translated_package =
+            translate_test_package_with_framework(SOURCE_PATH, MODULE_NAME);
+
+        RuntimeSandbox::new(&mut translated_package)
+    }
+
+    sol!(
+        #[allow(missing_docs)]
+        function create() public view;
+        function setBalance(bytes32 id, address owner, uint256 amount) public view;
+        function setBalanceTwice(bytes32 id, address owner, uint256 first, uint256 second) public view;
+        function setBalanceTwiceFlushed(bytes32 id, address owner, uint256 first, uint256 second, bool clear) public view;
+        function addToBalance(bytes32 id, address owner, uint256 amount, uint64 times) public view;
+        function balanceOf(bytes32 id, address owner) public view returns (uint256);
+        function removeBalance(bytes32 id, address owner) public view;
+        function hasBalance(bytes32 id, address owner) public view returns (bool);
+        function approve(bytes32 id, address owner, address spender, uint256 amount) public view;
+        function allowance(bytes32 id, address owner, address spender) public view returns (uint256);
+        function setPosition(bytes32 id, uint64 index, uint32 x, uint64 y, address owner) public view;
+        function setPositions(bytes32 id, uint64 count, address owner) public view returns (uint64);
+        function removePosition(bytes32 id, uint64 index) public view;
+        function positionX(bytes32 id, uint64 index) public view returns (uint32);
+        function positionY(bytes32 id, uint64 index) public view returns (uint64);
+        function positionOwner(bytes32 id, uint64 index) public view returns (address);
+        function setRecord(bytes32 id, uint64 index, uint256 amount, uint256 limit, address owner) public view;
+        function removeRecord(bytes32 id, uint64 index) public view;
+        function recordLimit(bytes32 id, uint64 index) public view returns (uint256);
+        function hasRecord(bytes32 id, uint64 index) public view returns (bool);
+    );
+
+    const SHARED: [u8; 20] = [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1];
+
+    /// keccak256(h(k) . p)
+    fn derive_mapping_slot(slot: U256, key: [u8; 32]) -> U256 {
+        let mut buf = [0u8; 64];
+        buf[..32].copy_from_slice(&key);
+        buf[32..].copy_from_slice(&slot.to_be_bytes::<32>());
+        U256::from_be_bytes(keccak256(buf).0)
+    }
+
+    fn create_ledger(runtime: &RuntimeSandbox) -> (FixedBytes<32>, U256) {
+        let call_data = createCall::new(()).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        // Read the object id emmited from the contract's events
+        let object_id = runtime.log_events.lock().unwrap().recv().unwrap().data;
+        let object_id = FixedBytes::<32>::from_slice(&object_id);
+
+        let object_slot = derive_object_slot(&SHARED, &object_id.0);
+
+        (object_id, U256::from_be_bytes(object_slot.0))
+    }
+
+    #[rstest]
+    fn test_mapping_of_values(runtime: RuntimeSandbox) {
+        let (object_id, object_slot) = create_ledger(&runtime);
+        let owner = address!("0x00000000000000000000000000000000abcdef01");
+        let other = address!("0x00000000000000000000000000000000abcdef02");
+
+        let call_data = hasBalanceCall::new((object_id, owner)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert!(!hasBalanceCall::abi_decode_returns(&return_data).unwrap());
+
+        let call_data = setBalanceCall::new((object_id, owner, U256::from(1000))).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        let call_data = balanceOfCall::new((object_id, owner)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert_eq!(
+            U256::from(1000),
+            balanceOfCall::abi_decode_returns(&return_data).unwrap()
+        );
+
+        let call_data = hasBalanceCall::new((object_id, owner)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert!(hasBalanceCall::abi_decode_returns(&return_data).unwrap());
+
+        let call_data = balanceOfCall::new((object_id, other)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert_eq!(
+            U256::ZERO,
+            balanceOfCall::abi_decode_returns(&return_data).unwrap()
+        );
+
+        // The balances mapping occupies the slot next to the UID
+        let value_slot = derive_mapping_slot(object_slot + U256::from(1), owner.into_word().0);
+        assert_eq!(
+            U256::from(1000).to_be_bytes::<32>(),
+            runtime.get_storage_at_slot(value_slot.to_be_bytes())
+        );
+    }
+
+    #[rstest]
+    fn test_repeated_writes_are_deduplicated(runtime: RuntimeSandbox) {
+        let (object_id, object_slot) = create_ledger(&runtime);
+        let owner = address!("0x00000000000000000000000000000000abcdef01");
+
+        let call_data =
+            setBalanceTwiceCall::new((object_id, owner, U256::from(1), U256::from(2))).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        // Both writes land in the storage cache; only the last value reaches the host
+        let value_slot = derive_mapping_slot(object_slot + U256::from(1), owner.into_word().0);
+        assert_eq!(
+            U256::from(2).to_be_bytes::<32>(),
+            runtime.get_storage_at_slot(value_slot.to_be_bytes())
+        );
+        assert_eq!(
+            1,
+            runtime.get_storage_writes_at_slot(value_slot.to_be_bytes())
+        );
+
+        let call_data = balanceOfCall::new((object_id, owner)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert_eq!(
+            U256::from(2),
+            balanceOfCall::abi_decode_returns(&return_data).unwrap()
+        );
+    }
+
+    #[rstest]
+    #[case(false)]
+    #[case(true)]
+    fn test_flush_sends_the_writes(runtime: RuntimeSandbox, #[case] clear: bool) {
+        let (object_id, object_slot) = create_ledger(&runtime);
+        let owner = address!("0x00000000000000000000000000000000abcdef01");
+
+        let call_data = setBalanceTwiceFlushedCall::new((
+            object_id,
+            owner,
+            U256::from(1),
+            U256::from(2),
+            clear,
+        ))
+        .abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        // The first write reaches the host when the cache is flushed, and the second one when it
+        // is committed at the end of the call
+        let value_slot = derive_mapping_slot(object_slot + U256::from(1), owner.into_word().0);
+        assert_eq!(
+            U256::from(2).to_be_bytes::<32>(),
+            runtime.get_storage_at_slot(value_slot.to_be_bytes())
+        );
+        assert_eq!(
+            2,
+            runtime.get_storage_writes_at_slot(value_slot.to_be_bytes())
+        );
+        assert_eq!(vec![clear, false], runtime.get_storage_flushes());
+    }
+
+    #[rstest]
+    fn test_evicted_writes_are_read_back(runtime: RuntimeSandbox) {
+        let (object_id, _) = create_ledger(&runtime);
+        let owner = address!("0x00000000000000000000000000000000abcdef01");
+
+        // The storage cache holds 32 slots, so the first positions are evicted before they are
+        // read back
+        let count = 100u64;
+        let call_data = setPositionsCall::new((object_id, count, owner)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert_eq!(
+            count * (count - 1) / 2,
+            setPositionsCall::abi_decode_returns(&return_data).unwrap()
+        );
+
+        for index in [0, count / 2, count - 1] {
+            let call_data = positionYCall::new((object_id, index)).abi_encode();
+            let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+            assert_eq!(0, result);
+            assert_eq!(
+                index,
+                positionYCall::abi_decode_returns(&return_data).unwrap()
+            );
+
+            let call_data = positionOwnerCall::new((object_id, index)).abi_encode();
+            let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+            assert_eq!(0, result);
+            assert_eq!(
+                owner,
+                positionOwnerCall::abi_decode_returns(&return_data).unwrap()
+            );
+        }
+    }
+
+    #[rstest]
+    fn test_repeated_slot_derivations_are_memoized(runtime: RuntimeSandbox) {
+        let (object_id, _) = create_ledger(&runtime);
+        let owner = address!("0x00000000000000000000000000000000abcdef01");
+
+        let call_data = addToBalanceCall::new((object_id, owner, U256::from(5), 1)).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        let hashes_for_one_iteration = runtime.get_keccak_calls();
+
+        // Every iteration derives the same slot, so it is only hashed once
+        let call_data = addToBalanceCall::new((object_id, owner, U256::from(5), 10)).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert_eq!(hashes_for_one_iteration, runtime.get_keccak_calls());
+
+        let call_data = balanceOfCall::new((object_id, owner)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert_eq!(
+            U256::from(55),
+            balanceOfCall::abi_decode_returns(&return_data).unwrap()
+        );
+    }
+
+    #[rstest]
+    fn test_storage_journal(runtime: RuntimeSandbox) {
+        let (object_id, object_slot) = create_ledger(&runtime);
+        let owner = address!("0x00000000000000000000000000000000abcdef01");
+
+        let call_data =
+            setBalanceTwiceCall::new((object_id, owner, U256::from(1), U256::from(2))).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        let journal = runtime.storage_journal();
+
+        // Only the balance changed, the object is written back with the same value
+        let value_slot = derive_mapping_slot(object_slot + U256::from(1), owner.into_word().0);
+        assert_eq!(
+            std::collections::BTreeMap::from([(
+                value_slot.to_be_bytes(),
+                U256::from(2).to_be_bytes()
+            )]),
+            journal.changed_slots()
+        );
+
+        // The storage cache asks the host for every slot at most once
+        for access in journal.accesses() {
+            assert!(journal.reads_at_slot(access.slot()) <= 1);
+        }
+        assert!(journal.cold_accesses() > 0);
+
+        // The journal only covers the last call
+        let call_data = balanceOfCall::new((object_id, owner)).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        let journal = runtime.storage_journal();
+        assert!(journal.changed_slots().is_empty());
+        assert!(
+            journal
+                .accesses()
+                .iter()
+                .all(|a| !a.is_write() && a.is_cold())
+        );
+    }
+
+    #[rstest]
+    fn test_remove_value(runtime: RuntimeSandbox) {
+        let (object_id, object_slot) = create_ledger(&runtime);
+        let owner = address!("0x00000000000000000000000000000000abcdef01");
+
+        let call_data = setBalanceCall::new((object_id, owner, U256::from(1000))).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        let call_data = removeBalanceCall::new((object_id, owner)).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        let call_data = hasBalanceCall::new((object_id, owner)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert!(!hasBalanceCall::abi_decode_returns(&return_data).unwrap());
+
+        let value_slot = derive_mapping_slot(object_slot + U256::from(1), owner.into_word().0);
+        assert_eq!(
+            [0; 32],
+            runtime.get_storage_at_slot(value_slot.to_be_bytes())
+        );
+    }
+
+    #[rstest]
+    fn test_remove_packed_struct(runtime: RuntimeSandbox) {
+        let (object_id, object_slot) = create_ledger(&runtime);
+        let owner = address!("0x00000000000000000000000000000000abcdef01");
+
+        let call_data =
+            setPositionCall::new((object_id, 7, 0xcafe, 0xdeadbeef, owner)).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        let call_data = removePositionCall::new((object_id, 7)).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        let call_data = positionOwnerCall::new((object_id, 7)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert_eq!(
+            address!("0x0000000000000000000000000000000000000000"),
+            positionOwnerCall::abi_decode_returns(&return_data).unwrap()
+        );
+
+        // The fields of the position are packed in a single slot
+        let value_slot =
+            derive_mapping_slot(object_slot + U256::from(3), U256::from(7).to_be_bytes());
+        assert_eq!(
+            [0; 32],
+            runtime.get_storage_at_slot(value_slot.to_be_bytes())
+        );
+    }
+
+    #[rstest]
+    fn test_contains_struct(runtime: RuntimeSandbox) {
+        let (object_id, _) = create_ledger(&runtime);
+        let owner = address!("0x00000000000000000000000000000000abcdef01");
+
+        // Only the last slot of the record is not zero
+        let call_data =
+            setRecordCall::new((object_id, 7, U256::ZERO, U256::ZERO, owner)).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        let call_data = hasRecordCall::new((object_id, 7)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert!(hasRecordCall::abi_decode_returns(&return_data).unwrap());
+
+        let call_data = removeRecordCall::new((object_id, 7)).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        let call_data = hasRecordCall::new((object_id, 7)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert!(!hasRecordCall::abi_decode_returns(&return_data).unwrap());
+    }
+
+    #[rstest]
+    fn test_remove_struct(runtime: RuntimeSandbox) {
+        let (object_id, object_slot) = create_ledger(&runtime);
+        let owner = address!("0x00000000000000000000000000000000abcdef01");
+
+        let call_data =
+            setRecordCall::new((object_id, 7, U256::from(1000), U256::from(5000), owner))
+                .abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        // The record takes the slot of its value and the two that follow it
+        let value_slot =
+            derive_mapping_slot(object_slot + U256::from(4), U256::from(7).to_be_bytes());
+        let record_slots: Vec<_> = (0..3u64)
+            .map(|i| (value_slot + U256::from(i)).to_be_bytes())
+            .collect();
+        assert_eq!(
+            U256::from(1000).to_be_bytes::<32>(),
+            runtime.get_storage_at_slot(record_slots[0])
+        );
+        assert_eq!(
+            U256::from(5000).to_be_bytes::<32>(),
+            runtime.get_storage_at_slot(record_slots[1])
+        );
+        assert_ne!([0; 32], runtime.get_storage_at_slot(record_slots[2]));
+
+        let call_data = removeRecordCall::new((object_id, 7)).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        let call_data = recordLimitCall::new((object_id, 7)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert_eq!(
+            U256::ZERO,
+            recordLimitCall::abi_decode_returns(&return_data).unwrap()
+        );
+
+        for slot in record_slots {
+            assert_eq!([0; 32], runtime.get_storage_at_slot(slot));
+        }
+    }
+
+    #[rstest]
+    fn test_nested_mapping(runtime: RuntimeSandbox) {
+        let (object_id, object_slot) = create_ledger(&runtime);
+        let owner = address!("0x00000000000000000000000000000000abcdef01");
+        let spender_1 = address!("0x00000000000000000000000000000000abcdef02");
+        let spender_2 = address!("0x00000000000000000000000000000000abcdef03");
+
+        for (spender, amount) in [(spender_1, 42), (spender_2, 314)] {
+            let call_data =
+                approveCall::new((object_id, owner, spender, U256::from(amount))).abi_encode();
+            let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+            assert_eq!(0, result);
+        }
+
+        for (spender, amount) in [(spender_1, 42), (spender_2, 314)] {
+            let call_data = allowanceCall::new((object_id, owner, spender)).abi_encode();
+            let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+            assert_eq!(0, result);
+            assert_eq!(
+                U256::from(amount),
+                allowanceCall::abi_decode_returns(&return_data).unwrap()
+            );
+
+            // keccak256(h(spender) . keccak256(h(owner) . p))
+            let inner_slot = derive_mapping_slot(object_slot + U256::from(2), owner.into_word().0);
+            let value_slot = derive_mapping_slot(inner_slot, spender.into_word().0);
+            assert_eq!(
+                U256::from(amount).to_be_bytes::<32>(),
+                runtime.get_storage_at_slot(value_slot.to_be_bytes())
+            );
+        }
+
+        // The allowances are independent for each owner
+        let call_data = allowanceCall::new((object_id, spender_1, owner)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert_eq!(
+            U256::ZERO,
+            allowanceCall::abi_decode_returns(&return_data).unwrap()
+        );
+    }
+
+    #[rstest]
+    fn test_mapping_of_structs(runtime: RuntimeSandbox) {
+        let (object_id, object_slot) = create_ledger(&runtime);
+        let owner = address!("0x00000000000000000000000000000000abcdef01");
+
+        let call_data =
+            setPositionCall::new((object_id, 7, 0xcafe, 0xdeadbeef, owner)).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        let call_data = positionXCall::new((object_id, 7)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert_eq!(
+            0xcafe,
+            positionXCall::abi_decode_returns(&return_data).unwrap()
+        );
+
+        let call_data = positionYCall::new((object_id, 7)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert_eq!(
+            0xdeadbeef,
+            positionYCall::abi_decode_returns(&return_data).unwrap()
+        );
+
+        let call_data = positionOwnerCall::new((object_id, 7)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert_eq!(
+            owner,
+            positionOwnerCall::abi_decode_returns(&return_data).unwrap()
+        );
+
+        // The struct is spread across two consecutive slots: x and y share the first one and the
+        // owner does not fit, so it is saved in the next one
+        let first_slot =
+            derive_mapping_slot(object_slot + U256::from(3), U256::from(7).to_be_bytes());
+
+        let mut expected_first_slot = [0u8; 32];
+        expected_first_slot[20..28].copy_from_slice(&0xdeadbeef_u64.to_be_bytes());
+        expected_first_slot[28..].copy_from_slice(&0xcafe_u32.to_be_bytes());
+        assert_eq!(
+            expected_first_slot,
+            runtime.get_storage_at_slot(first_slot.to_be_bytes())
+        );
+
+        let second_slot = first_slot + U256::from(1);
+        assert_eq!(
+            owner.into_word().0,
+            runtime.get_storage_at_slot(second_slot.to_be_bytes())
+        );
+    }
+}
+
+mod storage_vec {
+    use alloy_primitives::{FixedBytes, U256, address, keccak256};
+    use alloy_sol_types::{SolCall, sol};
+
+    use super::*;
+    use crate::storage_transfer::derive_object_slot;
+
+    #[fixture]
+    fn runtime() -> RuntimeSandbox {
+        const MODULE_NAME: &str = "storage_vec";
+        const SOURCE_PATH: &str = "tests/storage/storage_vec.move";
+
+        let mut translated_package =
+            translate_test_package_with_framework(SOURCE_PATH, MODULE_NAME);
+
+        RuntimeSandbox::new(&mut translated_package)
+    }
+
+    sol!(
+        #[allow(missing_docs)]
+        function create() public view;
+        function pushPosition(bytes32 id, uint32 x, uint64 y, address owner) public view;
+        function setPosition(bytes32 id, uint64 index, uint32 x, uint64 y, address owner) public view;
+        function movePosition(bytes32 id, uint64 index, uint64 y) public view;
+        function popPosition(bytes32 id) public view returns (uint32);
+        function positionX(bytes32 id, uint64 index) public view returns (uint32);
+        function positionY(bytes32 id, uint64 index) public view returns (uint64);
+        function positionOwner(bytes32 id, uint64 index) public view returns (address);
+        function positionsLength(bytes32 id) public view returns (uint64);
+        function pushValue(bytes32 id, uint256 value) public view;
+        function valueAt(bytes32 id, uint64 index) public view returns (uint256);
+    );
+
+    const SHARED: [u8; 20] = [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1];
+
+    fn create_route(runtime: &RuntimeSandbox) -> (FixedBytes<32>, U256) {
+        let call_data = createCall::new(()).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        // Read the object id emmited from the contract's events
+        let object_id = runtime.log_events.lock().unwrap().recv().unwrap().data;
+        let object_id = FixedBytes::<32>::from_slice(&object_id);
+
+        let object_slot = derive_object_slot(&SHARED, &object_id.0);
+
+        (object_id, U256::from_be_bytes(object_slot.0))
+    }
+
+    /// keccak256(p)
+    fn elements_slot(slot: U256) -> U256 {
+        U256::from_be_bytes(keccak256(slot.to_be_bytes::<32>()).0)
+    }
+
+    fn position(runtime: &RuntimeSandbox, object_id: FixedBytes<32>, index: u64) -> (u32, u64) {
+        let call_data = positionXCall::new((object_id, index)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        let x = positionXCall::abi_decode_returns(&return_data).unwrap();
+
+        let call_data = positionYCall::new((object_id, index)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        let y = positionYCall::abi_decode_returns(&return_data).unwrap();
+
+        (x, y)
+    }
+
+    fn positions_length(runtime: &RuntimeSandbox, object_id: FixedBytes<32>) -> u64 {
+        let call_data = positionsLengthCall::new((object_id,)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        positionsLengthCall::abi_decode_returns(&return_data).unwrap()
+    }
+
+    #[rstest]
+    fn test_vector_of_structs(runtime: RuntimeSandbox) {
+        let (object_id, object_slot) = create_route(&runtime);
+        let owner = address!("0x00000000000000000000000000000000abcdef01");
+
+        assert_eq!(0, positions_length(&runtime, object_id));
+
+        for i in 0..3 {
+            let call_data =
+                pushPositionCall::new((object_id, 0xcafe + i, 0xdeadbeef + i as u64, owner))
+                    .abi_encode();
+            let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+            assert_eq!(0, result);
+        }
+
+        assert_eq!(3, positions_length(&runtime, object_id));
+        for i in 0..3 {
+            assert_eq!(
+                (0xcafe + i as u32, 0xdeadbeef + i),
+                position(&runtime, object_id, i)
+            );
+        }
+
+        let call_data = positionOwnerCall::new((object_id, 2)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert_eq!(
+            owner,
+            positionOwnerCall::abi_decode_returns(&return_data).unwrap()
+        );
+
+        // The vector occupies the slot next to the UID, holding its length
+        let vec_slot = object_slot + U256::from(1);
+        assert_eq!(
+            U256::from(3).to_be_bytes::<32>(),
+            runtime.get_storage_at_slot(vec_slot.to_be_bytes())
+        );
+
+        // Every position occupies two slots: x and y share the first one and the owner does not
+        // fit, so it is saved in the next one
+        let second_element_slot = elements_slot(vec_slot) + U256::from(2);
+
+        let mut expected_first_slot = [0u8; 32];
+        expected_first_slot[20..28].copy_from_slice(&(0xdeadbeef_u64 + 1).to_be_bytes());
+        expected_first_slot[28..].copy_from_slice(&(0xcafe_u32 + 1).to_be_bytes());
+        assert_eq!(
+            expected_first_slot,
+            runtime.get_storage_at_slot(second_element_slot.to_be_bytes())
+        );
+        assert_eq!(
+            owner.into_word().0,
+            runtime.get_storage_at_slot((second_element_slot + U256::from(1)).to_be_bytes())
+        );
+    }
+
+    #[rstest]
+    fn test_index_based_mutation(runtime: RuntimeSandbox) {
+        let (object_id, object_slot) = create_route(&runtime);
+        let owner = address!("0x00000000000000000000000000000000abcdef01");
+        let other = address!("0x00000000000000000000000000000000abcdef02");
+
+        for i in 0..3 {
+            let call_data = pushPositionCall::new((object_id, i, i as u64, owner)).abi_encode();
+            let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+            assert_eq!(0, result);
+        }
+
+        let elements_slot = elements_slot(object_slot + U256::from(1));
+        let slot_writes = |index: u64| {
+            let first_slot = elements_slot + U256::from(index * 2);
+            (
+                runtime.get_storage_writes_at_slot(first_slot.to_be_bytes()),
+                runtime.get_storage_writes_at_slot((first_slot + U256::from(1)).to_be_bytes()),
+            )
+        };
+        let writes_before = [slot_writes(0), slot_writes(1), slot_writes(2)];
+
+        // Mutating an element through a reference only writes the slots of that element
+        let call_data = movePositionCall::new((object_id, 1, 42)).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        assert_eq!((1, 42), position(&runtime, object_id, 1));
+        assert_eq!(writes_before[0], slot_writes(0));
+        assert_eq!(writes_before[2], slot_writes(2));
+
+        let call_data = setPositionCall::new((object_id, 2, 7, 8, other)).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        assert_eq!((7, 8), position(&runtime, object_id, 2));
+        assert_eq!(writes_before[0], slot_writes(0));
+
+        let call_data = positionOwnerCall::new((object_id, 2)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert_eq!(
+            other,
+            positionOwnerCall::abi_decode_returns(&return_data).unwrap()
+        );
+
+        // The rest of the elements are untouched
+        assert_eq!((0, 0), position(&runtime, object_id, 0));
+        assert_eq!(3, positions_length(&runtime, object_id));
+    }
+
+    #[rstest]
+    fn test_pop_back(runtime: RuntimeSandbox) {
+        let (object_id, object_slot) = create_route(&runtime);
+        let owner = address!("0x00000000000000000000000000000000abcdef01");
+
+        for i in 0..2 {
+            let call_data = pushPositionCall::new((object_id, 10 + i, 1, owner)).abi_encode();
+            let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+            assert_eq!(0, result);
+        }
+
+        let call_data = popPositionCall::new((object_id,)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert_eq!(
+            11,
+            popPositionCall::abi_decode_returns(&return_data).unwrap()
+        );
+
+        assert_eq!(1, positions_length(&runtime, object_id));
+
+        // The slots occupied by the popped element are cleared
+        let popped_slot = elements_slot(object_slot + U256::from(1)) + U256::from(2);
+        assert_eq!(
+            [0; 32],
+            runtime.get_storage_at_slot(popped_slot.to_be_bytes())
+        );
+        assert_eq!(
+            [0; 32],
+            runtime.get_storage_at_slot((popped_slot + U256::from(1)).to_be_bytes())
+        );
+    }
+
+    #[rstest]
+    fn test_vector_of_values(runtime: RuntimeSandbox) {
+        let (object_id, object_slot) = create_route(&runtime);
+
+        for value in [U256::from(1), U256::MAX] {
+            let call_data = pushValueCall::new((object_id, value)).abi_encode();
+            let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+            assert_eq!(0, result);
+        }
+
+        let call_data = valueAtCall::new((object_id, 1)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert_eq!(
+            U256::MAX,
+            valueAtCall::abi_decode_returns(&return_data).unwrap()
+        );
+
+        // Values occupy a slot each
+        let vec_slot = object_slot + U256::from(2);
+        assert_eq!(
+            U256::MAX.to_be_bytes::<32>(),
+            runtime.get_storage_at_slot((elements_slot(vec_slot) + U256::from(1)).to_be_bytes())
+        );
+    }
+
+    #[rstest]
+    #[should_panic(expected = "unreachable")]
+    fn test_index_out_of_bounds(runtime: RuntimeSandbox) {
+        let (object_id, _) = create_route(&runtime);
+
+        let call_data = positionXCall::new((object_id, 0)).abi_encode();
+        runtime.call_entrypoint(call_data).unwrap();
+    }
+}
+
+mod lazy_load {
+    use alloy_primitives::{FixedBytes, U256, address};
+    use alloy_sol_types::{SolCall, sol};
+
+    use super::*;
+
+    #[fixture]
+    fn runtime() -> RuntimeSandbox {
+        const MODULE_NAME: &str = "lazy_load";
+        const SOURCE_PATH: &str = "tests/storage/lazy_load.move";
+
+        let mut translated_package =
+            translate_test_package_with_framework(SOURCE_PATH, MODULE_NAME);
+
+        RuntimeSandbox::new(&mut translated_package)
+    }
+
+    sol!(
+        #[allow(missing_docs)]
+        function create() public view;
+        function readA(bytes32 id) public view returns (uint8);
+        function readB(bytes32 id) public view returns (uint256);
+        function readE(bytes32 id) public view returns (address);
+        function readF(bytes32 id) public view returns (uint8[]);
+        function readGH(bytes32 id) public view returns (uint32, uint16);
+        function readAH(bytes32 id) public view returns (uint8, uint16);
+        function readAll(bytes32 id) public view returns (uint8, uint256, uint64, address, uint8[], uint32, uint16);
+    );
+
+    #[rstest]
+    fn test_lazy_load_fields(runtime: RuntimeSandbox) {
+        let call_data = createCall::new(()).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        let object_id = runtime.log_events.lock().unwrap().recv().unwrap().data;
+        let object_id = FixedBytes::<32>::from_slice(&object_id);
+
+        let (result, return_data) = runtime
+            .call_entrypoint(readACall::new((object_id,)).abi_encode())
+            .unwrap();
+        assert_eq!(0, result);
+        assert_eq!(1, readACall::abi_decode_returns(&return_data).unwrap());
+
+        let (result, return_data) = runtime
+            .call_entrypoint(readBCall::new((object_id,)).abi_encode())
+            .unwrap();
+        assert_eq!(0, result);
+        assert_eq!(
+            U256::from(2),
+            readBCall::abi_decode_returns(&return_data).unwrap()
+        );
+
+        let (result, return_data) = runtime
+            .call_entrypoint(readECall::new((object_id,)).abi_encode())
+            .unwrap();
+        assert_eq!(0, result);
+        assert_eq!(
+            address!("0x000000000000000000000000000000000000cafe"),
+            readECall::abi_decode_returns(&return_data).unwrap()
+        );
+
+        let (result, return_data) = runtime
+            .call_entrypoint(readFCall::new((object_id,)).abi_encode())
+            .unwrap();
+        assert_eq!(0, result);
+        assert_eq!(
+            b"hello world".to_vec(),
+            readFCall::abi_decode_returns(&return_data).unwrap()
+        );
+
+        let (result, return_data) = runtime
+            .call_entrypoint(readGHCall::new((object_id,)).abi_encode())
+            .unwrap();
+        assert_eq!(0, result);
+        let return_data = readGHCall::abi_decode_returns(&return_data).unwrap();
+        assert_eq!((4, 5), (return_data._0, return_data._1));
+
+        let (result, return_data) = runtime
+            .call_entrypoint(readAHCall::new((object_id,)).abi_encode())
+            .unwrap();
+        assert_eq!(0, result);
+        let return_data = readAHCall::abi_decode_returns(&return_data).unwrap();
+        assert_eq!((1, 5), (return_data._0, return_data._1));
+
+        let (result, return_data) = runtime
+            .call_entrypoint(readAllCall::new((object_id,)).abi_encode())
+            .unwrap();
+        assert_eq!(0, result);
+        let return_data = readAllCall::abi_decode_returns(&return_data).unwrap();
+        assert_eq!(1, return_data._0);
+        assert_eq!(U256::from(2), return_data._1);
+        assert_eq!(3, return_data._2);
+        assert_eq!(
+            address!("0x000000000000000000000000000000000000cafe"),
+            return_data._3
+        );
+        assert_eq!(b"hello world".to_vec(), return_data._4);
+        assert_eq!(4, return_data._5);
+        assert_eq!(5, return_data._6);
+    }
+}
+
+mod namespace {
+    use alloy_primitives::{FixedBytes, U256, keccak256};
+    use alloy_sol_types::{SolCall, sol};
+
+    use super::*;
+    use crate::storage_transfer::{derive_object_slot, derive_object_slot_with_root};
+
+    #[fixture]
+    fn runtime() -> RuntimeSandbox {
+        const MODULE_NAME: &str = "namespace";
+        const SOURCE_PATH: &str = "tests/storage/namespace.move";
+
+        let mut translated_package =
+            translate_test_package_with_framework(SOURCE_PATH, MODULE_NAME);
+
+        RuntimeSandbox::new(&mut translated_package)
+    }
+
+    sol!(
+        #[allow(missing_docs)]
+        function create() public view;
+        function increment(bytes32 id) public view;
+        function read(bytes32 id) public view returns (uint256);
+    );
+
+    const SHARED: [u8; 20] = [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1];
+
+    /// keccak256(keccak256(namespace) - 1) & ~0xff
+    fn erc7201_root_slot(namespace: &str) -> [u8; 32] {
+        let namespace_hash = U256::from_be_bytes(keccak256(namespace.as_bytes()).0);
+        let mut slot = keccak256((namespace_hash - U256::from(1)).to_be_bytes::<32>()).0;
+        slot[31] = 0;
+        slot
+    }
+
+    #[rstest]
+    fn test_namespaced_object(runtime: RuntimeSandbox) {
+        let call_data = createCall::new(()).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        let object_id = runtime.log_events.lock().unwrap().recv().unwrap().data;
+        let object_id = FixedBytes::<32>::from_slice(&object_id);
+
+        let call_data = incrementCall::new((object_id,)).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        let call_data = readCall::new((object_id,)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert_eq!(
+            U256::from(8),
+            readCall::abi_decode_returns(&return_data).unwrap()
+        );
+
+        // The object lives in the objects mapping rooted at the namespace slot
+        let root = erc7201_root_slot("test.namespace");
+        let object_slot = derive_object_slot_with_root(&root, &SHARED, &object_id.0);
+        assert_eq!(object_id.0, runtime.get_storage_at_slot(object_slot.0));
+
+        let value_slot = U256::from_be_bytes(object_slot.0) + U256::from(1);
+        assert_eq!(
+            U256::from(8).to_be_bytes::<32>(),
+            runtime.get_storage_at_slot(value_slot.to_be_bytes())
+        );
+
+        // Nothing is written in the default objects mapping
+        let default_slot = derive_object_slot(&SHARED, &object_id.0);
+        assert_eq!(0, runtime.get_storage_writes_at_slot(default_slot.0));
+    }
+}
+
+mod enumerable_mapping {
+    use alloy_primitives::{Address, FixedBytes, U256, address};
+    use alloy_sol_types::{SolCall, sol};
+
+    use super::*;
+
+    #[fixture]
+    fn runtime() -> RuntimeSandbox {
+        const MODULE_NAME: &str = "enumerable_mapping";
+        const SOURCE_PATH: &str = "tests/storage/enumerable_mapping.move";
+
+        let mut translated_package =
+            translate_test_package_with_framework(SOURCE_PATH, MODULE_NAME);
+
+        RuntimeSandbox::new(&mut translated_package)
+    }
+
+    sol!(
+        #[allow(missing_docs)]
+        function create() public view;
+        function setBalance(bytes32 id, address owner, uint256 amount) public view;
+        function removeBalance(bytes32 id, address owner) public view;
+        function balanceOf(bytes32 id, address owner) public view returns (uint256);
+        function hasBalance(bytes32 id, address owner) public view returns (bool);
+        function length(bytes32 id) public view returns (uint64);
+        function ownerAt(bytes32 id, uint64 index) public view returns (address);
+        function owners(bytes32 id) public view returns (address[]);
+        function ownersRange(bytes32 id, uint64 from, uint64 to) public view returns (address[]);
+    );
+
+    fn owners(runtime: &RuntimeSandbox, object_id: FixedBytes<32>) -> Vec<Address> {
+        let call_data = ownersCall::new((object_id,)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        ownersCall::abi_decode_returns(&return_data).unwrap()
+    }
+
+    #[rstest]
+    fn test_enumerable_mapping(runtime: RuntimeSandbox) {
+        let call_data = createCall::new(()).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        let object_id = runtime.log_events.lock().unwrap().recv().unwrap().data;
+        let object_id = FixedBytes::<32>::from_slice(&object_id);
+
+        let alice = address!("0x00000000000000000000000000000000000a11ce");
+        let bob = address!("0x0000000000000000000000000000000000000b0b");
+        let carol = address!("0x00000000000000000000000000000000000ca401");
+
+        assert!(owners(&runtime, object_id).is_empty());
+
+        for (owner, amount) in [(alice, 10), (bob, 20), (carol, 30), (alice, 15)] {
+            let call_data =
+                setBalanceCall::new((object_id, owner, U256::from(amount))).abi_encode();
+            let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+            assert_eq!(0, result);
+        }
+
+        // Overwriting a value does not add the key twice
+        let call_data = lengthCall::new((object_id,)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert_eq!(3, lengthCall::abi_decode_returns(&return_data).unwrap());
+        assert_eq!(vec![alice, bob, carol], owners(&runtime, object_id));
+
+        let call_data = balanceOfCall::new((object_id, alice)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert_eq!(
+            U256::from(15),
+            balanceOfCall::abi_decode_returns(&return_data).unwrap()
+        );
+
+        let call_data = ownerAtCall::new((object_id, 1)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert_eq!(bob, ownerAtCall::abi_decode_returns(&return_data).unwrap());
+
+        let call_data = ownersRangeCall::new((object_id, 1, 10)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert_eq!(
+            vec![bob, carol],
+            ownersRangeCall::abi_decode_returns(&return_data).unwrap()
+        );
+
+        // Removing a key moves the last one to its position
+        let call_data = removeBalanceCall::new((object_id, alice)).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert_eq!(vec![carol, bob], owners(&runtime, object_id));
+
+        let call_data = hasBalanceCall::new((object_id, alice)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert!(!hasBalanceCall::abi_decode_returns(&return_data).unwrap());
+
+        // Reading a removed key aborts
+        let call_data = balanceOfCall::new((object_id, alice)).abi_encode();
+        assert!(runtime.call_entrypoint(call_data).is_err());
+
+        // Reading out of bounds aborts
+        let call_data = ownerAtCall::new((object_id, 2)).abi_encode();
+        assert!(runtime.call_entrypoint(call_data).is_err());
+    }
+}
+
+mod table {
+    use alloy_primitives::{FixedBytes, U256, address};
+    use alloy_sol_types::{SolCall, sol};
+
+    use super::*;
+
+    #[fixture]
+    fn runtime() -> RuntimeSandbox {
+        const MODULE_NAME: &str = "table";
+        const SOURCE_PATH: &str = "tests/storage/table.move";
+
+        let mut translated_package =
+            translate_test_package_with_framework(SOURCE_PATH, MODULE_NAME);
+
+        RuntimeSandbox::new(&mut translated_package)
+    }
+
+    sol!(
+        #[allow(missing_docs)]
+        function create() public view;
+        function addBalance(bytes32 id, address owner, uint256 amount) public view;
+        function balanceOf(bytes32 id, address owner) public view returns (uint256);
+        function increaseBalance(bytes32 id, address owner, uint256 amount) public view;
+        function removeBalance(bytes32 id, address owner) public view returns (uint256);
+        function hasBalance(bytes32 id, address owner) public view returns (bool);
+        function balancesLength(bytes32 id) public view returns (uint64);
+        function addPosition(bytes32 id, uint64 key, uint32 x, uint64 y) public view;
+        function movePosition(bytes32 id, uint64 key, uint32 dx, uint64 dy) public view;
+        function getPosition(bytes32 id, uint64 key) public view returns (uint32, uint64);
+        function destroyTable(bool fill) public view;
+        function bagAddU64(bytes32 id, uint64 key, uint64 value) public view;
+        function bagAddAddress(bytes32 id, address key, address value) public view;
+        function bagGetU64(bytes32 id, uint64 key) public view returns (uint64);
+        function bagGetAddress(bytes32 id, address key) public view returns (address);
+        function bagGetU64AsAddress(bytes32 id, uint64 key) public view returns (address);
+        function bagIncrementU64(bytes32 id, uint64 key) public view;
+        function bagRemoveU64(bytes32 id, uint64 key) public view returns (uint64);
+        function bagContains(bytes32 id, uint64 key) public view returns (bool);
+        function bagLength(bytes32 id) public view returns (uint64);
+    );
+
+    fn create_registry(runtime: &RuntimeSandbox) -> FixedBytes<32> {
+        let call_data = createCall::new(()).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        let object_id = runtime.log_events.lock().unwrap().recv().unwrap().data;
+        FixedBytes::<32>::from_slice(&object_id)
+    }
+
+    #[rstest]
+    fn test_table(runtime: RuntimeSandbox) {
+        let object_id = create_registry(&runtime);
+        let alice = address!("0x00000000000000000000000000000000000a11ce");
+        let bob = address!("0x0000000000000000000000000000000000000b0b");
+
+        // A zero value is still a present key
+        for (owner, amount) in [(alice, 10), (bob, 0)] {
+            let call_data =
+                addBalanceCall::new((object_id, owner, U256::from(amount))).abi_encode();
+            let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+            assert_eq!(0, result);
+        }
+
+        let call_data = hasBalanceCall::new((object_id, bob)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert!(hasBalanceCall::abi_decode_returns(&return_data).unwrap());
+
+        let call_data = balancesLengthCall::new((object_id,)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert_eq!(
+            2,
+            balancesLengthCall::abi_decode_returns(&return_data).unwrap()
+        );
+
+        // Adding an existing key aborts
+        let call_data = addBalanceCall::new((object_id, alice, U256::from(1))).abi_encode();
+        assert!(runtime.call_entrypoint(call_data).is_err());
+
+        // Changes made through a mutable borrow are persisted
+        let call_data = increaseBalanceCall::new((object_id, alice, U256::from(5))).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        let call_data = balanceOfCall::new((object_id, alice)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert_eq!(
+            U256::from(15),
+            balanceOfCall::abi_decode_returns(&return_data).unwrap()
+        );
+
+        let call_data = removeBalanceCall::new((object_id, alice)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert_eq!(
+            U256::from(15),
+            removeBalanceCall::abi_decode_returns(&return_data).unwrap()
+        );
+
+        let call_data = balancesLengthCall::new((object_id,)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert_eq!(
+            1,
+            balancesLengthCall::abi_decode_returns(&return_data).unwrap()
+        );
+
+        // Accessing a removed key aborts
+        let call_data = balanceOfCall::new((object_id, alice)).abi_encode();
+        assert!(runtime.call_entrypoint(call_data).is_err());
+    }
+
+    #[rstest]
+    fn test_table_of_structs(runtime: RuntimeSandbox) {
+        let object_id = create_registry(&runtime);
+
+        let call_data = addPositionCall::new((object_id, 7, 1, 2)).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        let call_data = movePositionCall::new((object_id, 7, 10, 20)).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        let call_data = getPositionCall::new((object_id, 7)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        let position = getPositionCall::abi_decode_returns(&return_data).unwrap();
+        assert_eq!((11, 22), (position._0, position._1));
+    }
+
+    #[rstest]
+    #[case(false, true)]
+    #[case(true, false)]
+    fn test_table_destroy_empty(
+        runtime: RuntimeSandbox,
+        #[case] fill: bool,
+        #[case] success: bool,
+    ) {
+        let call_data = destroyTableCall::new((fill,)).abi_encode();
+        assert_eq!(success, runtime.call_entrypoint(call_data).is_ok());
+    }
+
+    #[rstest]
+    fn test_bag(runtime: RuntimeSandbox) {
+        let object_id = create_registry(&runtime);
+        let key = address!("0x00000000000000000000000000000000000a11ce");
+
+        let call_data = bagAddU64Call::new((object_id, 1, 41)).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        let call_data = bagAddAddressCall::new((object_id, key, key)).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        let call_data = bagLengthCall::new((object_id,)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert_eq!(2, bagLengthCall::abi_decode_returns(&return_data).unwrap());
+
+        let call_data = bagIncrementU64Call::new((object_id, 1)).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        let call_data = bagGetU64Call::new((object_id, 1)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert_eq!(42, bagGetU64Call::abi_decode_returns(&return_data).unwrap());
+
+        let call_data = bagGetAddressCall::new((object_id, key)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert_eq!(
+            key,
+            bagGetAddressCall::abi_decode_returns(&return_data).unwrap()
+        );
+
+        // Reading a value as a different type aborts
+        let call_data = bagGetU64AsAddressCall::new((object_id, 1)).abi_encode();
+        assert!(runtime.call_entrypoint(call_data).is_err());
+
+        let call_data = bagRemoveU64Call::new((object_id, 1)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert_eq!(
+            42,
+            bagRemoveU64Call::abi_decode_returns(&return_data).unwrap()
+        );
+
+        let call_data = bagContainsCall::new((object_id, 1)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert!(!bagContainsCall::abi_decode_returns(&return_data).unwrap());
+
+        let call_data = bagLengthCall::new((object_id,)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert_eq!(1, bagLengthCall::abi_decode_returns(&return_data).unwrap());
+    }
+}
+
+mod pinned_slots {
+    use alloy_primitives::{FixedBytes, U256, address, keccak256};
+    use alloy_sol_types::{SolCall, sol};
+
+    use super::*;
+
+    #[fixture]
+    fn runtime() -> RuntimeSandbox {
+        const MODULE_NAME: &str = "pinned_slots";
+        const SOURCE_PATH: &str = "tests/storage/pinned_slots.move";
+
+        let mut translated_package =
+            translate_test_package_with_framework(SOURCE_PATH, MODULE_NAME);
+
+        RuntimeSandbox::new(&mut translated_package)
+    }
+
+    sol!(
+        #[allow(missing_docs)]
+        function create() public view;
+        function mint(bytes32 id, address to, uint256 amount) public view;
+        function balanceOf(bytes32 id, address owner) public view returns (uint256);
+        function totalSupply(bytes32 id) public view returns (uint256);
+        function owner(bytes32 id) public view returns (address);
+        function decimals(bytes32 id) public view returns (uint8);
+    );
+
+    #[rstest]
+    fn test_pinned_slots(runtime: RuntimeSandbox) {
+        let call_data = createCall::new(()).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        let object_id = runtime.log_events.lock().unwrap().recv().unwrap().data;
+        let object_id = FixedBytes::<32>::from_slice(&object_id);
+
+        let holder = address!("0x00000000000000000000000000000000abcdef01");
+        for _ in 0..2 {
+            let call_data = mintCall::new((object_id, holder, U256::from(500))).abi_encode();
+            let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+            assert_eq!(0, result);
+        }
+
+        let call_data = balanceOfCall::new((object_id, holder)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert_eq!(
+            U256::from(1000),
+            balanceOfCall::abi_decode_returns(&return_data).unwrap()
+        );
+
+        let call_data = totalSupplyCall::new((object_id,)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert_eq!(
+            U256::from(1000),
+            totalSupplyCall::abi_decode_returns(&return_data).unwrap()
+        );
+
+        let call_data = decimalsCall::new((object_id,)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert_eq!(18, decimalsCall::abi_decode_returns(&return_data).unwrap());
+
+        let call_data = ownerCall::new((object_id,)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        let owner = ownerCall::abi_decode_returns(&return_data).unwrap();
+
+        // balances[holder] is saved where Solidity saves `mapping(address => uint256)` at slot 0
+        let mut buf = [0u8; 64];
+        buf[..32].copy_from_slice(&holder.into_word().0);
+        let balance_slot = keccak256(buf);
+        assert_eq!(
+            U256::from(1000).to_be_bytes::<32>(),
+            runtime.get_storage_at_slot(balance_slot.0)
+        );
+
+        // The total supply follows the allowances mapping, at slot 2
+        assert_eq!(
+            U256::from(1000).to_be_bytes::<32>(),
+            runtime.get_storage_at_slot(U256::from(2).to_be_bytes())
+        );
+
+        // The owner is pinned at slot 0x10, and the decimals are packed after it
+        let mut expected = [0u8; 32];
+        expected[11] = 18;
+        expected[12..].copy_from_slice(owner.as_slice());
+        assert_eq!(
+            expected,
+            runtime.get_storage_at_slot(U256::from(0x10).to_be_bytes())
+        );
+    }
+}
+
+mod reentrancy {
+    use std::sync::Arc;
+
+    use alloy_primitives::{FixedBytes, address};
+    use alloy_sol_types::{SolCall, sol};
+    use move_bytecode_to_wasm::TranslationOptions;
+    use move_stylus_testing::call_result::{CallResult, RevertReason};
+
+    use crate::common::translate_test_package_with_framework_and_options;
+
+    use super::*;
+
+    const ERROR_REENTRANT_CALL: i32 = -101;
+    const MODULE_NAME: &str = "reentrancy";
+    const SOURCE_PATH: &str = "tests/storage/reentrancy.move";
+
+    #[fixture]
+    fn runtime() -> RuntimeSandbox {
+        let mut translated_package =
+            translate_test_package_with_framework(SOURCE_PATH, MODULE_NAME);
+
+        RuntimeSandbox::new(&mut translated_package)
+    }
+
+    #[fixture]
+    fn guarded_runtime() -> RuntimeSandbox {
+        let options = TranslationOptions {
+            reentrancy_guard: true,
+            ..Default::default()
+        };
+
+        let mut translated_package =
+            translate_test_package_with_framework_and_options(SOURCE_PATH, MODULE_NAME, &options);
+
+        RuntimeSandbox::new(&mut translated_package)
+    }
+
+    sol!(
+        #[allow(missing_docs)]
+        function create() public view;
+        function deposit(bytes32 id, uint64 amount) public view;
+        function depositReentrant(bytes32 id, uint64 amount) public view;
+        function balance(bytes32 id) public view returns (uint64);
+        function withdraw(bytes32 id, address recipient, uint64 amount) public view;
+        function createLedger() public view;
+        function openAccount(bytes32 id, address account, uint64 amount) public view;
+        function creditAndNotify(bytes32 id, address account, uint64 amount) public view;
+        function ledgerBalance(bytes32 id, address account) public view returns (uint64);
+    );
+
+    #[rstest]
+    fn test_non_reentrant(runtime: RuntimeSandbox) {
+        let call_data = createCall::new(()).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        let object_id = runtime.log_events.lock().unwrap().recv().unwrap().data;
+        let object_id = FixedBytes::<32>::from_slice(&object_id);
+
+        let call_data = depositCall::new((object_id, 10)).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        // Reentrant calls to the guarded function are rejected before it is executed
+        runtime.set_msg_reentrant(true);
+
+        let call_data = depositCall::new((object_id, 5)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(ERROR_REENTRANT_CALL, result);
+        assert!(return_data.is_empty());
+
+        // Functions without the attribute are not affected
+        let call_data = depositReentrantCall::new((object_id, 7)).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        let call_data = balanceCall::new((object_id,)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        let return_data = balanceCall::abi_decode_returns(&return_data).unwrap();
+        assert_eq!(0, result);
+        assert_eq!(17, return_data);
+
+        runtime.set_msg_reentrant(false);
+
+        let call_data = depositCall::new((object_id, 5)).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        let call_data = balanceCall::new((object_id,)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        let return_data = balanceCall::abi_decode_returns(&return_data).unwrap();
+        assert_eq!(0, result);
+        assert_eq!(22, return_data);
+    }
+
+    #[rstest]
+    fn test_reentrancy_attack(runtime: RuntimeSandbox) {
+        let runtime = Arc::new(runtime);
+
+        let call_data = createCall::new(()).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        let object_id = runtime.log_events.lock().unwrap().recv().unwrap().data;
+        let object_id = FixedBytes::<32>::from_slice(&object_id);
+
+        let call_data = depositCall::new((object_id, 10)).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        // The attacker tries to withdraw again before its first withdrawal is accounted
+        let attacker_address = address!("0x00000000000000000000000000000000a77ac4e1");
+        let attacker = runtime.register_reentrancy_attacker(
+            attacker_address.0.0,
+            withdrawCall::new((object_id, attacker_address, 1)).abi_encode(),
+        );
+
+        let call_data = withdrawCall::new((object_id, attacker_address, 1)).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        // The guard rejects the re-entered call, without data
+        assert_eq!(
+            vec![CallResult::Revert(RevertReason::Raw(vec![]))],
+            attacker.reentries()
+        );
+
+        let call_data = balanceCall::new((object_id,)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert_eq!(9, balanceCall::abi_decode_returns(&return_data).unwrap());
+
+        // The functions without the attribute can be re-entered
+        let attacker_address = address!("0x00000000000000000000000000000000a77ac4e2");
+        let attacker = runtime.register_reentrancy_attacker(
+            attacker_address.0.0,
+            depositReentrantCall::new((object_id, 100)).abi_encode(),
+        );
+
+        let call_data = withdrawCall::new((object_id, attacker_address, 1)).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        let reentries = attacker.reentries();
+        assert_eq!(1, reentries.len());
+        assert!(reentries[0].is_success());
+    }
+
+    #[rstest]
+    fn test_reentrant_call_sees_borrowed_table_value(runtime: RuntimeSandbox) {
+        let runtime = Arc::new(runtime);
+
+        let call_data = createLedgerCall::new(()).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        let object_id = runtime.log_events.lock().unwrap().recv().unwrap().data;
+        let object_id = FixedBytes::<32>::from_slice(&object_id);
+
+        let account = address!("0x00000000000000000000000000000000a77ac4e3");
+        let call_data = openAccountCall::new((object_id, account, 10)).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        // The account reads its balance while the credit still holds the borrow of it
+        let attacker = runtime.register_reentrancy_attacker(
+            account.0.0,
+            ledgerBalanceCall::new((object_id, account)).abi_encode(),
+        );
+
+        let call_data = creditAndNotifyCall::new((object_id, account, 5)).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        // The value written through the borrow is saved before the call
+        assert_eq!(
+            vec![CallResult::Success(ledgerBalanceCall::abi_encode_returns(
+                &15
+            ))],
+            attacker.reentries()
+        );
+
+        // And the changes made through it after the call are kept
+        let call_data = ledgerBalanceCall::new((object_id, account)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert_eq!(
+            16,
+            ledgerBalanceCall::abi_decode_returns(&return_data).unwrap()
+        );
+    }
+
+    #[rstest]
+    fn test_reentrancy_guard(#[from(guarded_runtime)] runtime: RuntimeSandbox) {
+        let call_data = createCall::new(()).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        let object_id = runtime.log_events.lock().unwrap().recv().unwrap().data;
+        let object_id = FixedBytes::<32>::from_slice(&object_id);
+
+        runtime.set_msg_reentrant(true);
+
+        // Every entry function is guarded, with or without the attribute
+        let call_data = depositCall::new((object_id, 5)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(ERROR_REENTRANT_CALL, result);
+        assert!(return_data.is_empty());
+
+        let call_data = depositReentrantCall::new((object_id, 7)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(ERROR_REENTRANT_CALL, result);
+        assert!(return_data.is_empty());
+
+        let call_data = balanceCall::new((object_id,)).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(ERROR_REENTRANT_CALL, result);
+
+        runtime.set_msg_reentrant(false);
+
+        let call_data = depositReentrantCall::new((object_id, 7)).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        let call_data = balanceCall::new((object_id,)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        let return_data = balanceCall::abi_decode_returns(&return_data).unwrap();
+        assert_eq!(0, result);
+        assert_eq!(7, return_data);
+    }
+}
+
+mod versioning {
+    use alloy_primitives::{FixedBytes, U256};
+    use alloy_sol_types::{SolCall, sol};
+
+    use crate::storage_transfer::derive_object_slot;
+
+    use super::*;
+
+    const SHARED: [u8; 20] = [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1];
+
+    #[fixture]
+    fn runtime() -> RuntimeSandbox {
+        const MODULE_NAME: &str = "versioning";
+        const SOURCE_PATH: &str = "tests/storage/versioning.move";
+
+        let mut translated_package =
+            translate_test_package_with_framework(SOURCE_PATH, MODULE_NAME);
+
+        RuntimeSandbox::new(&mut translated_package)
+    }
+
+    sol!(
+        #[allow(missing_docs)]
+        function create(uint64 fee) public view;
+        function fee(bytes32 id) public view returns (uint64);
+        function maxFee(bytes32 id) public view returns (uint64);
+        function setFee(bytes32 id, uint64 fee) public view;
+    );
+
+    /// Creates a config with a fee of 5 and returns its id and first slot
+    fn create_config(runtime: &RuntimeSandbox) -> (FixedBytes<32>, U256) {
+        let call_data = createCall::new((5,)).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        let object_id = runtime.log_events.lock().unwrap().recv().unwrap().data;
+        let object_id = FixedBytes::<32>::from_slice(&object_id);
+        let object_slot = derive_object_slot(&SHARED, &object_id.0);
+
+        (object_id, U256::from_be_bytes(object_slot.0))
+    }
+
+    fn call_u64<T: SolCall<Return = u64>>(runtime: &RuntimeSandbox, call: T) -> u64 {
+        let (result, return_data) = runtime.call_entrypoint(call.abi_encode()).unwrap();
+        assert_eq!(0, result);
+        T::abi_decode_returns(&return_data).unwrap()
+    }
+
+    #[rstest]
+    fn test_version_is_saved(runtime: RuntimeSandbox) {
+        let (object_id, object_slot) = create_config(&runtime);
+
+        // The version is saved in the first slot, and the UID and the fields follow it
+        assert_eq!(
+            U256::from(2).to_be_bytes(),
+            runtime.get_storage_at_slot(object_slot.to_be_bytes())
+        );
+        assert_eq!(
+            object_id.0,
+            runtime.get_storage_at_slot((object_slot + U256::from(1)).to_be_bytes())
+        );
+
+        assert_eq!(5, call_u64(&runtime, feeCall::new((object_id,))));
+        assert_eq!(10, call_u64(&runtime, maxFeeCall::new((object_id,))));
+    }
+
+    #[rstest]
+    fn test_older_version_is_migrated(runtime: RuntimeSandbox) {
+        let (object_id, object_slot) = create_config(&runtime);
+
+        // Rewrite the object as the version 1 saved it, without the max fee
+        let fields_slot = (object_slot + U256::from(1) + U256::from(1)).to_be_bytes();
+        runtime.set_storage_at_slot(object_slot.to_be_bytes(), U256::from(1).to_be_bytes());
+        runtime.set_storage_at_slot(fields_slot, U256::from(5).to_be_bytes());
+
+        // Reading the object runs the migration, but it is not saved
+        assert_eq!(50, call_u64(&runtime, maxFeeCall::new((object_id,))));
+        assert_eq!(
+            U256::from(1).to_be_bytes(),
+            runtime.get_storage_at_slot(object_slot.to_be_bytes())
+        );
+
+        // Borrowing it mutably saves the migrated object with the current version
+        let call_data = setFeeCall::new((object_id, 7)).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        assert_eq!(
+            U256::from(2).to_be_bytes(),
+            runtime.get_storage_at_slot(object_slot.to_be_bytes())
+        );
+        assert_eq!(7, call_u64(&runtime, feeCall::new((object_id,))));
+        assert_eq!(50, call_u64(&runtime, maxFeeCall::new((object_id,))));
+    }
+
+    #[rstest]
+    #[should_panic(expected = "unreachable")]
+    fn test_newer_version_aborts(runtime: RuntimeSandbox) {
+        let (object_id, object_slot) = create_config(&runtime);
+
+        runtime.set_storage_at_slot(object_slot.to_be_bytes(), U256::from(3).to_be_bytes());
+
+        let call_data = feeCall::new((object_id,)).abi_encode();
+        runtime.call_entrypoint(call_data).unwrap();
+    }
+}
+
+mod view {
+    use alloy_primitives::FixedBytes;
+    use alloy_sol_types::{SolCall, sol};
+    use move_stylus_testing::StorageWriteInView;
+
+    use super::*;
+
+    #[fixture]
+    fn runtime() -> RuntimeSandbox {
+        const MODULE_NAME: &str = "view";
+        const SOURCE_PATH: &str = "tests/storage/view.move";
+
+        let mut translated_package =
+            translate_test_package_with_framework(SOURCE_PATH, MODULE_NAME);
+
+        RuntimeSandbox::new(&mut translated_package)
+    }
+
+    sol!(
+        #[allow(missing_docs)]
+        function create() public view;
+        function deposit(bytes32 id, uint64 amount) public view;
+        function balance(bytes32 id) public view returns (uint64);
+        function doubledBalance(bytes32 id) public view returns (uint64);
+    );
+
+    #[rstest]
+    fn test_view_functions(runtime: RuntimeSandbox) {
+        let call_data = createCall::new(()).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        let object_id = runtime.log_events.lock().unwrap().recv().unwrap().data;
+        let object_id = FixedBytes::<32>::from_slice(&object_id);
+
+        let call_data = depositCall::new((object_id, 21)).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        let call_data = balanceCall::new((object_id,)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        let return_data = balanceCall::abi_decode_returns(&return_data).unwrap();
+        assert_eq!(0, result);
+        assert_eq!(21, return_data);
+
+        let call_data = doubledBalanceCall::new((object_id,)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        let return_data = doubledBalanceCall::abi_decode_returns(&return_data).unwrap();
+        assert_eq!(0, result);
+        assert_eq!(42, return_data);
+    }
+
+    #[rstest]
+    fn test_view_functions_without_storage_writes(runtime: RuntimeSandbox) {
+        let call_data = createCall::new(()).abi_encode();
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        let object_id = runtime.log_events.lock().unwrap().recv().unwrap().data;
+        let object_id = FixedBytes::<32>::from_slice(&object_id);
+
+        for call_data in [
+            balanceCall::new((object_id,)).abi_encode(),
+            doubledBalanceCall::new((object_id,)).abi_encode(),
+        ] {
+            let (result, _) = runtime.call_view(call_data).unwrap();
+            assert_eq!(0, result);
+        }
+
+        // The functions that write to the storage fail as views
+        let call_data = depositCall::new((object_id, 21)).abi_encode();
+        let error = runtime.call_view(call_data).unwrap_err();
+        assert!(error.is::<StorageWriteInView>());
+
+        // The balance was not written
+        let call_data = balanceCall::new((object_id,)).abi_encode();
+        let (_, return_data) = runtime.call_view(call_data).unwrap();
+        assert_eq!(0, balanceCall::abi_decode_returns(&return_data).unwrap());
+    }
+
+    #[test]
+    #[should_panic(expected = "view_emits_event::log_balance calls")]
+    fn test_view_function_emitting_event() {
+        translate_test_package_with_framework(
+            "tests/storage/view_emits_event.move",
+            "view_emits_event",
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "argument 0 is a storage object taken by mutable reference")]
+    fn test_view_function_with_mutable_storage_object() {
+        translate_test_package_with_framework(
+            "tests/storage/view_mutable_argument.move",
+            "view_mutable_argument",
+        );
+    }
+}
+
+mod storage_layout {
+    use crate::common::translate_test_package_storage_layout;
+
+    #[test]
+    fn test_storage_layout() {
+        let layout = translate_test_package_storage_layout(
+            "tests/storage/pinned_slots.move",
+            "pinned_slots",
+        );
+
+        let entries: Vec<(&str, &str, u32, &str)> = layout
+            .storage
+            .iter()
+            .map(|e| {
+                (
+                    e.label.as_str(),
+                    e.slot.as_str(),
+                    e.offset,
+                    e.type_id.as_str(),
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            vec![
+                (
+                    "Token",
+                    "0",
+                    0,
+                    "t_mapping(t_bytes32,t_mapping(t_bytes32,t_struct(Token)_storage))"
+                ),
+                ("Token.balances", "0", 0, "t_mapping(t_address,t_uint256)"),
+                (
+                    "Token.allowances",
+                    "1",
+                    0,
+                    "t_mapping(t_address,t_mapping(t_address,t_uint256))"
+                ),
+                ("Token.total_supply", "2", 0, "t_uint256"),
+                ("Token.owner", "16", 0, "t_address"),
+                ("Token.decimals", "16", 20, "t_uint8"),
+            ],
+            entries
+        );
+
+        let balances = &layout.types["t_mapping(t_address,t_uint256)"];
+        assert_eq!("mapping", balances.encoding);
+        assert_eq!("mapping(address => uint256)", balances.label);
+        assert_eq!(Some("t_address"), balances.key.as_deref());
+        assert_eq!(Some("t_uint256"), balances.value.as_deref());
+
+        // Only the UID is saved relative to the object's slot
+        let token = &layout.types["t_struct(Token)_storage"];
+        assert_eq!("32", token.number_of_bytes);
+        assert_eq!(1, token.members.as_ref().unwrap().len());
+
+        let json: serde_json::Value = serde_json::from_str(&layout.to_json()).unwrap();
+        assert_eq!("16", json["storage"][4]["slot"]);
+        assert_eq!("t_address", json["storage"][4]["type"]);
+        assert_eq!("20", json["types"]["t_address"]["numberOfBytes"]);
+    }
+}
+
+mod storage_seeding {
+    use alloy_primitives::{FixedBytes, U256, address};
+    use alloy_sol_types::{SolCall, sol};
+    use move_stylus_testing::storage_layout::StorageLayout;
+
+    use super::*;
+    use crate::common::translate_test_package_storage_layout;
+
+    const SHARED: [u8; 20] = [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1];
+
+    sol!(
+        #[allow(missing_docs)]
+        function createShared() public view;
+        function readValue(bytes32 id) public view returns (uint64);
+        function create() public view;
+        function balanceOf(bytes32 id, address owner) public view returns (uint256);
+        function allowance(bytes32 id, address owner, address spender) public view returns (uint256);
+    );
+
+    fn setup(source_path: &str, module_name: &str) -> (RuntimeSandbox, StorageLayout) {
+        let mut translated_package =
+            translate_test_package_with_framework(source_path, module_name);
+        let layout = translate_test_package_storage_layout(source_path, module_name);
+
+        (
+            RuntimeSandbox::new(&mut translated_package),
+            StorageLayout::from_json(&layout.to_json()).unwrap(),
+        )
+    }
+
+    fn create_object(runtime: &RuntimeSandbox, call_data: Vec<u8>) -> FixedBytes<32> {
+        let (result, _) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+
+        // Read the object id emmited from the contract's events
+        let object_id = runtime.log_events.lock().unwrap().recv().unwrap().data;
+        FixedBytes::<32>::from_slice(&object_id)
+    }
+
+    #[test]
+    fn test_set_object_field() {
+        let (runtime, layout) = setup("tests/storage/transfer.move", "transfer");
+        let object_id = create_object(&runtime, createSharedCall::new(()).abi_encode());
+
+        runtime
+            .set_object_field(
+                &layout,
+                "Foo",
+                SHARED,
+                object_id.0,
+                "value",
+                &42u64.to_be_bytes(),
+            )
+            .unwrap();
+
+        let call_data = readValueCall::new((object_id,)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert_eq!(42, readValueCall::abi_decode_returns(&return_data).unwrap());
+
+        // The value must take the field's size
+        assert!(
+            runtime
+                .set_object_field(&layout, "Foo", SHARED, object_id.0, "value", &[42])
+                .is_err()
+        );
+        assert!(
+            runtime
+                .set_object_field(&layout, "Foo", SHARED, object_id.0, "missing", &[42])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_set_mapping_entry() {
+        let (runtime, layout) = setup("tests/storage/mapping.move", "mapping");
+        let object_id = create_object(&runtime, createCall::new(()).abi_encode());
+        let owner = address!("0x00000000000000000000000000000000abcdef01");
+        let spender = address!("0x00000000000000000000000000000000abcdef02");
+
+        let balances = layout
+            .object_field("Ledger", SHARED, object_id.0, "balances")
+            .unwrap();
+        runtime.set_mapping_entry(
+            balances.slot,
+            &[owner.into_word().0],
+            U256::from(1000).to_be_bytes(),
+        );
+
+        let call_data = balanceOfCall::new((object_id, owner)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert_eq!(
+            U256::from(1000),
+            balanceOfCall::abi_decode_returns(&return_data).unwrap()
+        );
+
+        let allowances = layout
+            .object_field("Ledger", SHARED, object_id.0, "allowances")
+            .unwrap();
+        runtime.set_mapping_entry(
+            allowances.slot,
+            &[owner.into_word().0, spender.into_word().0],
+            U256::from(42).to_be_bytes(),
+        );
+
+        let call_data = allowanceCall::new((object_id, owner, spender)).abi_encode();
+        let (result, return_data) = runtime.call_entrypoint(call_data).unwrap();
+        assert_eq!(0, result);
+        assert_eq!(
+            U256::from(42),
+            allowanceCall::abi_decode_returns(&return_data).unwrap()
+        );
+    }
+}
+
+mod storage_gas_report {
+    use move_binary_format::file_format::{Bytecode::*, FieldHandleIndex, StructDefinitionIndex};
+
+    use crate::common::{
+        translate_test_package_storage_gas_report,
+        translate_test_package_storage_gas_report_with_code,
+    };
+
+    #[test]
+    fn test_storage_gas_report() {
+        let report =
+            translate_test_package_storage_gas_report("tests/storage/counter.move", "counter");
+
+        let estimate = |name: &str| {
+            let function = report
+                .functions
+                .iter()
+                .find(|f| f.function == name)
+                .unwrap();
+            (
+                function.sloads,
+                function.warm_sstores,
+                function.cold_sstores,
+                function.gas,
+                function.bounded,
+            )
+        };
+
+        // Creating the id updates the ids counter. Sharing the new counter saves its two slots,
+        // and clears where it was saved before
+        assert_eq!((1, 3, 2, 106300, true), estimate("create"));
+        // The counter is looked up in the signer's and the shared objects, and saved back
+        assert_eq!((3, 2, 0, 46300, true), estimate("increment"));
+        assert_eq!((3, 2, 0, 46300, true), estimate("set_value"));
+        // Immutable references are also looked up in the frozen objects, and only the slots of
+        // the fields used are read
+        assert_eq!((4, 0, 0, 8400, true), estimate("read"));
+
+        let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+        assert_eq!(4, json["functions"].as_array().unwrap().len());
+        assert!(json["functions"][0]["warmSstores"].is_number());
+    }
+
+    #[test]
+    fn test_storage_gas_report_global_storage() {
+        let report = translate_test_package_storage_gas_report_with_code(
+            "tests/storage/global_storage.move",
+            "global_storage",
+            |module| {
+                let balance = StructDefinitionIndex(0);
+                let value_field = FieldHandleIndex(
+                    module
+                        .field_handles
+                        .iter()
+                        .position(|handle| handle.owner == balance && handle.field == 0)
+                        .unwrap() as u16,
+                );
+
+                vec![
+                    (
+                        "publish",
+                        vec![
+                            MoveLoc(0),
+                            MoveLoc(1),
+                            MoveLoc(2),
+                            Pack(balance),
+                            MoveToDeprecated(balance),
+                            Ret,
+                        ],
+                    ),
+                    (
+                        "remove",
+                        vec![
+                            MoveLoc(0),
+                            MoveFromDeprecated(balance),
+                            Unpack(balance),
+                            Pop,
+                            Ret,
+                        ],
+                    ),
+                    (
+                        "has_balance",
+                        vec![MoveLoc(0), ExistsDeprecated(balance), Ret],
+                    ),
+                    (
+                        "value",
+                        vec![
+                            MoveLoc(0),
+                            ImmBorrowGlobalDeprecated(balance),
+                            ImmBorrowField(value_field),
+                            ReadRef,
+                            Ret,
+                        ],
+                    ),
+                    (
+                        "set_value",
+                        vec![
+                            MoveLoc(1),
+                            MoveLoc(0),
+                            MutBorrowGlobalDeprecated(balance),
+                            MutBorrowField(value_field),
+                            WriteRef,
+                            Ret,
+                        ],
+                    ),
+                ]
+            },
+        );
+
+        let estimate = |name: &str| {
+            let function = report
+                .functions
+                .iter()
+                .find(|f| f.function == name)
+                .unwrap();
+            (
+                function.sloads,
+                function.warm_sstores,
+                function.cold_sstores,
+                function.gas,
+                function.bounded,
+            )
+        };
+
+        // The balance occupies two slots, saved after the slot of its published flag. Publishing
+        // checks the flag, sets it and saves the balance
+        assert_eq!((1, 1, 2, 66300, true), estimate("publish"));
+        // Removing reads the flag and the balance, and clears all of them
+        assert_eq!((3, 3, 0, 66300, true), estimate("remove"));
+        assert_eq!((1, 0, 0, 2100, true), estimate("has_balance"));
+        assert_eq!((3, 0, 0, 6300, true), estimate("value"));
+        // Mutable borrows write the balance back
+        assert_eq!((3, 2, 0, 46300, true), estimate("set_value"));
+    }
 }
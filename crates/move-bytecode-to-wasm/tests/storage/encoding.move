@@ -1,5 +1,6 @@
 module test::storage_encoding;
 
+use std::ascii::String;
 use stylus::object::UID;
 
 // This function will facilitate the reading from the test.
@@ -114,3 +115,26 @@ public fun save_static_nested_struct(
 public fun read_static_nested_struct(): StaticNestedStruct {
     read_slot<StaticNestedStruct>(0)
 }
+
+public struct DynamicFields has key {
+    id: UID,
+    a: u32,
+    b: vector<u8>,
+    c: String,
+    d: u64,
+}
+
+public fun save_dynamic_fields(
+    id: UID,
+    a: u32,
+    b: vector<u8>,
+    c: vector<u8>,
+    d: u64
+) {
+    let struct_ = DynamicFields { id, a, b, c: c.to_ascii_string(), d };
+    save_in_slot(struct_, 0);
+}
+
+public fun read_dynamic_fields(): DynamicFields {
+    read_slot<DynamicFields>(0)
+}
@@ -0,0 +1,52 @@
+module test::enumerable_mapping;
+
+use stylus::tx_context::TxContext;
+use stylus::object as object;
+use stylus::object::UID;
+use stylus::transfer as transfer;
+use stylus::enumerable_mapping as enumerable_mapping;
+use stylus::enumerable_mapping::EnumerableMapping;
+
+public struct Registry has key {
+    id: UID,
+    balances: EnumerableMapping<address, u256>,
+}
+
+public fun create(ctx: &mut TxContext) {
+    transfer::share_object(Registry {
+        id: object::new(ctx),
+        balances: enumerable_mapping::new(),
+    });
+}
+
+public fun set_balance(registry: &mut Registry, owner: address, amount: u256) {
+    enumerable_mapping::set(&mut registry.balances, owner, amount);
+}
+
+public fun remove_balance(registry: &mut Registry, owner: address) {
+    enumerable_mapping::remove(&mut registry.balances, owner);
+}
+
+public fun balance_of(registry: &Registry, owner: address): u256 {
+    enumerable_mapping::get(&registry.balances, owner)
+}
+
+public fun has_balance(registry: &Registry, owner: address): bool {
+    enumerable_mapping::contains(&registry.balances, owner)
+}
+
+public fun length(registry: &Registry): u64 {
+    enumerable_mapping::length(&registry.balances)
+}
+
+public fun owner_at(registry: &Registry, index: u64): address {
+    enumerable_mapping::key_at(&registry.balances, index)
+}
+
+public fun owners(registry: &Registry): vector<address> {
+    enumerable_mapping::keys(&registry.balances)
+}
+
+public fun owners_range(registry: &Registry, from: u64, to: u64): vector<address> {
+    enumerable_mapping::keys_range(&registry.balances, from, to)
+}
@@ -0,0 +1,33 @@
+module test::global_storage;
+
+// The compiler doesn't emit the global storage bytecodes, so the tests replace the code of these
+// functions with them
+public struct Balance has store, drop {
+    value: u64,
+    limit: u256,
+}
+
+public fun publish(_account: &signer, _value: u64, _limit: u256) {
+    abort 0
+}
+
+public fun remove(_owner: address): u64 {
+    abort 0
+}
+
+public fun has_balance(_owner: address): bool {
+    abort 0
+}
+
+public fun value(_owner: address): u64 {
+    abort 0
+}
+
+public fun set_value(_owner: address, _value: u64) {
+    abort 0
+}
+
+// Declares the handle of the field borrowed by `value` and `set_value`
+fun set_balance_value(balance: &mut Balance, value: u64) {
+    balance.value = value;
+}
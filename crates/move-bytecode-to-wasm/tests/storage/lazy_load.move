@@ -0,0 +1,67 @@
+module test::lazy_load;
+
+use stylus::tx_context::TxContext;
+use stylus::object as object;
+use stylus::object::UID;
+use stylus::transfer as transfer;
+
+public struct Child has store {
+    d: u64,
+    e: address,
+}
+
+public struct Data has key {
+    id: UID,
+    a: u8,
+    b: u256,
+    c: Child,
+    f: vector<u8>,
+    g: u32,
+    h: u16,
+}
+
+public fun create(ctx: &mut TxContext) {
+    transfer::share_object(Data {
+        id: object::new(ctx),
+        a: 1,
+        b: 2,
+        c: Child { d: 3, e: @0xcafe },
+        f: b"hello world",
+        g: 4,
+        h: 5,
+    });
+}
+
+// The following functions only access some of the fields, so only those are read from storage
+public fun read_a(data: &Data): u8 {
+    data.a
+}
+
+public fun read_b(data: &Data): u256 {
+    data.b
+}
+
+public fun read_e(data: &Data): address {
+    data.c.e
+}
+
+public fun read_f(data: &Data): vector<u8> {
+    data.f
+}
+
+public fun read_g_h(data: &Data): (u32, u16) {
+    (data.g, data.h)
+}
+
+public fun read_a_h(data: &Data): (u8, u16) {
+    (data.a, data.h)
+}
+
+// The reference is passed to another function, so the whole struct is read from storage
+public fun read_all(data: &Data): (u8, u256, u64, address, vector<u8>, u32, u16) {
+    unpack(data)
+}
+
+fun unpack(data: &Data): (u8, u256, u64, address, vector<u8>, u32, u16) {
+    (data.a, data.b, data.c.d, data.c.e, data.f, data.g, data.h)
+}
@@ -0,0 +1,150 @@
+module test::mapping;
+
+use stylus::tx_context::TxContext;
+use stylus::object as object;
+use stylus::object::UID;
+use stylus::transfer as transfer;
+use stylus::mapping as mapping;
+use stylus::mapping::Mapping;
+use stylus::storage as storage;
+
+public struct Position has store, copy, drop {
+    x: u32,
+    y: u64,
+    owner: address,
+}
+
+// Takes three slots, one for each field
+public struct Record has store, copy, drop {
+    amount: u256,
+    limit: u256,
+    owner: address,
+}
+
+public struct Ledger has key {
+    id: UID,
+    balances: Mapping<address, u256>,
+    allowances: Mapping<address, Mapping<address, u256>>,
+    positions: Mapping<u64, Position>,
+    records: Mapping<u64, Record>,
+}
+
+public fun create(ctx: &mut TxContext) {
+    transfer::share_object(Ledger {
+        id: object::new(ctx),
+        balances: mapping::new(),
+        allowances: mapping::new(),
+        positions: mapping::new(),
+        records: mapping::new(),
+    });
+}
+
+public fun set_balance(ledger: &mut Ledger, owner: address, amount: u256) {
+    mapping::set(&mut ledger.balances, owner, amount);
+}
+
+public fun set_balance_twice(ledger: &mut Ledger, owner: address, first: u256, second: u256) {
+    mapping::set(&mut ledger.balances, owner, first);
+    mapping::set(&mut ledger.balances, owner, second);
+}
+
+public fun set_balance_twice_flushed(
+    ledger: &mut Ledger,
+    owner: address,
+    first: u256,
+    second: u256,
+    clear: bool,
+) {
+    mapping::set(&mut ledger.balances, owner, first);
+    storage::flush(clear);
+    mapping::set(&mut ledger.balances, owner, second);
+}
+
+public fun add_to_balance(ledger: &mut Ledger, owner: address, amount: u256, times: u64) {
+    let mut i = 0;
+    while (i < times) {
+        let balance = mapping::get(&ledger.balances, owner);
+        mapping::set(&mut ledger.balances, owner, balance + amount);
+        i = i + 1;
+    }
+}
+
+public fun remove_balance(ledger: &mut Ledger, owner: address) {
+    mapping::remove(&mut ledger.balances, owner);
+}
+
+public fun balance_of(ledger: &Ledger, owner: address): u256 {
+    mapping::get(&ledger.balances, owner)
+}
+
+public fun has_balance(ledger: &Ledger, owner: address): bool {
+    mapping::contains(&ledger.balances, owner)
+}
+
+public fun approve(ledger: &mut Ledger, owner: address, spender: address, amount: u256) {
+    let mut owner_allowances = mapping::borrow_mapping(&ledger.allowances, owner);
+    mapping::set(&mut owner_allowances, spender, amount);
+}
+
+public fun allowance(ledger: &Ledger, owner: address, spender: address): u256 {
+    let owner_allowances = mapping::borrow_mapping(&ledger.allowances, owner);
+    mapping::get(&owner_allowances, spender)
+}
+
+public fun set_position(ledger: &mut Ledger, index: u64, x: u32, y: u64, owner: address) {
+    mapping::set(&mut ledger.positions, index, Position { x, y, owner });
+}
+
+// Writes more positions than the storage cache holds, and reads them back
+public fun set_positions(ledger: &mut Ledger, count: u64, owner: address): u64 {
+    let mut i = 0;
+    while (i < count) {
+        mapping::set(&mut ledger.positions, i, Position { x: (i as u32), y: i, owner });
+        i = i + 1;
+    };
+
+    let mut sum = 0;
+    let mut i = 0;
+    while (i < count) {
+        let position = mapping::get(&ledger.positions, i);
+        sum = sum + position.y;
+        i = i + 1;
+    };
+    sum
+}
+
+public fun remove_position(ledger: &mut Ledger, index: u64) {
+    mapping::remove(&mut ledger.positions, index);
+}
+
+public fun position_x(ledger: &Ledger, index: u64): u32 {
+    let position = mapping::get(&ledger.positions, index);
+    position.x
+}
+
+public fun position_y(ledger: &Ledger, index: u64): u64 {
+    let position = mapping::get(&ledger.positions, index);
+    position.y
+}
+
+public fun position_owner(ledger: &Ledger, index: u64): address {
+    let position = mapping::get(&ledger.positions, index);
+    position.owner
+}
+
+public fun set_record(ledger: &mut Ledger, index: u64, amount: u256, limit: u256, owner: address) {
+    mapping::set(&mut ledger.records, index, Record { amount, limit, owner });
+}
+
+public fun remove_record(ledger: &mut Ledger, index: u64) {
+    mapping::remove(&mut ledger.records, index);
+}
+
+public fun has_record(ledger: &Ledger, index: u64): bool {
+    mapping::contains(&ledger.records, index)
+}
+
+public fun record_limit(ledger: &Ledger, index: u64): u256 {
+    let record = mapping::get(&ledger.records, index);
+    record.limit
+}
@@ -0,0 +1,27 @@
+module test::namespace;
+
+use stylus::tx_context::TxContext;
+use stylus::object as object;
+use stylus::object::UID;
+use stylus::transfer as transfer;
+
+#[ext(storage_namespace = b"test.namespace")]
+public struct Config has key {
+    id: UID,
+    value: u256,
+}
+
+public fun create(ctx: &mut TxContext) {
+    transfer::share_object(Config {
+        id: object::new(ctx),
+        value: 7,
+    });
+}
+
+public fun increment(config: &mut Config) {
+    config.value = config.value + 1;
+}
+
+public fun read(config: &Config): u256 {
+    config.value
+}
@@ -0,0 +1,53 @@
+module test::pinned_slots;
+
+use stylus::tx_context::TxContext;
+use stylus::object as object;
+use stylus::object::UID;
+use stylus::transfer as transfer;
+use stylus::mapping as mapping;
+use stylus::mapping::Mapping;
+
+// Follows the layout of a Solidity ERC-20: balances at slot 0, allowances at slot 1 and the total
+// supply at slot 2. The owner is saved apart, at slot 0x10
+#[ext(storage_slot = 0x0, storage_slot(owner = 0x10))]
+public struct Token has key {
+    id: UID,
+    balances: Mapping<address, u256>,
+    allowances: Mapping<address, Mapping<address, u256>>,
+    total_supply: u256,
+    owner: address,
+    decimals: u8,
+}
+
+public fun create(ctx: &mut TxContext) {
+    transfer::share_object(Token {
+        id: object::new(ctx),
+        balances: mapping::new(),
+        allowances: mapping::new(),
+        total_supply: 0,
+        owner: ctx.sender(),
+        decimals: 18,
+    });
+}
+
+public fun mint(token: &mut Token, to: address, amount: u256) {
+    let balance = mapping::get(&token.balances, to);
+    mapping::set(&mut token.balances, to, balance + amount);
+    token.total_supply = token.total_supply + amount;
+}
+
+public fun balance_of(token: &Token, owner: address): u256 {
+    mapping::get(&token.balances, owner)
+}
+
+public fun total_supply(token: &Token): u256 {
+    token.total_supply
+}
+
+public fun owner(token: &Token): address {
+    token.owner
+}
+
+public fun decimals(token: &Token): u8 {
+    token.decimals
+}
@@ -0,0 +1,72 @@
+module test::reentrancy;
+
+use stylus::tx_context::TxContext;
+use stylus::object as object;
+use stylus::object::UID;
+use stylus::transfer as transfer;
+use stylus::table as table;
+use stylus::table::Table;
+use stylus::contract_calls::external_call;
+
+public struct Vault has key {
+    id: UID,
+    balance: u64
+}
+
+public struct Ledger has key {
+    id: UID,
+    balances: Table<address, u64>
+}
+
+public fun create(ctx: &mut TxContext) {
+  transfer::share_object(Vault {
+    id: object::new(ctx),
+    balance: 0
+  });
+}
+
+#[ext(non_reentrant)]
+public fun deposit(vault: &mut Vault, amount: u64) {
+    vault.balance = vault.balance + amount;
+}
+
+// Pays the recipient, notifying it with an external call, before updating the balance
+#[ext(non_reentrant)]
+public fun withdraw(vault: &mut Vault, recipient: address, amount: u64) {
+    let (success, _) = external_call(recipient, vector[], 0);
+    assert!(success, 1);
+    vault.balance = vault.balance - amount;
+}
+
+public fun deposit_reentrant(vault: &mut Vault, amount: u64) {
+    vault.balance = vault.balance + amount;
+}
+
+public fun balance(vault: &Vault): u64 {
+    vault.balance
+}
+
+public fun create_ledger(ctx: &mut TxContext) {
+  transfer::share_object(Ledger {
+    id: object::new(ctx),
+    balances: table::new(ctx)
+  });
+}
+
+public fun open_account(ledger: &mut Ledger, account: address, amount: u64) {
+    table::add(&mut ledger.balances, account, amount);
+}
+
+// Credits the account through a borrow that is still alive while the account is notified, and
+// after it returns
+public fun credit_and_notify(ledger: &mut Ledger, account: address, amount: u64) {
+    let balance = table::borrow_mut(&mut ledger.balances, account);
+    *balance = *balance + amount;
+    let (success, _) = external_call(account, vector[], 0);
+    assert!(success, 1);
+    *balance = *balance + 1;
+}
+
+public fun ledger_balance(ledger: &Ledger, account: address): u64 {
+    *table::borrow(&ledger.balances, account)
+}
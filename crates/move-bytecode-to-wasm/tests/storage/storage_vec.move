@@ -0,0 +1,70 @@
+module test::storage_vec;
+
+use stylus::tx_context::TxContext;
+use stylus::object as object;
+use stylus::object::UID;
+use stylus::transfer as transfer;
+use stylus::storage_vec as storage_vec;
+use stylus::storage_vec::StorageVec;
+
+public struct Position has store, copy, drop {
+    x: u32,
+    y: u64,
+    owner: address,
+}
+
+public struct Route has key {
+    id: UID,
+    positions: StorageVec<Position>,
+    values: StorageVec<u256>,
+}
+
+public fun create(ctx: &mut TxContext) {
+    transfer::share_object(Route {
+        id: object::new(ctx),
+        positions: storage_vec::new(),
+        values: storage_vec::new(),
+    });
+}
+
+public fun push_position(route: &mut Route, x: u32, y: u64, owner: address) {
+    storage_vec::push_back(&mut route.positions, Position { x, y, owner });
+}
+
+public fun set_position(route: &mut Route, index: u64, x: u32, y: u64, owner: address) {
+    storage_vec::set(&mut route.positions, index, Position { x, y, owner });
+}
+
+public fun move_position(route: &mut Route, index: u64, y: u64) {
+    let position = storage_vec::borrow_mut(&mut route.positions, index);
+    position.y = y;
+}
+
+public fun pop_position(route: &mut Route): u32 {
+    let position = storage_vec::pop_back(&mut route.positions);
+    position.x
+}
+
+public fun position_x(route: &Route, index: u64): u32 {
+    storage_vec::borrow(&route.positions, index).x
+}
+
+public fun position_y(route: &Route, index: u64): u64 {
+    storage_vec::borrow(&route.positions, index).y
+}
+
+public fun position_owner(route: &Route, index: u64): address {
+    storage_vec::get(&route.positions, index).owner
+}
+
+public fun positions_length(route: &Route): u64 {
+    storage_vec::length(&route.positions)
+}
+
+public fun push_value(route: &mut Route, value: u256) {
+    storage_vec::push_back(&mut route.values, value);
+}
+
+public fun value_at(route: &Route, index: u64): u256 {
+    storage_vec::get(&route.values, index)
+}
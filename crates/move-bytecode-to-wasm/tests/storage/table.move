@@ -0,0 +1,119 @@
+module test::table;
+
+use stylus::tx_context::TxContext;
+use stylus::object as object;
+use stylus::object::UID;
+use stylus::transfer as transfer;
+use stylus::table as table;
+use stylus::table::Table;
+use stylus::bag as bag;
+use stylus::bag::Bag;
+
+public struct Position has store, copy, drop {
+    x: u32,
+    y: u64,
+}
+
+public struct Registry has key {
+    id: UID,
+    balances: Table<address, u256>,
+    positions: Table<u64, Position>,
+    items: Bag,
+}
+
+public fun create(ctx: &mut TxContext) {
+    transfer::share_object(Registry {
+        id: object::new(ctx),
+        balances: table::new(ctx),
+        positions: table::new(ctx),
+        items: bag::new(ctx),
+    });
+}
+
+// Table
+public fun add_balance(registry: &mut Registry, owner: address, amount: u256) {
+    table::add(&mut registry.balances, owner, amount);
+}
+
+public fun balance_of(registry: &Registry, owner: address): u256 {
+    *table::borrow(&registry.balances, owner)
+}
+
+public fun increase_balance(registry: &mut Registry, owner: address, amount: u256) {
+    let balance = table::borrow_mut(&mut registry.balances, owner);
+    *balance = *balance + amount;
+}
+
+public fun remove_balance(registry: &mut Registry, owner: address): u256 {
+    table::remove(&mut registry.balances, owner)
+}
+
+public fun has_balance(registry: &Registry, owner: address): bool {
+    table::contains(&registry.balances, owner)
+}
+
+public fun balances_length(registry: &Registry): u64 {
+    table::length(&registry.balances)
+}
+
+public fun add_position(registry: &mut Registry, key: u64, x: u32, y: u64) {
+    table::add(&mut registry.positions, key, Position { x, y });
+}
+
+public fun move_position(registry: &mut Registry, key: u64, dx: u32, dy: u64) {
+    let position = table::borrow_mut(&mut registry.positions, key);
+    position.x = position.x + dx;
+    position.y = position.y + dy;
+}
+
+public fun get_position(registry: &Registry, key: u64): (u32, u64) {
+    let position = table::borrow(&registry.positions, key);
+    (position.x, position.y)
+}
+
+public fun destroy_table(fill: bool, ctx: &mut TxContext) {
+    let mut t = table::new<u64, u64>(ctx);
+    if (fill) {
+        table::add(&mut t, 1, 1);
+    };
+    table::destroy_empty(t);
+}
+
+// Bag
+public fun bag_add_u64(registry: &mut Registry, key: u64, value: u64) {
+    bag::add(&mut registry.items, key, value);
+}
+
+public fun bag_add_address(registry: &mut Registry, key: address, value: address) {
+    bag::add(&mut registry.items, key, value);
+}
+
+public fun bag_get_u64(registry: &Registry, key: u64): u64 {
+    *bag::borrow(&registry.items, key)
+}
+
+public fun bag_get_address(registry: &Registry, key: address): address {
+    *bag::borrow(&registry.items, key)
+}
+
+// Reads the value under an u64 key as an address, to check the type tags
+public fun bag_get_u64_as_address(registry: &Registry, key: u64): address {
+    *bag::borrow(&registry.items, key)
+}
+
+public fun bag_increment_u64(registry: &mut Registry, key: u64) {
+    let value = bag::borrow_mut(&mut registry.items, key);
+    *value = *value + 1;
+}
+
+public fun bag_remove_u64(registry: &mut Registry, key: u64): u64 {
+    bag::remove(&mut registry.items, key)
+}
+
+public fun bag_contains(registry: &Registry, key: u64): bool {
+    bag::contains(&registry.items, key)
+}
+
+public fun bag_length(registry: &Registry): u64 {
+    bag::length(&registry.items)
+}
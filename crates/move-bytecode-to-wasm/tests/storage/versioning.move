@@ -0,0 +1,41 @@
+module test::versioning;
+
+use stylus::tx_context::TxContext;
+use stylus::object as object;
+use stylus::object::UID;
+use stylus::transfer as transfer;
+
+#[ext(storage_version = 2)]
+public struct Config has key {
+    id: UID,
+    fee: u64,
+    // Added in version 2
+    max_fee: u64,
+}
+
+public fun create(fee: u64, ctx: &mut TxContext) {
+    transfer::share_object(Config {
+        id: object::new(ctx),
+        fee,
+        max_fee: fee * 2,
+    });
+}
+
+public fun fee(config: &Config): u64 {
+    config.fee
+}
+
+public fun max_fee(config: &Config): u64 {
+    config.max_fee
+}
+
+public fun set_fee(config: &mut Config, fee: u64) {
+    config.fee = fee;
+}
+
+#[ext(storage_migration)]
+fun migrate_config(config: &mut Config, from_version: u8) {
+    if (from_version < 2) {
+        config.max_fee = config.fee * 10;
+    }
+}
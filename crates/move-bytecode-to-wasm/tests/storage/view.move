@@ -0,0 +1,36 @@
+module test::view;
+
+use stylus::tx_context::TxContext;
+use stylus::object as object;
+use stylus::object::UID;
+use stylus::transfer as transfer;
+
+public struct Vault has key {
+    id: UID,
+    balance: u64
+}
+
+public fun create(ctx: &mut TxContext) {
+  transfer::share_object(Vault {
+    id: object::new(ctx),
+    balance: 0
+  });
+}
+
+public fun deposit(vault: &mut Vault, amount: u64) {
+    vault.balance = vault.balance + amount;
+}
+
+#[ext(view)]
+public fun balance(vault: &Vault): u64 {
+    vault.balance
+}
+
+#[ext(view)]
+public fun doubled_balance(vault: &Vault): u64 {
+    double(vault.balance)
+}
+
+fun double(value: u64): u64 {
+    value * 2
+}
@@ -0,0 +1,23 @@
+module test::view_emits_event;
+
+use stylus::event::emit;
+use stylus::object::UID;
+
+public struct Vault has key {
+    id: UID,
+    balance: u64
+}
+
+public struct BalanceRead has copy, drop {
+    balance: u64
+}
+
+#[ext(view)]
+public fun balance(vault: &Vault): u64 {
+    log_balance(vault.balance);
+    vault.balance
+}
+
+fun log_balance(balance: u64) {
+    emit(BalanceRead { balance });
+}
@@ -0,0 +1,13 @@
+module test::view_mutable_argument;
+
+use stylus::object::UID;
+
+public struct Vault has key {
+    id: UID,
+    balance: u64
+}
+
+#[ext(view)]
+public fun balance(vault: &mut Vault): u64 {
+    vault.balance
+}
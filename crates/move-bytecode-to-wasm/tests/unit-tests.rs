@@ -0,0 +1,52 @@
+mod common;
+
+use common::translate_test_package_with_unit_tests;
+use move_bytecode_to_wasm::ExpectedFailure;
+use move_stylus_testing::{MoveAbort, RuntimeSandbox};
+
+mod unit_tests {
+    use super::*;
+
+    const MODULE_NAME: &str = "unit_tests";
+    const SOURCE_PATH: &str = "tests/unit-tests/unit_tests.move";
+
+    #[test]
+    fn test_unit_tests() {
+        let (mut translated_package, unit_tests) =
+            translate_test_package_with_unit_tests(SOURCE_PATH, MODULE_NAME);
+        let runtime = RuntimeSandbox::new(&mut translated_package);
+
+        let expected_failures: Vec<(&str, Option<ExpectedFailure>)> = unit_tests
+            .iter()
+            .map(|unit_test| (unit_test.name.as_str(), unit_test.expected_failure))
+            .collect();
+        assert_eq!(
+            vec![
+                ("test_divide", None),
+                ("test_divide_by_zero", Some(ExpectedFailure::AbortCode(7))),
+                ("test_failing_assertion", None),
+            ],
+            expected_failures
+        );
+
+        let outcomes: Vec<Result<i32, Option<MoveAbort>>> = unit_tests
+            .iter()
+            .map(
+                |unit_test| match runtime.call_entrypoint(unit_test.selector.to_vec()) {
+                    Ok((status, _)) => Ok(status),
+                    Err(e) => Err(e.downcast_ref::<MoveAbort>().copied()),
+                },
+            )
+            .collect();
+
+        // The aborts of the tests and of the functions they call report their codes
+        assert_eq!(
+            vec![
+                Ok(0),
+                Err(Some(MoveAbort { code: 7 })),
+                Err(Some(MoveAbort { code: 3 })),
+            ],
+            outcomes
+        );
+    }
+}
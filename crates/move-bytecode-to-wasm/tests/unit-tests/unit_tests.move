@@ -0,0 +1,24 @@
+module 0x01::unit_tests;
+
+const EZeroDivisor: u64 = 7;
+
+public fun divide(a: u64, b: u64): u64 {
+    assert!(b != 0, EZeroDivisor);
+    a / b
+}
+
+#[test]
+fun test_divide() {
+    assert!(divide(10, 2) == 5, 0);
+}
+
+#[test]
+#[expected_failure(abort_code = EZeroDivisor)]
+fun test_divide_by_zero() {
+    divide(1, 0);
+}
+
+#[test]
+fun test_failing_assertion() {
+    assert!(divide(10, 2) == 4, 3);
+}
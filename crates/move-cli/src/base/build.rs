@@ -3,33 +3,423 @@
 
 use super::reroot_path;
 use clap::*;
-use move_bytecode_to_wasm::translate_package_cli;
-use move_package::BuildConfig;
-use std::path::Path;
+use move_bytecode_to_wasm::{
+    Artifact, ContractSelection, DEFAULT_CACHE_DIRECTORY, DEFAULT_OUTPUT_DIRECTORY, Diagnostic,
+    MAX_COMPRESSED_WASM_SIZE, MODULE_NAME_PLACEHOLDER, MemoryGrowth, MessageFormat,
+    OptimizationLevel, OutputOptions, PackageConfig, Profile, SizeBudget, StylusVersion,
+    TranslationCache, TranslationOptions, translate_package_cli_incremental,
+};
+use move_package::{BuildConfig, source_package::layout::SourcePackageLayout};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    io::Write,
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+/// Interval the sources are checked for changes at in watch mode
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
 
 /// Build the package at `path`. If no path is provided defaults to current directory.
-#[derive(Parser)]
+///
+/// The options set in the `Stylus.toml` of the package are used unless the flags set them: the
+/// flags that take a value replace the ones of the file, and the boolean flags turn on options the
+/// file leaves off.
+#[derive(Parser, Clone)]
 #[clap(name = "build")]
-pub struct Build;
+pub struct Build {
+    /// Presets of the options: `debug` prints the messages of `debug::log` and the aborts, imports
+    /// the host debug functions and keeps the names, with the debug information of `--debug-info`;
+    /// `release` strips them, builds the modules deterministically and optimizes them with
+    /// `wasm-opt -Oz`. The other flags override the profile.
+    #[clap(long = "profile", value_name = "PROFILE")]
+    pub profile: Option<Profile>,
+    /// Save every storage field in its own slot instead of packing the ones smaller than 32 bytes
+    /// together. Useful to keep compatibility with an existing storage layout.
+    #[clap(long = "no-storage-packing")]
+    pub no_storage_packing: bool,
+    /// Reject every reentrant call to the package's entry functions, as if all of them were
+    /// annotated with `#[ext(non_reentrant)]`.
+    #[clap(long = "reentrancy-guard")]
+    pub reentrancy_guard: bool,
+    /// Compute u256 divisions and remainders with Stylus' math hooks instead of WASM code, using
+    /// less ink.
+    #[clap(long = "math-hooks")]
+    pub math_hooks: bool,
+    /// Double the memory every time it runs out, growing it by at most this number of pages at
+    /// once, instead of growing only the pages each allocation needs.
+    #[clap(long = "memory-growth-cap", value_name = "PAGES")]
+    pub memory_growth_cap: Option<u32>,
+    /// Version of Stylus the package is deployed to, `arbos31` or `arbos32`, `arbos32` by
+    /// default. The build fails if the package needs a host function that the version does not
+    /// provide.
+    #[clap(long = "stylus-version", value_name = "VERSION")]
+    pub stylus_version: Option<StylusVersion>,
+    /// Print the messages of `debug::log` through the console's `log_txt` hook. Only nodes running
+    /// in debug mode provide it, so release builds should not use it.
+    #[clap(long = "debug")]
+    pub debug: bool,
+    /// Name the locals of the functions after the Move locals, keep the names of the functions,
+    /// and emit the source map from the offsets of the WASM instructions to the Move source as
+    /// `<module>.wasm.map`, so the stack traces of nodes and debuggers point to the source.
+    #[clap(long = "debug-info")]
+    pub debug_info: bool,
+    /// Translate the functions in an order that does not change from run to run, so two builds of
+    /// the package are byte-identical. Set by the release profile.
+    #[clap(long = "deterministic")]
+    pub deterministic: bool,
+    /// Report the execution of every basic block through the `coverage::hit` hook, and emit the
+    /// map from the blocks to the source as `<module>.coverage.json`. Only test runners provide the
+    /// hook, so the modules can't be deployed.
+    #[clap(long = "coverage")]
+    pub coverage: bool,
+    /// Translate the library modules too: the modules other modules of the package depend on
+    /// that have neither `entry` functions nor an `init` function. By default they are only linked
+    /// into the modules that use them.
+    #[clap(long = "include-libraries", conflicts_with = "contracts")]
+    pub include_libraries: bool,
+    /// Translate only these modules of the package, separated by commas.
+    #[clap(long = "contracts", value_name = "MODULES", value_delimiter = ',')]
+    pub contracts: Vec<String>,
+    /// Directory the modules and their artifacts are written to, relative to the package root.
+    #[clap(long = "output-dir", value_name = "DIR", default_value = DEFAULT_OUTPUT_DIRECTORY)]
+    pub output_dir: PathBuf,
+    /// Name of the artifacts of every module, before their extension. `{module}` is replaced by
+    /// the name of the module.
+    #[clap(long = "artifact-name", value_name = "NAME", default_value = MODULE_NAME_PLACEHOLDER)]
+    pub artifact_name: String,
+    /// Comma separated artifacts written for every module: `wasm`, `wat`, `abi`, `storage`, `gas`,
+    /// `coverage`, `size`, `ts`, `source-map` and `activation`. All but `wat` and `ts` by default.
+    /// The artifacts of the module left by previous builds that are not written are removed.
+    #[clap(long = "emit", value_name = "ARTIFACTS", value_delimiter = ',')]
+    pub emit: Vec<Artifact>,
+    /// Write the WAT of every module too, as `<module>.wat`.
+    #[clap(long = "wat")]
+    pub wat: bool,
+    /// Warn instead of failing when a module, compressed with brotli, is bigger than the 24 KiB
+    /// Stylus deploys, or when it is over the size budget.
+    #[clap(long = "allow-oversized")]
+    pub allow_oversized: bool,
+    /// Fail when a module is bigger than this many bytes, as emitted.
+    #[clap(long = "max-size", value_name = "BYTES")]
+    pub max_size: Option<usize>,
+    /// Fail when a module, compressed with brotli, is bigger than this many bytes, 24 KiB, the
+    /// limit of Stylus, by default.
+    #[clap(long = "max-compressed-size", value_name = "BYTES")]
+    pub max_compressed_size: Option<usize>,
+    /// Optimize the modules with binaryen's `wasm-opt` at this level, `0` to `4`, `s` or `z`,
+    /// after they are translated. Needs `wasm-opt`, installed with `make install-wasm-opt`.
+    #[clap(long = "wasm-opt", value_name = "LEVEL")]
+    pub wasm_opt: Option<OptimizationLevel>,
+    /// Directory the translated modules are cached in, relative to the package root. The modules
+    /// whose bytecode, and the bytecode of their dependencies, did not change since they were
+    /// cached are written from it instead of being translated again.
+    #[clap(long = "cache-dir", value_name = "DIR", default_value = DEFAULT_CACHE_DIRECTORY)]
+    pub cache_dir: PathBuf,
+    /// Translate every module, without reading nor writing the build cache.
+    #[clap(long = "no-cache")]
+    pub no_cache: bool,
+    /// Format the warnings and the errors are reported in: `human`, or `json` for a JSON object
+    /// per diagnostic on the standard output, with its `severity`, `code`, Move `file` and `line`,
+    /// and `message`. The progress of the build is reported as notes, and the Move compiler
+    /// reports its diagnostics as JSON too, writing its progress to the standard error.
+    #[clap(
+        long = "message-format",
+        value_name = "FORMAT",
+        default_value_t = MessageFormat::default()
+    )]
+    pub message_format: MessageFormat,
+    /// Rebuild the package every time its sources or its manifest change, translating and writing
+    /// only the modules that changed, until interrupted.
+    #[clap(long = "watch")]
+    pub watch: bool,
+    /// Build the package for every one of these targets, separated by commas, each into its own
+    /// subdirectory of the output directory, named after it. The targets are the
+    /// `[target.<name>]` tables of the `Stylus.toml`, which override its `[build]` options, and
+    /// the profiles, `debug` and `release`. The flags override the options of every target.
+    #[clap(
+        long = "target",
+        value_name = "TARGETS",
+        value_delimiter = ',',
+        conflicts_with = "watch"
+    )]
+    pub targets: Vec<String>,
+}
 
 impl Build {
     pub fn execute(self, path: Option<&Path>, config: BuildConfig) -> anyhow::Result<()> {
+        if self.watch && !config.fetch_deps_only {
+            return self.watch_package(path, config);
+        }
+        if !self.targets.is_empty() && !config.fetch_deps_only {
+            return self.build_targets(path, config);
+        }
+        self.build_modules(path, config).map(|_| ())
+    }
+
+    /// Builds the package once for every target, in the order they are given, with the options
+    /// of the target taking precedence over the ones of `[build]`.
+    fn build_targets(self, path: Option<&Path>, config: BuildConfig) -> anyhow::Result<()> {
+        let rerooted_path = reroot_path(path)?;
+        let package_config = PackageConfig::load(&rerooted_path)?;
+        for target in &self.targets {
+            let mut build = self.clone();
+            build.targets.clear();
+            build.output_dir = self.output_dir.join(target);
+            build.apply_package_config(&package_config.target(target)?);
+
+            Diagnostic::note("progress", format!("building target {target}..."))
+                .report(self.message_format);
+            build.build_modules(Some(&rerooted_path), config.clone())?;
+        }
+        Ok(())
+    }
+
+    /// Builds the package as `execute` does, returning the WASM of every module written, none if
+    /// only the dependencies are fetched.
+    pub fn build_modules(
+        mut self,
+        path: Option<&Path>,
+        config: BuildConfig,
+    ) -> anyhow::Result<HashMap<String, Vec<u8>>> {
         let rerooted_path = reroot_path(path)?;
+        self.apply_package_config(&PackageConfig::load(&rerooted_path)?);
+        self.report_panics();
         if config.fetch_deps_only {
             let mut config = config;
             if config.test_mode {
                 config.dev_mode = true;
             }
-            config.download_deps_for_package(&rerooted_path, &mut std::io::stdout())?;
-            return Ok(());
+            config.download_deps_for_package(&rerooted_path, &mut self.compiler_output())?;
+            return Ok(HashMap::new());
         }
 
-        let compiled = config.clone().cli_compile_package(
-            &rerooted_path,
-            &mut std::io::stdout(),
+        self.build_incremental(&rerooted_path, config, &mut TranslationCache::default())
+            .inspect_err(|e| {
+                // The human errors are reported by the caller
+                if self.message_format == MessageFormat::Json {
+                    Diagnostic::error("build", format!("{e:#}")).report(MessageFormat::Json);
+                }
+            })
+    }
+
+    /// Where the Move compiler writes its progress: the standard error if the diagnostics are
+    /// reported as JSON, so the standard output only holds them.
+    fn compiler_output(&self) -> Box<dyn Write> {
+        match self.message_format {
+            MessageFormat::Human => Box::new(std::io::stdout()),
+            MessageFormat::Json => Box::new(std::io::stderr()),
+        }
+    }
+
+    /// Reports the panics of the translation as diagnostics, if they are reported as JSON.
+    fn report_panics(&self) {
+        if self.message_format == MessageFormat::Json {
+            panic::set_hook(Box::new(|info| {
+                Diagnostic::from_panic(info).report(MessageFormat::Json)
+            }));
+        }
+    }
+
+    fn build_incremental(
+        &self,
+        rerooted_path: &Path,
+        mut config: BuildConfig,
+        cache: &mut TranslationCache,
+    ) -> anyhow::Result<HashMap<String, Vec<u8>>> {
+        config.json_errors |= self.message_format == MessageFormat::Json;
+        let compiled = config.cli_compile_package(
+            rerooted_path,
+            &mut self.compiler_output(),
             &mut std::io::stdin().lock(),
         )?;
-        translate_package_cli(compiled, &rerooted_path);
+        translate_package_cli_incremental(
+            compiled,
+            rerooted_path,
+            &self.translation_options(),
+            &self.output_options(),
+            cache,
+        )
+    }
+
+    /// Builds the package, and builds it again every time the files watched change. The errors
+    /// are reported without stopping, so the package is built again once they are fixed.
+    fn watch_package(mut self, path: Option<&Path>, config: BuildConfig) -> anyhow::Result<()> {
+        let rerooted_path = reroot_path(path)?;
+        self.apply_package_config(&PackageConfig::load(&rerooted_path)?);
+        self.report_panics();
+        let manifest_path = rerooted_path.join(SourcePackageLayout::Manifest.path());
+        let mut cache = TranslationCache::default();
+        let mut last_snapshot: Option<BTreeMap<PathBuf, SystemTime>> = None;
+
+        loop {
+            let snapshot = watched_files(&rerooted_path);
+            if last_snapshot.as_ref() != Some(&snapshot) {
+                // The dependencies change with the manifest
+                if last_snapshot
+                    .as_ref()
+                    .and_then(|last| last.get(&manifest_path))
+                    != snapshot.get(&manifest_path)
+                {
+                    cache.clear();
+                }
+
+                let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                    self.build_incremental(&rerooted_path, config.clone(), &mut cache)
+                }));
+                match result {
+                    Ok(Ok(_)) => {}
+                    Ok(Err(e)) => {
+                        Diagnostic::error("build", format!("{e:#}")).report(self.message_format)
+                    }
+                    // The panic is already reported, and the cache may be left half updated
+                    Err(_) => cache.clear(),
+                }
+
+                Diagnostic::note("progress", "watching the sources for changes...")
+                    .report(self.message_format);
+                last_snapshot = Some(snapshot);
+            }
+
+            thread::sleep(WATCH_INTERVAL);
+        }
+    }
+
+    /// Reads the `Stylus.toml` of the package at `path`, or of the current directory, and takes
+    /// the options the flags don't set from it, without rooting us to the package.
+    pub fn load_package_config(&mut self, path: Option<&Path>) -> anyhow::Result<()> {
+        let path = path
+            .map(Path::canonicalize)
+            .unwrap_or_else(|| PathBuf::from(".").canonicalize())?;
+        let package_root = SourcePackageLayout::try_find_root(&path)?;
+        self.apply_package_config(&PackageConfig::load(&package_root)?);
         Ok(())
     }
+
+    /// Takes the options the flags don't set from the configuration of the package. Applying it
+    /// again changes nothing.
+    pub fn apply_package_config(&mut self, package_config: &PackageConfig) {
+        let file = &package_config.build;
+        self.profile = self.profile.or(file.profile);
+        self.no_storage_packing |= file.storage_packing == Some(false);
+        self.reentrancy_guard |= file.reentrancy_guard == Some(true);
+        self.math_hooks |= file.math_hooks == Some(true);
+        self.memory_growth_cap = self.memory_growth_cap.or(file.memory_growth_cap);
+        self.stylus_version = self.stylus_version.or(file.stylus_version);
+        self.debug |= file.debug == Some(true);
+        self.debug_info |= file.debug_info == Some(true);
+        self.deterministic |= file.deterministic == Some(true);
+        if self.emit.is_empty() {
+            self.emit = file
+                .artifacts
+                .clone()
+                .unwrap_or_else(|| Artifact::DEFAULT.to_vec());
+        }
+        self.allow_oversized |= file.allow_oversized == Some(true);
+        self.max_size = self.max_size.or(file.max_size);
+        self.max_compressed_size = self.max_compressed_size.or(file.max_compressed_size);
+        self.wasm_opt = self.wasm_opt.or(file.wasm_opt);
+    }
+
+    /// Where the artifacts are written, and which ones, according to the flags.
+    pub fn output_options(&self) -> OutputOptions {
+        let emit = if self.emit.is_empty() {
+            Artifact::DEFAULT.to_vec()
+        } else {
+            self.emit.clone()
+        };
+        let mut output = OutputOptions {
+            directory: self.output_dir.clone(),
+            file_name: self.artifact_name.clone(),
+            artifacts: if self.wat && !emit.contains(&Artifact::Wat) {
+                [emit.as_slice(), &[Artifact::Wat]].concat()
+            } else {
+                emit
+            },
+            allow_oversized: self.allow_oversized,
+            size_budget: SizeBudget {
+                max_size: self.max_size,
+                max_compressed_size: self.max_compressed_size.unwrap_or(MAX_COMPRESSED_WASM_SIZE),
+            },
+            wasm_opt: self.wasm_opt,
+            cache_directory: (!self.no_cache).then(|| self.cache_dir.clone()),
+            message_format: self.message_format,
+        };
+        if let Some(profile) = self.profile {
+            profile.apply_to_output(&mut output);
+        }
+        output
+    }
+
+    /// Options the package is translated with, according to the flags.
+    pub fn translation_options(&self) -> TranslationOptions {
+        let mut options = TranslationOptions {
+            storage_packing: !self.no_storage_packing,
+            reentrancy_guard: self.reentrancy_guard,
+            math_hooks: self.math_hooks,
+            memory_growth: self
+                .memory_growth_cap
+                .map_or(MemoryGrowth::Exact, |max_pages| MemoryGrowth::Exponential {
+                    max_pages,
+                }),
+            stylus_version: self.stylus_version.unwrap_or_default(),
+            debug: self.debug,
+            host_debug_functions: false,
+            strip_names: false,
+            debug_info: self.debug_info,
+            deterministic: self.deterministic,
+            coverage: self.coverage,
+            unit_tests: false,
+            contracts: if !self.contracts.is_empty() {
+                ContractSelection::Modules(self.contracts.clone())
+            } else if self.include_libraries {
+                ContractSelection::All
+            } else {
+                ContractSelection::SkipLibraries
+            },
+        };
+        if let Some(profile) = self.profile {
+            profile.apply_to_translation(&mut options);
+            options.debug |= self.debug;
+            options.debug_info |= self.debug_info;
+            options.deterministic |= self.deterministic;
+        }
+        options
+    }
+}
+
+/// Last modification time of the manifest and of every file in the sources and tests of the
+/// package, the files watched in watch mode.
+fn watched_files(rerooted_path: &Path) -> BTreeMap<PathBuf, SystemTime> {
+    let mut files = BTreeMap::new();
+    let mut pending: Vec<PathBuf> = [
+        SourcePackageLayout::Manifest,
+        SourcePackageLayout::Sources,
+        SourcePackageLayout::Tests,
+    ]
+    .iter()
+    .map(|layout| rerooted_path.join(layout.path()))
+    .collect();
+
+    while let Some(path) = pending.pop() {
+        let Ok(metadata) = fs::metadata(&path) else {
+            continue;
+        };
+        if metadata.is_dir() {
+            if let Ok(entries) = fs::read_dir(&path) {
+                pending.extend(
+                    entries
+                        .filter_map(|entry| entry.ok())
+                        .map(|entry| entry.path()),
+                );
+            }
+        } else if let Ok(modified) = metadata.modified() {
+            files.insert(path, modified);
+        }
+    }
+
+    files
 }
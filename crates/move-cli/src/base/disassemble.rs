@@ -4,7 +4,7 @@
 use super::reroot_path;
 use clap::*;
 use move_bytecode_source_map::utils::serialize_to_json_string;
-use move_bytecode_to_wasm::translate_package_cli;
+use move_bytecode_to_wasm::{OutputOptions, TranslationOptions, translate_package_cli};
 use move_compiler::compiled_unit::NamedCompiledModule;
 use move_disassembler::disassembler::Disassembler;
 use move_package::{BuildConfig, compilation::compiled_package::CompiledUnitWithSource};
@@ -86,7 +86,12 @@ impl Disassemble {
             }
         }
 
-        translate_package_cli(package, &rerooted_path);
-        Ok(())
+        translate_package_cli(
+            package,
+            &rerooted_path,
+            &TranslationOptions::default(),
+            &OutputOptions::default(),
+        )
+        .map(|_| ())
     }
 }
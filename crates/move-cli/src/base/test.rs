@@ -6,6 +6,9 @@ use crate::NativeFunctionRecord;
 use anyhow::Result;
 use clap::*;
 use move_binary_format::CompiledModule;
+use move_bytecode_to_wasm::{
+    ExpectedFailure, TranslationOptions, translate_package_with_artifacts,
+};
 use move_command_line_common::files::MOVE_COVERAGE_MAP_EXTENSION;
 use move_compiler::{
     PASS_CFGIR,
@@ -15,6 +18,7 @@ use move_compiler::{
 };
 use move_coverage::coverage_map::{CoverageMap, output_map_to_file};
 use move_package::{BuildConfig, compilation::build_plan::BuildPlan};
+use move_stylus_testing::{MoveAbort, RuntimeSandbox};
 use move_unit_test::UnitTestingConfig;
 use move_vm_test_utils::gas_schedule::CostTable;
 use std::{io::Write, path::Path, process::ExitStatus};
@@ -72,6 +76,11 @@ pub struct Test {
     // Enable tracing for tests
     #[clap(long = "trace-execution", value_name = "PATH")]
     pub trace_execution: Option<Option<String>>,
+
+    /// Run the tests translated to WASM, in the Stylus sandbox, instead of the Move VM. Only the
+    /// filter applies to these runs, and the tests that take arguments are not supported.
+    #[clap(long = "wasm")]
+    pub wasm: bool,
 }
 
 impl Test {
@@ -83,6 +92,20 @@ impl Test {
         cost_table: Option<CostTable>,
     ) -> anyhow::Result<()> {
         let rerooted_path = reroot_path(path)?;
+        if self.wasm {
+            let result = run_wasm_unit_tests(
+                &rerooted_path,
+                config,
+                self.filter.as_deref(),
+                &mut std::io::stdout(),
+            )?;
+
+            if result == UnitTestResult::Failure {
+                std::process::exit(1)
+            }
+            return Ok(());
+        }
+
         let compute_coverage = self.compute_coverage;
         // save disassembly if trace execution is enabled
         let save_disassembly = self.trace_execution.is_some();
@@ -116,6 +139,7 @@ impl Test {
             seed,
             rand_num_iters,
             trace_execution,
+            wasm: _,
         } = self;
         UnitTestingConfig {
             gas_limit,
@@ -262,6 +286,126 @@ pub fn run_move_unit_tests<W: Write + Send>(
     Ok((UnitTestResult::Success, warning_diags))
 }
 
+/// Runs the package's Move unit tests translated to WASM, in the Stylus sandbox, and reports their
+/// results. Every test runs against a fresh sandbox, and passes if it ends as its
+/// `#[expected_failure]` attribute expects, if it has one, or returns otherwise.
+pub fn run_wasm_unit_tests<W: Write>(
+    pkg_path: &Path,
+    mut build_config: BuildConfig,
+    filter: Option<&str>,
+    writer: &mut W,
+) -> Result<UnitTestResult> {
+    build_config.test_mode = true;
+    build_config.dev_mode = true;
+
+    let package = build_config.compile_package(pkg_path, writer)?;
+    let options = TranslationOptions {
+        unit_tests: true,
+        ..Default::default()
+    };
+    let mut modules: Vec<_> = translate_package_with_artifacts(package, None, &options)
+        .into_iter()
+        .collect();
+    modules.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    writeln!(writer, "Running Move unit tests in the Stylus sandbox")?;
+    let mut passed = 0;
+    let mut failures = Vec::new();
+    for (module_name, mut translated_module) in modules {
+        let wasm = translated_module.module.emit_wasm();
+        for unit_test in &translated_module.unit_tests {
+            let test_name = format!("{module_name}::{}", unit_test.name);
+            if filter.is_some_and(|filter| !test_name.contains(filter)) {
+                continue;
+            }
+
+            let runtime = RuntimeSandbox::from_wasm(&wasm);
+            let outcome = WasmTestOutcome::run(&runtime, unit_test.selector);
+            match outcome.check(unit_test.expected_failure) {
+                Ok(()) => {
+                    writeln!(writer, "[ PASS    ] {test_name}")?;
+                    passed += 1;
+                }
+                Err(reason) => {
+                    writeln!(writer, "[ FAIL    ] {test_name}")?;
+                    failures.push((test_name, reason));
+                }
+            }
+        }
+    }
+
+    if !failures.is_empty() {
+        writeln!(writer, "\nTest failures:\n")?;
+        for (test_name, reason) in &failures {
+            writeln!(writer, "{test_name}: {reason}")?;
+        }
+        writeln!(writer)?;
+    }
+
+    let total = passed + failures.len();
+    let (result, status) = if failures.is_empty() {
+        (UnitTestResult::Success, "OK")
+    } else {
+        (UnitTestResult::Failure, "FAILED")
+    };
+    writeln!(
+        writer,
+        "Test result: {status}. Total tests: {total}; passed: {passed}; failed: {}",
+        failures.len()
+    )?;
+
+    Ok(result)
+}
+
+/// How a unit test run in WASM ended.
+enum WasmTestOutcome {
+    Returned,
+    Aborted(u64),
+    /// Trapped or reverted, with the reason
+    Failed(String),
+}
+
+impl WasmTestOutcome {
+    fn run(runtime: &RuntimeSandbox, selector: [u8; 4]) -> Self {
+        match runtime.call_entrypoint(selector.to_vec()) {
+            Ok((0, _)) => WasmTestOutcome::Returned,
+            Ok((status, _)) => WasmTestOutcome::Failed(format!("reverted with status {status}")),
+            Err(e) => match e.downcast_ref::<MoveAbort>() {
+                Some(MoveAbort { code }) => WasmTestOutcome::Aborted(*code),
+                None => WasmTestOutcome::Failed(e.to_string()),
+            },
+        }
+    }
+
+    /// Checks the outcome against the failure the test expects, returning why it failed if it did.
+    fn check(&self, expected_failure: Option<ExpectedFailure>) -> Result<(), String> {
+        match (self, expected_failure) {
+            (WasmTestOutcome::Returned, None) => Ok(()),
+            (WasmTestOutcome::Returned, Some(ExpectedFailure::Any)) => {
+                Err("expected the test to fail, but it returned".to_owned())
+            }
+            (WasmTestOutcome::Returned, Some(ExpectedFailure::AbortCode(expected))) => Err(
+                format!("expected the test to abort with code {expected}, but it returned"),
+            ),
+            (WasmTestOutcome::Aborted(code), None) => Err(format!("aborted with code {code}")),
+            (WasmTestOutcome::Failed(reason), None) => Err(reason.clone()),
+            (_, Some(ExpectedFailure::Any)) => Ok(()),
+            (WasmTestOutcome::Aborted(code), Some(ExpectedFailure::AbortCode(expected))) => {
+                if *code == expected {
+                    Ok(())
+                } else {
+                    Err(format!(
+                        "expected the test to abort with code {expected}, but it aborted with code {code}"
+                    ))
+                }
+            }
+            (WasmTestOutcome::Failed(reason), Some(ExpectedFailure::AbortCode(expected))) => Err(
+                format!("expected the test to abort with code {expected}, but it failed: {reason}"),
+            ),
+        }
+    }
+}
+
 impl From<UnitTestResult> for ExitStatus {
     fn from(result: UnitTestResult) -> Self {
         match result {
@@ -0,0 +1,121 @@
+//! Results of the calls to the entrypoint, with the reasons of the reverts decoded.
+//!
+//! The revert data follows Solidity's conventions: `Error(string)` for the reverts with a message,
+//! `Panic(uint256)` for the failed checks, and the selector of the error followed by its fields for
+//! the custom errors.
+use std::fmt;
+
+use alloy_primitives::{U256, hex};
+use alloy_sol_types::{Panic, Revert, SolError};
+
+/// Result of a call to the entrypoint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CallResult {
+    /// The call succeeded, returning the data
+    Success(Vec<u8>),
+    /// The call reverted, with the reason in the return data
+    Revert(RevertReason),
+    /// The execution trapped, as it does when the program aborts, with the trap's message
+    Trap(String),
+}
+
+impl CallResult {
+    pub fn is_success(&self) -> bool {
+        matches!(self, CallResult::Success(_))
+    }
+
+    /// Returns the return data of a successful call. Panics with the reason if the call failed.
+    pub fn unwrap(self) -> Vec<u8> {
+        match self {
+            CallResult::Success(return_data) => return_data,
+            result => panic!("the call failed: {result}"),
+        }
+    }
+
+    /// Returns the reason of the revert, or `None` if the call did not revert.
+    pub fn revert_reason(&self) -> Option<&RevertReason> {
+        match self {
+            CallResult::Revert(reason) => Some(reason),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for CallResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CallResult::Success(return_data) => {
+                write!(f, "succeeded with 0x{}", hex::encode(return_data))
+            }
+            CallResult::Revert(reason) => write!(f, "{reason}"),
+            CallResult::Trap(message) => write!(f, "aborted: {message}"),
+        }
+    }
+}
+
+/// Reason of a revert, decoded from the return data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RevertReason {
+    /// `Error(string)`, with the message
+    Error(String),
+    /// `Panic(uint256)`, with the code of the failed check
+    Panic(U256),
+    /// A custom error, identified by its selector, and its ABI-encoded fields
+    Custom { selector: [u8; 4], data: Vec<u8> },
+    /// Data too short to hold a selector, such as the empty data of a plain revert
+    Raw(Vec<u8>),
+}
+
+impl RevertReason {
+    pub fn decode(revert_data: &[u8]) -> Self {
+        if let Ok(revert) = Revert::abi_decode(revert_data) {
+            return RevertReason::Error(revert.reason);
+        }
+
+        if let Ok(panic) = Panic::abi_decode(revert_data) {
+            return RevertReason::Panic(panic.code);
+        }
+
+        match revert_data.split_first_chunk::<4>() {
+            Some((selector, data)) => RevertReason::Custom {
+                selector: *selector,
+                data: data.to_vec(),
+            },
+            None => RevertReason::Raw(revert_data.to_vec()),
+        }
+    }
+
+    /// Decodes the custom error as the error `E`, generated with alloy's `sol!` macro. Returns
+    /// `None` if it is another error.
+    pub fn custom<E: SolError>(&self) -> Option<E> {
+        match self {
+            RevertReason::Custom { selector, data } if *selector == E::SELECTOR => {
+                E::abi_decode_raw(data).ok()
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for RevertReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RevertReason::Error(message) => write!(f, "reverted with Error({message:?})"),
+            RevertReason::Panic(code) => {
+                let panic = Panic { code: *code };
+                match panic.kind() {
+                    Some(kind) => write!(f, "panicked with code 0x{code:02x}: {kind}"),
+                    None => write!(f, "panicked with code 0x{code:02x}"),
+                }
+            }
+            RevertReason::Custom { selector, data } => write!(
+                f,
+                "reverted with custom error 0x{} and data 0x{}",
+                hex::encode(selector),
+                hex::encode(data)
+            ),
+            RevertReason::Raw(data) if data.is_empty() => write!(f, "reverted without data"),
+            RevertReason::Raw(data) => write!(f, "reverted with data 0x{}", hex::encode(data)),
+        }
+    }
+}
@@ -1,5 +1,4 @@
-#![allow(dead_code)]
-//! Contants for the sandbox
+//! Default environment of the sandbox
 
 use alloy_primitives::U256;
 
@@ -19,7 +18,19 @@ pub const GAS_PRICE: U256 = U256::from_le_bytes([
     5, 5, 5, 5, 5, 5, 5, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
 ]);
 
+pub const BLOCK_COINBASE: [u8; 20] = [3, 1, 4, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 5, 9, 2, 6];
+
 pub const BLOCK_NUMBER: u64 = 3141592;
 pub const BLOCK_GAS_LIMIT: u64 = 30_000_000;
 pub const BLOCK_TIMESTAMP: u64 = 1_234_567_890;
 pub const CHAIN_ID: u64 = 42331;
+
+/// Units of fuel, about one per WebAssembly instruction, a call can consume before it times out
+pub const EXECUTION_BUDGET: u64 = 1_000_000_000;
+
+/// Gas available at the beginning of every call
+pub const GAS_LEFT: u64 = 10_000_000;
+/// Gas consumed by every query of the gas or ink left
+pub const GAS_LEFT_QUERY_COST: u64 = 5_000;
+/// Ink price, the units of ink a unit of gas is worth
+pub const INK_PER_GAS: u64 = 10_000;
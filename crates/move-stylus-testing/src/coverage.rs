@@ -0,0 +1,127 @@
+//! Coverage of the Move sources, measured from the blocks reported by the modules translated with
+//! the `coverage` option, and reported in the lcov format.
+//!
+//! The map from the blocks to the source is the `<module>.coverage.json` artifact emitted by the
+//! translation.
+use std::{collections::HashMap, fmt::Write};
+
+use anyhow::Result;
+use serde::Deserialize;
+
+/// Number of times each block ran, by the id of its function and the offset of its first
+/// instruction.
+pub type CoverageHits = HashMap<(u32, u16), u64>;
+
+#[derive(Deserialize)]
+struct CoverageMap {
+    source_path: String,
+    functions: Vec<CoverageFunction>,
+}
+
+#[derive(Deserialize)]
+struct CoverageFunction {
+    id: u32,
+    name: String,
+    line: u32,
+    blocks: Vec<CoverageBlock>,
+}
+
+#[derive(Deserialize)]
+struct CoverageBlock {
+    start: u16,
+    lines: Vec<u32>,
+    branch: Option<CoverageBranch>,
+}
+
+#[derive(Deserialize)]
+struct CoverageBranch {
+    line: u32,
+    targets: Vec<u16>,
+}
+
+/// Builds the lcov report of the hits, given the module's coverage map as JSON.
+///
+/// A line is hit as many times as the block that ran the most among the ones spanning it, and a
+/// function as many times as its entry block. Each branch is taken as many times as the block it
+/// continues in ran, so a block reached from several places counts the hits of all of them.
+pub fn lcov_report(coverage_map: &str, hits: &CoverageHits) -> Result<String> {
+    let coverage_map: CoverageMap = serde_json::from_str(coverage_map)?;
+    let block_hits = |id: u32, start: u16| hits.get(&(id, start)).copied().unwrap_or(0);
+
+    let mut report = String::new();
+    writeln!(report, "TN:")?;
+    writeln!(report, "SF:{}", coverage_map.source_path)?;
+
+    let mut lines: HashMap<u32, u64> = HashMap::new();
+    let mut branches = Vec::new();
+    let mut functions_hit = 0;
+    for function in &coverage_map.functions {
+        let entry_hits = function
+            .blocks
+            .iter()
+            .find(|block| block.start == 0)
+            .map_or(0, |block| block_hits(function.id, block.start));
+        if entry_hits > 0 {
+            functions_hit += 1;
+        }
+
+        writeln!(report, "FN:{},{}", function.line, function.name)?;
+        writeln!(report, "FNDA:{entry_hits},{}", function.name)?;
+
+        for block in &function.blocks {
+            let hits = block_hits(function.id, block.start);
+            for line in &block.lines {
+                let line_hits = lines.entry(*line).or_default();
+                *line_hits = (*line_hits).max(hits);
+            }
+
+            if let Some(branch) = &block.branch {
+                for target in &branch.targets {
+                    // Branches of blocks that never ran are reported as not evaluated
+                    let taken = (hits > 0).then(|| block_hits(function.id, *target));
+                    branches.push((branch.line, function.id, block.start, *target, taken));
+                }
+            }
+        }
+    }
+
+    writeln!(report, "FNF:{}", coverage_map.functions.len())?;
+    writeln!(report, "FNH:{functions_hit}")?;
+
+    for (line, function, block, target, taken) in &branches {
+        let taken = match taken {
+            Some(taken) => taken.to_string(),
+            None => "-".to_owned(),
+        };
+        // lcov identifies the branches by a block and a branch number, unique within the line
+        writeln!(
+            report,
+            "BRDA:{line},{},{target},{taken}",
+            (*function << 16) | u32::from(*block)
+        )?;
+    }
+    writeln!(report, "BRF:{}", branches.len())?;
+    writeln!(
+        report,
+        "BRH:{}",
+        branches
+            .iter()
+            .filter(|(.., taken)| taken.is_some_and(|taken| taken > 0))
+            .count()
+    )?;
+
+    let mut lines: Vec<(u32, u64)> = lines.into_iter().collect();
+    lines.sort();
+    for (line, hits) in &lines {
+        writeln!(report, "DA:{line},{hits}")?;
+    }
+    writeln!(report, "LF:{}", lines.len())?;
+    writeln!(
+        report,
+        "LH:{}",
+        lines.iter().filter(|(_, hits)| *hits > 0).count()
+    )?;
+    writeln!(report, "end_of_record")?;
+
+    Ok(report)
+}
@@ -0,0 +1,53 @@
+//! Storage resolver that forks the state of a contract deployed on a live node.
+use alloy_primitives::{Address, B256};
+use anyhow::{Context, Result, anyhow};
+use serde_json::{Value, json};
+
+use crate::StorageResolver;
+
+/// Resolves the slots with `eth_getStorageAt`, reading the storage of a contract at a pinned block
+/// so the tests are reproducible.
+pub struct RpcStorageResolver {
+    url: String,
+    address: Address,
+    block: u64,
+}
+
+impl RpcStorageResolver {
+    /// Creates a resolver reading the storage of the contract at `address`, as it was at `block`,
+    /// from the node's JSON-RPC endpoint at `url`.
+    pub fn new(url: impl Into<String>, address: [u8; 20], block: u64) -> Self {
+        Self {
+            url: url.into(),
+            address: Address::from(address),
+            block,
+        }
+    }
+}
+
+impl StorageResolver for RpcStorageResolver {
+    fn resolve(&self, slot: [u8; 32]) -> Result<[u8; 32]> {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "eth_getStorageAt",
+            "params": [self.address, B256::from(slot), format!("0x{:x}", self.block)],
+        });
+
+        let response: Value = ureq::post(&self.url)
+            .send_json(request)
+            .with_context(|| format!("failed to call eth_getStorageAt on {}", self.url))?
+            .into_json()?;
+
+        if let Some(error) = response.get("error") {
+            return Err(anyhow!("eth_getStorageAt failed: {error}"));
+        }
+
+        let value = response
+            .get("result")
+            .and_then(Value::as_str)
+            .ok_or_else(|| anyhow!("invalid eth_getStorageAt response: {response}"))?;
+
+        Ok(value.parse::<B256>()?.0)
+    }
+}
@@ -0,0 +1,91 @@
+//! Fuzzing harness for the entry functions, to be called from `cargo fuzz` targets.
+//!
+//! The fuzzer's input is turned into structurally valid calldata for the function, so the inputs
+//! reach the decoder and the function's body instead of being rejected by the router:
+//!
+//! ```ignore
+//! fuzz_target!(|input: &[u8]| {
+//!     let _ = fuzz_entrypoint(&SANDBOX, "addMod(uint256,uint256,uint256)", input);
+//! });
+//! ```
+use alloy_dyn_abi::{DynSolType, DynSolValue, JsonAbiExt};
+use alloy_json_abi::Function;
+use arbitrary::Unstructured;
+use wasmtime::Trap;
+
+use crate::{RuntimeSandbox, call_result::CallResult};
+
+/// Traps no program compiled from Move can reach, so they mean the generated code corrupted or
+/// misused its memory. Aborts, including the failed checks of the arithmetic, trap with
+/// `unreachable` instead.
+const CORRUPTION_TRAPS: [Trap; 5] = [
+    Trap::MemoryOutOfBounds,
+    Trap::HeapMisaligned,
+    Trap::TableOutOfBounds,
+    Trap::IndirectCallToNull,
+    Trap::BadSignature,
+];
+
+/// Calls the entry function with the given signature, such as `transfer(address,uint256)`, with
+/// arguments generated from the fuzzer's input. The sandbox's state is restored after the call,
+/// so every input runs against the same state.
+///
+/// Returns an error if the input is too short to generate the arguments. Panics if the signature
+/// is invalid, or if the execution traps in a way that means memory corruption.
+pub fn fuzz_entrypoint(
+    sandbox: &RuntimeSandbox,
+    signature: &str,
+    input: &[u8],
+) -> arbitrary::Result<CallResult> {
+    let function = parse_signature(signature);
+    let args = generate_arguments(&function, signature, input)?;
+    let calldata = function
+        .abi_encode_input(&args)
+        .expect("the arguments are generated from the function's types");
+
+    let snapshot = sandbox.snapshot();
+    let result = sandbox.call(calldata);
+    sandbox.revert_to_and_discard(snapshot);
+
+    if let CallResult::Trap(message) = &result {
+        if let Some(trap) = CORRUPTION_TRAPS
+            .iter()
+            .find(|trap| message.contains(&trap.to_string()))
+        {
+            panic!("{signature} trapped with `{trap}` for the arguments {args:?}: {message}");
+        }
+    }
+
+    Ok(result)
+}
+
+/// Returns the arguments `fuzz_entrypoint` calls the function with for the fuzzer's input, so a
+/// target can tell which results the arguments should give.
+///
+/// Returns an error if the input is too short to generate the arguments. Panics if the signature
+/// is invalid.
+pub fn fuzz_arguments(signature: &str, input: &[u8]) -> arbitrary::Result<Vec<DynSolValue>> {
+    generate_arguments(&parse_signature(signature), signature, input)
+}
+
+fn parse_signature(signature: &str) -> Function {
+    Function::parse(signature)
+        .unwrap_or_else(|e| panic!("invalid function signature {signature}: {e}"))
+}
+
+fn generate_arguments(
+    function: &Function,
+    signature: &str,
+    input: &[u8],
+) -> arbitrary::Result<Vec<DynSolValue>> {
+    let mut unstructured = Unstructured::new(input);
+    function
+        .inputs
+        .iter()
+        .map(|param| {
+            let ty = DynSolType::parse(&param.selector_type())
+                .unwrap_or_else(|e| panic!("invalid type in {signature}: {e}"));
+            ty.arbitrary_value(&mut unstructured)
+        })
+        .collect()
+}
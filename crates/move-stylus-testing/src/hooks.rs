@@ -0,0 +1,140 @@
+//! Overrides of the host functions, to inject faults in the sandbox's host or stub its behavior.
+//!
+//! A sandbox built with `RuntimeSandbox::builder` calls the override instead of the `vm_hooks`
+//! import it replaces. The override can call the original hook, so it can also wrap it:
+//!
+//! ```ignore
+//! let loads = AtomicUsize::new(0);
+//! let runtime = RuntimeSandbox::builder(&mut translated_package)
+//!     .override_hook("storage_load_bytes32", move |call| {
+//!         anyhow::ensure!(loads.fetch_add(1, Ordering::Relaxed) < 3, "storage unavailable");
+//!         call.call_original()
+//!     })
+//!     .build();
+//! ```
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::Result;
+use walrus::Module;
+use wasmtime::{Caller, Func, Val};
+
+use crate::{ModuleData, RuntimeSandbox, get_memory, profiling};
+
+/// Function a host function is replaced with. Returning an error traps the execution, as the
+/// hooks do when the host fails.
+pub type HookOverride = Arc<dyn Fn(&mut HookCall<'_, '_>) -> Result<()> + Send + Sync>;
+
+/// Invocation of an overridden host function.
+pub struct HookCall<'a, 'b> {
+    pub(crate) caller: Caller<'a, ModuleData>,
+    pub(crate) params: &'b [Val],
+    pub(crate) results: &'b mut [Val],
+    pub(crate) original: Func,
+}
+
+impl HookCall<'_, '_> {
+    /// Arguments the module called the hook with.
+    pub fn params(&self) -> &[Val] {
+        self.params
+    }
+
+    /// Arguments the module called the hook with, as the pointers and lengths they are.
+    ///
+    /// Panics if the argument is not an i32.
+    pub fn param_u32(&self, index: usize) -> u32 {
+        self.params[index]
+            .i32()
+            .unwrap_or_else(|| panic!("argument {index} is not an i32")) as u32
+    }
+
+    /// Values the hook returns to the module.
+    pub fn results(&mut self) -> &mut [Val] {
+        self.results
+    }
+
+    /// Runs the sandbox's implementation of the hook with the same arguments, setting its results.
+    pub fn call_original(&mut self) -> Result<()> {
+        self.original
+            .call(&mut self.caller, self.params, self.results)
+    }
+
+    pub fn read_memory(&self, ptr: u32, len: usize) -> Vec<u8> {
+        let mut data = vec![0; len];
+        get_memory(&self.caller)
+            .read(&self.caller, ptr as usize, &mut data)
+            .unwrap();
+        data
+    }
+
+    pub fn write_memory(&mut self, ptr: u32, data: &[u8]) {
+        get_memory(&self.caller)
+            .write(&mut self.caller, ptr as usize, data)
+            .unwrap();
+    }
+}
+
+/// Builds a sandbox with some of its host functions overridden.
+pub struct RuntimeSandboxBuilder {
+    wasm: Vec<u8>,
+    hook_overrides: HashMap<String, HookOverride>,
+    instruction_profiling: bool,
+}
+
+impl RuntimeSandboxBuilder {
+    pub(crate) fn new(module: &mut Module) -> Self {
+        Self {
+            wasm: module.emit_wasm(),
+            hook_overrides: HashMap::new(),
+            instruction_profiling: false,
+        }
+    }
+
+    /// Replaces the `vm_hooks` import `name`, such as `storage_load_bytes32`, with `hook`.
+    /// Overriding the same hook again replaces the previous override.
+    pub fn override_hook(
+        mut self,
+        name: &str,
+        hook: impl Fn(&mut HookCall<'_, '_>) -> Result<()> + Send + Sync + 'static,
+    ) -> Self {
+        self.hook_overrides.insert(name.to_owned(), Arc::new(hook));
+        self
+    }
+
+    /// Instruments the module to profile the instructions the calls run, by the stack of functions
+    /// they run in, returned by `RuntimeSandbox::get_instruction_profile`. The instrumentation
+    /// reports every run of instructions to the host, so the calls run much slower.
+    pub fn profile_instructions(mut self) -> Self {
+        self.instruction_profiling = true;
+        self
+    }
+
+    /// Creates the sandbox. Panics if the module does not import one of the overridden hooks,
+    /// since the override would never run.
+    pub fn build(self) -> RuntimeSandbox {
+        // The module is instrumented before the sandbox instruments its allocator, so the
+        // profile attributes the allocations to the allocator
+        let (wasm, profiler) = if self.instruction_profiling {
+            let mut module = Module::from_buffer(&self.wasm).unwrap();
+            let profiler = profiling::instrument(&mut module);
+            (module.emit_wasm(), Some(profiler))
+        } else {
+            (self.wasm, None)
+        };
+
+        let mut sandbox = RuntimeSandbox::from_wasm(&wasm);
+
+        for name in self.hook_overrides.keys() {
+            assert!(
+                sandbox
+                    .module
+                    .imports()
+                    .any(|import| import.module() == "vm_hooks" && import.name() == name),
+                "the module does not import the hook {name}"
+            );
+        }
+
+        sandbox.hook_overrides = self.hook_overrides;
+        *sandbox.profiler.lock().unwrap() = profiler;
+        sandbox
+    }
+}
@@ -0,0 +1,2439 @@
+//! Sandbox to unit-test Move contracts compiled to Stylus WebAssembly.
+//!
+//! The sandbox runs the module's `user_entrypoint` with wasmtime, and implements the `vm_hooks`
+//! host functions on top of an in-memory state: the storage, the balances and codes of the
+//! accounts, and the transaction and block environment, whose defaults are in [`constants`].
+//!
+//! Every call to [`RuntimeSandbox::call_entrypoint`] is a new transaction against the same
+//! state, so the storage written by one call is read by the following ones.
+pub mod call_result;
+pub mod constants;
+pub mod coverage;
+#[cfg(feature = "fork")]
+pub mod fork;
+#[cfg(feature = "fuzz")]
+pub mod fuzz;
+pub mod hooks;
+pub mod memory_stats;
+pub mod profiling;
+pub mod recording;
+pub mod reentrancy;
+#[cfg(feature = "storage-journal")]
+pub mod storage_journal;
+pub mod storage_layout;
+pub mod trace;
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::Path,
+    str::FromStr,
+    sync::{Arc, Mutex, mpsc},
+};
+
+use alloy_primitives::{Address, B256, Signature, U256, keccak256};
+use alloy_sol_types::{SolEvent, SolType};
+use anyhow::{Context, Result};
+use call_result::{CallResult, RevertReason};
+use constants::{
+    BLOCK_BASEFEE, BLOCK_COINBASE, BLOCK_GAS_LIMIT, BLOCK_NUMBER, BLOCK_TIMESTAMP, CHAIN_ID,
+    CONTRACT_ADDRESS, EXECUTION_BUDGET, GAS_LEFT, GAS_LEFT_QUERY_COST, GAS_PRICE, INK_PER_GAS,
+    MSG_SENDER_ADDRESS, MSG_VALUE, SIGNER_ADDRESS,
+};
+use coverage::CoverageHits;
+use hooks::{HookCall, HookOverride, RuntimeSandboxBuilder};
+use memory_stats::MemoryStats;
+use num_bigint::BigUint;
+use profiling::{InstructionProfile, Profiler};
+use recording::Recording;
+use reentrancy::ReentrancyAttacker;
+use ripemd::Ripemd160;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use storage_layout::StorageLayout;
+use trace::{CallTrace, HostCall};
+use walrus::Module;
+use wasmtime::{
+    AsContext, Caller, Config, Engine, Extern, Func, Instance, Linker, Memory,
+    Module as WasmModule, Store, Trap, Val,
+};
+
+#[cfg(feature = "storage-journal")]
+use storage_journal::StorageJournal;
+
+struct ModuleData {
+    pub data: Vec<u8>,
+    pub return_data: Vec<u8>,
+    /// Data returned by the last call to another contract
+    pub call_return_data: Vec<u8>,
+    /// Gas left in the call, consumed by every query of it
+    pub gas_left: u64,
+    /// Block the call is executed in
+    pub block: BlockEnv,
+    /// Value sent with the call, in wei
+    pub value: U256,
+    /// Memory of the module's instance, set once it is instantiated
+    pub memory: Option<Memory>,
+}
+
+/// Block the calls are executed in, and the chain it belongs to.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+struct BlockEnv {
+    number: u64,
+    timestamp: u64,
+    basefee: U256,
+    gas_limit: u64,
+    chain_id: u64,
+    coinbase: Address,
+}
+
+/// State saved by [`RuntimeSandbox::snapshot`].
+struct Snapshot {
+    storage: HashMap<[u8; 32], [u8; 32]>,
+    balances: HashMap<[u8; 20], U256>,
+    block: BlockEnv,
+}
+
+/// State saved by [`RuntimeSandbox::dump_state`]. The missing fields are left empty, or the
+/// default block, when it is loaded.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct SandboxState {
+    storage: BTreeMap<B256, B256>,
+    balances: BTreeMap<Address, U256>,
+    block: BlockEnv,
+}
+
+impl Default for BlockEnv {
+    fn default() -> Self {
+        Self {
+            number: BLOCK_NUMBER,
+            timestamp: BLOCK_TIMESTAMP,
+            basefee: BLOCK_BASEFEE,
+            gas_limit: BLOCK_GAS_LIMIT,
+            chain_id: CHAIN_ID,
+            coinbase: Address::new(BLOCK_COINBASE),
+        }
+    }
+}
+
+/// Source of the values of the slots the sandbox's storage has no value for, such as the node the
+/// state is forked from. The resolved values are cached in the storage, so every slot is resolved
+/// at most once.
+pub trait StorageResolver: Send + Sync {
+    fn resolve(&self, slot: [u8; 32]) -> Result<[u8; 32]>;
+}
+
+impl<F> StorageResolver for F
+where
+    F: Fn([u8; 32]) -> Result<[u8; 32]> + Send + Sync,
+{
+    fn resolve(&self, slot: [u8; 32]) -> Result<[u8; 32]> {
+        self(slot)
+    }
+}
+
+/// Status the router returns when no entry function matches the selector
+const ERROR_NO_FUNCTION_MATCH: i32 = -100;
+
+/// Error the `exit_early` hook stops the execution with, carrying the status code.
+#[derive(Debug)]
+struct ExitEarly(i32);
+
+impl std::fmt::Display for ExitEarly {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "exit early with status {}", self.0)
+    }
+}
+
+impl std::error::Error for ExitEarly {}
+
+/// Error the calls fail with when they run out of their execution budget, as a non-terminating
+/// loop does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExecutionTimeout {
+    /// Units of fuel the call was given
+    pub budget: u64,
+}
+
+impl std::fmt::Display for ExecutionTimeout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "execution timed out after consuming its budget of {} units of fuel",
+            self.budget
+        )
+    }
+}
+
+impl std::error::Error for ExecutionTimeout {}
+
+/// Error the calls fail with when the Move code aborts, in the modules translated with the
+/// `unit_tests` option, that report the abort code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MoveAbort {
+    pub code: u64,
+}
+
+impl std::fmt::Display for MoveAbort {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Move code aborted with code {}", self.code)
+    }
+}
+
+impl std::error::Error for MoveAbort {}
+
+/// Error the calls made with `call_view` fail with when they write to the storage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StorageWriteInView {
+    pub slot: [u8; 32],
+}
+
+impl std::fmt::Display for StorageWriteInView {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "the view call wrote to the storage slot {}",
+            B256::from(self.slot)
+        )
+    }
+}
+
+impl std::error::Error for StorageWriteInView {}
+
+/// A log emitted by the module, split into its topics and its data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogEvent {
+    pub topics: Vec<[u8; 32]>,
+    pub data: Vec<u8>,
+}
+
+impl LogEvent {
+    /// Decodes the log as the event `E`, generated with alloy's `sol!` macro. Returns `None` if
+    /// the log is not an `E` event.
+    ///
+    /// The data of the events with dynamic fields is the struct encoded as a single value, with
+    /// the offset at the beginning, so it is decoded as such instead of as a list of parameters.
+    pub fn decode<E: SolEvent>(&self) -> Option<E> {
+        if !E::ANONYMOUS && self.topics.first() != Some(&E::SIGNATURE_HASH.0) {
+            return None;
+        }
+
+        let topics = E::decode_topics(self.topics.iter().copied().map(B256::from)).ok()?;
+        let data = <E::DataTuple<'_> as SolType>::abi_decode(&self.data).ok()?;
+
+        Some(E::new(topics, data))
+    }
+}
+
+/// Hook a call to another contract was received by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallKind {
+    Call,
+    DelegateCall,
+    StaticCall,
+}
+
+/// A call to another contract received by the host.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExternalCall {
+    pub kind: CallKind,
+    pub target: [u8; 20],
+    pub calldata: Vec<u8>,
+    /// Value the called contract sees, in big-endian. Delegate calls keep the value of the current
+    /// call
+    pub value: [u8; 32],
+}
+
+/// A contract deployment received by the `create1` or `create2` hooks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContractCreation {
+    pub init_code: Vec<u8>,
+    /// Value sent, in big-endian
+    pub endowment: [u8; 32],
+    /// Salt of `create2` deployments
+    pub salt: Option<[u8; 32]>,
+}
+
+pub struct RuntimeSandbox {
+    engine: Engine,
+    linker: Linker<ModuleData>,
+    module: WasmModule,
+    pub log_events: Arc<Mutex<mpsc::Receiver<LogEvent>>>,
+    logs: Arc<Mutex<Vec<LogEvent>>>,
+    current_tx_origin: Arc<Mutex<[u8; 20]>>,
+    current_msg_sender: Arc<Mutex<[u8; 20]>>,
+    msg_reentrant: Arc<Mutex<bool>>,
+    /// Number of calls to the contract in progress, more than one while it is re-entered
+    call_depth: Arc<Mutex<usize>>,
+    storage: Arc<Mutex<HashMap<[u8; 32], [u8; 32]>>>,
+    storage_writes: Arc<Mutex<HashMap<[u8; 32], usize>>>,
+    /// Set during `call_view`, when the storage writes fail the call
+    view_call: Arc<Mutex<bool>>,
+    keccak_calls: Arc<Mutex<usize>>,
+    memory_grows: Arc<Mutex<Vec<u32>>>,
+    /// Memory used by the last call, or `None` if the module's allocator is not instrumented
+    memory_stats: Arc<Mutex<Option<MemoryStats>>>,
+    storage_flushes: Arc<Mutex<Vec<bool>>>,
+    debug_logs: Arc<Mutex<Vec<String>>>,
+    external_calls: Arc<Mutex<Vec<ExternalCall>>>,
+    external_call_response: Arc<Mutex<(bool, Vec<u8>)>>,
+    contract_creations: Arc<Mutex<Vec<ContractCreation>>>,
+    create_response: Arc<Mutex<(Option<[u8; 20]>, Vec<u8>)>>,
+    balances: Arc<Mutex<HashMap<[u8; 20], U256>>>,
+    codes: Arc<Mutex<HashMap<[u8; 20], Vec<u8>>>>,
+    gas_left: Arc<Mutex<u64>>,
+    /// Units of fuel every call can consume
+    execution_budget: Arc<Mutex<u64>>,
+    block: Arc<Mutex<BlockEnv>>,
+    snapshots: Mutex<Vec<Snapshot>>,
+    /// Calls recorded since `start_recording`, if the sandbox is recording
+    recording: Mutex<Option<Recording>>,
+    storage_resolver: Arc<Mutex<Option<Box<dyn StorageResolver>>>>,
+    /// Values the storage resolver returned, so every slot is resolved once. Kept apart from the
+    /// storage, since they were read and not written
+    resolved_storage: Arc<Mutex<HashMap<[u8; 32], [u8; 32]>>>,
+    block_hashes: Arc<Mutex<HashMap<u64, [u8; 32]>>>,
+    /// Seed of the hashes of the recent blocks without a configured hash, if any
+    block_hash_seed: Arc<Mutex<Option<[u8; 32]>>>,
+    contract_address: Arc<Mutex<[u8; 20]>>,
+    /// Contracts the calls are routed to, by address
+    contracts: Arc<Mutex<HashMap<[u8; 20], Arc<RuntimeSandbox>>>>,
+    /// Contracts that re-enter this one when it calls them, by address
+    attackers: Arc<Mutex<HashMap<[u8; 20], Arc<ReentrancyAttacker>>>>,
+    #[cfg(feature = "storage-journal")]
+    storage_journal: Arc<Mutex<StorageJournal>>,
+    /// Trace of the last call, or `None` if tracing is disabled
+    trace: Arc<Mutex<Option<CallTrace>>>,
+    /// Blocks reported by the coverage instrumentation, accumulated over all the calls
+    coverage_hits: Arc<Mutex<CoverageHits>>,
+    /// Instructions run by the calls, if the module is instrumented to profile them
+    profiler: Arc<Mutex<Option<Profiler>>>,
+    /// Functions the `vm_hooks` imports are replaced with, by name
+    hook_overrides: HashMap<String, HookOverride>,
+}
+
+macro_rules! link_fn_ret_constant {
+    ($linker:expr, $name:literal, $constant:expr, $constant_type: ty) => {
+        $linker
+            .func_wrap(
+                "vm_hooks",
+                $name,
+                move |_caller: Caller<'_, ModuleData>| -> $constant_type {
+                    $constant as $constant_type
+                },
+            )
+            .unwrap();
+    };
+}
+
+/// Returns the memory of the module's instance. It is kept in the store instead of being looked up
+/// in the caller's exports because the traced hooks are called from the tracer, not from the
+/// instance.
+fn get_memory(caller: &Caller<'_, ModuleData>) -> Memory {
+    caller.data().memory.expect("failed to find host memory")
+}
+
+/// Reads a big-endian u256 from the module's memory.
+fn read_u256_be(mem: &Memory, store: impl AsContext, ptr: u32) -> U256 {
+    let mut bytes = [0; 32];
+    mem.read(store, ptr as usize, &mut bytes).unwrap();
+    U256::from_be_bytes(bytes)
+}
+
+/// Moves `value` wei between two accounts. Returns `false`, and moves nothing, if `from` can't pay
+/// it.
+fn transfer(
+    balances: &mut HashMap<[u8; 20], U256>,
+    from: [u8; 20],
+    to: [u8; 20],
+    value: U256,
+) -> bool {
+    let from_balance = balances.get(&from).copied().unwrap_or(U256::ZERO);
+    if from_balance < value {
+        return false;
+    }
+
+    if !value.is_zero() && from != to {
+        balances.insert(from, from_balance - value);
+        *balances.entry(to).or_insert(U256::ZERO) += value;
+    }
+
+    true
+}
+
+/// Last byte of the address of ArbSys
+const ARB_SYS_ADDRESS: u8 = 0x64;
+
+/// Longest base, exponent and modulus the modexp precompile takes, in bytes, the bound EIP-7823
+/// sets
+const MODEXP_MAX_LENGTH: usize = 1024;
+
+/// Emulates the standard precompiles: ecrecover, sha256, ripemd160, identity and modexp. Returns
+/// whether the precompile at `target` succeeds and its output, or `None` if it is not a
+/// precompile.
+fn run_precompile(target: &[u8; 20], input: &[u8]) -> Option<(bool, Vec<u8>)> {
+    if target[..19].iter().any(|b| *b != 0) {
+        return None;
+    }
+
+    match target[19] {
+        0x01 => Some((true, ecrecover(input))),
+        0x02 => Some((true, Sha256::digest(input).to_vec())),
+        // The hash is left-padded to 32 bytes
+        0x03 => Some((
+            true,
+            [&[0; 12], Ripemd160::digest(input).as_slice()].concat(),
+        )),
+        0x04 => Some((true, input.to_vec())),
+        0x05 => Some(modexp(input).map_or((false, vec![]), |output| (true, output))),
+        _ => None,
+    }
+}
+
+/// Emulates the `arbBlockHash(uint256)` function of ArbSys with the configured block hashes.
+/// Returns the result of the call, or `None` if `target` is not ArbSys. It reverts for the blocks
+/// without a hash, the same as ArbSys does for the ones that are not among the 256 most recent.
+///
+/// With a seed, the 256 blocks before `current_block` without a configured hash have the hash of
+/// the seed and their number.
+fn run_arb_sys(
+    target: &[u8; 20],
+    calldata: &[u8],
+    block_hashes: &HashMap<u64, [u8; 32]>,
+    block_hash_seed: Option<[u8; 32]>,
+    current_block: u64,
+) -> Option<(bool, Vec<u8>)> {
+    if target[..19].iter().any(|b| *b != 0) || target[19] != ARB_SYS_ADDRESS {
+        return None;
+    }
+
+    let selector = &keccak256("arbBlockHash(uint256)")[..4];
+    if calldata.len() != 36 || &calldata[..4] != selector {
+        return Some((false, vec![]));
+    }
+
+    let number = U256::from_be_slice(&calldata[4..36]);
+    let hash = u64::try_from(number).ok().and_then(|number| {
+        block_hashes.get(&number).copied().or_else(|| {
+            let seed = block_hash_seed?;
+            let recent = number < current_block && current_block - number <= 256;
+            recent.then(|| keccak256([&seed[..], &number.to_be_bytes()].concat()).0)
+        })
+    });
+
+    match hash {
+        Some(hash) => Some((true, hash.to_vec())),
+        None => Some((false, vec![])),
+    }
+}
+
+/// Recovers the signer of a message hash. The input is read as four 32 bytes words, zero-padded if
+/// it is shorter, and invalid signatures return no data, the same as the ecrecover precompile.
+fn ecrecover(input: &[u8]) -> Vec<u8> {
+    let mut input = input.to_vec();
+    input.resize(128, 0);
+
+    let hash = B256::from_slice(&input[0..32]);
+    let v = U256::from_be_slice(&input[32..64]);
+    let r = U256::from_be_slice(&input[64..96]);
+    let s = U256::from_be_slice(&input[96..128]);
+
+    let y_parity = if v == U256::from(27) {
+        false
+    } else if v == U256::from(28) {
+        true
+    } else {
+        return vec![];
+    };
+
+    Signature::new(r, s, y_parity)
+        .recover_address_from_prehash(&hash)
+        .map(|address| address.into_word().to_vec())
+        .unwrap_or_default()
+}
+
+/// Computes `base ^ exponent % modulus`. The input is the lengths of the operands, as three 32
+/// bytes words, and the big-endian operands, zero-padded if it is shorter, the same as the modexp
+/// precompile. The result is as long as the modulus, and zero if the modulus is zero.
+///
+/// Returns `None`, failing the same as the precompile does, if an operand is longer than
+/// `MODEXP_MAX_LENGTH`.
+fn modexp(input: &[u8]) -> Option<Vec<u8>> {
+    // Reads `length` bytes from `offset`, zero-padded past the end of the input
+    let read = |offset: usize, length: usize| {
+        let mut bytes = input.get(offset..).unwrap_or_default().to_vec();
+        bytes.resize(length, 0);
+        bytes
+    };
+    let read_length = |offset: usize| {
+        usize::try_from(U256::from_be_slice(&read(offset, 32)))
+            .ok()
+            .filter(|length| *length <= MODEXP_MAX_LENGTH)
+    };
+
+    let base_length = read_length(0)?;
+    let exponent_length = read_length(32)?;
+    let modulus_length = read_length(64)?;
+
+    let base = BigUint::from_bytes_be(&read(96, base_length));
+    let exponent = BigUint::from_bytes_be(&read(96 + base_length, exponent_length));
+    let modulus = BigUint::from_bytes_be(&read(96 + base_length + exponent_length, modulus_length));
+
+    let mut output = vec![0; modulus_length];
+    if modulus != BigUint::ZERO {
+        let result = base.modpow(&exponent, &modulus).to_bytes_be();
+        // The result is left-padded to the length of the modulus, and zero is encoded as [0]
+        if result != [0] {
+            output[modulus_length - result.len()..].copy_from_slice(&result);
+        }
+    }
+
+    Some(output)
+}
+
+/// Links a math hook. The operands are read as big-endian u256, and the result overwrites the
+/// first one.
+macro_rules! link_fn_math_hook {
+    ($linker:expr, $name:literal, |$value:ident: U256, $operand:ident: U256| $result:expr) => {
+        $linker
+            .func_wrap(
+                "vm_hooks",
+                $name,
+                move |mut caller: Caller<'_, ModuleData>, value_ptr: u32, operand_ptr: u32| {
+                    let mem = get_memory(&caller);
+
+                    let $value = read_u256_be(&mem, &caller, value_ptr);
+                    let $operand = read_u256_be(&mem, &caller, operand_ptr);
+
+                    mem.write(
+                        &mut caller,
+                        value_ptr as usize,
+                        &$result.to_be_bytes::<32>(),
+                    )
+                    .unwrap();
+                },
+            )
+            .unwrap();
+    };
+    (
+        $linker:expr,
+        $name:literal,
+        |$value:ident: U256, $operand:ident: U256, $modulus:ident: U256| $result:expr
+    ) => {
+        $linker
+            .func_wrap(
+                "vm_hooks",
+                $name,
+                move |mut caller: Caller<'_, ModuleData>,
+                      value_ptr: u32,
+                      operand_ptr: u32,
+                      modulus_ptr: u32| {
+                    let mem = get_memory(&caller);
+
+                    let $value = read_u256_be(&mem, &caller, value_ptr);
+                    let $operand = read_u256_be(&mem, &caller, operand_ptr);
+                    let $modulus = read_u256_be(&mem, &caller, modulus_ptr);
+
+                    mem.write(
+                        &mut caller,
+                        value_ptr as usize,
+                        &$result.to_be_bytes::<32>(),
+                    )
+                    .unwrap();
+                },
+            )
+            .unwrap();
+    };
+}
+
+/// Links a hook that returns a field of the block the call is executed in.
+macro_rules! link_fn_ret_block_field {
+    ($linker:expr, $name:literal, $field:ident) => {
+        $linker
+            .func_wrap("vm_hooks", $name, |caller: Caller<'_, ModuleData>| -> i64 {
+                caller.data().block.$field as i64
+            })
+            .unwrap();
+    };
+}
+
+macro_rules! link_fn_write_constant {
+    ($linker:expr, $name:literal, $constant:expr) => {
+        $linker
+            .func_wrap(
+                "vm_hooks",
+                $name,
+                move |mut caller: Caller<'_, ModuleData>, ptr: u32| {
+                    let mem = get_memory(&caller);
+
+                    mem.write(&mut caller, ptr as usize, &$constant).unwrap();
+                },
+            )
+            .unwrap();
+    };
+    () => {};
+}
+
+impl RuntimeSandbox {
+    /// Creates a sandbox running the module, as translated by `move-bytecode-to-wasm`.
+    pub fn new(module: &mut Module) -> Self {
+        Self::from_wasm(&module.emit_wasm())
+    }
+
+    /// Starts building a sandbox running the module, with some of its host functions overridden
+    /// (see `hooks`).
+    pub fn builder(module: &mut Module) -> RuntimeSandboxBuilder {
+        RuntimeSandboxBuilder::new(module)
+    }
+
+    /// Creates a sandbox running an already emitted WebAssembly binary, such as the `.wasm` files
+    /// of a package's build directory.
+    pub fn from_wasm(wasm: &[u8]) -> Self {
+        // The executions consume fuel so the non-terminating ones time out instead of hanging
+        let engine = Engine::new(Config::new().consume_fuel(true)).unwrap();
+
+        // The allocator is instrumented to report the memory used by the calls
+        let mut instrumented_module = Module::from_buffer(wasm).unwrap();
+        memory_stats::instrument_allocator(&mut instrumented_module);
+        let module = WasmModule::from_binary(&engine, &instrumented_module.emit_wasm()).unwrap();
+
+        let storage: Arc<Mutex<HashMap<[u8; 32], [u8; 32]>>> = Arc::new(Mutex::new(HashMap::new()));
+        let storage_writes: Arc<Mutex<HashMap<[u8; 32], usize>>> =
+            Arc::new(Mutex::new(HashMap::new()));
+        let current_tx_origin = Arc::new(Mutex::new(SIGNER_ADDRESS));
+        let current_msg_sender = Arc::new(Mutex::new(MSG_SENDER_ADDRESS));
+        let msg_reentrant = Arc::new(Mutex::new(false));
+        let keccak_calls = Arc::new(Mutex::new(0));
+        let memory_grows = Arc::new(Mutex::new(Vec::new()));
+        let storage_flushes = Arc::new(Mutex::new(Vec::new()));
+        let debug_logs = Arc::new(Mutex::new(Vec::new()));
+        let external_calls = Arc::new(Mutex::new(Vec::new()));
+        let external_call_response = Arc::new(Mutex::new((true, Vec::new())));
+        let contract_creations = Arc::new(Mutex::new(Vec::new()));
+        let create_response = Arc::new(Mutex::new((None, Vec::new())));
+        let balances: Arc<Mutex<HashMap<[u8; 20], U256>>> = Arc::new(Mutex::new(HashMap::new()));
+        let codes: Arc<Mutex<HashMap<[u8; 20], Vec<u8>>>> = Arc::new(Mutex::new(HashMap::new()));
+        let block_hashes: Arc<Mutex<HashMap<u64, [u8; 32]>>> = Arc::new(Mutex::new(HashMap::new()));
+        let block_hash_seed = Arc::new(Mutex::new(None));
+        let contract_address = Arc::new(Mutex::new(
+            Address::from_str(CONTRACT_ADDRESS).unwrap().into_array(),
+        ));
+        let contracts: Arc<Mutex<HashMap<[u8; 20], Arc<RuntimeSandbox>>>> =
+            Arc::new(Mutex::new(HashMap::new()));
+        let attackers: Arc<Mutex<HashMap<[u8; 20], Arc<ReentrancyAttacker>>>> =
+            Arc::new(Mutex::new(HashMap::new()));
+
+        let (log_sender, log_receiver) = mpsc::channel::<LogEvent>();
+        let logs = Arc::new(Mutex::new(Vec::new()));
+        let mut linker = Linker::new(&engine);
+
+        linker
+            .func_wrap(
+                "vm_hooks",
+                "read_args",
+                move |mut caller: Caller<'_, ModuleData>, args_ptr: u32| {
+                    let mem = get_memory(&mut caller);
+
+                    let args_data = caller.data().data.clone();
+
+                    mem.write(&mut caller, args_ptr as usize, &args_data)
+                        .unwrap();
+
+                    Ok(())
+                },
+            )
+            .unwrap();
+
+        linker
+            .func_wrap(
+                "vm_hooks",
+                "write_result",
+                move |mut caller: Caller<'_, ModuleData>,
+                      return_data_pointer: u32,
+                      return_data_length: u32| {
+                    let mem = get_memory(&caller);
+
+                    let mut result = vec![0; return_data_length as usize];
+                    mem.read(&caller, return_data_pointer as usize, &mut result)
+                        .unwrap();
+
+                    let return_data = caller.data_mut();
+                    return_data.return_data = result;
+
+                    Ok(())
+                },
+            )
+            .unwrap();
+
+        linker
+            .func_wrap("vm_hooks", "exit_early", |status: u32| -> Result<()> {
+                Err(ExitEarly(status as i32).into())
+            })
+            .unwrap();
+
+        linker
+            .func_wrap("unit_test", "abort", |code: u64| -> Result<()> {
+                Err(MoveAbort { code }.into())
+            })
+            .unwrap();
+
+        let memory_grows_for_pay = memory_grows.clone();
+        linker
+            .func_wrap("vm_hooks", "pay_for_memory_grow", move |pages: u32| {
+                memory_grows_for_pay.lock().unwrap().push(pages);
+            })
+            .unwrap();
+
+        let storage_flushes_for_flush = storage_flushes.clone();
+        linker
+            .func_wrap("vm_hooks", "storage_flush_cache", move |clear: i32| {
+                storage_flushes_for_flush.lock().unwrap().push(clear != 0);
+            })
+            .unwrap();
+
+        let coverage_hits = Arc::new(Mutex::new(CoverageHits::new()));
+        let coverage_hits_for_hit = coverage_hits.clone();
+        linker
+            .func_wrap("coverage", "hit", move |function: u32, block: u32| {
+                *coverage_hits_for_hit
+                    .lock()
+                    .unwrap()
+                    .entry((function, block as u16))
+                    .or_insert(0) += 1;
+            })
+            .unwrap();
+
+        let profiler: Arc<Mutex<Option<Profiler>>> = Arc::new(Mutex::new(None));
+        let profiler_for_enter = profiler.clone();
+        linker
+            .func_wrap("profiler", "enter", move |function: u32| {
+                if let Some(profiler) = profiler_for_enter.lock().unwrap().as_mut() {
+                    profiler.enter(function);
+                }
+            })
+            .unwrap();
+
+        let profiler_for_enter_indirect = profiler.clone();
+        linker
+            .func_wrap("profiler", "enter_indirect", move |table_index: u32| {
+                if let Some(profiler) = profiler_for_enter_indirect.lock().unwrap().as_mut() {
+                    profiler.enter_indirect(table_index);
+                }
+            })
+            .unwrap();
+
+        let profiler_for_exit = profiler.clone();
+        linker
+            .func_wrap("profiler", "exit", move || {
+                if let Some(profiler) = profiler_for_exit.lock().unwrap().as_mut() {
+                    profiler.exit();
+                }
+            })
+            .unwrap();
+
+        let profiler_for_count = profiler.clone();
+        linker
+            .func_wrap("profiler", "count", move |instructions: u32| {
+                if let Some(profiler) = profiler_for_count.lock().unwrap().as_mut() {
+                    profiler.count(instructions);
+                }
+            })
+            .unwrap();
+
+        let debug_logs_for_log = debug_logs.clone();
+        linker
+            .func_wrap(
+                "console",
+                "log_txt",
+                move |mut caller: Caller<'_, ModuleData>, text_ptr: u32, text_len: u32| {
+                    let mem = get_memory(&mut caller);
+
+                    let mut text = vec![0; text_len as usize];
+                    mem.read(&mut caller, text_ptr as usize, &mut text).unwrap();
+
+                    debug_logs_for_log
+                        .lock()
+                        .unwrap()
+                        .push(String::from_utf8(text).unwrap());
+                },
+            )
+            .unwrap();
+
+        let keccak_calls_for_hash = keccak_calls.clone();
+        linker
+            .func_wrap(
+                "vm_hooks",
+                "native_keccak256",
+                move |mut caller: Caller<'_, ModuleData>,
+                      input_data_ptr: u32,
+                      data_length: u32,
+                      return_data_ptr: u32| {
+                    let mem = get_memory(&caller);
+
+                    let mut input_data = vec![0; data_length as usize];
+                    mem.read(&caller, input_data_ptr as usize, &mut input_data)
+                        .unwrap();
+
+                    let hash = keccak256(input_data);
+                    *keccak_calls_for_hash.lock().unwrap() += 1;
+
+                    mem.write(&mut caller, return_data_ptr as usize, hash.as_slice())
+                        .unwrap();
+
+                    Ok(())
+                },
+            )
+            .unwrap();
+
+        let logs_for_emit = logs.clone();
+        linker
+            .func_wrap(
+                "vm_hooks",
+                "emit_log",
+                move |mut caller: Caller<'_, ModuleData>, ptr: u32, len: u32, topics: u32| {
+                    let mem = get_memory(&mut caller);
+                    let mut buffer = vec![0; len as usize];
+
+                    mem.read(&mut caller, ptr as usize, &mut buffer).unwrap();
+
+                    // The topics come first, 32 bytes each, followed by the data
+                    let data = buffer.split_off(topics as usize * 32);
+                    let topics = buffer
+                        .chunks_exact(32)
+                        .map(|topic| topic.try_into().unwrap())
+                        .collect();
+
+                    let log = LogEvent { topics, data };
+                    logs_for_emit.lock().unwrap().push(log.clone());
+                    log_sender.send(log).unwrap();
+                },
+            )
+            .unwrap();
+
+        #[cfg(feature = "storage-journal")]
+        let storage_journal = Arc::new(Mutex::new(StorageJournal::default()));
+
+        let view_call = Arc::new(Mutex::new(false));
+
+        let storage_for_cache = storage.clone();
+        let storage_writes_for_cache = storage_writes.clone();
+        let view_call_for_cache = view_call.clone();
+        #[cfg(feature = "storage-journal")]
+        let storage_journal_for_cache = storage_journal.clone();
+        linker
+            .func_wrap(
+                "vm_hooks",
+                "storage_cache_bytes32",
+                move |mut caller: Caller<'_, ModuleData>,
+                      key_ptr: u32,
+                      value_ptr: u32|
+                      -> Result<()> {
+                    let mem = get_memory(&mut caller);
+                    let mut key_buffer = [0; 32];
+                    mem.read(&mut caller, key_ptr as usize, &mut key_buffer)
+                        .unwrap();
+
+                    if *view_call_for_cache.lock().unwrap() {
+                        return Err(StorageWriteInView { slot: key_buffer }.into());
+                    }
+
+                    let mut value_buffer = [0; 32];
+                    mem.read(&mut caller, value_ptr as usize, &mut value_buffer)
+                        .unwrap();
+
+                    let mut storage = storage_for_cache.lock().unwrap();
+                    let _previous = (*storage).insert(key_buffer, value_buffer);
+
+                    #[cfg(feature = "storage-journal")]
+                    storage_journal_for_cache.lock().unwrap().record_write(
+                        key_buffer,
+                        _previous.unwrap_or([0; 32]),
+                        value_buffer,
+                    );
+
+                    let mut storage_writes = storage_writes_for_cache.lock().unwrap();
+                    *(*storage_writes).entry(key_buffer).or_insert(0) += 1;
+
+                    Ok(())
+                },
+            )
+            .unwrap();
+
+        let storage_resolver: Arc<Mutex<Option<Box<dyn StorageResolver>>>> =
+            Arc::new(Mutex::new(None));
+        let resolved_storage: Arc<Mutex<HashMap<[u8; 32], [u8; 32]>>> =
+            Arc::new(Mutex::new(HashMap::new()));
+
+        let storage_for_cache = storage.clone();
+        let storage_resolver_for_load = storage_resolver.clone();
+        let resolved_storage_for_load = resolved_storage.clone();
+        #[cfg(feature = "storage-journal")]
+        let storage_journal_for_load = storage_journal.clone();
+        linker
+            .func_wrap(
+                "vm_hooks",
+                "storage_load_bytes32",
+                move |mut caller: Caller<'_, ModuleData>,
+                      key_ptr: u32,
+                      dest_ptr: u32|
+                      -> Result<()> {
+                    let mem = get_memory(&mut caller);
+                    let mut key_buffer = [0; 32];
+                    mem.read(&mut caller, key_ptr as usize, &mut key_buffer)
+                        .unwrap();
+
+                    let written = storage_for_cache.lock().unwrap().get(&key_buffer).copied();
+                    let value = match written {
+                        Some(value) => value,
+                        None => {
+                            let mut resolved_storage = resolved_storage_for_load.lock().unwrap();
+                            match (
+                                resolved_storage.get(&key_buffer),
+                                &*storage_resolver_for_load.lock().unwrap(),
+                            ) {
+                                (Some(value), _) => *value,
+                                (None, Some(resolver)) => {
+                                    let value = resolver.resolve(key_buffer)?;
+                                    resolved_storage.insert(key_buffer, value);
+                                    value
+                                }
+                                (None, None) => [0; 32],
+                            }
+                        }
+                    };
+
+                    #[cfg(feature = "storage-journal")]
+                    storage_journal_for_load
+                        .lock()
+                        .unwrap()
+                        .record_read(key_buffer, value);
+
+                    mem.write(&mut caller, dest_ptr as usize, value.as_slice())
+                        .unwrap();
+
+                    Ok(())
+                },
+            )
+            .unwrap();
+
+        let tx_orign = current_tx_origin.clone();
+        linker
+            .func_wrap(
+                "vm_hooks",
+                "tx_origin",
+                move |mut caller: Caller<'_, ModuleData>, ptr: u32| {
+                    let mem = get_memory(&caller);
+
+                    let data = tx_orign.lock().unwrap();
+                    mem.write(&mut caller, ptr as usize, &*data).unwrap();
+                },
+            )
+            .unwrap();
+
+        let msg_sender = current_msg_sender.clone();
+        linker
+            .func_wrap(
+                "vm_hooks",
+                "msg_sender",
+                move |mut caller: Caller<'_, ModuleData>, ptr: u32| {
+                    let mem = get_memory(&caller);
+
+                    let data = msg_sender.lock().unwrap();
+                    mem.write(&mut caller, ptr as usize, &*data).unwrap();
+                },
+            )
+            .unwrap();
+
+        let reentrant = msg_reentrant.clone();
+        let call_depth = Arc::new(Mutex::new(0));
+        let call_depth_for_reentrant = call_depth.clone();
+        linker
+            .func_wrap(
+                "vm_hooks",
+                "msg_reentrant",
+                move |_caller: Caller<'_, ModuleData>| -> i32 {
+                    (*reentrant.lock().unwrap() || *call_depth_for_reentrant.lock().unwrap() > 1)
+                        as i32
+                },
+            )
+            .unwrap();
+
+        let external_calls_for_call = external_calls.clone();
+        let external_call_response_for_call = external_call_response.clone();
+        let balances_for_call = balances.clone();
+        let contract_address_for_call = contract_address.clone();
+        let tx_origin_for_call = current_tx_origin.clone();
+        let contracts_for_call = contracts.clone();
+        let attackers_for_call = attackers.clone();
+        linker
+            .func_wrap(
+                "vm_hooks",
+                "call_contract",
+                move |mut caller: Caller<'_, ModuleData>,
+                      contract_ptr: u32,
+                      calldata_ptr: u32,
+                      calldata_len: u32,
+                      value_ptr: u32,
+                      _gas: u64,
+                      return_data_len_ptr: u32|
+                      -> i32 {
+                    let mem = get_memory(&mut caller);
+
+                    let mut target = [0; 20];
+                    mem.read(&mut caller, contract_ptr as usize, &mut target)
+                        .unwrap();
+
+                    let mut calldata = vec![0; calldata_len as usize];
+                    mem.read(&mut caller, calldata_ptr as usize, &mut calldata)
+                        .unwrap();
+
+                    let mut value = [0; 32];
+                    mem.read(&mut caller, value_ptr as usize, &mut value)
+                        .unwrap();
+
+                    external_calls_for_call.lock().unwrap().push(ExternalCall {
+                        kind: CallKind::Call,
+                        target,
+                        calldata: calldata.clone(),
+                        value,
+                    });
+
+                    // The call fails without reaching the target if the contract can't pay the
+                    // value, and the value is only moved if it succeeds
+                    let value = U256::from_be_bytes(value);
+                    let contract_address = *contract_address_for_call.lock().unwrap();
+                    let contract_balance = balances_for_call
+                        .lock()
+                        .unwrap()
+                        .get(&contract_address)
+                        .copied()
+                        .unwrap_or(U256::ZERO);
+
+                    // The lock is released before calling a registered contract, which uses its
+                    // own state
+                    let contract = contracts_for_call.lock().unwrap().get(&target).cloned();
+                    let attacker = attackers_for_call.lock().unwrap().get(&target).cloned();
+                    let (success, return_data) = if contract_balance < value {
+                        (false, Vec::new())
+                    } else if let Some(contract) = contract {
+                        let tx_origin = *tx_origin_for_call.lock().unwrap();
+                        let block = caller.data().block;
+                        contract.call_from(
+                            contract_address,
+                            tx_origin,
+                            block,
+                            calldata,
+                            value,
+                            false,
+                        )
+                    } else if let Some(attacker) = attacker {
+                        let tx_origin = *tx_origin_for_call.lock().unwrap();
+                        attacker.attack(tx_origin, caller.data().block)
+                    } else {
+                        external_call_response_for_call.lock().unwrap().clone()
+                    };
+
+                    if success {
+                        transfer(
+                            &mut balances_for_call.lock().unwrap(),
+                            contract_address,
+                            target,
+                            value,
+                        );
+                    }
+
+                    mem.write(
+                        &mut caller,
+                        return_data_len_ptr as usize,
+                        &(return_data.len() as u32).to_le_bytes(),
+                    )
+                    .unwrap();
+                    caller.data_mut().call_return_data = return_data;
+
+                    if success { 0 } else { 1 }
+                },
+            )
+            .unwrap();
+
+        let external_calls_for_delegate_call = external_calls.clone();
+        let external_call_response_for_delegate_call = external_call_response.clone();
+        linker
+            .func_wrap(
+                "vm_hooks",
+                "delegate_call_contract",
+                move |mut caller: Caller<'_, ModuleData>,
+                      contract_ptr: u32,
+                      calldata_ptr: u32,
+                      calldata_len: u32,
+                      _gas: u64,
+                      return_data_len_ptr: u32|
+                      -> i32 {
+                    let mem = get_memory(&mut caller);
+
+                    let mut target = [0; 20];
+                    mem.read(&mut caller, contract_ptr as usize, &mut target)
+                        .unwrap();
+
+                    let mut calldata = vec![0; calldata_len as usize];
+                    mem.read(&mut caller, calldata_ptr as usize, &mut calldata)
+                        .unwrap();
+
+                    external_calls_for_delegate_call
+                        .lock()
+                        .unwrap()
+                        .push(ExternalCall {
+                            kind: CallKind::DelegateCall,
+                            target,
+                            calldata,
+                            value: caller.data().value.to_be_bytes::<32>(),
+                        });
+
+                    let (success, return_data) = external_call_response_for_delegate_call
+                        .lock()
+                        .unwrap()
+                        .clone();
+
+                    mem.write(
+                        &mut caller,
+                        return_data_len_ptr as usize,
+                        &(return_data.len() as u32).to_le_bytes(),
+                    )
+                    .unwrap();
+                    caller.data_mut().call_return_data = return_data;
+
+                    if success { 0 } else { 1 }
+                },
+            )
+            .unwrap();
+
+        let external_calls_for_static_call = external_calls.clone();
+        let external_call_response_for_static_call = external_call_response.clone();
+        let block_hashes_for_static_call = block_hashes.clone();
+        let block_hash_seed_for_static_call = block_hash_seed.clone();
+        let contract_address_for_static_call = contract_address.clone();
+        let tx_origin_for_static_call = current_tx_origin.clone();
+        let contracts_for_static_call = contracts.clone();
+        linker
+            .func_wrap(
+                "vm_hooks",
+                "static_call_contract",
+                move |mut caller: Caller<'_, ModuleData>,
+                      contract_ptr: u32,
+                      calldata_ptr: u32,
+                      calldata_len: u32,
+                      _gas: u64,
+                      return_data_len_ptr: u32|
+                      -> i32 {
+                    let mem = get_memory(&mut caller);
+
+                    let mut target = [0; 20];
+                    mem.read(&mut caller, contract_ptr as usize, &mut target)
+                        .unwrap();
+
+                    let mut calldata = vec![0; calldata_len as usize];
+                    mem.read(&mut caller, calldata_ptr as usize, &mut calldata)
+                        .unwrap();
+
+                    // Precompiles are not recorded, their output is computed from the input
+                    let arb_sys_result = run_arb_sys(
+                        &target,
+                        &calldata,
+                        &block_hashes_for_static_call.lock().unwrap(),
+                        *block_hash_seed_for_static_call.lock().unwrap(),
+                        caller.data().block.number,
+                    );
+                    let (success, return_data) =
+                        if let Some(result) = run_precompile(&target, &calldata) {
+                            result
+                        } else if let Some(result) = arb_sys_result {
+                            result
+                        } else {
+                            external_calls_for_static_call
+                                .lock()
+                                .unwrap()
+                                .push(ExternalCall {
+                                    kind: CallKind::StaticCall,
+                                    target,
+                                    calldata: calldata.clone(),
+                                    value: [0; 32],
+                                });
+
+                            let contract = contracts_for_static_call
+                                .lock()
+                                .unwrap()
+                                .get(&target)
+                                .cloned();
+                            if let Some(contract) = contract {
+                                let contract_address =
+                                    *contract_address_for_static_call.lock().unwrap();
+                                let tx_origin = *tx_origin_for_static_call.lock().unwrap();
+                                let block = caller.data().block;
+                                contract.call_from(
+                                    contract_address,
+                                    tx_origin,
+                                    block,
+                                    calldata,
+                                    U256::ZERO,
+                                    true,
+                                )
+                            } else {
+                                external_call_response_for_static_call
+                                    .lock()
+                                    .unwrap()
+                                    .clone()
+                            }
+                        };
+
+                    mem.write(
+                        &mut caller,
+                        return_data_len_ptr as usize,
+                        &(return_data.len() as u32).to_le_bytes(),
+                    )
+                    .unwrap();
+                    caller.data_mut().call_return_data = return_data;
+
+                    if success { 0 } else { 1 }
+                },
+            )
+            .unwrap();
+
+        // Deployments share the same logic, create1 deployments have no salt
+        let contract_creations_for_create = contract_creations.clone();
+        let create_response_for_create = create_response.clone();
+        let balances_for_create = balances.clone();
+        let contract_address_for_create = contract_address.clone();
+        let create = move |caller: &mut Caller<'_, ModuleData>,
+                           code_ptr: u32,
+                           code_len: u32,
+                           endowment_ptr: u32,
+                           salt_ptr: Option<u32>,
+                           contract_ptr: u32,
+                           revert_data_len_ptr: u32| {
+            let mem = get_memory(caller);
+
+            let mut init_code = vec![0; code_len as usize];
+            mem.read(&mut *caller, code_ptr as usize, &mut init_code)
+                .unwrap();
+
+            let mut endowment = [0; 32];
+            mem.read(&mut *caller, endowment_ptr as usize, &mut endowment)
+                .unwrap();
+
+            let salt = salt_ptr.map(|salt_ptr| {
+                let mut salt = [0; 32];
+                mem.read(&mut *caller, salt_ptr as usize, &mut salt)
+                    .unwrap();
+                salt
+            });
+
+            contract_creations_for_create
+                .lock()
+                .unwrap()
+                .push(ContractCreation {
+                    init_code,
+                    endowment,
+                    salt,
+                });
+
+            // The deployment fails without running the init code if the contract can't pay the
+            // endowment, which is moved to the new contract if it succeeds
+            let endowment = U256::from_be_bytes(endowment);
+            let contract_address = *contract_address_for_create.lock().unwrap();
+            let mut balances = balances_for_create.lock().unwrap();
+            let contract_balance = balances
+                .get(&contract_address)
+                .copied()
+                .unwrap_or(U256::ZERO);
+
+            let (contract, revert_data) = if contract_balance < endowment {
+                (None, Vec::new())
+            } else {
+                create_response_for_create.lock().unwrap().clone()
+            };
+
+            if let Some(contract) = contract {
+                transfer(&mut balances, contract_address, contract, endowment);
+            }
+            drop(balances);
+
+            // The revert data is only set if the deployment failed
+            let revert_data = if contract.is_some() {
+                vec![]
+            } else {
+                revert_data
+            };
+
+            mem.write(
+                &mut *caller,
+                contract_ptr as usize,
+                &contract.unwrap_or([0; 20]),
+            )
+            .unwrap();
+            mem.write(
+                &mut *caller,
+                revert_data_len_ptr as usize,
+                &(revert_data.len() as u32).to_le_bytes(),
+            )
+            .unwrap();
+            caller.data_mut().call_return_data = revert_data;
+        };
+
+        let create1 = create.clone();
+        linker
+            .func_wrap(
+                "vm_hooks",
+                "create1",
+                move |mut caller: Caller<'_, ModuleData>,
+                      code_ptr: u32,
+                      code_len: u32,
+                      endowment_ptr: u32,
+                      contract_ptr: u32,
+                      revert_data_len_ptr: u32| {
+                    create1(
+                        &mut caller,
+                        code_ptr,
+                        code_len,
+                        endowment_ptr,
+                        None,
+                        contract_ptr,
+                        revert_data_len_ptr,
+                    )
+                },
+            )
+            .unwrap();
+
+        linker
+            .func_wrap(
+                "vm_hooks",
+                "create2",
+                move |mut caller: Caller<'_, ModuleData>,
+                      code_ptr: u32,
+                      code_len: u32,
+                      endowment_ptr: u32,
+                      salt_ptr: u32,
+                      contract_ptr: u32,
+                      revert_data_len_ptr: u32| {
+                    create(
+                        &mut caller,
+                        code_ptr,
+                        code_len,
+                        endowment_ptr,
+                        Some(salt_ptr),
+                        contract_ptr,
+                        revert_data_len_ptr,
+                    )
+                },
+            )
+            .unwrap();
+
+        linker
+            .func_wrap(
+                "vm_hooks",
+                "read_return_data",
+                move |mut caller: Caller<'_, ModuleData>,
+                      dest_ptr: u32,
+                      offset: u32,
+                      size: u32|
+                      -> u32 {
+                    let mem = get_memory(&mut caller);
+
+                    // Only the overlapping portion is copied
+                    let return_data = &caller.data().call_return_data;
+                    let start = (offset as usize).min(return_data.len());
+                    let end = (start + size as usize).min(return_data.len());
+                    let data = return_data[start..end].to_vec();
+
+                    mem.write(&mut caller, dest_ptr as usize, &data).unwrap();
+
+                    data.len() as u32
+                },
+            )
+            .unwrap();
+
+        linker
+            .func_wrap(
+                "vm_hooks",
+                "return_data_size",
+                move |caller: Caller<'_, ModuleData>| -> u32 {
+                    caller.data().call_return_data.len() as u32
+                },
+            )
+            .unwrap();
+
+        let balances_for_query = balances.clone();
+        linker
+            .func_wrap(
+                "vm_hooks",
+                "account_balance",
+                move |mut caller: Caller<'_, ModuleData>, address_ptr: u32, dest_ptr: u32| {
+                    let mem = get_memory(&mut caller);
+
+                    let mut address = [0; 20];
+                    mem.read(&mut caller, address_ptr as usize, &mut address)
+                        .unwrap();
+
+                    let balance = balances_for_query
+                        .lock()
+                        .unwrap()
+                        .get(&address)
+                        .copied()
+                        .unwrap_or(U256::ZERO);
+
+                    mem.write(&mut caller, dest_ptr as usize, &balance.to_be_bytes::<32>())
+                        .unwrap();
+                },
+            )
+            .unwrap();
+
+        let codes_for_code = codes.clone();
+        linker
+            .func_wrap(
+                "vm_hooks",
+                "account_code",
+                move |mut caller: Caller<'_, ModuleData>,
+                      address_ptr: u32,
+                      offset: u32,
+                      size: u32,
+                      dest_ptr: u32|
+                      -> u32 {
+                    let mem = get_memory(&mut caller);
+
+                    let mut address = [0; 20];
+                    mem.read(&mut caller, address_ptr as usize, &mut address)
+                        .unwrap();
+
+                    let code = codes_for_code
+                        .lock()
+                        .unwrap()
+                        .get(&address)
+                        .cloned()
+                        .unwrap_or_default();
+
+                    // The write stops after the last byte of the code
+                    let start = (offset as usize).min(code.len());
+                    let end = (start + size as usize).min(code.len());
+
+                    mem.write(&mut caller, dest_ptr as usize, &code[start..end])
+                        .unwrap();
+
+                    (end - start) as u32
+                },
+            )
+            .unwrap();
+
+        let codes_for_code_size = codes.clone();
+        linker
+            .func_wrap(
+                "vm_hooks",
+                "account_code_size",
+                move |mut caller: Caller<'_, ModuleData>, address_ptr: u32| -> u32 {
+                    let mem = get_memory(&mut caller);
+
+                    let mut address = [0; 20];
+                    mem.read(&mut caller, address_ptr as usize, &mut address)
+                        .unwrap();
+
+                    codes_for_code_size
+                        .lock()
+                        .unwrap()
+                        .get(&address)
+                        .map_or(0, |code| code.len() as u32)
+                },
+            )
+            .unwrap();
+
+        let codes_for_codehash = codes.clone();
+        linker
+            .func_wrap(
+                "vm_hooks",
+                "account_codehash",
+                move |mut caller: Caller<'_, ModuleData>, address_ptr: u32, dest_ptr: u32| {
+                    let mem = get_memory(&mut caller);
+
+                    let mut address = [0; 20];
+                    mem.read(&mut caller, address_ptr as usize, &mut address)
+                        .unwrap();
+
+                    // Accounts without code have the hash of the empty data
+                    let hash = codes_for_codehash
+                        .lock()
+                        .unwrap()
+                        .get(&address)
+                        .map_or(keccak256(b""), keccak256);
+
+                    mem.write(&mut caller, dest_ptr as usize, hash.as_slice())
+                        .unwrap();
+                },
+            )
+            .unwrap();
+
+        let contract_address_for_query = contract_address.clone();
+        link_fn_write_constant!(
+            linker,
+            "contract_address",
+            *contract_address_for_query.lock().unwrap()
+        );
+        linker
+            .func_wrap(
+                "vm_hooks",
+                "msg_value",
+                move |mut caller: Caller<'_, ModuleData>, ptr: u32| {
+                    let mem = get_memory(&mut caller);
+                    let value = caller.data().value.to_le_bytes::<32>();
+
+                    mem.write(&mut caller, ptr as usize, &value).unwrap();
+                },
+            )
+            .unwrap();
+
+        linker
+            .func_wrap(
+                "vm_hooks",
+                "block_basefee",
+                move |mut caller: Caller<'_, ModuleData>, ptr: u32| {
+                    let mem = get_memory(&mut caller);
+                    let basefee = caller.data().block.basefee.to_le_bytes::<32>();
+
+                    mem.write(&mut caller, ptr as usize, &basefee).unwrap();
+                },
+            )
+            .unwrap();
+
+        linker
+            .func_wrap(
+                "vm_hooks",
+                "block_coinbase",
+                move |mut caller: Caller<'_, ModuleData>, ptr: u32| {
+                    let mem = get_memory(&mut caller);
+                    let coinbase = caller.data().block.coinbase;
+
+                    mem.write(&mut caller, ptr as usize, coinbase.as_slice())
+                        .unwrap();
+                },
+            )
+            .unwrap();
+
+        link_fn_write_constant!(linker, "tx_gas_price", GAS_PRICE.to_le_bytes::<32>());
+
+        linker
+            .func_wrap(
+                "vm_hooks",
+                "evm_gas_left",
+                |mut caller: Caller<'_, ModuleData>| -> i64 {
+                    let data = caller.data_mut();
+                    data.gas_left = data.gas_left.saturating_sub(GAS_LEFT_QUERY_COST);
+                    data.gas_left as i64
+                },
+            )
+            .unwrap();
+
+        linker
+            .func_wrap(
+                "vm_hooks",
+                "evm_ink_left",
+                |mut caller: Caller<'_, ModuleData>| -> i64 {
+                    let data = caller.data_mut();
+                    data.gas_left = data.gas_left.saturating_sub(GAS_LEFT_QUERY_COST);
+                    (data.gas_left * INK_PER_GAS) as i64
+                },
+            )
+            .unwrap();
+
+        link_fn_math_hook!(linker, "math_div", |value: U256, divisor: U256| value
+            .checked_div(divisor)
+            .unwrap_or_default());
+        link_fn_math_hook!(linker, "math_mod", |value: U256, modulus: U256| value
+            .checked_rem(modulus)
+            .unwrap_or_default());
+        link_fn_math_hook!(linker, "math_pow", |value: U256, exponent: U256| value
+            .wrapping_pow(exponent));
+        link_fn_math_hook!(
+            linker,
+            "math_add_mod",
+            |value: U256, addend: U256, modulus: U256| value.add_mod(addend, modulus)
+        );
+        link_fn_math_hook!(
+            linker,
+            "math_mul_mod",
+            |value: U256, multiplier: U256, modulus: U256| value.mul_mod(multiplier, modulus)
+        );
+
+        link_fn_ret_constant!(linker, "tx_ink_price", INK_PER_GAS, i32);
+
+        link_fn_ret_block_field!(linker, "chainid", chain_id);
+        link_fn_ret_block_field!(linker, "block_number", number);
+        link_fn_ret_block_field!(linker, "block_gas_limit", gas_limit);
+        link_fn_ret_block_field!(linker, "block_timestamp", timestamp);
+
+        // Hooks imported by the modules translated with the `inject-host-debug-fns` feature
+        linker
+            .func_wrap("", "print_i64", |param: i64| {
+                println!("--- i64 ---> {param}");
+            })
+            .unwrap();
+
+        linker
+            .func_wrap("", "print_i32", |param: i32| {
+                println!("--- i32 ---> {param}");
+            })
+            .unwrap();
+
+        linker
+            .func_wrap("", "print_separator", || {
+                println!("-----------------------------------------------");
+            })
+            .unwrap();
+
+        linker
+            .func_wrap(
+                "",
+                "print_u128",
+                |caller: Caller<'_, ModuleData>, ptr: i32| {
+                    println!("--- u128 ---\nPointer {ptr}");
+
+                    let memory = get_memory(&caller);
+
+                    let mut result = [0; 16];
+                    memory.read(&caller, ptr as usize, &mut result).unwrap();
+                    println!("Data {result:?}");
+                    println!("Decimal data {}", u128::from_le_bytes(result));
+                    println!("--- end u128 ---\n");
+                },
+            )
+            .unwrap();
+
+        linker
+            .func_wrap(
+                "",
+                "print_memory_from",
+                |caller: Caller<'_, ModuleData>, ptr: i32| {
+                    println!("--- 512 from position {ptr}----");
+
+                    let memory = get_memory(&caller);
+
+                    let mut result = [0; 512];
+                    memory.read(&caller, ptr as usize, &mut result).unwrap();
+                    println!("Data {result:?}");
+                    println!("--- --- ---\n");
+                },
+            )
+            .unwrap();
+
+        linker
+            .func_wrap(
+                "",
+                "print_address",
+                |caller: Caller<'_, ModuleData>, ptr: i32| {
+                    println!("--- address ---\nPointer {ptr}");
+
+                    let memory = get_memory(&caller);
+
+                    let mut result = [0; 32];
+                    memory.read(&caller, ptr as usize, &mut result).unwrap();
+                    println!(
+                        "Data 0x{}",
+                        result[12..]
+                            .iter()
+                            .map(|b| format!("{:02x}", b))
+                            .collect::<String>()
+                    );
+                    println!("--- end address ---\n");
+                },
+            )
+            .unwrap();
+
+        Self {
+            engine,
+            linker,
+            module,
+            log_events: Arc::new(Mutex::new(log_receiver)),
+            logs,
+            current_tx_origin,
+            current_msg_sender,
+            msg_reentrant,
+            call_depth,
+            storage,
+            storage_writes,
+            view_call,
+            keccak_calls,
+            memory_grows,
+            memory_stats: Arc::new(Mutex::new(None)),
+            storage_flushes,
+            debug_logs,
+            external_calls,
+            external_call_response,
+            contract_creations,
+            create_response,
+            balances,
+            codes,
+            gas_left: Arc::new(Mutex::new(GAS_LEFT)),
+            execution_budget: Arc::new(Mutex::new(EXECUTION_BUDGET)),
+            block: Arc::new(Mutex::new(BlockEnv::default())),
+            snapshots: Mutex::new(Vec::new()),
+            recording: Mutex::new(None),
+            storage_resolver,
+            resolved_storage,
+            block_hashes,
+            block_hash_seed,
+            contract_address,
+            contracts,
+            attackers,
+            #[cfg(feature = "storage-journal")]
+            storage_journal,
+            trace: Arc::new(Mutex::new(None)),
+            coverage_hits,
+            profiler,
+            hook_overrides: HashMap::new(),
+        }
+    }
+
+    /// Creates a temporary runtime sandbox instance and calls the entrypoint with the given data.
+    /// The call is sent with `MSG_VALUE`, which is not paid by the sender.
+    ///
+    /// Returns the result of the entrypoint call and the return data.
+    pub fn call_entrypoint(&self, data: Vec<u8>) -> Result<(i32, Vec<u8>)> {
+        self.record(data, MSG_VALUE, false, |data| self.execute(data, MSG_VALUE))
+    }
+
+    /// Deploys the contract, running the constructor the translation injects: it calls the
+    /// module's `init` function and its `#[ext(constructor)]` function, and marks the contract as
+    /// initialized in the storage so a second deployment does nothing. The constructor is called
+    /// without arguments, use `deploy_with` for the modules whose `#[ext(constructor)]` function
+    /// takes them. Modules without either function have no constructor, and deploy without
+    /// running anything.
+    ///
+    /// Returns an error if the constructor aborts or reverts.
+    pub fn deploy(&self) -> Result<()> {
+        self.deploy_with(keccak256("constructor()")[..4].to_vec())
+    }
+
+    /// Deploys the contract as `deploy` does, calling the constructor with the given call data:
+    /// its selector followed by its ABI encoded arguments, as `move-stylus deploy` sends them.
+    pub fn deploy_with(&self, call_data: Vec<u8>) -> Result<()> {
+        match self.call_entrypoint(call_data)? {
+            (0 | ERROR_NO_FUNCTION_MATCH, _) => Ok(()),
+            (status, return_data) => Err(anyhow::anyhow!(
+                "the constructor failed with status {status}: {}",
+                RevertReason::decode(&return_data)
+            )),
+        }
+    }
+
+    /// Calls the entrypoint the same as `call_entrypoint`, verifying the called function is free
+    /// of side effects, as the functions documented as views must be: the call fails with a
+    /// `StorageWriteInView` error at the first storage write, including the writes of the calls
+    /// that re-enter the contract.
+    pub fn call_view(&self, data: Vec<u8>) -> Result<(i32, Vec<u8>)> {
+        *self.view_call.lock().unwrap() = true;
+        let result = self.call_entrypoint(data);
+        *self.view_call.lock().unwrap() = false;
+
+        result
+    }
+
+    /// Calls the entrypoint the same as `call_entrypoint`, decoding the reason of the revert if
+    /// the call fails.
+    pub fn call(&self, data: Vec<u8>) -> CallResult {
+        match self.call_entrypoint(data) {
+            Ok((0, return_data)) => CallResult::Success(return_data),
+            Ok((_, revert_data)) => CallResult::Revert(RevertReason::decode(&revert_data)),
+            Err(e) => CallResult::Trap(e.to_string()),
+        }
+    }
+
+    /// Calls the entrypoint with the given data, sending `value` wei from the sender to the
+    /// contract. The call fails without running if the sender can't pay the value, and the
+    /// balances are restored if it reverts.
+    ///
+    /// Returns the result of the entrypoint call and the return data.
+    pub fn call_entrypoint_with_value(&self, data: Vec<u8>, value: U256) -> Result<(i32, Vec<u8>)> {
+        self.record(data, value, true, |data| self.execute_paid(data, value))
+    }
+
+    /// Runs the entrypoint with the given data, sending `value` wei from the sender to the
+    /// contract, as `call_entrypoint_with_value` does.
+    fn execute_paid(&self, data: Vec<u8>, value: U256) -> Result<(i32, Vec<u8>)> {
+        let sender = *self.current_msg_sender.lock().unwrap();
+        let contract_address = *self.contract_address.lock().unwrap();
+        let previous_balances = self.balances.lock().unwrap().clone();
+
+        anyhow::ensure!(
+            transfer(
+                &mut self.balances.lock().unwrap(),
+                sender,
+                contract_address,
+                value
+            ),
+            "the sender can't pay the {value} wei sent with the call"
+        );
+
+        let result = self.execute(data, value);
+        if !matches!(result, Ok((0, _))) {
+            *self.balances.lock().unwrap() = previous_balances;
+        }
+
+        result
+    }
+
+    /// Runs the entrypoint with the given data, as if `value` wei were sent with the call.
+    fn execute(&self, data: Vec<u8>, value: U256) -> Result<(i32, Vec<u8>)> {
+        let depth = {
+            let mut call_depth = self.call_depth.lock().unwrap();
+            *call_depth += 1;
+            *call_depth
+        };
+
+        // Re-entrant calls run within the outer call, whose records they must not clear
+        if depth == 1 {
+            // Every call starts with all the slots cold
+            #[cfg(feature = "storage-journal")]
+            {
+                *self.storage_journal.lock().unwrap() = StorageJournal::default();
+            }
+
+            *self.keccak_calls.lock().unwrap() = 0;
+            self.memory_grows.lock().unwrap().clear();
+            self.storage_flushes.lock().unwrap().clear();
+            self.debug_logs.lock().unwrap().clear();
+            self.logs.lock().unwrap().clear();
+            self.external_calls.lock().unwrap().clear();
+            self.contract_creations.lock().unwrap().clear();
+        }
+
+        let result = self.run_entrypoint(data, value);
+        *self.call_depth.lock().unwrap() -= 1;
+
+        result
+    }
+
+    fn run_entrypoint(&self, data: Vec<u8>, value: U256) -> Result<(i32, Vec<u8>)> {
+        let data_len = data.len() as i32;
+        let mut store = Store::new(
+            &self.engine,
+            ModuleData {
+                data,
+                return_data: vec![],
+                call_return_data: vec![],
+                gas_left: *self.gas_left.lock().unwrap(),
+                block: *self.block.lock().unwrap(),
+                value,
+                memory: None,
+            },
+        );
+        let execution_budget = *self.execution_budget.lock().unwrap();
+        store.set_fuel(execution_budget)?;
+
+        let tracing = self.trace.lock().unwrap().is_some();
+        let host_calls = Arc::new(Mutex::new(Vec::new()));
+        let instance = if tracing || !self.hook_overrides.is_empty() {
+            self.instantiate_wrapped(&mut store, tracing.then(|| host_calls.clone()))?
+        } else {
+            self.linker.instantiate(&mut store, &self.module)?
+        };
+        store.data_mut().memory = instance.get_memory(&mut store, "memory");
+        let initial_pages = store.data().memory.map_or(0, |memory| memory.size(&store));
+
+        let entrypoint = instance.get_typed_func::<i32, i32>(&mut store, "user_entrypoint")?;
+
+        let profiler_depth = self
+            .profiler
+            .lock()
+            .unwrap()
+            .as_mut()
+            .map(Profiler::begin_call);
+        let result = entrypoint.call(&mut store, data_len);
+        if let (Some(profiler), Some(depth)) =
+            (self.profiler.lock().unwrap().as_mut(), profiler_depth)
+        {
+            profiler.end_call(depth);
+        }
+
+        *self.memory_stats.lock().unwrap() =
+            MemoryStats::read(&instance, &mut store, initial_pages);
+
+        if tracing {
+            *self.trace.lock().unwrap() = Some(CallTrace {
+                selector: store.data().data.first_chunk::<4>().copied(),
+                host_calls: std::mem::take(&mut *host_calls.lock().unwrap()),
+            });
+        }
+
+        let result = match result {
+            Ok(result) => result,
+            // The program ended through the exit_early hook
+            Err(e) => match e.downcast_ref::<ExitEarly>() {
+                Some(ExitEarly(status)) => *status,
+                None if e.is::<MoveAbort>() => return Err(e.downcast::<MoveAbort>()?.into()),
+                None if e.is::<StorageWriteInView>() => {
+                    return Err(e.downcast::<StorageWriteInView>()?.into());
+                }
+                None if e.downcast_ref::<Trap>() == Some(&Trap::OutOfFuel) => {
+                    return Err(ExecutionTimeout {
+                        budget: execution_budget,
+                    }
+                    .into());
+                }
+                None => return Err(anyhow::anyhow!("error calling entrypoint: {e:?}")),
+            },
+        };
+
+        Ok((result, store.data().return_data.clone()))
+    }
+
+    /// Instantiates the module with the overridden hooks replaced by their overrides and, if
+    /// `host_calls` is set, every import wrapped in a function that records its invocations in it
+    /// before returning the hook's results.
+    fn instantiate_wrapped(
+        &self,
+        store: &mut Store<ModuleData>,
+        host_calls: Option<Arc<Mutex<Vec<HostCall>>>>,
+    ) -> Result<Instance> {
+        let mut imports = Vec::new();
+        for import in self.module.imports() {
+            let Some(Extern::Func(mut hook)) =
+                self.linker.get(&mut *store, import.module(), import.name())
+            else {
+                anyhow::bail!("unknown import {}::{}", import.module(), import.name());
+            };
+
+            let hook_override = match import.module() {
+                "vm_hooks" => self.hook_overrides.get(import.name()).cloned(),
+                _ => None,
+            };
+            if let Some(hook_override) = hook_override {
+                let original = hook;
+                hook = Func::new(
+                    &mut *store,
+                    original.ty(&*store),
+                    move |caller: Caller<'_, ModuleData>, params: &[Val], results: &mut [Val]| {
+                        hook_override(&mut HookCall {
+                            caller,
+                            params,
+                            results,
+                            original,
+                        })
+                    },
+                );
+            }
+
+            // The profiler's hooks are the sandbox's instrumentation, not calls to the host
+            let Some(host_calls) = host_calls
+                .as_ref()
+                .filter(|_| import.module() != "profiler")
+            else {
+                imports.push(hook.into());
+                continue;
+            };
+
+            let name = import.name().to_owned();
+            let host_calls = host_calls.clone();
+            let traced_hook = Func::new(
+                &mut *store,
+                hook.ty(&*store),
+                move |mut caller: Caller<'_, ModuleData>, params: &[Val], results: &mut [Val]| {
+                    let result = hook.call(&mut caller, params, results);
+                    let returned: &[Val] = if result.is_ok() { results } else { &[] };
+                    host_calls
+                        .lock()
+                        .unwrap()
+                        .push(HostCall::new(&name, params, returned));
+
+                    result
+                },
+            );
+
+            imports.push(traced_hook.into());
+        }
+
+        Instance::new(store, &self.module, &imports)
+    }
+
+    /// Calls the contract on behalf of `sender`, as another contract of the sandbox does, in the
+    /// caller's block. The value, paid by the caller, is added to the contract's balance. Returns
+    /// whether the call succeeds and its return data. A static call fails at its first storage
+    /// write, as the views checked with `call_view` do. The changes to the storage and the
+    /// balances are reverted if it fails. The caller's block, sender and origin are restored once
+    /// it returns.
+    fn call_from(
+        &self,
+        sender: [u8; 20],
+        tx_origin: [u8; 20],
+        block: BlockEnv,
+        calldata: Vec<u8>,
+        value: U256,
+        is_static: bool,
+    ) -> (bool, Vec<u8>) {
+        let previous_block = std::mem::replace(&mut *self.block.lock().unwrap(), block);
+        let previous_sender =
+            std::mem::replace(&mut *self.current_msg_sender.lock().unwrap(), sender);
+        let previous_tx_origin =
+            std::mem::replace(&mut *self.current_tx_origin.lock().unwrap(), tx_origin);
+        // The calls made during a view or a static call are static too
+        let previous_view_call = {
+            let mut view_call = self.view_call.lock().unwrap();
+            let previous_view_call = *view_call;
+            *view_call = previous_view_call || is_static;
+            previous_view_call
+        };
+        let previous_storage = self.storage.lock().unwrap().clone();
+        let previous_balances = self.balances.lock().unwrap().clone();
+
+        let contract_address = *self.contract_address.lock().unwrap();
+        *self
+            .balances
+            .lock()
+            .unwrap()
+            .entry(contract_address)
+            .or_insert(U256::ZERO) += value;
+
+        // Traps, such as aborts and the writes of the static calls, revert without data
+        let (success, return_data) = match self.execute(calldata, value) {
+            Ok((status, return_data)) => (status == 0, return_data),
+            Err(_) => (false, Vec::new()),
+        };
+
+        *self.block.lock().unwrap() = previous_block;
+        *self.current_msg_sender.lock().unwrap() = previous_sender;
+        *self.current_tx_origin.lock().unwrap() = previous_tx_origin;
+        *self.view_call.lock().unwrap() = previous_view_call;
+
+        if !success {
+            *self.storage.lock().unwrap() = previous_storage;
+            *self.balances.lock().unwrap() = previous_balances;
+        }
+
+        (success, return_data)
+    }
+
+    /// Registers another sandbox's contract at `address`. The calls and static calls to it run
+    /// its module against its own state, with this contract as the sender, instead of returning
+    /// the external call response. Their return data and revert status are propagated to the
+    /// caller.
+    ///
+    /// Delegate calls are not routed, since the module would have to run against this contract's
+    /// storage.
+    pub fn register_contract(&self, address: [u8; 20], contract: RuntimeSandbox) {
+        *contract.contract_address.lock().unwrap() = address;
+        self.contracts
+            .lock()
+            .unwrap()
+            .insert(address, Arc::new(contract));
+    }
+
+    /// Returns the contract registered at `address`, to set up or inspect its state.
+    pub fn get_contract(&self, address: [u8; 20]) -> Option<Arc<RuntimeSandbox>> {
+        self.contracts.lock().unwrap().get(&address).cloned()
+    }
+
+    /// Sets the origin of the following transactions, the account that signs them.
+    pub fn set_tx_origin(&self, new_address: [u8; 20]) {
+        *self.current_tx_origin.lock().unwrap() = new_address;
+    }
+
+    /// Returns the origin of the following transactions.
+    pub fn get_tx_origin(&self) -> [u8; 20] {
+        *self.current_tx_origin.lock().unwrap()
+    }
+
+    /// Sets the sender of the following calls.
+    pub fn set_msg_sender(&self, new_address: [u8; 20]) {
+        *self.current_msg_sender.lock().unwrap() = new_address;
+    }
+
+    /// Sets the value returned by the `msg_reentrant` hook in the following calls. The hook returns
+    /// `true` regardless while the contract is re-entered, as it is by a `ReentrancyAttacker`.
+    pub fn set_msg_reentrant(&self, reentrant: bool) {
+        *self.msg_reentrant.lock().unwrap() = reentrant;
+    }
+
+    /// Returns the value of a slot. Panics if the slot was never written.
+    pub fn get_storage_at_slot(&self, slot: [u8; 32]) -> [u8; 32] {
+        let storage = self.storage.lock().unwrap();
+        *storage.get(&slot).unwrap()
+    }
+
+    /// Returns the value of every slot written. The values read from the storage resolver are not
+    /// included, unless the contract wrote them back.
+    pub fn get_storage(&self) -> HashMap<[u8; 32], [u8; 32]> {
+        self.storage.lock().unwrap().clone()
+    }
+
+    /// Sets where the values of the slots the storage has no value for are read from. Without a
+    /// resolver they are zero.
+    pub fn set_storage_resolver(&self, resolver: impl StorageResolver + 'static) {
+        *self.storage_resolver.lock().unwrap() = Some(Box::new(resolver));
+        self.resolved_storage.lock().unwrap().clear();
+    }
+
+    /// Overwrites the value of a slot, as if it was written by a previous version of the
+    /// contract.
+    pub fn set_storage_at_slot(&self, slot: [u8; 32], value: [u8; 32]) {
+        self.storage.lock().unwrap().insert(slot, value);
+    }
+
+    /// Sets the value of an entry of the mapping at `mapping_slot`. Nested mappings are entered
+    /// with one key per level, such as `[owner, spender]` for
+    /// `Mapping<address, Mapping<address, u256>>`.
+    ///
+    /// The keys and the value are padded to 32 bytes, as they are in the ABI.
+    pub fn set_mapping_entry(&self, mapping_slot: [u8; 32], keys: &[[u8; 32]], value: [u8; 32]) {
+        let slot = keys.iter().fold(mapping_slot, |slot, key| {
+            storage_layout::derive_mapping_slot(slot, *key)
+        });
+        self.set_storage_at_slot(slot, value);
+    }
+
+    /// Sets the field of an object, resolving where it is saved from the module's storage
+    /// layout, without touching the fields packed in the same slot. `value` holds the field's
+    /// bytes, in big-endian. The object must already be saved, or all its fields, including its
+    /// `id`, seeded.
+    pub fn set_object_field(
+        &self,
+        layout: &StorageLayout,
+        struct_name: &str,
+        owner: [u8; 20],
+        object_id: [u8; 32],
+        field: &str,
+        value: &[u8],
+    ) -> Result<()> {
+        let location = layout.object_field(struct_name, owner, object_id, field)?;
+        anyhow::ensure!(
+            value.len() == location.size as usize,
+            "{struct_name}.{field} takes {} bytes, got {}",
+            location.size,
+            value.len()
+        );
+
+        let mut storage = self.storage.lock().unwrap();
+        let slot_value = storage.entry(location.slot).or_insert([0; 32]);
+        let end = 32 - location.offset as usize;
+        slot_value[end - value.len()..end].copy_from_slice(value);
+
+        Ok(())
+    }
+
+    /// Returns how many times the host was asked to write the given slot since the sandbox was
+    /// created.
+    pub fn get_storage_writes_at_slot(&self, slot: [u8; 32]) -> usize {
+        let storage_writes = self.storage_writes.lock().unwrap();
+        *storage_writes.get(&slot).unwrap_or(&0)
+    }
+
+    /// Returns how many times the host was asked to compute a keccak256 hash during the last call.
+    pub fn get_keccak_calls(&self) -> usize {
+        *self.keccak_calls.lock().unwrap()
+    }
+
+    /// Returns the `clear` flag of every call to `storage_flush_cache` during the last call.
+    pub fn get_storage_flushes(&self) -> Vec<bool> {
+        self.storage_flushes.lock().unwrap().clone()
+    }
+
+    /// Sets the hash of the block `number` returned by ArbSys. The blocks without a hash are
+    /// treated as outside of the 256 most recent ones.
+    pub fn set_block_hash(&self, number: u64, hash: [u8; 32]) {
+        self.block_hashes.lock().unwrap().insert(number, hash);
+    }
+
+    /// Gives the 256 blocks before the current one, as ArbSys does, a hash derived from `seed` and
+    /// their number, so the tests of contracts that draw entropy from recent block hashes are
+    /// reproducible without setting every hash. The hashes set with `set_block_hash` take
+    /// precedence.
+    pub fn set_block_hash_seed(&self, seed: [u8; 32]) {
+        *self.block_hash_seed.lock().unwrap() = Some(seed);
+    }
+
+    /// Returns the texts printed through the console's `log_txt` hook during the last call.
+    pub fn get_debug_logs(&self) -> Vec<String> {
+        self.debug_logs.lock().unwrap().clone()
+    }
+
+    /// Returns the pages paid for in every call to `pay_for_memory_grow` during the last call.
+    pub fn get_memory_grows(&self) -> Vec<u32> {
+        self.memory_grows.lock().unwrap().clone()
+    }
+
+    /// Returns the memory used by the last call: the pages grown, the end of the memory
+    /// allocated and the number of allocations. Returns `None` if the module has no allocator the
+    /// sandbox can instrument.
+    pub fn get_memory_stats(&self) -> Option<MemoryStats> {
+        *self.memory_stats.lock().unwrap()
+    }
+
+    /// Starts recording the host functions invoked and the selector dispatched in every call.
+    pub fn enable_tracing(&self) {
+        let mut trace = self.trace.lock().unwrap();
+        if trace.is_none() {
+            *trace = Some(CallTrace::default());
+        }
+    }
+
+    /// Stops recording the calls, discarding the last trace.
+    pub fn disable_tracing(&self) {
+        *self.trace.lock().unwrap() = None;
+    }
+
+    /// Returns the trace of the last call, or `None` if tracing is disabled.
+    pub fn get_trace(&self) -> Option<CallTrace> {
+        self.trace.lock().unwrap().clone()
+    }
+
+    /// Returns the number of times each block reported by the coverage instrumentation ran, over
+    /// all the calls.
+    pub fn get_coverage_hits(&self) -> CoverageHits {
+        self.coverage_hits.lock().unwrap().clone()
+    }
+
+    /// Returns the instructions run by all the calls, by the stack of functions they ran in, or
+    /// `None` if the sandbox was not built with `RuntimeSandboxBuilder::profile_instructions`.
+    pub fn get_instruction_profile(&self) -> Option<InstructionProfile> {
+        self.profiler
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(Profiler::profile)
+    }
+
+    /// Builds the lcov report of the blocks that ran in all the calls, given the module's coverage
+    /// map, the `<module>.coverage.json` artifact.
+    pub fn lcov_report(&self, coverage_map: &str) -> Result<String> {
+        coverage::lcov_report(coverage_map, &self.coverage_hits.lock().unwrap())
+    }
+
+    /// Returns the logs emitted during the last call, in order.
+    pub fn get_logs(&self) -> Vec<LogEvent> {
+        self.logs.lock().unwrap().clone()
+    }
+
+    /// Returns the `E` events emitted during the last call, in order. The logs of other events are
+    /// skipped.
+    pub fn get_events<E: SolEvent>(&self) -> Vec<E> {
+        self.logs
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(LogEvent::decode::<E>)
+            .collect()
+    }
+
+    /// Panics if the last call did not emit the event.
+    pub fn assert_emitted<E: SolEvent + PartialEq + std::fmt::Debug>(&self, event: &E) {
+        let events = self.get_events::<E>();
+        assert!(
+            events.contains(event),
+            "event {event:?} was not emitted, the {} events emitted were {events:?}",
+            E::SIGNATURE
+        );
+    }
+
+    /// Panics if the last call emitted any `E` event.
+    pub fn assert_not_emitted<E: SolEvent + std::fmt::Debug>(&self) {
+        let events = self.get_events::<E>();
+        assert!(
+            events.is_empty(),
+            "no {} event was expected, but {events:?} were emitted",
+            E::SIGNATURE
+        );
+    }
+
+    /// Sets the result of the calls to other contracts in the following calls: whether they
+    /// succeed and the data they return.
+    pub fn set_external_call_response(&self, success: bool, return_data: Vec<u8>) {
+        *self.external_call_response.lock().unwrap() = (success, return_data);
+    }
+
+    /// Returns the calls to other contracts the host received during the last call.
+    pub fn get_external_calls(&self) -> Vec<ExternalCall> {
+        self.external_calls.lock().unwrap().clone()
+    }
+
+    /// Sets the result of the contract deployments in the following calls: the address of the
+    /// deployed contract, or `None` and the revert data if the deployment fails.
+    pub fn set_create_response(&self, contract: Option<[u8; 20]>, revert_data: Vec<u8>) {
+        *self.create_response.lock().unwrap() = (contract, revert_data);
+    }
+
+    /// Returns the contract deployments the host received during the last call.
+    pub fn get_contract_creations(&self) -> Vec<ContractCreation> {
+        self.contract_creations.lock().unwrap().clone()
+    }
+
+    /// Sets the ETH balance in wei of an account.
+    pub fn set_balance(&self, account: [u8; 20], balance: U256) {
+        self.balances.lock().unwrap().insert(account, balance);
+    }
+
+    /// Returns the ETH balance in wei of an account. The value sent by successful calls to other
+    /// contracts is moved from the contract's balance to the target's one.
+    pub fn get_balance(&self, account: [u8; 20]) -> U256 {
+        self.balances
+            .lock()
+            .unwrap()
+            .get(&account)
+            .copied()
+            .unwrap_or(U256::ZERO)
+    }
+
+    /// Sets the gas available at the beginning of the following calls. Every query of the gas or
+    /// the ink left consumes `GAS_LEFT_QUERY_COST` of it, so loops checking it eventually run out.
+    pub fn set_gas_left(&self, gas: u64) {
+        *self.gas_left.lock().unwrap() = gas;
+    }
+
+    /// Sets the units of fuel, about one per WebAssembly instruction, the following calls can
+    /// consume. The calls that run out of it fail with `ExecutionTimeout`. Defaults to
+    /// `EXECUTION_BUDGET`.
+    pub fn set_execution_budget(&self, fuel: u64) {
+        *self.execution_budget.lock().unwrap() = fuel;
+    }
+
+    /// Sets the number of the block the following calls are executed in.
+    pub fn set_block_number(&self, number: u64) {
+        self.block.lock().unwrap().number = number;
+    }
+
+    /// Sets the timestamp, in seconds, of the block the following calls are executed in.
+    pub fn set_block_timestamp(&self, timestamp: u64) {
+        self.block.lock().unwrap().timestamp = timestamp;
+    }
+
+    /// Sets the basefee of the block the following calls are executed in.
+    pub fn set_block_basefee(&self, basefee: U256) {
+        self.block.lock().unwrap().basefee = basefee;
+    }
+
+    /// Sets the gas limit of the block the following calls are executed in.
+    pub fn set_block_gas_limit(&self, gas_limit: u64) {
+        self.block.lock().unwrap().gas_limit = gas_limit;
+    }
+
+    /// Sets the address of the coinbase of the block the following calls are executed in.
+    pub fn set_block_coinbase(&self, coinbase: [u8; 20]) {
+        self.block.lock().unwrap().coinbase = Address::new(coinbase);
+    }
+
+    /// Sets the id of the chain the following calls are executed in.
+    pub fn set_chain_id(&self, chain_id: u64) {
+        self.block.lock().unwrap().chain_id = chain_id;
+    }
+
+    /// Moves the timestamp forward `seconds`, as if time passed between two calls.
+    pub fn warp(&self, seconds: u64) {
+        self.block.lock().unwrap().timestamp += seconds;
+    }
+
+    /// Moves the block number forward `blocks`, as if they were mined between two calls.
+    pub fn roll(&self, blocks: u64) {
+        self.block.lock().unwrap().number += blocks;
+    }
+
+    /// Saves the storage, the balances and the block, and returns the id to restore them with
+    /// `revert_to`.
+    pub fn snapshot(&self) -> usize {
+        let mut snapshots = self.snapshots.lock().unwrap();
+        snapshots.push(Snapshot {
+            storage: self.storage.lock().unwrap().clone(),
+            balances: self.balances.lock().unwrap().clone(),
+            block: *self.block.lock().unwrap(),
+        });
+
+        snapshots.len() - 1
+    }
+
+    /// Restores the state saved by the snapshot `id`, and discards the snapshots taken after it.
+    /// The snapshot is kept, so the state can be restored again. Returns `false` if there is no
+    /// such snapshot.
+    pub fn revert_to(&self, id: usize) -> bool {
+        self.restore_snapshot(id, id + 1)
+    }
+
+    /// Restores the state saved by the snapshot `id`, like `revert_to`, but discards the snapshot
+    /// too, so the ones taken and reverted over and over don't pile up. Returns `false` if there is
+    /// no such snapshot.
+    pub fn revert_to_and_discard(&self, id: usize) -> bool {
+        self.restore_snapshot(id, id)
+    }
+
+    /// Restores the state saved by the snapshot `id` and keeps the first `keep` snapshots.
+    fn restore_snapshot(&self, id: usize, keep: usize) -> bool {
+        let mut snapshots = self.snapshots.lock().unwrap();
+        let Some(snapshot) = snapshots.get(id) else {
+            return false;
+        };
+
+        *self.storage.lock().unwrap() = snapshot.storage.clone();
+        *self.balances.lock().unwrap() = snapshot.balances.clone();
+        *self.block.lock().unwrap() = snapshot.block;
+
+        snapshots.truncate(keep);
+        true
+    }
+
+    /// Writes the storage, the balances and the block to a JSON file, to be loaded with
+    /// `load_state`. The slots, values and addresses are written as hexadecimal strings.
+    pub fn dump_state(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        fs::write(path, serde_json::to_string_pretty(&self.state())?)
+            .with_context(|| format!("failed to write the state to {}", path.display()))
+    }
+
+    /// Replaces the storage, the balances and the block with the ones of a JSON file written by
+    /// `dump_state`.
+    pub fn load_state(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let state = fs::read_to_string(path)
+            .with_context(|| format!("failed to read the state from {}", path.display()))?;
+        let state: SandboxState = serde_json::from_str(&state)
+            .with_context(|| format!("invalid state in {}", path.display()))?;
+
+        self.set_state(state);
+        Ok(())
+    }
+
+    fn state(&self) -> SandboxState {
+        SandboxState {
+            storage: self
+                .storage
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(slot, value)| (B256::from(*slot), B256::from(*value)))
+                .collect(),
+            balances: self
+                .balances
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(account, balance)| (Address::from(*account), *balance))
+                .collect(),
+            block: *self.block.lock().unwrap(),
+        }
+    }
+
+    fn set_state(&self, state: SandboxState) {
+        *self.storage.lock().unwrap() = state
+            .storage
+            .into_iter()
+            .map(|(slot, value)| (slot.0, value.0))
+            .collect();
+        *self.balances.lock().unwrap() = state
+            .balances
+            .into_iter()
+            .map(|(account, balance)| (account.into_array(), balance))
+            .collect();
+        *self.block.lock().unwrap() = state.block;
+    }
+
+    /// Deploys `code` at an account, as if it was a contract.
+    pub fn set_code(&self, account: [u8; 20], code: Vec<u8>) {
+        self.codes.lock().unwrap().insert(account, code);
+    }
+
+    /// Returns the storage accesses the host received during the last call.
+    #[cfg(feature = "storage-journal")]
+    pub fn storage_journal(&self) -> StorageJournal {
+        self.storage_journal.lock().unwrap().clone()
+    }
+}
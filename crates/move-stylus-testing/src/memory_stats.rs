@@ -0,0 +1,131 @@
+//! Statistics of the memory used by the calls, so tests can catch regressions in the memory the
+//! generated code allocates.
+//!
+//! The sandbox instruments the module's allocator, found by its name in the name section: the
+//! calls to it go through a wrapper that counts them and records the end of the last allocation
+//! in exported globals. Modules without the allocator's name report no statistics.
+use walrus::{
+    ConstExpr, FunctionBuilder, FunctionId, GlobalId, Module, ValType,
+    ir::{self, BinaryOp, Value, VisitorMut},
+};
+use wasmtime::{Instance, Store};
+
+/// Name of the allocator function in the modules translated by `move-bytecode-to-wasm`
+const ALLOCATOR_FUNCTION_NAME: &str = "allocator";
+
+const ALLOCATIONS_EXPORT: &str = "sandbox_allocations";
+const PEAK_MEMORY_POINTER_EXPORT: &str = "sandbox_peak_memory_pointer";
+
+/// Memory used by a call.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryStats {
+    /// Pages the memory grew by
+    pub pages_grown: u32,
+    /// Highest value of the allocator's next free memory pointer, the end of the memory
+    /// allocated, or 0 if nothing was allocated
+    pub peak_memory_pointer: u32,
+    /// Calls to the allocator
+    pub allocations: u32,
+}
+
+impl MemoryStats {
+    /// Reads the statistics of a call from the instance once it ends, given the pages of memory
+    /// the instance started with. Returns `None` if the allocator is not instrumented.
+    pub(crate) fn read<T>(
+        instance: &Instance,
+        store: &mut Store<T>,
+        initial_pages: u64,
+    ) -> Option<Self> {
+        let allocations = instance.get_global(&mut *store, ALLOCATIONS_EXPORT)?;
+        let peak_memory_pointer = instance.get_global(&mut *store, PEAK_MEMORY_POINTER_EXPORT)?;
+        let memory = instance.get_memory(&mut *store, "memory")?;
+
+        Some(Self {
+            pages_grown: (memory.size(&*store) - initial_pages) as u32,
+            peak_memory_pointer: peak_memory_pointer.get(&mut *store).i32()? as u32,
+            allocations: allocations.get(&mut *store).i32()? as u32,
+        })
+    }
+}
+
+/// Wraps the module's allocator in a function that counts the allocations and records the end of
+/// the last one, redirecting every call to it. Returns `false`, leaving the module unchanged, if
+/// the module has no allocator.
+pub(crate) fn instrument_allocator(module: &mut Module) -> bool {
+    let Some(allocator) = module.funcs.by_name(ALLOCATOR_FUNCTION_NAME) else {
+        return false;
+    };
+
+    let allocations = add_exported_global(module, ALLOCATIONS_EXPORT);
+    let peak_memory_pointer = add_exported_global(module, PEAK_MEMORY_POINTER_EXPORT);
+
+    let mut function = FunctionBuilder::new(&mut module.types, &[ValType::I32], &[ValType::I32]);
+    let size = module.locals.add(ValType::I32);
+    let pointer = module.locals.add(ValType::I32);
+    let end = module.locals.add(ValType::I32);
+
+    let mut builder = function.func_body();
+    builder
+        .global_get(allocations)
+        .i32_const(1)
+        .binop(BinaryOp::I32Add)
+        .global_set(allocations);
+
+    builder
+        .local_get(size)
+        .call(allocator)
+        .local_tee(pointer)
+        .local_get(size)
+        .binop(BinaryOp::I32Add)
+        .local_set(end);
+
+    // The allocator never frees memory, but the peak is kept in case it does
+    builder
+        .local_get(end)
+        .global_get(peak_memory_pointer)
+        .local_get(end)
+        .global_get(peak_memory_pointer)
+        .binop(BinaryOp::I32GtU)
+        .select(None)
+        .global_set(peak_memory_pointer);
+
+    builder.local_get(pointer);
+
+    let wrapper = function.finish(vec![size], &mut module.funcs);
+
+    let mut redirect = RedirectCalls {
+        from: allocator,
+        to: wrapper,
+    };
+    for (id, function) in module.funcs.iter_local_mut() {
+        if id != wrapper {
+            let entry = function.entry_block();
+            ir::dfs_pre_order_mut(&mut redirect, function, entry);
+        }
+    }
+
+    true
+}
+
+fn add_exported_global(module: &mut Module, name: &str) -> GlobalId {
+    let global =
+        module
+            .globals
+            .add_local(ValType::I32, true, false, ConstExpr::Value(Value::I32(0)));
+    module.exports.add(name, global);
+    global
+}
+
+/// Replaces the calls to a function with calls to another one.
+struct RedirectCalls {
+    from: FunctionId,
+    to: FunctionId,
+}
+
+impl VisitorMut for RedirectCalls {
+    fn visit_call_mut(&mut self, instr: &mut ir::Call) {
+        if instr.func == self.from {
+            instr.func = self.to;
+        }
+    }
+}
@@ -0,0 +1,329 @@
+//! Profile of the WebAssembly instructions run by the calls, attributed to the functions that run
+//! them, to find the hot spots of the generated code.
+//!
+//! A sandbox built with `RuntimeSandboxBuilder::profile_instructions` instruments the module: every
+//! straight-line run of instructions reports its length through the `profiler::count` hook before
+//! running, and every call to a function of the module is surrounded by the `profiler::enter` and
+//! `profiler::exit` hooks, so the sandbox knows the stack of functions the instructions run in.
+//! The functions called through the function table are resolved from the table's elements, and the
+//! functions are named after the name section, where the translation names the Move functions.
+//!
+//! ```ignore
+//! let runtime = RuntimeSandbox::builder(&mut translated_package)
+//!     .profile_instructions()
+//!     .build();
+//! runtime.call_entrypoint(call_data).unwrap();
+//!
+//! // Folded stacks, for `flamegraph.pl` or `inferno-flamegraph`
+//! std::fs::write("profile.folded", runtime.get_instruction_profile().unwrap().folded()).unwrap();
+//! ```
+use std::{collections::HashMap, fmt::Write};
+
+use walrus::{
+    ConstExpr, ElementItems, ElementKind, ExportItem, FunctionId, FunctionKind, InstrLocId,
+    InstrSeqId, LocalId, Module, ValType,
+    ir::{self, Instr, InstrSeq, Value, Visitor},
+};
+
+const PROFILER_MODULE: &str = "profiler";
+
+/// Instructions run by the calls of a sandbox, by the stack of functions they ran in.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InstructionProfile {
+    /// Instructions run by each stack, outermost function first, in the innermost function
+    stacks: HashMap<Vec<String>, u64>,
+}
+
+impl InstructionProfile {
+    /// Total instructions run.
+    pub fn total(&self) -> u64 {
+        self.stacks.values().sum()
+    }
+
+    /// Instructions run by each function itself, without the functions it calls, from the one
+    /// that ran the most. Generic functions count the instructions of all their instantiations.
+    pub fn by_function(&self) -> Vec<(String, u64)> {
+        let mut functions: HashMap<&str, u64> = HashMap::new();
+        for (stack, instructions) in &self.stacks {
+            if let Some(function) = stack.last() {
+                *functions.entry(function).or_default() += instructions;
+            }
+        }
+
+        let mut functions: Vec<(String, u64)> = functions
+            .into_iter()
+            .map(|(function, instructions)| (function.to_owned(), instructions))
+            .collect();
+        functions.sort_by(|(a, a_instructions), (b, b_instructions)| {
+            b_instructions.cmp(a_instructions).then_with(|| a.cmp(b))
+        });
+        functions
+    }
+
+    /// Returns the profile in the folded stacks format read by the flame graph tools: a line per
+    /// stack, with its functions separated by `;` and the instructions run in it.
+    pub fn folded(&self) -> String {
+        let mut stacks: Vec<(&Vec<String>, &u64)> = self.stacks.iter().collect();
+        stacks.sort();
+
+        let mut folded = String::new();
+        for (stack, instructions) in stacks {
+            writeln!(folded, "{} {instructions}", stack.join(";")).unwrap();
+        }
+        folded
+    }
+}
+
+/// Stack of functions of the running calls, and the instructions each stack ran.
+pub(crate) struct Profiler {
+    /// Names of the module's functions, by their index in the profiler
+    functions: Vec<String>,
+    /// Function at each index of the function table, if any
+    table: Vec<Option<u32>>,
+    entrypoint: u32,
+    stack: Vec<u32>,
+    instructions: HashMap<Vec<u32>, u64>,
+}
+
+impl Profiler {
+    /// Starts a call to the entrypoint, on top of the calls running, if the contract is
+    /// re-entered. Returns the depth to pass to `end_call` once it ends.
+    pub(crate) fn begin_call(&mut self) -> usize {
+        let depth = self.stack.len();
+        self.stack.push(self.entrypoint);
+        depth
+    }
+
+    /// Ends the call started at `depth`, dropping the functions a trap did not exit.
+    pub(crate) fn end_call(&mut self, depth: usize) {
+        self.stack.truncate(depth);
+    }
+
+    pub(crate) fn enter(&mut self, function: u32) {
+        self.stack.push(function);
+    }
+
+    pub(crate) fn enter_indirect(&mut self, table_index: u32) {
+        // Calls through an empty or missing entry trap before running anything
+        if let Some(Some(function)) = self.table.get(table_index as usize) {
+            self.stack.push(*function);
+        }
+    }
+
+    pub(crate) fn exit(&mut self) {
+        self.stack.pop();
+    }
+
+    pub(crate) fn count(&mut self, instructions: u32) {
+        if !self.stack.is_empty() {
+            *self.instructions.entry(self.stack.clone()).or_default() += u64::from(instructions);
+        }
+    }
+
+    pub(crate) fn profile(&self) -> InstructionProfile {
+        let mut stacks = HashMap::new();
+        for (stack, instructions) in &self.instructions {
+            let stack = stack
+                .iter()
+                .map(|function| self.functions[*function as usize].clone())
+                .collect();
+            *stacks.entry(stack).or_default() += instructions;
+        }
+
+        InstructionProfile { stacks }
+    }
+}
+
+/// Instruments the module to report the instructions it runs and the functions it calls to the
+/// `profiler` hooks, returning the profiler that attributes them.
+pub(crate) fn instrument(module: &mut Module) -> Profiler {
+    let hooks = ProfilerHooks::import(module);
+
+    let exported_names: HashMap<FunctionId, &str> = module
+        .exports
+        .iter()
+        .filter_map(|export| match export.item {
+            ExportItem::Function(function) => Some((function, export.name.as_str())),
+            _ => None,
+        })
+        .collect();
+
+    // The functions without a name in the name section are named after their export, if any
+    let mut functions = Vec::new();
+    let mut function_indexes = HashMap::new();
+    for function in module.funcs.iter() {
+        if let FunctionKind::Local(_) = function.kind {
+            let name = match (&function.name, exported_names.get(&function.id())) {
+                (Some(name), _) => name.clone(),
+                (None, Some(export)) => (*export).to_owned(),
+                (None, None) => format!("func[{}]", function.id().index()),
+            };
+            function_indexes.insert(function.id(), functions.len() as u32);
+            functions.push(name);
+        }
+    }
+
+    let mut table = Vec::new();
+    for element in module.elements.iter() {
+        let (
+            ElementKind::Active {
+                offset: ConstExpr::Value(Value::I32(offset)),
+                ..
+            },
+            ElementItems::Functions(items),
+        ) = (&element.kind, &element.items)
+        else {
+            continue;
+        };
+
+        for (i, function) in items.iter().enumerate() {
+            let index = *offset as usize + i;
+            if table.len() <= index {
+                table.resize(index + 1, None);
+            }
+            table[index] = function_indexes.get(function).copied();
+        }
+    }
+
+    let entrypoint = module
+        .exports
+        .iter()
+        .find_map(|export| match export.item {
+            ExportItem::Function(function) if export.name == "user_entrypoint" => {
+                function_indexes.get(&function).copied()
+            }
+            _ => None,
+        })
+        .expect("the module does not export user_entrypoint");
+
+    for (_, function) in module.funcs.iter_local_mut() {
+        // Local for the table index of the indirect calls, reported before the call consumes it
+        let table_index = module.locals.add(ValType::I32);
+
+        let mut sequences = SequenceIds(Vec::new());
+        let entry = function.entry_block();
+        ir::dfs_in_order(&mut sequences, function, entry);
+        for sequence in sequences.0 {
+            let instrs = std::mem::take(&mut function.block_mut(sequence).instrs);
+            function.block_mut(sequence).instrs =
+                hooks.instrument_sequence(instrs, &function_indexes, table_index);
+        }
+    }
+
+    Profiler {
+        functions,
+        table,
+        entrypoint,
+        stack: Vec::new(),
+        instructions: HashMap::new(),
+    }
+}
+
+struct ProfilerHooks {
+    enter: FunctionId,
+    enter_indirect: FunctionId,
+    exit: FunctionId,
+    count: FunctionId,
+}
+
+impl ProfilerHooks {
+    fn import(module: &mut Module) -> Self {
+        let with_argument = module.types.add(&[ValType::I32], &[]);
+        let without_arguments = module.types.add(&[], &[]);
+
+        Self {
+            enter: module
+                .add_import_func(PROFILER_MODULE, "enter", with_argument)
+                .0,
+            enter_indirect: module
+                .add_import_func(PROFILER_MODULE, "enter_indirect", with_argument)
+                .0,
+            exit: module
+                .add_import_func(PROFILER_MODULE, "exit", without_arguments)
+                .0,
+            count: module
+                .add_import_func(PROFILER_MODULE, "count", with_argument)
+                .0,
+        }
+    }
+
+    /// Splits the sequence in runs of instructions that end with a branch or a call, each one
+    /// preceded by the report of its length, and surrounds the calls to the module's functions
+    /// with the reports of the function entered and exited.
+    fn instrument_sequence(
+        &self,
+        instrs: Vec<(Instr, InstrLocId)>,
+        function_indexes: &HashMap<FunctionId, u32>,
+        table_index: LocalId,
+    ) -> Vec<(Instr, InstrLocId)> {
+        let mut runs: Vec<Vec<(Instr, InstrLocId)>> = vec![Vec::new()];
+        for instr in instrs {
+            let ends_run = matches!(
+                instr.0,
+                Instr::Block(_)
+                    | Instr::Loop(_)
+                    | Instr::IfElse(_)
+                    | Instr::Br(_)
+                    | Instr::BrIf(_)
+                    | Instr::BrTable(_)
+                    | Instr::Call(_)
+                    | Instr::CallIndirect(_)
+                    | Instr::Return(_)
+                    | Instr::Unreachable(_)
+            );
+            runs.last_mut().unwrap().push(instr);
+            if ends_run {
+                runs.push(Vec::new());
+            }
+        }
+
+        let instr = |instr: Instr| (instr, InstrLocId::default());
+        let mut instrumented = Vec::new();
+        for run in runs.into_iter().filter(|run| !run.is_empty()) {
+            instrumented.push(instr(i32_const(run.len() as i32)));
+            instrumented.push(instr(call(self.count)));
+
+            for (run_instr, location) in run {
+                match &run_instr {
+                    Instr::Call(ir::Call { func }) if function_indexes.contains_key(func) => {
+                        instrumented.push(instr(i32_const(function_indexes[func] as i32)));
+                        instrumented.push(instr(call(self.enter)));
+                        instrumented.push((run_instr, location));
+                        instrumented.push(instr(call(self.exit)));
+                    }
+                    Instr::CallIndirect(_) => {
+                        instrumented
+                            .push(instr(Instr::LocalTee(ir::LocalTee { local: table_index })));
+                        instrumented
+                            .push(instr(Instr::LocalGet(ir::LocalGet { local: table_index })));
+                        instrumented.push(instr(call(self.enter_indirect)));
+                        instrumented.push((run_instr, location));
+                        instrumented.push(instr(call(self.exit)));
+                    }
+                    _ => instrumented.push((run_instr, location)),
+                }
+            }
+        }
+
+        instrumented
+    }
+}
+
+fn i32_const(value: i32) -> Instr {
+    Instr::Const(ir::Const {
+        value: Value::I32(value),
+    })
+}
+
+fn call(func: FunctionId) -> Instr {
+    Instr::Call(ir::Call { func })
+}
+
+/// Collects the ids of the instruction sequences of a function.
+struct SequenceIds(Vec<InstrSeqId>);
+
+impl Visitor<'_> for SequenceIds {
+    fn start_instr_seq(&mut self, instr_seq: &InstrSeq) {
+        self.0.push(instr_seq.id());
+    }
+}
@@ -0,0 +1,357 @@
+//! Record and replay of call sequences, for regression testing: the calls made to a sandbox are
+//! recorded, with their environment and results, to a JSON file, and replayed later against a new
+//! build of the contract, which must return the same data, emit the same logs and write the same
+//! storage.
+//!
+//! ```ignore
+//! // With the current build
+//! runtime.start_recording();
+//! runtime.call_entrypoint(incrementCall::new(()).abi_encode()).unwrap();
+//! runtime.save_recording("counter.recording.json").unwrap();
+//!
+//! // With the new one
+//! let divergences = runtime.replay("counter.recording.json").unwrap();
+//! assert!(divergences.is_empty(), "{divergences:?}");
+//! ```
+use std::{collections::BTreeMap, fmt, fs, path::Path};
+
+use alloy_primitives::{Address, B256, Bytes, U256, hex};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{BlockEnv, LogEvent, RuntimeSandbox, SandboxState};
+
+/// Calls recorded by a sandbox, and the state they were made from.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Recording {
+    initial_state: SandboxState,
+    calls: Vec<RecordedCall>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RecordedCall {
+    calldata: Bytes,
+    value: U256,
+    /// Whether the sender paid the value, as in `call_entrypoint_with_value`
+    paid: bool,
+    msg_sender: Address,
+    tx_origin: Address,
+    gas_left: u64,
+    block: BlockEnv,
+    outcome: CallOutcome,
+}
+
+/// What a call returned, emitted and wrote.
+#[derive(Serialize, Deserialize)]
+struct CallOutcome {
+    /// Status the entrypoint returned, or `None` if the call failed
+    status: Option<i32>,
+    return_data: Bytes,
+    /// Why the call failed, for the reader of the recording. It is not compared, since traps
+    /// report locations of the module
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    logs: Vec<RecordedLog>,
+    /// Slots whose value the call changed, with their new value
+    storage_changes: BTreeMap<B256, B256>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RecordedLog {
+    topics: Vec<B256>,
+    data: Bytes,
+}
+
+/// Difference between a replayed call and its recording.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// Index of the call in the recording
+    pub call: usize,
+    pub kind: DivergenceKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DivergenceKind {
+    /// Status returned by the entrypoint, `None` if the call failed
+    Status {
+        recorded: Option<i32>,
+        replayed: Option<i32>,
+    },
+    ReturnData {
+        recorded: Vec<u8>,
+        replayed: Vec<u8>,
+    },
+    Logs {
+        recorded: Vec<LogEvent>,
+        replayed: Vec<LogEvent>,
+    },
+    /// Value a slot was changed to, `None` if the call did not change it
+    Storage {
+        slot: [u8; 32],
+        recorded: Option<[u8; 32]>,
+        replayed: Option<[u8; 32]>,
+    },
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "call {}: ", self.call)?;
+        match &self.kind {
+            DivergenceKind::Status { recorded, replayed } => write!(
+                f,
+                "status {}, recorded {}",
+                display_status(*replayed),
+                display_status(*recorded)
+            ),
+            DivergenceKind::ReturnData { recorded, replayed } => write!(
+                f,
+                "returned {}, recorded {}",
+                hex::encode_prefixed(replayed),
+                hex::encode_prefixed(recorded)
+            ),
+            DivergenceKind::Logs { recorded, replayed } => write!(
+                f,
+                "emitted {} logs, recorded {} different ones",
+                replayed.len(),
+                recorded.len()
+            ),
+            DivergenceKind::Storage {
+                slot,
+                recorded,
+                replayed,
+            } => write!(
+                f,
+                "slot {} {}, recorded {}",
+                B256::from(*slot),
+                display_slot_change(*replayed),
+                display_slot_change(*recorded)
+            ),
+        }
+    }
+}
+
+fn display_status(status: Option<i32>) -> String {
+    status.map_or_else(|| "failed".to_owned(), |status| status.to_string())
+}
+
+fn display_slot_change(value: Option<[u8; 32]>) -> String {
+    value.map_or_else(
+        || "unchanged".to_owned(),
+        |value| format!("set to {}", B256::from(value)),
+    )
+}
+
+impl RecordedLog {
+    fn to_log_event(&self) -> LogEvent {
+        LogEvent {
+            topics: self.topics.iter().map(|topic| topic.0).collect(),
+            data: self.data.to_vec(),
+        }
+    }
+}
+
+impl CallOutcome {
+    /// Compares the outcome of a replayed call with the recorded one.
+    fn divergences(&self, recorded: &CallOutcome, call: usize) -> Vec<Divergence> {
+        let mut kinds = Vec::new();
+
+        if self.status != recorded.status {
+            kinds.push(DivergenceKind::Status {
+                recorded: recorded.status,
+                replayed: self.status,
+            });
+        }
+
+        if self.return_data != recorded.return_data {
+            kinds.push(DivergenceKind::ReturnData {
+                recorded: recorded.return_data.to_vec(),
+                replayed: self.return_data.to_vec(),
+            });
+        }
+
+        let logs: Vec<LogEvent> = self.logs.iter().map(RecordedLog::to_log_event).collect();
+        let recorded_logs: Vec<LogEvent> = recorded
+            .logs
+            .iter()
+            .map(RecordedLog::to_log_event)
+            .collect();
+        if logs != recorded_logs {
+            kinds.push(DivergenceKind::Logs {
+                recorded: recorded_logs,
+                replayed: logs,
+            });
+        }
+
+        let mut slots: Vec<&B256> = self
+            .storage_changes
+            .keys()
+            .chain(recorded.storage_changes.keys())
+            .collect();
+        slots.sort();
+        slots.dedup();
+        for slot in slots {
+            let replayed = self.storage_changes.get(slot);
+            let recorded = recorded.storage_changes.get(slot);
+            if replayed != recorded {
+                kinds.push(DivergenceKind::Storage {
+                    slot: slot.0,
+                    recorded: recorded.map(|value| value.0),
+                    replayed: replayed.map(|value| value.0),
+                });
+            }
+        }
+
+        kinds
+            .into_iter()
+            .map(|kind| Divergence { call, kind })
+            .collect()
+    }
+}
+
+impl RuntimeSandbox {
+    /// Starts recording the calls made with `call_entrypoint`, `call_entrypoint_with_value` and
+    /// the methods built on them, from the current storage, balances and block. Starting again
+    /// discards the calls recorded so far. The nested calls, made by other contracts of the
+    /// sandbox, are part of the call that makes them.
+    pub fn start_recording(&self) {
+        *self.recording.lock().unwrap() = Some(Recording {
+            initial_state: self.state(),
+            calls: Vec::new(),
+        });
+    }
+
+    /// Writes the calls recorded since `start_recording` to a JSON file, to be replayed with
+    /// `replay`. The sandbox keeps recording.
+    pub fn save_recording(&self, path: impl AsRef<Path>) -> Result<()> {
+        let recording = self.recording.lock().unwrap();
+        let recording = recording
+            .as_ref()
+            .context("the sandbox is not recording, call start_recording first")?;
+
+        let path = path.as_ref();
+        fs::write(path, serde_json::to_string_pretty(recording)?)
+            .with_context(|| format!("failed to write the recording to {}", path.display()))
+    }
+
+    /// Replays the calls of a JSON file written by `save_recording`: loads the state they were
+    /// recorded from, replacing the current one, and makes every call in the environment, sender,
+    /// origin, gas and block, it was recorded in.
+    ///
+    /// Returns how the replayed calls differ from the recorded ones, in their status, return data,
+    /// logs or storage changes, which is empty if the contract behaves the same. The replayed
+    /// calls are not recorded.
+    pub fn replay(&self, path: impl AsRef<Path>) -> Result<Vec<Divergence>> {
+        let path = path.as_ref();
+        let recording = fs::read_to_string(path)
+            .with_context(|| format!("failed to read the recording from {}", path.display()))?;
+        let recording: Recording = serde_json::from_str(&recording)
+            .with_context(|| format!("invalid recording in {}", path.display()))?;
+
+        self.set_state(recording.initial_state);
+
+        let mut divergences = Vec::new();
+        for (i, call) in recording.calls.iter().enumerate() {
+            self.set_msg_sender(call.msg_sender.into_array());
+            self.set_tx_origin(call.tx_origin.into_array());
+            self.set_gas_left(call.gas_left);
+            *self.block.lock().unwrap() = call.block;
+
+            let calldata = call.calldata.to_vec();
+            let (_, outcome) = self.observe(|| {
+                if call.paid {
+                    self.execute_paid(calldata, call.value)
+                } else {
+                    self.execute(calldata, call.value)
+                }
+            });
+
+            divergences.extend(outcome.divergences(&call.outcome, i));
+        }
+
+        Ok(divergences)
+    }
+
+    /// Makes a call through `call`, recording it if the sandbox is recording and it is not a
+    /// nested call.
+    pub(crate) fn record(
+        &self,
+        calldata: Vec<u8>,
+        value: U256,
+        paid: bool,
+        call: impl FnOnce(Vec<u8>) -> Result<(i32, Vec<u8>)>,
+    ) -> Result<(i32, Vec<u8>)> {
+        if *self.call_depth.lock().unwrap() > 0 || self.recording.lock().unwrap().is_none() {
+            return call(calldata);
+        }
+
+        let recorded_calldata = Bytes::copy_from_slice(&calldata);
+        let msg_sender = Address::from(*self.current_msg_sender.lock().unwrap());
+        let tx_origin = Address::from(*self.current_tx_origin.lock().unwrap());
+        let gas_left = *self.gas_left.lock().unwrap();
+        let block = *self.block.lock().unwrap();
+
+        let (result, outcome) = self.observe(|| call(calldata));
+
+        if let Some(recording) = self.recording.lock().unwrap().as_mut() {
+            recording.calls.push(RecordedCall {
+                calldata: recorded_calldata,
+                value,
+                paid,
+                msg_sender,
+                tx_origin,
+                gas_left,
+                block,
+                outcome,
+            });
+        }
+
+        result
+    }
+
+    /// Makes a call, returning its result and what it returned, emitted and wrote.
+    fn observe(
+        &self,
+        call: impl FnOnce() -> Result<(i32, Vec<u8>)>,
+    ) -> (Result<(i32, Vec<u8>)>, CallOutcome) {
+        let previous_storage = self.storage.lock().unwrap().clone();
+
+        let result = call();
+
+        let storage_changes = self
+            .storage
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(slot, value)| previous_storage.get(*slot) != Some(*value))
+            .map(|(slot, value)| (B256::from(*slot), B256::from(*value)))
+            .collect();
+
+        let logs = self
+            .get_logs()
+            .into_iter()
+            .map(|log| RecordedLog {
+                topics: log.topics.into_iter().map(B256::from).collect(),
+                data: log.data.into(),
+            })
+            .collect();
+
+        let outcome = match &result {
+            Ok((status, return_data)) => CallOutcome {
+                status: Some(*status),
+                return_data: Bytes::copy_from_slice(return_data),
+                error: None,
+                logs,
+                storage_changes,
+            },
+            Err(e) => CallOutcome {
+                status: None,
+                return_data: Bytes::new(),
+                error: Some(e.to_string()),
+                logs,
+                storage_changes,
+            },
+        };
+
+        (result, outcome)
+    }
+}
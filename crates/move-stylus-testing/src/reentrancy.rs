@@ -0,0 +1,102 @@
+//! Harness for reentrancy attacks: a malicious contract that, when the contract under test calls
+//! it, calls the contract back before returning, so tests can check that the reentrancy guards
+//! (`#[ext(non_reentrant)]`) hold.
+//!
+//! ```ignore
+//! let victim = Arc::new(RuntimeSandbox::new(&mut translated_package));
+//! let attacker = victim.register_reentrancy_attacker(ATTACKER, withdrawCall::new(()).abi_encode());
+//!
+//! victim.call_entrypoint(withdrawCall::new(()).abi_encode()).unwrap();
+//! assert!(!attacker.reentries()[0].is_success());
+//! ```
+use std::sync::{
+    Arc, Mutex, Weak,
+    atomic::{AtomicBool, Ordering},
+};
+
+use alloy_primitives::U256;
+
+use crate::{
+    BlockEnv, RuntimeSandbox,
+    call_result::{CallResult, RevertReason},
+};
+
+/// Contract that re-enters its caller.
+pub struct ReentrancyAttacker {
+    /// Weak, since the victim holds the attacker
+    victim: Weak<RuntimeSandbox>,
+    address: [u8; 20],
+    calldata: Vec<u8>,
+    /// Set while the attacker re-enters the victim, so the calls the re-entered victim makes to
+    /// it return instead of recursing
+    attacking: AtomicBool,
+    reentries: Mutex<Vec<CallResult>>,
+}
+
+impl ReentrancyAttacker {
+    /// Returns the results of the calls the attacker re-entered the victim with, in order. The
+    /// traps of the re-entered calls are reported as reverts without data.
+    pub fn reentries(&self) -> Vec<CallResult> {
+        self.reentries.lock().unwrap().clone()
+    }
+
+    /// Calls the victim back with the attacker's calldata, as the attacker, during the victim's
+    /// call to it. The attacker's own call always succeeds without returning data.
+    pub(crate) fn attack(&self, tx_origin: [u8; 20], block: BlockEnv) -> (bool, Vec<u8>) {
+        let Some(victim) = self.victim.upgrade() else {
+            return (true, Vec::new());
+        };
+
+        if self.attacking.swap(true, Ordering::Relaxed) {
+            return (true, Vec::new());
+        }
+
+        let (success, return_data) = victim.call_from(
+            self.address,
+            tx_origin,
+            block,
+            self.calldata.clone(),
+            U256::ZERO,
+            false,
+        );
+        self.attacking.store(false, Ordering::Relaxed);
+
+        let result = if success {
+            CallResult::Success(return_data)
+        } else {
+            CallResult::Revert(RevertReason::decode(&return_data))
+        };
+        self.reentries.lock().unwrap().push(result);
+
+        (true, Vec::new())
+    }
+}
+
+impl RuntimeSandbox {
+    /// Registers at `address` a contract that, whenever this contract calls it, calls this
+    /// contract back with `calldata` before returning. The re-entered call runs as a nested call,
+    /// sent by the attacker, during which the `msg_reentrant` hook returns `true`.
+    ///
+    /// Only calls are routed to the attacker: static and delegate calls to it return the external
+    /// call response.
+    pub fn register_reentrancy_attacker(
+        self: &Arc<Self>,
+        address: [u8; 20],
+        calldata: Vec<u8>,
+    ) -> Arc<ReentrancyAttacker> {
+        let attacker = Arc::new(ReentrancyAttacker {
+            victim: Arc::downgrade(self),
+            address,
+            calldata,
+            attacking: AtomicBool::new(false),
+            reentries: Mutex::new(Vec::new()),
+        });
+
+        self.attackers
+            .lock()
+            .unwrap()
+            .insert(address, attacker.clone());
+
+        attacker
+    }
+}
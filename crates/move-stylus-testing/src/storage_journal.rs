@@ -0,0 +1,107 @@
+//! Journal of the storage accesses the host receives during a call.
+//!
+//! Accesses follow the EVM warm/cold model: the first access to a slot during a call is cold, the
+//! following ones are warm.
+use std::collections::{BTreeMap, HashSet};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageAccess {
+    Read {
+        slot: [u8; 32],
+        value: [u8; 32],
+        cold: bool,
+    },
+    Write {
+        slot: [u8; 32],
+        previous: [u8; 32],
+        value: [u8; 32],
+        cold: bool,
+    },
+}
+
+impl StorageAccess {
+    pub fn slot(&self) -> [u8; 32] {
+        match self {
+            StorageAccess::Read { slot, .. } | StorageAccess::Write { slot, .. } => *slot,
+        }
+    }
+
+    pub fn is_cold(&self) -> bool {
+        match self {
+            StorageAccess::Read { cold, .. } | StorageAccess::Write { cold, .. } => *cold,
+        }
+    }
+
+    pub fn is_write(&self) -> bool {
+        matches!(self, StorageAccess::Write { .. })
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct StorageJournal {
+    accesses: Vec<StorageAccess>,
+    warm_slots: HashSet<[u8; 32]>,
+}
+
+impl StorageJournal {
+    pub(crate) fn record_read(&mut self, slot: [u8; 32], value: [u8; 32]) {
+        let cold = self.warm_slots.insert(slot);
+        self.accesses
+            .push(StorageAccess::Read { slot, value, cold });
+    }
+
+    pub(crate) fn record_write(&mut self, slot: [u8; 32], previous: [u8; 32], value: [u8; 32]) {
+        let cold = self.warm_slots.insert(slot);
+        self.accesses.push(StorageAccess::Write {
+            slot,
+            previous,
+            value,
+            cold,
+        });
+    }
+
+    /// Returns the accesses in the order they happened.
+    pub fn accesses(&self) -> &[StorageAccess] {
+        &self.accesses
+    }
+
+    /// Returns the slots whose value changed during the call, with their new value. Slots written
+    /// with the value they already had are not included.
+    pub fn changed_slots(&self) -> BTreeMap<[u8; 32], [u8; 32]> {
+        let mut original_values = BTreeMap::new();
+        let mut final_values = BTreeMap::new();
+        for access in &self.accesses {
+            if let StorageAccess::Write {
+                slot,
+                previous,
+                value,
+                ..
+            } = access
+            {
+                original_values.entry(*slot).or_insert(*previous);
+                final_values.insert(*slot, *value);
+            }
+        }
+
+        final_values
+            .into_iter()
+            .filter(|(slot, value)| original_values[slot] != *value)
+            .collect()
+    }
+
+    /// Returns how many times the host was asked to read the given slot.
+    pub fn reads_at_slot(&self, slot: [u8; 32]) -> usize {
+        self.accesses
+            .iter()
+            .filter(|a| !a.is_write() && a.slot() == slot)
+            .count()
+    }
+
+    pub fn cold_accesses(&self) -> usize {
+        self.accesses.iter().filter(|a| a.is_cold()).count()
+    }
+
+    pub fn warm_accesses(&self) -> usize {
+        self.accesses.iter().filter(|a| !a.is_cold()).count()
+    }
+}
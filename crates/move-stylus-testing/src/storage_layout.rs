@@ -0,0 +1,143 @@
+//! Slots of the objects' fields, resolved from the `<module>.storage.json` artifact emitted by the
+//! translation, so tests can seed the storage of a contract without replaying its transactions.
+//!
+//! The artifact follows the shape of the `storageLayout` output of solc. Objects are saved in the
+//! objects mapping, `mapping(bytes32 owner => mapping(bytes32 id => T))`, rooted at the slot of
+//! the struct's storage entry, and their fields at the slots relative to the object's slot, except
+//! for the ones pinned to absolute slots, which are storage entries of their own.
+use std::collections::HashMap;
+
+use alloy_primitives::{U256, keccak256};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+/// Storage layout of a module, parsed from its `<module>.storage.json` artifact.
+#[derive(Debug, Deserialize)]
+pub struct StorageLayout {
+    storage: Vec<StorageEntry>,
+    types: HashMap<String, StorageType>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StorageEntry {
+    label: String,
+    offset: u32,
+    slot: String,
+    #[serde(rename = "type")]
+    type_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StorageType {
+    encoding: String,
+    number_of_bytes: String,
+    members: Option<Vec<StorageEntry>>,
+}
+
+/// Where a field is saved: the bytes `offset..offset + size` of the slot, counted from its
+/// low-order end, as Solidity packs them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldLocation {
+    pub slot: [u8; 32],
+    pub offset: u32,
+    pub size: u32,
+}
+
+impl StorageLayout {
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Returns where the field of the object of type `struct_name`, with the given owner and id,
+    /// is saved. Fields of nested structs are named `<field>.<nested field>`.
+    ///
+    /// Mappings and StorageVecs occupy a whole slot, the one their values are derived from (see
+    /// `derive_mapping_slot`).
+    pub fn object_field(
+        &self,
+        struct_name: &str,
+        owner: [u8; 20],
+        object_id: [u8; 32],
+        field: &str,
+    ) -> Result<FieldLocation> {
+        // Pinned fields are saved at absolute slots
+        let pinned_label = format!("{struct_name}.{field}");
+        if let Some(entry) = self
+            .storage
+            .iter()
+            .find(|entry| entry.label == pinned_label)
+        {
+            return self.location(entry, U256::ZERO);
+        }
+
+        let object_slot = self.object_slot(struct_name, owner, object_id)?;
+
+        let struct_type_id = format!("t_struct({struct_name})_storage");
+        let member = self
+            .types
+            .get(&struct_type_id)
+            .and_then(|struct_type| struct_type.members.as_ref())
+            .and_then(|members| members.iter().find(|member| member.label == field))
+            .with_context(|| format!("{struct_name} has no field {field}"))?;
+
+        self.location(member, U256::from_be_bytes(object_slot))
+    }
+
+    /// Returns the slot the object of type `struct_name`, with the given owner and id, is saved
+    /// at.
+    pub fn object_slot(
+        &self,
+        struct_name: &str,
+        owner: [u8; 20],
+        object_id: [u8; 32],
+    ) -> Result<[u8; 32]> {
+        let entry = self
+            .storage
+            .iter()
+            .find(|entry| entry.label == struct_name)
+            .with_context(|| format!("{struct_name} is not saved in the storage"))?;
+        let root_slot = parse_slot(&entry.slot)?;
+
+        let mut owner_key = [0; 32];
+        owner_key[12..].copy_from_slice(&owner);
+        let owner_slot = derive_mapping_slot(root_slot.to_be_bytes(), owner_key);
+
+        Ok(derive_mapping_slot(owner_slot, object_id))
+    }
+
+    fn location(&self, entry: &StorageEntry, base_slot: U256) -> Result<FieldLocation> {
+        let field_type = self
+            .types
+            .get(&entry.type_id)
+            .with_context(|| format!("unknown type {}", entry.type_id))?;
+
+        // Dynamic data, mappings and StorageVecs take the whole slot
+        let size = match field_type.encoding.as_str() {
+            "inplace" => field_type.number_of_bytes.parse()?,
+            _ => 32,
+        };
+
+        Ok(FieldLocation {
+            slot: (base_slot + parse_slot(&entry.slot)?).to_be_bytes(),
+            offset: entry.offset,
+            size,
+        })
+    }
+}
+
+/// Returns the slot the value of `key` is saved at in the mapping at `mapping_slot`:
+/// `keccak256(key . mapping_slot)`. Keys of value types are padded to 32 bytes, as they are in
+/// the ABI.
+pub fn derive_mapping_slot(mapping_slot: [u8; 32], key: [u8; 32]) -> [u8; 32] {
+    let mut preimage = [0; 64];
+    preimage[..32].copy_from_slice(&key);
+    preimage[32..].copy_from_slice(&mapping_slot);
+
+    keccak256(preimage).0
+}
+
+fn parse_slot(slot: &str) -> Result<U256> {
+    slot.parse::<U256>()
+        .with_context(|| format!("invalid slot number {slot}"))
+}
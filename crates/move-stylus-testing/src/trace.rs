@@ -0,0 +1,98 @@
+//! Traces of the calls to the entrypoint, recording the host functions the module invokes.
+//!
+//! Tracing is opt-in, enabled with `RuntimeSandbox::enable_tracing`, since every hook goes through
+//! an extra dynamic call while it is on.
+use std::fmt;
+
+use alloy_primitives::hex;
+use alloy_sol_types::SolCall;
+use wasmtime::Val;
+
+/// Trace of a call to the entrypoint.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CallTrace {
+    /// Selector the router dispatched the call with, or `None` if the calldata is too short to
+    /// hold one
+    pub selector: Option<[u8; 4]>,
+    /// Host functions invoked during the call, in order
+    pub host_calls: Vec<HostCall>,
+}
+
+impl CallTrace {
+    /// Returns whether the call was dispatched to the entry function of `C`, generated with
+    /// alloy's `sol!` macro.
+    pub fn dispatched<C: SolCall>(&self) -> bool {
+        self.selector == Some(C::SELECTOR)
+    }
+
+    /// Returns the names of the host functions invoked, in order.
+    pub fn host_call_names(&self) -> Vec<&str> {
+        self.host_calls
+            .iter()
+            .map(|call| call.name.as_str())
+            .collect()
+    }
+}
+
+/// One line per host call, preceded by the selector, so traces can be compared with golden files.
+impl fmt::Display for CallTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.selector {
+            Some(selector) => writeln!(f, "entry 0x{}", hex::encode(selector))?,
+            None => writeln!(f, "entry without selector")?,
+        }
+
+        for host_call in &self.host_calls {
+            writeln!(f, "{host_call}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Invocation of a host function. The hooks only take and return integers, so the values are
+/// widened to i64.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostCall {
+    pub name: String,
+    pub args: Vec<i64>,
+    /// Values returned, empty if the hook does not return any or the execution ended in it
+    pub results: Vec<i64>,
+}
+
+impl HostCall {
+    pub(crate) fn new(name: &str, args: &[Val], results: &[Val]) -> Self {
+        Self {
+            name: name.to_owned(),
+            args: args.iter().map(trace_value).collect(),
+            results: results.iter().map(trace_value).collect(),
+        }
+    }
+}
+
+impl fmt::Display for HostCall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join = |values: &[i64]| {
+            values
+                .iter()
+                .map(i64::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        write!(f, "{}({})", self.name, join(&self.args))?;
+        if !self.results.is_empty() {
+            write!(f, " -> {}", join(&self.results))?;
+        }
+
+        Ok(())
+    }
+}
+
+fn trace_value(value: &Val) -> i64 {
+    match value {
+        Val::I32(value) => i64::from(*value),
+        Val::I64(value) => *value,
+        value => panic!("host functions only use integers, got {value:?}"),
+    }
+}
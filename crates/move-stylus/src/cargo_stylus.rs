@@ -0,0 +1,122 @@
+//! Wrappers around `cargo stylus`, which the modules are checked against a node and deployed with.
+//! It runs as a subprocess, so it must be installed, with `make setup-stylus`.
+use std::{
+    ffi::OsString,
+    io::{BufRead, BufReader},
+    path::Path,
+    process::{Command, ExitCode, ExitStatus, Stdio},
+};
+
+use anyhow::Context;
+
+/// Subcommand of `cargo stylus` run on a module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Subcommand {
+    /// Simulates the activation of the module on the node, without sending a transaction
+    Check,
+    /// Sends the deployment and activation transactions
+    Deploy,
+}
+
+impl Subcommand {
+    fn name(&self) -> &'static str {
+        match self {
+            Subcommand::Check => "check",
+            Subcommand::Deploy => "deploy",
+        }
+    }
+}
+
+/// Runs `cargo stylus` on the module in the file against the node, with the extra arguments of
+/// the subcommand. Its output is not captured.
+pub fn run(
+    subcommand: Subcommand,
+    wasm_file: &Path,
+    endpoint: &str,
+    args: Vec<OsString>,
+) -> anyhow::Result<ExitStatus> {
+    cargo_stylus_command(subcommand, wasm_file, endpoint, args)
+        .status()
+        .context("failed to run cargo stylus, install it with `make setup-stylus`")
+}
+
+/// Runs `cargo stylus` as `run` does, printing its output as it comes and returning it too, so the
+/// results `cargo stylus` prints can be read from it.
+pub fn run_capturing_output(
+    subcommand: Subcommand,
+    wasm_file: &Path,
+    endpoint: &str,
+    args: Vec<OsString>,
+) -> anyhow::Result<(ExitStatus, String)> {
+    let mut child = cargo_stylus_command(subcommand, wasm_file, endpoint, args)
+        .stdout(Stdio::piped())
+        .spawn()
+        .context("failed to run cargo stylus, install it with `make setup-stylus`")?;
+
+    let mut output = String::new();
+    let stdout = child.stdout.take().expect("the output is piped");
+    for line in BufReader::new(stdout).lines() {
+        let line = line.context("failed to read the output of cargo stylus")?;
+        println!("{line}");
+        output.push_str(&line);
+        output.push('\n');
+    }
+
+    let status = child.wait().context("failed to run cargo stylus")?;
+    Ok((status, output))
+}
+
+/// Exit code `move-stylus` forwards from `cargo stylus`, and from `cargo test`.
+pub fn exit_code(status: ExitStatus) -> ExitCode {
+    // Killed by a signal
+    let code = status.code().unwrap_or(1);
+    ExitCode::from(u8::try_from(code).unwrap_or(1))
+}
+
+fn cargo_stylus_command(
+    subcommand: Subcommand,
+    wasm_file: &Path,
+    endpoint: &str,
+    args: Vec<OsString>,
+) -> Command {
+    let mut command = Command::new("cargo");
+    command
+        .args(["stylus", subcommand.name(), "--endpoint", endpoint])
+        .arg("--wasm-file")
+        .arg(wasm_file)
+        .args(args);
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cargo_stylus_command() {
+        let command = cargo_stylus_command(
+            Subcommand::Deploy,
+            Path::new("build/wasm/counter.wasm"),
+            "http://localhost:8547",
+            vec!["--no-verify".into()],
+        );
+
+        let args: Vec<&str> = command
+            .get_args()
+            .map(|arg| arg.to_str().unwrap())
+            .collect();
+        assert_eq!("cargo", command.get_program());
+        assert_eq!(
+            vec![
+                "stylus",
+                "deploy",
+                "--endpoint",
+                "http://localhost:8547",
+                "--wasm-file",
+                "build/wasm/counter.wasm",
+                "--no-verify",
+            ],
+            args
+        );
+    }
+}
@@ -0,0 +1,409 @@
+//! Constraints Stylus puts on the programs it activates, checked before deploying so a module
+//! Stylus would reject fails here instead of in the activation transaction.
+//!
+//! The translation already validates the module and the host functions it imports, so these are
+//! the constraints a valid translation can still break: the size of the module, and the imports of
+//! the hooks only test runners and debug nodes provide.
+use std::{fmt, path::Path, process::ExitCode};
+
+use clap::Parser;
+use move_bytecode_to_wasm::{
+    Artifact, CodeSize, MAX_COMPRESSED_WASM_SIZE, optimize_module, translate_package_with_artifacts,
+};
+use move_cli::base::{build::Build, reroot_path};
+use move_package::BuildConfig;
+use wasmparser::{BinaryReaderError, ExternalKind, FuncType, Payload, TypeRef, ValType, Validator};
+
+use crate::cargo_stylus::{self, Subcommand};
+
+/// Largest module Stylus activates, uncompressed
+pub const MAX_WASM_SIZE: usize = 128 * 1024;
+
+/// Module of the host functions Stylus provides
+const HOST_MODULE: &str = "vm_hooks";
+
+/// Module of the hooks only nodes running in debug mode provide
+const DEBUG_MODULE: &str = "console";
+
+/// Translate the package and check its modules against the constraints Stylus puts on the programs
+/// it activates, without writing them. With `--endpoint`, the package is built and the activation
+/// of every module that passes is also simulated on the node with `cargo stylus check`.
+#[derive(Parser)]
+#[clap(name = "check")]
+pub struct Check {
+    /// RPC endpoint of the node `cargo stylus check` simulates the activation of the modules on.
+    #[clap(long = "endpoint")]
+    pub endpoint: Option<String>,
+    #[clap(flatten)]
+    pub build: Build,
+}
+
+impl Check {
+    pub fn execute(self, path: Option<&Path>, config: BuildConfig) -> anyhow::Result<ExitCode> {
+        let mut build = self.build;
+        build.load_package_config(path)?;
+        let Some(endpoint) = self.endpoint else {
+            return check_in_memory(&build, path, config);
+        };
+
+        if !build.emit.contains(&Artifact::Wasm) {
+            build.emit.push(Artifact::Wasm);
+        }
+        // The oversized modules are reported as violations, along with the rest
+        build.allow_oversized = true;
+        let output = build.output_options();
+
+        // Building roots us to the package root, the artifact paths are relative to
+        let mut modules: Vec<_> = build.build_modules(path, config)?.into_iter().collect();
+        modules.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        let mut passed = true;
+        for (module_name, wasm) in modules {
+            let violations = check_stylus_constraints(&wasm);
+            if !violations.is_empty() {
+                report_violations(&module_name, &violations);
+                passed = false;
+                continue;
+            }
+
+            let wasm_file = output.artifact_path(&module_name, Artifact::Wasm);
+            println!("{module_name}: simulating the activation on {endpoint}");
+            if cargo_stylus::run(Subcommand::Check, &wasm_file, &endpoint, Vec::new())?.success() {
+                println!("{module_name}: ok");
+            } else {
+                eprintln!("{module_name}: cargo stylus check failed");
+                passed = false;
+            }
+        }
+
+        Ok(if passed {
+            ExitCode::SUCCESS
+        } else {
+            ExitCode::FAILURE
+        })
+    }
+}
+
+/// Checks the modules as the build would write them, optimized with `wasm-opt` if the flags, the
+/// profile or `Stylus.toml` ask for it, without writing them nor contacting a node.
+fn check_in_memory(
+    build: &Build,
+    path: Option<&Path>,
+    config: BuildConfig,
+) -> anyhow::Result<ExitCode> {
+    let rerooted_path = reroot_path(path)?;
+    let compiled = config.cli_compile_package(
+        &rerooted_path,
+        &mut std::io::stdout(),
+        &mut std::io::stdin().lock(),
+    )?;
+
+    let options = build.translation_options();
+    let output = build.output_options();
+    let mut modules: Vec<_> = translate_package_with_artifacts(compiled, None, &options)
+        .into_iter()
+        .collect();
+    modules.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    let mut passed = true;
+    for (module_name, mut translated_module) in modules {
+        let mut wasm = translated_module.module.emit_wasm();
+        if let Some(level) = output.wasm_opt {
+            wasm = optimize_module(&wasm, level, options.debug || options.debug_info)?;
+        }
+
+        let violations = check_stylus_constraints(&wasm);
+        if violations.is_empty() {
+            println!("{module_name}: ok");
+        } else {
+            report_violations(&module_name, &violations);
+            passed = false;
+        }
+    }
+
+    Ok(if passed {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+/// Constraint of Stylus a module violates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violation {
+    InvalidWasm(String),
+    MissingEntrypoint,
+    /// `user_entrypoint` is not of type `user_entrypoint(i32) -> i32`
+    InvalidEntrypoint,
+    MissingMemory,
+    /// Function imported from a module other than `vm_hooks`
+    UnsupportedImport {
+        module: String,
+        name: String,
+    },
+    StartFunction,
+    TooLarge {
+        size: usize,
+    },
+    TooLargeCompressed {
+        size: usize,
+    },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::InvalidWasm(e) => write!(f, "invalid WASM: {e}"),
+            Violation::MissingEntrypoint => write!(f, "user_entrypoint function not exported"),
+            Violation::InvalidEntrypoint => {
+                write!(
+                    f,
+                    "user_entrypoint must be of type user_entrypoint(i32) -> i32"
+                )
+            }
+            Violation::MissingMemory => write!(f, "memory not exported"),
+            Violation::UnsupportedImport { module, name } if module == DEBUG_MODULE => write!(
+                f,
+                "imports {module}::{name}, which only nodes running in debug mode provide, build without --debug to deploy"
+            ),
+            Violation::UnsupportedImport { module, name } => {
+                write!(f, "imports {module}::{name}, which Stylus does not provide")
+            }
+            Violation::StartFunction => write!(f, "start functions are not supported"),
+            Violation::TooLarge { size } => write!(
+                f,
+                "the module is {size} bytes, more than the {MAX_WASM_SIZE} bytes allowed"
+            ),
+            Violation::TooLargeCompressed { size } => write!(
+                f,
+                "the module is {size} bytes compressed, more than the {MAX_COMPRESSED_WASM_SIZE} bytes allowed"
+            ),
+        }
+    }
+}
+
+pub fn report_violations(module_name: &str, violations: &[Violation]) {
+    for violation in violations {
+        eprintln!("{module_name}: {violation}");
+    }
+}
+
+/// Checks the module against the constraints Stylus puts on the programs it activates, returning
+/// the ones it violates.
+pub fn check_stylus_constraints(wasm: &[u8]) -> Vec<Violation> {
+    if let Err(e) = Validator::new().validate_all(wasm) {
+        return vec![Violation::InvalidWasm(e.to_string())];
+    }
+
+    let mut violations = Vec::new();
+
+    if wasm.len() > MAX_WASM_SIZE {
+        violations.push(Violation::TooLarge { size: wasm.len() });
+    }
+
+    match CodeSize::measure(wasm) {
+        Ok(code_size) if code_size.exceeds_limit() => {
+            violations.push(Violation::TooLargeCompressed {
+                size: code_size.compressed_size,
+            });
+        }
+        Ok(_) => {}
+        Err(e) => violations.push(Violation::InvalidWasm(e.to_string())),
+    }
+
+    if let Err(e) = check_interface(wasm, &mut violations) {
+        violations.push(Violation::InvalidWasm(e.to_string()));
+    }
+
+    violations
+}
+
+/// Checks the entrypoint and memory exports, the imports and the start function.
+fn check_interface(wasm: &[u8], violations: &mut Vec<Violation>) -> Result<(), BinaryReaderError> {
+    let mut types: Vec<FuncType> = Vec::new();
+    // Type of every function, the imported ones first, by their index
+    let mut functions: Vec<u32> = Vec::new();
+    let mut entrypoint = None;
+    let mut memory_exported = false;
+
+    for payload in wasmparser::Parser::new(0).parse_all(wasm) {
+        match payload? {
+            Payload::TypeSection(reader) => {
+                for function_type in reader.into_iter_err_on_gc_types() {
+                    types.push(function_type?);
+                }
+            }
+            Payload::ImportSection(reader) => {
+                for import in reader {
+                    let import = import?;
+                    if let TypeRef::Func(function_type) = import.ty {
+                        functions.push(function_type);
+                    }
+                    if import.module != HOST_MODULE {
+                        violations.push(Violation::UnsupportedImport {
+                            module: import.module.to_owned(),
+                            name: import.name.to_owned(),
+                        });
+                    }
+                }
+            }
+            Payload::FunctionSection(reader) => {
+                for function_type in reader {
+                    functions.push(function_type?);
+                }
+            }
+            Payload::ExportSection(reader) => {
+                for export in reader {
+                    let export = export?;
+                    match (export.name, export.kind) {
+                        ("user_entrypoint", ExternalKind::Func) => entrypoint = Some(export.index),
+                        ("memory", ExternalKind::Memory) => memory_exported = true,
+                        _ => {}
+                    }
+                }
+            }
+            Payload::StartSection { .. } => violations.push(Violation::StartFunction),
+            _ => {}
+        }
+    }
+
+    match entrypoint {
+        None => violations.push(Violation::MissingEntrypoint),
+        Some(function) => {
+            let function_type = &types[functions[function as usize] as usize];
+            if function_type.params() != [ValType::I32] || function_type.results() != [ValType::I32]
+            {
+                violations.push(Violation::InvalidEntrypoint);
+            }
+        }
+    }
+
+    if !memory_exported {
+        violations.push(Violation::MissingMemory);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENTRYPOINT: &str =
+        r#"(func (export "user_entrypoint") (param i32) (result i32) i32.const 0)"#;
+
+    fn module(body: &str) -> Vec<u8> {
+        wat::parse_str(format!(
+            r#"(module
+                (import "vm_hooks" "pay_for_memory_grow" (func (param i32)))
+                {body}
+            )"#
+        ))
+        .unwrap()
+    }
+
+    /// Data segment of pseudo-random bytes, which don't compress
+    fn random_data(length: usize) -> String {
+        let mut state: u32 = 0x2545_f491;
+        let mut data = String::new();
+        for _ in 0..length {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            data.push_str(&format!("\\{:02x}", state as u8));
+        }
+        format!(r#"(data (i32.const 0) "{data}")"#)
+    }
+
+    #[test]
+    fn test_check_valid_module() {
+        let wasm = module(&format!(r#"(memory (export "memory") 1) {ENTRYPOINT}"#));
+
+        assert_eq!(Vec::<Violation>::new(), check_stylus_constraints(&wasm));
+    }
+
+    #[test]
+    fn test_check_missing_exports() {
+        let wasm = module("(memory 1) (func (param i32) (result i32) i32.const 0)");
+
+        assert_eq!(
+            vec![Violation::MissingEntrypoint, Violation::MissingMemory],
+            check_stylus_constraints(&wasm)
+        );
+    }
+
+    #[test]
+    fn test_check_invalid_entrypoint() {
+        let wasm =
+            module(r#"(memory (export "memory") 1) (func (export "user_entrypoint") (param i32))"#);
+
+        assert_eq!(
+            vec![Violation::InvalidEntrypoint],
+            check_stylus_constraints(&wasm)
+        );
+    }
+
+    #[test]
+    fn test_check_unsupported_imports_and_start() {
+        let wasm = wat::parse_str(format!(
+            r#"(module
+                (import "vm_hooks" "pay_for_memory_grow" (func (param i32)))
+                (import "console" "log_txt" (func (param i32 i32)))
+                (import "coverage" "hit" (func (param i32)))
+                (memory (export "memory") 1)
+                (func $init)
+                (start $init)
+                {ENTRYPOINT}
+            )"#
+        ))
+        .unwrap();
+
+        assert_eq!(
+            vec![
+                Violation::UnsupportedImport {
+                    module: "console".to_owned(),
+                    name: "log_txt".to_owned(),
+                },
+                Violation::UnsupportedImport {
+                    module: "coverage".to_owned(),
+                    name: "hit".to_owned(),
+                },
+                Violation::StartFunction,
+            ],
+            check_stylus_constraints(&wasm)
+        );
+    }
+
+    #[test]
+    fn test_check_size() {
+        // Data that compresses well only counts against the uncompressed limit
+        let wasm = module(&format!(
+            r#"(memory (export "memory") 2) (data (i32.const 0) "{}") {ENTRYPOINT}"#,
+            "\\00".repeat(100 * 1024)
+        ));
+        assert_eq!(Vec::<Violation>::new(), check_stylus_constraints(&wasm));
+
+        let wasm = module(&format!(
+            r#"(memory (export "memory") 1) {} {ENTRYPOINT}"#,
+            random_data(32 * 1024)
+        ));
+        let violations = check_stylus_constraints(&wasm);
+        assert!(matches!(
+            violations.as_slice(),
+            [Violation::TooLargeCompressed { size }] if *size > MAX_COMPRESSED_WASM_SIZE
+        ));
+
+        let wasm = module(&format!(
+            r#"(memory (export "memory") 3) {} {ENTRYPOINT}"#,
+            random_data(MAX_WASM_SIZE)
+        ));
+        let violations = check_stylus_constraints(&wasm);
+        assert!(matches!(
+            violations.as_slice(),
+            [
+                Violation::TooLarge { .. },
+                Violation::TooLargeCompressed { .. }
+            ]
+        ));
+    }
+}
@@ -0,0 +1,162 @@
+//! Call of the constructor of a deployed module. `cargo stylus deploy` only deploys and activates
+//! the program, so the constructor the modules with an `init` or a `#[ext(constructor)]` function
+//! export is called afterwards over JSON-RPC, with its arguments encoded after the ABI artifact of
+//! the module.
+use alloy::{
+    dyn_abi::{DynSolValue, JsonAbiExt, Specifier},
+    json_abi::JsonAbi,
+    network::TransactionBuilder,
+    primitives::{Address, TxHash},
+    providers::{Provider, ProviderBuilder},
+    rpc::types::TransactionRequest,
+    signers::local::PrivateKeySigner,
+    transports::http::reqwest::Url,
+};
+use anyhow::{Context, bail};
+
+const CONSTRUCTOR_NAME: &str = "constructor";
+
+/// Encodes the call to the constructor the ABI describes, parsing the arguments as its Solidity
+/// types, or returns `None` if the module has no constructor.
+pub fn encode_constructor_call(abi: &str, args: &[String]) -> anyhow::Result<Option<Vec<u8>>> {
+    let abi: JsonAbi = serde_json::from_str(abi).context("invalid ABI")?;
+    let Some(constructor) = abi
+        .function(CONSTRUCTOR_NAME)
+        .and_then(|overloads| overloads.first())
+    else {
+        if !args.is_empty() {
+            bail!("the module has no constructor to pass the arguments to");
+        }
+        return Ok(None);
+    };
+
+    if constructor.inputs.len() != args.len() {
+        bail!(
+            "the constructor takes {} arguments, {} given",
+            constructor.inputs.len(),
+            args.len()
+        );
+    }
+
+    let values = constructor
+        .inputs
+        .iter()
+        .zip(args)
+        .map(|(input, arg)| {
+            input
+                .resolve()?
+                .coerce_str(arg)
+                .with_context(|| format!("invalid {} argument {arg}", input.ty))
+        })
+        .collect::<anyhow::Result<Vec<DynSolValue>>>()?;
+
+    Ok(Some(constructor.abi_encode_input(&values)?))
+}
+
+/// Calls the constructor of the contract deployed at the address, and waits for the transaction
+/// to be mined. Fails if it reverts.
+pub async fn call_constructor(
+    endpoint: &str,
+    signer: PrivateKeySigner,
+    address: Address,
+    call_data: Vec<u8>,
+) -> anyhow::Result<TxHash> {
+    let provider = ProviderBuilder::new()
+        .wallet(signer)
+        .connect_http(parse_endpoint(endpoint)?);
+    let transaction = TransactionRequest::default()
+        .with_to(address)
+        .with_input(call_data);
+
+    let receipt = provider
+        .send_transaction(transaction)
+        .await?
+        .get_receipt()
+        .await?;
+    if !receipt.status() {
+        bail!(
+            "the constructor reverted in transaction {}",
+            receipt.transaction_hash
+        );
+    }
+
+    Ok(receipt.transaction_hash)
+}
+
+pub fn parse_endpoint(endpoint: &str) -> anyhow::Result<Url> {
+    endpoint
+        .parse()
+        .with_context(|| format!("invalid endpoint {endpoint}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{U256, keccak256};
+
+    use super::*;
+
+    const ABI: &str = r#"[
+        { "type": "function", "name": "increment", "inputs": [{ "name": "", "type": "bytes32" }], "outputs": [], "stateMutability": "nonpayable" },
+        { "type": "function", "name": "constructor", "inputs": [], "outputs": [], "stateMutability": "nonpayable" }
+    ]"#;
+
+    const ABI_WITH_ARGUMENTS: &str = r#"[
+        {
+            "type": "function",
+            "name": "constructor",
+            "inputs": [
+                { "name": "", "type": "uint64" },
+                { "name": "", "type": "tuple", "components": [
+                    { "name": "owner", "type": "address" },
+                    { "name": "tags", "type": "string[]" }
+                ] }
+            ],
+            "outputs": [],
+            "stateMutability": "nonpayable"
+        }
+    ]"#;
+
+    #[test]
+    fn test_encode_constructor_call() {
+        let call_data = encode_constructor_call(ABI, &[]).unwrap().unwrap();
+
+        // Only the selector the router dispatches the constructor with
+        assert_eq!(keccak256("constructor()")[..4], call_data);
+    }
+
+    #[test]
+    fn test_encode_constructor_arguments() {
+        let args = [
+            "42".to_owned(),
+            "(0x00000000000000000000000000000000000000ff, [a, b])".to_owned(),
+        ];
+        let call_data = encode_constructor_call(ABI_WITH_ARGUMENTS, &args)
+            .unwrap()
+            .unwrap();
+
+        let expected = DynSolValue::Tuple(vec![
+            DynSolValue::Uint(U256::from(42), 64),
+            DynSolValue::Tuple(vec![
+                DynSolValue::Address(Address::with_last_byte(0xff)),
+                DynSolValue::Array(vec![
+                    DynSolValue::String("a".to_owned()),
+                    DynSolValue::String("b".to_owned()),
+                ]),
+            ]),
+        ]);
+        assert_eq!(expected.abi_encode_params(), call_data[4..]);
+    }
+
+    #[test]
+    fn test_encode_constructor_call_errors() {
+        // Wrong number of arguments
+        assert!(encode_constructor_call(ABI, &["1".to_owned()]).is_err());
+        assert!(encode_constructor_call(ABI_WITH_ARGUMENTS, &["1".to_owned()]).is_err());
+        // Argument not of the type of the input
+        let args = ["-1".to_owned(), "(0x00, [])".to_owned()];
+        assert!(encode_constructor_call(ABI_WITH_ARGUMENTS, &args).is_err());
+        // No constructor
+        assert_eq!(None, encode_constructor_call("[]", &[]).unwrap());
+        assert!(encode_constructor_call("[]", &["1".to_owned()]).is_err());
+    }
+}
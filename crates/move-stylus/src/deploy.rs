@@ -0,0 +1,387 @@
+//! Deployment of a module with `cargo stylus deploy`, which compresses it, sends the deployment
+//! transaction and activates the program, followed by the call to its constructor. Every
+//! deployment is appended to the `deployments.json` of the package.
+use std::{
+    ffi::OsString,
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use alloy::{
+    primitives::{Address, keccak256},
+    providers::{Provider, ProviderBuilder},
+    signers::local::PrivateKeySigner,
+};
+use anyhow::{Context, bail};
+use clap::Parser;
+use move_bytecode_to_wasm::{Artifact, DEPLOYMENTS_FILE_NAME, Deployment, Deployments, Profile};
+use move_cli::base::build::Build;
+use move_package::BuildConfig;
+
+use crate::{
+    cargo_stylus::{self, Subcommand},
+    check::{check_stylus_constraints, report_violations},
+    constructor::{call_constructor, encode_constructor_call, parse_endpoint},
+};
+
+/// Environment variable holding the private key used when no other key is given
+const PRIVATE_KEY_VAR: &str = "PRIV_KEY";
+
+/// Build the package, check the module against the constraints of Stylus, deploy it to the node
+/// and call its constructor, if it has an `init` function, printing the address of the contract
+/// and appending the deployment to the manifest of the package.
+///
+/// The module is built with the release profile unless `--profile` sets another one, so it is
+/// stripped of its names and optimized with `wasm-opt -Oz` unless `--wasm-opt` sets another level,
+/// `0` to skip the optimizations. Needs `cargo stylus`, installed with `make setup-stylus`, and
+/// `wasm-opt`, installed with `make install-wasm-opt`.
+#[derive(Parser)]
+#[clap(name = "deploy")]
+pub struct Deploy {
+    /// Name of the module to deploy.
+    #[clap(long = "module", value_name = "NAME")]
+    pub module: String,
+    /// RPC endpoint of the node the module is deployed to.
+    #[clap(long = "endpoint", default_value = "http://localhost:8547")]
+    pub endpoint: String,
+    /// Private key, in hex, of the account that sends the transactions. Read from `PRIV_KEY` if
+    /// no key is given.
+    #[clap(long = "private-key", conflicts_with_all = ["private_key_path", "keystore_path"])]
+    pub private_key: Option<String>,
+    /// File holding the private key of the account that sends the transactions.
+    #[clap(
+        long = "private-key-path",
+        value_name = "PATH",
+        conflicts_with = "keystore_path"
+    )]
+    pub private_key_path: Option<PathBuf>,
+    /// Encrypted keystore of the account that sends the transactions.
+    #[clap(
+        long = "keystore-path",
+        value_name = "PATH",
+        requires = "keystore_password_path"
+    )]
+    pub keystore_path: Option<PathBuf>,
+    /// File holding the password of the keystore.
+    #[clap(long = "keystore-password-path", value_name = "PATH")]
+    pub keystore_password_path: Option<PathBuf>,
+    /// Arguments of the constructor, parsed as the Solidity types of its inputs in the ABI of the
+    /// module, such as `42`, `0x…` or `(1, [a, b])` for a tuple. `@<module>` is replaced by the
+    /// address of the latest deployment of the module on the same chain, read from the manifest.
+    #[clap(
+        long = "constructor-args",
+        value_name = "ARG",
+        num_args = 1..,
+        allow_hyphen_values = true
+    )]
+    pub constructor_args: Vec<String>,
+    /// Estimate the gas of the deployment without sending it.
+    #[clap(long = "estimate-gas")]
+    pub estimate_gas: bool,
+    /// Skip the reproducible build verification of `cargo stylus`.
+    #[clap(long = "no-verify")]
+    pub no_verify: bool,
+    /// Manifest the deployment is appended to, relative to the package root.
+    #[clap(
+        long = "deployments",
+        value_name = "PATH",
+        default_value = DEPLOYMENTS_FILE_NAME
+    )]
+    pub deployments: PathBuf,
+    #[clap(flatten)]
+    pub build: Build,
+}
+
+impl Deploy {
+    pub fn execute(self, path: Option<&Path>, config: BuildConfig) -> anyhow::Result<ExitCode> {
+        // Removed once the deployment is done
+        let (signer, mut args, _private_key_file) = self.signer()?;
+
+        let mut build = self.build;
+        build.load_package_config(path)?;
+        for artifact in [Artifact::Wasm, Artifact::Abi] {
+            if !build.emit.contains(&artifact) {
+                build.emit.push(artifact);
+            }
+        }
+        // Deployments pay for every byte, so they are optimized for size unless told otherwise,
+        // by the flags or by the Stylus.toml
+        build.profile.get_or_insert(Profile::Release);
+        // So `verify` rebuilds the same module, whatever the profile
+        build.deterministic = true;
+        let output = build.output_options();
+        let wasm_file = output.artifact_path(&self.module, Artifact::Wasm);
+
+        // Building roots us to the package root, the artifact paths are relative to
+        let modules = build.build_modules(path, config)?;
+        let wasm = modules
+            .get(&self.module)
+            .with_context(|| format!("module {} not found in the package", self.module))?;
+
+        let violations = check_stylus_constraints(wasm);
+        if !violations.is_empty() {
+            report_violations(&self.module, &violations);
+            bail!("{} can't be deployed", self.module);
+        }
+
+        let runtime = tokio::runtime::Runtime::new()?;
+        let provider = ProviderBuilder::new().connect_http(parse_endpoint(&self.endpoint)?);
+        let chain_id = runtime.block_on(async { provider.get_chain_id().await })?;
+
+        // The arguments are checked before sending anything
+        let abi_file = output.artifact_path(&self.module, Artifact::Abi);
+        let abi = fs::read_to_string(&abi_file)
+            .with_context(|| format!("failed to read {}", abi_file.display()))?;
+        let constructor_args = resolve_deployed_addresses(
+            &self.constructor_args,
+            &Deployments::load(&self.deployments)?,
+            chain_id,
+        )?;
+        let constructor_call = encode_constructor_call(&abi, &constructor_args)?;
+
+        if self.no_verify {
+            args.push("--no-verify".into());
+        }
+        if self.estimate_gas {
+            args.push("--estimate-gas".into());
+            let status = cargo_stylus::run(Subcommand::Deploy, &wasm_file, &self.endpoint, args)?;
+            return Ok(cargo_stylus::exit_code(status));
+        }
+
+        let (status, deploy_output) = cargo_stylus::run_capturing_output(
+            Subcommand::Deploy,
+            &wasm_file,
+            &self.endpoint,
+            args,
+        )?;
+        if !status.success() {
+            return Ok(cargo_stylus::exit_code(status));
+        }
+
+        // Deployed by the account or by the deployer contract of `cargo stylus`, so only the
+        // address it reports is certain
+        let address = parse_deployed_address(&deploy_output)
+            .context("cargo stylus did not report the address of the contract deployed")?;
+        println!("{}: deployed at {address}", self.module);
+
+        let mut constructor_transaction = None;
+        if let Some(call_data) = constructor_call {
+            let transaction =
+                runtime.block_on(call_constructor(&self.endpoint, signer, address, call_data))?;
+            println!(
+                "{}: constructor called in transaction {transaction}",
+                self.module
+            );
+            constructor_transaction = Some(transaction.to_string());
+        }
+
+        let code = runtime.block_on(async { provider.get_code_at(address).await })?;
+        Deployments::append(
+            &self.deployments,
+            Deployment {
+                module: self.module.clone(),
+                address: address.to_string(),
+                chain_id,
+                endpoint: self.endpoint.clone(),
+                code_hash: keccak256(&code).to_string(),
+                module_hash: keccak256(wasm).to_string(),
+                constructor_args,
+                constructor_transaction,
+                timestamp: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+            },
+        )?;
+        println!(
+            "{}: deployment recorded in {}",
+            self.module,
+            self.deployments.display()
+        );
+
+        Ok(ExitCode::SUCCESS)
+    }
+
+    /// Signer of the transactions, the arguments that make `cargo stylus` sign with it, and the
+    /// file the key is written to for `cargo stylus` if it was not given in one.
+    fn signer(&self) -> anyhow::Result<(PrivateKeySigner, Vec<OsString>, Option<PrivateKeyFile>)> {
+        if let Some(keystore_path) = &self.keystore_path {
+            let password_path = self
+                .keystore_password_path
+                .as_ref()
+                .expect("required by clap");
+            let password = fs::read_to_string(password_path)
+                .with_context(|| format!("failed to read {}", password_path.display()))?;
+            let signer = PrivateKeySigner::decrypt_keystore(keystore_path, password.trim())
+                .with_context(|| format!("failed to decrypt {}", keystore_path.display()))?;
+
+            let args = vec![
+                "--keystore-path".into(),
+                keystore_path.into(),
+                "--keystore-password-path".into(),
+                password_path.into(),
+            ];
+            return Ok((signer, args, None));
+        }
+
+        if let Some(private_key_path) = &self.private_key_path {
+            let private_key = fs::read_to_string(private_key_path)
+                .with_context(|| format!("failed to read {}", private_key_path.display()))?;
+            let signer = private_key.trim().parse().context("invalid private key")?;
+
+            let args = vec!["--private-key-path".into(), private_key_path.into()];
+            return Ok((signer, args, None));
+        }
+
+        let private_key = match &self.private_key {
+            Some(private_key) => private_key.clone(),
+            None => std::env::var(PRIVATE_KEY_VAR).with_context(|| {
+                format!(
+                    "no private key given, pass --private-key, --private-key-path or \
+                     --keystore-path, or set {PRIVATE_KEY_VAR}"
+                )
+            })?,
+        };
+        let signer = private_key.parse().context("invalid private key")?;
+
+        // Every user can read the arguments of the processes, so the key is passed in a file
+        let private_key_file = PrivateKeyFile::new(&private_key)?;
+        let args = vec![
+            "--private-key-path".into(),
+            private_key_file.path.clone().into(),
+        ];
+        Ok((signer, args, Some(private_key_file)))
+    }
+}
+
+/// Private key written to a file only its owner can read, removed when dropped.
+struct PrivateKeyFile {
+    path: PathBuf,
+}
+
+impl PrivateKeyFile {
+    fn new(private_key: &str) -> anyhow::Result<Self> {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH)?.subsec_nanos();
+        let path =
+            std::env::temp_dir().join(format!("move-stylus-key-{}-{nanos}", std::process::id()));
+
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options
+            .open(&path)
+            .with_context(|| format!("failed to create {}", path.display()))?;
+
+        // Removed by the drop if it can't be written
+        let private_key_file = Self { path };
+        file.write_all(private_key.as_bytes())
+            .with_context(|| format!("failed to write {}", private_key_file.path.display()))?;
+        Ok(private_key_file)
+    }
+}
+
+impl Drop for PrivateKeyFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Address of the contract `cargo stylus deploy` reports in its output, as
+/// `deployed code at address: 0x…`, colored or not.
+fn parse_deployed_address(output: &str) -> Option<Address> {
+    output
+        .lines()
+        .filter(|line| line.contains("deployed code at address"))
+        .find_map(|line| {
+            line.match_indices("0x").find_map(|(start, _)| {
+                line.get(start..start + 42)
+                    .filter(|address| address[2..].chars().all(|c| c.is_ascii_hexdigit()))
+                    .and_then(|address| address.parse().ok())
+            })
+        })
+}
+
+/// Replaces the `@<module>` arguments by the address of the latest deployment of the module on the
+/// chain.
+fn resolve_deployed_addresses(
+    args: &[String],
+    deployments: &Deployments,
+    chain_id: u64,
+) -> anyhow::Result<Vec<String>> {
+    args.iter()
+        .map(|arg| match arg.strip_prefix('@') {
+            Some(module) => deployments
+                .latest(module, chain_id)
+                .map(|deployment| deployment.address.clone())
+                .with_context(|| format!("{module} is not deployed on chain {chain_id}")),
+            None => Ok(arg.clone()),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_deployed_addresses() {
+        let deployment = |module: &str, address: &str, chain_id| Deployment {
+            module: module.to_owned(),
+            address: address.to_owned(),
+            chain_id,
+            endpoint: "http://localhost:8547".to_owned(),
+            code_hash: String::new(),
+            module_hash: String::new(),
+            constructor_args: Vec::new(),
+            constructor_transaction: None,
+            timestamp: 0,
+        };
+        let deployments = Deployments {
+            deployments: vec![
+                deployment("token", "0x01", 412346),
+                deployment("token", "0x02", 421614),
+            ],
+        };
+
+        let args = ["@token".to_owned(), "42".to_owned()];
+        assert_eq!(
+            vec!["0x01".to_owned(), "42".to_owned()],
+            resolve_deployed_addresses(&args, &deployments, 412346).unwrap()
+        );
+        assert!(resolve_deployed_addresses(&["@vault".to_owned()], &deployments, 412346).is_err());
+    }
+
+    #[test]
+    fn test_parse_deployed_address() {
+        let output = "stripped custom section from user wasm to remove any sensitive data\n\
+                      contract size: 4.3 KB\n\
+                      deployed code at address: \u{1b}[1;94m0x33f54de59419570a9442e788f5dd5cf635b3c7ac\u{1b}[0m\n\
+                      deployment tx hash: 0x1d3a63e0b9e9649e0a1be0b8c5fa4c3a2e9f9a0c5b0e34e5b7bb0b0e3b2b4d9a\n";
+        assert_eq!(
+            Some(
+                "0x33f54de59419570a9442e788f5dd5cf635b3c7ac"
+                    .parse()
+                    .unwrap()
+            ),
+            parse_deployed_address(output)
+        );
+        assert_eq!(None, parse_deployed_address("contract size: 4.3 KB\n"));
+    }
+
+    #[test]
+    fn test_private_key_file() {
+        let private_key_file = PrivateKeyFile::new("0x01").unwrap();
+        let path = private_key_file.path.clone();
+        assert_eq!("0x01", fs::read_to_string(&path).unwrap());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(0o600, mode & 0o777);
+        }
+
+        drop(private_key_file);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,143 @@
+//! Nitro devnode the on-chain tests run against: a local Arbitrum chain with Stylus enabled,
+//! started in Docker from the image of Offchain Labs, or an already running node the tests attach
+//! to. Docker must be installed to start it.
+use std::{
+    process::Command,
+    thread,
+    time::{Duration, Instant},
+};
+
+use alloy::providers::{Provider, ProviderBuilder};
+use anyhow::{Context, bail};
+
+use crate::constructor::parse_endpoint;
+
+/// Image of the node started when the tests don't attach to one
+pub const DEVNODE_IMAGE: &str = "offchainlabs/nitro-node:v3.5.3-0a9c975";
+
+/// Key of the account the devnode funds at genesis
+pub const DEVNODE_PRIVATE_KEY: &str =
+    "0xb6b15c8cb491557369f3c7d2c287b053eb229daa9c22138887752191c9520659";
+
+/// Port the devnode serves JSON-RPC on
+const DEVNODE_PORT: u16 = 8547;
+
+/// Time the node is given to serve JSON-RPC once started
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+
+pub struct Devnode {
+    pub endpoint: String,
+    /// Container of the node started, stopped when the devnode is dropped. `None` if the tests
+    /// attached to a running node.
+    container: Option<String>,
+}
+
+impl Devnode {
+    /// Starts a devnode from the image, and waits until it serves JSON-RPC.
+    pub fn start(image: &str) -> anyhow::Result<Self> {
+        println!("starting the devnode {image}...");
+        let output = docker_run_command(image)
+            .output()
+            .context("failed to run docker, install it to start the devnode")?;
+        if !output.status.success() {
+            bail!(
+                "failed to start the devnode: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+
+        // Stopped by the drop if it does not start
+        let devnode = Self {
+            endpoint: format!("http://localhost:{DEVNODE_PORT}"),
+            container: Some(String::from_utf8_lossy(&output.stdout).trim().to_owned()),
+        };
+        devnode.wait_until_ready()?;
+        Ok(devnode)
+    }
+
+    /// Attaches to the node at the endpoint, which is left running.
+    pub fn attach(endpoint: String) -> anyhow::Result<Self> {
+        let devnode = Self {
+            endpoint,
+            container: None,
+        };
+        devnode.wait_until_ready()?;
+        Ok(devnode)
+    }
+
+    fn wait_until_ready(&self) -> anyhow::Result<()> {
+        let provider = ProviderBuilder::new().connect_http(parse_endpoint(&self.endpoint)?);
+        let runtime = tokio::runtime::Runtime::new()?;
+
+        let start = Instant::now();
+        while runtime
+            .block_on(async { provider.get_chain_id().await })
+            .is_err()
+        {
+            if start.elapsed() > STARTUP_TIMEOUT {
+                bail!(
+                    "the node at {} did not respond in {} seconds",
+                    self.endpoint,
+                    STARTUP_TIMEOUT.as_secs()
+                );
+            }
+            thread::sleep(Duration::from_secs(1));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Devnode {
+    fn drop(&mut self) {
+        if let Some(container) = &self.container {
+            // The container is removed once stopped
+            let _ = Command::new("docker").args(["stop", container]).output();
+        }
+    }
+}
+
+fn docker_run_command(image: &str) -> Command {
+    let mut command = Command::new("docker");
+    command
+        .args(["run", "--rm", "--detach", "--publish"])
+        .arg(format!("{DEVNODE_PORT}:{DEVNODE_PORT}"))
+        .arg(image)
+        .args([
+            "--dev",
+            "--http.addr",
+            "0.0.0.0",
+            "--http.api=net,web3,eth,debug",
+        ]);
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_docker_run_command() {
+        let command = docker_run_command(DEVNODE_IMAGE);
+
+        let args: Vec<&str> = command
+            .get_args()
+            .map(|arg| arg.to_str().unwrap())
+            .collect();
+        assert_eq!("docker", command.get_program());
+        assert_eq!(
+            vec![
+                "run",
+                "--rm",
+                "--detach",
+                "--publish",
+                "8547:8547",
+                DEVNODE_IMAGE,
+                "--dev",
+                "--http.addr",
+                "0.0.0.0",
+                "--http.api=net,web3,eth,debug",
+            ],
+            args
+        );
+    }
+}
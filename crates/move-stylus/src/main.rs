@@ -0,0 +1,77 @@
+//! `move-stylus` creates Move packages for Stylus, builds them to WebAssembly, checks the modules
+//! against the constraints Stylus puts on the programs it activates, deploys them, tests them on a
+//! devnode, and verifies the deployed contracts against the package.
+//!
+//! It exits with 0 if the command succeeds, 1 if it fails or a module violates a constraint, and
+//! 2 if the arguments are invalid. `deploy` exits with the code of `cargo stylus deploy`, and
+//! `check --endpoint` with 1 if `cargo stylus check` fails for any module. `verify` exits with 1
+//! if the contract is not built from the package, and `test` with the code of `cargo test`.
+use std::{path::PathBuf, process::ExitCode};
+
+use check::Check;
+use clap::Parser;
+use deploy::Deploy;
+use move_cli::base::build::Build;
+use move_package::BuildConfig;
+use move_packages_build::implicit_dependencies;
+use new::New;
+use test::Test;
+use verify::Verify;
+
+mod cargo_stylus;
+mod check;
+mod constructor;
+mod deploy;
+mod devnode;
+mod new;
+mod test;
+mod verify;
+
+#[derive(Parser)]
+#[clap(name = "move-stylus", author, version, about)]
+struct MoveStylus {
+    /// Path to a package which the command should be run with respect to.
+    #[clap(long = "path", short = 'p', global = true)]
+    package_path: Option<PathBuf>,
+
+    /// Package build options
+    #[clap(flatten)]
+    build_config: BuildConfig,
+
+    #[clap(subcommand)]
+    cmd: Command,
+}
+
+#[derive(Parser)]
+enum Command {
+    Build(Build),
+    Check(Check),
+    Deploy(Deploy),
+    New(New),
+    Test(Test),
+    Verify(Verify),
+}
+
+fn main() -> ExitCode {
+    let MoveStylus {
+        package_path,
+        mut build_config,
+        cmd,
+    } = MoveStylus::parse();
+    build_config.implicit_dependencies = implicit_dependencies();
+
+    let path = package_path.as_deref();
+    let result = match cmd {
+        Command::Build(c) => c.execute(path, build_config).map(|()| ExitCode::SUCCESS),
+        Command::Check(c) => c.execute(path, build_config),
+        Command::Deploy(c) => c.execute(path, build_config),
+        Command::New(c) => c.execute(path).map(|()| ExitCode::SUCCESS),
+        Command::Test(c) => c.execute(path, build_config),
+        Command::Verify(c) => c.execute(path, build_config),
+    };
+
+    result.unwrap_or_else(|e| {
+        eprintln!("error: {e:#}");
+        ExitCode::FAILURE
+    })
+}
@@ -0,0 +1,219 @@
+//! Scaffolding of a new Move package for Stylus: the manifest with the framework and the named
+//! address of the package, a counter contract, and a Cargo harness testing it in the sandbox.
+use std::{
+    fs::{self, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::{Component, Path, PathBuf},
+};
+
+use anyhow::{Context, bail};
+use clap::Parser;
+use move_cli::base::new::New as MoveNew;
+use move_package::source_package::layout::SourcePackageLayout;
+
+/// Repository the framework and the crates of the test harness are fetched from
+const REPOSITORY: &str = "https://github.com/rather-labs/move-stylus-poc.git";
+
+/// Placeholders of the templates
+const NAME_PLACEHOLDER: &str = "{{name}}";
+const REPOSITORY_PLACEHOLDER: &str = "{{repository}}";
+
+const COUNTER_TEMPLATE: &str = include_str!("../templates/counter.move");
+const CARGO_TOML_TEMPLATE: &str = include_str!("../templates/Cargo.toml.tmpl");
+const COUNTER_TESTS_TEMPLATE: &str = include_str!("../templates/counter.rs.tmpl");
+
+/// Create a new Move package for Stylus named `name` at `path`, or in the directory `name` if
+/// no path is given. The package has a counter contract and a Cargo harness that tests it in the
+/// sandbox, run with `cargo test`.
+#[derive(Parser)]
+#[clap(name = "new")]
+pub struct New {
+    /// The name of the package to be created.
+    pub name: String,
+    /// Local checkout of the Stylus framework the package depends on, instead of the one in the
+    /// repository of `move-stylus`. The manifest points to it relative to the package, so it is
+    /// found wherever the package is built from.
+    #[clap(long = "framework-path", value_name = "PATH")]
+    pub framework_path: Option<PathBuf>,
+}
+
+impl New {
+    pub fn execute(self, path: Option<&Path>) -> anyhow::Result<()> {
+        let package_path = path.map_or_else(|| PathBuf::from(&self.name), Path::to_path_buf);
+        if package_path
+            .join(SourcePackageLayout::Manifest.path())
+            .exists()
+        {
+            bail!("{} already holds a package", package_path.display());
+        }
+
+        let framework = match &self.framework_path {
+            Some(framework_path) => format!(
+                "{{ local = \"{}\" }}",
+                framework_dependency_path(framework_path, &package_path)?.display()
+            ),
+            None => format!(
+                "{{ git = \"{REPOSITORY}\", subdir = \"stylus-framework\", rev = \"main\" }}"
+            ),
+        };
+
+        let name = self.name.clone();
+        MoveNew { name: name.clone() }.execute(
+            Some(&package_path),
+            [("StylusFramework", framework)],
+            [(name.as_str(), "0x0")],
+            "",
+        )?;
+
+        let tests_path = package_path.join(SourcePackageLayout::Tests.path());
+        fs::create_dir_all(&tests_path)?;
+
+        write_template(
+            &package_path
+                .join(SourcePackageLayout::Sources.path())
+                .join("counter.move"),
+            COUNTER_TEMPLATE,
+            &name,
+        )?;
+        write_template(&package_path.join("Cargo.toml"), CARGO_TOML_TEMPLATE, &name)?;
+        write_template(
+            &tests_path.join("counter.rs"),
+            COUNTER_TESTS_TEMPLATE,
+            &name,
+        )?;
+        ignore_cargo_target(&package_path)?;
+
+        println!("Created package {name} in {}", package_path.display());
+        Ok(())
+    }
+}
+
+/// Path of the framework relative to the package, as Move resolves the local dependencies from the
+/// package's directory and not from the one `move-stylus new` ran in.
+fn framework_dependency_path(
+    framework_path: &Path,
+    package_path: &Path,
+) -> anyhow::Result<PathBuf> {
+    let framework = fs::canonicalize(framework_path).with_context(|| {
+        format!(
+            "failed to find the framework at {}",
+            framework_path.display()
+        )
+    })?;
+    fs::create_dir_all(package_path)
+        .with_context(|| format!("failed to create {}", package_path.display()))?;
+    let package = fs::canonicalize(package_path)?;
+
+    Ok(relative_path(&package, &framework))
+}
+
+/// Path of `to` relative to `from`, both absolute. If they share no prefix, as paths in different
+/// drives, `to` is returned as it is.
+fn relative_path(from: &Path, to: &Path) -> PathBuf {
+    let from: Vec<_> = from.components().collect();
+    let to: Vec<_> = to.components().collect();
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+    if common == 0 {
+        return to.iter().collect();
+    }
+
+    let mut path: PathBuf = from[common..]
+        .iter()
+        .map(|_| Component::ParentDir)
+        .collect();
+    path.extend(&to[common..]);
+    path
+}
+
+fn write_template(path: &Path, template: &str, name: &str) -> anyhow::Result<()> {
+    let contents = template
+        .replace(NAME_PLACEHOLDER, name)
+        .replace(REPOSITORY_PLACEHOLDER, REPOSITORY);
+    fs::write(path, contents).with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// Adds the target directory of the test harness to the `.gitignore` created with the package.
+fn ignore_cargo_target(path: &Path) -> anyhow::Result<()> {
+    let gitignore_entry = "target/";
+
+    let mut file = OpenOptions::new()
+        .read(true)
+        .append(true)
+        .open(path.join(".gitignore"))
+        .context("failed to open .gitignore")?;
+    for line in BufReader::new(&file).lines().map_while(Result::ok) {
+        if line == gitignore_entry {
+            return Ok(());
+        }
+    }
+
+    writeln!(file, "{gitignore_entry}")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_package() {
+        let root = std::env::temp_dir().join(format!("move-stylus-new-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let framework_path = root.join("stylus-framework");
+        fs::create_dir_all(&framework_path).unwrap();
+        let path = root.join("my_counter");
+
+        New {
+            name: "my_counter".to_owned(),
+            framework_path: Some(framework_path),
+        }
+        .execute(Some(&path))
+        .unwrap();
+
+        let manifest = fs::read_to_string(path.join("Move.toml")).unwrap();
+        assert!(manifest.contains("name = \"my_counter\""));
+        assert!(manifest.contains("StylusFramework = { local = \"../stylus-framework\" }"));
+        assert!(manifest.contains("my_counter = \"0x0\""));
+
+        let counter = fs::read_to_string(path.join("sources/counter.move")).unwrap();
+        assert!(counter.contains("module my_counter::counter;"));
+
+        let cargo_toml = fs::read_to_string(path.join("Cargo.toml")).unwrap();
+        assert!(cargo_toml.contains("name = \"my_counter-tests\""));
+        assert!(!cargo_toml.contains(REPOSITORY_PLACEHOLDER));
+        assert!(path.join("tests/counter.rs").exists());
+
+        let gitignore = fs::read_to_string(path.join(".gitignore")).unwrap();
+        assert_eq!(
+            vec!["build/*", "target/"],
+            gitignore.lines().collect::<Vec<_>>()
+        );
+
+        // The package is not overwritten
+        assert!(
+            New {
+                name: "my_counter".to_owned(),
+                framework_path: None,
+            }
+            .execute(Some(&path))
+            .is_err()
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_relative_path() {
+        assert_eq!(
+            PathBuf::from("../../stylus-framework"),
+            relative_path(
+                Path::new("/home/dev/packages/counter"),
+                Path::new("/home/dev/stylus-framework")
+            )
+        );
+        assert_eq!(
+            PathBuf::from("framework"),
+            relative_path(Path::new("/home/dev"), Path::new("/home/dev/framework"))
+        );
+    }
+}
@@ -0,0 +1,151 @@
+//! Tests of a package with the Cargo harness `move-stylus new` creates, run with `cargo test`. The
+//! tests call the modules in the sandbox of `move-stylus-testing` or, with `--onchain`, the
+//! contracts deployed to a nitro devnode, started in Docker or already running.
+//!
+//! The on-chain tests find the node, the addresses of the contracts and the key of the funded
+//! account in the environment variables of `cargo test`.
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, ExitCode},
+};
+
+use anyhow::{Context, bail};
+use clap::Parser;
+use move_cli::base::build::Build;
+use move_package::{BuildConfig, source_package::layout::SourcePackageLayout};
+
+use crate::{
+    cargo_stylus,
+    deploy::Deploy,
+    devnode::{DEVNODE_IMAGE, DEVNODE_PRIVATE_KEY, Devnode},
+};
+
+/// Endpoint of the node the contracts are deployed to
+const ENDPOINT_VAR: &str = "MOVE_STYLUS_ENDPOINT";
+/// Manifest of the contracts deployed, read with `Deployments::load`
+const DEPLOYMENTS_VAR: &str = "MOVE_STYLUS_DEPLOYMENTS";
+/// Private key of the account the contracts are deployed with
+const PRIVATE_KEY_VAR: &str = "PRIV_KEY";
+
+/// Manifest the contracts are deployed to, relative to the package root. It is written again by
+/// every run, so the package's `deployments.json` only lists the real deployments.
+const ONCHAIN_DEPLOYMENTS_FILE: &str = "build/deployments.onchain.json";
+
+/// Run the Cargo test harness of the package, in the sandbox or, with `--onchain`, against a
+/// devnode the contracts of the package are deployed to. Exits with the code of `cargo test`.
+///
+/// The on-chain tests read the endpoint of the node from `MOVE_STYLUS_ENDPOINT`, the manifest of
+/// the deployments from `MOVE_STYLUS_DEPLOYMENTS` and the key of the deployer from `PRIV_KEY`.
+#[derive(Parser)]
+#[clap(name = "test")]
+pub struct Test {
+    /// Deploy every contract of the package to a nitro devnode, started in Docker unless
+    /// `--endpoint` is given, and run the tests against it. The devnode started is stopped once
+    /// the tests finish.
+    #[clap(long = "onchain")]
+    pub onchain: bool,
+    /// RPC endpoint of a running node the tests attach to, instead of starting a devnode.
+    #[clap(long = "endpoint", requires = "onchain")]
+    pub endpoint: Option<String>,
+    /// Docker image of the devnode started.
+    #[clap(
+        long = "devnode-image",
+        value_name = "IMAGE",
+        default_value = DEVNODE_IMAGE,
+        conflicts_with = "endpoint"
+    )]
+    pub devnode_image: String,
+    /// Private key, in hex, of the account the contracts are deployed with, the one the devnode
+    /// funds by default.
+    #[clap(long = "private-key", default_value = DEVNODE_PRIVATE_KEY)]
+    pub private_key: String,
+    /// Arguments passed to `cargo test`, after `--`.
+    #[clap(last = true)]
+    pub cargo_args: Vec<String>,
+    #[clap(flatten)]
+    pub build: Build,
+}
+
+impl Test {
+    pub fn execute(self, path: Option<&Path>, config: BuildConfig) -> anyhow::Result<ExitCode> {
+        let path = path
+            .map(Path::canonicalize)
+            .unwrap_or_else(|| PathBuf::from(".").canonicalize())?;
+        let package_root = SourcePackageLayout::try_find_root(&path)?;
+        if !package_root.join("Cargo.toml").exists() {
+            bail!(
+                "{} has no Cargo.toml, the test harness `move-stylus new` creates",
+                package_root.display()
+            );
+        }
+
+        let mut command = Command::new("cargo");
+        command
+            .arg("test")
+            .args(&self.cargo_args)
+            .current_dir(&package_root);
+
+        // Running until the tests finish
+        let mut _devnode = None;
+        if self.onchain {
+            let devnode = match &self.endpoint {
+                Some(endpoint) => Devnode::attach(endpoint.clone())?,
+                None => Devnode::start(&self.devnode_image)?,
+            };
+
+            let deployments = package_root.join(ONCHAIN_DEPLOYMENTS_FILE);
+            if deployments.exists() {
+                fs::remove_file(&deployments)
+                    .with_context(|| format!("failed to remove {}", deployments.display()))?;
+            }
+            self.deploy_contracts(&package_root, config, &devnode.endpoint, &deployments)?;
+
+            command
+                .env(ENDPOINT_VAR, &devnode.endpoint)
+                .env(DEPLOYMENTS_VAR, &deployments)
+                .env(PRIVATE_KEY_VAR, &self.private_key);
+            _devnode = Some(devnode);
+        }
+
+        let status = command.status().context("failed to run cargo test")?;
+        Ok(cargo_stylus::exit_code(status))
+    }
+
+    /// Deploys every contract of the package, in the order of their names, as `deploy` does.
+    fn deploy_contracts(
+        &self,
+        package_root: &Path,
+        config: BuildConfig,
+        endpoint: &str,
+        deployments: &Path,
+    ) -> anyhow::Result<()> {
+        let mut modules: Vec<String> = self
+            .build
+            .clone()
+            .build_modules(Some(package_root), config.clone())?
+            .into_keys()
+            .collect();
+        modules.sort();
+
+        for module in modules {
+            let deploy = Deploy {
+                module: module.clone(),
+                endpoint: endpoint.to_owned(),
+                private_key: Some(self.private_key.clone()),
+                private_key_path: None,
+                keystore_path: None,
+                keystore_password_path: None,
+                constructor_args: Vec::new(),
+                estimate_gas: false,
+                no_verify: false,
+                deployments: deployments.to_path_buf(),
+                build: self.build.clone(),
+            };
+            if deploy.execute(Some(package_root), config.clone())? != ExitCode::SUCCESS {
+                bail!("failed to deploy {module} to {endpoint}");
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,192 @@
+//! Verification of a deployed contract against the package it was built from. The package is
+//! built deterministically, as `deploy` builds it, so the same sources, translated by the same
+//! version of the compiler with the same flags, give the same module, byte by byte.
+//!
+//! Stylus saves the module compressed with brotli, after a prefix, so the code of the contract is
+//! decompressed and its hash compared with the hash of the rebuilt module.
+use std::{
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use alloy::{
+    primitives::{Address, keccak256},
+    providers::{Provider, ProviderBuilder},
+};
+use anyhow::{Context, bail};
+use clap::Parser;
+use move_bytecode_to_wasm::{Artifact, Profile};
+use move_cli::base::build::Build;
+use move_package::BuildConfig;
+use serde::Serialize;
+
+use crate::constructor::parse_endpoint;
+
+/// Prefix of the code of the Stylus programs, before the compressed module
+const STYLUS_PREFIX: [u8; 3] = [0xef, 0xf0, 0x00];
+
+/// Dictionary byte of the modules compressed without a dictionary, the ones `cargo stylus deploy`
+/// deploys
+const NO_DICTIONARY: u8 = 0x00;
+
+/// Rebuild the package and check that the contract deployed at the address is the module. The
+/// package must be built with the flags it was deployed with, so the module is built with the
+/// release profile unless `--profile` sets another one, as `deploy` does.
+#[derive(Parser)]
+#[clap(name = "verify")]
+pub struct Verify {
+    /// Name of the module the contract was deployed from.
+    #[clap(long = "module", value_name = "NAME")]
+    pub module: String,
+    /// Address of the contract.
+    #[clap(long = "address")]
+    pub address: Address,
+    /// RPC endpoint of the node the contract is deployed to.
+    #[clap(long = "endpoint", default_value = "http://localhost:8547")]
+    pub endpoint: String,
+    /// Write the verification report, in JSON, to this file.
+    #[clap(long = "report", value_name = "PATH")]
+    pub report: Option<PathBuf>,
+    #[clap(flatten)]
+    pub build: Build,
+}
+
+/// Result of a verification, in the format block explorers read.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerificationReport {
+    pub module: String,
+    pub address: String,
+    pub chain_id: u64,
+    pub compiler_version: &'static str,
+    /// Arguments `move-stylus` was run with, which rebuild the module
+    pub command: Vec<String>,
+    /// Hash of the code of the contract, as `EXTCODEHASH` returns it
+    pub code_hash: String,
+    pub deployed_module_hash: String,
+    pub built_module_hash: String,
+    pub verified: bool,
+}
+
+impl Verify {
+    pub fn execute(self, path: Option<&Path>, config: BuildConfig) -> anyhow::Result<ExitCode> {
+        let mut build = self.build;
+        build.load_package_config(path)?;
+        if !build.emit.contains(&Artifact::Wasm) {
+            build.emit.push(Artifact::Wasm);
+        }
+        build.profile.get_or_insert(Profile::Release);
+        build.deterministic = true;
+
+        let modules = build.build_modules(path, config)?;
+        let wasm = modules
+            .get(&self.module)
+            .with_context(|| format!("module {} not found in the package", self.module))?;
+
+        let endpoint = parse_endpoint(&self.endpoint)?;
+        let address = self.address;
+        let runtime = tokio::runtime::Runtime::new()?;
+        let (chain_id, code) = runtime.block_on(async {
+            let provider = ProviderBuilder::new().connect_http(endpoint);
+            let chain_id = provider.get_chain_id().await?;
+            let code = provider.get_code_at(address).await?;
+            anyhow::Ok((chain_id, code))
+        })?;
+        if code.is_empty() {
+            bail!("no contract deployed at {}", self.address);
+        }
+
+        let deployed_module = deployed_module(&code)
+            .with_context(|| format!("{} is not a Stylus program", self.address))?;
+        let built_module_hash = keccak256(wasm);
+        let deployed_module_hash = keccak256(&deployed_module);
+
+        let report = VerificationReport {
+            module: self.module,
+            address: self.address.to_string(),
+            chain_id,
+            compiler_version: env!("CARGO_PKG_VERSION"),
+            command: std::env::args().skip(1).collect(),
+            code_hash: keccak256(&code).to_string(),
+            deployed_module_hash: deployed_module_hash.to_string(),
+            built_module_hash: built_module_hash.to_string(),
+            verified: built_module_hash == deployed_module_hash,
+        };
+
+        if let Some(report_path) = &self.report {
+            let json = serde_json::to_string_pretty(&report)
+                .expect("failed to serialize the verification report");
+            fs::write(report_path, json)
+                .with_context(|| format!("failed to write {}", report_path.display()))?;
+        }
+
+        if report.verified {
+            println!(
+                "{}: verified, {} is built from the package",
+                report.module, report.address
+            );
+            Ok(ExitCode::SUCCESS)
+        } else {
+            eprintln!(
+                "{}: not verified, {} runs {}, the package builds {}",
+                report.module,
+                report.address,
+                report.deployed_module_hash,
+                report.built_module_hash
+            );
+            Ok(ExitCode::FAILURE)
+        }
+    }
+}
+
+/// Module deployed as the code of a Stylus program, decompressed.
+fn deployed_module(code: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let Some((dictionary, compressed)) = code
+        .strip_prefix(&STYLUS_PREFIX)
+        .and_then(|code| code.split_first())
+    else {
+        bail!("the code does not start with the Stylus prefix");
+    };
+    if *dictionary != NO_DICTIONARY {
+        bail!("modules compressed with a dictionary are not supported");
+    }
+
+    let mut wasm = Vec::new();
+    brotli::Decompressor::new(compressed, 4096)
+        .read_to_end(&mut wasm)
+        .context("failed to decompress the module")?;
+    Ok(wasm)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    fn stylus_code(dictionary: u8, wasm: &[u8]) -> Vec<u8> {
+        let mut code = STYLUS_PREFIX.to_vec();
+        code.push(dictionary);
+        {
+            let mut writer = brotli::CompressorWriter::new(&mut code, 4096, 11, 22);
+            writer.write_all(wasm).unwrap();
+        }
+        code
+    }
+
+    #[test]
+    fn test_deployed_module() {
+        let wasm = wat::parse_str(r#"(module (memory (export "memory") 1))"#).unwrap();
+
+        let code = stylus_code(NO_DICTIONARY, &wasm);
+        assert_eq!(wasm, deployed_module(&code).unwrap());
+
+        // Compressed with the Stylus dictionary
+        assert!(deployed_module(&stylus_code(0x01, &wasm)).is_err());
+        // EVM bytecode
+        assert!(deployed_module(&[0x60, 0x80, 0x60, 0x40]).is_err());
+        assert!(deployed_module(&STYLUS_PREFIX).is_err());
+    }
+}
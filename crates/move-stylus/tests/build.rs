@@ -0,0 +1,56 @@
+//! Runs `move-stylus build` on a package scaffolded with `move-stylus new`.
+use std::{fs, path::Path, process::Command};
+
+#[test]
+fn test_build_json_messages() {
+    let root = std::env::temp_dir().join(format!("move-stylus-build-json-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    let path = root.join("counter");
+    let framework_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../stylus-framework");
+
+    let status = Command::new(env!("CARGO_BIN_EXE_move-stylus"))
+        .arg("--path")
+        .arg(&path)
+        .args(["new", "counter", "--framework-path"])
+        .arg(&framework_path)
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    // The second build writes the module from the build cache
+    for cached in [false, true] {
+        let output = Command::new(env!("CARGO_BIN_EXE_move-stylus"))
+            .arg("--path")
+            .arg(&path)
+            .args(["build", "--message-format", "json"])
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        let messages: Vec<serde_json::Value> = stdout
+            .lines()
+            .map(|line| {
+                serde_json::from_str(line)
+                    .unwrap_or_else(|e| panic!("{line:?} is not a JSON object: {e}"))
+            })
+            .collect();
+        assert!(
+            messages
+                .iter()
+                .any(|message| message["severity"] == "note" && message["code"] == "code-size")
+        );
+        assert_eq!(
+            cached,
+            messages
+                .iter()
+                .any(|message| message["severity"] == "note" && message["code"] == "build-cache")
+        );
+    }
+
+    fs::remove_dir_all(&root).unwrap();
+}
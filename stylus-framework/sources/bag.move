@@ -0,0 +1,135 @@
+module stylus::bag;
+
+use stylus::mapping as mapping;
+use stylus::mapping::Mapping;
+use stylus::tx_context::TxContext;
+
+/// Attempted to add a key that is already present in the bag.
+const EKeyAlreadyExists: u64 = 0;
+
+/// Attempted to access a key that is not present in the bag.
+const EKeyNotFound: u64 = 1;
+
+/// The value saved under the key does not have the requested type.
+const ETypeMismatch: u64 = 2;
+
+/// Attempted to destroy a bag that still holds values.
+const EBagNotEmpty: u64 = 3;
+
+/// A heterogeneous map-like collection, with the same API as Sui's `sui::bag::Bag`. Keys and
+/// values can be of any type, and different entries can use different types.
+///
+/// Values are saved in the slots derived from their keys, laid out as a Solidity `mapping`. Under
+/// each key the bag also saves a tag identifying the type of the value (`types`), so values can't
+/// be read as a different type. A zero tag means the key is not present.
+///
+/// Keys of different types with the same encoding (for example `1u8` and `1u64`) refer to the
+/// same entry.
+///
+/// The key and value types of the `types` and `values` mappings are placeholders: the natives
+/// only use the slot of the mapping and receive the actual types as type parameters.
+public struct Bag has store {
+    size: u64,
+    types: Mapping<u8, u256>,
+    values: Mapping<u8, u8>,
+}
+
+/// Creates a new, empty bag.
+public fun new(_ctx: &mut TxContext): Bag {
+    Bag {
+        size: 0,
+        types: mapping::new(),
+        values: mapping::new(),
+    }
+}
+
+/// Adds a key-value pair to the bag.
+///
+/// Aborts with `EKeyAlreadyExists` if the bag already has an entry with that key.
+public fun add<K: copy + drop + store, V: store>(bag: &mut Bag, k: K, v: V) {
+    assert!(!contains(bag, k), EKeyAlreadyExists);
+    bag_set_type_tag(&mut bag.types, k, bag_type_tag<V>());
+    bag_add(&mut bag.values, k, v);
+    bag.size = bag.size + 1;
+}
+
+/// Immutably borrows the value associated with the key in the bag.
+///
+/// Aborts with `EKeyNotFound` if the bag does not have an entry with that key, or with
+/// `ETypeMismatch` if the value is not of type `V`.
+public fun borrow<K: copy + drop + store, V: store>(bag: &Bag, k: K): &V {
+    assert_type<K, V>(bag, k);
+    bag_borrow(&bag.values, k)
+}
+
+/// Mutably borrows the value associated with the key in the bag.
+///
+/// Aborts with `EKeyNotFound` if the bag does not have an entry with that key, or with
+/// `ETypeMismatch` if the value is not of type `V`.
+public fun borrow_mut<K: copy + drop + store, V: store>(bag: &mut Bag, k: K): &mut V {
+    assert_type<K, V>(bag, k);
+    bag_borrow_mut(&mut bag.values, k)
+}
+
+/// Removes the key-value pair from the bag and returns the value.
+///
+/// Aborts with `EKeyNotFound` if the bag does not have an entry with that key, or with
+/// `ETypeMismatch` if the value is not of type `V`.
+public fun remove<K: copy + drop + store, V: store>(bag: &mut Bag, k: K): V {
+    assert_type<K, V>(bag, k);
+    bag_set_type_tag(&mut bag.types, k, 0);
+    bag.size = bag.size - 1;
+    bag_remove(&mut bag.values, k)
+}
+
+/// Returns true if there is a value associated with the key in the bag.
+public fun contains<K: copy + drop + store>(bag: &Bag, k: K): bool {
+    bag_get_type_tag(&bag.types, k) != 0
+}
+
+/// Returns true if there is a value of type `V` associated with the key in the bag.
+public fun contains_with_type<K: copy + drop + store, V: store>(bag: &Bag, k: K): bool {
+    bag_get_type_tag(&bag.types, k) == bag_type_tag<V>()
+}
+
+/// Returns the number of entries in the bag.
+public fun length(bag: &Bag): u64 {
+    bag.size
+}
+
+/// Returns true if the bag is empty.
+public fun is_empty(bag: &Bag): bool {
+    bag.size == 0
+}
+
+/// Destroys an empty bag.
+///
+/// Aborts with `EBagNotEmpty` if the bag still contains values.
+public fun destroy_empty(bag: Bag) {
+    let Bag { size, types: _, values: _ } = bag;
+    assert!(size == 0, EBagNotEmpty);
+}
+
+fun assert_type<K: copy + drop + store, V: store>(bag: &Bag, k: K) {
+    let tag = bag_get_type_tag(&bag.types, k);
+    assert!(tag != 0, EKeyNotFound);
+    assert!(tag == bag_type_tag<V>(), ETypeMismatch);
+}
+
+/// Returns the tag identifying the type `V` inside the bag.
+native fun bag_type_tag<V>(): u256;
+
+native fun bag_get_type_tag<K: copy + drop + store>(types: &Mapping<u8, u256>, k: K): u256;
+
+native fun bag_set_type_tag<K: copy + drop + store>(types: &mut Mapping<u8, u256>, k: K, tag: u256);
+
+native fun bag_add<K: copy + drop + store, V: store>(values: &mut Mapping<u8, u8>, k: K, v: V);
+
+native fun bag_borrow<K: copy + drop + store, V: store>(values: &Mapping<u8, u8>, k: K): &V;
+
+native fun bag_borrow_mut<K: copy + drop + store, V: store>(
+    values: &mut Mapping<u8, u8>,
+    k: K,
+): &mut V;
+
+native fun bag_remove<K: copy + drop + store, V: store>(values: &mut Mapping<u8, u8>, k: K): V;
@@ -0,0 +1,104 @@
+/// Contract calls module.
+///
+/// Defines the functions to call other contracts, and to deploy new ones from factory contracts.
+///
+/// For more information:
+/// https://www.evm.codes/#f1
+/// https://www.evm.codes/#f4
+/// https://www.evm.codes/#fa
+/// https://www.evm.codes/#f0
+/// https://www.evm.codes/#f5
+module stylus::contract_calls;
+
+/// Calls the contract at `target` with `calldata`, sending `value` wei and forwarding all the gas
+/// available.
+///
+/// Returns whether the call succeeded and the data it returned. If the call reverted, the data is
+/// the revert reason.
+///
+/// The values written to the storage so far are visible to the called contract, and the values it
+/// writes, for example if it calls back into this contract, are read again after it returns.
+/// Objects received by the entry function and values borrowed mutably from the storage are only
+/// saved when the transaction ends.
+public fun external_call(target: address, calldata: vector<u8>, value: u256): (bool, vector<u8>) {
+    let success = native_call_contract(target, calldata, value);
+    (success, native_return_data())
+}
+native fun native_call_contract(target: address, calldata: vector<u8>, value: u256): bool;
+
+/// Delegate calls the contract at `target` with `calldata`, forwarding all the gas available.
+///
+/// The code of `target` runs in the context of this contract: it reads and writes this contract's
+/// storage, and sees the sender and the value of the current call. This is the building block of
+/// proxies and libraries.
+///
+/// Returns whether the call succeeded and the data it returned. If the call reverted, the data is
+/// the revert reason.
+///
+/// The same as with `external_call`, the values written to the storage so far are visible to the
+/// delegated code, and the values it writes are read again after it returns.
+public fun delegate_call(target: address, calldata: vector<u8>): (bool, vector<u8>) {
+    let success = native_delegate_call_contract(target, calldata);
+    (success, native_return_data())
+}
+native fun native_delegate_call_contract(target: address, calldata: vector<u8>): bool;
+
+/// Static calls the contract at `target` with `calldata`, forwarding all the gas available.
+///
+/// The call is guaranteed not to change the state: it reverts if `target`, or any contract it
+/// calls, tries to modify it. This is the way to query other contracts, such as token balances or
+/// oracles, and it can be used in view functions.
+///
+/// Returns whether the call succeeded and the data it returned. If the call reverted, the data is
+/// the revert reason.
+public fun static_call(target: address, calldata: vector<u8>): (bool, vector<u8>) {
+    let success = native_static_call_contract(target, calldata);
+    (success, native_return_data())
+}
+native fun native_static_call_contract(target: address, calldata: vector<u8>): bool;
+
+/// Deploys a new contract with `init_code`, sending it `endowment` wei. The address of the new
+/// contract is derived from this contract's address and nonce.
+///
+/// The init code is EVM bytecode that returns the code of the new contract, that can be a Stylus
+/// program.
+///
+/// Returns the address of the new contract and, if the deployment failed, the zero address and
+/// the revert data.
+public fun create(init_code: vector<u8>, endowment: u256): (address, vector<u8>) {
+    let contract = native_create1(init_code, endowment);
+    (contract, native_return_data())
+}
+native fun native_create1(init_code: vector<u8>, endowment: u256): address;
+
+/// Deploys a new contract with `init_code`, sending it `endowment` wei. The address of the new
+/// contract is derived from this contract's address, `salt` and the hash of `init_code`, so it can
+/// be known before the deployment.
+///
+/// Returns the address of the new contract and, if the deployment failed, the zero address and
+/// the revert data.
+public fun create2(init_code: vector<u8>, endowment: u256, salt: u256): (address, vector<u8>) {
+    let contract = native_create2(init_code, endowment, salt);
+    (contract, native_return_data())
+}
+native fun native_create2(init_code: vector<u8>, endowment: u256, salt: u256): address;
+
+/// Calls the entry function of this contract matching the selector of `calldata`, in the same
+/// execution instead of with an external call, and returns its ABI-encoded return values.
+///
+/// This is the building block of multicalls, that batch calls to several entry functions in one
+/// transaction reusing their selector dispatch. Unlike an external call to the contract's own
+/// address, the sender and the value are the ones of the current call, and `msg::data` returns
+/// `calldata` while the function runs. If the function aborts, or no entry function matches the
+/// selector, the whole transaction aborts.
+///
+/// Objects received by the entry function are only saved when the transaction ends, so the calls
+/// should not modify objects the caller holds.
+public fun self_call(calldata: vector<u8>): vector<u8> {
+    native_self_call(calldata)
+}
+native fun native_self_call(calldata: vector<u8>): vector<u8>;
+
+/// Returns the data returned by the last contract call, or the revert data of the last failed
+/// deployment.
+native fun native_return_data(): vector<u8>;
@@ -0,0 +1,42 @@
+/// Crypto module.
+///
+/// Defines the cryptographic functions computed by the EVM precompiles, such as the recovery of
+/// the signer of a message, or the SHA-256 and RIPEMD-160 hashes used to verify Bitcoin
+/// transactions.
+///
+/// For more information:
+/// https://www.evm.codes/precompiled#0x01
+/// https://www.evm.codes/precompiled#0x02
+/// https://www.evm.codes/precompiled#0x03
+module stylus::crypto;
+
+/// A hash or a component of a signature is not 32 bytes long.
+const EInvalidLength: u64 = 0;
+
+/// Returns the address that signed the message `hash` with the secp256k1 signature (`v`, `r`,
+/// `s`), the same as Solidity's `ecrecover`. `hash`, `r` and `s` are 32 bytes long, and `v` is 27
+/// or 28.
+///
+/// Returns the zero address if the signature is invalid, so the result must be checked against
+/// the expected signer, never against `@0x0`.
+public fun ecrecover(hash: vector<u8>, v: u8, r: vector<u8>, s: vector<u8>): address {
+    assert!(hash.length() == 32, EInvalidLength);
+    assert!(r.length() == 32, EInvalidLength);
+    assert!(s.length() == 32, EInvalidLength);
+    native_ecrecover(hash, v, r, s)
+}
+native fun native_ecrecover(hash: vector<u8>, v: u8, r: vector<u8>, s: vector<u8>): address;
+
+/// Returns the SHA-256 hash of `data`, the same as Solidity's `sha256`. The hash is 32 bytes
+/// long.
+public fun sha256(data: vector<u8>): vector<u8> {
+    native_sha256(data)
+}
+native fun native_sha256(data: vector<u8>): vector<u8>;
+
+/// Returns the RIPEMD-160 hash of `data`, the same as Solidity's `ripemd160`. The hash is 20
+/// bytes long.
+public fun ripemd160(data: vector<u8>): vector<u8> {
+    native_ripemd160(data)
+}
+native fun native_ripemd160(data: vector<u8>): vector<u8>;
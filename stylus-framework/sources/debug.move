@@ -0,0 +1,19 @@
+/// Debug module.
+///
+/// Defines `log`, that prints a message and a value while testing the contracts, similar to
+/// Hardhat's `console.log`.
+///
+/// The messages are printed through the console's `log_txt` hook, that only nodes running in
+/// debug mode provide. Packages are translated without it unless they are built with `--debug`,
+/// in which case `log` does nothing and costs only the call.
+module stylus::debug;
+
+use std::string::String;
+
+/// Prints `<msg>: <value>`. Booleans are printed as `true` or `false`, the integers up to u64 in
+/// decimal, and u128, u256 and addresses in hexadecimal. Values of other types can not be printed,
+/// and the translation fails.
+public fun log<T: drop>(msg: String, value: T) {
+    native_log(msg.into_bytes(), value)
+}
+native fun native_log<T: drop>(msg: vector<u8>, value: T);
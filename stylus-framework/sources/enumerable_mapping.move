@@ -0,0 +1,128 @@
+module stylus::enumerable_mapping;
+
+use stylus::mapping as mapping;
+use stylus::mapping::Mapping;
+
+/// A key was not found in the mapping.
+const EKeyNotFound: u64 = 0;
+
+/// An index is out of the bounds of the keys list.
+const EIndexOutOfBounds: u64 = 1;
+
+/// A `Mapping` that also keeps track of its keys, so they can be iterated.
+///
+/// Keys are saved in an index array mapping (`keys`) alongside the position of each of them
+/// (`positions`, offset by one so the zero value means the key is not present). Removing a key
+/// moves the last key to the removed position, so the order of the keys is not preserved.
+///
+/// Iterating all the keys on-chain costs one storage read per key, so prefer the bounded
+/// `keys_range` over `keys` when the mapping can grow large.
+public struct EnumerableMapping<K: copy + drop + store, phantom V: store> has store, drop {
+    length: u64,
+    keys: Mapping<u64, K>,
+    positions: Mapping<K, u64>,
+    values: Mapping<K, V>,
+}
+
+/// Creates a new, empty `EnumerableMapping`.
+public fun new<K: copy + drop + store, V: store>(): EnumerableMapping<K, V> {
+    EnumerableMapping {
+        length: 0,
+        keys: mapping::new(),
+        positions: mapping::new(),
+        values: mapping::new(),
+    }
+}
+
+/// Returns the number of keys saved in the mapping.
+public fun length<K: copy + drop + store, V: store>(self: &EnumerableMapping<K, V>): u64 {
+    self.length
+}
+
+/// Returns true if the mapping has no keys.
+public fun is_empty<K: copy + drop + store, V: store>(self: &EnumerableMapping<K, V>): bool {
+    self.length == 0
+}
+
+/// Returns true if there is a value saved under `key`.
+public fun contains<K: copy + drop + store, V: store>(self: &EnumerableMapping<K, V>, key: K): bool {
+    mapping::get(&self.positions, key) != 0
+}
+
+/// Returns a copy of the value saved under `key`.
+///
+/// Aborts with `EKeyNotFound` if the key is not present.
+public fun get<K: copy + drop + store, V: store + copy>(self: &EnumerableMapping<K, V>, key: K): V {
+    assert!(contains(self, key), EKeyNotFound);
+    mapping::get(&self.values, key)
+}
+
+/// Saves `value` under `key`, overwriting the previous one. If the key is new, it is appended
+/// to the keys list.
+public fun set<K: copy + drop + store, V: store + drop>(
+    self: &mut EnumerableMapping<K, V>,
+    key: K,
+    value: V,
+) {
+    if (!contains(self, key)) {
+        mapping::set(&mut self.keys, self.length, key);
+        self.length = self.length + 1;
+        mapping::set(&mut self.positions, key, self.length);
+    };
+
+    mapping::set(&mut self.values, key, value);
+}
+
+/// Removes `key` from the mapping, clearing the slots of its value.
+///
+/// Aborts with `EKeyNotFound` if the key is not present.
+public fun remove<K: copy + drop + store, V: store + drop>(
+    self: &mut EnumerableMapping<K, V>,
+    key: K,
+) {
+    let position = mapping::get(&self.positions, key);
+    assert!(position != 0, EKeyNotFound);
+
+    // Move the last key to the position of the removed one
+    let last_index = self.length - 1;
+    if (position - 1 != last_index) {
+        let last_key = mapping::get(&self.keys, last_index);
+        mapping::set(&mut self.keys, position - 1, last_key);
+        mapping::set(&mut self.positions, last_key, position);
+    };
+
+    mapping::remove(&mut self.keys, last_index);
+    mapping::remove(&mut self.positions, key);
+    mapping::remove(&mut self.values, key);
+    self.length = last_index;
+}
+
+/// Returns the key saved at `index`.
+///
+/// Aborts with `EIndexOutOfBounds` if `index` is not lower than the length of the mapping.
+public fun key_at<K: copy + drop + store, V: store>(self: &EnumerableMapping<K, V>, index: u64): K {
+    assert!(index < self.length, EIndexOutOfBounds);
+    mapping::get(&self.keys, index)
+}
+
+/// Returns the keys saved in the positions `[from, to)`. `to` is clamped to the length of the
+/// mapping.
+public fun keys_range<K: copy + drop + store, V: store>(
+    self: &EnumerableMapping<K, V>,
+    from: u64,
+    to: u64,
+): vector<K> {
+    let to = if (to > self.length) { self.length } else { to };
+    let mut keys = vector[];
+    let mut i = from;
+    while (i < to) {
+        keys.push_back(mapping::get(&self.keys, i));
+        i = i + 1;
+    };
+    keys
+}
+
+/// Returns all the keys saved in the mapping.
+public fun keys<K: copy + drop + store, V: store>(self: &EnumerableMapping<K, V>): vector<K> {
+    keys_range(self, 0, self.length)
+}
@@ -0,0 +1,88 @@
+/// EVM module.
+///
+/// Defines the functions to query the state of the EVM, such as the balance and the code of the
+/// accounts, or the gas left in the current call.
+module stylus::evm;
+
+/// Returns the address of this contract, the same as Solidity's `address(this)`.
+public fun self_address(): address {
+    native_contract_address()
+}
+native fun native_contract_address(): address;
+
+/// Returns the ETH balance in wei of the account at `account`, the same as Solidity's
+/// `address.balance`.
+public fun balance(account: address): u256 {
+    native_account_balance(account)
+}
+native fun native_account_balance(account: address): u256;
+
+/// Sends `amount` wei from this contract to `to`, the same as Solidity's
+/// `payable(to).transfer(amount)`, but forwarding all the gas available instead of a stipend.
+///
+/// `to` is called with empty calldata, so a contract receiving the ETH runs its fallback code. If
+/// the transfer fails, for example because this contract does not have enough balance or `to`
+/// rejects it, the transaction reverts with the revert data of the call.
+///
+/// The same as with `contract_calls::external_call`, the values written to the storage so far are
+/// visible to `to` if it calls back into this contract.
+public fun transfer_eth(to: address, amount: u256) {
+    native_transfer_eth(to, amount)
+}
+native fun native_transfer_eth(to: address, amount: u256);
+
+/// Returns the code of the contract at `account`, the same as Solidity's `address.code`. It is
+/// empty if `account` is not a contract.
+public fun code(account: address): vector<u8> {
+    native_account_code(account)
+}
+native fun native_account_code(account: address): vector<u8>;
+
+/// Returns the size in bytes of the code of the contract at `account`, the same as Solidity's
+/// `address.code.length`.
+public fun code_size(account: address): u64 {
+    native_account_code_size(account)
+}
+native fun native_account_code_size(account: address): u64;
+
+/// Returns the keccak256 hash of the code of the contract at `account` (32 bytes), the same as
+/// Solidity's `address.codehash`. The hash of an account without code is the hash of the empty
+/// data.
+public fun code_hash(account: address): vector<u8> {
+    native_account_codehash(account)
+}
+native fun native_account_codehash(account: address): vector<u8>;
+
+/// Returns true if there is a contract deployed at `account`.
+///
+/// Note that this is false for a contract that is being constructed, since its code is only saved
+/// once the constructor returns.
+public fun is_contract(account: address): bool {
+    native_account_code_size(account) > 0
+}
+
+/// Returns the hash of the block `n` (32 bytes), the same as Solidity's `blockhash(n)`.
+///
+/// Stylus does not expose block hashes to the contracts, so they are queried from the ArbSys
+/// precompile. Only the hashes of the 256 most recent blocks are available. For the rest, and for
+/// the current block and the future ones, the hash is zero.
+public fun block_hash(n: u64): vector<u8> {
+    native_block_hash(n)
+}
+native fun native_block_hash(n: u64): vector<u8>;
+
+/// Returns the gas left in the current call, the same as Solidity's `gasleft()`.
+///
+/// Contracts processing an unbounded amount of work can check it to stop before running out of
+/// gas, saving how far they got so the next call resumes from there.
+public fun gas_left(): u64 {
+    native_gas_left()
+}
+native fun native_gas_left(): u64;
+
+/// Returns the ink left in the current call. Ink is the unit Stylus meters WASM execution in, and
+/// is more precise than gas: by default a unit of gas is worth 10000 units of ink.
+public fun ink_left(): u64 {
+    native_ink_left()
+}
+native fun native_ink_left(): u64;
@@ -0,0 +1,15 @@
+/// Hash module.
+///
+/// Defines the hash functions computed by the host, that are cheaper than the ones computed by
+/// the precompiles.
+///
+/// For more information:
+/// https://www.evm.codes/#20
+module stylus::hash;
+
+/// Returns the keccak256 hash of `data`, the same as Solidity's `keccak256`. The hash is 32 bytes
+/// long.
+public fun keccak256(data: vector<u8>): vector<u8> {
+    native_keccak256(data)
+}
+native fun native_keccak256(data: vector<u8>): vector<u8>;
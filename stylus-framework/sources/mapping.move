@@ -0,0 +1,61 @@
+module stylus::mapping;
+
+/// A key-value map living in the contract's storage, laid out following the Solidity storage
+/// layout for `mapping(K => V)`.
+///
+/// A `Mapping` occupies one (empty) slot `p` of the object that contains it. The value for the
+/// key `k` is located at `keccak256(h(k) . p)`. If the value is a struct, its fields are spread
+/// across the consecutive slots starting at the derived one.
+///
+/// The mapping slot is assigned when the object containing it is read from storage, so a mapping
+/// can only be accessed once its parent object has been persisted.
+public struct Mapping<phantom K: copy + drop, phantom V: store> has store, drop {
+    slot: u256,
+}
+
+/// Creates a new, empty `Mapping`.
+public fun new<K: copy + drop, V: store>(): Mapping<K, V> {
+    Mapping { slot: 0 }
+}
+
+/// Returns true if there is a non-zero value saved under `key`, in any of the slots it occupies.
+public fun contains<K: copy + drop, V: store>(self: &Mapping<K, V>, key: K): bool {
+    mapping_contains(self, key)
+}
+native fun mapping_contains<K: copy + drop, V: store>(self: &Mapping<K, V>, key: K): bool;
+
+/// Returns a copy of the value saved under `key`. If nothing was saved, the zero value of `V`
+/// is returned.
+public fun get<K: copy + drop, V: store + copy>(self: &Mapping<K, V>, key: K): V {
+    mapping_get(self, key)
+}
+native fun mapping_get<K: copy + drop, V: store + copy>(self: &Mapping<K, V>, key: K): V;
+
+/// Saves `value` under `key`, overwriting the previous one.
+public fun set<K: copy + drop, V: store + drop>(self: &mut Mapping<K, V>, key: K, value: V) {
+    mapping_set(self, key, value)
+}
+native fun mapping_set<K: copy + drop, V: store + drop>(
+    self: &mut Mapping<K, V>,
+    key: K,
+    value: V,
+);
+
+/// Removes the value saved under `key`, writing zero to every slot it occupied.
+public fun remove<K: copy + drop, V: store + drop>(self: &mut Mapping<K, V>, key: K) {
+    mapping_remove(self, key)
+}
+native fun mapping_remove<K: copy + drop, V: store + drop>(self: &mut Mapping<K, V>, key: K);
+
+/// Returns the inner mapping saved under `key`. Used to access nested mappings such as
+/// `Mapping<address, Mapping<address, u256>>`.
+public fun borrow_mapping<K: copy + drop, K2: copy + drop, V2: store>(
+    self: &Mapping<K, Mapping<K2, V2>>,
+    key: K,
+): Mapping<K2, V2> {
+    mapping_borrow_mapping(self, key)
+}
+native fun mapping_borrow_mapping<K: copy + drop, K2: copy + drop, V2: store>(
+    self: &Mapping<K, Mapping<K2, V2>>,
+    key: K,
+): Mapping<K2, V2>;
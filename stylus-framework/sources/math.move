@@ -0,0 +1,37 @@
+/// Math module.
+///
+/// Defines u256 operations computed by Stylus' math hooks, that have no Move operator and would be
+/// expensive to implement with the u256 arithmetic, such as modular arithmetic on values that
+/// overflow.
+///
+/// For more information:
+/// https://www.evm.codes/#08
+/// https://www.evm.codes/#09
+/// https://www.evm.codes/#0a
+module stylus::math;
+
+/// Attempted to compute a remainder modulo zero.
+const EZeroModulus: u64 = 0;
+
+/// Returns `(a + b) % modulus`, the same as Solidity's `addmod`. The sum does not overflow even if
+/// it does not fit in an u256.
+public fun add_mod(a: u256, b: u256, modulus: u256): u256 {
+    assert!(modulus != 0, EZeroModulus);
+    native_add_mod(a, b, modulus)
+}
+native fun native_add_mod(a: u256, b: u256, modulus: u256): u256;
+
+/// Returns `(a * b) % modulus`, the same as Solidity's `mulmod`. The product does not overflow
+/// even if it does not fit in an u256.
+public fun mul_mod(a: u256, b: u256, modulus: u256): u256 {
+    assert!(modulus != 0, EZeroModulus);
+    native_mul_mod(a, b, modulus)
+}
+native fun native_mul_mod(a: u256, b: u256, modulus: u256): u256;
+
+/// Returns `base` raised to `exponent`, wrapping around on overflow, the same as Solidity's `**`
+/// in an `unchecked` block.
+public fun wrapping_pow(base: u256, exponent: u256): u256 {
+    native_wrapping_pow(base, exponent)
+}
+native fun native_wrapping_pow(base: u256, exponent: u256): u256;
@@ -0,0 +1,19 @@
+/// Msg module.
+///
+/// Defines the functions to read the calldata of the current call, for contracts that decode it
+/// themselves, such as forwarders or contracts with a custom dispatch.
+module stylus::msg;
+
+/// Returns the complete calldata of the current call, including the function selector, the same
+/// as Solidity's `msg.data`.
+public fun data(): vector<u8> {
+    native_msg_data()
+}
+native fun native_msg_data(): vector<u8>;
+
+/// Returns the function selector of the current call, the first 4 bytes of its calldata, the
+/// same as Solidity's `msg.sig`.
+public fun sig(): vector<u8> {
+    native_msg_sig()
+}
+native fun native_msg_sig(): vector<u8>;
@@ -0,0 +1,17 @@
+/// Storage module.
+///
+/// Defines the functions to control when the values written to the storage are committed. The
+/// values written during a call are kept in a cache, and committed once the call ends.
+module stylus::storage;
+
+/// Sends the values written to the storage so far to the host and persists them, instead of
+/// waiting for the call to end, for example to bound the memory taken by the cache in long
+/// running entry functions. If `clear` is true, the host's storage cache is dropped too, so the
+/// following reads load the values from the state.
+///
+/// Objects borrowed mutably from the storage are not saved until the call ends, because they can
+/// still be modified, and the cache is committed once more before the call returns.
+public fun flush(clear: bool) {
+    native_flush(clear)
+}
+native fun native_flush(clear: bool);
@@ -0,0 +1,92 @@
+module stylus::storage_vec;
+
+/// Attempted to access an index that is out of bounds.
+const EIndexOutOfBounds: u64 = 0;
+
+/// Attempted to pop an element from an empty vector.
+const EEmptyVector: u64 = 1;
+
+/// A growable array living in the contract's storage, laid out following the Solidity storage
+/// layout for dynamic arrays.
+///
+/// A `StorageVec` occupies one slot `p` of the object that contains it, holding its length. The
+/// elements are saved starting at `keccak256(p)`. Every element occupies the same number of
+/// consecutive slots, so the element at index `i` of a vector whose elements occupy `n` slots
+/// starts at `keccak256(p) + i * n`. This allows reading and writing a single element without
+/// touching the rest of the vector.
+///
+/// Unlike Solidity, elements smaller than a slot are not packed together: every element starts at
+/// its own slot.
+///
+/// Elements returned by `borrow_mut` are saved back into the storage once the reference is no
+/// longer used.
+///
+/// The vector slot is assigned when the object containing it is read from storage, so a
+/// `StorageVec` can only be accessed once its parent object has been persisted.
+public struct StorageVec<phantom T: store> has store, drop {
+    slot: u256,
+}
+
+/// Creates a new, empty `StorageVec`.
+public fun new<T: store>(): StorageVec<T> {
+    StorageVec { slot: 0 }
+}
+
+/// Returns the number of elements in the vector.
+public fun length<T: store>(self: &StorageVec<T>): u64 {
+    storage_vec_length(self)
+}
+native fun storage_vec_length<T: store>(self: &StorageVec<T>): u64;
+
+/// Returns true if the vector has no elements.
+public fun is_empty<T: store>(self: &StorageVec<T>): bool {
+    storage_vec_length(self) == 0
+}
+
+/// Adds `value` at the end of the vector.
+public fun push_back<T: store + drop>(self: &mut StorageVec<T>, value: T) {
+    storage_vec_push_back(self, value)
+}
+native fun storage_vec_push_back<T: store + drop>(self: &mut StorageVec<T>, value: T);
+
+/// Immutably borrows the element at index `i`.
+///
+/// Aborts with `EIndexOutOfBounds` if `i` is out of bounds.
+public fun borrow<T: store>(self: &StorageVec<T>, i: u64): &T {
+    assert!(i < storage_vec_length(self), EIndexOutOfBounds);
+    storage_vec_borrow(self, i)
+}
+native fun storage_vec_borrow<T: store>(self: &StorageVec<T>, i: u64): &T;
+
+/// Mutably borrows the element at index `i`. Only the slots of that element are written back.
+///
+/// Aborts with `EIndexOutOfBounds` if `i` is out of bounds.
+public fun borrow_mut<T: store>(self: &mut StorageVec<T>, i: u64): &mut T {
+    assert!(i < storage_vec_length(self), EIndexOutOfBounds);
+    storage_vec_borrow_mut(self, i)
+}
+native fun storage_vec_borrow_mut<T: store>(self: &mut StorageVec<T>, i: u64): &mut T;
+
+/// Returns a copy of the element at index `i`.
+///
+/// Aborts with `EIndexOutOfBounds` if `i` is out of bounds.
+public fun get<T: store + copy>(self: &StorageVec<T>, i: u64): T {
+    *borrow(self, i)
+}
+
+/// Replaces the element at index `i` with `value`. Only the slots of that element are written.
+///
+/// Aborts with `EIndexOutOfBounds` if `i` is out of bounds.
+public fun set<T: store + drop>(self: &mut StorageVec<T>, i: u64, value: T) {
+    *borrow_mut(self, i) = value;
+}
+
+/// Removes the last element of the vector and returns it, writing zero to every slot it
+/// occupied.
+///
+/// Aborts with `EEmptyVector` if the vector is empty.
+public fun pop_back<T: store>(self: &mut StorageVec<T>): T {
+    assert!(storage_vec_length(self) > 0, EEmptyVector);
+    storage_vec_pop_back(self)
+}
+native fun storage_vec_pop_back<T: store>(self: &mut StorageVec<T>): T;
@@ -0,0 +1,112 @@
+module stylus::table;
+
+use stylus::mapping as mapping;
+use stylus::mapping::Mapping;
+use stylus::tx_context::TxContext;
+
+/// Attempted to add a key that is already present in the table.
+const EKeyAlreadyExists: u64 = 0;
+
+/// Attempted to access a key that is not present in the table.
+const EKeyNotFound: u64 = 1;
+
+/// Attempted to destroy a table that still holds values.
+const ETableNotEmpty: u64 = 2;
+
+/// A map-like collection, with the same API as Sui's `sui::table::Table`.
+///
+/// Values are saved in the slots derived from their keys, laid out as a Solidity `mapping`.
+/// Which keys are present is tracked separately (`keys`), so a key holding a zero value is still
+/// considered present.
+///
+/// Values returned by `borrow_mut` are saved back into the storage once the reference is no longer
+/// used.
+public struct Table<phantom K: copy + drop + store, phantom V: store> has store {
+    size: u64,
+    keys: Mapping<K, bool>,
+    values: Mapping<K, V>,
+}
+
+/// Creates a new, empty table.
+public fun new<K: copy + drop + store, V: store>(_ctx: &mut TxContext): Table<K, V> {
+    Table {
+        size: 0,
+        keys: mapping::new(),
+        values: mapping::new(),
+    }
+}
+
+/// Adds a key-value pair to the table.
+///
+/// Aborts with `EKeyAlreadyExists` if the table already has an entry with that key.
+public fun add<K: copy + drop + store, V: store>(table: &mut Table<K, V>, k: K, v: V) {
+    assert!(!contains(table, k), EKeyAlreadyExists);
+    mapping::set(&mut table.keys, k, true);
+    table_add(&mut table.values, k, v);
+    table.size = table.size + 1;
+}
+
+/// Immutably borrows the value associated with the key in the table.
+///
+/// Aborts with `EKeyNotFound` if the table does not have an entry with that key.
+public fun borrow<K: copy + drop + store, V: store>(table: &Table<K, V>, k: K): &V {
+    assert!(contains(table, k), EKeyNotFound);
+    table_borrow(&table.values, k)
+}
+
+/// Mutably borrows the value associated with the key in the table.
+///
+/// Aborts with `EKeyNotFound` if the table does not have an entry with that key.
+public fun borrow_mut<K: copy + drop + store, V: store>(table: &mut Table<K, V>, k: K): &mut V {
+    assert!(contains(table, k), EKeyNotFound);
+    table_borrow_mut(&mut table.values, k)
+}
+
+/// Removes the key-value pair from the table and returns the value.
+///
+/// Aborts with `EKeyNotFound` if the table does not have an entry with that key.
+public fun remove<K: copy + drop + store, V: store>(table: &mut Table<K, V>, k: K): V {
+    assert!(contains(table, k), EKeyNotFound);
+    mapping::set(&mut table.keys, k, false);
+    table.size = table.size - 1;
+    table_remove(&mut table.values, k)
+}
+
+/// Returns true if there is a value associated with the key in the table.
+public fun contains<K: copy + drop + store, V: store>(table: &Table<K, V>, k: K): bool {
+    mapping::contains(&table.keys, k)
+}
+
+/// Returns the number of entries in the table.
+public fun length<K: copy + drop + store, V: store>(table: &Table<K, V>): u64 {
+    table.size
+}
+
+/// Returns true if the table is empty.
+public fun is_empty<K: copy + drop + store, V: store>(table: &Table<K, V>): bool {
+    table.size == 0
+}
+
+/// Destroys an empty table.
+///
+/// Aborts with `ETableNotEmpty` if the table still contains values.
+public fun destroy_empty<K: copy + drop + store, V: store>(table: Table<K, V>) {
+    let Table { size, keys: _, values: _ } = table;
+    assert!(size == 0, ETableNotEmpty);
+}
+
+/// Drops a possibly non-empty table. Usable only if the value type `V` has the `drop` ability.
+public fun drop<K: copy + drop + store, V: drop + store>(table: Table<K, V>) {
+    let Table { size: _, keys: _, values: _ } = table;
+}
+
+native fun table_add<K: copy + drop + store, V: store>(values: &mut Mapping<K, V>, k: K, v: V);
+
+native fun table_borrow<K: copy + drop + store, V: store>(values: &Mapping<K, V>, k: K): &V;
+
+native fun table_borrow_mut<K: copy + drop + store, V: store>(
+    values: &mut Mapping<K, V>,
+    k: K,
+): &mut V;
+
+native fun table_remove<K: copy + drop + store, V: store>(values: &mut Mapping<K, V>, k: K): V;
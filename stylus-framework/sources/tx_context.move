@@ -5,6 +5,8 @@ module stylus::tx_context;
 /// the VM and passed in to the entrypoint of the transaction as `&mut TxContext`.
 public struct TxContext has drop {}
 
+const MAX_U64: u64 = 18446744073709551615;
+
 /// Return the address of the user that signed the current
 /// transaction
 public fun sender(_self: &TxContext): address {
@@ -18,6 +20,13 @@ public fun msg_value(_self: &TxContext): u256 {
 }
 native fun native_msg_value(): u256;
 
+/// Return true if the current call reentered the contract, that is, if the contract is already
+/// executing further up in the call stack.
+public fun reentrant(_self: &TxContext): bool {
+    native_msg_reentrant()
+}
+native fun native_msg_reentrant(): bool;
+
 /// Return the current block's number.
 public fun block_number(_self: &TxContext): u64 {
     native_block_number()
@@ -30,6 +39,13 @@ public fun block_basefee(_self: &TxContext): u256 {
 }
 native fun native_block_basefee(): u256;
 
+/// Return the current block's coinbase. On Arbitrum chains it is the address of the L1 batch
+/// poster, not the one of a validator.
+public fun block_coinbase(_self: &TxContext): address {
+    native_block_coinbase()
+}
+native fun native_block_coinbase(): address;
+
 /// Return the current block's gas limit.
 public fun block_gas_limit(_self: &TxContext): u64 {
     native_block_gas_limit()
@@ -54,6 +70,28 @@ public fun gas_price(_self: &TxContext): u256 {
 }
 native fun native_gas_price(): u256;
 
+/// Return the price of ink, the unit Stylus meters WASM execution in, as the number of units of
+/// ink a unit of gas is worth.
+public fun ink_price(_self: &TxContext): u32 {
+    native_ink_price()
+}
+native fun native_ink_price(): u32;
+
+/// Convert an amount of ink to gas, rounding down.
+public fun ink_to_gas(self: &TxContext, ink: u64): u64 {
+    ink / (self.ink_price() as u64)
+}
+
+/// Convert an amount of gas to ink. The result saturates at the maximum `u64`.
+public fun gas_to_ink(self: &TxContext, gas: u64): u64 {
+    let price = self.ink_price() as u64;
+    if (gas > MAX_U64 / price) {
+        MAX_U64
+    } else {
+        gas * price
+    }
+}
+
 /// Create an `address` that has not been used. As it is an object address, it will never
 /// occur as the address for a user.
 /// In other words, the generated address is a globally unique object ID.